@@ -107,6 +107,32 @@ typedef struct {{
             "ino_t",
             "rusage",
             "dirent",
+            "tms",
+            "sigevent",
+            "itimerspec",
+            "signalfd_siginfo",
+            "siginfo_t",
+            "flock",
+            "inotify_event",
+            "key_t",
+            "ipc_perm",
+            "shmid_ds",
+            "shminfo",
+            "shm_info",
+            "semid_ds",
+            "seminfo",
+            "sembuf",
+            "msqid_ds",
+            "msginfo",
+            "mqd_t",
+            "mq_attr",
+            "aiocb",
+            "io_uring_.*",
+            "io_sqring_offsets",
+            "io_cqring_offsets",
+            "statx",
+            "statx_timestamp",
+            "statfs",
         ];
         let allow_vars = [
             "O_.*",
@@ -120,6 +146,22 @@ typedef struct {{
             "_SC_.*",
             "EPOLL_CTL_.*",
             "EPOLL.*",
+            "EFD_.*",
+            "TFD_.*",
+            "SFD_.*",
+            "SPLICE_F_.*",
+            "LOCK_.*",
+            "IN_.*",
+            "IORING_.*",
+            "IOSQE_.*",
+            "IO_URING_OP_SUPPORTED",
+            "AIO_.*",
+            "LIO_.*",
+            "IPC_.*",
+            "SHM_.*",
+            "SEM_.*",
+            "MSG_(NOERROR|EXCEPT|COPY|STAT|INFO)",
+            "(GET|SET)(PID|VAL|ALL|NCNT|ZCNT)",
             "RLIMIT_.*",
             "EAI_.*",
             "MAXADDRS",
@@ -128,12 +170,19 @@ typedef struct {{
             "EINVAL",
             "CLONE_.*",
             "AT_.*",
+            "RENAME_.*",
+            "STATX_.*",
+            "UTIME_.*",
+            "[FRWX]_OK",
             "MAP_.+",
             "PROT_.+",
             "MS_.+",
             "MREMAP_.+",
+            "MFD_.+",
             "GRND_.*",
             "S_IF.+",
+            "CLOCK_.*",
+            "TIMER_ABSTIME",
         ];
 
         #[derive(Debug)]
@@ -0,0 +1,572 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! POSIX asynchronous I/O.
+//!
+//! Requests are run by a [`WorkerPool`] of the submitting process, on the
+//! file the descriptor refers to when the request is queued. A request is
+//! known by the address of its control block until `aio_return` collects its
+//! result.
+
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::ffi::c_int;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use ruxfdtable::FileLike;
+use ruxtask::fs::{get_file_like, File};
+use ruxtask::signal::NSIG;
+use ruxtask::worker_pool::WorkerPool;
+use ruxtask::{SigInfo, Signal, WaitQueue};
+use spinlock::SpinNoIrq;
+
+use crate::ctypes;
+
+/// Maximum number of workers running the requests of a process.
+const AIO_MAX_WORKERS: usize = 16;
+/// Maximum number of requests in a `lio_listio` call.
+const AIO_LISTIO_MAX: c_int = 1024;
+
+/// Worker pools by process id.
+static AIO_POOLS: SpinNoIrq<BTreeMap<u64, WorkerPool>> = SpinNoIrq::new(BTreeMap::new());
+/// Requests by process id and address of their control block.
+static AIO_REQUESTS: SpinNoIrq<BTreeMap<(u64, usize), Arc<AioRequest>>> =
+    SpinNoIrq::new(BTreeMap::new());
+/// Tasks waiting for requests to complete.
+static AIO_WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+#[derive(Clone, Copy)]
+enum AioOp {
+    Read,
+    Write,
+    Fsync,
+}
+
+#[derive(Clone, Copy)]
+enum AioState {
+    Queued,
+    Running,
+    Done(LinuxResult<usize>),
+}
+
+/// Function started in a new thread by `SIGEV_THREAD`.
+#[derive(Clone, Copy)]
+struct SigevThread {
+    function: unsafe extern "C" fn(ctypes::sigval),
+    value: ctypes::sigval,
+}
+
+// The value is handed to the application function as is.
+unsafe impl Send for SigevThread {}
+unsafe impl Sync for SigevThread {}
+
+/// How to notify the completion of a request, or of a list of requests.
+enum AioNotify {
+    None,
+    Signal {
+        signo: u8,
+        /// Process that submitted the request.
+        pid: i32,
+        signal_if: Arc<SpinNoIrq<Signal>>,
+    },
+    Thread(SigevThread),
+}
+
+impl AioNotify {
+    fn from_sigevent(sev: &ctypes::sigevent) -> LinuxResult<Self> {
+        match sev.sigev_notify as u32 {
+            ctypes::SIGEV_NONE => Ok(Self::None),
+            ctypes::SIGEV_SIGNAL if (1..NSIG as c_int).contains(&sev.sigev_signo) => {
+                Ok(Self::Signal {
+                    signo: sev.sigev_signo as u8,
+                    pid: ruxtask::current().process_id().as_u64() as i32,
+                    signal_if: ruxtask::current().signal_if.clone(),
+                })
+            }
+            ctypes::SIGEV_THREAD => {
+                let function = unsafe { sev.__sev_fields.__sev_thread.sigev_notify_function }
+                    .ok_or(LinuxError::EINVAL)?;
+                Ok(Self::Thread(SigevThread {
+                    function,
+                    value: sev.sigev_value,
+                }))
+            }
+            _ => Err(LinuxError::EINVAL),
+        }
+    }
+
+    fn notify(&self) {
+        match self {
+            Self::None => {}
+            Self::Signal {
+                signo,
+                pid,
+                signal_if,
+            } => {
+                let info = SigInfo {
+                    code: SigInfo::SI_ASYNCIO,
+                    pid: *pid,
+                };
+                Signal::raise(signal_if, *signo, info);
+            }
+            Self::Thread(thread) => {
+                let thread = *thread;
+                ruxtask::spawn(move || unsafe { (thread.function)(thread.value) });
+            }
+        }
+    }
+}
+
+/// Requests of a `LIO_NOWAIT` list, to notify once all of them complete.
+struct AioList {
+    remaining: AtomicUsize,
+    notify: AioNotify,
+}
+
+impl AioList {
+    fn complete_one(&self) {
+        if self.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.notify.notify();
+        }
+    }
+}
+
+struct AioRequest {
+    op: AioOp,
+    fd: c_int,
+    file: Arc<dyn FileLike>,
+    buf: usize,
+    nbytes: usize,
+    offset: ctypes::off_t,
+    notify: AioNotify,
+    /// requests on the same descriptor queued before an `aio_fsync`
+    before: Vec<Arc<AioRequest>>,
+    list: Option<Arc<AioList>>,
+    state: SpinNoIrq<AioState>,
+}
+
+impl AioRequest {
+    fn result(&self) -> Option<LinuxResult<usize>> {
+        match *self.state.lock() {
+            AioState::Done(result) => Some(result),
+            _ => None,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.result().is_some()
+    }
+
+    fn run(&self) {
+        {
+            let mut state = self.state.lock();
+            // canceled before a worker got it
+            if !matches!(*state, AioState::Queued) {
+                return;
+            }
+            *state = AioState::Running;
+        }
+        let result = self.execute();
+        *self.state.lock() = AioState::Done(result);
+        self.completed();
+    }
+
+    fn execute(&self) -> LinuxResult<usize> {
+        if self.nbytes == 0 && !matches!(self.op, AioOp::Fsync) {
+            return Ok(0);
+        }
+        let file = self.file.clone().into_any().downcast::<File>();
+        match self.op {
+            AioOp::Read => {
+                let buf =
+                    unsafe { core::slice::from_raw_parts_mut(self.buf as *mut u8, self.nbytes) };
+                match file {
+                    Ok(file) => Ok(file.inner.read().read_at(self.offset as u64, buf)?),
+                    Err(_) => self.file.read(buf),
+                }
+            }
+            AioOp::Write => {
+                let buf =
+                    unsafe { core::slice::from_raw_parts(self.buf as *const u8, self.nbytes) };
+                match file {
+                    Ok(file) => {
+                        let mut inner = file.inner.write();
+                        if inner.is_append() {
+                            Ok(inner.write(buf)?)
+                        } else {
+                            Ok(inner.write_at(self.offset as u64, buf)?)
+                        }
+                    }
+                    Err(_) => self.file.write(buf),
+                }
+            }
+            AioOp::Fsync => {
+                AIO_WAIT_QUEUE.wait_until(|| self.before.iter().all(|req| req.is_done()));
+                self.file.flush().map(|_| 0)
+            }
+        }
+    }
+
+    /// Sends the notifications of the request once it is done.
+    fn completed(&self) {
+        self.notify.notify();
+        if let Some(list) = &self.list {
+            list.complete_one();
+        }
+        AIO_WAIT_QUEUE.notify_all(true);
+    }
+}
+
+fn current_pid() -> u64 {
+    ruxtask::current().process_id().as_u64()
+}
+
+/// Queues the request of `aiocbp` on the worker pool of the current process.
+unsafe fn submit(
+    aiocbp: *mut ctypes::aiocb,
+    op: AioOp,
+    list: Option<Arc<AioList>>,
+) -> LinuxResult<Arc<AioRequest>> {
+    if aiocbp.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let cb = &*aiocbp;
+    if cb.aio_reqprio < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let file = get_file_like(cb.aio_fildes)?;
+    if !matches!(op, AioOp::Fsync) {
+        if cb.aio_buf.is_null() && cb.aio_nbytes > 0 {
+            return Err(LinuxError::EFAULT);
+        }
+        if cb.aio_offset < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+    }
+    let notify = AioNotify::from_sigevent(&cb.aio_sigevent)?;
+
+    let pid = current_pid();
+    let mut requests = AIO_REQUESTS.lock();
+    let key = (pid, aiocbp as usize);
+    if requests.get(&key).is_some_and(|req| !req.is_done()) {
+        return Err(LinuxError::EINVAL);
+    }
+    let before = match op {
+        AioOp::Fsync => requests
+            .iter()
+            .filter(|((p, _), req)| *p == pid && req.fd == cb.aio_fildes && !req.is_done())
+            .map(|(_, req)| req.clone())
+            .collect(),
+        _ => Vec::new(),
+    };
+    let request = Arc::new(AioRequest {
+        op,
+        fd: cb.aio_fildes,
+        file,
+        buf: cb.aio_buf as usize,
+        nbytes: cb.aio_nbytes as usize,
+        offset: cb.aio_offset,
+        notify,
+        before,
+        list,
+        state: SpinNoIrq::new(AioState::Queued),
+    });
+    requests.insert(key, request.clone());
+    drop(requests);
+
+    let pool = AIO_POOLS
+        .lock()
+        .entry(pid)
+        .or_insert_with(|| WorkerPool::new("aio", AIO_MAX_WORKERS))
+        .clone();
+    let req = request.clone();
+    pool.queue(move || req.run());
+    Ok(request)
+}
+
+fn find_request(aiocbp: *const ctypes::aiocb) -> Option<Arc<AioRequest>> {
+    AIO_REQUESTS
+        .lock()
+        .get(&(current_pid(), aiocbp as usize))
+        .cloned()
+}
+
+/// Cancels the requests of the process `pid` no worker has started yet and
+/// forgets all of them with its worker pool, on its exit or `execve`.
+///
+/// No completion is notified, as the control blocks are gone with the
+/// address space. Workers in the middle of a request finish it and exit once
+/// idle.
+pub(crate) fn release_process_aio(pid: u64) {
+    let mut released = Vec::new();
+    AIO_REQUESTS.lock().retain(|&(p, _), req| {
+        if p == pid {
+            released.push(req.clone());
+        }
+        p != pid
+    });
+    for request in released {
+        let mut state = request.state.lock();
+        if matches!(*state, AioState::Queued) {
+            *state = AioState::Done(Err(LinuxError::ECANCELED));
+        }
+    }
+    AIO_POOLS.lock().remove(&pid);
+    // wake the `aio_fsync` requests waiting for the canceled ones
+    AIO_WAIT_QUEUE.notify_all(true);
+}
+
+/// Queue a read of `aio_nbytes` bytes at `aio_offset` of `aio_fildes` into
+/// `aio_buf`, as described by `aiocbp`.
+pub unsafe fn sys_aio_read(aiocbp: *mut ctypes::aiocb) -> c_int {
+    debug!("sys_aio_read <= aiocbp: {:p}", aiocbp);
+    syscall_body!(sys_aio_read, {
+        submit(aiocbp, AioOp::Read, None)?;
+        Ok(0)
+    })
+}
+
+/// Queue a write of `aio_nbytes` bytes from `aio_buf` at `aio_offset` of
+/// `aio_fildes`, or at its end if it is opened with `O_APPEND`, as described
+/// by `aiocbp`.
+pub unsafe fn sys_aio_write(aiocbp: *mut ctypes::aiocb) -> c_int {
+    debug!("sys_aio_write <= aiocbp: {:p}", aiocbp);
+    syscall_body!(sys_aio_write, {
+        submit(aiocbp, AioOp::Write, None)?;
+        Ok(0)
+    })
+}
+
+/// Queue a synchronization of `aio_fildes` in `aiocbp`, done after all the
+/// requests on it queued before.
+///
+/// `op` is `O_SYNC` or `O_DSYNC`, which are handled the same.
+pub unsafe fn sys_aio_fsync(op: c_int, aiocbp: *mut ctypes::aiocb) -> c_int {
+    debug!("sys_aio_fsync <= op: {:#x}, aiocbp: {:p}", op, aiocbp);
+    syscall_body!(sys_aio_fsync, {
+        if op as u32 != ctypes::O_SYNC && op as u32 != ctypes::O_DSYNC {
+            return Err(LinuxError::EINVAL);
+        }
+        submit(aiocbp, AioOp::Fsync, None)?;
+        Ok(0)
+    })
+}
+
+/// Get the error status of the request of `aiocbp`: `EINPROGRESS` until it
+/// completes, then 0 or the error it failed with.
+pub fn sys_aio_error(aiocbp: *const ctypes::aiocb) -> c_int {
+    debug!("sys_aio_error <= aiocbp: {:p}", aiocbp);
+    syscall_body!(sys_aio_error, {
+        let request = find_request(aiocbp).ok_or(LinuxError::EINVAL)?;
+        Ok(match request.result() {
+            None => LinuxError::EINPROGRESS as c_int,
+            Some(Ok(_)) => 0,
+            Some(Err(e)) => e as c_int,
+        })
+    })
+}
+
+/// Get the result of the completed request of `aiocbp`, which is forgotten
+/// afterwards.
+pub fn sys_aio_return(aiocbp: *mut ctypes::aiocb) -> ctypes::ssize_t {
+    debug!("sys_aio_return <= aiocbp: {:p}", aiocbp);
+    syscall_body!(sys_aio_return, {
+        let mut requests = AIO_REQUESTS.lock();
+        let key = (current_pid(), aiocbp as usize);
+        let result = requests
+            .get(&key)
+            .and_then(|req| req.result())
+            .ok_or(LinuxError::EINVAL)?;
+        requests.remove(&key);
+        result.map(|n| n as ctypes::ssize_t)
+    })
+}
+
+/// Cancel the request of `aiocbp`, or all the requests on `fd` if it is NULL.
+///
+/// Only the requests no worker has started yet are canceled, they complete
+/// with `ECANCELED`. Returns `AIO_CANCELED`, `AIO_NOTCANCELED` if some
+/// requests are in progress, or `AIO_ALLDONE`.
+pub unsafe fn sys_aio_cancel(fd: c_int, aiocbp: *mut ctypes::aiocb) -> c_int {
+    debug!("sys_aio_cancel <= fd: {}, aiocbp: {:p}", fd, aiocbp);
+    syscall_body!(sys_aio_cancel, {
+        get_file_like(fd)?;
+        let requests: Vec<_> = if aiocbp.is_null() {
+            let pid = current_pid();
+            AIO_REQUESTS
+                .lock()
+                .iter()
+                .filter(|((p, _), req)| *p == pid && req.fd == fd)
+                .map(|(_, req)| req.clone())
+                .collect()
+        } else {
+            if (*aiocbp).aio_fildes != fd {
+                return Err(LinuxError::EINVAL);
+            }
+            find_request(aiocbp).into_iter().collect()
+        };
+
+        let (mut canceled, mut not_canceled) = (false, false);
+        for request in requests {
+            let mut state = request.state.lock();
+            match *state {
+                AioState::Queued => {
+                    *state = AioState::Done(Err(LinuxError::ECANCELED));
+                    drop(state);
+                    request.completed();
+                    canceled = true;
+                }
+                AioState::Running => not_canceled = true,
+                AioState::Done(_) => {}
+            }
+        }
+        let status = if not_canceled {
+            ctypes::AIO_NOTCANCELED
+        } else if canceled {
+            ctypes::AIO_CANCELED
+        } else {
+            ctypes::AIO_ALLDONE
+        };
+        Ok(status as c_int)
+    })
+}
+
+/// Wait until one of the `nent` requests in `list` completes, or until the
+/// relative `timeout` passes if it is not NULL, which fails with `EAGAIN`.
+///
+/// NULL entries of `list` are ignored.
+pub unsafe fn sys_aio_suspend(
+    list: *const *const ctypes::aiocb,
+    nent: c_int,
+    timeout: *const ctypes::timespec,
+) -> c_int {
+    debug!(
+        "sys_aio_suspend <= list: {:p}, nent: {}, timeout: {:p}",
+        list, nent, timeout
+    );
+    syscall_body!(sys_aio_suspend, {
+        if nent < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if nent == 0 {
+            return Ok(0);
+        }
+        if list.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let entries = core::slice::from_raw_parts(list, nent as usize);
+        let mut requests = Vec::new();
+        for &aiocbp in entries.iter().filter(|cb| !cb.is_null()) {
+            // a request collected by `aio_return` is complete
+            let Some(request) = find_request(aiocbp) else {
+                return Ok(0);
+            };
+            requests.push(request);
+        }
+        let any_done = || requests.iter().any(|req| req.is_done());
+        if timeout.is_null() {
+            AIO_WAIT_QUEUE.wait_until(any_done);
+            return Ok(0);
+        }
+        let ts = *timeout;
+        if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+            return Err(LinuxError::EINVAL);
+        }
+        if AIO_WAIT_QUEUE.wait_timeout_until(Duration::from(ts), any_done) && !any_done() {
+            return Err(LinuxError::EAGAIN);
+        }
+        Ok(0)
+    })
+}
+
+/// Queue the `nent` requests in `list`, each a read, a write or nothing by
+/// its `aio_lio_opcode`.
+///
+/// With `LIO_WAIT` it returns once all of them complete, with `LIO_NOWAIT`
+/// it returns at once and notifies by `sevp` when all of them complete.
+/// Fails with `EIO` if one of the requests cannot be queued or fails.
+pub unsafe fn sys_lio_listio(
+    mode: c_int,
+    list: *const *mut ctypes::aiocb,
+    nent: c_int,
+    sevp: *mut ctypes::sigevent,
+) -> c_int {
+    debug!(
+        "sys_lio_listio <= mode: {}, list: {:p}, nent: {}, sevp: {:p}",
+        mode, list, nent, sevp
+    );
+    syscall_body!(sys_lio_listio, {
+        let wait = match mode as u32 {
+            ctypes::LIO_WAIT => true,
+            ctypes::LIO_NOWAIT => false,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        if !(0..=AIO_LISTIO_MAX).contains(&nent) {
+            return Err(LinuxError::EINVAL);
+        }
+        if list.is_null() && nent > 0 {
+            return Err(LinuxError::EFAULT);
+        }
+        let entries = match nent {
+            0 => &[],
+            _ => core::slice::from_raw_parts(list, nent as usize),
+        };
+        let lio_list = if !wait && !sevp.is_null() {
+            // one count for the submission itself, so that requests completing
+            // early do not notify before all of them are queued
+            Some(Arc::new(AioList {
+                remaining: AtomicUsize::new(1),
+                notify: AioNotify::from_sigevent(&*sevp)?,
+            }))
+        } else {
+            None
+        };
+
+        let mut failed = false;
+        let mut requests = Vec::new();
+        for &aiocbp in entries.iter().filter(|cb| !cb.is_null()) {
+            let op = match (*aiocbp).aio_lio_opcode as u32 {
+                ctypes::LIO_READ => AioOp::Read,
+                ctypes::LIO_WRITE => AioOp::Write,
+                ctypes::LIO_NOP => continue,
+                _ => {
+                    failed = true;
+                    continue;
+                }
+            };
+            if let Some(lio_list) = &lio_list {
+                lio_list.remaining.fetch_add(1, Ordering::AcqRel);
+            }
+            match submit(aiocbp, op, lio_list.clone()) {
+                Ok(request) => requests.push(request),
+                Err(_) => {
+                    if let Some(lio_list) = &lio_list {
+                        lio_list.complete_one();
+                    }
+                    failed = true;
+                }
+            }
+        }
+        if let Some(lio_list) = &lio_list {
+            lio_list.complete_one();
+        }
+
+        if wait {
+            AIO_WAIT_QUEUE.wait_until(|| requests.iter().all(|req| req.is_done()));
+            failed |= requests
+                .iter()
+                .any(|req| matches!(req.result(), Some(Err(_))));
+        }
+        if failed {
+            return Err(LinuxError::EIO);
+        }
+        Ok(0)
+    })
+}
@@ -0,0 +1,212 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! `eventfd` implementation.
+
+use alloc::sync::{Arc, Weak};
+use core::ffi::{c_int, c_uint};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use ruxfdtable::{FileLike, PollObserver, PollObservers, RuxStat};
+use ruxfs::AbsPath;
+use ruxtask::fs::add_file_like;
+use spinlock::SpinNoIrq;
+
+use crate::{ctypes, imp::fs::flags_to_options};
+
+/// The counter never reaches this value, writes that would overflow it block.
+const EVENTFD_MAX: u64 = u64::MAX - 1;
+
+pub struct EventFd {
+    count: SpinNoIrq<u64>,
+    semaphore: bool,
+    nonblocking: AtomicBool,
+    /// Tasks waiting for the counter to become nonzero or to have room.
+    #[cfg(feature = "multitask")]
+    wait_queue: ruxtask::WaitQueue,
+    observers: PollObservers,
+}
+
+impl EventFd {
+    fn new(initval: u64, flags: u32) -> Self {
+        Self {
+            count: SpinNoIrq::new(initval),
+            semaphore: flags & ctypes::EFD_SEMAPHORE != 0,
+            nonblocking: AtomicBool::new(flags & ctypes::EFD_NONBLOCK != 0),
+            #[cfg(feature = "multitask")]
+            wait_queue: ruxtask::WaitQueue::new(),
+            observers: PollObservers::new(),
+        }
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    /// Block the current task until `condition` holds.
+    fn wait_until(&self, condition: impl FnMut() -> bool) {
+        #[cfg(feature = "multitask")]
+        self.wait_queue.wait_until(condition);
+        #[cfg(not(feature = "multitask"))]
+        {
+            let mut condition = condition;
+            while !condition() {
+                crate::sys_sched_yield();
+            }
+        }
+    }
+
+    /// Adds `value` to the counter without blocking, saturating at its
+    /// maximum, to notify the reader of events.
+    pub(crate) fn signal(&self, value: u64) {
+        let mut count = self.count.lock();
+        *count = count.saturating_add(value).min(EVENTFD_MAX);
+        drop(count);
+        self.wake();
+    }
+
+    /// Wake up the waiting tasks and epoll instances after the counter changed.
+    fn wake(&self) {
+        #[cfg(feature = "multitask")]
+        self.wait_queue.notify_all(true);
+        self.observers.notify();
+    }
+}
+
+impl FileLike for EventFd {
+    fn path(&self) -> AbsPath {
+        AbsPath::new("/eventfd")
+    }
+
+    /// Reads the counter and resets it, or decreases it by one in semaphore mode.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.len() < 8 {
+            return Err(LinuxError::EINVAL);
+        }
+        loop {
+            let mut count = self.count.lock();
+            if *count > 0 {
+                let value = if self.semaphore { 1 } else { *count };
+                *count -= value;
+                drop(count);
+                buf[..8].copy_from_slice(&value.to_ne_bytes());
+                self.wake();
+                return Ok(8);
+            }
+            drop(count);
+            if self.nonblocking() {
+                return Err(LinuxError::EAGAIN);
+            }
+            self.wait_until(|| *self.count.lock() > 0);
+        }
+    }
+
+    /// Adds an 8-byte integer to the counter.
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        if buf.len() < 8 {
+            return Err(LinuxError::EINVAL);
+        }
+        let value = u64::from_ne_bytes(buf[..8].try_into().unwrap());
+        if value == u64::MAX {
+            return Err(LinuxError::EINVAL);
+        }
+        loop {
+            let mut count = self.count.lock();
+            if EVENTFD_MAX - *count >= value {
+                *count += value;
+                drop(count);
+                self.wake();
+                return Ok(8);
+            }
+            drop(count);
+            if self.nonblocking() {
+                return Err(LinuxError::EAGAIN);
+            }
+            self.wait_until(|| EVENTFD_MAX - *self.count.lock() >= value);
+        }
+    }
+
+    fn flush(&self) -> LinuxResult {
+        Ok(())
+    }
+
+    fn stat(&self) -> LinuxResult<RuxStat> {
+        let st_mode = 0o600u32; // rw-------
+        Ok(RuxStat::from(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            ..Default::default()
+        }))
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let count = *self.count.lock();
+        Ok(PollState {
+            readable: count > 0,
+            writable: count < EVENTFD_MAX,
+            pollhup: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        self.observers.add(observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        self.observers.remove(observer, key);
+    }
+}
+
+/// Create an eventfd object with the counter set to `initval`.
+///
+/// `flags` can be `EFD_SEMAPHORE`, `EFD_NONBLOCK` and `EFD_CLOEXEC`.
+pub fn sys_eventfd2(initval: c_uint, flags: c_int) -> c_int {
+    debug!("sys_eventfd2 <= initval: {}, flags: {:#x}", initval, flags);
+    syscall_body!(sys_eventfd2, {
+        let flags = flags as u32;
+        if flags & !(ctypes::EFD_SEMAPHORE | ctypes::EFD_NONBLOCK | ctypes::EFD_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let eventfd = EventFd::new(initval as u64, flags);
+        add_file_like(
+            Arc::new(eventfd),
+            flags_to_options(
+                (flags & ctypes::EFD_CLOEXEC) as c_int | ctypes::O_RDWR as c_int,
+                0,
+            ),
+        )
+    })
+}
+
+/// Create an eventfd object, same as `eventfd2` with no flags.
+pub fn sys_eventfd(initval: c_uint) -> c_int {
+    sys_eventfd2(initval, 0)
+}
@@ -142,6 +142,7 @@ pub fn sys_execve(pathname: *const c_char, argv: usize, envp: usize) -> ! {
         .unwrap()
         .fd_table
         .do_close_on_exec();
+    crate::imp::task::release_process(false);
 
     set_sp_and_jmp(sp, entry);
 }
@@ -9,7 +9,7 @@
 
 use core::ffi::c_int;
 
-use axerrno::LinuxError;
+use axerrno::{LinuxError, LinuxResult};
 use ruxfdtable::{RuxStat, RuxTimeSpec};
 use ruxtask::current;
 pub use ruxtask::fs::{close_file_like, get_file_like, RUX_FILE_LIMIT};
@@ -198,7 +198,8 @@ pub fn sys_dup3(old_fd: c_int, new_fd: c_int, flags: c_int) -> c_int {
 
 /// Manipulate file descriptor.
 ///
-/// TODO: `SET/GET` command is ignored, hard-code stdin/stdout
+/// `F_SETFL` changes `O_APPEND`, `O_NONBLOCK`, `O_DIRECT` and `O_NOATIME`
+/// of the open file description, the other status flags are ignored.
 pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
     debug!("sys_fcntl <= fd: {} cmd: {} arg: {}", fd, cmd, arg);
     syscall_body!(sys_fcntl, {
@@ -238,26 +239,10 @@ pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
                 Ok(new_fd as _)
             }
             ctypes::F_SETFL => {
-                if fd == 0 || fd == 1 || fd == 2 {
-                    return Ok(0);
-                }
-                get_file_like(fd)?.set_nonblocking(arg & (ctypes::O_NONBLOCK as usize) > 0)?;
+                set_status_flags(fd, arg as u32)?;
                 Ok(0)
             }
-            ctypes::F_GETFL => {
-                use ctypes::{O_RDONLY, O_RDWR, O_WRONLY};
-                let f_state = get_file_like(fd)?.poll()?;
-                let mut flags: core::ffi::c_uint = 0;
-                // Only support read/write flags(O_ACCMODE)
-                if f_state.writable && f_state.readable {
-                    flags |= O_RDWR;
-                } else if f_state.writable {
-                    flags |= O_WRONLY;
-                } else if f_state.readable {
-                    flags |= O_RDONLY;
-                }
-                Ok(flags as c_int)
-            }
+            ctypes::F_GETFL => Ok(get_status_flags(fd)? as c_int),
             ctypes::F_SETFD => {
                 let cloexec = (arg as u32 & ctypes::FD_CLOEXEC) == 1;
                 let binding_task = current();
@@ -269,6 +254,23 @@ pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
                 fd_table.set_cloexec(fd as _, cloexec);
                 Ok(0)
             }
+            #[cfg(feature = "fs")]
+            ctypes::F_GETLK
+            | ctypes::F_SETLK
+            | ctypes::F_SETLKW
+            | ctypes::F_OFD_GETLK
+            | ctypes::F_OFD_SETLK
+            | ctypes::F_OFD_SETLKW => unsafe {
+                crate::imp::flock::fcntl_lock(fd, cmd as u32, arg as *mut ctypes::flock)
+            },
+            #[cfg(feature = "fs")]
+            ctypes::F_ADD_SEALS | ctypes::F_GET_SEALS => {
+                crate::imp::memfd::fcntl_seals(fd, cmd as u32, arg)
+            }
+            #[cfg(any(feature = "pipe", feature = "fs"))]
+            ctypes::F_GETPIPE_SZ => Ok(pipe_capacity(fd, None)? as _),
+            #[cfg(any(feature = "pipe", feature = "fs"))]
+            ctypes::F_SETPIPE_SZ => Ok(pipe_capacity(fd, Some(arg))? as _),
             _ => {
                 warn!("unsupported fcntl parameters: cmd {}", cmd);
                 Ok(0)
@@ -276,3 +278,122 @@ pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
         }
     })
 }
+
+/// Gets the access mode and the status flags of the open file description
+/// of `fd`, as returned by `F_GETFL`.
+fn get_status_flags(fd: c_int) -> LinuxResult<u32> {
+    let f = get_file_like(fd)?;
+    #[cfg(feature = "fs")]
+    {
+        let any = f.clone().into_any();
+        if let Ok(file) = any.clone().downcast::<ruxtask::fs::File>() {
+            let file = file.inner.read();
+            if file.is_path_only() {
+                return Ok(ctypes::O_PATH);
+            }
+            let mut flags = match (file.is_readable(), file.is_writable()) {
+                (true, true) => ctypes::O_RDWR,
+                (false, true) => ctypes::O_WRONLY,
+                _ => ctypes::O_RDONLY,
+            };
+            for (set, flag) in [
+                (file.is_append(), ctypes::O_APPEND),
+                (file.is_nonblocking(), ctypes::O_NONBLOCK),
+                (file.is_sync(), ctypes::O_SYNC),
+                (file.is_dsync(), ctypes::O_DSYNC),
+                (file.is_direct(), ctypes::O_DIRECT),
+                (file.is_noatime(), ctypes::O_NOATIME),
+            ] {
+                if set {
+                    flags |= flag;
+                }
+            }
+            return Ok(flags);
+        }
+        if let Ok(dir) = any.downcast::<ruxtask::fs::Directory>() {
+            let dir = dir.inner.read();
+            if dir.is_path_only() {
+                return Ok(ctypes::O_PATH);
+            }
+            let mut flags = ctypes::O_RDONLY | ctypes::O_DIRECTORY;
+            if dir.is_nonblocking() {
+                flags |= ctypes::O_NONBLOCK;
+            }
+            if dir.is_noatime() {
+                flags |= ctypes::O_NOATIME;
+            }
+            return Ok(flags);
+        }
+    }
+    // other objects don't keep their access mode, guess it from their readiness
+    let state = f.poll()?;
+    let mut flags = if state.readable && state.writable {
+        ctypes::O_RDWR
+    } else if state.writable {
+        ctypes::O_WRONLY
+    } else {
+        ctypes::O_RDONLY
+    };
+    if f.is_nonblocking() {
+        flags |= ctypes::O_NONBLOCK;
+    }
+    Ok(flags)
+}
+
+/// Changes the status flags of the open file description of `fd`, as
+/// `F_SETFL` does.
+fn set_status_flags(fd: c_int, flags: u32) -> LinuxResult {
+    let f = get_file_like(fd)?;
+    #[cfg(feature = "fs")]
+    {
+        let any = f.clone().into_any();
+        if let Ok(file) = any.clone().downcast::<ruxtask::fs::File>() {
+            let mut file = file.inner.write();
+            if file.is_path_only() {
+                return Err(LinuxError::EBADF);
+            }
+            file.set_append(flags & ctypes::O_APPEND != 0);
+            file.set_nonblocking(flags & ctypes::O_NONBLOCK != 0);
+            file.set_direct(flags & ctypes::O_DIRECT != 0);
+            file.set_noatime(flags & ctypes::O_NOATIME != 0);
+            return Ok(());
+        }
+        if let Ok(dir) = any.downcast::<ruxtask::fs::Directory>() {
+            let mut dir = dir.inner.write();
+            if dir.is_path_only() {
+                return Err(LinuxError::EBADF);
+            }
+            dir.set_nonblocking(flags & ctypes::O_NONBLOCK != 0);
+            dir.set_noatime(flags & ctypes::O_NOATIME != 0);
+            return Ok(());
+        }
+    }
+    f.set_nonblocking(flags & ctypes::O_NONBLOCK != 0)
+}
+
+/// Gets the capacity of the pipe or FIFO `fd`, after resizing it to `size` if
+/// given, as `F_GETPIPE_SZ` and `F_SETPIPE_SZ` do.
+#[cfg(any(feature = "pipe", feature = "fs"))]
+fn pipe_capacity(fd: c_int, size: Option<usize>) -> LinuxResult<usize> {
+    #[cfg(feature = "pipe")]
+    if let Ok(pipe) = crate::imp::pipe::Pipe::from_fd(fd) {
+        return match size {
+            Some(size) => pipe.set_capacity(size),
+            None => Ok(pipe.capacity()),
+        };
+    }
+    #[cfg(feature = "fs")]
+    if let Ok(file) = ruxtask::fs::File::from_fd(fd) {
+        let file = file.inner.read();
+        let result = match size {
+            Some(size) => file.set_fifo_capacity(size),
+            None => file.fifo_capacity(),
+        };
+        // not a FIFO
+        return result.map_err(|e| match e {
+            axerrno::AxError::Unsupported => LinuxError::EBADF,
+            e => e.into(),
+        });
+    }
+    Err(LinuxError::EBADF)
+}
@@ -0,0 +1,152 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Advisory file locks: record locks of `fcntl` and whole-file locks of `flock`.
+//!
+//! The locks themselves are managed by [`ruxfs::lock`].
+
+use core::ffi::c_int;
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::SeekFrom;
+use ruxfs::lock::{FileLock, LockOwner, LockType};
+use ruxtask::fs::File;
+
+use crate::ctypes;
+
+/// The owner of POSIX record locks placed by the current process.
+fn process_owner() -> LockOwner {
+    LockOwner::Process(ruxtask::current().process_id().as_u64())
+}
+
+/// Get the byte range `start..end` described by `flock`, relative to the
+/// beginning of the file.
+fn lock_range(file: &File, flock: &ctypes::flock) -> LinuxResult<(u64, u64)> {
+    let base = match flock.l_whence {
+        0 => 0,
+        1 => file.inner.write().seek(SeekFrom::Current(0))?,
+        2 => file.inner.read().get_attr()?.size(),
+        _ => return Err(LinuxError::EINVAL),
+    };
+    let start = (base as i64)
+        .checked_add(flock.l_start)
+        .ok_or(LinuxError::EOVERFLOW)?;
+    // a negative length locks the bytes before `start`
+    let (start, end) = match flock.l_len {
+        0 => (start, None),
+        len if len > 0 => (
+            start,
+            Some(start.checked_add(len).ok_or(LinuxError::EOVERFLOW)?),
+        ),
+        len => (start + len, Some(start)),
+    };
+    if start < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok((start as u64, end.map_or(u64::MAX, |end| end as u64)))
+}
+
+fn lock_type(l_type: i16) -> LinuxResult<LockType> {
+    match l_type as u32 {
+        ctypes::F_RDLCK => Ok(LockType::Read),
+        ctypes::F_WRLCK => Ok(LockType::Write),
+        ctypes::F_UNLCK => Ok(LockType::Unlock),
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Handle the `F_GETLK`, `F_SETLK`, `F_SETLKW` commands of `fcntl` and their
+/// `F_OFD_*` counterparts.
+///
+/// POSIX locks are owned by the process and released when it closes any
+/// descriptor of the file, OFD locks are owned by the open file.
+pub(crate) unsafe fn fcntl_lock(
+    fd: c_int,
+    cmd: u32,
+    flock: *mut ctypes::flock,
+) -> LinuxResult<c_int> {
+    if flock.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let flock = &mut *flock;
+    let file = File::from_fd(fd)?;
+    let ofd = matches!(
+        cmd,
+        ctypes::F_OFD_GETLK | ctypes::F_OFD_SETLK | ctypes::F_OFD_SETLKW
+    );
+    if ofd && flock.l_pid != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let owner = if ofd {
+        file.lock_owner()
+    } else {
+        process_owner()
+    };
+    let (start, end) = lock_range(&file, flock)?;
+    let lock = FileLock {
+        owner,
+        ty: lock_type(flock.l_type)?,
+        start,
+        end,
+    };
+
+    match cmd {
+        ctypes::F_GETLK | ctypes::F_OFD_GETLK => {
+            if lock.ty == LockType::Unlock {
+                return Err(LinuxError::EINVAL);
+            }
+            let pid = ruxtask::current().process_id().as_u64();
+            match file.inner.read().get_lock(&lock, pid)? {
+                Some(conflict) => {
+                    let l_type = match conflict.ty {
+                        LockType::Read => ctypes::F_RDLCK,
+                        _ => ctypes::F_WRLCK,
+                    };
+                    flock.l_type = l_type as _;
+                    flock.l_whence = 0;
+                    flock.l_start = conflict.start as _;
+                    flock.l_len = if conflict.end == u64::MAX {
+                        0
+                    } else {
+                        (conflict.end - conflict.start) as _
+                    };
+                    flock.l_pid = match conflict.owner {
+                        LockOwner::Process(pid) => pid as _,
+                        LockOwner::OpenFile(_) => -1,
+                    };
+                }
+                None => flock.l_type = ctypes::F_UNLCK as _,
+            }
+        }
+        ctypes::F_SETLKW | ctypes::F_OFD_SETLKW => file.set_lock(lock, true)?,
+        _ => file.set_lock(lock, false)?,
+    }
+    Ok(0)
+}
+
+/// Apply or remove an advisory lock on the whole open file `fd`.
+///
+/// `operation` is one of `LOCK_SH`, `LOCK_EX` and `LOCK_UN`, optionally
+/// combined with `LOCK_NB` not to wait for conflicting locks. The lock is
+/// shared by all descriptors of the same open file.
+pub fn sys_flock(fd: c_int, operation: c_int) -> c_int {
+    debug!("sys_flock <= fd: {}, operation: {:#x}", fd, operation);
+    syscall_body!(sys_flock, {
+        let file = File::from_fd(fd)?;
+        let nonblocking = operation as u32 & ctypes::LOCK_NB != 0;
+        let ty = match operation as u32 & !ctypes::LOCK_NB {
+            ctypes::LOCK_SH => LockType::Read,
+            ctypes::LOCK_EX => LockType::Write,
+            ctypes::LOCK_UN => LockType::Unlock,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        file.set_flock(FileLock::whole_file(file.lock_owner(), ty), !nonblocking)?;
+        Ok(0)
+    })
+}
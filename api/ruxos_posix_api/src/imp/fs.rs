@@ -7,18 +7,19 @@
  *   See the Mulan PSL v2 for more details.
  */
 
-use alloc::sync::Arc;
+use alloc::{format, sync::Arc};
 use core::{
-    ffi::{c_char, c_int, c_long, c_void, CStr},
+    ffi::{c_char, c_int, c_long, c_uint, c_void, CStr},
     str,
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 use axerrno::{LinuxError, LinuxResult};
 use axio::{Error, SeekFrom};
-use ruxfdtable::{FileLike, RuxStat};
+use ruxfdtable::{FileLike, RuxStat, RuxTimeSpec};
 use ruxfs::api::FileType;
 use ruxfs::{
-    fops::{self, DirEntry, OpenOptions},
+    fops::{self, DirEntry, FilePerm, OpenOptions},
     AbsPath, RelPath,
 };
 
@@ -41,9 +42,18 @@ impl ruxtask::fs::InitFs for InitFsImpl {
 }
 
 /// Convert open flags to [`OpenOptions`].
+///
+/// `O_NOCTTY` and `O_LARGEFILE` need no handling, a file never becomes the
+/// controlling terminal on open, and file offsets are always 64-bit.
 pub fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     let flags = flags as u32;
     let mut options = OpenOptions::new();
+    if flags & ctypes::O_PATH != 0 {
+        // only the file itself is opened, the other flags are ignored
+        options.path(true);
+        options.cloexec(flags & ctypes::O_CLOEXEC != 0);
+        return options;
+    }
     match flags & 0b11 {
         ctypes::O_RDONLY => options.read(true),
         ctypes::O_WRONLY => options.write(true),
@@ -61,7 +71,7 @@ pub fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     if flags & ctypes::O_CREAT != 0 {
         options.create(true);
     }
-    if flags & ctypes::O_EXEC != 0 {
+    if flags & ctypes::O_EXCL != 0 && flags & ctypes::O_CREAT != 0 {
         options.create_new(true);
     }
     if flags & ctypes::O_CLOEXEC != 0 {
@@ -70,6 +80,18 @@ pub fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     if flags & ctypes::O_NONBLOCK != 0 {
         options.non_blocking(true);
     }
+    // the bits of `O_SYNC` include `O_DSYNC`
+    if flags & ctypes::O_SYNC == ctypes::O_SYNC {
+        options.sync(true);
+    } else if flags & ctypes::O_DSYNC != 0 {
+        options.dsync(true);
+    }
+    if flags & ctypes::O_DIRECT != 0 {
+        options.direct(true);
+    }
+    if flags & ctypes::O_NOATIME != 0 {
+        options.noatime(true);
+    }
     options
 }
 
@@ -79,113 +101,149 @@ pub fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
 /// has the maximum number of files open.
 pub fn sys_open(filename: *const c_char, flags: c_int, mode: ctypes::mode_t) -> c_int {
     syscall_body!(sys_open, {
-        let path = parse_path(filename)?;
-        let mut opts = flags_to_options(flags, mode);
+        let path = resolve_open_path(ctypes::AT_FDCWD, filename, flags)?;
         debug!("sys_open <= {:?} {:#o} {:#o}", path, flags, mode);
-        // Check flag and attr
-        let node = match fops::lookup(&path) {
-            Ok(node) => {
-                if opts.create_new {
-                    return Err(LinuxError::EEXIST);
-                }
-                node
+        open_path(&path, flags, mode)
+    })
+}
+
+/// Open the file at the absolute `path` and insert it into the file
+/// descriptor table, creating it if requested by `flags`.
+pub(crate) fn open_path(path: &AbsPath, flags: c_int, mode: ctypes::mode_t) -> LinuxResult<c_int> {
+    if flags as u32 & ctypes::O_TMPFILE == ctypes::O_TMPFILE {
+        return open_tmpfile(path, flags, mode);
+    }
+    let mut opts = flags_to_options(flags, mode);
+    let open_dir = flags as u32 & ctypes::O_DIRECTORY != 0;
+    let searchable = flags as u32 & ctypes::O_SEARCH != 0;
+    // Check node attributes and handle not found
+    let node = match fops::lookup(path) {
+        Ok(node) => {
+            let attr = node.get_attr()?;
+            // Node exists but O_EXCL is set
+            if opts.create_new {
+                return Err(LinuxError::EEXIST);
             }
-            Err(Error::NotFound) => {
-                if !opts.create {
-                    return Err(LinuxError::ENOENT);
-                }
-                fops::create_file(&path)?;
-                fops::lookup(&path)?
+            // Node is a symbolic link which was not followed
+            if attr.is_symlink() && !opts.path {
+                return Err(LinuxError::ELOOP);
             }
-            Err(e) => return Err(e.into()),
-        };
-        if node.get_attr()?.is_dir() {
-            return Err(LinuxError::EISDIR);
-        } else if node.get_attr()?.is_fifo() {
-            opts.truncate = false;
-            // process error return in non-blocking mode
-            // note: blocking mode waiting logic is implemented in the file system/FIFO implementation
-            if opts.non_blocking {
-                if !opts.read && opts.write {
-                    if !node.fifo_has_readers() {
-                        return Err(LinuxError::ENXIO);
-                    }
-                }
+            // Node is not a directory but O_DIRECTORY is set
+            if !attr.is_dir() && open_dir {
+                return Err(LinuxError::ENOTDIR);
             }
-            let file = fops::open_fifo(&path, node, &opts)?;
-            return File::new(file).add_to_fd_table(opts);
-        } else {
-            // for regular file, truncate and open
-            if opts.truncate {
+            // Directories can't be opened for writing
+            if attr.is_dir() && opts.write {
+                return Err(LinuxError::EISDIR);
+            }
+            // Truncate
+            if attr.is_file() && opts.truncate {
                 node.truncate(0)?;
             }
-            let file = fops::open_file(&path, node, &opts)?;
-            File::new(file).add_to_fd_table(opts)
+            node
         }
-    })
+        Err(Error::NotFound) => {
+            // O_CREAT is not set or O_DIRECTORY is set
+            if open_dir || !opts.create {
+                return Err(LinuxError::ENOENT);
+            }
+            // Create file
+            fops::create_file(path)?;
+            fops::lookup(path)?
+        }
+        Err(e) => return Err(e.into()),
+    };
+    // Open file or directory or FIFO
+    if node.get_attr()?.is_dir() {
+        let dir = fops::open_dir(path, node, &opts)?;
+        Directory::new(dir, searchable).add_to_fd_table(opts)
+    } else if node.get_attr()?.is_fifo() && !opts.path {
+        opts.truncate = false;
+        // process error return in non-blocking mode
+        if opts.non_blocking {
+            if !opts.read && opts.write {
+                if !node.fifo_has_readers() {
+                    return Err(LinuxError::ENXIO);
+                }
+            }
+        }
+        let file = File::new(fops::open_fifo(path, node, &opts)?);
+        if !opts.non_blocking {
+            file.wait_fifo_peer();
+        }
+        file.add_to_fd_table(opts)
+    } else {
+        let file = fops::open_file(path, node, &opts)?;
+        File::new(file).add_to_fd_table(opts)
+    }
+}
+
+/// Resolve the `path` to open from `dirfd`.
+///
+/// A symbolic link at the last component is not followed with `O_NOFOLLOW`,
+/// or with `O_CREAT | O_EXCL`.
+fn resolve_open_path(
+    dirfd: c_int,
+    path: *const c_char,
+    flags: c_int,
+) -> LinuxResult<AbsPath<'static>> {
+    let flags = flags as u32;
+    let excl = ctypes::O_CREAT | ctypes::O_EXCL;
+    let follow = flags & ctypes::O_NOFOLLOW == 0 && flags & excl != excl;
+    resolve_at(dirfd, path, follow, false)
+}
+
+/// Create an unnamed regular file in the directory `dir` for `O_TMPFILE`.
+///
+/// The file is created under a hidden name that is removed right away, so
+/// it is freed with its last opened file.
+fn open_tmpfile(dir: &AbsPath, flags: c_int, mode: ctypes::mode_t) -> LinuxResult<c_int> {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    let opts = flags_to_options(flags & !(ctypes::O_CREAT | ctypes::O_TRUNC) as c_int, mode);
+    if !opts.write || opts.path {
+        return Err(LinuxError::EINVAL);
+    }
+    let dir_node = fops::lookup(dir)?;
+    if !dir_node.get_attr()?.is_dir() {
+        return Err(LinuxError::ENOTDIR);
+    }
+    let name = loop {
+        let name = format!(".tmpfile.{}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        match dir_node.clone().lookup(&RelPath::new(&name)) {
+            Err(Error::NotFound) => break name,
+            Ok(_) => continue,
+            Err(e) => return Err(e.into()),
+        }
+    };
+    let name = RelPath::new(&name);
+    dir_node
+        .create(&name, FileType::File)
+        .map_err(|e| match e {
+            Error::Unsupported => LinuxError::EOPNOTSUPP,
+            e => e.into(),
+        })?;
+    let node = dir_node.clone().lookup(&name)?;
+    let file = fops::open_file(&dir.join(&name), node.clone(), &opts).and_then(|file| {
+        // the mode restricts later opens, e.g. once linked, not this one
+        match node.set_perm(FilePerm::from_bits_truncate(mode as u16)) {
+            Ok(()) | Err(Error::Unsupported) => Ok(file),
+            Err(e) => Err(e),
+        }
+    });
+    dir_node.unlink(&name)?;
+    File::new(file?).add_to_fd_table(opts)
 }
 
 /// Open a file under a specific dir
 pub fn sys_openat(fd: c_int, path: *const c_char, flags: c_int, mode: ctypes::mode_t) -> c_int {
     syscall_body!(sys_openat, {
-        let path = parse_path_at(fd, path)?;
-        let mut opts = flags_to_options(flags, mode);
-        let open_dir = flags as u32 & ctypes::O_DIRECTORY != 0;
-        let searchable = flags as u32 & ctypes::O_SEARCH != 0;
+        let path = resolve_open_path(fd, path, flags)?;
         debug!(
             "sys_openat <= {}, {:?}, {:#o}, {:#o}",
             fd, path, flags, mode
         );
-        // Check node attributes and handle not found
-        let node = match fops::lookup(&path) {
-            Ok(node) => {
-                let attr = node.get_attr()?;
-                // Node exists but O_EXCL is set
-                if opts.create_new {
-                    return Err(LinuxError::EEXIST);
-                }
-                // Node is not a directory but O_DIRECTORY is set
-                if !attr.is_dir() && open_dir {
-                    return Err(LinuxError::ENOTDIR);
-                }
-                // Truncate
-                if attr.is_file() && opts.truncate {
-                    node.truncate(0)?;
-                }
-                node
-            }
-            Err(Error::NotFound) => {
-                // O_CREAT is not set or O_DIRECTORY is set
-                if open_dir || !opts.create {
-                    return Err(LinuxError::ENOENT);
-                }
-                // Create file
-                fops::create_file(&path)?;
-                fops::lookup(&path)?
-            }
-            Err(e) => return Err(e.into()),
-        };
-        // Open file or directory or FIFO
-        if node.get_attr()?.is_dir() {
-            let dir = fops::open_dir(&path, node, &opts)?;
-            Directory::new(dir, searchable).add_to_fd_table(opts)
-        } else if node.get_attr()?.is_fifo() {
-            opts.truncate = false;
-            // process error return in non-blocking mode
-            // note: blocking mode waiting logic is implemented in the file system/FIFO implementation
-            if opts.non_blocking {
-                if !opts.read && opts.write {
-                    if !node.fifo_has_readers() {
-                        return Err(LinuxError::ENXIO);
-                    }
-                }
-            }
-            let file = fops::open_fifo(&path, node, &opts)?;
-            return File::new(file).add_to_fd_table(opts);
-        } else {
-            let file = fops::open_file(&path, node, &opts)?;
-            File::new(file).add_to_fd_table(opts)
-        }
+        open_path(&path, flags, mode)
     })
 }
 
@@ -239,6 +297,18 @@ pub fn sys_lseek(fd: c_int, offset: ctypes::off_t, whence: c_int) -> ctypes::off
             0 => SeekFrom::Start(offset as _),
             1 => SeekFrom::Current(offset as _),
             2 => SeekFrom::End(offset as _),
+            // SEEK_DATA and SEEK_HOLE
+            3 | 4 => {
+                let offset = u64::try_from(offset).map_err(|_| LinuxError::EINVAL)?;
+                let file = File::from_fd(fd)?;
+                let mut file = file.inner.write();
+                let off = if whence == 3 {
+                    file.seek_data(offset)?
+                } else {
+                    file.seek_hole(offset)?
+                };
+                return Ok(off);
+            }
             _ => return Err(LinuxError::EINVAL),
         };
         let off = File::from_fd(fd)?.inner.write().seek(pos)?;
@@ -272,37 +342,18 @@ pub unsafe fn sys_stat(path: *const c_char, buf: *mut core::ffi::c_void) -> c_in
         if buf.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        let node = fops::lookup(&path)?;
-        let attr = node.get_attr()?;
-        let st: RuxStat = if attr.is_dir() {
-            let dir = fops::open_dir(&path, node, &OpenOptions::new())?;
-            Directory::new(dir, false).stat()?
-        } else {
-            let file = fops::open_file(&path, node, &OpenOptions::new())?;
-            File::new(file).stat()?
-        };
+        let st = stat_path(&path)?;
 
         #[cfg(not(feature = "musl"))]
         {
             let buf = buf as *mut ctypes::stat;
-            unsafe { *buf = st };
+            unsafe { *buf = st.into() };
             Ok(0)
         }
 
         #[cfg(feature = "musl")]
         {
-            let kst = buf as *mut ctypes::kstat;
-            unsafe {
-                (*kst).st_dev = st.st_dev;
-                (*kst).st_ino = st.st_ino;
-                (*kst).st_mode = st.st_mode;
-                (*kst).st_nlink = st.st_nlink;
-                (*kst).st_uid = st.st_uid;
-                (*kst).st_gid = st.st_gid;
-                (*kst).st_size = st.st_size;
-                (*kst).st_blocks = st.st_blocks;
-                (*kst).st_blksize = st.st_blksize;
-            }
+            unsafe { write_kstat(&st, buf as *mut ctypes::kstat) };
             Ok(0)
         }
     })
@@ -324,25 +375,7 @@ pub fn sys_fstat(fd: c_int, kst: *mut core::ffi::c_void) -> c_int {
         #[cfg(feature = "musl")]
         {
             let st = get_file_like(fd)?.stat()?;
-            let kst = kst as *mut ctypes::kstat;
-            unsafe {
-                (*kst).st_dev = st.st_dev;
-                (*kst).st_ino = st.st_ino;
-                (*kst).st_mode = st.st_mode;
-                (*kst).st_nlink = st.st_nlink;
-                (*kst).st_uid = st.st_uid;
-                (*kst).st_gid = st.st_gid;
-                (*kst).st_size = st.st_size;
-                (*kst).st_blocks = st.st_blocks;
-                (*kst).st_blksize = st.st_blksize;
-                (*kst).st_atime_sec = st.st_atime.tv_sec;
-                (*kst).st_atime_nsec = st.st_atime.tv_nsec;
-                (*kst).st_mtime_sec = st.st_mtime.tv_sec;
-                (*kst).st_mtime_nsec = st.st_mtime.tv_nsec;
-                (*kst).st_ctime_sec = st.st_ctime.tv_sec;
-                (*kst).st_ctime_nsec = st.st_ctime.tv_nsec;
-                (*kst).st_rdev = st.st_rdev;
-            }
+            unsafe { write_kstat(&st, kst as *mut ctypes::kstat) };
             Ok(0)
         }
     })
@@ -353,12 +386,12 @@ pub fn sys_fstat(fd: c_int, kst: *mut core::ffi::c_void) -> c_int {
 /// Return 0 if success.
 pub unsafe fn sys_lstat(path: *const c_char, buf: *mut ctypes::stat) -> ctypes::ssize_t {
     syscall_body!(sys_lstat, {
-        let path = parse_path(path)?;
+        let path = resolve_at(ctypes::AT_FDCWD, path, false, false)?;
         debug!("sys_lstat <= {:?} {:#x}", path, buf as usize);
         if buf.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        unsafe { *buf = Default::default() }; // TODO
+        unsafe { *buf = stat_path(&path)?.into() };
         Ok(0)
     })
 }
@@ -371,38 +404,189 @@ pub unsafe fn sys_newfstatat(
     flag: c_int,
 ) -> c_int {
     syscall_body!(sys_newfstatat, {
-        let path = parse_path_at(fd, path)?;
         debug!(
             "sys_newfstatat <= fd: {}, path: {:?}, flag: {:x}",
-            fd, path, flag
+            fd,
+            char_ptr_to_path_str(path),
+            flag
         );
         if kst.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        let node = fops::lookup(&path)?;
-        let st = if node.get_attr()?.is_dir() {
-            Directory::new(fops::open_dir(&path, node, &OpenOptions::new())?, false).stat()?
-        } else if node.get_attr()?.is_file() || node.get_attr()?.is_fifo() {
-            File::new(fops::open_file(&path, node, &OpenOptions::new())?).stat()?
-        } else {
-            return Err(LinuxError::EAFNOSUPPORT);
+        let st = stat_at(fd, path, flag)?;
+        unsafe { write_kstat(&st, kst) };
+        Ok(0)
+    })
+}
+
+/// Get the extended metadata of the file at `path` under the directory
+/// pointed by `dirfd`, and write into `statxbuf`.
+///
+/// All the basic fields are filled, whatever `mask` asks for.
+pub unsafe fn sys_statx(
+    dirfd: c_int,
+    path: *const c_char,
+    flags: c_int,
+    mask: c_uint,
+    statxbuf: *mut ctypes::statx,
+) -> c_int {
+    syscall_body!(sys_statx, {
+        debug!(
+            "sys_statx <= dirfd: {}, path: {:?}, flags: {:#x}, mask: {:#x}",
+            dirfd,
+            char_ptr_to_path_str(path),
+            flags,
+            mask
+        );
+        if statxbuf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let sync_type = flags as u32 & ctypes::AT_STATX_SYNC_TYPE;
+        if sync_type == ctypes::AT_STATX_SYNC_TYPE {
+            return Err(LinuxError::EINVAL);
+        }
+        let st = stat_at(dirfd, path, flags & !(sync_type as c_int))?;
+        let timestamp = |ts: &RuxTimeSpec| ctypes::statx_timestamp {
+            tv_sec: ts.tv_sec as _,
+            tv_nsec: ts.tv_nsec as _,
+            __pad: 0,
+        };
+        let stx = ctypes::statx {
+            stx_mask: ctypes::STATX_BASIC_STATS,
+            stx_blksize: st.st_blksize as _,
+            stx_nlink: st.st_nlink as _,
+            stx_uid: st.st_uid,
+            stx_gid: st.st_gid,
+            stx_mode: st.st_mode as _,
+            stx_ino: st.st_ino,
+            stx_size: st.st_size as _,
+            stx_blocks: st.st_blocks as _,
+            stx_atime: timestamp(&st.st_atime),
+            stx_ctime: timestamp(&st.st_ctime),
+            stx_mtime: timestamp(&st.st_mtime),
+            stx_rdev_major: dev_major(st.st_rdev),
+            stx_rdev_minor: dev_minor(st.st_rdev),
+            stx_dev_major: dev_major(st.st_dev),
+            stx_dev_minor: dev_minor(st.st_dev),
+            ..Default::default()
         };
+        unsafe { statxbuf.write(stx) };
+        Ok(0)
+    })
+}
 
-        unsafe {
-            (*kst).st_dev = st.st_dev;
-            (*kst).st_ino = st.st_ino;
-            (*kst).st_mode = st.st_mode;
-            (*kst).st_nlink = st.st_nlink;
-            (*kst).st_uid = st.st_uid;
-            (*kst).st_gid = st.st_gid;
-            (*kst).st_size = st.st_size;
-            (*kst).st_blocks = st.st_blocks;
-            (*kst).st_blksize = st.st_blksize;
+/// Get the usage of the filesystem that holds `path` and write into `buf`.
+///
+/// Return 0 if success.
+pub unsafe fn sys_statfs(path: *const c_char, buf: *mut ctypes::statfs) -> c_int {
+    syscall_body!(sys_statfs, {
+        let path = parse_path(path)?;
+        debug!("sys_statfs <= {:?} {:#x}", path, buf as usize);
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        fops::lookup(&path)?;
+        unsafe { buf.write(statfs_to_ctype(&fops::statfs(&path)?)) };
+        Ok(0)
+    })
+}
+
+/// Get the usage of the filesystem that holds the file `fd` and write into
+/// `buf`.
+///
+/// Return 0 if success.
+pub unsafe fn sys_fstatfs(fd: c_int, buf: *mut ctypes::statfs) -> c_int {
+    syscall_body!(sys_fstatfs, {
+        debug!("sys_fstatfs <= {} {:#x}", fd, buf as usize);
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
         }
+        let path = get_file_like(fd)?.path().to_owned();
+        unsafe { buf.write(statfs_to_ctype(&fops::statfs(&path)?)) };
         Ok(0)
     })
 }
 
+fn statfs_to_ctype(info: &fops::FileSystemInfo) -> ctypes::statfs {
+    ctypes::statfs {
+        f_type: info.fs_type as _,
+        f_bsize: info.block_size as _,
+        f_blocks: info.blocks as _,
+        f_bfree: info.blocks_free as _,
+        f_bavail: info.blocks_avail as _,
+        f_files: info.files as _,
+        f_ffree: info.files_free as _,
+        f_namelen: info.name_len as _,
+        f_frsize: info.block_size as _,
+        ..Default::default()
+    }
+}
+
+/// Get the metadata of the file at `path` under the directory pointed by
+/// `dirfd`, honoring the `AT_SYMLINK_NOFOLLOW` and `AT_EMPTY_PATH` bits of
+/// `flags`.
+fn stat_at(dirfd: c_int, path: *const c_char, flags: c_int) -> LinuxResult<RuxStat> {
+    let valid = ctypes::AT_SYMLINK_NOFOLLOW | ctypes::AT_EMPTY_PATH | ctypes::AT_NO_AUTOMOUNT;
+    if flags as u32 & !valid != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    if flags as u32 & ctypes::AT_EMPTY_PATH != 0 && char_ptr_to_path_str(path)?.is_empty() {
+        // the file pointed by `dirfd` may be of any type, with no path
+        return if dirfd == ctypes::AT_FDCWD {
+            stat_path(&fops::current_dir()?)
+        } else {
+            get_file_like(dirfd)?.stat()
+        };
+    }
+    stat_path(&parse_path_at_flags(dirfd, path, flags)?)
+}
+
+/// Get the metadata of the file at `path`, which is a symbolic link itself if
+/// `path` names one.
+fn stat_path(path: &AbsPath) -> LinuxResult<RuxStat> {
+    let node = fops::lookup(path)?;
+    let attr = node.get_attr()?;
+    if attr.is_dir() {
+        Directory::new(fops::open_dir(path, node, &OpenOptions::new())?, false).stat()
+    } else if attr.is_file() {
+        File::new(fops::open_file(path, node, &OpenOptions::new())?).stat()
+    } else {
+        Ok(attr.into())
+    }
+}
+
+/// Write the metadata `st` into the `kstat` buffer of the syscalls.
+unsafe fn write_kstat(st: &RuxStat, kst: *mut ctypes::kstat) {
+    unsafe {
+        (*kst).st_dev = st.st_dev;
+        (*kst).st_ino = st.st_ino;
+        (*kst).st_mode = st.st_mode;
+        (*kst).st_nlink = st.st_nlink;
+        (*kst).st_uid = st.st_uid;
+        (*kst).st_gid = st.st_gid;
+        (*kst).st_size = st.st_size;
+        (*kst).st_blocks = st.st_blocks;
+        (*kst).st_blksize = st.st_blksize;
+        (*kst).st_atime_sec = st.st_atime.tv_sec;
+        (*kst).st_atime_nsec = st.st_atime.tv_nsec;
+        (*kst).st_mtime_sec = st.st_mtime.tv_sec;
+        (*kst).st_mtime_nsec = st.st_mtime.tv_nsec;
+        (*kst).st_ctime_sec = st.st_ctime.tv_sec;
+        (*kst).st_ctime_nsec = st.st_ctime.tv_nsec;
+        (*kst).st_rdev = st.st_rdev;
+    }
+}
+
+/// Major number of the device number `dev`.
+fn dev_major(dev: u64) -> u32 {
+    (((dev >> 32) & 0xffff_f000) | ((dev >> 8) & 0xfff)) as u32
+}
+
+/// Minor number of the device number `dev`.
+fn dev_minor(dev: u64) -> u32 {
+    (((dev >> 12) & 0xffff_ff00) | (dev & 0xff)) as u32
+}
+
 /// Get the path of the current directory.
 pub fn sys_getcwd(buf: *mut c_char, size: usize) -> c_int {
     debug!("sys_getcwd <= {:#x} {}", buf as usize, size);
@@ -429,22 +613,10 @@ pub fn sys_getcwd(buf: *mut c_char, size: usize) -> c_int {
 /// Return 0 if the operation succeeds, otherwise return -1.
 pub fn sys_rename(old: *const c_char, new: *const c_char) -> c_int {
     syscall_body!(sys_rename, {
-        let old = parse_path(old)?;
-        let new = parse_path(new)?;
+        let old = resolve_at(ctypes::AT_FDCWD, old, false, false)?;
+        let new = resolve_at(ctypes::AT_FDCWD, new, false, false)?;
         debug!("sys_rename <= old: {:?}, new: {:?}", old, new);
-        if old == new {
-            return Ok(0);
-        }
-        match fops::lookup(&old) {
-            Ok(_) => {}
-            Err(e) => return Err(e.into()),
-        }
-        match fops::lookup(&new) {
-            Ok(_) => return Err(LinuxError::EEXIST),
-            Err(Error::NotFound) => {}
-            Err(e) => return Err(e.into()),
-        }
-        fops::rename(&old, &new)?;
+        rename_at(&old, &new, 0)?;
         Ok(0)
     })
 }
@@ -452,41 +624,102 @@ pub fn sys_rename(old: *const c_char, new: *const c_char) -> c_int {
 /// Rename at certain directory pointed by `oldfd`
 pub fn sys_renameat(oldfd: c_int, old: *const c_char, newfd: c_int, new: *const c_char) -> c_int {
     syscall_body!(sys_renameat, {
-        let old_path = parse_path_at(oldfd, old)?;
-        let new_path = parse_path_at(newfd, new)?;
+        let old_path = resolve_at(oldfd, old, false, false)?;
+        let new_path = resolve_at(newfd, new, false, false)?;
         debug!(
             "sys_renameat <= oldfd: {}, old: {:?}, newfd: {}, new: {:?}",
             oldfd, old_path, newfd, new_path
         );
-        fops::rename(&old_path, &new_path)?;
+        rename_at(&old_path, &new_path, 0)?;
         Ok(0)
     })
 }
 
+/// Rename at certain directories like [`sys_renameat`], with `flags` of
+/// `RENAME_NOREPLACE` or `RENAME_EXCHANGE`.
+pub fn sys_renameat2(
+    oldfd: c_int,
+    old: *const c_char,
+    newfd: c_int,
+    new: *const c_char,
+    flags: c_uint,
+) -> c_int {
+    syscall_body!(sys_renameat2, {
+        let old_path = resolve_at(oldfd, old, false, false)?;
+        let new_path = resolve_at(newfd, new, false, false)?;
+        debug!(
+            "sys_renameat2 <= oldfd: {}, old: {:?}, newfd: {}, new: {:?}, flags: {:#x}",
+            oldfd, old_path, newfd, new_path, flags
+        );
+        rename_at(&old_path, &new_path, flags)?;
+        Ok(0)
+    })
+}
+
+/// Rename `old` to `new` with `flags` of `renameat2`.
+///
+/// An existing `new` is replaced unless `RENAME_NOREPLACE` is set, and it is
+/// swapped with `old` if `RENAME_EXCHANGE` is set.
+fn rename_at(old: &AbsPath, new: &AbsPath, flags: c_uint) -> LinuxResult {
+    let noreplace = flags & ctypes::RENAME_NOREPLACE != 0;
+    let exchange = flags & ctypes::RENAME_EXCHANGE != 0;
+    if flags & !(ctypes::RENAME_NOREPLACE | ctypes::RENAME_EXCHANGE) != 0 || noreplace && exchange {
+        return Err(LinuxError::EINVAL);
+    }
+    let old_node = fops::lookup(old)?;
+    let new_node = match fops::lookup(new) {
+        Ok(node) => Some(node),
+        Err(Error::NotFound) if !exchange => None,
+        Err(e) => return Err(e.into()),
+    };
+    if !fops::same_fs(old, new) {
+        return Err(LinuxError::EXDEV);
+    }
+    if fops::is_mount_point(old) || fops::is_mount_point(new) {
+        return Err(LinuxError::EBUSY);
+    }
+    let is_within = |path: &AbsPath, dir: &AbsPath| {
+        path.strip_prefix(dir.as_str())
+            .is_some_and(|rest| rest.starts_with('/'))
+    };
+    // a directory can't be moved into itself
+    if is_within(new, old) || exchange && is_within(old, new) {
+        return Err(LinuxError::EINVAL);
+    }
+    let Some(new_node) = new_node else {
+        fops::rename(old, new)?;
+        return Ok(());
+    };
+    if noreplace {
+        return Err(LinuxError::EEXIST);
+    }
+    // nothing to do if both names link to the same file
+    if Arc::as_ptr(&old_node) as *const () == Arc::as_ptr(&new_node) as *const () {
+        return Ok(());
+    }
+    if exchange {
+        // filesystems that can't exchange atomically don't support the flag
+        return fops::exchange(old, new).map_err(|e| match e {
+            Error::Unsupported => LinuxError::EINVAL,
+            e => e.into(),
+        });
+    }
+    match (old_node.get_attr()?.is_dir(), new_node.get_attr()?.is_dir()) {
+        (true, false) => return Err(LinuxError::ENOTDIR),
+        (false, true) => return Err(LinuxError::EISDIR),
+        (true, true) if !new_node.is_empty()? => return Err(LinuxError::ENOTEMPTY),
+        _ => {}
+    }
+    fops::rename(old, new)?;
+    Ok(())
+}
+
 /// Remove a directory, which must be empty
 pub fn sys_rmdir(pathname: *const c_char) -> c_int {
     syscall_body!(sys_rmdir, {
-        let path = parse_path(pathname)?;
+        let path = resolve_at(ctypes::AT_FDCWD, pathname, false, false)?;
         debug!("sys_rmdir <= path: {:?}", path);
-        match fops::lookup(&path) {
-            Ok(node) => {
-                let attr = node.get_attr()?;
-                if !attr.is_dir() {
-                    return Err(LinuxError::ENOTDIR);
-                }
-                if fops::is_mount_point(&path) {
-                    return Err(LinuxError::EPERM);
-                }
-                if !attr.perm().owner_writable() {
-                    return Err(LinuxError::EPERM);
-                }
-                if !node.is_empty()? {
-                    return Err(LinuxError::ENOTEMPTY);
-                }
-                fops::remove_dir(&path)?;
-            }
-            Err(e) => return Err(e.into()),
-        }
+        unlink_at(&path, true)?;
         Ok(0)
     })
 }
@@ -494,21 +727,9 @@ pub fn sys_rmdir(pathname: *const c_char) -> c_int {
 /// Removes a file from the filesystem.
 pub fn sys_unlink(pathname: *const c_char) -> c_int {
     syscall_body!(sys_unlink, {
-        let path = parse_path(pathname)?;
+        let path = resolve_at(ctypes::AT_FDCWD, pathname, false, false)?;
         debug!("sys_unlink <= path: {:?}", path);
-        match fops::lookup(&path) {
-            Ok(node) => {
-                let attr = node.get_attr()?;
-                if attr.is_dir() {
-                    return Err(LinuxError::EISDIR);
-                }
-                if !attr.perm().owner_writable() {
-                    return Err(LinuxError::EPERM);
-                }
-                fops::remove_file(&path)?;
-            }
-            Err(e) => return Err(e.into()),
-        }
+        unlink_at(&path, false)?;
         Ok(0)
     })
 }
@@ -516,45 +737,51 @@ pub fn sys_unlink(pathname: *const c_char) -> c_int {
 /// deletes a name from the filesystem
 pub fn sys_unlinkat(fd: c_int, pathname: *const c_char, flags: c_int) -> c_int {
     syscall_body!(sys_unlinkat, {
-        let path = parse_path_at(fd, pathname)?;
+        if flags as u32 & !ctypes::AT_REMOVEDIR != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = resolve_at(fd, pathname, false, false)?;
         let rmdir = flags as u32 & ctypes::AT_REMOVEDIR != 0;
         debug!(
             "sys_unlinkat <= fd: {}, pathname: {:?}, flags: {}",
             fd, path, flags
         );
-        match fops::lookup(&path) {
-            Ok(node) => {
-                let attr = node.get_attr()?;
-                if rmdir {
-                    if !attr.is_dir() {
-                        return Err(LinuxError::ENOTDIR);
-                    }
-                    if fops::is_mount_point(&path) {
-                        return Err(LinuxError::EPERM);
-                    }
-                    if !attr.perm().owner_writable() {
-                        return Err(LinuxError::EPERM);
-                    }
-                    if !node.is_empty()? {
-                        return Err(LinuxError::ENOTEMPTY);
-                    }
-                    fops::remove_dir(&path)?;
-                } else {
-                    if attr.is_dir() {
-                        return Err(LinuxError::EISDIR);
-                    }
-                    if !attr.perm().owner_writable() {
-                        return Err(LinuxError::EPERM);
-                    }
-                    fops::remove_file(&path)?;
-                }
-            }
-            Err(e) => return Err(e.into()),
-        }
+        unlink_at(&path, rmdir)?;
         Ok(0)
     })
 }
 
+/// Remove the name `path`, which must be an empty directory if `rmdir` is
+/// set, and must not be a directory otherwise.
+fn unlink_at(path: &AbsPath, rmdir: bool) -> LinuxResult {
+    let node = fops::lookup(path)?;
+    let attr = node.get_attr()?;
+    if rmdir {
+        if !attr.is_dir() {
+            return Err(LinuxError::ENOTDIR);
+        }
+        if fops::is_mount_point(path) {
+            return Err(LinuxError::EPERM);
+        }
+        if !attr.perm().owner_writable() {
+            return Err(LinuxError::EPERM);
+        }
+        if !node.is_empty()? {
+            return Err(LinuxError::ENOTEMPTY);
+        }
+        fops::remove_dir(path)?;
+    } else {
+        if attr.is_dir() {
+            return Err(LinuxError::EISDIR);
+        }
+        if !attr.perm().owner_writable() {
+            return Err(LinuxError::EPERM);
+        }
+        fops::remove_file(path)?;
+    }
+    Ok(())
+}
+
 pub fn sys_mknodat(
     fd: c_int,
     pathname: *const c_char,
@@ -563,7 +790,7 @@ pub fn sys_mknodat(
 ) -> c_int {
     // TODO: implement permissions mode
     syscall_body!(sys_mknodat, {
-        let path = parse_path_at(fd, pathname)?;
+        let path = resolve_at(fd, pathname, false, false)?;
         debug!(
             "sys_mknodat <= fd: {}, pathname: {:?}, mode: {:x?}, dev: {:x?}",
             fd, path, mode, _dev
@@ -584,7 +811,7 @@ pub fn sys_mknodat(
 pub fn sys_mkdir(pathname: *const c_char, mode: ctypes::mode_t) -> c_int {
     // TODO: implement mode
     syscall_body!(sys_mkdir, {
-        let path = parse_path(pathname)?;
+        let path = resolve_at(ctypes::AT_FDCWD, pathname, false, false)?;
         debug!("sys_mkdir <= path: {:?}, mode: {:?}", path, mode);
         let node = fops::lookup(&path);
         match node {
@@ -599,7 +826,7 @@ pub fn sys_mkdir(pathname: *const c_char, mode: ctypes::mode_t) -> c_int {
 /// attempts to create a directory named pathname under directory pointed by `fd`
 pub fn sys_mkdirat(fd: c_int, pathname: *const c_char, mode: ctypes::mode_t) -> c_int {
     syscall_body!(sys_mkdirat, {
-        let path = parse_path_at(fd, pathname)?;
+        let path = resolve_at(fd, pathname, false, false)?;
         debug!(
             "sys_mkdirat <= fd: {}, pathname: {:?}, mode: {:x?}",
             fd, path, mode
@@ -622,17 +849,243 @@ pub fn sys_fchownat(
     flag: c_int,
 ) -> c_int {
     syscall_body!(sys_fchownat, {
-        let path = parse_path_at(fd, path)?;
+        if flag as u32 & !(ctypes::AT_SYMLINK_NOFOLLOW | ctypes::AT_EMPTY_PATH) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = parse_path_at_flags(fd, path, flag)?;
         debug!(
             "sys_fchownat <= fd: {}, path: {:?}, uid: {}, gid: {}, flag: {}",
             fd, path, uid, gid, flag
         );
+        fops::lookup(&path)?;
+        ruxfs::notify::notify(&path, ruxfs::notify::FsEvent::ATTRIB);
+        Ok(0)
+    })
+}
+
+/// Changes the permission mode of the file at `pathname` under the directory
+/// pointed by `dirfd`.
+///
+/// The mode of a symbolic link itself can't be changed.
+pub fn sys_fchmodat(
+    dirfd: c_int,
+    pathname: *const c_char,
+    mode: ctypes::mode_t,
+    flags: c_int,
+) -> c_int {
+    syscall_body!(sys_fchmodat, {
+        if flags as u32 & !(ctypes::AT_SYMLINK_NOFOLLOW | ctypes::AT_EMPTY_PATH) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = parse_path_at_flags(dirfd, pathname, flags)?;
+        debug!(
+            "sys_fchmodat <= dirfd: {}, path: {:?}, mode: {:#o}, flags: {:#x}",
+            dirfd, path, mode, flags
+        );
+        chmod_path(&path, mode)?;
+        Ok(0)
+    })
+}
+
+/// Changes the permission mode of the file at `pathname`.
+pub fn sys_chmod(pathname: *const c_char, mode: ctypes::mode_t) -> c_int {
+    syscall_body!(sys_chmod, {
+        let path = parse_path(pathname)?;
+        debug!("sys_chmod <= path: {:?}, mode: {:#o}", path, mode);
+        chmod_path(&path, mode)?;
+        Ok(0)
+    })
+}
+
+/// Changes the permission mode of the file pointed by `fd`.
+pub fn sys_fchmod(fd: c_int, mode: ctypes::mode_t) -> c_int {
+    syscall_body!(sys_fchmod, {
+        debug!("sys_fchmod <= fd: {}, mode: {:#o}", fd, mode);
+        let path = get_file_like(fd)?.path().to_owned();
+        chmod_path(&path, mode)?;
+        Ok(0)
+    })
+}
+
+fn chmod_path(path: &AbsPath, mode: ctypes::mode_t) -> LinuxResult {
+    if fops::lookup(path)?.get_attr()?.is_symlink() {
+        return Err(LinuxError::EOPNOTSUPP);
+    }
+    fops::set_perm(path, FilePerm::from_bits_truncate(mode as u16)).map_err(|e| match e {
+        Error::Unsupported => LinuxError::EOPNOTSUPP,
+        e => e.into(),
+    })
+}
+
+/// Changes the access and modification times of the file at `path` under the
+/// directory pointed by `dirfd`, or of the file pointed by `dirfd` if `path`
+/// is null.
+///
+/// The times are checked but not kept, as the filesystems have no file times,
+/// only an `IN_ATTRIB` event is notified.
+pub unsafe fn sys_utimensat(
+    dirfd: c_int,
+    path: *const c_char,
+    times: *const ctypes::timespec,
+    flags: c_int,
+) -> c_int {
+    syscall_body!(sys_utimensat, {
+        if flags as u32 & !(ctypes::AT_SYMLINK_NOFOLLOW | ctypes::AT_EMPTY_PATH) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if !times.is_null() {
+            for ts in unsafe { core::slice::from_raw_parts(times, 2) } {
+                let special = ts.tv_nsec == ctypes::UTIME_NOW as c_long
+                    || ts.tv_nsec == ctypes::UTIME_OMIT as c_long;
+                if !special && !(0..1_000_000_000).contains(&ts.tv_nsec) {
+                    return Err(LinuxError::EINVAL);
+                }
+            }
+        }
+        let path = if path.is_null() {
+            if dirfd == ctypes::AT_FDCWD {
+                return Err(LinuxError::EFAULT);
+            }
+            get_file_like(dirfd)?.path().to_owned()
+        } else {
+            parse_path_at_flags(dirfd, path, flags)?
+        };
+        debug!(
+            "sys_utimensat <= dirfd: {}, path: {:?}, flags: {:#x}",
+            dirfd, path, flags
+        );
+        fops::lookup(&path)?;
+        ruxfs::notify::notify(&path, ruxfs::notify::FsEvent::ATTRIB);
         Ok(0)
     })
 }
 
+/// Creates a hard link `new` under the directory pointed by `newdirfd` to the
+/// file `old` under the directory pointed by `olddirfd`.
+pub fn sys_linkat(
+    olddirfd: c_int,
+    old: *const c_char,
+    newdirfd: c_int,
+    new: *const c_char,
+    flags: c_int,
+) -> c_int {
+    syscall_body!(sys_linkat, {
+        let flags = flags as u32;
+        if flags & !(ctypes::AT_SYMLINK_FOLLOW | ctypes::AT_EMPTY_PATH) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let follow = flags & ctypes::AT_SYMLINK_FOLLOW != 0;
+        let empty_path = flags & ctypes::AT_EMPTY_PATH != 0;
+        let new_path = resolve_at(newdirfd, new, false, false)?;
+        if empty_path && olddirfd != ctypes::AT_FDCWD && char_ptr_to_path_str(old)?.is_empty() {
+            debug!(
+                "sys_linkat <= oldfd: {}, new: {:?}, flags: {:#x}",
+                olddirfd, new_path, flags
+            );
+            link_fd(olddirfd, &new_path)?;
+            return Ok(0);
+        }
+        let old_path = resolve_at(olddirfd, old, follow, empty_path)?;
+        debug!(
+            "sys_linkat <= old: {:?}, new: {:?}, flags: {:#x}",
+            old_path, new_path, flags
+        );
+        link_path(&old_path, &new_path)?;
+        Ok(0)
+    })
+}
+
+/// Creates a hard link `new` to the file `old`.
+pub fn sys_link(old: *const c_char, new: *const c_char) -> c_int {
+    syscall_body!(sys_link, {
+        let old_path = resolve_at(ctypes::AT_FDCWD, old, false, false)?;
+        let new_path = resolve_at(ctypes::AT_FDCWD, new, false, false)?;
+        debug!("sys_link <= old: {:?}, new: {:?}", old_path, new_path);
+        link_path(&old_path, &new_path)?;
+        Ok(0)
+    })
+}
+
+fn link_path(old: &AbsPath, new: &AbsPath) -> LinuxResult {
+    if fops::lookup(old)?.get_attr()?.is_dir() {
+        return Err(LinuxError::EPERM);
+    }
+    match fops::lookup(new) {
+        Ok(_) => return Err(LinuxError::EEXIST),
+        Err(Error::NotFound) => {}
+        Err(e) => return Err(e.into()),
+    }
+    if !fops::same_fs(old, new) {
+        return Err(LinuxError::EXDEV);
+    }
+    fops::create_link(old, new).map_err(|e| match e {
+        Error::Unsupported => LinuxError::EPERM,
+        e => e.into(),
+    })
+}
+
+/// Creates a hard link `new` to the file opened as `fd` by its node, as the
+/// file may have no name left, e.g. after `O_TMPFILE`.
+fn link_fd(fd: c_int, new: &AbsPath) -> LinuxResult {
+    let file = match File::from_fd(fd) {
+        Ok(file) => file,
+        Err(_) if Directory::from_fd(fd).is_ok() => return Err(LinuxError::EPERM),
+        Err(e) => return Err(e),
+    };
+    match fops::lookup(new) {
+        Ok(_) => return Err(LinuxError::EEXIST),
+        Err(Error::NotFound) => {}
+        Err(e) => return Err(e.into()),
+    }
+    let inner = file.inner.read();
+    if !fops::same_fs(&inner.path(), new) {
+        return Err(LinuxError::EXDEV);
+    }
+    inner.link_to(new).map_err(|e| match e {
+        Error::Unsupported => LinuxError::EPERM,
+        e => e.into(),
+    })
+}
+
+/// Creates a symbolic link `linkpath` under the directory pointed by
+/// `newdirfd`, which contains `target`.
+pub fn sys_symlinkat(target: *const c_char, newdirfd: c_int, linkpath: *const c_char) -> c_int {
+    syscall_body!(sys_symlinkat, {
+        let target = char_ptr_to_path_str(target)?;
+        let path = resolve_at(newdirfd, linkpath, false, false)?;
+        debug!("sys_symlinkat <= target: {:?}, path: {:?}", target, path);
+        symlink_path(target, &path)?;
+        Ok(0)
+    })
+}
+
+/// Creates a symbolic link `linkpath` which contains `target`.
+pub fn sys_symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    syscall_body!(sys_symlink, {
+        let target = char_ptr_to_path_str(target)?;
+        let path = resolve_at(ctypes::AT_FDCWD, linkpath, false, false)?;
+        debug!("sys_symlink <= target: {:?}, path: {:?}", target, path);
+        symlink_path(target, &path)?;
+        Ok(0)
+    })
+}
+
+fn symlink_path(target: &str, path: &AbsPath) -> LinuxResult {
+    if target.is_empty() {
+        return Err(LinuxError::ENOENT);
+    }
+    match fops::lookup(path) {
+        Ok(_) => return Err(LinuxError::EEXIST),
+        Err(Error::NotFound) => {}
+        Err(e) => return Err(e.into()),
+    }
+    fops::create_symlink(path, target).map_err(|e| match e {
+        Error::Unsupported => LinuxError::EPERM,
+        e => e.into(),
+    })
+}
+
 /// read value of a symbolic link relative to directory file descriptor
-/// TODO: currently only support symlink, so return EINVAL anyway
 pub fn sys_readlinkat(
     fd: c_int,
     pathname: *const c_char,
@@ -640,12 +1093,27 @@ pub fn sys_readlinkat(
     bufsize: usize,
 ) -> usize {
     syscall_body!(sys_readlinkat, {
-        let path = parse_path_at(fd, pathname)?;
+        let path = resolve_at(fd, pathname, false, true)?;
         debug!(
             "sys_readlinkat <= path = {:?}, fd = {:}, buf = {:p}, bufsize = {:}",
             path, fd, buf, bufsize
         );
-        Err::<usize, LinuxError>(LinuxError::EINVAL)
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        if bufsize == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let node = fops::lookup(&path)?;
+        if !node.get_attr()?.is_symlink() {
+            return Err(LinuxError::EINVAL);
+        }
+        let target = fops::read_link(&node)?;
+        // the target is truncated to `bufsize`, with no terminating NUL
+        let len = target.len().min(bufsize);
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, len) };
+        dst.copy_from_slice(&target.as_bytes()[..len]);
+        Ok(len)
     })
 }
 
@@ -666,6 +1134,9 @@ pub unsafe fn sys_getdents64(fd: c_int, dirp: *mut LinuxDirent64, count: ctypes:
         let buf = unsafe { core::slice::from_raw_parts_mut(dirp as *mut u8, count) };
         // EBADFD handles here
         let dir = Directory::from_fd(fd)?;
+        if dir.inner.read().is_path_only() {
+            return Err(LinuxError::EBADF);
+        }
         // bytes written in buf
         let mut written = 0;
 
@@ -745,16 +1216,31 @@ pub unsafe fn sys_preadv(
 }
 
 /// checks accessibility to the file `pathname`.
-/// If pathname is a symbolic link, it is dereferenced.
+/// If pathname is a symbolic link, it is dereferenced, unless `AT_SYMLINK_NOFOLLOW` is set.
 /// The mode is either the value F_OK, for the existence of the file,
 /// or a mask consisting of the bitwise OR of one or more of R_OK, W_OK, and X_OK, for the read, write, execute permissions.
 pub fn sys_faccessat(dirfd: c_int, pathname: *const c_char, mode: c_int, flags: c_int) -> c_int {
     syscall_body!(sys_faccessat, {
-        let path = parse_path_at(dirfd, pathname)?;
+        let valid = ctypes::AT_SYMLINK_NOFOLLOW | ctypes::AT_EMPTY_PATH | ctypes::AT_EACCESS;
+        if flags as u32 & !valid != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let mode = mode as u32;
+        if mode & !(ctypes::R_OK | ctypes::W_OK | ctypes::X_OK) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = parse_path_at_flags(dirfd, pathname, flags)?;
         debug!(
             "sys_faccessat <= dirfd {} path {} mode {} flags {}",
             dirfd, path, mode, flags
         );
+        let perm = fops::lookup(&path)?.get_attr()?.perm();
+        if mode & ctypes::R_OK != 0 && !perm.owner_readable()
+            || mode & ctypes::W_OK != 0 && !perm.owner_writable()
+            || mode & ctypes::X_OK != 0 && !perm.owner_executable()
+        {
+            return Err(LinuxError::EACCES);
+        }
         Ok(0)
     })
 }
@@ -780,38 +1266,51 @@ fn char_ptr_to_path_str<'a>(ptr: *const c_char) -> LinuxResult<&'a str> {
     }
 }
 
+/// Resolve `path` under the directory pointed by `dirfd` with
+/// [`ruxtask::fs::resolve_path_at`].
+fn resolve_at(
+    dirfd: c_int,
+    path: *const c_char,
+    follow_last: bool,
+    empty_path: bool,
+) -> LinuxResult<AbsPath<'static>> {
+    let path = char_ptr_to_path_str(path)?;
+    let dirfd = (dirfd != ctypes::AT_FDCWD).then_some(dirfd);
+    ruxtask::fs::resolve_path_at(dirfd, path, follow_last, empty_path)
+}
+
 /// Parse `path` argument for fs syscalls.
 ///
-/// * If the given `path` is absolute, return it as is.
-/// * If the given `path` is relative, join it against the current working directory.
+/// * If the given `path` is absolute, resolve it from the root directory.
+/// * If the given `path` is relative, resolve it from the current working directory.
+///
+/// Symbolic links are followed, including at the last component.
 pub fn parse_path(path: *const c_char) -> LinuxResult<AbsPath<'static>> {
-    let path = char_ptr_to_path_str(path)?;
-    if path.starts_with('/') {
-        Ok(AbsPath::new_canonicalized(path))
-    } else {
-        Ok(fops::current_dir()?.join(&RelPath::new_canonicalized(path)))
-    }
+    resolve_at(ctypes::AT_FDCWD, path, true, false)
 }
 
 /// Parse `path` and `dirfd` arguments for fs syscalls.
 ///
-/// * If the given `path` is absolute, return it as is.
-/// * If the given `path` is relative and `dirfd` is `AT_FDCWD`, join it against the
+/// * If the given `path` is absolute, resolve it from the root directory.
+/// * If the given `path` is relative and `dirfd` is `AT_FDCWD`, resolve it from the
 ///   current working directory.
-/// * If the given `path` is relative and `dirfd` is not `AT_FDCWD`, join it against the
-///   directory of the file descriptor.
+/// * If the given `path` is relative and `dirfd` is not `AT_FDCWD`, resolve it from the
+///   directory of the file descriptor, or fail with `ENOTDIR` if it is not a directory.
+///
+/// Symbolic links are followed, including at the last component.
 pub fn parse_path_at(dirfd: c_int, path: *const c_char) -> LinuxResult<AbsPath<'static>> {
-    let path = char_ptr_to_path_str(path)?;
-    if path.starts_with('/') {
-        Ok(AbsPath::new_canonicalized(path))
-    } else if dirfd == ctypes::AT_FDCWD {
-        Ok(fops::current_dir()?.join(&RelPath::new_canonicalized(path)))
-    } else {
-        let dir = Directory::from_fd(dirfd)?;
-        if dir.searchable {
-            Ok(dir.path().join(&RelPath::new_canonicalized(path)))
-        } else {
-            Err(LinuxError::EACCES)
-        }
-    }
+    resolve_at(dirfd, path, true, false)
+}
+
+/// Parse `path` and `dirfd` arguments like [`parse_path_at`], honoring the
+/// `AT_SYMLINK_NOFOLLOW` and `AT_EMPTY_PATH` bits of `flags`.
+pub fn parse_path_at_flags(
+    dirfd: c_int,
+    path: *const c_char,
+    flags: c_int,
+) -> LinuxResult<AbsPath<'static>> {
+    let flags = flags as u32;
+    let follow_last = flags & ctypes::AT_SYMLINK_NOFOLLOW == 0;
+    let empty_path = flags & ctypes::AT_EMPTY_PATH != 0;
+    resolve_at(dirfd, path, follow_last, empty_path)
 }
@@ -0,0 +1,423 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! `inotify` implementation.
+//!
+//! Watches are keyed by the watched file rather than its path, so they follow
+//! it across renames. Events come from [`ruxfs::notify`], so only changes
+//! made through RuxOS are reported.
+
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_int};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use ruxfdtable::{FileLike, PollObserver, PollObservers, RuxStat};
+use ruxfs::lock::FileKey;
+use ruxfs::notify::{self, FsEvent, FsEventListener};
+use ruxfs::{fops, AbsPath};
+use ruxtask::fs::{add_file_like, get_file_like};
+use spinlock::SpinNoIrq;
+
+use crate::{ctypes, imp::fs::flags_to_options, imp::fs::parse_path};
+
+/// Events are dropped, and `IN_Q_OVERFLOW` is queued, beyond this length.
+const MAX_QUEUED_EVENTS: usize = 16384;
+
+/// Size of `struct inotify_event` without the name.
+const EVENT_HEADER_SIZE: usize = core::mem::size_of::<ctypes::inotify_event>();
+
+/// Flags of `inotify_add_watch` that change how the watch is added.
+const IN_WATCH_FLAGS: u32 = ctypes::IN_ONLYDIR
+    | ctypes::IN_DONT_FOLLOW
+    | ctypes::IN_EXCL_UNLINK
+    | ctypes::IN_MASK_CREATE
+    | ctypes::IN_MASK_ADD
+    | ctypes::IN_ONESHOT;
+
+struct Watch {
+    wd: c_int,
+    key: FileKey,
+    mask: u32,
+}
+
+#[derive(PartialEq, Eq)]
+struct Event {
+    wd: c_int,
+    mask: u32,
+    cookie: u32,
+    name: String,
+}
+
+impl Event {
+    /// Length of the name field, padded with NULs to the size of the header.
+    fn name_len(&self) -> usize {
+        if self.name.is_empty() {
+            0
+        } else {
+            (self.name.len() + 1).next_multiple_of(EVENT_HEADER_SIZE)
+        }
+    }
+
+    fn size(&self) -> usize {
+        EVENT_HEADER_SIZE + self.name_len()
+    }
+
+    fn write_to(&self, buf: &mut [u8]) {
+        let name_len = self.name_len();
+        buf[0..4].copy_from_slice(&self.wd.to_ne_bytes());
+        buf[4..8].copy_from_slice(&self.mask.to_ne_bytes());
+        buf[8..12].copy_from_slice(&self.cookie.to_ne_bytes());
+        buf[12..16].copy_from_slice(&(name_len as u32).to_ne_bytes());
+        let name = &mut buf[EVENT_HEADER_SIZE..EVENT_HEADER_SIZE + name_len];
+        name.fill(0);
+        name[..self.name.len()].copy_from_slice(self.name.as_bytes());
+    }
+}
+
+struct InotifyInner {
+    watches: Vec<Watch>,
+    next_wd: c_int,
+    events: VecDeque<Event>,
+}
+
+impl InotifyInner {
+    fn push(&mut self, event: Event) {
+        // identical consecutive events are merged
+        if self.events.back() == Some(&event) {
+            return;
+        }
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            let overflow = Event {
+                wd: -1,
+                mask: ctypes::IN_Q_OVERFLOW,
+                cookie: 0,
+                name: String::new(),
+            };
+            if self.events.back() != Some(&overflow) {
+                self.events.push_back(overflow);
+            }
+            return;
+        }
+        self.events.push_back(event);
+    }
+
+    /// Queue the event of a watch, if the watch asks for it.
+    ///
+    /// Returns whether the watch should be removed.
+    fn report(watch: &Watch, events: &mut Vec<Event>, mask: u32, cookie: u32, name: &str) -> bool {
+        let wanted = mask & watch.mask & ctypes::IN_ALL_EVENTS;
+        if wanted == 0 {
+            return false;
+        }
+        events.push(Event {
+            wd: watch.wd,
+            mask: wanted | (mask & ctypes::IN_ISDIR),
+            cookie,
+            name: name.to_string(),
+        });
+        watch.mask & ctypes::IN_ONESHOT != 0
+    }
+
+    fn on_event(
+        &mut self,
+        dir: Option<&FileKey>,
+        name: &str,
+        file: Option<&FileKey>,
+        event: FsEvent,
+        cookie: u32,
+    ) {
+        let mask = event.bits();
+        let mut events = Vec::new();
+        let mut removed = Vec::new();
+        for watch in &self.watches {
+            let mut remove = false;
+            if dir == Some(&watch.key) {
+                remove |= Self::report(watch, &mut events, mask, cookie, name);
+            }
+            if file == Some(&watch.key) {
+                // the watched object itself
+                if mask & ctypes::IN_DELETE != 0 {
+                    Self::report(watch, &mut events, ctypes::IN_DELETE_SELF, 0, "");
+                    remove = true;
+                } else if mask & ctypes::IN_MOVED_FROM != 0 {
+                    remove |= Self::report(watch, &mut events, ctypes::IN_MOVE_SELF, 0, "");
+                } else if mask & (ctypes::IN_MOVED_TO | ctypes::IN_CREATE) == 0 {
+                    remove |= Self::report(watch, &mut events, mask, 0, "");
+                }
+            }
+            if remove {
+                removed.push(watch.wd);
+            }
+        }
+        for event in events {
+            self.push(event);
+        }
+        for wd in removed {
+            self.remove_watch(wd);
+        }
+    }
+
+    /// Remove a watch and queue `IN_IGNORED` for it.
+    fn remove_watch(&mut self, wd: c_int) -> bool {
+        let Some(index) = self.watches.iter().position(|w| w.wd == wd) else {
+            return false;
+        };
+        self.watches.remove(index);
+        self.push(Event {
+            wd,
+            mask: ctypes::IN_IGNORED,
+            cookie: 0,
+            name: String::new(),
+        });
+        true
+    }
+}
+
+pub struct Inotify {
+    inner: SpinNoIrq<InotifyInner>,
+    nonblocking: AtomicBool,
+    #[cfg(feature = "multitask")]
+    wait_queue: ruxtask::WaitQueue,
+    observers: PollObservers,
+}
+
+impl Inotify {
+    fn new(nonblocking: bool) -> Self {
+        Self {
+            inner: SpinNoIrq::new(InotifyInner {
+                watches: Vec::new(),
+                next_wd: 1,
+                events: VecDeque::new(),
+            }),
+            nonblocking: AtomicBool::new(nonblocking),
+            #[cfg(feature = "multitask")]
+            wait_queue: ruxtask::WaitQueue::new(),
+            observers: PollObservers::new(),
+        }
+    }
+
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::EINVAL)
+    }
+
+    fn wake(&self) {
+        // events may be reported while the fd table is locked, do not reschedule
+        #[cfg(feature = "multitask")]
+        self.wait_queue.notify_all(false);
+        self.observers.notify();
+    }
+
+    fn add_watch(&self, key: FileKey, mask: u32) -> LinuxResult<c_int> {
+        let mut inner = self.inner.lock();
+        if let Some(watch) = inner.watches.iter_mut().find(|w| w.key == key) {
+            if mask & ctypes::IN_MASK_CREATE != 0 {
+                return Err(LinuxError::EEXIST);
+            }
+            if mask & ctypes::IN_MASK_ADD != 0 {
+                watch.mask |= mask;
+            } else {
+                watch.mask = mask;
+            }
+            return Ok(watch.wd);
+        }
+        let wd = inner.next_wd;
+        inner.next_wd += 1;
+        inner.watches.push(Watch { wd, key, mask });
+        Ok(wd)
+    }
+}
+
+impl FsEventListener for Inotify {
+    fn on_event(
+        &self,
+        dir: Option<&FileKey>,
+        name: &str,
+        file: Option<&FileKey>,
+        event: FsEvent,
+        cookie: u32,
+    ) {
+        let mut inner = self.inner.lock();
+        let queued = inner.events.len();
+        inner.on_event(dir, name, file, event, cookie);
+        let changed = inner.events.len() != queued;
+        drop(inner);
+        if changed {
+            self.wake();
+        }
+    }
+}
+
+impl FileLike for Inotify {
+    fn path(&self) -> AbsPath {
+        AbsPath::new("/inotify")
+    }
+
+    /// Reads as many whole events as fit in `buf`.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        loop {
+            let mut inner = self.inner.lock();
+            if let Some(first) = inner.events.front() {
+                if buf.len() < first.size() {
+                    return Err(LinuxError::EINVAL);
+                }
+                let mut read_len = 0;
+                while let Some(event) = inner.events.front() {
+                    let size = event.size();
+                    if buf.len() - read_len < size {
+                        break;
+                    }
+                    event.write_to(&mut buf[read_len..read_len + size]);
+                    read_len += size;
+                    inner.events.pop_front();
+                }
+                return Ok(read_len);
+            }
+            drop(inner);
+            if self.nonblocking.load(Ordering::Relaxed) {
+                return Err(LinuxError::EAGAIN);
+            }
+            #[cfg(feature = "multitask")]
+            self.wait_queue
+                .wait_until(|| !self.inner.lock().events.is_empty());
+            #[cfg(not(feature = "multitask"))]
+            crate::sys_sched_yield();
+        }
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn flush(&self) -> LinuxResult {
+        Ok(())
+    }
+
+    fn stat(&self) -> LinuxResult<RuxStat> {
+        let st_mode = 0o600u32; // rw-------
+        Ok(RuxStat::from(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            ..Default::default()
+        }))
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: !self.inner.lock().events.is_empty(),
+            writable: false,
+            pollhup: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        self.observers.add(observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        self.observers.remove(observer, key);
+    }
+}
+
+/// Create an inotify instance.
+///
+/// `flags` can be `IN_NONBLOCK` and `IN_CLOEXEC`.
+pub fn sys_inotify_init1(flags: c_int) -> c_int {
+    debug!("sys_inotify_init1 <= flags: {:#x}", flags);
+    syscall_body!(sys_inotify_init1, {
+        let flags = flags as u32;
+        if flags & !(ctypes::IN_NONBLOCK | ctypes::IN_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let inotify = Arc::new(Inotify::new(flags & ctypes::IN_NONBLOCK != 0));
+        let listener: Weak<dyn FsEventListener> = Arc::downgrade(&inotify) as _;
+        notify::add_listener(listener);
+        add_file_like(
+            inotify,
+            flags_to_options(
+                (flags & ctypes::IN_CLOEXEC) as c_int | ctypes::O_RDONLY as c_int,
+                0,
+            ),
+        )
+    })
+}
+
+/// Create an inotify instance, same as `inotify_init1` with no flags.
+pub fn sys_inotify_init() -> c_int {
+    sys_inotify_init1(0)
+}
+
+/// Watch the file or directory at `pathname` for the events in `mask`.
+///
+/// Returns the watch descriptor, which is the same if `pathname` is
+/// already watched.
+pub fn sys_inotify_add_watch(fd: c_int, pathname: *const c_char, mask: u32) -> c_int {
+    debug!(
+        "sys_inotify_add_watch <= fd: {}, pathname: {:?}, mask: {:#x}",
+        fd, pathname, mask
+    );
+    syscall_body!(sys_inotify_add_watch, {
+        let inotify = Inotify::from_fd(fd)?;
+        if mask & !(ctypes::IN_ALL_EVENTS | IN_WATCH_FLAGS) != 0
+            || mask & ctypes::IN_ALL_EVENTS == 0
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        if mask & ctypes::IN_MASK_CREATE != 0 && mask & ctypes::IN_MASK_ADD != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = parse_path(pathname)?;
+        let attr = fops::get_attr(&path)?;
+        if mask & ctypes::IN_ONLYDIR != 0 && !attr.is_dir() {
+            return Err(LinuxError::ENOTDIR);
+        }
+        let key = notify::watch_key(&path).ok_or(LinuxError::ENOENT)?;
+        inotify.add_watch(key, mask)
+    })
+}
+
+/// Remove the watch `wd`, an `IN_IGNORED` event is queued for it.
+pub fn sys_inotify_rm_watch(fd: c_int, wd: c_int) -> c_int {
+    debug!("sys_inotify_rm_watch <= fd: {}, wd: {}", fd, wd);
+    syscall_body!(sys_inotify_rm_watch, {
+        let inotify = Inotify::from_fd(fd)?;
+        if !inotify.inner.lock().remove_watch(wd) {
+            return Err(LinuxError::EINVAL);
+        }
+        inotify.wake();
+        Ok(0)
+    })
+}
@@ -51,7 +51,13 @@ pub fn sys_write(fd: c_int, buf: *const c_void, count: usize) -> ctypes::ssize_t
         let src = unsafe { core::slice::from_raw_parts(buf as *const u8, count) };
         #[cfg(feature = "fd")]
         {
-            Ok(get_file_like(fd)?.write(src)? as ctypes::ssize_t)
+            let ret = get_file_like(fd)?.write(src);
+            // writing to a pipe or socket without readers also raises `SIGPIPE`
+            #[cfg(feature = "signal")]
+            if ret == Err(LinuxError::EPIPE) {
+                crate::imp::signal::raise_sigpipe();
+            }
+            Ok(ret? as ctypes::ssize_t)
         }
         #[cfg(not(feature = "fd"))]
         match fd {
@@ -9,35 +9,83 @@
 
 //! `epoll` implementation.
 //!
-//! TODO: do not support `EPOLLET` flag
+//! Files that push their readiness changes (see [`FileLike::add_poll_observer`])
+//! are only polled after they notify the instance, so idle ones cost nothing
+//! on each wait. Other files are polled on every wait, and every
+//! [`POLL_INTERVAL`] while waiting.
+//!
+//! Interest items are keyed by the open file rather than the fd, so an item
+//! lives until the file is closed everywhere, and a new file reusing the fd
+//! is not mistaken for it.
 
 use alloc::collections::btree_map::Entry;
-use alloc::collections::BTreeMap;
-use alloc::sync::Arc;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
 use core::{ffi::c_int, time::Duration};
 
 use axerrno::{LinuxError, LinuxResult};
 use axsync::Mutex;
-use ruxfdtable::{FileLike, RuxStat};
+use ruxfdtable::{FileLike, PollObserver, RuxStat};
 use ruxhal::time::current_time;
+use spinlock::SpinNoIrq;
 
 use crate::{ctypes, imp::fs::flags_to_options};
 use ruxfs::AbsPath;
 use ruxtask::fs::{add_file_like, get_file_like};
 
+/// Events always reported, even if not requested.
+const EPOLL_ALWAYS: u32 = ctypes::EPOLLERR | ctypes::EPOLLHUP;
+
+/// How often files that can't push readiness changes, and the network
+/// interfaces, are polled while waiting.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Key of the interest item of `file`, the address of the open file.
+///
+/// Items hold a `Weak` of the file, which keeps the address from being reused.
+fn file_key(file: &Arc<dyn FileLike>) -> usize {
+    Arc::as_ptr(file) as *const u8 as usize
+}
+
+struct EpollItem {
+    file: Weak<dyn FileLike>,
+    event: ctypes::epoll_event,
+    /// The file pushes its readiness changes, so it's only polled when it is
+    /// in the ready set.
+    pushed: bool,
+    /// Disarmed by `EPOLLONESHOT` after an event is reported.
+    disabled: bool,
+    /// Events seen by the last poll, to find edges of polled files.
+    last_events: u32,
+}
+
 pub struct EpollInstance {
-    events: Mutex<BTreeMap<usize, ctypes::epoll_event>>,
+    this: Weak<EpollInstance>,
+    /// Interest items keyed by [`file_key`].
+    items: Mutex<BTreeMap<usize, EpollItem>>,
+    /// Keys of files that are not able to push readiness changes.
+    polled: Mutex<BTreeSet<usize>>,
+    /// Keys of pushing files that may be ready.
+    ready: SpinNoIrq<BTreeSet<usize>>,
+    #[cfg(feature = "multitask")]
+    wait_queue: ruxtask::WaitQueue,
 }
 
 unsafe impl Send for ctypes::epoll_event {}
 unsafe impl Sync for ctypes::epoll_event {}
 
 impl EpollInstance {
-    // TODO: parse flags
-    pub fn new(_flags: usize) -> Self {
-        Self {
-            events: Mutex::new(BTreeMap::new()),
-        }
+    pub fn new() -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            this: this.clone(),
+            items: Mutex::new(BTreeMap::new()),
+            polled: Mutex::new(BTreeSet::new()),
+            ready: SpinNoIrq::new(BTreeSet::new()),
+            #[cfg(feature = "multitask")]
+            wait_queue: ruxtask::WaitQueue::new(),
+        })
     }
 
     fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
@@ -47,78 +95,232 @@ impl EpollInstance {
             .map_err(|_| LinuxError::EINVAL)
     }
 
-    fn control(&self, op: usize, fd: usize, event: &ctypes::epoll_event) -> LinuxResult<usize> {
-        match get_file_like(fd as c_int) {
-            Ok(_) => {}
-            Err(e) => return Err(e),
+    fn observer(&self) -> Weak<dyn PollObserver> {
+        self.this.clone()
+    }
+
+    fn control(&self, op: usize, fd: usize, event: Option<ctypes::epoll_event>) -> LinuxResult {
+        let file = get_file_like(fd as c_int)?;
+        if core::ptr::eq(
+            Arc::as_ptr(&file) as *const u8,
+            self as *const Self as *const u8,
+        ) {
+            return Err(LinuxError::EINVAL);
         }
+        let key = file_key(&file);
 
+        let mut items = self.items.lock();
         match op as u32 {
             ctypes::EPOLL_CTL_ADD => {
-                if let Entry::Vacant(e) = self.events.lock().entry(fd) {
-                    e.insert(*event);
-                } else {
+                let event = event.ok_or(LinuxError::EFAULT)?;
+                let exclusive = event.events & ctypes::EPOLLEXCLUSIVE != 0;
+                if exclusive && event.events & ctypes::EPOLLONESHOT != 0 {
+                    return Err(LinuxError::EINVAL);
+                }
+                let Entry::Vacant(entry) = items.entry(key) else {
                     return Err(LinuxError::EEXIST);
+                };
+                let pushed = file.add_poll_observer(self.observer(), key, exclusive);
+                entry.insert(EpollItem {
+                    file: Arc::downgrade(&file),
+                    event,
+                    pushed,
+                    disabled: false,
+                    last_events: 0,
+                });
+                if pushed {
+                    // the file may be ready already
+                    self.ready.lock().insert(key);
+                } else {
+                    self.polled.lock().insert(key);
                 }
             }
             ctypes::EPOLL_CTL_MOD => {
-                let mut events = self.events.lock();
-                if let Entry::Occupied(mut ocp) = events.entry(fd) {
-                    ocp.insert(*event);
-                } else {
-                    return Err(LinuxError::ENOENT);
+                let event = event.ok_or(LinuxError::EFAULT)?;
+                let item = items.get_mut(&key).ok_or(LinuxError::ENOENT)?;
+                if (item.event.events | event.events) & ctypes::EPOLLEXCLUSIVE != 0 {
+                    return Err(LinuxError::EINVAL);
+                }
+                item.event = event;
+                item.disabled = false;
+                item.last_events = 0;
+                if item.pushed {
+                    self.ready.lock().insert(key);
                 }
             }
             ctypes::EPOLL_CTL_DEL => {
-                let mut events = self.events.lock();
-                if let Entry::Occupied(ocp) = events.entry(fd) {
-                    ocp.remove_entry();
-                } else {
-                    return Err(LinuxError::ENOENT);
-                }
+                let item = items.remove(&key).ok_or(LinuxError::ENOENT)?;
+                self.forget(key, &item);
             }
-            _ => {
-                return Err(LinuxError::EINVAL);
+            _ => return Err(LinuxError::EINVAL),
+        }
+        drop(items);
+        // a waiter may be interested in the new item
+        self.wake_up();
+        Ok(())
+    }
+
+    fn forget(&self, key: usize, item: &EpollItem) {
+        if item.pushed {
+            if let Some(file) = item.file.upgrade() {
+                file.remove_poll_observer(&self.observer(), key);
             }
+            self.ready.lock().remove(&key);
+        } else {
+            self.polled.lock().remove(&key);
         }
-        Ok(0)
     }
 
-    fn poll_all(&self, events: &mut [ctypes::epoll_event]) -> LinuxResult<usize> {
-        let ready_list = self.events.lock();
-        let mut events_num = 0;
+    fn wake_up(&self) -> bool {
+        #[cfg(feature = "multitask")]
+        return self.wait_queue.notify_one(true);
+        #[cfg(not(feature = "multitask"))]
+        return false;
+    }
 
-        for (infd, ev) in ready_list.iter() {
-            match get_file_like(*infd as c_int)?.poll() {
-                Err(_) => {
-                    if (ev.events & ctypes::EPOLLERR) != 0 {
-                        events[events_num].events = ctypes::EPOLLERR;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
+    /// Polls the file of `item` and returns the events to report.
+    fn check_item(item: &mut EpollItem) -> Option<u32> {
+        let file = item.file.upgrade()?;
+        let interest = item.event.events | EPOLL_ALWAYS;
+        let events = match file.poll() {
+            Err(_) => ctypes::EPOLLERR,
+            Ok(state) => {
+                let mut events = 0;
+                if state.readable {
+                    events |= ctypes::EPOLLIN;
+                }
+                if state.writable {
+                    events |= ctypes::EPOLLOUT;
                 }
-                Ok(state) => {
-                    if state.readable && (ev.events & ctypes::EPOLLIN != 0) {
-                        events[events_num].events = ctypes::EPOLLIN;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
-
-                    if state.writable && (ev.events & ctypes::EPOLLOUT != 0) {
-                        events[events_num].events = ctypes::EPOLLOUT;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
-
-                    if state.pollhup {
-                        events[events_num].events = ctypes::EPOLLHUP;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
-                    }
+                if state.pollhup {
+                    events |= ctypes::EPOLLHUP;
                 }
+                events
+            }
+        } & interest;
+        let edge = events & !item.last_events;
+        item.last_events = events;
+        let edge_triggered = item.event.events & ctypes::EPOLLET != 0;
+        // pushing files only get here after a notification, which is an edge
+        let report = if edge_triggered && !item.pushed {
+            edge != 0
+        } else {
+            events != 0
+        };
+        report.then_some(events)
+    }
+
+    fn poll_all(&self, events: &mut [ctypes::epoll_event]) -> usize {
+        let mut items = self.items.lock();
+        let mut candidates: Vec<usize> = core::mem::take(&mut *self.ready.lock())
+            .into_iter()
+            .collect();
+        candidates.extend(self.polled.lock().iter());
+
+        let mut events_num = 0;
+        let mut still_ready = Vec::new();
+        let mut dead = Vec::new();
+        for (i, &key) in candidates.iter().enumerate() {
+            if events_num == events.len() {
+                // check the remaining pushing files next time
+                still_ready.extend(candidates[i..].iter().copied());
+                break;
+            }
+            let Some(item) = items.get_mut(&key) else {
+                continue;
+            };
+            if item.disabled {
+                continue;
+            }
+            if item.file.strong_count() == 0 {
+                dead.push(key);
+                continue;
+            }
+            let Some(revents) = Self::check_item(item) else {
+                continue;
+            };
+            events[events_num].events = revents;
+            events[events_num].data = item.event.data;
+            events_num += 1;
+            if item.event.events & ctypes::EPOLLONESHOT != 0 {
+                item.disabled = true;
+            } else if item.pushed && item.event.events & ctypes::EPOLLET == 0 {
+                // level-triggered: report again until it is not ready
+                still_ready.push(key);
+            }
+        }
+
+        // files closed everywhere are removed from the interest list
+        for key in dead {
+            if let Some(item) = items.remove(&key) {
+                self.forget(key, &item);
+            }
+        }
+        if !still_ready.is_empty() {
+            let mut ready = self.ready.lock();
+            ready.extend(
+                still_ready
+                    .into_iter()
+                    .filter(|key| items.get(key).is_some_and(|item| item.pushed)),
+            );
+        }
+        events_num
+    }
+
+    /// Whether files must be polled again while waiting, because they can't
+    /// push readiness changes or need the network interfaces polled.
+    #[cfg(all(feature = "multitask", feature = "irq"))]
+    fn needs_polling(&self) -> bool {
+        #[cfg(feature = "net")]
+        if crate::imp::net::inet_observed() {
+            return true;
+        }
+        !self.polled.lock().is_empty()
+    }
+
+    /// Waits until a pushing file notifies or `deadline` is reached, at most
+    /// [`POLL_INTERVAL`] if files must be polled.
+    fn wait(&self, deadline: Option<Duration>) {
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        {
+            let deadline = if self.needs_polling() {
+                let next_poll = current_time() + POLL_INTERVAL;
+                Some(deadline.map_or(next_poll, |ddl| ddl.min(next_poll)))
+            } else {
+                deadline
+            };
+            let has_ready = || !self.ready.lock().is_empty();
+            match deadline {
+                Some(ddl) => {
+                    self.wait_queue
+                        .wait_timeout_until(ddl.saturating_sub(current_time()), has_ready);
+                }
+                None => self.wait_queue.wait_until(has_ready),
+            }
+        }
+        #[cfg(not(all(feature = "multitask", feature = "irq")))]
+        {
+            let _ = deadline;
+            crate::sys_sched_yield();
+        }
+    }
+}
+
+impl PollObserver for EpollInstance {
+    fn on_ready(&self, key: usize) -> bool {
+        self.ready.lock().insert(key);
+        self.wake_up()
+    }
+}
+
+impl Drop for EpollInstance {
+    fn drop(&mut self) {
+        let observer = self.observer();
+        for (key, item) in self.items.get_mut().iter() {
+            if let Some(file) = item.file.upgrade() {
+                file.remove_poll_observer(&observer, *key);
             }
         }
-        Ok(events_num)
     }
 }
 
@@ -168,11 +370,21 @@ impl FileLike for EpollInstance {
 pub fn sys_epoll_create1(flags: c_int) -> c_int {
     debug!("sys_epoll_create <= {}", flags);
     syscall_body!(sys_epoll_create, {
-        if flags < 0 {
+        if flags as u32 & !ctypes::EPOLL_CLOEXEC != 0 {
             return Err(LinuxError::EINVAL);
         }
-        let epoll_instance = EpollInstance::new(0);
-        add_file_like(Arc::new(epoll_instance), flags_to_options(flags, 0))
+        add_file_like(EpollInstance::new(), flags_to_options(flags, 0))
+    })
+}
+
+/// Creates a new epoll instance, `size` is ignored but must be positive.
+pub fn sys_epoll_create(size: c_int) -> c_int {
+    debug!("sys_epoll_create <= size: {}", size);
+    syscall_body!(sys_epoll_create, {
+        if size <= 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        add_file_like(EpollInstance::new(), flags_to_options(0, 0))
     })
 }
 
@@ -185,10 +397,9 @@ pub unsafe fn sys_epoll_ctl(
 ) -> c_int {
     debug!("sys_epoll_ctl <= epfd: {} op: {} fd: {}", epfd, op, fd);
     syscall_body!(sys_epoll_ctl, {
-        let ret = unsafe {
-            EpollInstance::from_fd(epfd)?.control(op as usize, fd as usize, &(*event))? as c_int
-        };
-        Ok(ret)
+        let event = (!event.is_null()).then(|| unsafe { *event });
+        EpollInstance::from_fd(epfd)?.control(op as usize, fd as usize, event)?;
+        Ok(0)
     })
 }
 
@@ -230,28 +441,8 @@ pub unsafe fn sys_epoll_wait(
         let epoll_instance = EpollInstance::from_fd(epfd)?;
         loop {
             #[cfg(feature = "net")]
-            ruxnet::poll_interfaces();
-            let poll_all_res = epoll_instance.poll_all(events);
-            let mut events_num = 0;
-            match poll_all_res {
-                Ok(num) => events_num = num,
-                Err(LinuxError::EBADF) => {
-                    error!("sys_epoll_wait a non-exist fd");
-                    let mut events = epoll_instance.events.lock();
-                    let del_fds = events
-                        .iter()
-                        .filter(|(&fd, _)| get_file_like(fd as _).is_err())
-                        .map(|(&fd, _)| fd)
-                        .collect::<alloc::vec::Vec<_>>();
-                    del_fds.iter().for_each(|&fd| {
-                        if let Entry::Occupied(ocp) = events.entry(fd) {
-                            ocp.remove_entry();
-                        }
-                    });
-                    return Ok(0);
-                }
-                Err(_) => {}
-            }
+            crate::imp::net::poll_interfaces();
+            let events_num = epoll_instance.poll_all(events);
             if events_num > 0 {
                 return Ok(events_num as c_int);
             }
@@ -260,7 +451,7 @@ pub unsafe fn sys_epoll_wait(
                 debug!("    timeout!");
                 return Ok(0);
             }
-            crate::sys_sched_yield();
+            epoll_instance.wait(deadline);
         }
     })
 }
@@ -0,0 +1,1256 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! `io_uring` instances for asynchronous I/O.
+//!
+//! The submission and completion rings are pages allocated by the instance,
+//! registered as the shared pages of an anonymous memory file, so that the
+//! application maps them with `mmap` on the ring descriptor and the page fault
+//! handler finds them like the pages of any shared file mapping.
+//!
+//! Submitted requests are run by a [`WorkerPool`] of the submitting process,
+//! which calls the same file and socket operations as the synchronous API and
+//! posts their results to the completion ring.
+//!
+//! A request that has to wait for a file to become ready or for a timeout
+//! doesn't take a worker meanwhile: its job is parked with an observer of the
+//! file or a timer, which may run in interrupt context, so a dispatcher task
+//! of the instance queues the job to the workers again once it is woken up.
+
+use alloc::{
+    collections::VecDeque,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::ffi::{c_int, c_uint, c_void};
+use core::mem::size_of;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axalloc::global_allocator;
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use memory_addr::PAGE_SIZE_4K;
+use ruxfdtable::{FileLike, PollObserver, PollObservers, RuxStat};
+use ruxfs::AbsPath;
+use ruxhal::mem::direct_virt_to_phys;
+use ruxhal::time::current_time;
+use ruxtask::fs::{add_file_like, get_file_like, File};
+use ruxtask::timers::{cancel_timer_callback, set_timer_callback, TimerCallbackId};
+use ruxtask::vma::{add_shared_page, PageInfo};
+use ruxtask::worker_pool::WorkerPool;
+use ruxtask::WaitQueue;
+use spinlock::SpinNoIrq;
+
+#[cfg(feature = "net")]
+use crate::imp::net::Socket;
+use crate::{ctypes, imp::eventfd::EventFd, imp::fs::flags_to_options};
+
+/// Maximum number of submission queue entries.
+const IORING_MAX_ENTRIES: u32 = 32768;
+/// Maximum number of completion queue entries.
+const IORING_MAX_CQ_ENTRIES: u32 = 2 * IORING_MAX_ENTRIES;
+/// Maximum number of registered files.
+const IORING_MAX_FIXED_FILES: u32 = 1 << 15;
+/// Maximum number of worker threads of an instance.
+const MAX_WORKERS: usize = 64;
+/// Interval to poll the network interfaces while jobs are parked, as TCP and
+/// UDP sockets only become ready when they are polled.
+const NET_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// opcodes of the submission queue entries
+const IORING_OP_NOP: u8 = 0;
+const IORING_OP_READV: u8 = 1;
+const IORING_OP_WRITEV: u8 = 2;
+const IORING_OP_FSYNC: u8 = 3;
+const IORING_OP_POLL_ADD: u8 = 6;
+const IORING_OP_TIMEOUT: u8 = 11;
+const IORING_OP_ACCEPT: u8 = 13;
+const IORING_OP_CONNECT: u8 = 16;
+const IORING_OP_READ: u8 = 22;
+const IORING_OP_WRITE: u8 = 23;
+const IORING_OP_SEND: u8 = 26;
+const IORING_OP_RECV: u8 = 27;
+const IORING_OP_LAST: u8 = 28;
+
+/// Opcodes reported as supported by `IORING_REGISTER_PROBE`.
+const SUPPORTED_OPS: [u8; 12] = [
+    IORING_OP_NOP,
+    IORING_OP_READV,
+    IORING_OP_WRITEV,
+    IORING_OP_FSYNC,
+    IORING_OP_POLL_ADD,
+    IORING_OP_TIMEOUT,
+    IORING_OP_ACCEPT,
+    IORING_OP_CONNECT,
+    IORING_OP_READ,
+    IORING_OP_WRITE,
+    IORING_OP_SEND,
+    IORING_OP_RECV,
+];
+
+// Layout of the rings region, shared by the submission and completion queues.
+const SQ_HEAD: usize = 0;
+const SQ_TAIL: usize = 4;
+const SQ_RING_MASK: usize = 8;
+const SQ_RING_ENTRIES: usize = 12;
+const SQ_FLAGS: usize = 16;
+const SQ_DROPPED: usize = 20;
+const CQ_HEAD: usize = 24;
+const CQ_TAIL: usize = 28;
+const CQ_RING_MASK: usize = 32;
+const CQ_RING_ENTRIES: usize = 36;
+const CQ_OVERFLOW: usize = 40;
+const CQ_FLAGS: usize = 44;
+const CQ_CQES: usize = 64;
+
+/// Pages of the rings region followed by the submission queue entries.
+struct RingMemory {
+    /// anonymous file whose shared pages are `pages`
+    file: Arc<File>,
+    /// the file only records weak references to its pages, this keeps them
+    /// as long as the instance lives
+    _pages: Vec<Arc<PageInfo>>,
+    /// kernel address of the first page
+    base: usize,
+    rings_size: usize,
+    /// offset of the submission queue entries in the file
+    sqes_offset: usize,
+    sqes_size: usize,
+}
+
+impl RingMemory {
+    fn new(rings_size: usize, sqes_size: usize) -> LinuxResult<Self> {
+        let sqes_offset = rings_size.next_multiple_of(PAGE_SIZE_4K);
+        let size = sqes_offset + sqes_size.next_multiple_of(PAGE_SIZE_4K);
+        let num_pages = size / PAGE_SIZE_4K;
+        let base = global_allocator()
+            .alloc_pages(num_pages, PAGE_SIZE_4K)
+            .map_err(|_| LinuxError::ENOMEM)?;
+        unsafe { core::ptr::write_bytes(base as *mut u8, 0, size) };
+        let pages: Vec<_> = (0..num_pages)
+            .map(|i| {
+                Arc::new(PageInfo {
+                    paddr: direct_virt_to_phys((base + i * PAGE_SIZE_4K).into()),
+                    mapping_file: None,
+                })
+            })
+            .collect();
+
+        let file = ruxfs::memfd::create("[io_uring]", false)?;
+        file.truncate(size as u64)?;
+        let node_id = file.node_id();
+        for (i, page) in pages.iter().enumerate() {
+            add_shared_page((node_id, i * PAGE_SIZE_4K), page);
+        }
+        Ok(Self {
+            file: Arc::new(File::new(file)),
+            _pages: pages,
+            base,
+            rings_size,
+            sqes_offset,
+            sqes_size,
+        })
+    }
+
+    /// The 32-bit field at `offset` of the rings region.
+    fn field(&self, offset: usize) -> &AtomicU32 {
+        unsafe { &*((self.base + offset) as *const AtomicU32) }
+    }
+
+    fn read_sqe(&self, index: u32) -> ctypes::io_uring_sqe {
+        let sqes = (self.base + self.sqes_offset) as *const ctypes::io_uring_sqe;
+        unsafe { sqes.add(index as usize).read_volatile() }
+    }
+
+    fn write_cqe(&self, index: u32, cqe: ctypes::io_uring_cqe) {
+        let cqes = (self.base + CQ_CQES) as *mut ctypes::io_uring_cqe;
+        unsafe { cqes.add(index as usize).write_volatile(cqe) }
+    }
+}
+
+/// A submitted request, or a chain of linked requests run in order.
+struct Job {
+    sqes: Vec<ctypes::io_uring_sqe>,
+    /// index of the next request to run
+    next: usize,
+    /// number of completions posted when the job was submitted
+    completed: u64,
+    /// file of the next request, kept while it waits
+    file: Option<Arc<dyn FileLike>>,
+    /// deadline of the next request if it is a timeout
+    deadline: Option<Duration>,
+    /// the next request runs without waiting, as its file doesn't push
+    /// readiness changes
+    no_wait: bool,
+}
+
+impl Job {
+    fn new(sqes: Vec<ctypes::io_uring_sqe>, completed: u64) -> Self {
+        Self {
+            sqes,
+            next: 0,
+            completed,
+            file: None,
+            deadline: None,
+            no_wait: false,
+        }
+    }
+}
+
+/// What the next request of a job waits for.
+enum Wait {
+    /// the file to have one of the events
+    Poll(Arc<dyn FileLike>, u32),
+    /// the deadline, or the number of posted completions to reach `completed`
+    Timeout {
+        deadline: Duration,
+        completed: Option<u64>,
+    },
+}
+
+enum Step {
+    /// the request completed with the result
+    Done(i32),
+    /// the request has to wait first
+    Wait(Wait),
+}
+
+/// A job parked until its next request can go on.
+struct Waiter {
+    ring: Weak<IoUringInner>,
+    job: SpinNoIrq<Option<Job>>,
+    wait: Wait,
+    timer: SpinNoIrq<Option<TimerCallbackId>>,
+}
+
+impl Waiter {
+    /// Hands the job to the dispatcher of the instance, returning whether it
+    /// was still parked.
+    fn wake(&self) -> bool {
+        let Some(job) = self.job.lock().take() else {
+            return false;
+        };
+        if let Some(ring) = self.ring.upgrade() {
+            ring.resumed.lock().push_back(job);
+            ring.resume_wait_queue.notify_one(true);
+        }
+        true
+    }
+
+    /// Stops watching the file or the clock.
+    fn disarm(self: &Arc<Self>) {
+        match &self.wait {
+            Wait::Poll(file, _) => {
+                let observer: Weak<dyn PollObserver> = Arc::downgrade(self);
+                file.remove_poll_observer(&observer, 0);
+            }
+            Wait::Timeout { .. } => {
+                if let Some(id) = self.timer.lock().take() {
+                    cancel_timer_callback(id);
+                }
+            }
+        }
+    }
+}
+
+impl PollObserver for Waiter {
+    fn on_ready(&self, _key: usize) -> bool {
+        self.wake()
+    }
+}
+
+struct IoUringInner {
+    memory: RingMemory,
+    sq_entries: u32,
+    cq_entries: u32,
+    /// offset of the submission queue array in the rings region
+    sq_array: usize,
+    /// serializes the submitters
+    submit_lock: Mutex<()>,
+    /// completions that did not fit in the completion queue, this lock also
+    /// serializes the posting of completions
+    overflow: SpinNoIrq<VecDeque<ctypes::io_uring_cqe>>,
+    pool: WorkerPool,
+    /// parked jobs, which are only removed by the dispatcher
+    waiting: SpinNoIrq<Vec<Arc<Waiter>>>,
+    /// parked jobs woken up, for the dispatcher to queue to the workers
+    resumed: SpinNoIrq<VecDeque<Job>>,
+    /// the dispatcher waiting for jobs woken up
+    resume_wait_queue: WaitQueue,
+    /// tasks waiting for completions
+    cq_wait_queue: WaitQueue,
+    /// number of submitted requests that are not completed yet
+    inflight: AtomicUsize,
+    /// number of completions posted
+    completed: AtomicU64,
+    closed: AtomicBool,
+    /// registered files, `None` for holes
+    files: SpinNoIrq<Vec<Option<Arc<dyn FileLike>>>>,
+    eventfd: SpinNoIrq<Option<Arc<EventFd>>>,
+    observers: PollObservers,
+}
+
+impl IoUringInner {
+    fn new(sq_entries: u32, cq_entries: u32) -> LinuxResult<Self> {
+        let sq_array = CQ_CQES + cq_entries as usize * size_of::<ctypes::io_uring_cqe>();
+        let rings_size = sq_array + sq_entries as usize * size_of::<u32>();
+        let sqes_size = sq_entries as usize * size_of::<ctypes::io_uring_sqe>();
+        let memory = RingMemory::new(rings_size, sqes_size)?;
+        memory
+            .field(SQ_RING_MASK)
+            .store(sq_entries - 1, Ordering::Relaxed);
+        memory
+            .field(SQ_RING_ENTRIES)
+            .store(sq_entries, Ordering::Relaxed);
+        memory
+            .field(CQ_RING_MASK)
+            .store(cq_entries - 1, Ordering::Relaxed);
+        memory
+            .field(CQ_RING_ENTRIES)
+            .store(cq_entries, Ordering::Relaxed);
+        Ok(Self {
+            memory,
+            sq_entries,
+            cq_entries,
+            sq_array,
+            submit_lock: Mutex::new(()),
+            overflow: SpinNoIrq::new(VecDeque::new()),
+            pool: WorkerPool::new("io_uring", MAX_WORKERS),
+            waiting: SpinNoIrq::new(Vec::new()),
+            resumed: SpinNoIrq::new(VecDeque::new()),
+            resume_wait_queue: WaitQueue::new(),
+            cq_wait_queue: WaitQueue::new(),
+            inflight: AtomicUsize::new(0),
+            completed: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+            files: SpinNoIrq::new(Vec::new()),
+            eventfd: SpinNoIrq::new(None),
+            observers: PollObservers::new(),
+        })
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Consumes up to `to_submit` entries of the submission queue.
+    fn submit(self: &Arc<Self>, to_submit: u32) -> u32 {
+        let _guard = self.submit_lock.lock();
+        let mem = &self.memory;
+        let head = mem.field(SQ_HEAD).load(Ordering::Relaxed);
+        let tail = mem.field(SQ_TAIL).load(Ordering::Acquire);
+        let count = tail.wrapping_sub(head).min(to_submit);
+        let mut chain = Vec::new();
+        for i in 0..count {
+            let slot = head.wrapping_add(i) & (self.sq_entries - 1);
+            let index = mem
+                .field(self.sq_array + slot as usize * size_of::<u32>())
+                .load(Ordering::Relaxed);
+            if index >= self.sq_entries {
+                mem.field(SQ_DROPPED).fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            let sqe = mem.read_sqe(index);
+            chain.push(sqe);
+            if sqe.flags as u32 & (ctypes::IOSQE_IO_LINK | ctypes::IOSQE_IO_HARDLINK) == 0 {
+                self.dispatch(core::mem::take(&mut chain));
+            }
+        }
+        // a link flag on the last entry does not carry over to the next call
+        if !chain.is_empty() {
+            self.dispatch(chain);
+        }
+        mem.field(SQ_HEAD)
+            .store(head.wrapping_add(count), Ordering::Release);
+        count
+    }
+
+    /// Queues a chain of requests for the workers, after all the previous
+    /// requests complete and before any later one starts if it is drained.
+    fn dispatch(self: &Arc<Self>, sqes: Vec<ctypes::io_uring_sqe>) {
+        let drain = sqes[0].flags as u32 & ctypes::IOSQE_IO_DRAIN != 0;
+        if drain {
+            self.wait_idle();
+        }
+        self.inflight.fetch_add(sqes.len(), Ordering::AcqRel);
+        self.queue(Job::new(sqes, self.completed.load(Ordering::Acquire)));
+        if drain {
+            self.wait_idle();
+        }
+    }
+
+    fn queue(self: &Arc<Self>, job: Job) {
+        let ring = self.clone();
+        self.pool.queue(move || ring.run(job));
+    }
+
+    fn wait_idle(&self) {
+        self.cq_wait_queue
+            .wait_until(|| self.inflight.load(Ordering::Acquire) == 0);
+    }
+
+    /// Queues the parked jobs that are woken up to the workers, until the
+    /// instance is closed and no job is parked.
+    fn dispatch_resumed(self: Arc<Self>) {
+        loop {
+            let has_resumed = || self.is_closed() || !self.resumed.lock().is_empty();
+            if self.polls_net() {
+                let timed_out = self
+                    .resume_wait_queue
+                    .wait_timeout_until(NET_POLL_INTERVAL, has_resumed);
+                if timed_out {
+                    #[cfg(feature = "net")]
+                    crate::imp::net::poll_interfaces();
+                }
+            } else {
+                self.resume_wait_queue.wait_until(has_resumed);
+            }
+
+            let woken: Vec<Arc<Waiter>> = {
+                let mut waiting = self.waiting.lock();
+                let (woken, parked): (Vec<_>, Vec<_>) =
+                    waiting.drain(..).partition(|w| w.job.lock().is_none());
+                *waiting = parked;
+                woken
+            };
+            for waiter in woken {
+                waiter.disarm();
+            }
+            let jobs: Vec<Job> = self.resumed.lock().drain(..).collect();
+            for job in jobs {
+                self.queue(job);
+            }
+            if self.is_closed() && self.waiting.lock().is_empty() && self.resumed.lock().is_empty()
+            {
+                return;
+            }
+        }
+    }
+
+    /// Whether the network interfaces must be polled while jobs are parked.
+    fn polls_net(&self) -> bool {
+        #[cfg(feature = "net")]
+        return crate::imp::net::inet_observed() && !self.waiting.lock().is_empty();
+        #[cfg(not(feature = "net"))]
+        false
+    }
+
+    /// Parks `job` until its next request can go on, or gives it back if the
+    /// instance is closed or the file doesn't push readiness changes.
+    fn park(self: &Arc<Self>, mut job: Job, wait: Wait) -> Result<(), Job> {
+        let waiter = Arc::new(Waiter {
+            ring: Arc::downgrade(self),
+            job: SpinNoIrq::new(None),
+            wait,
+            timer: SpinNoIrq::new(None),
+        });
+        {
+            // checked with the lock held, as `close` wakes up the parked jobs
+            let mut waiting = self.waiting.lock();
+            if self.is_closed() {
+                return Err(job);
+            }
+            job.no_wait = false;
+            *waiter.job.lock() = Some(job);
+            waiting.push(waiter.clone());
+        }
+        match &waiter.wait {
+            Wait::Poll(file, events) => {
+                let observer: Weak<dyn PollObserver> = Arc::downgrade(&waiter);
+                if !file.add_poll_observer(observer, 0, false) {
+                    let job = waiter.job.lock().take();
+                    return job.map_or(Ok(()), |mut job| {
+                        job.no_wait = true;
+                        Err(job)
+                    });
+                }
+                // the file may have become ready before the observer was added
+                if poll_events(&**file, *events) != 0 {
+                    waiter.wake();
+                }
+            }
+            Wait::Timeout {
+                deadline,
+                completed,
+            } => {
+                let weak = Arc::downgrade(&waiter);
+                let id = set_timer_callback(*deadline, move |_| {
+                    if let Some(waiter) = weak.upgrade() {
+                        waiter.wake();
+                    }
+                });
+                *waiter.timer.lock() = Some(id);
+                if completed.is_some_and(|c| self.completed.load(Ordering::Acquire) >= c) {
+                    waiter.wake();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the requests of a job in order until one has to wait, a failed
+    /// request cancels the rest of the chain unless it is hard linked.
+    fn run(self: &Arc<Self>, mut job: Job) {
+        while let Some(sqe) = job.sqes.get(job.next).copied() {
+            let res = match self.step(&sqe, &mut job) {
+                Step::Done(res) => res,
+                Step::Wait(wait) => match self.park(job, wait) {
+                    Ok(()) => return,
+                    // the request runs again, without waiting or cancelled
+                    Err(unparked) => {
+                        job = unparked;
+                        continue;
+                    }
+                },
+            };
+            job.next += 1;
+            job.file = None;
+            job.deadline = None;
+            job.no_wait = false;
+            let broken = res < 0 && sqe.flags as u32 & ctypes::IOSQE_IO_HARDLINK == 0;
+            self.complete(&sqe, res);
+            if broken {
+                for sqe in &job.sqes[job.next..] {
+                    self.complete(sqe, -(LinuxError::ECANCELED as i32));
+                }
+                return;
+            }
+        }
+    }
+
+    /// The file of a request, a registered one with `IOSQE_FIXED_FILE`.
+    fn file(&self, sqe: &ctypes::io_uring_sqe) -> LinuxResult<Arc<dyn FileLike>> {
+        if sqe.flags as u32 & ctypes::IOSQE_FIXED_FILE != 0 {
+            self.files
+                .lock()
+                .get(sqe.fd as u32 as usize)
+                .cloned()
+                .flatten()
+                .ok_or(LinuxError::EBADF)
+        } else {
+            get_file_like(sqe.fd)
+        }
+    }
+
+    /// Runs a request, unless it has to wait for its file first.
+    fn step(&self, sqe: &ctypes::io_uring_sqe, job: &mut Job) -> Step {
+        match sqe.opcode {
+            IORING_OP_NOP => return Step::Done(0),
+            IORING_OP_TIMEOUT => return self.timeout(sqe, job),
+            _ => {}
+        }
+        let file = match job.file.clone().map_or_else(|| self.file(sqe), Ok) {
+            Ok(file) => file,
+            Err(err) => return Step::Done(-(err as i32)),
+        };
+        job.file = Some(file.clone());
+        if sqe.opcode == IORING_OP_POLL_ADD {
+            let events = unsafe { sqe.__bindgen_anon_3.poll32_events };
+            let revents = poll_events(&*file, events);
+            return if revents != 0 || job.no_wait {
+                Step::Done(revents as i32)
+            } else {
+                self.wait_for(file, events)
+            };
+        }
+        let events = match sqe.opcode {
+            IORING_OP_READ | IORING_OP_READV | IORING_OP_RECV | IORING_OP_ACCEPT => ctypes::EPOLLIN,
+            IORING_OP_WRITE | IORING_OP_WRITEV | IORING_OP_SEND => ctypes::EPOLLOUT,
+            _ => 0,
+        };
+        if events != 0 && !job.no_wait && !file.is_nonblocking() && poll_events(&*file, events) == 0
+        {
+            return self.wait_for(file, events);
+        }
+        Step::Done(execute(sqe, &file))
+    }
+
+    fn wait_for(&self, file: Arc<dyn FileLike>, events: u32) -> Step {
+        if self.is_closed() {
+            Step::Done(-(LinuxError::ECANCELED as i32))
+        } else {
+            Step::Wait(Wait::Poll(file, events))
+        }
+    }
+
+    /// Waits until the time in the request passes or `off` more requests
+    /// complete after it is submitted.
+    fn timeout(&self, sqe: &ctypes::io_uring_sqe, job: &mut Job) -> Step {
+        let (count, addr) = unsafe { (sqe.__bindgen_anon_1.off, sqe.__bindgen_anon_2.addr) };
+        let deadline = match job.deadline {
+            Some(deadline) => deadline,
+            None => {
+                let flags = unsafe { sqe.__bindgen_anon_3.timeout_flags };
+                if sqe.len != 1 || flags & !ctypes::IORING_TIMEOUT_ABS != 0 {
+                    return Step::Done(-(LinuxError::EINVAL as i32));
+                }
+                let ts = addr as *const ctypes::timespec;
+                if ts.is_null() {
+                    return Step::Done(-(LinuxError::EFAULT as i32));
+                }
+                let ts = unsafe { *ts };
+                if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+                    return Step::Done(-(LinuxError::EINVAL as i32));
+                }
+                let deadline = if flags & ctypes::IORING_TIMEOUT_ABS != 0 {
+                    Duration::from(ts)
+                } else {
+                    current_time() + Duration::from(ts)
+                };
+                *job.deadline.insert(deadline)
+            }
+        };
+        let completed = (count > 0).then_some(job.completed + count);
+        if self.is_closed() {
+            Step::Done(-(LinuxError::ECANCELED as i32))
+        } else if completed.is_some_and(|c| self.completed.load(Ordering::Acquire) >= c) {
+            Step::Done(0)
+        } else if current_time() >= deadline {
+            Step::Done(-(LinuxError::ETIME as i32))
+        } else {
+            Step::Wait(Wait::Timeout {
+                deadline,
+                completed,
+            })
+        }
+    }
+
+    fn complete(&self, sqe: &ctypes::io_uring_sqe, res: i32) {
+        if res < 0 || sqe.flags as u32 & ctypes::IOSQE_CQE_SKIP_SUCCESS == 0 {
+            self.post(ctypes::io_uring_cqe {
+                user_data: sqe.user_data,
+                res,
+                flags: 0,
+            });
+        }
+        self.inflight.fetch_sub(1, Ordering::AcqRel);
+        self.cq_wait_queue.notify_all(true);
+        self.observers.notify();
+    }
+
+    fn post(&self, cqe: ctypes::io_uring_cqe) {
+        let mut overflow = self.overflow.lock();
+        self.flush_overflow(&mut overflow);
+        if !overflow.is_empty() || !self.push_cqe(cqe) {
+            overflow.push_back(cqe);
+            self.memory
+                .field(SQ_FLAGS)
+                .fetch_or(ctypes::IORING_SQ_CQ_OVERFLOW, Ordering::Release);
+        }
+        drop(overflow);
+        let completed = self.completed.fetch_add(1, Ordering::AcqRel) + 1;
+        self.wake_timeouts(completed);
+
+        let eventfd = self.eventfd.lock().clone();
+        let disabled = self.memory.field(CQ_FLAGS).load(Ordering::Acquire)
+            & ctypes::IORING_CQ_EVENTFD_DISABLED
+            != 0;
+        if let Some(eventfd) = eventfd.filter(|_| !disabled) {
+            eventfd.signal(1);
+        }
+    }
+
+    /// Wakes up the timeouts waiting for `completed` completions.
+    fn wake_timeouts(&self, completed: u64) {
+        let reached: Vec<Arc<Waiter>> = self
+            .waiting
+            .lock()
+            .iter()
+            .filter(
+                |w| matches!(w.wait, Wait::Timeout { completed: Some(c), .. } if completed >= c),
+            )
+            .cloned()
+            .collect();
+        for waiter in reached {
+            waiter.wake();
+        }
+    }
+
+    fn push_cqe(&self, cqe: ctypes::io_uring_cqe) -> bool {
+        let mem = &self.memory;
+        let tail = mem.field(CQ_TAIL).load(Ordering::Relaxed);
+        let head = mem.field(CQ_HEAD).load(Ordering::Acquire);
+        if tail.wrapping_sub(head) >= self.cq_entries {
+            return false;
+        }
+        mem.write_cqe(tail & (self.cq_entries - 1), cqe);
+        mem.field(CQ_TAIL)
+            .store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// Moves the overflown completions to the completion queue as it has room.
+    fn flush_overflow(&self, overflow: &mut VecDeque<ctypes::io_uring_cqe>) {
+        while let Some(&cqe) = overflow.front() {
+            if !self.push_cqe(cqe) {
+                return;
+            }
+            overflow.pop_front();
+        }
+        self.memory
+            .field(SQ_FLAGS)
+            .fetch_and(!ctypes::IORING_SQ_CQ_OVERFLOW, Ordering::Release);
+    }
+
+    /// Number of completions in the completion queue, after moving the
+    /// overflown ones there.
+    fn cq_ready(&self) -> u32 {
+        self.flush_overflow(&mut self.overflow.lock());
+        let tail = self.memory.field(CQ_TAIL).load(Ordering::Acquire);
+        let head = self.memory.field(CQ_HEAD).load(Ordering::Acquire);
+        tail.wrapping_sub(head)
+    }
+
+    fn sq_full(&self) -> bool {
+        let tail = self.memory.field(SQ_TAIL).load(Ordering::Acquire);
+        let head = self.memory.field(SQ_HEAD).load(Ordering::Acquire);
+        tail.wrapping_sub(head) >= self.sq_entries
+    }
+
+    /// Cancels the parked requests, and the requests that would wait later.
+    fn close(&self) {
+        let parked = {
+            let waiting = self.waiting.lock();
+            self.closed.store(true, Ordering::Release);
+            waiting.clone()
+        };
+        for waiter in parked {
+            waiter.wake();
+        }
+        self.resume_wait_queue.notify_all(true);
+        self.cq_wait_queue.notify_all(true);
+    }
+}
+
+/// The events of `events` that `file` has, `EPOLLHUP` and `EPOLLERR` being
+/// always reported.
+fn poll_events(file: &dyn FileLike, events: u32) -> u32 {
+    match file.poll() {
+        Ok(state) => {
+            let mut revents = 0;
+            if state.readable {
+                revents |= ctypes::EPOLLIN;
+            }
+            if state.writable {
+                revents |= ctypes::EPOLLOUT;
+            }
+            revents &= events;
+            if state.pollhup {
+                revents |= ctypes::EPOLLHUP;
+            }
+            revents
+        }
+        Err(_) => ctypes::EPOLLERR,
+    }
+}
+
+/// Runs a request on `file` that doesn't wait for readiness.
+fn execute(sqe: &ctypes::io_uring_sqe, file: &Arc<dyn FileLike>) -> i32 {
+    let (off, addr) = unsafe { (sqe.__bindgen_anon_1.off, sqe.__bindgen_anon_2.addr) };
+    let op_flags = unsafe { sqe.__bindgen_anon_3.msg_flags };
+    let len = sqe.len as usize;
+    let res = unsafe {
+        match sqe.opcode {
+            IORING_OP_READ => buffer_mut(addr, len).and_then(|buf| read_file(file, off, buf)),
+            IORING_OP_WRITE => buffer(addr, len).and_then(|buf| write_file(file, off, buf)),
+            IORING_OP_READV => iovecs(addr, len).and_then(|iovs| {
+                transfer_iovecs(iovs, |done, iov| {
+                    let buf = buffer_mut(iov.iov_base as u64, iov.iov_len)?;
+                    read_file(file, advance(off, done), buf)
+                })
+            }),
+            IORING_OP_WRITEV => iovecs(addr, len).and_then(|iovs| {
+                transfer_iovecs(iovs, |done, iov| {
+                    let buf = buffer(iov.iov_base as u64, iov.iov_len)?;
+                    write_file(file, advance(off, done), buf)
+                })
+            }),
+            IORING_OP_FSYNC => {
+                let fsync_flags = sqe.__bindgen_anon_3.fsync_flags;
+                if fsync_flags & !ctypes::IORING_FSYNC_DATASYNC != 0 {
+                    Err(LinuxError::EINVAL)
+                } else {
+                    file.flush().map(|_| 0)
+                }
+            }
+            #[cfg(feature = "net")]
+            IORING_OP_ACCEPT => Socket::from_file(file.clone()).and_then(|socket| {
+                let addrlen = sqe.__bindgen_anon_1.addr2 as *mut ctypes::socklen_t;
+                accept(&socket, addr as *mut ctypes::sockaddr, addrlen, op_flags)
+            }),
+            #[cfg(feature = "net")]
+            IORING_OP_CONNECT => Socket::from_file(file.clone())
+                .and_then(|socket| {
+                    socket.connect(addr as *const ctypes::sockaddr, off as ctypes::socklen_t)
+                })
+                .map(|_| 0),
+            #[cfg(feature = "net")]
+            IORING_OP_SEND => {
+                Socket::from_file(file.clone()).and_then(|socket| socket.send(buffer(addr, len)?))
+            }
+            #[cfg(feature = "net")]
+            IORING_OP_RECV => Socket::from_file(file.clone())
+                .and_then(|socket| socket.recv(buffer_mut(addr, len)?, op_flags as c_int)),
+            #[cfg(not(feature = "net"))]
+            IORING_OP_ACCEPT | IORING_OP_CONNECT | IORING_OP_SEND | IORING_OP_RECV => {
+                Err(LinuxError::EOPNOTSUPP)
+            }
+            _ => Err(LinuxError::EINVAL),
+        }
+    };
+    match res {
+        Ok(size) => size.min(i32::MAX as usize) as i32,
+        Err(err) => -(err as i32),
+    }
+}
+
+/// The user buffer of `len` bytes at `addr`.
+unsafe fn buffer<'a>(addr: u64, len: usize) -> LinuxResult<&'a [u8]> {
+    match addr {
+        0 if len > 0 => Err(LinuxError::EFAULT),
+        0 => Ok(&[]),
+        addr => Ok(core::slice::from_raw_parts(addr as usize as *const u8, len)),
+    }
+}
+
+/// The writable user buffer of `len` bytes at `addr`.
+unsafe fn buffer_mut<'a>(addr: u64, len: usize) -> LinuxResult<&'a mut [u8]> {
+    match addr {
+        0 if len > 0 => Err(LinuxError::EFAULT),
+        0 => Ok(&mut []),
+        addr => Ok(core::slice::from_raw_parts_mut(
+            addr as usize as *mut u8,
+            len,
+        )),
+    }
+}
+
+/// The `count` buffers described by the `iovec` array at `addr`.
+unsafe fn iovecs<'a>(addr: u64, count: usize) -> LinuxResult<&'a [ctypes::iovec]> {
+    match addr {
+        _ if count > 1024 => Err(LinuxError::EINVAL),
+        0 if count > 0 => Err(LinuxError::EFAULT),
+        0 => Ok(&[]),
+        addr => Ok(core::slice::from_raw_parts(
+            addr as usize as *const ctypes::iovec,
+            count,
+        )),
+    }
+}
+
+/// Transfers the buffers of `iovs` in order with `f`, which is given the
+/// number of bytes transferred before, until a transfer is short.
+fn transfer_iovecs(
+    iovs: &[ctypes::iovec],
+    mut f: impl FnMut(usize, &ctypes::iovec) -> LinuxResult<usize>,
+) -> LinuxResult<usize> {
+    let mut done = 0;
+    for iov in iovs {
+        match f(done, iov) {
+            Ok(size) => {
+                done += size;
+                if size < iov.iov_len {
+                    break;
+                }
+            }
+            Err(err) => return if done > 0 { Ok(done) } else { Err(err) },
+        }
+    }
+    Ok(done)
+}
+
+/// The offset `done` bytes after `off`, which stays `-1` for the current
+/// position of the file.
+fn advance(off: u64, done: usize) -> u64 {
+    if off == u64::MAX {
+        off
+    } else {
+        off + done as u64
+    }
+}
+
+/// The regular file to do positional I/O on.
+fn regular_file(file: &Arc<dyn FileLike>) -> LinuxResult<Arc<File>> {
+    file.clone()
+        .into_any()
+        .downcast::<File>()
+        .map_err(|_| LinuxError::ESPIPE)
+}
+
+/// Reads `file` at its current position if `off` is `-1`, or else at `off`.
+fn read_file(file: &Arc<dyn FileLike>, off: u64, buf: &mut [u8]) -> LinuxResult<usize> {
+    if off == u64::MAX {
+        file.read(buf)
+    } else {
+        Ok(regular_file(file)?.inner.write().read_at(off, buf)?)
+    }
+}
+
+/// Writes `file` at its current position if `off` is `-1`, or else at `off`.
+fn write_file(file: &Arc<dyn FileLike>, off: u64, buf: &[u8]) -> LinuxResult<usize> {
+    if off != u64::MAX {
+        return Ok(regular_file(file)?.inner.write().write_at(off, buf)?);
+    }
+    let ret = file.write(buf);
+    // writing to a pipe or socket without readers also raises `SIGPIPE`
+    #[cfg(feature = "signal")]
+    if ret == Err(LinuxError::EPIPE) {
+        crate::imp::signal::raise_sigpipe();
+    }
+    ret
+}
+
+/// Accepts a connection on `socket`, with the `SOCK_NONBLOCK` and
+/// `SOCK_CLOEXEC` flags of `accept4`.
+#[cfg(feature = "net")]
+unsafe fn accept(
+    socket: &Socket,
+    addr: *mut ctypes::sockaddr,
+    addrlen: *mut ctypes::socklen_t,
+    flags: u32,
+) -> LinuxResult<usize> {
+    if flags & !(ctypes::SOCK_NONBLOCK | ctypes::SOCK_CLOEXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let new_fd = crate::imp::net::accept_socket(socket, addr, addrlen)?;
+    if flags & ctypes::SOCK_NONBLOCK != 0 {
+        crate::sys_fcntl(
+            new_fd,
+            ctypes::F_SETFL as c_int,
+            ctypes::O_NONBLOCK as usize,
+        );
+    }
+    if flags & ctypes::SOCK_CLOEXEC != 0 {
+        crate::sys_fcntl(
+            new_fd,
+            ctypes::F_SETFD as c_int,
+            ctypes::FD_CLOEXEC as usize,
+        );
+    }
+    Ok(new_fd as usize)
+}
+
+pub struct IoUring {
+    inner: Arc<IoUringInner>,
+}
+
+impl IoUring {
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::EOPNOTSUPP)
+    }
+}
+
+impl Drop for IoUring {
+    fn drop(&mut self) {
+        self.inner.close();
+    }
+}
+
+impl FileLike for IoUring {
+    fn path(&self) -> AbsPath {
+        AbsPath::new("/io_uring")
+    }
+
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn flush(&self) -> LinuxResult {
+        Ok(())
+    }
+
+    fn stat(&self) -> LinuxResult<RuxStat> {
+        let st_mode = 0o600u32; // rw-------
+        Ok(RuxStat::from(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            ..Default::default()
+        }))
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: self.inner.cq_ready() > 0,
+            writable: !self.inner.sq_full(),
+            pollhup: false,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+
+    fn add_poll_observer(
+        &self,
+        observer: alloc::sync::Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        self.inner.observers.add(observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &alloc::sync::Weak<dyn PollObserver>, key: usize) {
+        self.inner.observers.remove(observer, key);
+    }
+}
+
+/// Finds the part of the file of an io_uring instance mapped by `mmap` at
+/// `offset` on `fd`, or `None` if `fd` is not an io_uring instance.
+pub(crate) fn mmap_region(
+    fd: c_int,
+    offset: usize,
+    len: usize,
+) -> LinuxResult<Option<(Arc<File>, usize)>> {
+    let Ok(ring) = get_file_like(fd)?.into_any().downcast::<IoUring>() else {
+        return Ok(None);
+    };
+    let mem = &ring.inner.memory;
+    let (start, size) = match offset as u64 {
+        o if o == ctypes::IORING_OFF_SQ_RING as u64 || o == ctypes::IORING_OFF_CQ_RING as u64 => {
+            (0, mem.rings_size)
+        }
+        o if o == ctypes::IORING_OFF_SQES as u64 => (mem.sqes_offset, mem.sqes_size),
+        _ => return Err(LinuxError::EINVAL),
+    };
+    if len > size.next_multiple_of(PAGE_SIZE_4K) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Some((mem.file.clone(), start)))
+}
+
+/// Set up an io_uring instance with at least `entries` submission queue
+/// entries, and return its file descriptor.
+///
+/// `IORING_SETUP_CQSIZE` and `IORING_SETUP_CLAMP` are the supported flags,
+/// the offsets of the ring fields to `mmap` are written back to `params`.
+pub unsafe fn sys_io_uring_setup(entries: c_uint, params: *mut ctypes::io_uring_params) -> c_int {
+    debug!(
+        "sys_io_uring_setup <= entries: {}, params: {:p}",
+        entries, params
+    );
+    syscall_body!(sys_io_uring_setup, {
+        if params.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let mut p = *params;
+        let clamp = p.flags & ctypes::IORING_SETUP_CLAMP != 0;
+        if p.flags & !(ctypes::IORING_SETUP_CQSIZE | ctypes::IORING_SETUP_CLAMP) != 0
+            || p.resv.iter().any(|&r| r != 0)
+            || entries == 0
+            || (entries > IORING_MAX_ENTRIES && !clamp)
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let sq_entries = entries.min(IORING_MAX_ENTRIES).next_power_of_two();
+        let cq_entries = if p.flags & ctypes::IORING_SETUP_CQSIZE != 0 {
+            if p.cq_entries == 0 || (p.cq_entries > IORING_MAX_CQ_ENTRIES && !clamp) {
+                return Err(LinuxError::EINVAL);
+            }
+            let cq_entries = p.cq_entries.min(IORING_MAX_CQ_ENTRIES).next_power_of_two();
+            if cq_entries < sq_entries {
+                return Err(LinuxError::EINVAL);
+            }
+            cq_entries
+        } else {
+            2 * sq_entries
+        };
+
+        let inner = IoUringInner::new(sq_entries, cq_entries)?;
+        p.sq_entries = sq_entries;
+        p.cq_entries = cq_entries;
+        p.features = ctypes::IORING_FEAT_SINGLE_MMAP
+            | ctypes::IORING_FEAT_NODROP
+            | ctypes::IORING_FEAT_SUBMIT_STABLE
+            | ctypes::IORING_FEAT_RW_CUR_POS;
+        p.sq_off = ctypes::io_sqring_offsets {
+            head: SQ_HEAD as u32,
+            tail: SQ_TAIL as u32,
+            ring_mask: SQ_RING_MASK as u32,
+            ring_entries: SQ_RING_ENTRIES as u32,
+            flags: SQ_FLAGS as u32,
+            dropped: SQ_DROPPED as u32,
+            array: inner.sq_array as u32,
+            ..Default::default()
+        };
+        p.cq_off = ctypes::io_cqring_offsets {
+            head: CQ_HEAD as u32,
+            tail: CQ_TAIL as u32,
+            ring_mask: CQ_RING_MASK as u32,
+            ring_entries: CQ_RING_ENTRIES as u32,
+            overflow: CQ_OVERFLOW as u32,
+            cqes: CQ_CQES as u32,
+            flags: CQ_FLAGS as u32,
+            ..Default::default()
+        };
+        *params = p;
+        let inner = Arc::new(inner);
+        let dispatcher = inner.clone();
+        ruxtask::spawn(move || dispatcher.dispatch_resumed());
+        let ring = IoUring { inner };
+        add_file_like(
+            Arc::new(ring),
+            flags_to_options((ctypes::O_RDWR | ctypes::O_CLOEXEC) as c_int, 0),
+        )
+    })
+}
+
+/// Submit `to_submit` entries of the submission queue of the io_uring
+/// instance `fd`, and with `IORING_ENTER_GETEVENTS` wait until at least
+/// `min_complete` completions are in the completion queue.
+///
+/// Returns the number of consumed submission queue entries. The signal mask
+/// in `argp` is not applied while waiting.
+pub unsafe fn sys_io_uring_enter(
+    fd: c_uint,
+    to_submit: c_uint,
+    min_complete: c_uint,
+    flags: c_uint,
+    argp: *const c_void,
+    argsz: ctypes::size_t,
+) -> c_int {
+    debug!(
+        "sys_io_uring_enter <= fd: {}, to_submit: {}, min_complete: {}, flags: {:#x}, argp: {:p}, argsz: {}",
+        fd, to_submit, min_complete, flags, argp, argsz
+    );
+    syscall_body!(sys_io_uring_enter, {
+        if flags
+            & !(ctypes::IORING_ENTER_GETEVENTS
+                | ctypes::IORING_ENTER_SQ_WAKEUP
+                | ctypes::IORING_ENTER_SQ_WAIT)
+            != 0
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let ring = IoUring::from_fd(fd as c_int)?;
+        let inner = &ring.inner;
+        let submitted = if to_submit > 0 {
+            inner.submit(to_submit)
+        } else {
+            0
+        };
+        if flags & ctypes::IORING_ENTER_GETEVENTS != 0 && min_complete > 0 {
+            inner
+                .cq_wait_queue
+                .wait_until(|| inner.cq_ready() >= min_complete.min(inner.cq_entries));
+        }
+        Ok(submitted as c_int)
+    })
+}
+
+/// Register files or an eventfd to the io_uring instance `fd`, or probe its
+/// supported opcodes.
+pub unsafe fn sys_io_uring_register(
+    fd: c_uint,
+    opcode: c_uint,
+    arg: *mut c_void,
+    nr_args: c_uint,
+) -> c_int {
+    debug!(
+        "sys_io_uring_register <= fd: {}, opcode: {}, arg: {:p}, nr_args: {}",
+        fd, opcode, arg, nr_args
+    );
+    syscall_body!(sys_io_uring_register, {
+        let ring = IoUring::from_fd(fd as c_int)?;
+        let inner = &ring.inner;
+        match opcode {
+            ctypes::IORING_REGISTER_FILES => {
+                if nr_args == 0 || nr_args > IORING_MAX_FIXED_FILES {
+                    return Err(LinuxError::EINVAL);
+                }
+                if arg.is_null() {
+                    return Err(LinuxError::EFAULT);
+                }
+                let fds = core::slice::from_raw_parts(arg as *const c_int, nr_args as usize);
+                let new_files = fds
+                    .iter()
+                    .map(|&fd| match fd {
+                        -1 => Ok(None),
+                        fd => get_file_like(fd).map(Some),
+                    })
+                    .collect::<LinuxResult<Vec<_>>>()?;
+                let mut files = inner.files.lock();
+                if !files.is_empty() {
+                    return Err(LinuxError::EBUSY);
+                }
+                *files = new_files;
+            }
+            ctypes::IORING_UNREGISTER_FILES => {
+                let mut files = inner.files.lock();
+                if files.is_empty() {
+                    return Err(LinuxError::ENXIO);
+                }
+                files.clear();
+            }
+            ctypes::IORING_REGISTER_EVENTFD | ctypes::IORING_REGISTER_EVENTFD_ASYNC => {
+                if nr_args != 1 {
+                    return Err(LinuxError::EINVAL);
+                }
+                if arg.is_null() {
+                    return Err(LinuxError::EFAULT);
+                }
+                let file = get_file_like(*(arg as *const c_int))?
+                    .into_any()
+                    .downcast::<EventFd>()
+                    .map_err(|_| LinuxError::EINVAL)?;
+                let mut eventfd = inner.eventfd.lock();
+                if eventfd.is_some() {
+                    return Err(LinuxError::EBUSY);
+                }
+                *eventfd = Some(file);
+            }
+            ctypes::IORING_UNREGISTER_EVENTFD => {
+                inner.eventfd.lock().take().ok_or(LinuxError::ENXIO)?;
+            }
+            ctypes::IORING_REGISTER_PROBE => {
+                if nr_args > 256 {
+                    return Err(LinuxError::EINVAL);
+                }
+                if arg.is_null() {
+                    return Err(LinuxError::EFAULT);
+                }
+                let probe = arg as *mut ctypes::io_uring_probe;
+                let ops_len = nr_args.min(IORING_OP_LAST as c_uint) as usize;
+                core::ptr::write_bytes(probe, 0, 1);
+                (*probe).last_op = IORING_OP_LAST - 1;
+                (*probe).ops_len = ops_len as u8;
+                let ops = (probe as *mut u8).add(size_of::<ctypes::io_uring_probe>())
+                    as *mut ctypes::io_uring_probe_op;
+                let ops = core::slice::from_raw_parts_mut(ops, ops_len);
+                for (op, entry) in ops.iter_mut().enumerate() {
+                    *entry = ctypes::io_uring_probe_op {
+                        op: op as u8,
+                        flags: if SUPPORTED_OPS.contains(&(op as u8)) {
+                            ctypes::IO_URING_OP_SUPPORTED as u16
+                        } else {
+                            0
+                        },
+                        ..Default::default()
+                    };
+                }
+            }
+            _ => return Err(LinuxError::EINVAL),
+        }
+        Ok(0)
+    })
+}
@@ -29,18 +29,26 @@ pub struct ConsoleWinSize {
 }
 
 /// ioctl implementation,
-/// currently only support fd = 1
+/// the requests of ttys and other devices are passed to the file of `fd`
+/// first, falling back to the pretended console for the standard streams
 pub fn sys_ioctl(fd: c_int, request: usize, data: usize) -> c_int {
     debug!("sys_ioctl <= fd: {}, request: {}", fd, request);
     syscall_body!(sys_ioctl, {
-        match request {
+        let ret = match request {
             FIONBIO => {
                 unsafe {
                     get_file_like(fd)?.set_nonblocking(*(data as *const i32) > 0)?;
                 }
-                Ok(0)
+                return Ok(0);
             }
-            // TODO: a temporary solution for TIOCGWINSZ.
+            FIOCLEX => return Ok(0),
+            _ => get_file_like(fd)?.ioctl(request, data),
+        };
+        if ret != Err(LinuxError::ENOTTY) {
+            return Ok(ret? as c_int);
+        }
+        match request {
+            // without a tty, the standard streams pretend to be one
             TIOCGWINSZ => {
                 let winsize = data as *mut ConsoleWinSize;
                 unsafe {
@@ -67,8 +75,7 @@ pub fn sys_ioctl(fd: c_int, request: usize, data: usize) -> c_int {
                 }
                 Ok(0)
             }
-            FIOCLEX => Ok(0),
-            _ => Err(LinuxError::EINVAL),
+            _ => Err(LinuxError::ENOTTY),
         }
     })
 }
@@ -0,0 +1,328 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! System V IPC: shared memory segments, semaphore sets and message queues.
+//!
+//! Each kind of object lives in its own namespace, where it is found by a key
+//! and then referred to by an identifier. Objects carry an owner and
+//! permission bits, checked against the calling process as for files.
+
+pub mod msg;
+pub mod sem;
+pub mod shm;
+
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use axerrno::{LinuxError, LinuxResult};
+use core::{ffi::c_int, time::Duration};
+use spinlock::SpinNoIrq;
+
+use crate::ctypes;
+
+/// The key to always create a new object.
+const IPC_PRIVATE: ctypes::key_t = 0;
+
+/// Maximum number of objects in a namespace, which also separates the
+/// sequence number from the index in identifiers.
+const IPCMNI: usize = 32768;
+
+/// Permission bits required to read an object.
+const ACCESS_READ: u32 = 0o444;
+
+/// Permission bits required to change an object.
+const ACCESS_WRITE: u32 = 0o222;
+
+/// Ownership and permissions of an IPC object.
+#[derive(Clone, Copy)]
+struct IpcPerm {
+    key: ctypes::key_t,
+    uid: u32,
+    gid: u32,
+    cuid: u32,
+    cgid: u32,
+    /// permission bits, and the flags of the kind of object
+    mode: u32,
+    /// time of the creation or of the last `IPC_SET`
+    ctime: ctypes::time_t,
+}
+
+impl IpcPerm {
+    fn new(key: ctypes::key_t, flags: c_int) -> Self {
+        let uid = crate::sys_geteuid();
+        let gid = crate::sys_getegid();
+        Self {
+            key,
+            uid,
+            gid,
+            cuid: uid,
+            cgid: gid,
+            mode: flags as u32 & 0o777,
+            ctime: now(),
+        }
+    }
+
+    fn is_owner(&self) -> bool {
+        let euid = crate::sys_geteuid();
+        euid == self.uid || euid == self.cuid
+    }
+
+    /// Checks that the calling process has the `access` permission bits.
+    fn check(&self, access: u32) -> LinuxResult {
+        let egid = crate::sys_getegid();
+        let granted = if self.is_owner() {
+            self.mode >> 6
+        } else if egid == self.gid || egid == self.cgid {
+            self.mode >> 3
+        } else {
+            self.mode
+        };
+        let requested = (access >> 6) | (access >> 3) | access;
+        if requested & !granted & 0o7 != 0 {
+            return Err(LinuxError::EACCES);
+        }
+        Ok(())
+    }
+
+    fn to_ipc_perm(self, seq: u32) -> ctypes::ipc_perm {
+        ctypes::ipc_perm {
+            __ipc_perm_key: self.key,
+            uid: self.uid,
+            gid: self.gid,
+            cuid: self.cuid,
+            cgid: self.cgid,
+            mode: self.mode,
+            __ipc_perm_seq: seq as _,
+            ..Default::default()
+        }
+    }
+}
+
+struct IpcEntry<T> {
+    perm: IpcPerm,
+    seq: u32,
+    object: Arc<T>,
+}
+
+struct IpcTable<T> {
+    entries: BTreeMap<usize, IpcEntry<T>>,
+    next_seq: u32,
+}
+
+/// The objects of one kind, by index in identifiers.
+struct IpcNamespace<T> {
+    table: SpinNoIrq<IpcTable<T>>,
+    /// maximum number of objects
+    max: usize,
+}
+
+impl<T> IpcNamespace<T> {
+    const fn new(max: usize) -> Self {
+        Self {
+            table: SpinNoIrq::new(IpcTable {
+                entries: BTreeMap::new(),
+                next_seq: 0,
+            }),
+            max,
+        }
+    }
+
+    /// Gets the identifier of the object with `key`, checking that `validate`
+    /// accepts it, or creates it with `create` as `flags` tell.
+    fn get(
+        &self,
+        key: ctypes::key_t,
+        flags: c_int,
+        create: impl FnOnce() -> LinuxResult<T>,
+        validate: impl FnOnce(&T) -> LinuxResult,
+    ) -> LinuxResult<c_int> {
+        let flags = flags as u32;
+        let mut table = self.table.lock();
+        if key != IPC_PRIVATE {
+            if let Some((&index, entry)) = table.entries.iter().find(|(_, e)| e.perm.key == key) {
+                if flags & ctypes::IPC_CREAT != 0 && flags & ctypes::IPC_EXCL != 0 {
+                    return Err(LinuxError::EEXIST);
+                }
+                entry.perm.check(flags & 0o777)?;
+                validate(&entry.object)?;
+                return Ok(Self::id(index, entry.seq));
+            }
+            if flags & ctypes::IPC_CREAT == 0 {
+                return Err(LinuxError::ENOENT);
+            }
+        }
+        if table.entries.len() >= self.max {
+            return Err(LinuxError::ENOSPC);
+        }
+        let index = (0..)
+            .find(|index| !table.entries.contains_key(index))
+            .unwrap();
+        let seq = table.next_seq;
+        table.next_seq = (seq + 1) % (i32::MAX as usize / IPCMNI) as u32;
+        let entry = IpcEntry {
+            perm: IpcPerm::new(key, flags as c_int),
+            seq,
+            object: Arc::new(create()?),
+        };
+        table.entries.insert(index, entry);
+        Ok(Self::id(index, seq))
+    }
+
+    fn id(index: usize, seq: u32) -> c_int {
+        (seq as usize * IPCMNI + index) as c_int
+    }
+
+    /// Runs `f` on the object `id`, failing with `EINVAL` if there is none.
+    fn control<R>(
+        &self,
+        id: c_int,
+        f: impl FnOnce(&mut IpcPerm, &Arc<T>) -> LinuxResult<R>,
+    ) -> LinuxResult<R> {
+        if id < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let (index, seq) = (id as usize % IPCMNI, (id as usize / IPCMNI) as u32);
+        let mut table = self.table.lock();
+        let entry = table
+            .entries
+            .get_mut(&index)
+            .filter(|entry| entry.seq == seq)
+            .ok_or(LinuxError::EINVAL)?;
+        f(&mut entry.perm, &entry.object)
+    }
+
+    /// Gets the object `id` if the calling process has `access` to it.
+    fn obtain(&self, id: c_int, access: u32) -> LinuxResult<Arc<T>> {
+        self.control(id, |perm, object| {
+            perm.check(access)?;
+            Ok(object.clone())
+        })
+    }
+
+    /// Gets the permissions, the change time and the object `id`, for
+    /// `IPC_STAT`.
+    fn stat(&self, id: c_int) -> LinuxResult<(ctypes::ipc_perm, ctypes::time_t, Arc<T>)> {
+        let seq = (id / IPCMNI as c_int) as u32;
+        self.control(id, |perm, object| {
+            perm.check(ACCESS_READ)?;
+            Ok((perm.to_ipc_perm(seq), perm.ctime, object.clone()))
+        })
+    }
+
+    /// Changes the owner and the permission bits of the object `id`, which
+    /// only its owner may do, after `apply` accepts and applies the changes
+    /// of the kind of object.
+    fn set(
+        &self,
+        id: c_int,
+        new: &ctypes::ipc_perm,
+        apply: impl FnOnce(&T) -> LinuxResult,
+    ) -> LinuxResult<Arc<T>> {
+        self.control(id, |perm, object| {
+            if !perm.is_owner() {
+                return Err(LinuxError::EPERM);
+            }
+            apply(object)?;
+            perm.uid = new.uid;
+            perm.gid = new.gid;
+            perm.mode = (perm.mode & !0o777) | (new.mode & 0o777);
+            perm.ctime = now();
+            Ok(object.clone())
+        })
+    }
+
+    /// Removes the object `id` from the namespace, which only its owner may do.
+    fn remove(&self, id: c_int) -> LinuxResult<Arc<T>> {
+        self.control(id, |perm, _| {
+            if !perm.is_owner() {
+                return Err(LinuxError::EPERM);
+            }
+            Ok(())
+        })?;
+        let index = id as usize % IPCMNI;
+        Ok(self.table.lock().entries.remove(&index).unwrap().object)
+    }
+
+    /// Keeps only the objects for which `f` returns true.
+    fn retain(&self, mut f: impl FnMut(&IpcPerm, &T) -> bool) {
+        self.table
+            .lock()
+            .entries
+            .retain(|_, entry| f(&entry.perm, &entry.object));
+    }
+
+    /// Finds an object that `f` accepts.
+    fn find(&self, mut f: impl FnMut(&T) -> bool) -> Option<Arc<T>> {
+        let table = self.table.lock();
+        let entry = table.entries.values().find(|entry| f(&entry.object))?;
+        Some(entry.object.clone())
+    }
+
+    /// Gets all the objects.
+    fn objects(&self) -> Vec<Arc<T>> {
+        let table = self.table.lock();
+        table.entries.values().map(|e| e.object.clone()).collect()
+    }
+
+    /// Gets the highest index in use, as returned by the `IPC_INFO` commands.
+    fn max_index(&self) -> c_int {
+        let table = self.table.lock();
+        table
+            .entries
+            .keys()
+            .next_back()
+            .map_or(0, |&index| index as c_int)
+    }
+}
+
+/// The current wall time in seconds, for the times of objects.
+fn now() -> ctypes::time_t {
+    ruxhal::time::wall_time().as_secs() as _
+}
+
+/// Gets the relative timeout `timeout` as a deadline of the monotonic clock.
+fn deadline(timeout: *const ctypes::timespec) -> LinuxResult<Option<Duration>> {
+    if timeout.is_null() {
+        return Ok(None);
+    }
+    let timeout = unsafe { *timeout };
+    if timeout.tv_sec < 0 || !(0..1_000_000_000).contains(&timeout.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Some(ruxhal::time::current_time() + Duration::from(timeout)))
+}
+
+/// Blocks on `wait_queue` until `condition` holds, or until `deadline` of the
+/// monotonic clock if any. Returns false on timeout.
+///
+/// `condition` runs with the scheduler locked, so wakers must notify the
+/// queue without holding the locks it takes.
+fn wait_until(
+    wait_queue: &ruxtask::WaitQueue,
+    deadline: Option<Duration>,
+    mut condition: impl FnMut() -> bool,
+) -> bool {
+    let Some(deadline) = deadline else {
+        wait_queue.wait_until(condition);
+        return true;
+    };
+    #[cfg(feature = "irq")]
+    {
+        let now = ruxhal::time::current_time();
+        !wait_queue.wait_timeout_until(deadline.saturating_sub(now), condition)
+    }
+    #[cfg(not(feature = "irq"))]
+    loop {
+        if condition() {
+            return true;
+        }
+        if ruxhal::time::current_time() >= deadline {
+            return false;
+        }
+        crate::sys_sched_yield();
+    }
+}
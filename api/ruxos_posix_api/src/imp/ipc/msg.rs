@@ -0,0 +1,325 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! System V message queues.
+//!
+//! Messages carry a positive type, with which receivers choose the message
+//! they take from the queue.
+
+use alloc::{collections::VecDeque, vec::Vec};
+use axerrno::{LinuxError, LinuxResult};
+use core::ffi::{c_int, c_long, c_void};
+use core::mem::size_of;
+use spinlock::SpinNoIrq;
+
+use super::{now, wait_until, IpcNamespace, ACCESS_READ, ACCESS_WRITE};
+use crate::ctypes;
+
+/// Maximum size of a message.
+const MSGMAX: usize = 8192;
+/// Default maximum number of bytes in a queue.
+const MSGMNB: usize = 16384;
+/// Maximum number of queues.
+const MSGMNI: usize = 32000;
+
+static MSG_IDS: IpcNamespace<MsgQueue> = IpcNamespace::new(MSGMNI);
+
+struct Message {
+    mtype: c_long,
+    text: Vec<u8>,
+}
+
+struct MsgState {
+    messages: VecDeque<Message>,
+    /// number of bytes in the queue
+    cbytes: usize,
+    /// maximum number of bytes in the queue
+    qbytes: usize,
+    lspid: ctypes::pid_t,
+    lrpid: ctypes::pid_t,
+    stime: ctypes::time_t,
+    rtime: ctypes::time_t,
+    removed: bool,
+}
+
+impl MsgState {
+    fn has_room(&self, size: usize) -> bool {
+        self.cbytes + size <= self.qbytes && self.messages.len() < self.qbytes
+    }
+
+    /// Finds the message to receive for `msgtyp`, as its index.
+    fn find(&self, msgtyp: c_long, except: bool) -> Option<usize> {
+        let mut messages = self.messages.iter().enumerate();
+        match msgtyp {
+            0 => (!self.messages.is_empty()).then_some(0),
+            msgtyp if msgtyp > 0 => messages
+                .find(|(_, msg)| (msg.mtype == msgtyp) != except)
+                .map(|(i, _)| i),
+            // the lowest type not above the absolute value
+            msgtyp => messages
+                .filter(|(_, msg)| msg.mtype.unsigned_abs() <= msgtyp.unsigned_abs())
+                .min_by_key(|(_, msg)| msg.mtype)
+                .map(|(i, _)| i),
+        }
+    }
+}
+
+struct MsgQueue {
+    state: SpinNoIrq<MsgState>,
+    /// tasks waiting for messages or for room in the queue
+    wait_queue: ruxtask::WaitQueue,
+}
+
+impl MsgQueue {
+    fn new() -> Self {
+        Self {
+            state: SpinNoIrq::new(MsgState {
+                messages: VecDeque::new(),
+                cbytes: 0,
+                qbytes: MSGMNB,
+                lspid: 0,
+                lrpid: 0,
+                stime: 0,
+                rtime: 0,
+                removed: false,
+            }),
+            wait_queue: ruxtask::WaitQueue::new(),
+        }
+    }
+
+    /// Wakes up the waiters after a change of the queue.
+    ///
+    /// The state must not be locked, as waiters check it with the scheduler
+    /// locked.
+    fn wake(&self) {
+        self.wait_queue.notify_all(true);
+    }
+}
+
+/// Get the identifier of the message queue of `key`, or create it as
+/// `msgflg` tells.
+pub fn sys_msgget(key: ctypes::key_t, msgflg: c_int) -> c_int {
+    debug!("sys_msgget <= key: {:#x}, msgflg: {:#o}", key, msgflg);
+    syscall_body!(sys_msgget, {
+        MSG_IDS.get(key, msgflg, || Ok(MsgQueue::new()), |_| Ok(()))
+    })
+}
+
+/// Send the message `msgp` of `msgsz` bytes of text to the queue `msqid`,
+/// waiting for room in the queue unless `IPC_NOWAIT` is set.
+pub fn sys_msgsnd(
+    msqid: c_int,
+    msgp: *const c_void,
+    msgsz: ctypes::size_t,
+    msgflg: c_int,
+) -> c_int {
+    debug!(
+        "sys_msgsnd <= msqid: {}, msgp: {:p}, msgsz: {}, msgflg: {:#o}",
+        msqid, msgp, msgsz, msgflg
+    );
+    syscall_body!(sys_msgsnd, {
+        let size = msgsz as usize;
+        if size > MSGMAX {
+            return Err(LinuxError::EINVAL);
+        }
+        if msgp.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let mtype = unsafe { msgp.cast::<c_long>().read_unaligned() };
+        if mtype < 1 {
+            return Err(LinuxError::EINVAL);
+        }
+        let text = unsafe {
+            core::slice::from_raw_parts(msgp.cast::<u8>().add(size_of::<c_long>()), size)
+        };
+        let queue = MSG_IDS.obtain(msqid, ACCESS_WRITE)?;
+
+        loop {
+            let mut state = queue.state.lock();
+            if state.removed {
+                return Err(LinuxError::EIDRM);
+            }
+            if state.has_room(size) {
+                state.messages.push_back(Message {
+                    mtype,
+                    text: text.to_vec(),
+                });
+                state.cbytes += size;
+                state.lspid = crate::sys_getpid();
+                state.stime = now();
+                drop(state);
+                queue.wake();
+                return Ok(0);
+            }
+            if msgflg as u32 & ctypes::IPC_NOWAIT != 0 {
+                return Err(LinuxError::EAGAIN);
+            }
+            drop(state);
+            wait_until(&queue.wait_queue, None, || {
+                let state = queue.state.lock();
+                state.removed || state.has_room(size)
+            });
+        }
+    })
+}
+
+/// Receive a message of the queue `msqid` into `msgp`, with at most `msgsz`
+/// bytes of text.
+///
+/// A `msgtyp` of 0 takes the first message, a positive one the first message
+/// of that type, or of another type with `MSG_EXCEPT`, and a negative one the
+/// first message of the lowest type not above its absolute value.
+pub fn sys_msgrcv(
+    msqid: c_int,
+    msgp: *mut c_void,
+    msgsz: ctypes::size_t,
+    msgtyp: c_long,
+    msgflg: c_int,
+) -> ctypes::ssize_t {
+    debug!(
+        "sys_msgrcv <= msqid: {}, msgp: {:p}, msgsz: {}, msgtyp: {}, msgflg: {:#o}",
+        msqid, msgp, msgsz, msgtyp, msgflg
+    );
+    syscall_body!(sys_msgrcv, {
+        let flags = msgflg as u32;
+        if (msgsz as ctypes::ssize_t) < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if flags & ctypes::MSG_COPY != 0 {
+            return Err(LinuxError::ENOSYS);
+        }
+        if msgp.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let except = flags & ctypes::MSG_EXCEPT != 0;
+        let queue = MSG_IDS.obtain(msqid, ACCESS_READ)?;
+
+        let message = loop {
+            let mut state = queue.state.lock();
+            if state.removed {
+                return Err(LinuxError::EIDRM);
+            }
+            if let Some(i) = state.find(msgtyp, except) {
+                if state.messages[i].text.len() > msgsz as usize && flags & ctypes::MSG_NOERROR == 0
+                {
+                    return Err(LinuxError::E2BIG);
+                }
+                let message = state.messages.remove(i).unwrap();
+                state.cbytes -= message.text.len();
+                state.lrpid = crate::sys_getpid();
+                state.rtime = now();
+                drop(state);
+                queue.wake();
+                break message;
+            }
+            if flags & ctypes::IPC_NOWAIT != 0 {
+                return Err(LinuxError::ENOMSG);
+            }
+            drop(state);
+            wait_until(&queue.wait_queue, None, || {
+                let state = queue.state.lock();
+                state.removed || state.find(msgtyp, except).is_some()
+            });
+        };
+
+        // the text is truncated with `MSG_NOERROR`
+        let len = message.text.len().min(msgsz as usize);
+        unsafe {
+            msgp.cast::<c_long>().write_unaligned(message.mtype);
+            let dst = msgp.cast::<u8>().add(size_of::<c_long>());
+            dst.copy_from_nonoverlapping(message.text.as_ptr(), len);
+        }
+        Ok(len as ctypes::ssize_t)
+    })
+}
+
+/// Control the message queue `msqid`.
+pub fn sys_msgctl(msqid: c_int, cmd: c_int, buf: *mut ctypes::msqid_ds) -> c_int {
+    debug!(
+        "sys_msgctl <= msqid: {}, cmd: {}, buf: {:p}",
+        msqid, cmd, buf
+    );
+    syscall_body!(sys_msgctl, {
+        let cmd = cmd as u32 & !ctypes::IPC_64;
+        let uses_buf = matches!(
+            cmd,
+            ctypes::IPC_STAT | ctypes::IPC_SET | ctypes::IPC_INFO | ctypes::MSG_INFO
+        );
+        if uses_buf && buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        match cmd {
+            ctypes::IPC_INFO | ctypes::MSG_INFO => {
+                let mut info = ctypes::msginfo {
+                    msgpool: (MSGMNI * MSGMNB / 1024) as _,
+                    msgmap: MSGMNB as _,
+                    msgmax: MSGMAX as _,
+                    msgmnb: MSGMNB as _,
+                    msgmni: MSGMNI as _,
+                    msgssz: 16,
+                    msgtql: MSGMNB as _,
+                    msgseg: 0xffff,
+                };
+                if cmd == ctypes::MSG_INFO {
+                    let queues = MSG_IDS.objects();
+                    let (messages, bytes) = queues.iter().fold((0, 0), |(n, bytes), queue| {
+                        let state = queue.state.lock();
+                        (n + state.messages.len(), bytes + state.cbytes)
+                    });
+                    info.msgpool = queues.len() as _;
+                    info.msgmap = messages as _;
+                    info.msgtql = bytes as _;
+                }
+                unsafe { buf.cast::<ctypes::msginfo>().write(info) };
+                Ok(MSG_IDS.max_index())
+            }
+            ctypes::IPC_STAT => {
+                let (msg_perm, msg_ctime, queue) = MSG_IDS.stat(msqid)?;
+                let state = queue.state.lock();
+                let ds = ctypes::msqid_ds {
+                    msg_perm,
+                    msg_stime: state.stime,
+                    msg_rtime: state.rtime,
+                    msg_ctime,
+                    msg_cbytes: state.cbytes as _,
+                    msg_qnum: state.messages.len() as _,
+                    msg_qbytes: state.qbytes as _,
+                    msg_lspid: state.lspid,
+                    msg_lrpid: state.lrpid,
+                    ..Default::default()
+                };
+                drop(state);
+                unsafe { buf.write(ds) };
+                Ok(0)
+            }
+            ctypes::IPC_SET => {
+                let ds = unsafe { buf.read() };
+                let qbytes = ds.msg_qbytes as usize;
+                let queue = MSG_IDS.set(msqid, &ds.msg_perm, |queue| {
+                    let mut state = queue.state.lock();
+                    // raising the limit above the default needs privileges
+                    if qbytes > MSGMNB {
+                        return Err(LinuxError::EPERM);
+                    }
+                    state.qbytes = qbytes;
+                    Ok(())
+                })?;
+                queue.wake();
+                Ok(0)
+            }
+            ctypes::IPC_RMID => {
+                let queue = MSG_IDS.remove(msqid)?;
+                queue.state.lock().removed = true;
+                queue.wake();
+                Ok(0)
+            }
+            _ => Err(LinuxError::EINVAL),
+        }
+    })
+}
@@ -0,0 +1,436 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! System V semaphore sets.
+//!
+//! The operations of a `semop` call are applied all at once, or the caller
+//! waits until they can be. Operations with `SEM_UNDO` are reverted when the
+//! process exits.
+
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use axerrno::{LinuxError, LinuxResult};
+use core::ffi::c_int;
+use spinlock::SpinNoIrq;
+
+use super::{deadline, now, wait_until, IpcNamespace, ACCESS_READ, ACCESS_WRITE};
+use crate::ctypes;
+
+/// Maximum number of semaphores in a set.
+const SEMMSL: usize = 32000;
+/// Maximum number of semaphore sets.
+const SEMMNI: usize = 32000;
+/// Maximum number of semaphores in all sets.
+const SEMMNS: usize = SEMMSL * SEMMNI;
+/// Maximum number of operations in a `semop` call.
+const SEMOPM: usize = 500;
+/// Maximum value of a semaphore.
+const SEMVMX: i32 = 32767;
+/// Maximum adjustment of a semaphore on exit.
+const SEMAEM: i32 = SEMVMX;
+
+static SEM_IDS: IpcNamespace<SemSet> = IpcNamespace::new(SEMMNI);
+
+/// Adjustments of the operations with `SEM_UNDO`, by process and set.
+static SEM_UNDOS: SpinNoIrq<BTreeMap<(u64, c_int), Vec<i32>>> = SpinNoIrq::new(BTreeMap::new());
+
+#[derive(Clone, Default)]
+struct Sem {
+    val: i32,
+    /// process of the last operation
+    pid: ctypes::pid_t,
+    /// number of tasks waiting for the value to increase
+    ncnt: usize,
+    /// number of tasks waiting for the value to become zero
+    zcnt: usize,
+}
+
+impl Sem {
+    /// Gets the number of tasks waiting for the value to become zero if
+    /// `zero` is set, or to increase otherwise.
+    fn waiters(&mut self, zero: bool) -> &mut usize {
+        if zero {
+            &mut self.zcnt
+        } else {
+            &mut self.ncnt
+        }
+    }
+}
+
+/// Why the operations of a `semop` call cannot be applied.
+enum SemopError {
+    /// the operation at this index must wait
+    Blocked(usize),
+    /// a value would exceed `SEMVMX`
+    OutOfRange,
+}
+
+struct SemState {
+    sems: Vec<Sem>,
+    otime: ctypes::time_t,
+    removed: bool,
+}
+
+impl SemState {
+    /// Gets the values of the semaphores after the operations `ops`.
+    fn simulate(&self, ops: &[ctypes::sembuf]) -> Result<Vec<i32>, SemopError> {
+        let mut vals: Vec<i32> = self.sems.iter().map(|sem| sem.val).collect();
+        for (i, op) in ops.iter().enumerate() {
+            let val = &mut vals[op.sem_num as usize];
+            let new = *val + op.sem_op as i32;
+            if op.sem_op == 0 && *val != 0 || new < 0 {
+                return Err(SemopError::Blocked(i));
+            }
+            if new > SEMVMX {
+                return Err(SemopError::OutOfRange);
+            }
+            *val = new;
+        }
+        Ok(vals)
+    }
+}
+
+struct SemSet {
+    state: SpinNoIrq<SemState>,
+    wait_queue: ruxtask::WaitQueue,
+}
+
+impl SemSet {
+    fn new(nsems: usize) -> Self {
+        Self {
+            state: SpinNoIrq::new(SemState {
+                sems: vec![Sem::default(); nsems],
+                otime: 0,
+                removed: false,
+            }),
+            wait_queue: ruxtask::WaitQueue::new(),
+        }
+    }
+
+    fn nsems(&self) -> usize {
+        self.state.lock().sems.len()
+    }
+
+    /// Wakes up the waiters after a change of the values.
+    ///
+    /// The state must not be locked, as waiters check it with the scheduler
+    /// locked.
+    fn wake(&self) {
+        self.wait_queue.notify_all(true);
+    }
+}
+
+/// Forgets the adjustments of the semaphore `semnum` of the set `semid`, or of
+/// the whole set if `semnum` is `None`, when the values are set directly.
+fn clear_undos(semid: c_int, semnum: Option<usize>) {
+    let mut undos = SEM_UNDOS.lock();
+    undos.retain(|&(_, id), adjs| {
+        if id != semid {
+            return true;
+        }
+        match semnum {
+            Some(semnum) => {
+                adjs[semnum] = 0;
+                true
+            }
+            None => false,
+        }
+    });
+}
+
+/// Get the identifier of the semaphore set of `key`, or create it with
+/// `nsems` semaphores as `semflg` tells.
+pub fn sys_semget(key: ctypes::key_t, nsems: c_int, semflg: c_int) -> c_int {
+    debug!(
+        "sys_semget <= key: {:#x}, nsems: {}, semflg: {:#o}",
+        key, nsems, semflg
+    );
+    syscall_body!(sys_semget, {
+        if nsems < 0 || nsems as usize > SEMMSL {
+            return Err(LinuxError::EINVAL);
+        }
+        let nsems = nsems as usize;
+        SEM_IDS.get(
+            key,
+            semflg,
+            || {
+                if nsems == 0 {
+                    return Err(LinuxError::EINVAL);
+                }
+                Ok(SemSet::new(nsems))
+            },
+            |set| {
+                if nsems > set.nsems() {
+                    return Err(LinuxError::EINVAL);
+                }
+                Ok(())
+            },
+        )
+    })
+}
+
+/// Perform the operations `sops` on the semaphore set `semid` all at once.
+pub fn sys_semop(semid: c_int, sops: *mut ctypes::sembuf, nsops: ctypes::size_t) -> c_int {
+    sys_semtimedop(semid, sops, nsops, core::ptr::null())
+}
+
+/// Perform the operations `sops` on the semaphore set `semid` all at once,
+/// waiting at most `timeout` if it is not NULL.
+pub fn sys_semtimedop(
+    semid: c_int,
+    sops: *mut ctypes::sembuf,
+    nsops: ctypes::size_t,
+    timeout: *const ctypes::timespec,
+) -> c_int {
+    debug!(
+        "sys_semtimedop <= semid: {}, sops: {:p}, nsops: {}, timeout: {:p}",
+        semid, sops, nsops, timeout
+    );
+    syscall_body!(sys_semtimedop, {
+        let nsops = nsops as usize;
+        if nsops == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if nsops > SEMOPM {
+            return Err(LinuxError::E2BIG);
+        }
+        if sops.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let ops = unsafe { core::slice::from_raw_parts(sops, nsops) }.to_vec();
+        let deadline = deadline(timeout)?;
+        let alter = ops.iter().any(|op| op.sem_op != 0);
+        let access = if alter { ACCESS_WRITE } else { ACCESS_READ };
+        let set = SEM_IDS.obtain(semid, access)?;
+        let nsems = set.nsems();
+        if ops.iter().any(|op| op.sem_num as usize >= nsems) {
+            return Err(LinuxError::EFBIG);
+        }
+        let pid = crate::sys_getpid();
+
+        loop {
+            let mut state = set.state.lock();
+            if state.removed {
+                return Err(LinuxError::EIDRM);
+            }
+            let blocked = match state.simulate(&ops) {
+                Ok(vals) => {
+                    for op in &ops {
+                        let sem = &mut state.sems[op.sem_num as usize];
+                        sem.val = vals[op.sem_num as usize];
+                        sem.pid = pid;
+                    }
+                    state.otime = now();
+                    record_undos(semid, nsems, &ops);
+                    drop(state);
+                    if alter {
+                        set.wake();
+                    }
+                    return Ok(0);
+                }
+                Err(SemopError::OutOfRange) => return Err(LinuxError::ERANGE),
+                Err(SemopError::Blocked(i)) => ops[i],
+            };
+            if blocked.sem_flg as u32 & ctypes::IPC_NOWAIT != 0 {
+                return Err(LinuxError::EAGAIN);
+            }
+
+            let waiting_zero = blocked.sem_op == 0;
+            *state.sems[blocked.sem_num as usize].waiters(waiting_zero) += 1;
+            drop(state);
+            let done = wait_until(&set.wait_queue, deadline, || {
+                let state = set.state.lock();
+                state.removed || !matches!(state.simulate(&ops), Err(SemopError::Blocked(_)))
+            });
+            let mut state = set.state.lock();
+            *state.sems[blocked.sem_num as usize].waiters(waiting_zero) -= 1;
+            if !done {
+                return Err(LinuxError::EAGAIN);
+            }
+        }
+    })
+}
+
+/// Records the adjustments to revert the operations `ops` with `SEM_UNDO`
+/// on exit.
+fn record_undos(semid: c_int, nsems: usize, ops: &[ctypes::sembuf]) {
+    let undo_flag = ctypes::SEM_UNDO as i16;
+    if ops.iter().all(|op| op.sem_flg & undo_flag == 0) {
+        return;
+    }
+    let pid = ruxtask::current().process_id().as_u64();
+    let mut undos = SEM_UNDOS.lock();
+    let adjs = undos.entry((pid, semid)).or_insert_with(|| vec![0; nsems]);
+    for op in ops.iter().filter(|op| op.sem_flg & undo_flag != 0) {
+        let adj = &mut adjs[op.sem_num as usize];
+        *adj = (*adj - op.sem_op as i32).clamp(-SEMAEM, SEMAEM);
+    }
+}
+
+/// Reverts the operations with `SEM_UNDO` of the exiting process.
+pub(crate) fn exit_sem() {
+    let pid = ruxtask::current().process_id().as_u64();
+    let undos: Vec<(c_int, Vec<i32>)> = {
+        let mut undos = SEM_UNDOS.lock();
+        let ids: Vec<c_int> = undos
+            .range((pid, c_int::MIN)..=(pid, c_int::MAX))
+            .map(|(&(_, id), _)| id)
+            .collect();
+        ids.into_iter()
+            .map(|id| (id, undos.remove(&(pid, id)).unwrap()))
+            .collect()
+    };
+    for (semid, adjs) in undos {
+        let Ok(set) = SEM_IDS.control(semid, |_, set| Ok(set.clone())) else {
+            continue;
+        };
+        let mut state = set.state.lock();
+        for (sem, adj) in state.sems.iter_mut().zip(adjs) {
+            if adj != 0 {
+                sem.val = (sem.val + adj).clamp(0, SEMVMX);
+                sem.pid = pid as _;
+            }
+        }
+        drop(state);
+        set.wake();
+    }
+}
+
+/// Control the semaphore set `semid`, or its semaphore `semnum`.
+///
+/// `arg` is the `union semun` argument of the command.
+pub fn sys_semctl(semid: c_int, semnum: c_int, cmd: c_int, arg: usize) -> c_int {
+    debug!(
+        "sys_semctl <= semid: {}, semnum: {}, cmd: {}, arg: {:#x}",
+        semid, semnum, cmd, arg
+    );
+    syscall_body!(sys_semctl, {
+        let cmd = cmd as u32 & !ctypes::IPC_64;
+        let uses_buf = matches!(
+            cmd,
+            ctypes::IPC_STAT
+                | ctypes::IPC_SET
+                | ctypes::IPC_INFO
+                | ctypes::SEM_INFO
+                | ctypes::GETALL
+                | ctypes::SETALL
+        );
+        if uses_buf && arg == 0 {
+            return Err(LinuxError::EFAULT);
+        }
+        match cmd {
+            ctypes::IPC_INFO | ctypes::SEM_INFO => {
+                let mut info = ctypes::seminfo {
+                    semmap: SEMMNS as _,
+                    semmni: SEMMNI as _,
+                    semmns: SEMMNS as _,
+                    semmnu: SEMMNS as _,
+                    semmsl: SEMMSL as _,
+                    semopm: SEMOPM as _,
+                    semume: SEMOPM as _,
+                    semusz: 0,
+                    semvmx: SEMVMX,
+                    semaem: SEMAEM,
+                };
+                if cmd == ctypes::SEM_INFO {
+                    let sets = SEM_IDS.objects();
+                    info.semusz = sets.len() as _;
+                    info.semaem = sets.iter().map(|set| set.nsems()).sum::<usize>() as _;
+                }
+                unsafe { (arg as *mut ctypes::seminfo).write(info) };
+                Ok(SEM_IDS.max_index())
+            }
+            ctypes::IPC_STAT => {
+                let (sem_perm, sem_ctime, set) = SEM_IDS.stat(semid)?;
+                let state = set.state.lock();
+                let ds = ctypes::semid_ds {
+                    sem_perm,
+                    sem_otime: state.otime,
+                    sem_ctime,
+                    sem_nsems: state.sems.len() as _,
+                    ..Default::default()
+                };
+                drop(state);
+                unsafe { (arg as *mut ctypes::semid_ds).write(ds) };
+                Ok(0)
+            }
+            ctypes::IPC_SET => {
+                let ds = unsafe { (arg as *const ctypes::semid_ds).read() };
+                SEM_IDS.set(semid, &ds.sem_perm, |_| Ok(()))?;
+                Ok(0)
+            }
+            ctypes::IPC_RMID => {
+                let set = SEM_IDS.remove(semid)?;
+                set.state.lock().removed = true;
+                set.wake();
+                clear_undos(semid, None);
+                Ok(0)
+            }
+            ctypes::GETVAL | ctypes::GETPID | ctypes::GETNCNT | ctypes::GETZCNT => {
+                let set = SEM_IDS.obtain(semid, ACCESS_READ)?;
+                let state = set.state.lock();
+                let sem = usize::try_from(semnum)
+                    .ok()
+                    .and_then(|semnum| state.sems.get(semnum))
+                    .ok_or(LinuxError::EINVAL)?;
+                Ok(match cmd {
+                    ctypes::GETVAL => sem.val,
+                    ctypes::GETPID => sem.pid,
+                    ctypes::GETNCNT => sem.ncnt as c_int,
+                    _ => sem.zcnt as c_int,
+                })
+            }
+            ctypes::GETALL => {
+                let set = SEM_IDS.obtain(semid, ACCESS_READ)?;
+                let state = set.state.lock();
+                let dst = arg as *mut u16;
+                for (i, sem) in state.sems.iter().enumerate() {
+                    unsafe { dst.add(i).write(sem.val as u16) };
+                }
+                Ok(0)
+            }
+            ctypes::SETVAL | ctypes::SETALL => {
+                let set = SEM_IDS.control(semid, |perm, set| {
+                    perm.check(ACCESS_WRITE)?;
+                    perm.ctime = now();
+                    Ok(set.clone())
+                })?;
+                let mut state = set.state.lock();
+                let pid = crate::sys_getpid();
+                if cmd == ctypes::SETVAL {
+                    let val = arg as c_int;
+                    let sem = usize::try_from(semnum)
+                        .ok()
+                        .filter(|&semnum| semnum < state.sems.len())
+                        .ok_or(LinuxError::EINVAL)?;
+                    if !(0..=SEMVMX).contains(&val) {
+                        return Err(LinuxError::ERANGE);
+                    }
+                    state.sems[sem].val = val;
+                    state.sems[sem].pid = pid;
+                    clear_undos(semid, Some(sem));
+                } else {
+                    let vals =
+                        unsafe { core::slice::from_raw_parts(arg as *const u16, state.sems.len()) };
+                    if vals.iter().any(|&val| val as i32 > SEMVMX) {
+                        return Err(LinuxError::ERANGE);
+                    }
+                    for (sem, &val) in state.sems.iter_mut().zip(vals) {
+                        sem.val = val as i32;
+                        sem.pid = pid;
+                    }
+                    clear_undos(semid, None);
+                }
+                drop(state);
+                set.wake();
+                Ok(0)
+            }
+            _ => Err(LinuxError::EINVAL),
+        }
+    })
+}
@@ -0,0 +1,369 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! System V shared memory segments.
+//!
+//! A segment is an anonymous memory file, attached with shared file mappings
+//! that fault in through the common page fault handler, so that all the
+//! processes attaching it use the same pages.
+
+use alloc::{
+    collections::BTreeSet,
+    format,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::{LinuxError, LinuxResult};
+use core::ffi::{c_int, c_void};
+use memory_addr::PAGE_SIZE_4K;
+use ruxtask::{
+    fs::File,
+    task::{TaskState, PROCESS_MAP},
+};
+use spinlock::SpinNoIrq;
+
+use super::{now, IpcNamespace, ACCESS_READ, ACCESS_WRITE, IPC_PRIVATE};
+use crate::{ctypes, imp::mmap::mmap_shared_file};
+
+/// Maximum size of a segment.
+const SHMMAX: usize = usize::MAX - (1 << 24);
+/// Minimum size of a segment.
+const SHMMIN: usize = 1;
+/// Maximum number of segments.
+const SHMMNI: usize = 4096;
+/// Maximum number of pages in all segments.
+const SHMALL: usize = usize::MAX - (1 << 24);
+
+static SHM_IDS: IpcNamespace<ShmSegment> = IpcNamespace::new(SHMMNI);
+
+struct ShmTimes {
+    atime: ctypes::time_t,
+    dtime: ctypes::time_t,
+    lpid: ctypes::pid_t,
+}
+
+struct ShmSegment {
+    /// memory file of the segment, dropped once the segment is removed so that
+    /// the file is freed with the last attachment
+    file: SpinNoIrq<Option<Arc<File>>>,
+    weak_file: Weak<File>,
+    /// node of the file, to find the mappings of the segment
+    node_id: usize,
+    size: usize,
+    cpid: ctypes::pid_t,
+    times: SpinNoIrq<ShmTimes>,
+}
+
+impl ShmSegment {
+    fn new(key: ctypes::key_t, size: usize) -> LinuxResult<Self> {
+        let name = format!("SYSV{:08x}", key);
+        let file = ruxfs::memfd::create(&name, false)?;
+        file.truncate(size.next_multiple_of(PAGE_SIZE_4K) as u64)?;
+        let node_id = file.node_id();
+        let file = Arc::new(File::new(file));
+        Ok(Self {
+            weak_file: Arc::downgrade(&file),
+            file: SpinNoIrq::new(Some(file)),
+            node_id,
+            size,
+            cpid: crate::sys_getpid(),
+            times: SpinNoIrq::new(ShmTimes {
+                atime: 0,
+                dtime: 0,
+                lpid: 0,
+            }),
+        })
+    }
+
+    fn is_mapped_by(&self, file: &File) -> bool {
+        file.inner.read().node_id() == self.node_id
+    }
+
+    /// Gets the memory file, unless the segment was removed and is no longer
+    /// attached.
+    fn file(&self) -> Option<Arc<File>> {
+        self.file
+            .lock()
+            .clone()
+            .or_else(|| self.weak_file.upgrade())
+    }
+
+    /// Counts the attachments of the segment in live processes, where an
+    /// attachment split by `munmap` or `mprotect` counts once.
+    fn nattch(&self) -> usize {
+        PROCESS_MAP
+            .lock()
+            .values()
+            .filter(|task| task.state() != TaskState::Exited)
+            .map(|task| {
+                let vma_map = task.mm.vma_map.lock();
+                // the areas of an attachment map the segment at the same base
+                vma_map
+                    .values()
+                    .filter(|vma| vma.file.as_deref().is_some_and(|f| self.is_mapped_by(f)))
+                    .map(|vma| vma.start_addr - vma.offset)
+                    .collect::<BTreeSet<_>>()
+                    .len()
+            })
+            .sum()
+    }
+
+    fn touch(&self, attach: bool) {
+        let mut times = self.times.lock();
+        if attach {
+            times.atime = now();
+        } else {
+            times.dtime = now();
+        }
+        times.lpid = crate::sys_getpid();
+    }
+}
+
+/// Drops the identifiers of the removed segments whose memory was freed with
+/// their last attachment.
+fn destroy_unused() {
+    SHM_IDS.retain(|_, segment| segment.weak_file.strong_count() > 0);
+}
+
+/// Detaches all the segments attached by the current process, as it exits or
+/// runs another program.
+pub(crate) fn exit_shm() {
+    let nodes: Vec<usize> = SHM_IDS.objects().iter().map(|s| s.node_id).collect();
+    let curr = ruxtask::current();
+    let ranges: Vec<(usize, usize)> = curr
+        .mm
+        .vma_map
+        .lock()
+        .values()
+        .filter(|vma| {
+            vma.file
+                .as_deref()
+                .is_some_and(|f| nodes.contains(&f.inner.read().node_id()))
+        })
+        .map(|vma| (vma.start_addr, vma.end_addr))
+        .collect();
+    for (start, end) in ranges {
+        crate::sys_munmap(start as *mut c_void, end - start);
+    }
+    destroy_unused();
+}
+
+/// Get the identifier of the shared memory segment of `key`, or create it as
+/// `shmflg` tells.
+pub fn sys_shmget(key: ctypes::key_t, size: ctypes::size_t, shmflg: c_int) -> c_int {
+    debug!(
+        "sys_shmget <= key: {:#x}, size: {}, shmflg: {:#o}",
+        key, size, shmflg
+    );
+    syscall_body!(sys_shmget, {
+        let size = size as usize;
+        destroy_unused();
+        SHM_IDS.get(
+            key,
+            shmflg,
+            || {
+                if !(SHMMIN..=SHMMAX).contains(&size) {
+                    return Err(LinuxError::EINVAL);
+                }
+                ShmSegment::new(key, size)
+            },
+            |segment| {
+                if size > segment.size {
+                    return Err(LinuxError::EINVAL);
+                }
+                Ok(())
+            },
+        )
+    })
+}
+
+/// Attach the shared memory segment `shmid` at `shmaddr`, or at an address
+/// chosen by the system if it is NULL.
+pub fn sys_shmat(shmid: c_int, shmaddr: *const c_void, shmflg: c_int) -> *mut c_void {
+    debug!(
+        "sys_shmat <= shmid: {}, shmaddr: {:p}, shmflg: {:#o}",
+        shmid, shmaddr, shmflg
+    );
+    syscall_body!(sys_shmat, {
+        let flags = shmflg as u32;
+        let (mut access, mut prot) = if flags & ctypes::SHM_RDONLY != 0 {
+            (ACCESS_READ, ctypes::PROT_READ)
+        } else {
+            (
+                ACCESS_READ | ACCESS_WRITE,
+                ctypes::PROT_READ | ctypes::PROT_WRITE,
+            )
+        };
+        if flags & ctypes::SHM_EXEC != 0 {
+            access |= 0o111;
+            prot |= ctypes::PROT_EXEC;
+        }
+        let mut addr = shmaddr as usize;
+        let shmlba = ctypes::SHMLBA as usize;
+        if addr % shmlba != 0 {
+            if flags & ctypes::SHM_RND == 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            addr -= addr % shmlba;
+        }
+        let remap = flags & ctypes::SHM_REMAP != 0;
+        let start = match addr {
+            0 if remap => return Err(LinuxError::EINVAL),
+            0 => None,
+            addr => Some(addr),
+        };
+
+        let segment = SHM_IDS.obtain(shmid, access)?;
+        let file = segment.file().ok_or(LinuxError::EINVAL)?;
+        let vaddr = mmap_shared_file(start, segment.size, prot, file, 0, remap)?;
+        segment.touch(true);
+        Ok(vaddr as *mut c_void)
+    })
+}
+
+/// Detach the shared memory segment attached at `shmaddr`.
+pub fn sys_shmdt(shmaddr: *const c_void) -> c_int {
+    debug!("sys_shmdt <= shmaddr: {:p}", shmaddr);
+    syscall_body!(sys_shmdt, {
+        let addr = shmaddr as usize;
+        let curr = ruxtask::current();
+        let file = curr
+            .mm
+            .vma_map
+            .lock()
+            .get(&addr)
+            .filter(|vma| vma.is_shared() && vma.offset == 0)
+            .and_then(|vma| vma.file.clone())
+            .ok_or(LinuxError::EINVAL)?;
+        let segment = SHM_IDS
+            .find(|segment| segment.is_mapped_by(&file))
+            .ok_or(LinuxError::EINVAL)?;
+
+        // the attachment may have been split by `munmap` or `mprotect`
+        let end = addr + segment.size.next_multiple_of(PAGE_SIZE_4K);
+        let ranges: Vec<(usize, usize)> = curr
+            .mm
+            .vma_map
+            .lock()
+            .range(addr..end)
+            .filter(|(_, vma)| vma.file.as_deref().is_some_and(|f| segment.is_mapped_by(f)))
+            .map(|(&start, vma)| (start, vma.end_addr))
+            .collect();
+        for (start, end) in ranges {
+            crate::sys_munmap(start as *mut c_void, end - start);
+        }
+        segment.touch(false);
+        drop(segment);
+        destroy_unused();
+        Ok(0)
+    })
+}
+
+/// Control the shared memory segment `shmid`.
+///
+/// Removed segments are freed with their last attachment.
+pub fn sys_shmctl(shmid: c_int, cmd: c_int, buf: *mut ctypes::shmid_ds) -> c_int {
+    debug!(
+        "sys_shmctl <= shmid: {}, cmd: {}, buf: {:p}",
+        shmid, cmd, buf
+    );
+    syscall_body!(sys_shmctl, {
+        let cmd = cmd as u32 & !ctypes::IPC_64;
+        let uses_buf = matches!(
+            cmd,
+            ctypes::IPC_STAT | ctypes::IPC_SET | ctypes::IPC_INFO | ctypes::SHM_INFO
+        );
+        if uses_buf && buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        destroy_unused();
+        match cmd {
+            ctypes::IPC_INFO => {
+                let info = ctypes::shminfo {
+                    shmmax: SHMMAX as _,
+                    shmmin: SHMMIN as _,
+                    shmmni: SHMMNI as _,
+                    shmseg: SHMMNI as _,
+                    shmall: SHMALL as _,
+                    ..Default::default()
+                };
+                unsafe { buf.cast::<ctypes::shminfo>().write(info) };
+                Ok(SHM_IDS.max_index())
+            }
+            ctypes::SHM_INFO => {
+                let segments = SHM_IDS.objects();
+                let pages = segments
+                    .iter()
+                    .map(|s| s.size.div_ceil(PAGE_SIZE_4K))
+                    .sum::<usize>();
+                let info = ctypes::shm_info {
+                    __used_ids: segments.len() as _,
+                    shm_tot: pages as _,
+                    shm_rss: pages as _,
+                    ..Default::default()
+                };
+                unsafe { buf.cast::<ctypes::shm_info>().write(info) };
+                Ok(SHM_IDS.max_index())
+            }
+            ctypes::IPC_STAT => {
+                let (shm_perm, shm_ctime, segment) = SHM_IDS.stat(shmid)?;
+                let shm_nattch = segment.nattch() as _;
+                let times = segment.times.lock();
+                let ds = ctypes::shmid_ds {
+                    shm_perm,
+                    shm_segsz: segment.size as _,
+                    shm_atime: times.atime,
+                    shm_dtime: times.dtime,
+                    shm_ctime,
+                    shm_cpid: segment.cpid,
+                    shm_lpid: times.lpid,
+                    shm_nattch,
+                    ..Default::default()
+                };
+                unsafe { buf.write(ds) };
+                Ok(0)
+            }
+            ctypes::IPC_SET => {
+                let ds = unsafe { buf.read() };
+                SHM_IDS.set(shmid, &ds.shm_perm, |_| Ok(()))?;
+                Ok(0)
+            }
+            ctypes::IPC_RMID => {
+                SHM_IDS.control(shmid, |perm, segment| {
+                    if !perm.is_owner() {
+                        return Err(LinuxError::EPERM);
+                    }
+                    // only attaching by identifier is still possible
+                    perm.key = IPC_PRIVATE;
+                    perm.mode |= ctypes::SHM_DEST;
+                    segment.file.lock().take();
+                    Ok(())
+                })?;
+                destroy_unused();
+                Ok(0)
+            }
+            ctypes::SHM_LOCK | ctypes::SHM_UNLOCK => {
+                // segments are never swapped out
+                SHM_IDS.control(shmid, |perm, _| {
+                    if !perm.is_owner() {
+                        return Err(LinuxError::EPERM);
+                    }
+                    if cmd == ctypes::SHM_LOCK {
+                        perm.mode |= ctypes::SHM_LOCKED;
+                    } else {
+                        perm.mode &= !ctypes::SHM_LOCKED;
+                    }
+                    Ok(())
+                })?;
+                Ok(0)
+            }
+            _ => Err(LinuxError::EINVAL),
+        }
+    })
+}
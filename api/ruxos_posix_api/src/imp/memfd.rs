@@ -0,0 +1,146 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Shareable memory objects: anonymous files of `memfd_create` with their
+//! seals, and POSIX shared memory objects, which are files in the tmpfs
+//! mounted on `/dev/shm`.
+
+use alloc::format;
+use core::ffi::{c_char, c_int, c_uint, CStr};
+
+use axerrno::{LinuxError, LinuxResult};
+use ruxfs::{fops::FileSeals, AbsPath};
+use ruxtask::fs::File;
+#[cfg(feature = "paging")]
+use ruxtask::task::{TaskState, PROCESS_MAP};
+
+use crate::ctypes;
+use crate::imp::fs::{flags_to_options, open_path};
+
+/// Maximum length of a memfd name, without the `memfd:` prefix.
+const MFD_NAME_MAX: usize = 249;
+
+/// Directory of POSIX shared memory objects.
+const SHM_DIR: &str = "/dev/shm";
+
+/// Maximum length of a POSIX shared memory object name.
+const SHM_NAME_MAX: usize = 255;
+
+/// Create an anonymous file in memory and return a file descriptor for it.
+///
+/// The file can be mapped with `MAP_SHARED` to share memory with forked
+/// children. `name` is only used for debugging.
+pub fn sys_memfd_create(name: *const c_char, flags: c_uint) -> c_int {
+    debug!("sys_memfd_create <= name: {:p}, flags: {:#x}", name, flags);
+    syscall_body!(sys_memfd_create, {
+        if name.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let name = unsafe { CStr::from_ptr(name) }
+            .to_str()
+            .map_err(|_| LinuxError::EINVAL)?;
+        if name.len() > MFD_NAME_MAX {
+            return Err(LinuxError::EINVAL);
+        }
+        // huge pages are not supported
+        if flags & !(ctypes::MFD_CLOEXEC | ctypes::MFD_ALLOW_SEALING) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let file = ruxfs::memfd::create(name, flags & ctypes::MFD_ALLOW_SEALING != 0)?;
+        let cloexec = if flags & ctypes::MFD_CLOEXEC != 0 {
+            ctypes::O_CLOEXEC
+        } else {
+            0
+        };
+        File::new(file).add_to_fd_table(flags_to_options((ctypes::O_RDWR | cloexec) as _, 0))
+    })
+}
+
+/// Whether a live process maps the file with a writable shared mapping.
+#[cfg(feature = "paging")]
+fn is_mapped_writable(file: &File) -> bool {
+    let node_id = file.inner.read().node_id();
+    PROCESS_MAP
+        .lock()
+        .values()
+        .filter(|task| task.state() != TaskState::Exited)
+        .any(|task| {
+            task.mm.vma_map.lock().values().any(|vma| {
+                vma.is_shared()
+                    && vma.prot & ctypes::PROT_WRITE != 0
+                    && vma
+                        .file
+                        .as_ref()
+                        .is_some_and(|f| f.inner.read().node_id() == node_id)
+            })
+        })
+}
+
+/// Handle the `F_ADD_SEALS` and `F_GET_SEALS` commands of `fcntl`.
+pub(crate) fn fcntl_seals(fd: c_int, cmd: u32, arg: usize) -> LinuxResult<c_int> {
+    let file = File::from_fd(fd)?;
+    if cmd == ctypes::F_GET_SEALS {
+        return Ok(file.inner.read().get_seals()?.bits() as c_int);
+    }
+    let seals = FileSeals::from_bits(arg as u32).ok_or(LinuxError::EINVAL)?;
+    // writable shared mappings could still change the content
+    #[cfg(feature = "paging")]
+    if seals.contains(FileSeals::WRITE) && is_mapped_writable(&file) {
+        return Err(LinuxError::EBUSY);
+    }
+    file.inner.read().add_seals(seals)?;
+    Ok(0)
+}
+
+/// Get the path of the POSIX shared memory object `name`.
+fn shm_path(name: *const c_char) -> LinuxResult<AbsPath<'static>> {
+    if name.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let name = unsafe { CStr::from_ptr(name) }
+        .to_str()
+        .map_err(|_| LinuxError::EINVAL)?
+        .trim_start_matches('/');
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        return Err(LinuxError::EINVAL);
+    }
+    if name.len() > SHM_NAME_MAX {
+        return Err(LinuxError::ENAMETOOLONG);
+    }
+    Ok(AbsPath::new_owned(format!("{}/{}", SHM_DIR, name)))
+}
+
+/// Create or open the POSIX shared memory object `name`.
+///
+/// The descriptor is always closed on `exec`.
+pub fn sys_shm_open(name: *const c_char, oflag: c_int, mode: ctypes::mode_t) -> c_int {
+    debug!(
+        "sys_shm_open <= name: {:p}, oflag: {:#o}, mode: {:#o}",
+        name, oflag, mode
+    );
+    syscall_body!(sys_shm_open, {
+        let path = shm_path(name)?;
+        open_path(&path, oflag | ctypes::O_CLOEXEC as c_int, mode)
+    })
+}
+
+/// Remove the POSIX shared memory object `name`.
+///
+/// The memory is freed once the object is no longer opened or mapped.
+pub fn sys_shm_unlink(name: *const c_char) -> c_int {
+    debug!("sys_shm_unlink <= name: {:p}", name);
+    syscall_body!(sys_shm_unlink, {
+        let path = shm_path(name)?;
+        if ruxfs::fops::get_attr(&path)?.is_dir() {
+            return Err(LinuxError::EISDIR);
+        }
+        ruxfs::fops::remove_file(&path)?;
+        Ok(0)
+    })
+}
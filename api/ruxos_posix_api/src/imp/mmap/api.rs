@@ -9,7 +9,7 @@
 
 use crate::ctypes;
 use alloc::vec::Vec;
-use axerrno::LinuxError;
+use axerrno::{LinuxError, LinuxResult};
 use core::{
     ffi::{c_int, c_void},
     ops::Bound,
@@ -27,9 +27,9 @@ use ruxtask::vma::Vma;
 
 #[cfg(feature = "fs")]
 use {
-    super::utils::{release_pages_swaped, write_into},
+    super::utils::{add_device_pages, is_write_sealed, release_pages_swaped},
     alloc::sync::Arc,
-    ruxtask::vma::FileInfo,
+    ruxtask::{fs::File, vma::DeviceMapping},
 };
 
 /// Creates a new mapping in the virtual address space of the calling process.
@@ -79,7 +79,53 @@ pub fn sys_mmap(
             fid
         };
 
+        // the rings of an io_uring instance are shared pages of its own file
+        #[cfg(feature = "io_uring")]
+        if fid >= 0 {
+            if let Some((file, offset)) = crate::imp::io_uring::mmap_region(fid, offset, len)? {
+                if flags & ctypes::MAP_SHARED == 0 {
+                    return Err(LinuxError::EINVAL);
+                }
+                let fixed = flags & ctypes::MAP_FIXED != 0;
+                let start = (fixed || start != 0).then_some(start);
+                let vaddr = mmap_shared_file(start, len, prot, file, offset, fixed)?;
+                return Ok(vaddr as *mut c_void);
+            }
+        }
+
+        #[cfg(feature = "fs")]
+        let mut device = None;
+        #[cfg(feature = "fs")]
+        if fid >= 0 {
+            if !VirtAddr::from(offset).is_aligned(PAGE_SIZE_4K) {
+                return Err(LinuxError::EINVAL);
+            }
+            let file = File::from_fd(fid).map_err(|e| match e {
+                LinuxError::EBADF => e,
+                _ => LinuxError::ENODEV,
+            })?;
+            if flags & ctypes::MAP_SHARED != 0
+                && prot & ctypes::PROT_WRITE != 0
+                && is_write_sealed(&file)
+            {
+                return Err(LinuxError::EPERM);
+            }
+            // the memory of devices such as framebuffers is mapped as it is
+            if let Some(size) = add_device_pages(&file) {
+                // released when the mapping fails or is gone
+                let mapping = DeviceMapping::new(file);
+                if offset + len > size.next_multiple_of(PAGE_SIZE_4K) {
+                    return Err(LinuxError::EINVAL);
+                }
+                device = Some(Arc::new(mapping));
+            }
+        }
+
         let mut new = Vma::new(fid, offset, prot, flags);
+        #[cfg(feature = "fs")]
+        {
+            new.device = device;
+        }
         let binding_task = current();
         let mut vma_map = binding_task.mm.vma_map.lock();
         let addr_condition = if start == 0 { None } else { Some(start) };
@@ -102,6 +148,40 @@ pub fn sys_mmap(
     })
 }
 
+/// Maps `len` bytes of `file` from `offset` shared into the current process,
+/// for memory objects that have no file descriptor of their own, like SysV
+/// shared memory segments.
+///
+/// The mapping is placed exactly at `start` if given, replacing the mappings
+/// there only if `replace` is set, or in a free region otherwise.
+#[cfg(feature = "fs")]
+pub(crate) fn mmap_shared_file(
+    start: Option<usize>,
+    len: usize,
+    prot: u32,
+    file: Arc<File>,
+    offset: usize,
+    replace: bool,
+) -> LinuxResult<usize> {
+    let len = VirtAddr::from(len).align_up_4k().as_usize();
+    let binding_task = current();
+    let mut vma_map = binding_task.mm.vma_map.lock();
+    let vaddr = match start {
+        Some(start) if replace => {
+            snatch_fixed_region(&mut vma_map, start, len).ok_or(LinuxError::EINVAL)?
+        }
+        Some(start) => find_free_region(&vma_map, Some(start), len)
+            .filter(|&vaddr| vaddr == start)
+            .ok_or(LinuxError::EINVAL)?,
+        None => find_free_region(&vma_map, None, len).ok_or(LinuxError::ENOMEM)?,
+    };
+    let mut new = Vma::with_file(file, offset, prot, ctypes::MAP_SHARED);
+    new.start_addr = vaddr;
+    new.end_addr = vaddr + len;
+    vma_map.insert(vaddr, new);
+    Ok(vaddr)
+}
+
 /// Deletes the mappings for the specified address range
 pub fn sys_munmap(start: *mut c_void, len: ctypes::size_t) -> c_int {
     debug!("sys_munmap <= start: {:p}, len: 0x{:x}", start, len);
@@ -223,6 +303,16 @@ pub fn sys_mprotect(start: *mut c_void, len: ctypes::size_t, prot: c_int) -> c_i
                 // Accumulate the size of the mapping area to be released
                 counter += overlapped_end - overlapped_start;
 
+                // shared mappings of a sealed file cannot become writable
+                #[cfg(feature = "fs")]
+                if prot as u32 & ctypes::PROT_WRITE != 0
+                    && vma.prot & ctypes::PROT_WRITE == 0
+                    && vma.is_shared()
+                    && vma.file.as_deref().is_some_and(is_write_sealed)
+                {
+                    return Err(LinuxError::EACCES);
+                }
+
                 // add node for overlapped vma_ptr
                 if vma.end_addr > overlapped_end {
                     let right_vma = Vma::clone_from(vma, overlapped_end, vma.end_addr);
@@ -305,9 +395,8 @@ pub fn sys_msync(start: *mut c_void, len: ctypes::size_t, flags: c_int) -> c_int
                 return Err(LinuxError::EINVAL);
             }
             for (&vaddr, page_info) in current().mm.mem_map.lock().range(start..end) {
-                if let Some(FileInfo { file, offset, size }) = &page_info.mapping_file {
-                    let src = vaddr as *mut u8;
-                    write_into(file, src, *offset as u64, *size);
+                if let Some(file_info) = &page_info.mapping_file {
+                    file_info.write_back(vaddr as *const u8);
                 }
             }
         }
@@ -424,7 +513,7 @@ pub fn sys_mremap(
                 // copy the old to the new.
                 vma_map.insert(
                     new_start,
-                    Vma::clone_from(&old_vma, new_start, new_start + new_size),
+                    Vma::move_from(&old_vma, new_start, new_start + new_size),
                 );
 
                 // Remove the mapping debris and combine them into a large one.(for performance)
@@ -506,7 +595,7 @@ pub fn sys_mremap(
                     );
 
                     // Insert the new vma.
-                    vma_map.insert(vaddr, Vma::clone_from(&old_vma, vaddr, vaddr + new_size));
+                    vma_map.insert(vaddr, Vma::move_from(&old_vma, vaddr, vaddr + new_size));
 
                     // remove the old vma deris.
                     for key in post_remove {
@@ -15,6 +15,18 @@ use core::{
 };
 
 use axerrno::LinuxError;
+#[cfg(feature = "fs")]
+use {
+    crate::imp::fd_ops::get_file_like,
+    alloc::{sync::Arc, vec::Vec},
+    spinlock::SpinNoIrq,
+};
+
+/// The addresses returned by `mmap` for device memory with their files,
+/// which are released instead of freed by `munmap`.
+#[cfg(feature = "fs")]
+static DEVICE_MAPPINGS: SpinNoIrq<Vec<(usize, Arc<ruxtask::fs::File>)>> =
+    SpinNoIrq::new(Vec::new());
 
 /// Creates a new mapping in the virtual address space of the call‐
 /// ing process.
@@ -31,10 +43,29 @@ pub fn sys_mmap(
 ) -> *mut c_void {
     debug!("sys_mmap <= start: {:p}, len: {}, fd: {}", start, len, fd);
     syscall_body!(sys_mmap, {
+        // the memory of devices such as framebuffers is used as it is
+        #[cfg(feature = "fs")]
+        if fd > 0 {
+            let file = get_file_like(fd)?
+                .into_any()
+                .downcast::<ruxtask::fs::File>();
+            if let Ok(file) = file {
+                let memory = file.inner.read().device_memory();
+                if let Some((base, size)) = memory {
+                    let off = _off as usize;
+                    if off + len > size.next_multiple_of(memory_addr::PAGE_SIZE_4K) {
+                        file.inner.read().device_unmapped();
+                        return Err(LinuxError::EINVAL);
+                    }
+                    DEVICE_MAPPINGS.lock().push((base + off, file));
+                    return Ok((base + off) as *mut c_void);
+                }
+            }
+        }
+
         #[cfg(feature = "fs")]
         if !start.is_null() && fd > 0 {
             let ptr = start;
-            use crate::imp::fd_ops::get_file_like;
             let dst = unsafe { core::slice::from_raw_parts_mut(ptr as *mut u8, len) };
             crate::sys_lseek(fd, _off, 0);
             get_file_like(fd)?.read(dst)?;
@@ -52,7 +83,6 @@ pub fn sys_mmap(
 
         #[cfg(feature = "fs")]
         if fd > 0 {
-            use crate::imp::fd_ops::get_file_like;
             let dst = unsafe { core::slice::from_raw_parts_mut(ptr as *mut u8, len) };
             crate::sys_lseek(fd, _off, 0);
             get_file_like(fd)?.read(dst)?;
@@ -62,6 +92,21 @@ pub fn sys_mmap(
     })
 }
 
+/// Maps `len` bytes of `file` shared into the current process.
+///
+/// Shared mappings need the page-fault based `mmap`, which is unavailable.
+#[cfg(feature = "fs")]
+pub(crate) fn mmap_shared_file(
+    _start: Option<usize>,
+    _len: usize,
+    _prot: u32,
+    _file: alloc::sync::Arc<ruxtask::fs::File>,
+    _offset: usize,
+    _replace: bool,
+) -> axerrno::LinuxResult<usize> {
+    Err(LinuxError::ENOSYS)
+}
+
 /// Deletes the mappings for the specified address range
 pub fn sys_munmap(start: *mut c_void, len: ctypes::size_t) -> c_int {
     debug!("sys_munmap <= start: {:p}, len: {}", start, len);
@@ -69,6 +114,19 @@ pub fn sys_munmap(start: *mut c_void, len: ctypes::size_t) -> c_int {
         if start.is_null() {
             return Err(LinuxError::EINVAL);
         }
+        #[cfg(feature = "fs")]
+        {
+            let mut mappings = DEVICE_MAPPINGS.lock();
+            if let Some(i) = mappings
+                .iter()
+                .position(|(addr, _)| *addr == start as usize)
+            {
+                let (_, file) = mappings.swap_remove(i);
+                drop(mappings);
+                file.inner.read().device_unmapped();
+                return Ok(0);
+            }
+        }
         let layout = Layout::from_size_align(len, 8).unwrap();
         unsafe { dealloc(start.cast(), layout) }
         Ok(0)
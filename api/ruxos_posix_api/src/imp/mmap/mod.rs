@@ -15,8 +15,12 @@ cfg_if::cfg_if! {
         mod api;
         mod trap;
         pub use self::api::{sys_madvise, sys_mmap, sys_mprotect, sys_mremap, sys_msync, sys_munmap};
+        #[cfg(feature = "fs")]
+        pub(crate) use self::api::mmap_shared_file;
     }else {
         mod legacy;
         pub use self::legacy::{sys_madvise, sys_mmap, sys_mprotect, sys_mremap, sys_msync, sys_munmap};
+        #[cfg(feature = "fs")]
+        pub(crate) use self::legacy::mmap_shared_file;
     }
 }
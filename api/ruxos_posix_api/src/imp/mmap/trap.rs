@@ -15,7 +15,10 @@ use {
         ctypes,
         imp::mmap::utils::{preload_page_with_swap, read_from},
     },
-    ruxtask::vma::{FileInfo, BITMAP_FREE, SWAPED_MAP, SWAP_FILE},
+    ruxhal::mem::phys_to_virt,
+    ruxtask::vma::{
+        add_shared_page, find_shared_page, FileInfo, BITMAP_FREE, SWAPED_MAP, SWAP_FILE,
+    },
 };
 
 use crate::imp::mmap::utils::get_mflags_from_usize;
@@ -110,6 +113,17 @@ impl ruxhal::trap::TrapHandler for TrapHandlerImpl {
                 false
             };
 
+            // Map the page loaded by another mapping of the same file page, so
+            // that the changes are seen by all of them.
+            #[cfg(feature = "fs")]
+            let shared_key = vma.shared_page_key(vaddr);
+            #[cfg(feature = "fs")]
+            if let Some(page) = shared_key.filter(|_| !is_cow).and_then(find_shared_page) {
+                let page_vaddr = phys_to_virt(page.paddr);
+                memory_map.insert(vaddr, page);
+                return do_pte_map(VirtAddr::from(vaddr), page_vaddr, map_flag).is_ok();
+            }
+
             // Due to the existence of only one page table in ruxos, in
             // order to prevent data race in multi-threaded environ-
             // -ments caused by adding the current virtual address to the
@@ -170,19 +184,23 @@ impl ruxhal::trap::TrapHandler for TrapHandlerImpl {
                         offset,
                         size: map_length,
                     };
-                    let page_info = PageInfo {
+                    let page_info = Arc::new(PageInfo {
                         paddr: direct_virt_to_phys(fake_vaddr),
                         mapping_file: Some(file_info),
-                    };
-                    memory_map.insert(vaddr, Arc::new(page_info));
+                    });
+                    if let Some(key) = shared_key {
+                        add_shared_page(key, &page_info);
+                    }
+                    memory_map.insert(vaddr, page_info);
                 } else {
-                    memory_map.insert(
-                        vaddr,
-                        Arc::new(PageInfo {
-                            paddr: direct_virt_to_phys(fake_vaddr),
-                            mapping_file: None,
-                        }),
-                    );
+                    let page_info = Arc::new(PageInfo {
+                        paddr: direct_virt_to_phys(fake_vaddr),
+                        mapping_file: None,
+                    });
+                    if let Some(key) = shared_key {
+                        add_shared_page(key, &page_info);
+                    }
+                    memory_map.insert(vaddr, page_info);
                 }
                 #[cfg(not(feature = "fs"))]
                 memory_map.insert(
@@ -10,7 +10,13 @@
 use crate::ctypes;
 
 #[cfg(feature = "fs")]
-use {alloc::sync::Arc, page_table::PagingError, ruxtask::fs::File};
+use {
+    alloc::sync::Arc,
+    page_table::PagingError,
+    ruxhal::mem::direct_virt_to_phys,
+    ruxtask::{fs::File, vma::add_shared_page},
+    spinlock::SpinNoIrq,
+};
 
 use alloc::{collections::BTreeMap, vec::Vec};
 use core::{
@@ -22,7 +28,7 @@ use page_table::MappingFlags;
 use ruxhal::mem::VirtAddr;
 use ruxmm::paging::{alloc_page_preload, do_pte_map, pte_query, pte_swap_preload, pte_unmap_page};
 #[cfg(feature = "fs")]
-use ruxtask::vma::{FileInfo, PageInfo, SwapInfo, BITMAP_FREE, SWAPED_MAP, SWAP_FILE};
+use ruxtask::vma::{PageInfo, SwapInfo, BITMAP_FREE, SWAPED_MAP, SWAP_FILE};
 use ruxtask::{current, vma::Vma};
 
 pub(crate) const VMA_START: usize = ruxconfig::MMAP_START_VADDR;
@@ -67,6 +73,48 @@ pub(crate) fn write_into(file: &Arc<File>, buf: *mut u8, offset: u64, len: usize
     }
 }
 
+/// whether the file is sealed against writing, so that it cannot have new
+/// writable shared mappings.
+#[cfg(feature = "fs")]
+pub(crate) fn is_write_sealed(file: &File) -> bool {
+    file.inner.read().get_seals().is_ok_and(|seals| {
+        seals.intersects(ruxfs::fops::FileSeals::WRITE | ruxfs::fops::FileSeals::FUTURE_WRITE)
+    })
+}
+
+used_fs! {
+    /// the pages of device memory mapped by `mmap`, keyed by the device node.
+    /// They are never freed, as the memory belongs to the device.
+    static DEVICE_PAGES: SpinNoIrq<BTreeMap<usize, Vec<Arc<PageInfo>>>> =
+        SpinNoIrq::new(BTreeMap::new());
+}
+
+/// Make the device memory of `file` the shared pages of its mappings, so
+/// that the page faults on them map the device memory directly.
+///
+/// Returns the size of the device memory, or `None` if `file` is not backed
+/// by device memory.
+#[cfg(feature = "fs")]
+pub(crate) fn add_device_pages(file: &File) -> Option<usize> {
+    let inner = file.inner.read();
+    let (base, size) = inner.device_memory()?;
+    let node_id = inner.node_id();
+    DEVICE_PAGES.lock().entry(node_id).or_insert_with(|| {
+        (0..size.div_ceil(PAGE_SIZE_4K))
+            .map(|i| {
+                let offset = i * PAGE_SIZE_4K;
+                let page = Arc::new(PageInfo {
+                    paddr: direct_virt_to_phys((base + offset).into()),
+                    mapping_file: None,
+                });
+                add_shared_page((node_id, offset), &page);
+                page
+            })
+            .collect()
+    });
+    Some(size)
+}
+
 /// transform usize-like mmap flags to MappingFlags
 pub(crate) fn get_mflags_from_usize(prot: u32) -> MappingFlags {
     let mut mmap_prot = MappingFlags::empty();
@@ -208,12 +256,8 @@ pub(crate) fn release_pages_mapped(start: usize, end: usize) {
     let binding = current();
     let mut memory_map = binding.mm.mem_map.lock();
     let mut removing_vaddr = Vec::new();
-    for (&vaddr, page_info) in memory_map.range(start..end) {
-        #[cfg(feature = "fs")]
-        if let Some(FileInfo { file, offset, size }) = &page_info.mapping_file {
-            let src = vaddr as *mut u8;
-            write_into(file, src, *offset as u64, *size);
-        }
+    // shared file pages are written back once their last mapping is dropped
+    for (&vaddr, _) in memory_map.range(start..end) {
         if pte_unmap_page(VirtAddr::from(vaddr)).is_err() {
             panic!("Release page failed when munmapping!");
         }
@@ -334,9 +378,8 @@ pub(crate) fn preload_page_with_swap(
             Some((vaddr_swapped, page_info)) => {
                 match &page_info.mapping_file {
                     // For file mapping, the mapped content will be written directly to the original file.
-                    Some(FileInfo { file, offset, size }) => {
-                        let offset = *offset as u64;
-                        write_into(file, vaddr_swapped as *mut u8, offset, *size);
+                    Some(file_info) => {
+                        file_info.write_back(vaddr_swapped as *const u8);
                         pte_swap_preload(VirtAddr::from(vaddr_swapped)).unwrap()
                     }
                     // For anonymous mapping, you need to save the mapped memory to the prepared swap file,
@@ -19,18 +19,36 @@ pub mod sys;
 pub mod task;
 pub mod time;
 
+#[cfg(feature = "aio")]
+pub mod aio;
+#[cfg(feature = "eventfd")]
+pub mod eventfd;
 #[cfg(feature = "fs")]
 pub mod execve;
 #[cfg(feature = "fd")]
 pub mod fd_ops;
 #[cfg(feature = "fs")]
+pub mod flock;
+#[cfg(feature = "fs")]
 pub mod fs;
+#[cfg(feature = "inotify")]
+pub mod inotify;
 #[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
 pub mod io_mpx;
+#[cfg(feature = "io_uring")]
+pub mod io_uring;
 #[cfg(feature = "fd")]
 pub mod ioctl;
+#[cfg(feature = "sysvipc")]
+pub mod ipc;
+#[cfg(feature = "fs")]
+pub mod memfd;
 #[cfg(feature = "alloc")]
 pub mod mmap;
+#[cfg(feature = "fs")]
+pub mod mount;
+#[cfg(feature = "mqueue")]
+pub mod mqueue;
 #[cfg(feature = "net")]
 pub mod net;
 #[cfg(feature = "pipe")]
@@ -41,6 +59,14 @@ pub mod pthread;
 pub mod rt_sig;
 #[cfg(feature = "signal")]
 pub mod signal;
+#[cfg(feature = "signalfd")]
+pub mod signalfd;
+#[cfg(feature = "fs")]
+pub mod splice;
+#[cfg(feature = "timer")]
+pub mod timer;
+#[cfg(feature = "timerfd")]
+pub mod timerfd;
 
 /// Invalid syscall
 pub fn sys_invalid(id: core::ffi::c_int) -> core::ffi::c_int {
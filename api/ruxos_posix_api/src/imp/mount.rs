@@ -0,0 +1,108 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Mounting filesystems at runtime, whose types are provided by
+//! [`ruxfs::fstype`].
+
+use core::ffi::{c_char, c_int, c_ulong, c_void};
+
+use axerrno::LinuxError;
+
+use super::fs::parse_path;
+use crate::utils::char_ptr_to_str;
+
+const MS_NOSUID: c_ulong = 2;
+const MS_NODEV: c_ulong = 4;
+const MS_NOEXEC: c_ulong = 8;
+const MS_SILENT: c_ulong = 1 << 15;
+const MS_PRIVATE: c_ulong = 1 << 18;
+const MS_SLAVE: c_ulong = 1 << 19;
+const MS_SHARED: c_ulong = 1 << 20;
+const MS_RELATIME: c_ulong = 1 << 21;
+const MS_MGC_MSK: c_ulong = 0xffff_0000;
+const MS_MGC_VAL: c_ulong = 0xc0ed_0000;
+
+/// Flags which have no effect here, since there is a single user and no
+/// propagation between mount namespaces.
+const MS_IGNORED: c_ulong =
+    MS_NOSUID | MS_NODEV | MS_NOEXEC | MS_SILENT | MS_PRIVATE | MS_SLAVE | MS_SHARED | MS_RELATIME;
+
+const MNT_FORCE: c_int = 1;
+const MNT_DETACH: c_int = 2;
+const UMOUNT_NOFOLLOW: c_int = 8;
+
+/// Mount the filesystem of type `fstype` created from `source` on `target`,
+/// with the comma-separated options in `data`.
+///
+/// Remounts, bind and move mounts and read-only mounts are not supported.
+pub fn sys_mount(
+    source: *const c_char,
+    target: *const c_char,
+    fstype: *const c_char,
+    flags: c_ulong,
+    data: *const c_void,
+) -> c_int {
+    syscall_body!(sys_mount, {
+        let source = if source.is_null() {
+            ""
+        } else {
+            char_ptr_to_str(source)?
+        };
+        let fstype = char_ptr_to_str(fstype)?;
+        let options = if data.is_null() {
+            ""
+        } else {
+            char_ptr_to_str(data as *const c_char)?
+        };
+        let target = parse_path(target)?;
+        debug!(
+            "sys_mount <= source: {:?}, target: {:?}, fstype: {:?}, flags: {:#x}, data: {:?}",
+            source, target, fstype, flags, options
+        );
+        // the magic number required by old kernels
+        let flags = match flags & MS_MGC_MSK {
+            MS_MGC_VAL => flags & !MS_MGC_MSK,
+            _ => flags,
+        };
+        if flags & !MS_IGNORED != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if !ruxfs::fstype::exists(fstype) {
+            return Err(LinuxError::ENODEV);
+        }
+        if !ruxfs::fops::get_attr(&target)?.is_dir() {
+            return Err(LinuxError::ENOTDIR);
+        }
+        if ruxfs::fops::is_mount_point(&target) {
+            return Err(LinuxError::EBUSY);
+        }
+        ruxfs::fops::mount(source, &target, fstype, options)?;
+        Ok(0)
+    })
+}
+
+/// Unmount the filesystem mounted on `target`.
+///
+/// It fails with `EBUSY` while a process has its current directory or an open
+/// file inside the filesystem, unless `MNT_DETACH` is given to detach it at once
+/// anyway. `MNT_FORCE` is accepted and ignored.
+pub fn sys_umount2(target: *const c_char, flags: c_int) -> c_int {
+    syscall_body!(sys_umount2, {
+        let target = parse_path(target)?;
+        debug!("sys_umount2 <= target: {:?}, flags: {:#x}", target, flags);
+        if flags & !(MNT_FORCE | MNT_DETACH | UMOUNT_NOFOLLOW) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if flags & MNT_DETACH == 0 && ruxtask::fs::path_in_use(&target) {
+            return Err(LinuxError::EBUSY);
+        }
+        ruxfs::fops::umount(&target)?;
+        Ok(0)
+    })
+}
@@ -0,0 +1,633 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! POSIX message queues.
+//!
+//! Queues are named by files in the ramfs mounted on `/dev/mqueue`, and live
+//! until they are unlinked. Their descriptors are pollable, readable when the
+//! queue has messages and writable when it has room for more.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::format;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_int, c_uint, CStr};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::{Error, PollState};
+use axsync::Mutex;
+use ruxfdtable::{FileLike, PollObserver, PollObservers, RuxStat};
+use ruxfs::fops::{self, OpenOptions};
+use ruxfs::AbsPath;
+use ruxtask::fs::{add_file_like, get_file_like};
+use ruxtask::signal::NSIG;
+use ruxtask::{SigInfo, Signal};
+use spinlock::SpinNoIrq;
+
+use crate::ctypes;
+use crate::imp::fs::flags_to_options;
+use crate::imp::time::Clock;
+
+/// Directory where the queues are named.
+const MQUEUE_DIR: &str = "/dev/mqueue";
+
+/// Maximum length of a queue name.
+const NAME_MAX: usize = 255;
+
+/// Priorities are below this value.
+const MQ_PRIO_MAX: c_uint = 32768;
+
+/// Default maximum number of messages in a queue.
+const DFLT_MSGMAX: usize = 10;
+/// Default maximum size of a message.
+const DFLT_MSGSIZEMAX: usize = 8192;
+/// Upper bound of the maximum number of messages in a queue.
+const HARD_MSGMAX: usize = 65536;
+/// Upper bound of the maximum size of a message.
+const HARD_MSGSIZEMAX: usize = 16 * 1024 * 1024;
+
+/// The queues by the file node naming them, along with a file that keeps the
+/// node alive.
+static MQUEUES: Mutex<BTreeMap<usize, (fops::File, Arc<MessageQueue>)>> =
+    Mutex::new(BTreeMap::new());
+
+/// Process to notify of a message arriving on an empty queue.
+struct MqNotify {
+    pid: u64,
+    sigev_notify: c_int,
+    /// signal raised, `None` for `SIGEV_NONE`
+    signo: Option<u8>,
+    signal_if: Arc<SpinNoIrq<Signal>>,
+}
+
+pub(crate) struct MqState {
+    /// messages by priority, each priority in the order they were sent
+    messages: BTreeMap<c_uint, VecDeque<Vec<u8>>>,
+    /// number of messages
+    count: usize,
+    /// number of bytes of all the messages
+    size: usize,
+    /// number of tasks blocked in receiving
+    receivers: usize,
+    notify: Option<MqNotify>,
+}
+
+impl MqState {
+    pub(crate) fn new() -> Self {
+        Self {
+            messages: BTreeMap::new(),
+            count: 0,
+            size: 0,
+            receivers: 0,
+            notify: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, prio: c_uint, msg: Vec<u8>) {
+        self.count += 1;
+        self.size += msg.len();
+        self.messages.entry(prio).or_default().push_back(msg);
+    }
+
+    /// Takes the oldest message of the highest priority.
+    pub(crate) fn pop(&mut self) -> Option<(c_uint, Vec<u8>)> {
+        let mut entry = self.messages.last_entry()?;
+        let prio = *entry.key();
+        let msg = entry.get_mut().pop_front().unwrap();
+        if entry.get().is_empty() {
+            entry.remove();
+        }
+        self.count -= 1;
+        self.size -= msg.len();
+        Some((prio, msg))
+    }
+}
+
+struct MessageQueue {
+    maxmsg: usize,
+    msgsize: usize,
+    state: SpinNoIrq<MqState>,
+    /// tasks waiting for messages or for room in the queue
+    wait_queue: ruxtask::WaitQueue,
+    observers: PollObservers,
+}
+
+impl MessageQueue {
+    fn new(maxmsg: usize, msgsize: usize) -> Self {
+        Self {
+            maxmsg,
+            msgsize,
+            state: SpinNoIrq::new(MqState::new()),
+            wait_queue: ruxtask::WaitQueue::new(),
+            observers: PollObservers::new(),
+        }
+    }
+
+    /// Wakes up the waiting tasks and epoll instances after a change of the
+    /// queue.
+    ///
+    /// The state must not be locked, as waiters check it with the scheduler
+    /// locked.
+    fn wake(&self) {
+        self.wait_queue.notify_all(true);
+        self.observers.notify();
+    }
+
+    /// Blocks until `condition` holds, or until `deadline` of the monotonic
+    /// clock if any. Returns `ETIMEDOUT` on timeout.
+    fn wait(&self, deadline: Option<Duration>, condition: impl FnMut() -> bool) -> LinuxResult {
+        match deadline {
+            None => self.wait_queue.wait_until(condition),
+            Some(deadline) => {
+                let timeout = deadline.saturating_sub(ruxhal::time::current_time());
+                if self.wait_queue.wait_timeout_until(timeout, condition) {
+                    return Err(LinuxError::ETIMEDOUT);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn send(
+        &self,
+        msg: &[u8],
+        prio: c_uint,
+        nonblocking: bool,
+        deadline: Option<Duration>,
+    ) -> LinuxResult {
+        loop {
+            let mut state = self.state.lock();
+            if state.count < self.maxmsg {
+                // notify only of messages no receiver is already waiting for
+                let notify = if state.count == 0 && state.receivers == 0 {
+                    state.notify.take()
+                } else {
+                    None
+                };
+                state.push(prio, msg.to_vec());
+                drop(state);
+                if let Some(MqNotify {
+                    signo: Some(signo),
+                    signal_if,
+                    ..
+                }) = notify
+                {
+                    let info = SigInfo {
+                        code: SigInfo::SI_MESGQ,
+                        pid: ruxtask::current().process_id().as_u64() as i32,
+                    };
+                    Signal::raise(&signal_if, signo, info);
+                }
+                self.wake();
+                return Ok(());
+            }
+            drop(state);
+            if nonblocking {
+                return Err(LinuxError::EAGAIN);
+            }
+            self.wait(deadline, || self.state.lock().count < self.maxmsg)?;
+        }
+    }
+
+    fn receive(
+        &self,
+        nonblocking: bool,
+        deadline: Option<Duration>,
+    ) -> LinuxResult<(c_uint, Vec<u8>)> {
+        loop {
+            let mut state = self.state.lock();
+            if let Some(message) = state.pop() {
+                drop(state);
+                self.wake();
+                return Ok(message);
+            }
+            if nonblocking {
+                return Err(LinuxError::EAGAIN);
+            }
+            state.receivers += 1;
+            drop(state);
+            let res = self.wait(deadline, || self.state.lock().count > 0);
+            self.state.lock().receivers -= 1;
+            res?;
+        }
+    }
+
+    /// The content read from a descriptor of the queue, as on Linux.
+    fn status(&self) -> Vec<u8> {
+        let state = self.state.lock();
+        let (sigev_notify, signo, pid) = state.notify.as_ref().map_or((0, 0, 0), |n| {
+            (n.sigev_notify, n.signo.unwrap_or(0) as c_int, n.pid)
+        });
+        format!(
+            "QSIZE:{:<10} NOTIFY:{:<5} SIGNO:{:<5} NOTIFY_PID:{:<6}\n",
+            state.size, sigev_notify, signo, pid
+        )
+        .into_bytes()
+    }
+}
+
+/// A descriptor of a message queue, as returned by `mq_open`.
+pub struct MqDescriptor {
+    queue: Arc<MessageQueue>,
+    file: fops::File,
+    readable: bool,
+    writable: bool,
+    nonblocking: AtomicBool,
+    /// position in the status read from the descriptor
+    offset: AtomicUsize,
+}
+
+impl MqDescriptor {
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<MqDescriptor>()
+            .map_err(|_| LinuxError::EBADF)
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn attr(&self) -> ctypes::mq_attr {
+        ctypes::mq_attr {
+            mq_flags: if self.nonblocking() {
+                ctypes::O_NONBLOCK as _
+            } else {
+                0
+            },
+            mq_maxmsg: self.queue.maxmsg as _,
+            mq_msgsize: self.queue.msgsize as _,
+            mq_curmsgs: self.queue.state.lock().count as _,
+            ..Default::default()
+        }
+    }
+}
+
+impl Drop for MqDescriptor {
+    fn drop(&mut self) {
+        // the notification is removed when its process closes the queue
+        let pid = ruxtask::current().process_id().as_u64();
+        let mut state = self.queue.state.lock();
+        if state.notify.as_ref().is_some_and(|n| n.pid == pid) {
+            state.notify = None;
+        }
+    }
+}
+
+impl FileLike for MqDescriptor {
+    fn path(&self) -> AbsPath {
+        self.file.path()
+    }
+
+    /// Reads the status of the queue.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        let status = self.queue.status();
+        let offset = self.offset.load(Ordering::Relaxed).min(status.len());
+        let len = buf.len().min(status.len() - offset);
+        buf[..len].copy_from_slice(&status[offset..offset + len]);
+        self.offset.fetch_add(len, Ordering::Relaxed);
+        Ok(len)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn flush(&self) -> LinuxResult {
+        Ok(())
+    }
+
+    fn stat(&self) -> LinuxResult<RuxStat> {
+        Ok(RuxStat::from(self.file.get_attr()?))
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let count = self.queue.state.lock().count;
+        Ok(PollState {
+            readable: count > 0,
+            writable: count < self.queue.maxmsg,
+            pollhup: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        self.queue.observers.add(observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        self.queue.observers.remove(observer, key);
+    }
+}
+
+/// Get the path of the queue `name`, which the C library passes without the
+/// leading slash.
+fn mq_path(name: *const c_char) -> LinuxResult<AbsPath<'static>> {
+    if name.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let name = unsafe { CStr::from_ptr(name) }
+        .to_str()
+        .map_err(|_| LinuxError::EINVAL)?;
+    if name.is_empty() {
+        return Err(LinuxError::ENOENT);
+    }
+    if name.len() > NAME_MAX {
+        return Err(LinuxError::ENAMETOOLONG);
+    }
+    if name == "." || name == ".." || name.contains('/') {
+        return Err(LinuxError::EACCES);
+    }
+    Ok(AbsPath::new_owned(format!("{}/{}", MQUEUE_DIR, name)))
+}
+
+/// Gets the limits of a new queue from `attr`, or the default ones.
+fn queue_limits(attr: *const ctypes::mq_attr) -> LinuxResult<(usize, usize)> {
+    if attr.is_null() {
+        return Ok((DFLT_MSGMAX, DFLT_MSGSIZEMAX));
+    }
+    let attr = unsafe { *attr };
+    if attr.mq_maxmsg <= 0 || attr.mq_msgsize <= 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let (maxmsg, msgsize) = (attr.mq_maxmsg as usize, attr.mq_msgsize as usize);
+    if maxmsg > HARD_MSGMAX || msgsize > HARD_MSGSIZEMAX {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok((maxmsg, msgsize))
+}
+
+/// Gets the absolute timeout `abs_timeout` of `CLOCK_REALTIME` as a deadline
+/// of the monotonic clock.
+fn deadline(abs_timeout: *const ctypes::timespec) -> LinuxResult<Option<Duration>> {
+    if abs_timeout.is_null() {
+        return Ok(None);
+    }
+    let ts = unsafe { *abs_timeout };
+    if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Some(
+        Clock::Realtime.to_monotonic_deadline(Duration::from(ts)),
+    ))
+}
+
+/// Open the message queue `name`, or create it with the limits of `attr` if
+/// `O_CREAT` is set in `oflag`.
+///
+/// The descriptor is always closed on `exec`.
+pub fn sys_mq_open(
+    name: *const c_char,
+    oflag: c_int,
+    mode: ctypes::mode_t,
+    attr: *const ctypes::mq_attr,
+) -> c_int {
+    debug!(
+        "sys_mq_open <= name: {:p}, oflag: {:#o}, mode: {:#o}, attr: {:p}",
+        name, oflag, mode, attr
+    );
+    syscall_body!(sys_mq_open, {
+        let path = mq_path(name)?;
+        let flags = oflag as u32;
+        let opts = flags_to_options((oflag as u32 | ctypes::O_CLOEXEC) as c_int, mode);
+        let create = flags & ctypes::O_CREAT != 0;
+
+        let mut queues = MQUEUES.lock();
+        let node = match fops::lookup(&path) {
+            Ok(_) if create && flags & ctypes::O_EXCL != 0 => return Err(LinuxError::EEXIST),
+            Ok(node) => node,
+            Err(Error::NotFound) if create => {
+                // check the limits before naming the queue
+                queue_limits(attr)?;
+                fops::create_file(&path)?;
+                fops::lookup(&path)?
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if node.get_attr()?.is_dir() {
+            return Err(LinuxError::EISDIR);
+        }
+        let file = fops::open_file(&path, node.clone(), &opts)?;
+        let queue = match queues.get(&file.node_id()) {
+            Some((_, queue)) => queue.clone(),
+            None => {
+                let (maxmsg, msgsize) = if create {
+                    queue_limits(attr)?
+                } else {
+                    (DFLT_MSGMAX, DFLT_MSGSIZEMAX)
+                };
+                let queue = Arc::new(MessageQueue::new(maxmsg, msgsize));
+                let holder = fops::open_file(&path, node, &OpenOptions::new())?;
+                queues.insert(file.node_id(), (holder, queue.clone()));
+                queue
+            }
+        };
+        drop(queues);
+
+        let descriptor = MqDescriptor {
+            queue,
+            file,
+            readable: opts.read,
+            writable: opts.write,
+            nonblocking: AtomicBool::new(flags & ctypes::O_NONBLOCK != 0),
+            offset: AtomicUsize::new(0),
+        };
+        add_file_like(Arc::new(descriptor), opts)
+    })
+}
+
+/// Remove the message queue `name`.
+///
+/// The queue is freed once it is no longer opened.
+pub fn sys_mq_unlink(name: *const c_char) -> c_int {
+    debug!("sys_mq_unlink <= name: {:p}", name);
+    syscall_body!(sys_mq_unlink, {
+        let path = mq_path(name)?;
+        let mut queues = MQUEUES.lock();
+        let node = fops::lookup(&path)?;
+        if node.get_attr()?.is_dir() {
+            return Err(LinuxError::EISDIR);
+        }
+        fops::remove_file(&path)?;
+        let node_id = Arc::as_ptr(&node) as *const () as usize;
+        queues.remove(&node_id);
+        Ok(0)
+    })
+}
+
+/// Send the message `msg_ptr` of `msg_len` bytes with the priority `msg_prio`
+/// to the queue `mqdes`, waiting for room until `abs_timeout` of
+/// `CLOCK_REALTIME` if it is full.
+pub fn sys_mq_timedsend(
+    mqdes: c_int,
+    msg_ptr: *const c_char,
+    msg_len: ctypes::size_t,
+    msg_prio: c_uint,
+    abs_timeout: *const ctypes::timespec,
+) -> c_int {
+    debug!(
+        "sys_mq_timedsend <= mqdes: {}, msg_ptr: {:p}, msg_len: {}, msg_prio: {}",
+        mqdes, msg_ptr, msg_len, msg_prio
+    );
+    syscall_body!(sys_mq_timedsend, {
+        if msg_prio >= MQ_PRIO_MAX {
+            return Err(LinuxError::EINVAL);
+        }
+        let deadline = deadline(abs_timeout)?;
+        let mq = MqDescriptor::from_fd(mqdes)?;
+        if !mq.writable {
+            return Err(LinuxError::EBADF);
+        }
+        let len = msg_len as usize;
+        if len > mq.queue.msgsize {
+            return Err(LinuxError::EMSGSIZE);
+        }
+        if msg_ptr.is_null() && len > 0 {
+            return Err(LinuxError::EFAULT);
+        }
+        let msg = if len > 0 {
+            unsafe { core::slice::from_raw_parts(msg_ptr as *const u8, len) }
+        } else {
+            &[]
+        };
+        mq.queue.send(msg, msg_prio, mq.nonblocking(), deadline)?;
+        Ok(0)
+    })
+}
+
+/// Receive the oldest message of the highest priority of the queue `mqdes`
+/// into `msg_ptr`, waiting for one until `abs_timeout` of `CLOCK_REALTIME`
+/// if it is empty. Its priority is stored in `msg_prio` if not NULL.
+pub fn sys_mq_timedreceive(
+    mqdes: c_int,
+    msg_ptr: *mut c_char,
+    msg_len: ctypes::size_t,
+    msg_prio: *mut c_uint,
+    abs_timeout: *const ctypes::timespec,
+) -> ctypes::ssize_t {
+    debug!(
+        "sys_mq_timedreceive <= mqdes: {}, msg_ptr: {:p}, msg_len: {}",
+        mqdes, msg_ptr, msg_len
+    );
+    syscall_body!(sys_mq_timedreceive, {
+        let deadline = deadline(abs_timeout)?;
+        let mq = MqDescriptor::from_fd(mqdes)?;
+        if !mq.readable {
+            return Err(LinuxError::EBADF);
+        }
+        // the buffer must fit any message of the queue
+        if (msg_len as usize) < mq.queue.msgsize {
+            return Err(LinuxError::EMSGSIZE);
+        }
+        if msg_ptr.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let (prio, msg) = mq.queue.receive(mq.nonblocking(), deadline)?;
+        unsafe {
+            core::ptr::copy_nonoverlapping(msg.as_ptr(), msg_ptr as *mut u8, msg.len());
+            if !msg_prio.is_null() {
+                *msg_prio = prio;
+            }
+        }
+        Ok(msg.len() as ctypes::ssize_t)
+    })
+}
+
+/// Register the calling process to be notified by `sevp` when a message
+/// arrives on the queue `mqdes` while it is empty, or unregister it if
+/// `sevp` is NULL.
+///
+/// Only `SIGEV_NONE` and `SIGEV_SIGNAL` are supported. The registration is
+/// removed once the notification is sent.
+pub fn sys_mq_notify(mqdes: c_int, sevp: *const ctypes::sigevent) -> c_int {
+    debug!("sys_mq_notify <= mqdes: {}, sevp: {:p}", mqdes, sevp);
+    syscall_body!(sys_mq_notify, {
+        let mq = MqDescriptor::from_fd(mqdes)?;
+        let curr = ruxtask::current();
+        let pid = curr.process_id().as_u64();
+        let mut state = mq.queue.state.lock();
+        if sevp.is_null() {
+            if state.notify.as_ref().is_some_and(|n| n.pid == pid) {
+                state.notify = None;
+            }
+            return Ok(0);
+        }
+        let sev = unsafe { &*sevp };
+        let signo = match sev.sigev_notify as u32 {
+            ctypes::SIGEV_NONE => None,
+            ctypes::SIGEV_SIGNAL if (1..NSIG as c_int).contains(&sev.sigev_signo) => {
+                Some(sev.sigev_signo as u8)
+            }
+            _ => return Err(LinuxError::EINVAL),
+        };
+        if state.notify.is_some() {
+            return Err(LinuxError::EBUSY);
+        }
+        state.notify = Some(MqNotify {
+            pid,
+            sigev_notify: sev.sigev_notify,
+            signo,
+            signal_if: curr.signal_if.clone(),
+        });
+        Ok(0)
+    })
+}
+
+/// Get the attributes of the queue `mqdes` into `oldattr`, and set its
+/// `O_NONBLOCK` flag from `newattr`, if they are not NULL.
+pub fn sys_mq_getsetattr(
+    mqdes: c_int,
+    newattr: *const ctypes::mq_attr,
+    oldattr: *mut ctypes::mq_attr,
+) -> c_int {
+    debug!(
+        "sys_mq_getsetattr <= mqdes: {}, newattr: {:p}, oldattr: {:p}",
+        mqdes, newattr, oldattr
+    );
+    syscall_body!(sys_mq_getsetattr, {
+        let mq = MqDescriptor::from_fd(mqdes)?;
+        let new_flags = if newattr.is_null() {
+            None
+        } else {
+            let flags = unsafe { (*newattr).mq_flags };
+            if flags & !(ctypes::O_NONBLOCK as core::ffi::c_long) != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            Some(flags)
+        };
+        if !oldattr.is_null() {
+            unsafe { *oldattr = mq.attr() };
+        }
+        if let Some(flags) = new_flags {
+            mq.set_nonblocking(flags != 0)?;
+        }
+        Ok(0)
+    })
+}
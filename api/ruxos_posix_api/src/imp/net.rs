@@ -7,17 +7,22 @@
  *   See the Mulan PSL v2 for more details.
  */
 
-use alloc::{sync::Arc, vec, vec::Vec};
+use alloc::{
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
 use core::ffi::{c_char, c_int, c_void};
 use core::iter;
 use core::mem::size_of;
 use core::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use core::sync::atomic::{AtomicUsize, Ordering};
 use ruxfs::{fops, AbsPath};
 
 use axerrno::{LinuxError, LinuxResult};
 use axio::PollState;
 use axsync::Mutex;
-use ruxfdtable::{FileLike, RuxStat};
+use ruxfdtable::{FileLike, PollObserver, PollObservers, RuxStat};
 use ruxnet::{SocketAddrUnix, TcpSocket, UdpSocket, UnixSocket, UnixSocketType};
 
 use crate::ctypes;
@@ -50,6 +55,36 @@ fn addrun_convert_withlen(addr: *const ctypes::sockaddr_un, addrlen: usize) -> S
     }
 }
 
+/// Observers of all sockets.
+///
+/// The readiness of sockets changes on socket operations, which may affect
+/// the peer, and when the network interfaces are polled. Neither tells which
+/// sockets changed, so every observer is notified.
+static SOCKET_OBSERVERS: PollObservers = PollObservers::new();
+/// Number of observers of TCP and UDP sockets, which only become ready when
+/// the network interfaces are polled.
+static INET_OBSERVERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Notifies the observers of sockets if a socket operation succeeded.
+fn sockets_changed<T>(ret: LinuxResult<T>) -> LinuxResult<T> {
+    if ret.is_ok() {
+        SOCKET_OBSERVERS.notify();
+    }
+    ret
+}
+
+/// Polls the network interfaces and notifies the observers of sockets.
+pub(crate) fn poll_interfaces() {
+    ruxnet::poll_interfaces();
+    SOCKET_OBSERVERS.notify();
+}
+
+/// Whether TCP or UDP sockets are observed, so the network interfaces must be
+/// polled periodically while waiting for them.
+pub(crate) fn inet_observed() -> bool {
+    INET_OBSERVERS.load(Ordering::Relaxed) > 0
+}
+
 #[derive(Debug)]
 pub enum UnifiedSocketAddress {
     Net(SocketAddr),
@@ -68,26 +103,29 @@ impl Socket {
     }
 
     fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
-        let f = ruxtask::fs::get_file_like(fd)?;
-        f.into_any()
+        Self::from_file(ruxtask::fs::get_file_like(fd)?)
+    }
+
+    pub(crate) fn from_file(file: Arc<dyn FileLike>) -> LinuxResult<Arc<Self>> {
+        file.into_any()
             .downcast::<Self>()
             .map_err(|_| LinuxError::EINVAL)
     }
 
-    fn send(&self, buf: &[u8]) -> LinuxResult<usize> {
-        match self {
+    pub(crate) fn send(&self, buf: &[u8]) -> LinuxResult<usize> {
+        sockets_changed(match self {
             Socket::Udp(udpsocket) => Ok(udpsocket.lock().send(buf)?),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().send(buf)?),
             Socket::Unix(socket) => Ok(socket.lock().send(buf)?),
-        }
+        })
     }
 
-    fn recv(&self, buf: &mut [u8], flags: i32) -> LinuxResult<usize> {
-        match self {
+    pub(crate) fn recv(&self, buf: &mut [u8], flags: i32) -> LinuxResult<usize> {
+        sockets_changed(match self {
             Socket::Udp(udpsocket) => Ok(udpsocket.lock().recv_from(buf).map(|e| e.0)?),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf, flags)?),
             Socket::Unix(socket) => Ok(socket.lock().recv(buf, flags)?),
-        }
+        })
     }
 
     pub fn poll(&self) -> LinuxResult<PollState> {
@@ -144,12 +182,12 @@ impl Socket {
         }
     }
 
-    fn connect(
+    pub(crate) fn connect(
         &self,
         socket_addr: *const ctypes::sockaddr,
         addrlen: ctypes::socklen_t,
     ) -> LinuxResult {
-        match self {
+        sockets_changed(match self {
             Socket::Udp(udpsocket) => {
                 let addr = from_sockaddr(socket_addr, addrlen)?;
                 Ok(udpsocket.lock().connect(addr)?)
@@ -167,7 +205,7 @@ impl Socket {
                     addrlen.try_into().unwrap(),
                 ))?)
             }
-        }
+        })
     }
 
     fn sendto(
@@ -176,7 +214,7 @@ impl Socket {
         socket_addr: *const ctypes::sockaddr,
         addrlen: ctypes::socklen_t,
     ) -> LinuxResult<usize> {
-        match self {
+        sockets_changed(match self {
             // diff: must bind before sendto
             Socket::Udp(udpsocket) => {
                 let addr = from_sockaddr(socket_addr, addrlen)?;
@@ -195,11 +233,11 @@ impl Socket {
                     ),
                 )?)
             }
-        }
+        })
     }
 
     fn recvfrom(&self, buf: &mut [u8]) -> LinuxResult<(usize, Option<UnifiedSocketAddress>)> {
-        match self {
+        sockets_changed(match self {
             // diff: must bind before recvfrom
             Socket::Udp(udpsocket) => {
                 let (size, addr) = udpsocket.lock().recv_from(buf)?;
@@ -224,27 +262,27 @@ impl Socket {
                     _ => Err(LinuxError::EOPNOTSUPP),
                 }
             }
-        }
+        })
     }
 
     fn listen(&self) -> LinuxResult {
-        match self {
+        sockets_changed(match self {
             Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().listen()?),
             Socket::Unix(socket) => Ok(socket.lock().listen()?),
-        }
+        })
     }
 
     fn accept(&self) -> LinuxResult<Socket> {
-        match self {
+        sockets_changed(match self {
             Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
             Socket::Tcp(tcpsocket) => Ok(Socket::Tcp(Mutex::new(tcpsocket.lock().accept()?))),
             Socket::Unix(unixsocket) => Ok(Socket::Unix(Mutex::new(unixsocket.lock().accept()?))),
-        }
+        })
     }
 
     fn shutdown(&self) -> LinuxResult {
-        match self {
+        sockets_changed(match self {
             Socket::Udp(udpsocket) => {
                 let udpsocket = udpsocket.lock();
                 udpsocket.peer_addr()?;
@@ -264,7 +302,14 @@ impl Socket {
                 socket.shutdown()?;
                 Ok(())
             }
-        }
+        })
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        // the peer may see the connection closed
+        SOCKET_OBSERVERS.notify();
     }
 }
 
@@ -316,6 +361,34 @@ impl FileLike for Socket {
         }
         Ok(())
     }
+
+    fn is_nonblocking(&self) -> bool {
+        match self {
+            Socket::Udp(udpsocket) => udpsocket.lock().is_nonblocking(),
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().is_nonblocking(),
+            Socket::Unix(unixsocket) => unixsocket.lock().is_nonblocking(),
+        }
+    }
+
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        if !matches!(self, Socket::Unix(_)) {
+            INET_OBSERVERS.fetch_add(1, Ordering::Relaxed);
+        }
+        SOCKET_OBSERVERS.add(observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        if !matches!(self, Socket::Unix(_)) {
+            INET_OBSERVERS.fetch_sub(1, Ordering::Relaxed);
+        }
+        SOCKET_OBSERVERS.remove(observer, key);
+    }
 }
 
 impl From<SocketAddrV4> for ctypes::sockaddr_in {
@@ -688,22 +761,34 @@ pub unsafe fn sys_accept(
         if socket_addr.is_null() || socket_len.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        let user_buf_len = unsafe { *socket_len } as usize;
-        let socket = Socket::from_fd(socket_fd)?;
-        let new_socket = socket.accept()?;
-        let peer_addr = new_socket.peer_addr()?;
-        let new_fd = Socket::add_to_fd_table(new_socket, fops::OpenOptions::new())?;
+        accept_socket(&Socket::from_fd(socket_fd)?, socket_addr, socket_len)
+    })
+}
 
-        let (addr_bytes, actual_len) = unified_into_sockaddr(peer_addr);
+/// Accepts a connection on `socket` into a new file descriptor, and writes
+/// the peer address to `socket_addr` unless it is NULL.
+pub(crate) unsafe fn accept_socket(
+    socket: &Socket,
+    socket_addr: *mut ctypes::sockaddr,
+    socket_len: *mut ctypes::socklen_t,
+) -> LinuxResult<c_int> {
+    let new_socket = socket.accept()?;
+    let peer_addr = new_socket.peer_addr()?;
+    let new_fd = Socket::add_to_fd_table(new_socket, fops::OpenOptions::new())?;
+    if socket_addr.is_null() || socket_len.is_null() {
+        return Ok(new_fd);
+    }
 
-        let copy_len = user_buf_len.min(addr_bytes.len());
+    let user_buf_len = unsafe { *socket_len } as usize;
+    let (addr_bytes, actual_len) = unified_into_sockaddr(peer_addr);
 
-        unsafe {
-            core::ptr::copy_nonoverlapping(addr_bytes.as_ptr(), socket_addr as *mut u8, copy_len);
-            *socket_len = actual_len;
-        }
-        Ok(new_fd)
-    })
+    let copy_len = user_buf_len.min(addr_bytes.len());
+
+    unsafe {
+        core::ptr::copy_nonoverlapping(addr_bytes.as_ptr(), socket_addr as *mut u8, copy_len);
+        *socket_len = actual_len;
+    }
+    Ok(new_fd)
 }
 
 /// Shut down a full-duplex connection.
@@ -998,7 +1083,7 @@ pub unsafe fn sys_sendmsg(
                 )?,
             };
         }
-        Ok(ret)
+        sockets_changed(Ok(ret))
     })
 }
 
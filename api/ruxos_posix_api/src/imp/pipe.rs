@@ -8,106 +8,191 @@
  */
 
 use alloc::sync::{Arc, Weak};
+use alloc::{vec, vec::Vec};
 use core::ffi::c_int;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use ruxfs::{fops, AbsPath};
 
 use axerrno::{LinuxError, LinuxResult};
 use axio::PollState;
-use axsync::Mutex;
-use ruxfdtable::{FileLike, RuxStat};
+use memory_addr::PAGE_SIZE_4K;
+use ruxfdtable::{FileLike, PollObserver, PollObservers, RuxStat, PIPE_BUF};
+use spinlock::SpinNoIrq;
 
 use crate::{ctypes, sys_fcntl};
-use ruxtask::fs::{add_file_like, close_file_like};
-
-#[derive(Copy, Clone, PartialEq)]
-enum RingBufferStatus {
-    Full,
-    Empty,
-    Normal,
-}
+use ruxtask::fs::{add_file_like, close_file_like, get_file_like};
 
 const RING_BUFFER_SIZE: usize = ruxconfig::PIPE_BUFFER_SIZE;
 
+/// Upper bound of the capacity set by `F_SETPIPE_SZ`.
+const PIPE_MAX_SIZE: usize = 0x100000;
+
 pub struct PipeRingBuffer {
-    arr: [u8; RING_BUFFER_SIZE],
+    arr: Vec<u8>,
     head: usize,
-    tail: usize,
-    status: RingBufferStatus,
+    len: usize,
 }
 
 impl PipeRingBuffer {
-    pub const fn new() -> Self {
+    pub fn new(capacity: usize) -> Self {
         Self {
-            arr: [0; RING_BUFFER_SIZE],
+            arr: vec![0; capacity],
             head: 0,
-            tail: 0,
-            status: RingBufferStatus::Empty,
+            len: 0,
         }
     }
 
-    pub fn write_byte(&mut self, byte: u8) {
-        self.status = RingBufferStatus::Normal;
-        self.arr[self.tail] = byte;
-        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
-        if self.tail == self.head {
-            self.status = RingBufferStatus::Full;
-        }
-    }
-
-    pub fn read_byte(&mut self) -> u8 {
-        self.status = RingBufferStatus::Normal;
-        let c = self.arr[self.head];
-        self.head = (self.head + 1) % RING_BUFFER_SIZE;
-        if self.head == self.tail {
-            self.status = RingBufferStatus::Empty;
-        }
-        c
+    pub fn capacity(&self) -> usize {
+        self.arr.len()
     }
 
     /// Get the length of remaining data in the buffer
     pub const fn available_read(&self) -> usize {
-        if matches!(self.status, RingBufferStatus::Empty) {
-            0
-        } else if self.tail > self.head {
-            self.tail - self.head
-        } else {
-            self.tail + RING_BUFFER_SIZE - self.head
-        }
+        self.len
     }
 
     /// Get the length of remaining space in the buffer
-    pub const fn available_write(&self) -> usize {
-        if matches!(self.status, RingBufferStatus::Full) {
-            0
-        } else {
-            RING_BUFFER_SIZE - self.available_read()
+    pub fn available_write(&self) -> usize {
+        self.capacity() - self.len
+    }
+
+    /// Copy data from the buffer without consuming it, returns the copied length
+    pub fn peek(&self, buf: &mut [u8]) -> usize {
+        let len = self.len.min(buf.len());
+        let first = len.min(self.capacity() - self.head);
+        buf[..first].copy_from_slice(&self.arr[self.head..self.head + first]);
+        buf[first..len].copy_from_slice(&self.arr[..len - first]);
+        len
+    }
+
+    /// Move data out of the buffer, returns the read length
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let len = self.peek(buf);
+        self.consume(len);
+        len
+    }
+
+    /// Drop up to `len` bytes from the front of the buffer
+    pub fn consume(&mut self, len: usize) {
+        let len = len.min(self.len);
+        self.head = (self.head + len) % self.capacity();
+        self.len -= len;
+    }
+
+    /// Append up to `len` bytes of this buffer to `other` without consuming
+    /// them, returns the copied length
+    pub fn copy_to(&self, other: &mut PipeRingBuffer, len: usize) -> usize {
+        let len = len.min(self.len).min(other.available_write());
+        let first = len.min(self.capacity() - self.head);
+        other.write(&self.arr[self.head..self.head + first]);
+        other.write(&self.arr[..len - first]);
+        len
+    }
+
+    /// Append as much of `buf` as fits, returns the written length
+    pub fn write(&mut self, buf: &[u8]) -> usize {
+        let capacity = self.capacity();
+        let len = self.available_write().min(buf.len());
+        let tail = (self.head + self.len) % capacity;
+        let first = len.min(capacity - tail);
+        self.arr[tail..tail + first].copy_from_slice(&buf[..first]);
+        self.arr[..len - first].copy_from_slice(&buf[first..len]);
+        self.len += len;
+        len
+    }
+
+    /// Change the capacity, fails with `EBUSY` if the data would not fit
+    pub fn resize(&mut self, capacity: usize) -> LinuxResult {
+        if self.len > capacity {
+            return Err(LinuxError::EBUSY);
         }
+        let mut arr = vec![0; capacity];
+        self.peek(&mut arr);
+        self.arr = arr;
+        self.head = 0;
+        Ok(())
+    }
+}
+
+/// The state shared by both ends of a pipe.
+struct PipeShared {
+    buffer: SpinNoIrq<PipeRingBuffer>,
+    readers: AtomicUsize,
+    writers: AtomicUsize,
+    /// Tasks waiting for data, space, or the other end to be closed.
+    #[cfg(feature = "multitask")]
+    wait_queue: ruxtask::WaitQueue,
+    // each end changes the readiness of the other
+    observers: PollObservers,
+}
+
+impl PipeShared {
+    fn has_readers(&self) -> bool {
+        self.readers.load(Ordering::Acquire) > 0
+    }
+
+    fn has_writers(&self) -> bool {
+        self.writers.load(Ordering::Acquire) > 0
+    }
+
+    /// Block until `condition` returns true.
+    fn wait_until(&self, condition: impl FnMut() -> bool) {
+        #[cfg(feature = "multitask")]
+        self.wait_queue.wait_until(condition);
+        #[cfg(not(feature = "multitask"))]
+        {
+            let mut condition = condition;
+            while !condition() {
+                crate::sys_sched_yield();
+            }
+        }
+    }
+
+    /// Wake up the waiting tasks and epoll instances after a state change.
+    fn wake(&self) {
+        #[cfg(feature = "multitask")]
+        self.wait_queue.notify_all(true);
+        self.observers.notify();
     }
 }
 
 pub struct Pipe {
     readable: bool,
-    buffer: Arc<Mutex<PipeRingBuffer>>,
-    // to find the write end when the read end is closed
-    _write_end_closed: Option<Weak<Mutex<PipeRingBuffer>>>,
+    shared: Arc<PipeShared>,
+    nonblocking: AtomicBool,
 }
 
 impl Pipe {
     pub fn new() -> (Pipe, Pipe) {
-        let buffer = Arc::new(Mutex::new(PipeRingBuffer::new()));
+        let shared = Arc::new(PipeShared {
+            buffer: SpinNoIrq::new(PipeRingBuffer::new(RING_BUFFER_SIZE)),
+            readers: AtomicUsize::new(1),
+            writers: AtomicUsize::new(1),
+            #[cfg(feature = "multitask")]
+            wait_queue: ruxtask::WaitQueue::new(),
+            observers: PollObservers::new(),
+        });
         let read_end = Pipe {
             readable: true,
-            buffer: buffer.clone(),
-            _write_end_closed: None,
+            shared: shared.clone(),
+            nonblocking: AtomicBool::new(false),
         };
         let write_end = Pipe {
             readable: false,
-            buffer: buffer.clone(),
-            _write_end_closed: Some(Arc::downgrade(&buffer)),
+            shared,
+            nonblocking: AtomicBool::new(false),
         };
         (read_end, write_end)
     }
 
+    /// Get the pipe of `fd`, `EBADF` if it is not a pipe.
+    pub fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<Pipe>()
+            .map_err(|_| LinuxError::EBADF)
+    }
+
     pub const fn readable(&self) -> bool {
         self.readable
     }
@@ -117,80 +202,269 @@ impl Pipe {
     }
 
     pub fn write_end_close(&self) -> bool {
-        let write_end_count = Arc::weak_count(&self.buffer);
-        write_end_count == 0
+        !self.shared.has_writers()
     }
-}
 
-impl FileLike for Pipe {
-    fn path(&self) -> AbsPath {
-        AbsPath::new("/dev/pipe")
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
     }
 
-    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        if !self.readable() {
+    /// Whether `other` is an end of the same pipe.
+    pub fn is_same_pipe(&self, other: &Pipe) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+
+    /// Get the capacity of the pipe buffer in bytes.
+    pub fn capacity(&self) -> usize {
+        self.shared.buffer.lock().capacity()
+    }
+
+    /// Resize the pipe buffer to at least `size` bytes, used by `F_SETPIPE_SZ`.
+    ///
+    /// The capacity is rounded up to a power-of-two number of pages. Returns
+    /// the new capacity.
+    pub fn set_capacity(&self, size: usize) -> LinuxResult<usize> {
+        if size > PIPE_MAX_SIZE {
             return Err(LinuxError::EPERM);
         }
-        let mut read_size = 0usize;
-        let max_len = buf.len();
-        let mut ring_buffer = self.buffer.lock();
-        // First, check if there is data in the read end.
-        // This loop is only runs when the write end is open
-        // and there is no data available
+        let capacity = size.max(PAGE_SIZE_4K).next_power_of_two();
+        self.shared.buffer.lock().resize(capacity)?;
+        self.shared.wake();
+        Ok(capacity)
+    }
+
+    /// Duplicate up to `len` bytes from this read end to the write end `out`
+    /// without consuming them, used by `tee`.
+    ///
+    /// Return 0 if there is no data and the write end of this pipe is closed.
+    pub fn tee(&self, out: &Pipe, len: usize, nonblocking: bool) -> LinuxResult<usize> {
+        self.copy_to(out, len, nonblocking, false)
+    }
+
+    /// Move up to `len` bytes from this read end to the write end `out`, used
+    /// by `splice`.
+    ///
+    /// Return 0 if there is no data and the write end of this pipe is closed.
+    pub fn splice_to(&self, out: &Pipe, len: usize, nonblocking: bool) -> LinuxResult<usize> {
+        self.copy_to(out, len, nonblocking, true)
+    }
+
+    /// Copy data straight from the buffer of this read end to the one of the
+    /// write end `out`, consuming it if `consume` is set.
+    fn copy_to(
+        &self,
+        out: &Pipe,
+        len: usize,
+        nonblocking: bool,
+        consume: bool,
+    ) -> LinuxResult<usize> {
+        if !self.readable() || !out.writable() || self.is_same_pipe(out) {
+            return Err(LinuxError::EINVAL);
+        }
         loop {
-            let loop_read = ring_buffer.available_read();
-            // If there is no data
-            if loop_read == 0 {
-                if self.write_end_close() {
-                    // write end is closed, read 0 bytes.
-                    return Ok(0);
-                } else {
-                    // write end is open
-                    drop(ring_buffer);
-                    // Data not ready, wait for write end
-                    crate::sys_sched_yield(); // TODO: use synconize primitive
-                    ring_buffer = self.buffer.lock();
+            if !out.shared.has_readers() {
+                return Err(LinuxError::EPIPE);
+            }
+            let (available, copied) = self.with_buffers(out, |src, dst| {
+                let copied = src.copy_to(dst, len);
+                if consume {
+                    src.consume(copied);
+                }
+                (src.available_read(), copied)
+            });
+            if copied > 0 {
+                if consume {
+                    self.shared.wake();
                 }
+                out.shared.wake();
+                return Ok(copied);
+            }
+            if available == 0 && !self.shared.has_writers() {
+                return Ok(0);
+            }
+            if nonblocking {
+                return Err(LinuxError::EAGAIN);
+            }
+            if available == 0 {
+                self.shared.wait_until(|| {
+                    self.shared.buffer.lock().available_read() > 0 || !self.shared.has_writers()
+                });
             } else {
-                break;
+                out.shared.wait_until(|| {
+                    out.shared.buffer.lock().available_write() > 0 || !out.shared.has_readers()
+                });
             }
         }
-        // read data
-        let loop_read = ring_buffer.available_read();
-        for _ in 0..loop_read {
-            if read_size == max_len {
-                return Ok(read_size);
+    }
+
+    /// Run `f` on the buffers of this pipe and `out`, locked in a fixed order
+    /// so that transfers in opposite directions can't deadlock.
+    fn with_buffers<R>(
+        &self,
+        out: &Pipe,
+        f: impl FnOnce(&mut PipeRingBuffer, &mut PipeRingBuffer) -> R,
+    ) -> R {
+        if Arc::as_ptr(&self.shared) < Arc::as_ptr(&out.shared) {
+            let mut src = self.shared.buffer.lock();
+            f(&mut src, &mut out.shared.buffer.lock())
+        } else {
+            let mut dst = out.shared.buffer.lock();
+            f(&mut self.shared.buffer.lock(), &mut dst)
+        }
+    }
+
+    /// Copy data from this read end without consuming it, used by `splice` to
+    /// consume only what was written out, see [`Pipe::consume`].
+    ///
+    /// Return 0 if there is no data and the write end of this pipe is closed.
+    pub fn peek(&self, buf: &mut [u8], nonblocking: bool) -> LinuxResult<usize> {
+        if !self.readable() {
+            return Err(LinuxError::EPERM);
+        }
+        let shared = &self.shared;
+        loop {
+            let peek_size = shared.buffer.lock().peek(buf);
+            if peek_size > 0 || buf.is_empty() || !shared.has_writers() {
+                return Ok(peek_size);
+            }
+            if nonblocking {
+                return Err(LinuxError::EAGAIN);
             }
-            buf[read_size] = ring_buffer.read_byte();
-            read_size += 1;
+            shared
+                .wait_until(|| shared.buffer.lock().available_read() > 0 || !shared.has_writers());
         }
-        Ok(read_size)
     }
 
-    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+    /// Drop `len` bytes returned by [`Pipe::peek`].
+    pub fn consume(&self, len: usize) {
+        self.shared.buffer.lock().consume(len);
+        self.shared.wake();
+    }
+
+    /// Wait for free space in this write end and return its size, used by
+    /// `splice` to read no more from a stream than the pipe takes.
+    pub fn wait_room(&self, nonblocking: bool) -> LinuxResult<usize> {
         if !self.writable() {
             return Err(LinuxError::EPERM);
         }
-        let mut write_size = 0usize;
-        let max_len = buf.len();
+        let shared = &self.shared;
         loop {
-            let mut ring_buffer = self.buffer.lock();
-            let loop_write = ring_buffer.available_write();
-            if loop_write == 0 {
-                drop(ring_buffer);
-                // Buffer is full, wait for read end to consume
-                crate::sys_sched_yield(); // TODO: use synconize primitive
-                continue;
+            if !shared.has_readers() {
+                return Err(LinuxError::EPIPE);
             }
-            for _ in 0..loop_write {
-                if write_size == max_len {
+            let room = shared.buffer.lock().available_write();
+            if room > 0 {
+                return Ok(room);
+            }
+            if nonblocking {
+                return Err(LinuxError::EAGAIN);
+            }
+            shared
+                .wait_until(|| shared.buffer.lock().available_write() > 0 || !shared.has_readers());
+        }
+    }
+
+    /// Write `buf` after [`Pipe::wait_room`] found room for it. It blocks even
+    /// if the pipe is nonblocking, in case another writer took the room, as
+    /// the data can't be given back to where it came from.
+    pub fn write_reserved(&self, buf: &[u8]) -> LinuxResult<usize> {
+        self.write_with(buf, false)
+    }
+
+    /// Writes of at most [`PIPE_BUF`] bytes are never interleaved with other
+    /// writes, larger ones may be split.
+    fn write_with(&self, buf: &[u8], nonblocking: bool) -> LinuxResult<usize> {
+        if !self.writable() {
+            return Err(LinuxError::EPERM);
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let shared = &self.shared;
+        // space needed before writing anything
+        let min_space = if buf.len() <= PIPE_BUF { buf.len() } else { 1 };
+        let mut write_size = 0;
+        loop {
+            if !shared.has_readers() {
+                return if write_size > 0 {
+                    Ok(write_size)
+                } else {
+                    Err(LinuxError::EPIPE)
+                };
+            }
+            let mut ring_buffer = shared.buffer.lock();
+            if ring_buffer.available_write() >= min_space {
+                write_size += ring_buffer.write(&buf[write_size..]);
+                drop(ring_buffer);
+                shared.wake();
+                if write_size == buf.len() {
                     return Ok(write_size);
                 }
-                ring_buffer.write_byte(buf[write_size]);
-                write_size += 1;
+            } else {
+                drop(ring_buffer);
+            }
+            if nonblocking {
+                return if write_size > 0 {
+                    Ok(write_size)
+                } else {
+                    Err(LinuxError::EAGAIN)
+                };
             }
+            shared.wait_until(|| {
+                shared.buffer.lock().available_write() >= min_space || !shared.has_readers()
+            });
         }
     }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        if self.readable {
+            self.shared.readers.fetch_sub(1, Ordering::AcqRel);
+        } else {
+            self.shared.writers.fetch_sub(1, Ordering::AcqRel);
+        }
+        // closing an end hangs up the other
+        self.shared.wake();
+    }
+}
+
+impl FileLike for Pipe {
+    fn path(&self) -> AbsPath {
+        AbsPath::new("/dev/pipe")
+    }
+
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if !self.readable() {
+            return Err(LinuxError::EPERM);
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let shared = &self.shared;
+        loop {
+            let read_size = shared.buffer.lock().read(buf);
+            if read_size > 0 {
+                shared.wake();
+                return Ok(read_size);
+            }
+            if !shared.has_writers() {
+                // write end is closed, read 0 bytes.
+                return Ok(0);
+            }
+            if self.nonblocking() {
+                return Err(LinuxError::EAGAIN);
+            }
+            shared
+                .wait_until(|| shared.buffer.lock().available_read() > 0 || !shared.has_writers());
+        }
+    }
+
+    /// Writes of at most [`PIPE_BUF`] bytes are never interleaved with other
+    /// writes, larger ones may be split.
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        self.write_with(buf, self.nonblocking())
+    }
 
     fn flush(&self) -> LinuxResult {
         Ok(())
@@ -214,17 +488,38 @@ impl FileLike for Pipe {
     }
 
     fn poll(&self) -> LinuxResult<PollState> {
-        let buf = self.buffer.lock();
+        let buf = self.shared.buffer.lock();
         Ok(PollState {
             readable: self.readable() && buf.available_read() > 0,
-            writable: self.writable() && buf.available_write() > 0,
-            pollhup: self.write_end_close(),
+            // a writer without readers is woken up to get `EPIPE`
+            writable: self.writable()
+                && (buf.available_write() >= PIPE_BUF || !self.shared.has_readers()),
+            pollhup: self.readable() && !self.shared.has_writers(),
         })
     }
 
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
         Ok(())
     }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        self.shared.observers.add(observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        self.shared.observers.remove(observer, key);
+    }
 }
 
 /// Create a pipe
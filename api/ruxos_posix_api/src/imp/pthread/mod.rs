@@ -178,6 +178,14 @@ impl Pthread {
     }
 }
 
+/// Returns the task of the thread `tid`, if it has not exited.
+pub(crate) fn task_by_tid(tid: u64) -> Option<AxTaskRef> {
+    let threads = TID_TO_PTHREAD.read();
+    let thread = threads.get(&tid)?.0 as *const Pthread;
+    // the struct is only freed after its entry is removed
+    Some(unsafe { (*thread).inner.clone() })
+}
+
 /// Returns the `pthread` struct of current thread.
 pub fn sys_pthread_self() -> ctypes::pthread_t {
     Pthread::current().expect("fail to get current thread") as *const Pthread as _
@@ -234,6 +242,7 @@ pub fn sys_exit_group(status: c_int) -> ! {
 
     //  drop all file opened by current task
     current().fs.lock().as_mut().unwrap().close_all_files();
+    crate::imp::task::release_process(true);
 
     #[cfg(feature = "multitask")]
     ruxtask::exit(status);
@@ -15,10 +15,8 @@ use crate::{
     ctypes::{self, k_sigaction},
     sys_sigaction,
 };
-use core::{
-    ffi::c_int,
-    sync::atomic::{AtomicUsize, Ordering},
-};
+use core::ffi::c_int;
+use ruxtask::Signal;
 
 enum RTSigprocmaskHow {
     Block = 0,
@@ -38,8 +36,6 @@ impl TryFrom<c_int> for RTSigprocmaskHow {
     }
 }
 
-static mut MASK_TMP: AtomicUsize = AtomicUsize::new(0);
-
 fn set_mask(old: *mut usize, new: usize) {
     unsafe {
         *old = new;
@@ -51,6 +47,8 @@ fn get_mask(mask: *const usize) -> usize {
 }
 
 /// Set mask for given thread
+///
+/// Bit `n - 1` of a user `sigset_t` stands for signal `n`, as in the kernel.
 pub fn sys_rt_sigprocmask(
     how: c_int,
     _new_mask: *const usize,
@@ -63,29 +61,69 @@ pub fn sys_rt_sigprocmask(
     );
 
     syscall_body!(sys_rt_sigprocmask, {
+        let old = Signal::blocked(None);
         if !_old_mask.is_null() {
-            unsafe {
-                let new = MASK_TMP.load(Ordering::Relaxed);
-                set_mask(_old_mask, new);
-            }
+            set_mask(_old_mask, old as usize);
         }
 
         if !_new_mask.is_null() {
-            unsafe {
-                let set = get_mask(_new_mask);
-                match how.try_into() {
-                    Ok(RTSigprocmaskHow::Block) => MASK_TMP.fetch_or(set, Ordering::Relaxed),
-                    Ok(RTSigprocmaskHow::UnBlock) => MASK_TMP.fetch_and(!set, Ordering::Relaxed),
-                    Ok(RTSigprocmaskHow::SetMask) => MASK_TMP.swap(set, Ordering::Relaxed),
-                    _ => return Err(LinuxError::EINVAL),
-                };
-            }
+            let set = get_mask(_new_mask) as u64;
+            let new = match how.try_into() {
+                Ok(RTSigprocmaskHow::Block) => old | set,
+                Ok(RTSigprocmaskHow::UnBlock) => old & !set,
+                Ok(RTSigprocmaskHow::SetMask) => set,
+                _ => return Err(LinuxError::EINVAL),
+            };
+            Signal::blocked(Some(new));
         }
 
         Ok(0)
     })
 }
 
+/// Wait for one of the signals in `set` to be pending and take it, storing
+/// where it comes from in `info`
+///
+/// Gives up with `EAGAIN` after `timeout` if it is not null.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub unsafe fn sys_rt_sigtimedwait(
+    set: *const ctypes::sigset_t,
+    info: *mut ctypes::siginfo_t,
+    timeout: *const ctypes::timespec,
+    sigsetsize: ctypes::size_t,
+) -> c_int {
+    debug!(
+        "sys_rt_sigtimedwait <= set: {:p}, info: {:p}, timeout: {:p}",
+        set, info, timeout
+    );
+    syscall_body!(sys_rt_sigtimedwait, {
+        if sigsetsize != 8 {
+            return Err(LinuxError::EINVAL);
+        }
+        if set.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let mask = (*set).__bits[0] as u64;
+        let deadline = match timeout.as_ref() {
+            None => None,
+            Some(ts) if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) => {
+                return Err(LinuxError::EINVAL);
+            }
+            Some(ts) => Some(ruxhal::time::current_time() + core::time::Duration::from(*ts)),
+        };
+        let (signo, si) = Signal::wait_dequeue(&ruxtask::current().signal_if, mask, deadline)
+            .ok_or(LinuxError::EAGAIN)?;
+        if !info.is_null() {
+            let mut siginfo: ctypes::siginfo_t = core::mem::zeroed();
+            siginfo.si_signo = signo as c_int;
+            siginfo.si_code = si.code;
+            siginfo.__si_fields.__si_common.__first.__piduid.si_pid = si.pid;
+            *info = siginfo;
+        }
+        Ok(signo as c_int)
+    })
+}
+
 /// sigaction syscall for A64 musl
 ///
 /// TODO: if sa is 0, return now action
@@ -14,7 +14,20 @@ use crate::ctypes::k_sigaction;
 use crate::ctypes::{self, pid_t};
 
 use axerrno::LinuxError;
-use ruxtask::{rx_sigaction, Signal};
+use ruxtask::signal::NSIG;
+use ruxtask::{rx_sigaction, SigInfo, Signal};
+
+struct ProcessExitImpl;
+
+#[crate_interface::impl_interface]
+impl ruxtask::signal::ProcessExitIf for ProcessExitImpl {
+    fn exit_process(exit_code: i32) {
+        #[cfg(feature = "multitask")]
+        crate::sys_exit_group(exit_code);
+        #[cfg(not(feature = "multitask"))]
+        crate::sys_exit(exit_code);
+    }
+}
 
 /// Set signal handler
 pub fn sys_sigaction(
@@ -59,11 +72,7 @@ pub unsafe fn sys_getitimer(which: c_int, curr_value: *mut ctypes::itimerval) ->
         if ddl.as_nanos() == 0 {
             return Err(LinuxError::EINVAL);
         }
-        let mut now: ctypes::timespec = ctypes::timespec::default();
-        unsafe {
-            crate::sys_clock_gettime(0, &mut now);
-        }
-        let now = Duration::from(now);
+        let now = ruxhal::time::current_time();
         if ddl > now {
             (*curr_value).it_value = ctypes::timeval::from(ddl - now);
         } else {
@@ -90,16 +99,53 @@ pub unsafe fn sys_sigaltstack(
 pub fn sys_kill(pid: pid_t, sig: c_int) -> c_int {
     debug!("sys_kill <= pid {} sig {}", pid, sig);
     syscall_body!(sys_kill, {
-        match Signal::signal(sig as _, true) {
-            None => Err(LinuxError::EINVAL),
-            Some(_) => Ok(0),
+        if !(0..NSIG as c_int).contains(&sig) {
+            return Err(LinuxError::EINVAL);
+        }
+        if sig != 0 {
+            let curr = ruxtask::current();
+            let info = SigInfo {
+                code: SigInfo::SI_USER,
+                pid: curr.process_id().as_u64() as i32,
+            };
+            Signal::raise(&curr.signal_if, sig as u8, info);
         }
+        Ok(0)
     })
 }
 
+/// Raise `SIGPIPE` on the calling process after a write to a pipe or socket
+/// without readers failed with `EPIPE`, even from one of its workers.
+pub(crate) fn raise_sigpipe() {
+    let curr = ruxtask::current();
+    let info = SigInfo {
+        code: SigInfo::SI_USER,
+        pid: curr.process_id().as_u64() as i32,
+    };
+    Signal::raise(&curr.signal_if, ctypes::SIGPIPE as u8, info);
+}
+
 /// send a signal to a thread
-/// TODO: send to the specified thread.
 pub fn sys_tkill(tid: pid_t, sig: c_int) -> c_int {
     debug!("sys_tkill <= tid {} sig {}", tid, sig);
+    #[cfg(feature = "multitask")]
+    return syscall_body!(sys_tkill, {
+        if !(0..NSIG as c_int).contains(&sig) {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = ruxtask::current();
+        let task = crate::imp::pthread::task_by_tid(tid as u64)
+            .filter(|task| tid > 0 && task.process_id() == curr.process_id())
+            .ok_or(LinuxError::ESRCH)?;
+        if sig != 0 {
+            let info = SigInfo {
+                code: SigInfo::SI_TKILL,
+                pid: curr.process_id().as_u64() as i32,
+            };
+            Signal::raise_thread(&task, sig as u8, info);
+        }
+        Ok(0)
+    });
+    #[cfg(not(feature = "multitask"))]
     sys_kill(tid, sig)
 }
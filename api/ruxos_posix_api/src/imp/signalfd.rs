@@ -0,0 +1,196 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! `signalfd` implementation.
+//!
+//! Reading a signalfd takes signals out of the pending sets of the process
+//! that created it, and of the calling thread if it belongs to that process,
+//! so the signals should be blocked by `sigprocmask` to keep them from being
+//! handled first.
+
+use alloc::sync::{Arc, Weak};
+use core::ffi::c_int;
+use core::mem::size_of;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use ruxfdtable::{FileLike, PollObserver, RuxStat};
+use ruxfs::AbsPath;
+use ruxtask::fs::{add_file_like, get_file_like};
+use ruxtask::Signal;
+use spinlock::SpinNoIrq;
+
+use crate::{ctypes, imp::fs::flags_to_options};
+
+pub struct SignalFd {
+    /// Signals to accept, bit n - 1 for signal n.
+    mask: AtomicU64,
+    nonblocking: AtomicBool,
+    /// Signals of the process that created the signalfd, even if it is read
+    /// or polled by another task, such as an io_uring worker.
+    signal_if: Arc<SpinNoIrq<Signal>>,
+}
+
+impl SignalFd {
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<SignalFd>()
+            .map_err(|_| LinuxError::EINVAL)
+    }
+}
+
+impl FileLike for SignalFd {
+    fn path(&self) -> AbsPath {
+        AbsPath::new("/signalfd")
+    }
+
+    /// Reads as many pending signals as fit in `buf`, one `signalfd_siginfo` each.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        const INFO_SIZE: usize = size_of::<ctypes::signalfd_siginfo>();
+        if buf.len() < INFO_SIZE {
+            return Err(LinuxError::EINVAL);
+        }
+        let mask = self.mask.load(Ordering::Relaxed);
+        let mut read_size = 0;
+        while read_size + INFO_SIZE <= buf.len() {
+            // only block for the first signal
+            let sig = if read_size == 0 && !self.nonblocking.load(Ordering::Relaxed) {
+                Signal::wait_dequeue(&self.signal_if, mask, None)
+            } else {
+                Signal::dequeue_from(&self.signal_if, mask)
+            };
+            let Some((signo, si)) = sig else {
+                break;
+            };
+            let info = ctypes::signalfd_siginfo {
+                ssi_signo: signo as u32,
+                ssi_code: si.code,
+                ssi_pid: si.pid as u32,
+                ..Default::default()
+            };
+            let bytes = unsafe {
+                core::slice::from_raw_parts(
+                    &info as *const ctypes::signalfd_siginfo as *const u8,
+                    INFO_SIZE,
+                )
+            };
+            buf[read_size..read_size + INFO_SIZE].copy_from_slice(bytes);
+            read_size += INFO_SIZE;
+        }
+        if read_size == 0 {
+            return Err(LinuxError::EAGAIN);
+        }
+        Ok(read_size)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn flush(&self) -> LinuxResult {
+        Ok(())
+    }
+
+    fn stat(&self) -> LinuxResult<RuxStat> {
+        let st_mode = 0o600u32; // rw-------
+        Ok(RuxStat::from(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            ..Default::default()
+        }))
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let pending = Signal::pending(&self.signal_if);
+        Ok(PollState {
+            readable: pending & self.mask.load(Ordering::Relaxed) != 0,
+            writable: false,
+            pollhup: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        Signal::add_poll_observer(&self.signal_if, observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        Signal::remove_poll_observer(&self.signal_if, observer, key);
+    }
+}
+
+/// Create a file descriptor to accept the signals in `mask`, or update the
+/// mask of the signalfd `fd` if it is not `-1`.
+pub unsafe fn sys_signalfd4(
+    fd: c_int,
+    mask: *const ctypes::sigset_t,
+    sizemask: ctypes::size_t,
+    flags: c_int,
+) -> c_int {
+    debug!(
+        "sys_signalfd4 <= fd: {}, mask: {:p}, sizemask: {}, flags: {:#x}",
+        fd, mask, sizemask, flags
+    );
+    syscall_body!(sys_signalfd4, {
+        let flags = flags as u32;
+        if flags & !(ctypes::SFD_NONBLOCK | ctypes::SFD_CLOEXEC) != 0 || sizemask != 8 {
+            return Err(LinuxError::EINVAL);
+        }
+        if mask.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let new_mask = (*mask).__bits[0] as u64;
+
+        if fd != -1 {
+            SignalFd::from_fd(fd)?
+                .mask
+                .store(new_mask, Ordering::Relaxed);
+            return Ok(fd);
+        }
+        let signalfd = SignalFd {
+            mask: AtomicU64::new(new_mask),
+            nonblocking: AtomicBool::new(flags & ctypes::SFD_NONBLOCK != 0),
+            signal_if: ruxtask::current().signal_if.clone(),
+        };
+        add_file_like(
+            Arc::new(signalfd),
+            flags_to_options((flags & ctypes::SFD_CLOEXEC) as c_int, 0),
+        )
+    })
+}
+
+/// Create a signalfd, same as `signalfd4` with no flags.
+pub unsafe fn sys_signalfd(
+    fd: c_int,
+    mask: *const ctypes::sigset_t,
+    sizemask: ctypes::size_t,
+) -> c_int {
+    sys_signalfd4(fd, mask, sizemask, 0)
+}
@@ -0,0 +1,405 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! In-kernel data transfer between file descriptors: `sendfile`, `splice`,
+//! `tee` and `copy_file_range`.
+//!
+//! Data never reaches user space, but it is still copied: pages are not moved
+//! between files and pipes. Splicing between two pipes copies straight from
+//! one pipe buffer to the other, other transfers go through a kernel buffer
+//! of [`TRANSFER_CHUNK_SIZE`] bytes. Files of ruxfs are accessed with
+//! `read_at`/`write_at`, while sockets go through their `FileLike`
+//! implementations.
+//!
+//! No data is lost if the output fails: files and pipes are only consumed as
+//! far as the data was written out, and no more is read from other streams
+//! than the output pipe has room for.
+
+use alloc::{sync::Arc, vec};
+use core::ffi::{c_int, c_uint};
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::SeekFrom;
+use ruxfdtable::FileLike;
+use ruxtask::fs::{get_file_like, File};
+
+use crate::ctypes;
+#[cfg(feature = "pipe")]
+use crate::imp::pipe::Pipe;
+
+/// Size of the kernel buffer used for one round of transfer.
+const TRANSFER_CHUNK_SIZE: usize = 0x10000;
+
+/// One side of a transfer.
+enum Stream {
+    /// A ruxfs file accessed at `offset`. If `cursor` is set, the offset was
+    /// taken from the file cursor, which is updated when the transfer ends.
+    File {
+        file: Arc<File>,
+        offset: u64,
+        cursor: bool,
+    },
+    /// A pipe, whose data is only consumed after it is written out.
+    #[cfg(feature = "pipe")]
+    Pipe(Arc<Pipe>),
+    /// A socket or other stream without offsets.
+    Stream(Arc<dyn FileLike>),
+}
+
+impl Stream {
+    /// Get the stream of `fd`, starting at `*offset` if it is not null.
+    unsafe fn from_fd(fd: c_int, offset: *const ctypes::off_t) -> LinuxResult<Self> {
+        let file_like = get_file_like(fd)?;
+        match file_like.clone().into_any().downcast::<File>() {
+            Ok(file) => {
+                let cursor = offset.is_null();
+                let offset = if cursor {
+                    file.inner.write().seek(SeekFrom::Current(0))?
+                } else if *offset < 0 {
+                    return Err(LinuxError::EINVAL);
+                } else {
+                    *offset as u64
+                };
+                Ok(Stream::File {
+                    file,
+                    offset,
+                    cursor,
+                })
+            }
+            Err(_) if !offset.is_null() => Err(LinuxError::ESPIPE),
+            #[cfg(feature = "pipe")]
+            Err(any) => match any.downcast::<Pipe>() {
+                Ok(pipe) => Ok(Stream::Pipe(pipe)),
+                Err(_) => Ok(Stream::Stream(file_like)),
+            },
+            #[cfg(not(feature = "pipe"))]
+            Err(_) => Ok(Stream::Stream(file_like)),
+        }
+    }
+
+    fn is_file(&self) -> bool {
+        matches!(self, Stream::File { .. })
+    }
+
+    /// Whether data read is only consumed by [`Stream::consume`], so it is
+    /// kept if it can't be written out.
+    fn can_peek(&self) -> bool {
+        !matches!(self, Stream::Stream(_))
+    }
+
+    /// Whether a read or write would not block, files are always ready.
+    fn ready(&self, write: bool) -> bool {
+        let file_like: &dyn FileLike = match self {
+            Stream::File { .. } => return true,
+            #[cfg(feature = "pipe")]
+            Stream::Pipe(pipe) => pipe.as_ref(),
+            Stream::Stream(file_like) => file_like.as_ref(),
+        };
+        file_like.poll().is_ok_and(|state| {
+            if write {
+                state.writable
+            } else {
+                state.readable || state.pollhup
+            }
+        })
+    }
+
+    /// How many bytes can be written without blocking, waiting for some room
+    /// in a pipe unless `nonblocking` is set.
+    fn room(&self, nonblocking: bool) -> LinuxResult<usize> {
+        match self {
+            #[cfg(feature = "pipe")]
+            Stream::Pipe(pipe) => pipe.wait_room(nonblocking || pipe.is_nonblocking()),
+            _ => Ok(usize::MAX),
+        }
+    }
+
+    /// Read without consuming the data of a file or pipe, see [`Stream::consume`].
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        match self {
+            Stream::File { file, offset, .. } => Ok(file.inner.read().read_at(*offset, buf)?),
+            #[cfg(feature = "pipe")]
+            Stream::Pipe(pipe) => pipe.peek(buf, pipe.is_nonblocking()),
+            Stream::Stream(file_like) => file_like.read(buf),
+        }
+    }
+
+    fn consume(&mut self, len: usize) {
+        match self {
+            Stream::File { offset, .. } => *offset += len as u64,
+            #[cfg(feature = "pipe")]
+            Stream::Pipe(pipe) => pipe.consume(len),
+            Stream::Stream(_) => {}
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> LinuxResult<usize> {
+        match self {
+            Stream::File {
+                file,
+                offset,
+                cursor: true,
+            } => {
+                // write at the cursor to respect `O_APPEND`
+                let mut file = file.inner.write();
+                let write_len = file.write(buf)?;
+                *offset = file.seek(SeekFrom::Current(0))?;
+                Ok(write_len)
+            }
+            Stream::File { file, offset, .. } => {
+                let write_len = file.inner.read().write_at(*offset, buf)?;
+                *offset += write_len as u64;
+                Ok(write_len)
+            }
+            // the room was found by `Stream::room`
+            #[cfg(feature = "pipe")]
+            Stream::Pipe(pipe) => pipe.write_reserved(buf),
+            Stream::Stream(file_like) => file_like.write(buf),
+        }
+    }
+
+    /// Store the final offset back to the file cursor or to `*offset`.
+    unsafe fn finish(&self, offset: *mut ctypes::off_t) -> LinuxResult {
+        if let Stream::File {
+            file,
+            offset: end,
+            cursor,
+        } = self
+        {
+            if *cursor {
+                file.inner.write().seek(SeekFrom::Start(*end))?;
+            } else if !offset.is_null() {
+                *offset = *end as ctypes::off_t;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writing to a pipe or socket without readers also raises `SIGPIPE`, as in
+/// `write`.
+fn check_epipe<T>(ret: LinuxResult<T>) -> LinuxResult<T> {
+    #[cfg(feature = "signal")]
+    if matches!(ret, Err(LinuxError::EPIPE)) {
+        crate::imp::signal::raise_sigpipe();
+    }
+    ret
+}
+
+/// Move up to `len` bytes from `src` to `dst`.
+///
+/// Only the first round may block when `nonblocking` is not set, later rounds
+/// stop as soon as a pipe or socket is not ready, like a short `read`.
+fn transfer(
+    src: &mut Stream,
+    dst: &mut Stream,
+    len: usize,
+    nonblocking: bool,
+) -> LinuxResult<usize> {
+    let mut buf = vec![0; len.min(TRANSFER_CHUNK_SIZE)];
+    let mut total = 0;
+    while total < len {
+        let must_not_block = total > 0 || nonblocking;
+        if must_not_block && !(src.ready(false) && dst.ready(true)) {
+            if total == 0 {
+                return Err(LinuxError::EAGAIN);
+            }
+            break;
+        }
+        // data read from a stream is gone, so read no more than fits
+        let room = match dst.room(must_not_block) {
+            Ok(room) => room,
+            Err(_) if total > 0 => break,
+            Err(e) => return Err(e),
+        };
+        let chunk = (len - total).min(buf.len()).min(room);
+        let read_len = match src.read(&mut buf[..chunk]) {
+            Ok(0) => break,
+            Ok(read_len) => read_len,
+            Err(_) if total > 0 => break,
+            Err(e) => return Err(e),
+        };
+
+        let mut write_len = 0;
+        while write_len < read_len {
+            match dst.write(&buf[write_len..read_len]) {
+                Ok(0) => break,
+                Ok(n) => write_len += n,
+                Err(_) if total + write_len > 0 => break,
+                Err(e) => return Err(e),
+            }
+            if src.can_peek() {
+                break;
+            }
+        }
+        src.consume(write_len);
+        total += write_len;
+        if write_len < read_len {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+/// Transfer `count` bytes from the file `in_fd` to `out_fd`.
+///
+/// If `offset` is not null, reading starts from `*offset`, which is updated
+/// afterwards, and the file cursor of `in_fd` is left unchanged.
+pub unsafe fn sys_sendfile(
+    out_fd: c_int,
+    in_fd: c_int,
+    offset: *mut ctypes::off_t,
+    count: ctypes::size_t,
+) -> ctypes::ssize_t {
+    debug!(
+        "sys_sendfile <= out_fd: {}, in_fd: {}, offset: {:p}, count: {}",
+        out_fd, in_fd, offset, count
+    );
+    syscall_body!(sys_sendfile, {
+        let mut src = Stream::from_fd(in_fd, offset)?;
+        if !src.is_file() {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut dst = Stream::from_fd(out_fd, core::ptr::null())?;
+        let len = check_epipe(transfer(&mut src, &mut dst, count as usize, false))?;
+        src.finish(offset)?;
+        dst.finish(core::ptr::null_mut())?;
+        Ok(len as ctypes::ssize_t)
+    })
+}
+
+/// Move up to `len` bytes between `fd_in` and `fd_out`, one of which must be a pipe.
+///
+/// The offsets must be null for pipes. For files they work as in `sendfile`.
+#[cfg(feature = "pipe")]
+pub unsafe fn sys_splice(
+    fd_in: c_int,
+    off_in: *mut ctypes::off_t,
+    fd_out: c_int,
+    off_out: *mut ctypes::off_t,
+    len: ctypes::size_t,
+    flags: c_uint,
+) -> ctypes::ssize_t {
+    debug!(
+        "sys_splice <= fd_in: {}, off_in: {:p}, fd_out: {}, off_out: {:p}, len: {}, flags: {:#x}",
+        fd_in, off_in, fd_out, off_out, len, flags
+    );
+    syscall_body!(sys_splice, {
+        if flags
+            & !(ctypes::SPLICE_F_MOVE
+                | ctypes::SPLICE_F_NONBLOCK
+                | ctypes::SPLICE_F_MORE
+                | ctypes::SPLICE_F_GIFT)
+            != 0
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut src = Stream::from_fd(fd_in, off_in)?;
+        let mut dst = Stream::from_fd(fd_out, off_out)?;
+        let nonblocking = flags & ctypes::SPLICE_F_NONBLOCK != 0;
+        match (&src, &dst) {
+            (Stream::Pipe(pipe_in), Stream::Pipe(pipe_out)) => {
+                if pipe_in.is_same_pipe(pipe_out) {
+                    return Err(LinuxError::EINVAL);
+                }
+                if len == 0 {
+                    return Ok(0);
+                }
+                let len = check_epipe(pipe_in.splice_to(pipe_out, len as usize, nonblocking))?;
+                return Ok(len as ctypes::ssize_t);
+            }
+            (Stream::Pipe(_), _) | (_, Stream::Pipe(_)) => {}
+            _ => return Err(LinuxError::EINVAL),
+        }
+        if len == 0 {
+            return Ok(0);
+        }
+        let len = check_epipe(transfer(&mut src, &mut dst, len as usize, nonblocking))?;
+        src.finish(off_in)?;
+        dst.finish(off_out)?;
+        Ok(len as ctypes::ssize_t)
+    })
+}
+
+/// Duplicate up to `len` bytes from the pipe `fd_in` to the pipe `fd_out`,
+/// without consuming them from `fd_in`.
+#[cfg(feature = "pipe")]
+pub fn sys_tee(fd_in: c_int, fd_out: c_int, len: ctypes::size_t, flags: c_uint) -> ctypes::ssize_t {
+    debug!(
+        "sys_tee <= fd_in: {}, fd_out: {}, len: {}, flags: {:#x}",
+        fd_in, fd_out, len, flags
+    );
+    syscall_body!(sys_tee, {
+        let as_pipe = |fd| {
+            get_file_like(fd)?
+                .into_any()
+                .downcast::<Pipe>()
+                .map_err(|_| LinuxError::EINVAL)
+        };
+        let (pipe_in, pipe_out) = (as_pipe(fd_in)?, as_pipe(fd_out)?);
+        if len == 0 {
+            return Ok(0);
+        }
+        let nonblocking = flags & ctypes::SPLICE_F_NONBLOCK != 0;
+        let len = check_epipe(pipe_in.tee(&pipe_out, len as usize, nonblocking))?;
+        Ok(len as ctypes::ssize_t)
+    })
+}
+
+/// Copy up to `len` bytes between two ruxfs files.
+///
+/// The offsets work as in `sendfile`, and `flags` must be 0.
+pub unsafe fn sys_copy_file_range(
+    fd_in: c_int,
+    off_in: *mut ctypes::off_t,
+    fd_out: c_int,
+    off_out: *mut ctypes::off_t,
+    len: ctypes::size_t,
+    flags: c_uint,
+) -> ctypes::ssize_t {
+    debug!(
+        "sys_copy_file_range <= fd_in: {}, off_in: {:p}, fd_out: {}, off_out: {:p}, len: {}, flags: {:#x}",
+        fd_in, off_in, fd_out, off_out, len, flags
+    );
+    syscall_body!(sys_copy_file_range, {
+        if flags != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut src = Stream::from_fd(fd_in, off_in)?;
+        let mut dst = Stream::from_fd(fd_out, off_out)?;
+        let (
+            Stream::File {
+                file: file_in,
+                offset: start_in,
+                ..
+            },
+            Stream::File {
+                file: file_out,
+                offset: start_out,
+                ..
+            },
+        ) = (&src, &dst)
+        else {
+            return Err(LinuxError::EINVAL);
+        };
+        // overlapping ranges of the same file are not allowed, even if it
+        // is opened by different paths
+        let len = len as u64;
+        if file_in.inner.read().lock_key()? == file_out.inner.read().lock_key()?
+            && *start_in < start_out.saturating_add(len)
+            && *start_out < start_in.saturating_add(len)
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let len = transfer(&mut src, &mut dst, len as usize, false)?;
+        src.finish(off_in)?;
+        dst.finish(off_out)?;
+        Ok(len as ctypes::ssize_t)
+    })
+}
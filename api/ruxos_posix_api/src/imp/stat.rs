@@ -62,10 +62,12 @@ pub fn sys_setpgid(pid: pid_t, pgid: pid_t) -> c_int {
     syscall_body!(sys_setpgid, Ok(0))
 }
 
-/// set process sid (empty implementation)
+/// set process sid, which only detaches the process from its controlling tty
 ///
 /// TODO:
 pub fn sys_setsid() -> c_int {
-    warn!("sys_setsid: do nothing",);
+    warn!("sys_setsid: only detach from the controlling tty",);
+    #[cfg(all(feature = "signal", feature = "multitask"))]
+    ruxtask::Signal::set_controlling_tty(&ruxtask::current().signal_if, None);
     syscall_body!(sys_setsid, Ok(0))
 }
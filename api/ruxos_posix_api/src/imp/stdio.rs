@@ -42,9 +42,10 @@ fn get_stdio_tty_name() -> &'static str {
 }
 
 impl Read for StdinRaw {
-    // Non-blocking read, returns number of bytes read.
+    // Non-blocking read, returns number of bytes read, or `WouldBlock` if
+    // there is no input yet.
     fn read(&mut self, buf: &mut [u8]) -> AxResult<usize> {
-        Ok(ruxhal::tty_read(buf, get_stdio_tty_name()))
+        ruxhal::tty_read(buf, get_stdio_tty_name()).ok_or(axerrno::AxError::WouldBlock)
     }
 }
 
@@ -65,19 +66,20 @@ pub struct Stdin {
 }
 
 impl Stdin {
-    // Block until at least one byte is read.
+    // Block until at least one byte is read, or the end of file.
     fn read_blocked(&self, buf: &mut [u8]) -> AxResult<usize> {
-        let read_len = self.inner.lock().read(buf)?;
-        if buf.is_empty() || read_len > 0 {
-            return Ok(read_len);
+        if buf.is_empty() {
+            return Ok(0);
         }
         // try again until we get something
         loop {
-            let read_len = self.inner.lock().read(buf)?;
-            if read_len > 0 {
-                return Ok(read_len);
+            let result = self.inner.lock().read(buf);
+            match result {
+                Err(axerrno::AxError::WouldBlock) => {
+                    crate::sys_sched_yield();
+                }
+                result => return result,
             }
-            crate::sys_sched_yield();
         }
     }
 
@@ -172,10 +174,18 @@ impl ruxfdtable::FileLike for Stdin {
         })
     }
 
+    fn ioctl(&self, cmd: usize, arg: usize) -> LinuxResult<usize> {
+        ruxhal::tty_ioctl(get_stdio_tty_name(), cmd, arg).ok_or(LinuxError::ENOTTY)
+    }
+
     fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
         self.nonblocking.store(nonblocking, Ordering::Relaxed);
         Ok(())
     }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
 }
 
 #[cfg(feature = "fd")]
@@ -218,6 +228,10 @@ impl ruxfdtable::FileLike for Stdout {
         })
     }
 
+    fn ioctl(&self, cmd: usize, arg: usize) -> LinuxResult<usize> {
+        ruxhal::tty_ioctl(get_stdio_tty_name(), cmd, arg).ok_or(LinuxError::ENOTTY)
+    }
+
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
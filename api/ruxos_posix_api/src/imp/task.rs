@@ -99,12 +99,10 @@ pub unsafe fn sys_wait4(
                 if task.state() == ruxtask::task::TaskState::Exited {
                     if !wstatus.is_null() {
                         unsafe {
-                            // lower 8 bits of exit_code is the signal number, while upper 8 bits of exit_code is the exit status
-                            // according to "bits/waitstatus.h" in glibc source code.
-                            // TODO: add signal number to wstatus
-                            wstatus.write(task.exit_code() << 8);
+                            wstatus.write(task.wait_status());
                         }
                     }
+                    ruxtask::current().add_children_cpu_time(task);
                     process_map.remove(&(pid as u64));
                     return pid;
                 } else if options & WNOHANG != 0 {
@@ -133,12 +131,10 @@ pub unsafe fn sys_wait4(
                     // add to to_remove list
                     if !wstatus.is_null() {
                         unsafe {
-                            // lower 8 bits of exit_code is the signal number, while upper 8 bits of exit_code is the exit status
-                            // according to "bits/waitstatus.h" in glibc source code.
-                            // TODO: add signal number to wstatus
-                            wstatus.write(task.exit_code() << 8);
+                            wstatus.write(task.wait_status());
                         }
                     }
+                    ruxtask::current().add_children_cpu_time(task);
                     let _ = to_remove.insert(*child_pid);
                     break;
                 }
@@ -162,6 +158,21 @@ pub unsafe fn sys_wait4(
     }
 }
 
+/// Releases the resources the current process holds outside its tasks, on
+/// its exit if `exit` is set, or else on `execve`.
+pub(crate) fn release_process(exit: bool) {
+    #[cfg(feature = "timer")]
+    crate::imp::timer::delete_process_timers(ruxtask::current().process_id().as_u64());
+    #[cfg(feature = "aio")]
+    crate::imp::aio::release_process_aio(ruxtask::current().process_id().as_u64());
+    #[cfg(feature = "sysvipc")]
+    crate::imp::ipc::shm::exit_shm();
+    if exit {
+        #[cfg(feature = "sysvipc")]
+        crate::imp::ipc::sem::exit_sem();
+    }
+}
+
 /// Exit current task
 pub fn sys_exit(exit_code: c_int) -> ! {
     debug!("sys_exit <= {}", exit_code);
@@ -8,15 +8,67 @@
  */
 
 use core::ffi::{c_int, c_long};
+#[cfg(all(feature = "multitask", feature = "irq"))]
+use core::sync::atomic::{AtomicU64, Ordering};
 use core::time::Duration;
 
 use crate::ctypes;
 
-use axerrno::LinuxError;
+use axerrno::{LinuxError, LinuxResult};
 
 // nanoseconds per a second
 const NANO_PER_SECOND: i64 = 1000000000;
 
+/// Woken when the wall clock is set, to re-arm the sleeps until a realtime.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+static WALL_CLOCK_SET: ruxtask::WaitQueue = ruxtask::WaitQueue::new();
+
+/// The wall clock generation the waits until a realtime were last woken for.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+static WALL_CLOCK_NOTIFIED: AtomicU64 = AtomicU64::new(0);
+
+/// Wakes the sleeps until a realtime and re-arms the realtime timerfds, once
+/// per change of the wall clock by `clock_settime` or a re-sync with the RTC.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+fn wall_clock_changed() {
+    let generation = ruxhal::time::wall_clock_generation();
+    if WALL_CLOCK_NOTIFIED.swap(generation, Ordering::AcqRel) == generation {
+        return;
+    }
+    WALL_CLOCK_SET.notify_all(true);
+    #[cfg(feature = "timerfd")]
+    crate::imp::timerfd::clock_was_set();
+}
+
+/// Has the timer interrupt call [`wall_clock_changed`] when the wall clock is
+/// re-synced with the RTC, before anything waits until a realtime.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub(crate) fn watch_wall_clock() {
+    static WATCHING: spin::Once = spin::Once::new();
+    WATCHING.call_once(|| {
+        WALL_CLOCK_NOTIFIED.store(ruxhal::time::wall_clock_generation(), Ordering::Release);
+        ruxtask::timers::set_wall_clock_handler(wall_clock_changed);
+    });
+}
+
+/// Sleeps until the wall clock reaches `abs_time`, re-armed each time the
+/// wall clock is set.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+fn sleep_until_realtime(abs_time: Duration) {
+    watch_wall_clock();
+    loop {
+        let generation = ruxhal::time::wall_clock_generation();
+        let deadline = Clock::Realtime.to_monotonic_deadline(abs_time);
+        let now = ruxhal::time::current_time();
+        if now >= deadline {
+            return;
+        }
+        WALL_CLOCK_SET.wait_timeout_until(deadline - now, || {
+            ruxhal::time::wall_clock_generation() != generation
+        });
+    }
+}
+
 impl From<ctypes::timespec> for Duration {
     fn from(ts: ctypes::timespec) -> Self {
         Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
@@ -47,61 +99,170 @@ impl From<Duration> for ctypes::timeval {
     }
 }
 
-/// Get clock time since booting
-pub unsafe fn sys_clock_gettime(_clk: ctypes::clockid_t, ts: *mut ctypes::timespec) -> c_int {
+/// Clocks that can be read by `clock_gettime`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Clock {
+    /// Settable wall clock.
+    Realtime,
+    /// Time since boot, not affected by `clock_settime`.
+    Monotonic,
+    /// CPU time of a process, `0` for the current process.
+    ProcessCpu(u64),
+    /// CPU time of a thread, `0` for the current thread.
+    ThreadCpu(u64),
+}
+
+impl Clock {
+    pub(crate) fn from_clockid(clk: ctypes::clockid_t) -> LinuxResult<Self> {
+        if clk < 0 {
+            // Dynamic CPU-time clocks made by `clock_getcpuclockid` and
+            // `pthread_getcpuclockid`, encoded as `(~id << 3) | type`.
+            const CPUCLOCK_PERTHREAD_MASK: ctypes::clockid_t = 4;
+            const CPUCLOCK_CLOCK_MASK: ctypes::clockid_t = 3;
+            const CPUCLOCK_SCHED: ctypes::clockid_t = 2;
+            if clk & CPUCLOCK_CLOCK_MASK > CPUCLOCK_SCHED {
+                return Err(LinuxError::EINVAL);
+            }
+            let id = !(clk >> 3) as u64;
+            return Ok(if clk & CPUCLOCK_PERTHREAD_MASK != 0 {
+                Self::ThreadCpu(id)
+            } else {
+                Self::ProcessCpu(id)
+            });
+        }
+        match clk as u32 {
+            ctypes::CLOCK_REALTIME | ctypes::CLOCK_REALTIME_COARSE => Ok(Self::Realtime),
+            ctypes::CLOCK_MONOTONIC
+            | ctypes::CLOCK_MONOTONIC_RAW
+            | ctypes::CLOCK_MONOTONIC_COARSE
+            | ctypes::CLOCK_BOOTTIME => Ok(Self::Monotonic),
+            ctypes::CLOCK_PROCESS_CPUTIME_ID => Ok(Self::ProcessCpu(0)),
+            ctypes::CLOCK_THREAD_CPUTIME_ID => Ok(Self::ThreadCpu(0)),
+            _ => Err(LinuxError::EINVAL),
+        }
+    }
+
+    pub(crate) fn now(self) -> LinuxResult<Duration> {
+        match self {
+            Self::Realtime => Ok(ruxhal::time::wall_time()),
+            Self::Monotonic => Ok(ruxhal::time::current_time()),
+            #[cfg(feature = "multitask")]
+            Self::ProcessCpu(pid) => {
+                let curr = ruxtask::current();
+                if pid == 0 || pid == curr.process_id().as_u64() {
+                    Ok(curr.process_cpu_time())
+                } else {
+                    ruxtask::task::PROCESS_MAP
+                        .lock()
+                        .get(&pid)
+                        .map(|task| task.process_cpu_time())
+                        .ok_or(LinuxError::EINVAL)
+                }
+            }
+            #[cfg(feature = "multitask")]
+            Self::ThreadCpu(tid) => {
+                let curr = ruxtask::current();
+                if tid == 0 || tid == curr.id().as_u64() {
+                    Ok(curr.thread_cpu_time())
+                } else {
+                    Err(LinuxError::EINVAL)
+                }
+            }
+            // Without multitask, the only task owns all of the CPU time.
+            #[cfg(not(feature = "multitask"))]
+            Self::ProcessCpu(_) | Self::ThreadCpu(_) => Ok(ruxhal::time::current_time()),
+        }
+    }
+
+    /// Converts an absolute time of this clock to a monotonic deadline.
+    pub(crate) fn to_monotonic_deadline(self, abs_time: Duration) -> Duration {
+        match self {
+            Self::Realtime => {
+                let mono_now = ruxhal::time::current_time();
+                let wall_now = ruxhal::time::wall_time();
+                match abs_time.checked_sub(wall_now) {
+                    Some(left) => mono_now + left,
+                    None => mono_now,
+                }
+            }
+            _ => abs_time,
+        }
+    }
+}
+
+/// Get the time of the clock `clk`
+pub unsafe fn sys_clock_gettime(clk: ctypes::clockid_t, ts: *mut ctypes::timespec) -> c_int {
     syscall_body!(sys_clock_gettime, {
         if ts.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        let now = ruxhal::time::current_time().into();
+        let now = Clock::from_clockid(clk)?.now()?.into();
         unsafe { *ts = now };
-        debug!("sys_clock_gettime: {}.{:09}s", now.tv_sec, now.tv_nsec);
+        debug!(
+            "sys_clock_gettime: clk={}, {}.{:09}s",
+            clk, now.tv_sec, now.tv_nsec
+        );
         Ok(0)
     })
 }
 
-/// Get clock time since booting
-pub unsafe fn sys_clock_settime(_clk: ctypes::clockid_t, ts: *const ctypes::timespec) -> c_int {
+/// Set the time of the clock `clk`, only `CLOCK_REALTIME` is settable.
+pub unsafe fn sys_clock_settime(clk: ctypes::clockid_t, ts: *const ctypes::timespec) -> c_int {
     syscall_body!(sys_clock_setttime, {
         if ts.is_null() {
             return Err(LinuxError::EFAULT);
         }
+        if (*ts).tv_nsec < 0 || (*ts).tv_nsec >= NANO_PER_SECOND || (*ts).tv_sec < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if clk != ctypes::CLOCK_REALTIME as ctypes::clockid_t {
+            return Err(LinuxError::EINVAL);
+        }
         let new_tv = Duration::from(*ts);
         debug!(
             "sys_clock_setttime: {}.{:09}s",
             new_tv.as_secs(),
-            new_tv.as_nanos()
+            new_tv.subsec_nanos()
         );
-        ruxhal::time::set_current_time(new_tv);
+        ruxhal::time::set_wall_time(new_tv);
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        wall_clock_changed();
         Ok(0)
     })
 }
 
 /// Return the resolution (precision) of a specified clock `clk_id`.
-/// TODO: Currently we only have one simple clock source in the OS.
-/// We ignore `clk_id` and always return a fixed resolution of 1ns.
-/// In the future, we may:
-///  - Distinguish different clock IDs (e.g., REALTIME vs MONOTONIC).
-///  - Return a more realistic resolution based on hardware capabilities.
+///
+/// The coarse clocks are updated on every timer tick, the others have the
+/// resolution of the hardware counter.
 pub unsafe fn sys_clock_getres(clk_id: ctypes::clockid_t, ts: *mut ctypes::timespec) -> c_int {
     syscall_body!(sys_clock_getres, {
+        Clock::from_clockid(clk_id)?;
         if ts.is_null() {
-            return Err(LinuxError::EFAULT);
+            return Ok(0);
         }
-        (*ts).tv_sec = 0;
-        (*ts).tv_nsec = 1;
-        debug!("sys_clock_getres: clk_id={}, returning 0s + 1ns", clk_id);
+        let res_nanos = match clk_id as u32 {
+            ctypes::CLOCK_REALTIME_COARSE | ctypes::CLOCK_MONOTONIC_COARSE => {
+                ruxhal::time::NANOS_PER_SEC / ruxconfig::TICKS_PER_SEC as u64
+            }
+            _ => ruxhal::time::clock_resolution_nanos(),
+        };
+        (*ts) = Duration::from_nanos(res_nanos).into();
+        debug!("sys_clock_getres: clk_id={}, {}ns", clk_id, res_nanos);
         Ok(0)
     })
 }
 
-/// Sleep until some nanoseconds
+/// Sleep until the clock `which_clock` reaches `req` if `TIMER_ABSTIME` is
+/// set in `flags`, otherwise sleep for the interval `req`.
+///
+/// A sleep until a `CLOCK_REALTIME` time follows the changes of the clock by
+/// `clock_settime`.
 ///
 /// TODO: should be woken by signals, and set errno
-/// TODO: deal with flags
 pub unsafe fn sys_clock_nanosleep(
-    _which_clock: ctypes::clockid_t,
-    _flags: c_int,
+    which_clock: ctypes::clockid_t,
+    flags: c_int,
     req: *const ctypes::timespec,
     rem: *mut ctypes::timespec,
 ) -> c_int {
@@ -111,10 +272,25 @@ pub unsafe fn sys_clock_nanosleep(
                 return Err(LinuxError::EINVAL);
             }
         }
+        let clock = Clock::from_clockid(which_clock)?;
+        if matches!(clock, Clock::ProcessCpu(_) | Clock::ThreadCpu(_)) {
+            // sleeping on CPU-time clocks is not supported
+            return Err(LinuxError::EINVAL);
+        }
+        let req = unsafe { Duration::from(*req) };
 
-        let deadline = unsafe { Duration::from(*req) };
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        if clock == Clock::Realtime && flags & ctypes::TIMER_ABSTIME as c_int != 0 {
+            sleep_until_realtime(req);
+            return Ok(0);
+        }
 
         let now = ruxhal::time::current_time();
+        let deadline = if flags & ctypes::TIMER_ABSTIME as c_int != 0 {
+            clock.to_monotonic_deadline(req)
+        } else {
+            now + req
+        };
 
         if now >= deadline {
             return Ok(0);
@@ -126,11 +302,9 @@ pub unsafe fn sys_clock_nanosleep(
         ruxhal::time::busy_wait_until(deadline);
 
         let after = ruxhal::time::current_time();
-        let actual = after - now;
-        let due = deadline - now;
-
-        if let Some(diff) = due.checked_sub(actual) {
-            if !rem.is_null() {
+        if let Some(diff) = deadline.checked_sub(after) {
+            // the remaining time is only reported for relative sleeps
+            if !rem.is_null() && flags & ctypes::TIMER_ABSTIME as c_int == 0 {
                 unsafe { (*rem) = diff.into() };
             }
             return Err(LinuxError::EINTR);
@@ -178,10 +352,39 @@ pub unsafe fn sys_nanosleep(req: *const ctypes::timespec, rem: *mut ctypes::time
 /// Get time of the day, ignore second parameter
 pub unsafe fn sys_gettimeofday(ts: *mut ctypes::timespec, flags: c_int) -> c_int {
     debug!("sys_gettimeofday <= flags: {}", flags);
-    unsafe { sys_clock_gettime(0, ts) }
+    unsafe { sys_clock_gettime(ctypes::CLOCK_REALTIME as ctypes::clockid_t, ts) }
 }
 
-/// TODO: get process and waited-for child process times
-pub unsafe fn sys_times(_buf: *mut usize) -> c_int {
-    syscall_body!(sys_times, Ok(0))
+/// Number of clock ticks per second reported by `sysconf(_SC_CLK_TCK)`.
+const CLK_TCK: u64 = 100;
+
+fn duration_to_clock_t(d: Duration) -> ctypes::clock_t {
+    (d.as_nanos() / (ruxhal::time::NANOS_PER_SEC / CLK_TCK) as u128) as ctypes::clock_t
+}
+
+/// Get process and waited-for child process times
+///
+/// RuxOS doesn't distinguish between user and system time, all of the CPU
+/// time is reported as user time. Returns the elapsed clock ticks since boot.
+pub unsafe fn sys_times(buf: *mut ctypes::tms) -> c_long {
+    syscall_body!(sys_times, {
+        if !buf.is_null() {
+            #[cfg(feature = "multitask")]
+            let (utime, cutime) = {
+                let curr = ruxtask::current();
+                (curr.process_cpu_time(), curr.children_cpu_time())
+            };
+            #[cfg(not(feature = "multitask"))]
+            let (utime, cutime) = (ruxhal::time::current_time(), Duration::ZERO);
+            unsafe {
+                *buf = ctypes::tms {
+                    tms_utime: duration_to_clock_t(utime),
+                    tms_stime: 0,
+                    tms_cutime: duration_to_clock_t(cutime),
+                    tms_cstime: 0,
+                };
+            }
+        }
+        Ok(duration_to_clock_t(ruxhal::time::current_time()))
+    })
 }
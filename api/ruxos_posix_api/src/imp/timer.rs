@@ -0,0 +1,318 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! POSIX per-process timers (`timer_create` and friends).
+//!
+//! Expirations are driven by callbacks in the timer list of `ruxtask`, so
+//! they fire from the timer interrupt. Deadlines of `CLOCK_REALTIME` timers
+//! are converted to the monotonic clock when the timer is armed. Timers are
+//! deleted when their process exits or calls `execve`.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::ffi::c_int;
+use core::sync::atomic::{AtomicI32, Ordering};
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use ruxtask::signal::NSIG;
+use ruxtask::timers::{cancel_timer_callback, set_timer_callback, TimerCallbackId};
+use ruxtask::{AxTaskRef, SigInfo, Signal, TaskState};
+use spinlock::SpinNoIrq;
+
+use crate::ctypes;
+use crate::imp::time::Clock;
+
+/// Upper bound of the overrun count, `DELAYTIMER_MAX` in Linux.
+const DELAYTIMER_MAX: u32 = i32::MAX as u32;
+
+static TIMERS: SpinNoIrq<BTreeMap<c_int, Arc<PosixTimer>>> = SpinNoIrq::new(BTreeMap::new());
+static NEXT_TIMER_ID: AtomicI32 = AtomicI32::new(0);
+
+#[derive(Default)]
+struct TimerState {
+    /// Next expiration on the monotonic clock, `None` if disarmed.
+    deadline: Option<Duration>,
+    interval: Duration,
+    /// Bumped whenever the timer is re-armed or deleted, to ignore stale callbacks.
+    generation: u64,
+    callback: Option<TimerCallbackId>,
+    /// Expirations missed since the signal of the last expiration was raised.
+    overrun: u32,
+}
+
+/// Receiver of the signal raised on expiration.
+enum SignalTarget {
+    /// `SIGEV_SIGNAL`, any thread of the process.
+    Process(Arc<SpinNoIrq<Signal>>),
+    /// `SIGEV_THREAD_ID`, the signal is dropped once the thread exits.
+    Thread(AxTaskRef),
+}
+
+struct PosixTimer {
+    pid: u64,
+    clock: Clock,
+    /// Signal raised on expiration, `None` for `SIGEV_NONE`.
+    signo: Option<u8>,
+    target: SignalTarget,
+    state: SpinNoIrq<TimerState>,
+}
+
+impl PosixTimer {
+    fn arm(self: &Arc<Self>, state: &mut TimerState, deadline: Duration) {
+        let timer = Arc::downgrade(self);
+        let generation = state.generation;
+        state.deadline = Some(deadline);
+        state.callback = Some(set_timer_callback(deadline, move |now| {
+            Self::expire(timer, generation, now)
+        }));
+    }
+
+    fn disarm(&self, state: &mut TimerState) {
+        state.generation += 1;
+        state.deadline = None;
+        if let Some(id) = state.callback.take() {
+            cancel_timer_callback(id);
+        }
+    }
+
+    fn expire(timer: Weak<Self>, generation: u64, now: Duration) {
+        let Some(timer) = timer.upgrade() else {
+            return;
+        };
+        let mut state = timer.state.lock();
+        if state.generation != generation {
+            return;
+        }
+        let Some(deadline) = state.deadline else {
+            return;
+        };
+
+        let mut missed = 0;
+        if state.interval.is_zero() {
+            state.deadline = None;
+            state.callback = None;
+        } else {
+            let interval = state.interval.as_nanos();
+            missed = now.saturating_sub(deadline).as_nanos() / interval;
+            let next = deadline.as_nanos() + (missed + 1) * interval;
+            let next = Duration::new((next / 1_000_000_000) as u64, (next % 1_000_000_000) as u32);
+            timer.arm(&mut state, next);
+        }
+
+        if let Some(signo) = timer.signo {
+            let missed = missed.min(DELAYTIMER_MAX as u128) as u32;
+            let info = SigInfo {
+                code: SigInfo::SI_TIMER,
+                pid: 0,
+            };
+            let was_pending = match &timer.target {
+                SignalTarget::Process(signal_if) => Signal::raise(signal_if, signo, info),
+                SignalTarget::Thread(task) if task.state() == TaskState::Exited => return,
+                SignalTarget::Thread(task) => Signal::raise_thread(task, signo, info),
+            };
+            state.overrun = if was_pending {
+                // the previous signal is still pending
+                state.overrun.saturating_add(missed).saturating_add(1)
+            } else {
+                missed
+            }
+            .min(DELAYTIMER_MAX);
+        }
+    }
+
+    fn get(&self) -> ctypes::itimerspec {
+        let state = self.state.lock();
+        let value = state.deadline.map_or(Duration::ZERO, |deadline| {
+            // an armed timer never reports a zero value
+            deadline
+                .saturating_sub(ruxhal::time::current_time())
+                .max(Duration::from_nanos(1))
+        });
+        ctypes::itimerspec {
+            it_interval: state.interval.into(),
+            it_value: value.into(),
+        }
+    }
+}
+
+fn find_timer(timerid: c_int) -> LinuxResult<Arc<PosixTimer>> {
+    let pid = ruxtask::current().process_id().as_u64();
+    TIMERS
+        .lock()
+        .get(&timerid)
+        .filter(|timer| timer.pid == pid)
+        .cloned()
+        .ok_or(LinuxError::EINVAL)
+}
+
+fn timespec_to_duration(ts: ctypes::timespec) -> LinuxResult<Duration> {
+    if ts.tv_sec < 0 || ts.tv_nsec < 0 || ts.tv_nsec >= 1_000_000_000 {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Duration::from(ts))
+}
+
+/// Deletes the timers of the process `pid`, on its exit or `execve`.
+pub(crate) fn delete_process_timers(pid: u64) {
+    let mut deleted = Vec::new();
+    TIMERS.lock().retain(|_, timer| {
+        if timer.pid == pid {
+            deleted.push(timer.clone());
+        }
+        timer.pid != pid
+    });
+    for timer in deleted {
+        timer.disarm(&mut timer.state.lock());
+    }
+}
+
+/// Create a per-process timer on the clock `clk`, whose id is stored in `timerid`.
+///
+/// Only `SIGEV_NONE`, `SIGEV_SIGNAL` and `SIGEV_THREAD_ID` are supported.
+/// `SIGEV_THREAD_ID` sends the signal to the thread `sigev_notify_thread_id`
+/// of the calling process, which is how musl implements `SIGEV_THREAD`.
+pub unsafe fn sys_timer_create(
+    clk: ctypes::clockid_t,
+    sevp: *const ctypes::sigevent,
+    timerid: *mut c_int,
+) -> c_int {
+    debug!(
+        "sys_timer_create <= clk: {}, sevp: {:p}, timerid: {:p}",
+        clk, sevp, timerid
+    );
+    syscall_body!(sys_timer_create, {
+        if timerid.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let clock = Clock::from_clockid(clk)?;
+        if matches!(clock, Clock::ProcessCpu(_) | Clock::ThreadCpu(_)) {
+            // timers on CPU-time clocks are not supported
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = ruxtask::current();
+        let mut target = SignalTarget::Process(curr.signal_if.clone());
+        let signo = if sevp.is_null() {
+            Some(ctypes::SIGALRM as c_int)
+        } else {
+            let sev = &*sevp;
+            match sev.sigev_notify as u32 {
+                ctypes::SIGEV_NONE => None,
+                ctypes::SIGEV_SIGNAL => Some(sev.sigev_signo),
+                ctypes::SIGEV_THREAD_ID => {
+                    let tid = sev.__sev_fields.sigev_notify_thread_id;
+                    let task = crate::imp::pthread::task_by_tid(tid as u64)
+                        .filter(|task| tid > 0 && task.process_id() == curr.process_id())
+                        .ok_or(LinuxError::EINVAL)?;
+                    target = SignalTarget::Thread(task);
+                    Some(sev.sigev_signo)
+                }
+                _ => return Err(LinuxError::EINVAL),
+            }
+        };
+        let signo = match signo {
+            Some(signo) if !(1..NSIG as c_int).contains(&signo) => return Err(LinuxError::EINVAL),
+            signo => signo.map(|signo| signo as u8),
+        };
+
+        let timer = Arc::new(PosixTimer {
+            pid: curr.process_id().as_u64(),
+            clock,
+            signo,
+            target,
+            state: SpinNoIrq::new(TimerState::default()),
+        });
+        let id = NEXT_TIMER_ID.fetch_add(1, Ordering::Relaxed);
+        if id < 0 {
+            return Err(LinuxError::EAGAIN);
+        }
+        TIMERS.lock().insert(id, timer);
+        *timerid = id;
+        Ok(0)
+    })
+}
+
+/// Arm or disarm the timer `timerid`, returning its previous setting in `old_value`.
+///
+/// The initial expiration is absolute if `TIMER_ABSTIME` is set in `flags`.
+pub unsafe fn sys_timer_settime(
+    timerid: c_int,
+    flags: c_int,
+    new_value: *const ctypes::itimerspec,
+    old_value: *mut ctypes::itimerspec,
+) -> c_int {
+    debug!(
+        "sys_timer_settime <= timerid: {}, flags: {}, new_value: {:p}",
+        timerid, flags, new_value
+    );
+    syscall_body!(sys_timer_settime, {
+        if new_value.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let timer = find_timer(timerid)?;
+        let value = timespec_to_duration((*new_value).it_value)?;
+        let interval = timespec_to_duration((*new_value).it_interval)?;
+
+        if !old_value.is_null() {
+            *old_value = timer.get();
+        }
+
+        let mut state = timer.state.lock();
+        timer.disarm(&mut state);
+        state.interval = interval;
+        state.overrun = 0;
+        if !value.is_zero() {
+            let deadline = if flags & ctypes::TIMER_ABSTIME as c_int != 0 {
+                timer.clock.to_monotonic_deadline(value)
+            } else {
+                ruxhal::time::current_time() + value
+            };
+            timer.arm(&mut state, deadline);
+        }
+        Ok(0)
+    })
+}
+
+/// Get the time until the next expiration and the interval of the timer `timerid`.
+pub unsafe fn sys_timer_gettime(timerid: c_int, curr_value: *mut ctypes::itimerspec) -> c_int {
+    debug!(
+        "sys_timer_gettime <= timerid: {}, curr_value: {:p}",
+        timerid, curr_value
+    );
+    syscall_body!(sys_timer_gettime, {
+        if curr_value.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        *curr_value = find_timer(timerid)?.get();
+        Ok(0)
+    })
+}
+
+/// Get the number of expirations of the timer `timerid` that were merged
+/// into its last signal.
+pub fn sys_timer_getoverrun(timerid: c_int) -> c_int {
+    debug!("sys_timer_getoverrun <= timerid: {}", timerid);
+    syscall_body!(sys_timer_getoverrun, {
+        let timer = find_timer(timerid)?;
+        let overrun = timer.state.lock().overrun;
+        Ok(overrun as c_int)
+    })
+}
+
+/// Disarm and delete the timer `timerid`.
+pub fn sys_timer_delete(timerid: c_int) -> c_int {
+    debug!("sys_timer_delete <= timerid: {}", timerid);
+    syscall_body!(sys_timer_delete, {
+        find_timer(timerid)?;
+        let timer = TIMERS.lock().remove(&timerid).ok_or(LinuxError::EINVAL)?;
+        timer.disarm(&mut timer.state.lock());
+        Ok(0)
+    })
+}
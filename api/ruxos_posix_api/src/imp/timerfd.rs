@@ -0,0 +1,383 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! `timerfd` implementation.
+//!
+//! Expirations are counted by callbacks in the timer list of `ruxtask`.
+//! Absolute `CLOCK_REALTIME` timers are re-armed when the wall clock is set.
+
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::ffi::c_int;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use ruxfdtable::{FileLike, PollObserver, PollObservers, RuxStat};
+use ruxfs::AbsPath;
+use ruxtask::fs::{add_file_like, get_file_like};
+use ruxtask::timers::{cancel_timer_callback, set_timer_callback, TimerCallbackId};
+use spinlock::SpinNoIrq;
+
+use crate::{ctypes, imp::fs::flags_to_options, imp::time::Clock};
+
+#[derive(Default)]
+struct TimerFdState {
+    /// Next expiration on the monotonic clock, `None` if disarmed.
+    deadline: Option<Duration>,
+    interval: Duration,
+    /// Bumped whenever the timer is re-armed, to ignore stale callbacks.
+    generation: u64,
+    callback: Option<TimerCallbackId>,
+    /// Expirations since the last read.
+    expirations: u64,
+    /// Next expiration on the wall clock, for absolute `CLOCK_REALTIME` timers.
+    realtime_deadline: Option<Duration>,
+    /// Whether `TFD_TIMER_CANCEL_ON_SET` was given when the timer was armed.
+    cancel_on_set: bool,
+    /// The wall clock was set since the last read, which fails with `ECANCELED`.
+    canceled: bool,
+}
+
+/// Absolute `CLOCK_REALTIME` timerfds, re-armed by [`clock_was_set`].
+static REALTIME_TIMERS: SpinNoIrq<Vec<Weak<TimerFd>>> = SpinNoIrq::new(Vec::new());
+
+pub struct TimerFd {
+    clock: Clock,
+    state: SpinNoIrq<TimerFdState>,
+    nonblocking: AtomicBool,
+    /// Tasks blocked in `read`.
+    wait_queue: ruxtask::WaitQueue,
+    observers: PollObservers,
+}
+
+impl TimerFd {
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<TimerFd>()
+            .map_err(|_| LinuxError::EINVAL)
+    }
+
+    fn arm(self: &Arc<Self>, state: &mut TimerFdState, deadline: Duration) {
+        let timerfd = Arc::downgrade(self);
+        let generation = state.generation;
+        state.deadline = Some(deadline);
+        state.callback = Some(set_timer_callback(deadline, move |now| {
+            Self::expire(timerfd, generation, now)
+        }));
+    }
+
+    fn disarm(&self, state: &mut TimerFdState) {
+        state.generation += 1;
+        state.deadline = None;
+        if let Some(id) = state.callback.take() {
+            cancel_timer_callback(id);
+        }
+    }
+
+    fn expire(timerfd: Weak<Self>, generation: u64, now: Duration) {
+        let Some(timerfd) = timerfd.upgrade() else {
+            return;
+        };
+        let mut state = timerfd.state.lock();
+        if state.generation != generation {
+            return;
+        }
+        let Some(deadline) = state.deadline else {
+            return;
+        };
+
+        if state.interval.is_zero() {
+            state.deadline = None;
+            state.callback = None;
+            state.realtime_deadline = None;
+            state.expirations = state.expirations.saturating_add(1);
+        } else {
+            let interval = state.interval.as_nanos();
+            let missed = now.saturating_sub(deadline).as_nanos() / interval;
+            let next = deadline.as_nanos() + (missed + 1) * interval;
+            let next = Duration::new((next / 1_000_000_000) as u64, (next % 1_000_000_000) as u32);
+            state.expirations = state
+                .expirations
+                .saturating_add(missed.min(u64::MAX as u128) as u64)
+                .saturating_add(1);
+            if let Some(realtime_deadline) = state.realtime_deadline {
+                state.realtime_deadline = Some(realtime_deadline + (next - deadline));
+            }
+            timerfd.arm(&mut state, next);
+        }
+        drop(state);
+        timerfd.wake();
+    }
+
+    /// Wake up the readers and epoll instances after an expiration.
+    fn wake(&self) {
+        self.wait_queue.notify_all(true);
+        self.observers.notify();
+    }
+
+    fn get(&self) -> ctypes::itimerspec {
+        let state = self.state.lock();
+        let value = state.deadline.map_or(Duration::ZERO, |deadline| {
+            // an armed timer never reports a zero value
+            deadline
+                .saturating_sub(ruxhal::time::current_time())
+                .max(Duration::from_nanos(1))
+        });
+        ctypes::itimerspec {
+            it_interval: state.interval.into(),
+            it_value: value.into(),
+        }
+    }
+}
+
+impl Drop for TimerFd {
+    fn drop(&mut self) {
+        if let Some(id) = self.state.get_mut().callback.take() {
+            cancel_timer_callback(id);
+        }
+    }
+}
+
+impl FileLike for TimerFd {
+    fn path(&self) -> AbsPath {
+        AbsPath::new("/timerfd")
+    }
+
+    /// Reads the number of expirations since the last read as an 8-byte integer.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.len() < 8 {
+            return Err(LinuxError::EINVAL);
+        }
+        loop {
+            let mut state = self.state.lock();
+            if state.canceled {
+                state.canceled = false;
+                return Err(LinuxError::ECANCELED);
+            }
+            if state.expirations > 0 {
+                buf[..8].copy_from_slice(&state.expirations.to_ne_bytes());
+                state.expirations = 0;
+                return Ok(8);
+            }
+            drop(state);
+            if self.nonblocking.load(Ordering::Relaxed) {
+                return Err(LinuxError::EAGAIN);
+            }
+            self.wait_queue.wait_until(|| {
+                let state = self.state.lock();
+                state.expirations > 0 || state.canceled
+            });
+        }
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn flush(&self) -> LinuxResult {
+        Ok(())
+    }
+
+    fn stat(&self) -> LinuxResult<RuxStat> {
+        let st_mode = 0o600u32; // rw-------
+        Ok(RuxStat::from(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            ..Default::default()
+        }))
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let state = self.state.lock();
+        Ok(PollState {
+            readable: state.expirations > 0 || state.canceled,
+            writable: false,
+            pollhup: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        self.observers.add(observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        self.observers.remove(observer, key);
+    }
+}
+
+fn timespec_to_duration(ts: ctypes::timespec) -> LinuxResult<Duration> {
+    if ts.tv_sec < 0 || ts.tv_nsec < 0 || ts.tv_nsec >= 1_000_000_000 {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Duration::from(ts))
+}
+
+/// Create a timer that notifies via a file descriptor.
+///
+/// Only `CLOCK_REALTIME`, `CLOCK_MONOTONIC` and `CLOCK_BOOTTIME` are supported.
+pub fn sys_timerfd_create(clockid: ctypes::clockid_t, flags: c_int) -> c_int {
+    debug!(
+        "sys_timerfd_create <= clockid: {}, flags: {:#x}",
+        clockid, flags
+    );
+    syscall_body!(sys_timerfd_create, {
+        let flags = flags as u32;
+        if flags & !(ctypes::TFD_NONBLOCK | ctypes::TFD_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let clock = match clockid as u32 {
+            ctypes::CLOCK_REALTIME | ctypes::CLOCK_MONOTONIC | ctypes::CLOCK_BOOTTIME => {
+                Clock::from_clockid(clockid)?
+            }
+            _ => return Err(LinuxError::EINVAL),
+        };
+        let timerfd = TimerFd {
+            clock,
+            state: SpinNoIrq::new(TimerFdState::default()),
+            nonblocking: AtomicBool::new(flags & ctypes::TFD_NONBLOCK != 0),
+            wait_queue: ruxtask::WaitQueue::new(),
+            observers: PollObservers::new(),
+        };
+        add_file_like(
+            Arc::new(timerfd),
+            flags_to_options((flags & ctypes::TFD_CLOEXEC) as c_int, 0),
+        )
+    })
+}
+
+/// Arm or disarm the timer referred to by `fd`, returning its previous setting
+/// in `old_value`.
+///
+/// The initial expiration is absolute if `TFD_TIMER_ABSTIME` is set in `flags`.
+/// An absolute `CLOCK_REALTIME` timer follows changes of the wall clock, and
+/// with `TFD_TIMER_CANCEL_ON_SET` such a change also fails the next `read`
+/// with `ECANCELED`.
+pub unsafe fn sys_timerfd_settime(
+    fd: c_int,
+    flags: c_int,
+    new_value: *const ctypes::itimerspec,
+    old_value: *mut ctypes::itimerspec,
+) -> c_int {
+    debug!(
+        "sys_timerfd_settime <= fd: {}, flags: {:#x}, new_value: {:p}",
+        fd, flags, new_value
+    );
+    syscall_body!(sys_timerfd_settime, {
+        let flags = flags as u32;
+        if flags & !(ctypes::TFD_TIMER_ABSTIME | ctypes::TFD_TIMER_CANCEL_ON_SET) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if new_value.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let timerfd = TimerFd::from_fd(fd)?;
+        let value = timespec_to_duration((*new_value).it_value)?;
+        let interval = timespec_to_duration((*new_value).it_interval)?;
+
+        if !old_value.is_null() {
+            *old_value = timerfd.get();
+        }
+
+        let abstime = flags & ctypes::TFD_TIMER_ABSTIME != 0;
+        let realtime = abstime && !value.is_zero() && matches!(timerfd.clock, Clock::Realtime);
+
+        let mut state = timerfd.state.lock();
+        timerfd.disarm(&mut state);
+        state.interval = interval;
+        state.expirations = 0;
+        state.canceled = false;
+        state.realtime_deadline = realtime.then_some(value);
+        state.cancel_on_set = realtime && flags & ctypes::TFD_TIMER_CANCEL_ON_SET != 0;
+        if !value.is_zero() {
+            let deadline = if abstime {
+                timerfd.clock.to_monotonic_deadline(value)
+            } else {
+                ruxhal::time::current_time() + value
+            };
+            timerfd.arm(&mut state, deadline);
+        }
+        drop(state);
+
+        if realtime {
+            crate::imp::time::watch_wall_clock();
+            let mut timers = REALTIME_TIMERS.lock();
+            timers.retain(|t| t.strong_count() > 0);
+            let weak = Arc::downgrade(&timerfd);
+            if !timers.iter().any(|t| t.ptr_eq(&weak)) {
+                timers.push(weak);
+            }
+        }
+        Ok(0)
+    })
+}
+
+/// Get the time until the next expiration and the interval of the timer
+/// referred to by `fd`.
+pub unsafe fn sys_timerfd_gettime(fd: c_int, curr_value: *mut ctypes::itimerspec) -> c_int {
+    debug!(
+        "sys_timerfd_gettime <= fd: {}, curr_value: {:p}",
+        fd, curr_value
+    );
+    syscall_body!(sys_timerfd_gettime, {
+        if curr_value.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        *curr_value = TimerFd::from_fd(fd)?.get();
+        Ok(0)
+    })
+}
+
+/// Re-arm the absolute `CLOCK_REALTIME` timerfds after the wall clock was set,
+/// and cancel the ones armed with `TFD_TIMER_CANCEL_ON_SET`.
+pub(crate) fn clock_was_set() {
+    let timers: Vec<Arc<TimerFd>> = {
+        let mut timers = REALTIME_TIMERS.lock();
+        timers.retain(|t| t.strong_count() > 0);
+        timers.iter().filter_map(Weak::upgrade).collect()
+    };
+    for timerfd in timers {
+        let mut state = timerfd.state.lock();
+        let Some(realtime_deadline) = state.realtime_deadline else {
+            continue;
+        };
+        timerfd.disarm(&mut state);
+        timerfd.arm(
+            &mut state,
+            Clock::Realtime.to_monotonic_deadline(realtime_deadline),
+        );
+        if state.cancel_on_set {
+            state.canceled = true;
+        }
+        drop(state);
+        timerfd.wake();
+    }
+}
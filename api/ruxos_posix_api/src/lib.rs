@@ -33,6 +33,9 @@ mod utils;
 
 mod imp;
 
+#[cfg(all(test, feature = "mqueue"))]
+mod tests;
+
 /// Platform-specific constants and parameters.
 pub mod config {
     pub use memory_addr::PAGE_SIZE_4K;
@@ -64,27 +67,61 @@ pub use imp::time::{
     sys_nanosleep, sys_times,
 };
 
+#[cfg(feature = "aio")]
+pub use imp::aio::{
+    sys_aio_cancel, sys_aio_error, sys_aio_fsync, sys_aio_read, sys_aio_return, sys_aio_suspend,
+    sys_aio_write, sys_lio_listio,
+};
+#[cfg(feature = "eventfd")]
+pub use imp::eventfd::{sys_eventfd, sys_eventfd2};
 #[cfg(all(feature = "fd", feature = "musl"))]
 pub use imp::fd_ops::sys_dup3;
 #[cfg(feature = "fd")]
 pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl};
 #[cfg(feature = "fs")]
+pub use imp::flock::sys_flock;
+#[cfg(feature = "fs")]
 pub use imp::fs::{
-    sys_chdir, sys_faccessat, sys_fchownat, sys_fdatasync, sys_fstat, sys_fsync, sys_getcwd,
-    sys_getdents64, sys_lseek, sys_lstat, sys_mkdir, sys_mkdirat, sys_mknodat, sys_newfstatat,
-    sys_open, sys_openat, sys_pread64, sys_preadv, sys_pwrite64, sys_readlinkat, sys_rename,
-    sys_renameat, sys_rmdir, sys_stat, sys_unlink, sys_unlinkat,
+    sys_chdir, sys_chmod, sys_faccessat, sys_fchmod, sys_fchmodat, sys_fchownat, sys_fdatasync,
+    sys_fstat, sys_fstatfs, sys_fsync, sys_getcwd, sys_getdents64, sys_link, sys_linkat, sys_lseek,
+    sys_lstat, sys_mkdir, sys_mkdirat, sys_mknodat, sys_newfstatat, sys_open, sys_openat,
+    sys_pread64, sys_preadv, sys_pwrite64, sys_readlinkat, sys_rename, sys_renameat, sys_renameat2,
+    sys_rmdir, sys_stat, sys_statfs, sys_statx, sys_symlink, sys_symlinkat, sys_unlink,
+    sys_unlinkat, sys_utimensat,
+};
+#[cfg(feature = "fs")]
+pub use imp::mount::{sys_mount, sys_umount2};
+#[cfg(feature = "inotify")]
+pub use imp::inotify::{
+    sys_inotify_add_watch, sys_inotify_init, sys_inotify_init1, sys_inotify_rm_watch,
 };
 #[cfg(feature = "epoll")]
-pub use imp::io_mpx::{sys_epoll_create1, sys_epoll_ctl, sys_epoll_pwait, sys_epoll_wait};
+pub use imp::io_mpx::{
+    sys_epoll_create, sys_epoll_create1, sys_epoll_ctl, sys_epoll_pwait, sys_epoll_wait,
+};
 #[cfg(feature = "poll")]
 pub use imp::io_mpx::{sys_poll, sys_ppoll};
 #[cfg(feature = "select")]
 pub use imp::io_mpx::{sys_pselect6, sys_select};
+#[cfg(feature = "io_uring")]
+pub use imp::io_uring::{sys_io_uring_enter, sys_io_uring_register, sys_io_uring_setup};
 #[cfg(feature = "fd")]
 pub use imp::ioctl::sys_ioctl;
+#[cfg(feature = "sysvipc")]
+pub use imp::ipc::{
+    msg::{sys_msgctl, sys_msgget, sys_msgrcv, sys_msgsnd},
+    sem::{sys_semctl, sys_semget, sys_semop, sys_semtimedop},
+    shm::{sys_shmat, sys_shmctl, sys_shmdt, sys_shmget},
+};
+#[cfg(feature = "fs")]
+pub use imp::memfd::{sys_memfd_create, sys_shm_open, sys_shm_unlink};
 #[cfg(feature = "alloc")]
 pub use imp::mmap::{sys_madvise, sys_mmap, sys_mprotect, sys_mremap, sys_msync, sys_munmap};
+#[cfg(feature = "mqueue")]
+pub use imp::mqueue::{
+    sys_mq_getsetattr, sys_mq_notify, sys_mq_open, sys_mq_timedreceive, sys_mq_timedsend,
+    sys_mq_unlink,
+};
 #[cfg(feature = "net")]
 pub use imp::net::{
     sys_accept, sys_bind, sys_connect, sys_freeaddrinfo, sys_getaddrinfo, sys_getpeername,
@@ -110,10 +147,24 @@ pub use imp::pthread::{
 };
 #[cfg(feature = "signal")]
 pub use imp::rt_sig::{sys_rt_sigaction, sys_rt_sigprocmask};
+#[cfg(all(feature = "signal", feature = "multitask", feature = "irq"))]
+pub use imp::rt_sig::sys_rt_sigtimedwait;
 #[cfg(feature = "signal")]
 pub use imp::signal::{
     sys_getitimer, sys_kill, sys_setitimer, sys_sigaction, sys_sigaltstack, sys_tkill,
 };
+#[cfg(feature = "signalfd")]
+pub use imp::signalfd::{sys_signalfd, sys_signalfd4};
+#[cfg(feature = "fs")]
+pub use imp::splice::{sys_copy_file_range, sys_sendfile};
+#[cfg(all(feature = "fs", feature = "pipe"))]
+pub use imp::splice::{sys_splice, sys_tee};
+#[cfg(feature = "timer")]
+pub use imp::timer::{
+    sys_timer_create, sys_timer_delete, sys_timer_getoverrun, sys_timer_gettime, sys_timer_settime,
+};
+#[cfg(feature = "timerfd")]
+pub use imp::timerfd::{sys_timerfd_create, sys_timerfd_gettime, sys_timerfd_settime};
 
 #[cfg(feature = "multitask")]
 pub use imp::pthread::futex::sys_futex;
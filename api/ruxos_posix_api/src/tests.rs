@@ -0,0 +1,56 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+use crate::imp::mqueue::MqState;
+
+fn pop_all(state: &mut MqState) -> Vec<(u32, Vec<u8>)> {
+    core::iter::from_fn(|| state.pop()).collect()
+}
+
+#[test]
+fn test_mqueue_priority_order() {
+    let mut state = MqState::new();
+    state.push(1, b"low 1".to_vec());
+    state.push(5, b"high 1".to_vec());
+    state.push(0, b"lowest".to_vec());
+    state.push(5, b"high 2".to_vec());
+    state.push(1, b"low 2".to_vec());
+
+    // the highest priority first, each priority in the order sent
+    assert_eq!(
+        pop_all(&mut state),
+        [
+            (5, b"high 1".to_vec()),
+            (5, b"high 2".to_vec()),
+            (1, b"low 1".to_vec()),
+            (1, b"low 2".to_vec()),
+            (0, b"lowest".to_vec()),
+        ]
+    );
+    assert!(state.pop().is_none());
+}
+
+#[test]
+fn test_mqueue_priority_interleaved() {
+    let mut state = MqState::new();
+    state.push(2, b"a".to_vec());
+    state.push(2, b"b".to_vec());
+    assert_eq!(state.pop(), Some((2, b"a".to_vec())));
+
+    // a message of a higher priority overtakes the ones queued before
+    state.push(3, b"c".to_vec());
+    state.push(2, b"d".to_vec());
+    assert_eq!(state.pop(), Some((3, b"c".to_vec())));
+    // a priority emptied and sent to again starts a new queue
+    state.push(3, Vec::new());
+    assert_eq!(
+        pop_all(&mut state),
+        [(3, Vec::new()), (2, b"b".to_vec()), (2, b"d".to_vec())]
+    );
+}
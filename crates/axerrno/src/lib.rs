@@ -97,6 +97,17 @@ pub enum AxError {
     /// It is a temporary error code that usually returns when a non_blocking operation
     /// is not completed, prompting the caller to try again later.
     InProgress,
+    /// Writing to a pipe or FIFO that has no readers.
+    BrokenPipe,
+    /// The operation is not permitted on the object, such as writing to a
+    /// sealed file.
+    OperationNotPermitted,
+    /// The operation would move a filesystem object across filesystems, or
+    /// across layers of a filesystem.
+    CrossesDevices,
+    /// A device node refers to a device that doesn't exist, or there is no
+    /// data or hole to seek to.
+    NoSuchDevice,
 }
 
 /// A specialized [`Result`] type with [`AxError`] as the error type.
@@ -248,6 +259,10 @@ impl AxError {
             WouldBlock => "Operation would block",
             WriteZero => "Write zero",
             InProgress => "non_blocking operation is not completed",
+            BrokenPipe => "Broken pipe",
+            OperationNotPermitted => "Operation not permitted",
+            CrossesDevices => "Cross-device link",
+            NoSuchDevice => "No such device or address",
         }
     }
 
@@ -300,6 +315,10 @@ impl From<AxError> for LinuxError {
             UnexpectedEof | WriteZero => LinuxError::EIO,
             WouldBlock => LinuxError::EAGAIN,
             InProgress => LinuxError::EINPROGRESS,
+            BrokenPipe => LinuxError::EPIPE,
+            OperationNotPermitted => LinuxError::EPERM,
+            CrossesDevices => LinuxError::EXDEV,
+            NoSuchDevice => LinuxError::ENXIO,
         }
     }
 }
@@ -322,13 +341,13 @@ mod tests {
     #[test]
     fn test_try_from() {
         let max_code = core::mem::variant_count::<AxError>() as i32;
-        assert_eq!(max_code, 23);
-        assert_eq!(max_code, AxError::InProgress.code());
+        assert_eq!(max_code, 27);
+        assert_eq!(max_code, AxError::NoSuchDevice.code());
 
         assert_eq!(AxError::AddrInUse.code(), 1);
         assert_eq!(Ok(AxError::AddrInUse), AxError::try_from(1));
         assert_eq!(Ok(AxError::AlreadyExists), AxError::try_from(2));
-        assert_eq!(Ok(AxError::InProgress), AxError::try_from(max_code));
+        assert_eq!(Ok(AxError::NoSuchDevice), AxError::try_from(max_code));
         assert_eq!(Err(max_code + 1), AxError::try_from(max_code + 1));
         assert_eq!(Err(0), AxError::try_from(0));
         assert_eq!(Err(-1), AxError::try_from(-1));
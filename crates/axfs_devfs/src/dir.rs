@@ -8,6 +8,7 @@
  */
 
 use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::{Arc, Weak};
 use axfs_vfs::{RelPath, VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
 use axfs_vfs::{VfsError, VfsResult};
@@ -21,7 +22,7 @@ use crate::InoAllocator;
 pub struct DirNode {
     ino: u64,
     parent: RwLock<Weak<dyn VfsNodeOps>>,
-    children: RwLock<BTreeMap<&'static str, VfsNodeRef>>,
+    children: RwLock<BTreeMap<String, VfsNodeRef>>,
     ialloc: Weak<InoAllocator>,
 }
 
@@ -52,13 +53,13 @@ impl DirNode {
             Some(&parent),
             self.ialloc.clone(),
         );
-        self.children.write().insert(name, node.clone());
+        self.children.write().insert(name.into(), node.clone());
         node
     }
 
     /// Add a node to this directory.
-    pub fn add(&self, name: &'static str, node: VfsNodeRef) {
-        self.children.write().insert(name, node);
+    pub fn add(&self, name: impl Into<String>, node: VfsNodeRef) {
+        self.children.write().insert(name.into(), node);
     }
 }
 
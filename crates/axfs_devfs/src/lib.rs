@@ -28,11 +28,21 @@ pub use self::null::NullDev;
 pub use self::random::RandomDev;
 pub use self::zero::ZeroDev;
 
-use alloc::sync::Arc;
+use alloc::{string::String, sync::Arc};
 use axfs_vfs::{AbsPath, VfsNodeRef, VfsOps, VfsResult};
 use core::sync::atomic::AtomicU64;
 use spin::once::Once;
 
+/// Encodes the device number of the given `major` and `minor` numbers the
+/// same way Linux does.
+pub const fn makedev(major: u32, minor: u32) -> u64 {
+    let (major, minor) = (major as u64, minor as u64);
+    ((major & 0xffff_f000) << 32)
+        | ((major & 0xfff) << 8)
+        | ((minor & 0xffff_ff00) << 12)
+        | (minor & 0xff)
+}
+
 /// An auto-increasing inode number allocator.
 pub struct InoAllocator {
     current: AtomicU64,
@@ -57,7 +67,7 @@ impl InoAllocator {
 pub struct DeviceFileSystem {
     parent: Once<VfsNodeRef>,
     root: Arc<DirNode>,
-    _ialloc: Arc<InoAllocator>,
+    ialloc: Arc<InoAllocator>,
 }
 
 impl DeviceFileSystem {
@@ -67,10 +77,15 @@ impl DeviceFileSystem {
         Self {
             parent: Once::new(),
             root: DirNode::new(2, None, Arc::downgrade(&ialloc)),
-            _ialloc: ialloc,
+            ialloc,
         }
     }
 
+    /// Allocate an inode number for a node to be added to this filesystem.
+    pub fn alloc_ino(&self) -> u64 {
+        self.ialloc.alloc()
+    }
+
     /// Create a subdirectory at the root directory.
     pub fn mkdir(&self, name: &'static str) -> Arc<DirNode> {
         self.root.mkdir(name)
@@ -79,7 +94,7 @@ impl DeviceFileSystem {
     /// Add a node to the root directory.
     ///
     /// The node must implement [`axfs_vfs::VfsNodeOps`], and be wrapped in [`Arc`].
-    pub fn add(&self, name: &'static str, node: VfsNodeRef) {
+    pub fn add(&self, name: impl Into<String>, node: VfsNodeRef) {
         self.root.add(name, node);
     }
 }
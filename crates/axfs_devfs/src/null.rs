@@ -22,7 +22,8 @@ impl VfsNodeOps for NullDev {
             VfsNodeType::CharDevice,
             0,
             0,
-        ))
+        )
+        .with_rdev(crate::makedev(1, 3)))
     }
 
     fn read_at(&self, _offset: u64, _buf: &mut [u8]) -> VfsResult<usize> {
@@ -35,7 +35,8 @@ impl VfsNodeOps for RandomDev {
             VfsNodeType::CharDevice,
             0,
             0,
-        ))
+        )
+        .with_rdev(crate::makedev(1, 8)))
     }
 
     fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
@@ -22,7 +22,8 @@ impl VfsNodeOps for ZeroDev {
             VfsNodeType::CharDevice,
             0,
             0,
-        ))
+        )
+        .with_rdev(crate::makedev(1, 5)))
     }
 
     fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
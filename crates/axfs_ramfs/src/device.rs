@@ -0,0 +1,73 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+use axfs_vfs::VfsResult;
+use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType};
+use core::sync::atomic::{AtomicU16, Ordering};
+use core::time::Duration;
+
+use crate::meta::NodeMeta;
+
+/// The character or block device node in the RAM filesystem.
+///
+/// It only records the device number, and opening it is up to the caller,
+/// which finds the device by the type and [`rdev`](VfsNodeAttr::rdev) of the
+/// node.
+pub struct DeviceNode {
+    ino: u64,
+    /// Bits of [`VfsNodePerm`].
+    perm: AtomicU16,
+    meta: NodeMeta,
+    ty: VfsNodeType,
+    rdev: u64,
+}
+
+impl DeviceNode {
+    pub(super) fn new(ino: u64, ty: VfsNodeType, rdev: u64) -> Self {
+        Self {
+            ino,
+            perm: AtomicU16::new(VfsNodePerm::default_file().bits()),
+            meta: NodeMeta::default(),
+            ty,
+            rdev,
+        }
+    }
+}
+
+impl VfsNodeOps for DeviceNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(self.meta.apply(
+            VfsNodeAttr::new(
+                self.ino,
+                VfsNodePerm::from_bits_truncate(self.perm.load(Ordering::Relaxed)),
+                self.ty,
+                0,
+                0,
+            )
+            .with_rdev(self.rdev),
+        ))
+    }
+
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        self.perm.store(perm.bits(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn set_owner(&self, uid: u32, gid: u32) -> VfsResult {
+        self.meta.set_owner(uid, gid);
+        Ok(())
+    }
+
+    fn set_times(&self, atime: Duration, mtime: Duration) -> VfsResult {
+        self.meta.set_times(atime, mtime);
+        Ok(())
+    }
+
+    impl_vfs_non_dir_default! {}
+}
@@ -11,23 +11,34 @@ use alloc::collections::BTreeMap;
 use alloc::sync::{Arc, Weak};
 use alloc::{string::String, vec::Vec};
 
-use axfs_vfs::{RelPath, VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{
+    RelPath, VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType,
+};
 use axfs_vfs::{VfsError, VfsResult};
+use core::sync::atomic::{AtomicU16, Ordering};
+use core::time::Duration;
 use spin::rwlock::RwLock;
 
+use crate::device::DeviceNode;
 use crate::fifo::FifoNode;
 use crate::file::FileNode;
-use crate::InoAllocator;
+use crate::meta::NodeMeta;
+use crate::symlink::SymlinkNode;
+use crate::{InoAllocator, PageUsage};
 
 /// The directory node in the RAM filesystem.
 ///
 /// It implements [`axfs_vfs::VfsNodeOps`].
 pub struct DirNode {
     ino: u64,
+    /// Bits of [`VfsNodePerm`].
+    perm: AtomicU16,
+    meta: NodeMeta,
     this: Weak<DirNode>,
     parent: RwLock<Weak<dyn VfsNodeOps>>,
     children: RwLock<BTreeMap<String, VfsNodeRef>>,
     ialloc: Weak<InoAllocator>,
+    usage: Arc<PageUsage>,
 }
 
 impl DirNode {
@@ -35,13 +46,17 @@ impl DirNode {
         ino: u64,
         parent: Option<Weak<dyn VfsNodeOps>>,
         ialloc: Weak<InoAllocator>,
+        usage: Arc<PageUsage>,
     ) -> Arc<Self> {
         Arc::new_cyclic(|this| Self {
             ino,
+            perm: AtomicU16::new(VfsNodePerm::default_dir().bits()),
+            meta: NodeMeta::default(),
             this: this.clone(),
             parent: RwLock::new(parent.unwrap_or_else(|| Weak::<Self>::new())),
             children: RwLock::new(BTreeMap::new()),
             ialloc,
+            usage,
         })
     }
 
@@ -66,12 +81,16 @@ impl DirNode {
             return Err(VfsError::AlreadyExists);
         }
         let node: VfsNodeRef = match ty {
-            VfsNodeType::File => Arc::new(FileNode::new(self.ialloc.upgrade().unwrap().alloc())),
+            VfsNodeType::File => Arc::new(FileNode::new(
+                self.ialloc.upgrade().unwrap().alloc(),
+                self.usage.clone(),
+            )),
             VfsNodeType::Fifo => Arc::new(FifoNode::new(self.ialloc.upgrade().unwrap().alloc())),
             VfsNodeType::Dir => Self::new(
                 self.ialloc.upgrade().unwrap().alloc(),
                 Some(self.this.clone()),
                 self.ialloc.clone(),
+                self.usage.clone(),
             ),
             _ => return Err(VfsError::Unsupported),
         };
@@ -79,6 +98,46 @@ impl DirNode {
         Ok(())
     }
 
+    /// Creates a symbolic link with the given name in this directory, which
+    /// points to `target`.
+    pub fn create_symlink(&self, name: &str, target: &str) -> VfsResult {
+        if self.exist(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        let ino = self.ialloc.upgrade().unwrap().alloc();
+        let node = Arc::new(SymlinkNode::new(ino, target));
+        self.children.write().insert(name.into(), node);
+        Ok(())
+    }
+
+    /// Creates a character or block device node with the given name and
+    /// device number in this directory.
+    pub fn create_device(&self, name: &str, ty: VfsNodeType, rdev: u64) -> VfsResult {
+        if !matches!(ty, VfsNodeType::CharDevice | VfsNodeType::BlockDevice) {
+            return Err(VfsError::InvalidInput);
+        }
+        if self.exist(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        let ino = self.ialloc.upgrade().unwrap().alloc();
+        let node = Arc::new(DeviceNode::new(ino, ty, rdev));
+        self.children.write().insert(name.into(), node);
+        Ok(())
+    }
+
+    /// Adds `node` to this directory as a hard link with the given name.
+    fn link_node(&self, name: &str, node: VfsNodeRef) -> VfsResult<VfsNodeRef> {
+        if node.get_attr()?.is_dir() {
+            return Err(VfsError::OperationNotPermitted);
+        }
+        let mut children = self.children.write();
+        if children.contains_key(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        children.insert(name.into(), node.clone());
+        Ok(node)
+    }
+
     /// Removes a node by the given name in this directory.
     pub fn remove_node(&self, name: &str) -> VfsResult {
         let mut children = self.children.write();
@@ -95,7 +154,28 @@ impl DirNode {
 
 impl VfsNodeOps for DirNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_dir(self.ino, 4096, 0))
+        Ok(self.meta.apply(VfsNodeAttr::new(
+            self.ino,
+            VfsNodePerm::from_bits_truncate(self.perm.load(Ordering::Relaxed)),
+            VfsNodeType::Dir,
+            4096,
+            0,
+        )))
+    }
+
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        self.perm.store(perm.bits(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn set_owner(&self, uid: u32, gid: u32) -> VfsResult {
+        self.meta.set_owner(uid, gid);
+        Ok(())
+    }
+
+    fn set_times(&self, atime: Duration, mtime: Duration) -> VfsResult {
+        self.meta.set_times(atime, mtime);
+        Ok(())
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
@@ -166,6 +246,163 @@ impl VfsNodeOps for DirNode {
         }
     }
 
+    fn symlink(&self, path: &RelPath, target: &str) -> VfsResult {
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                ".." => self
+                    .parent()
+                    .ok_or(VfsError::NotFound)?
+                    .symlink(&rest, target),
+                _ => self
+                    .children
+                    .read()
+                    .get(name)
+                    .ok_or(VfsError::NotFound)?
+                    .symlink(&rest, target),
+            }
+        } else if name.is_empty() || name == ".." {
+            Err(VfsError::AlreadyExists)
+        } else {
+            self.create_symlink(name, target)
+        }
+    }
+
+    fn link(&self, name: &RelPath, src: VfsNodeRef) -> VfsResult<VfsNodeRef> {
+        let (first, rest) = split_path(name);
+        if let Some(rest) = rest {
+            match first {
+                ".." => self.parent().ok_or(VfsError::NotFound)?.link(&rest, src),
+                _ => self
+                    .children
+                    .read()
+                    .get(first)
+                    .ok_or(VfsError::NotFound)?
+                    .link(&rest, src),
+            }
+        } else if first.is_empty() || first == ".." {
+            Err(VfsError::AlreadyExists)
+        } else {
+            self.link_node(first, src)
+        }
+    }
+
+    fn rename(&self, src_path: &RelPath, dst_path: &RelPath) -> VfsResult {
+        let this = self.this.upgrade().ok_or(VfsError::NotFound)?;
+        let (src_parent, src_name) = split_parent(src_path);
+        let (dst_parent, dst_name) = split_parent(dst_path);
+        if [src_name, dst_name]
+            .iter()
+            .any(|name| name.is_empty() || *name == "." || *name == "..")
+        {
+            return Err(VfsError::InvalidInput);
+        }
+        let src_dir_node = this.clone().lookup(&src_parent)?;
+        let dst_dir_node = this.lookup(&dst_parent)?;
+        let src_dir = src_dir_node
+            .as_any()
+            .downcast_ref::<DirNode>()
+            .ok_or(VfsError::NotADirectory)?;
+        let dst_dir = dst_dir_node
+            .as_any()
+            .downcast_ref::<DirNode>()
+            .ok_or(VfsError::NotADirectory)?;
+
+        let node = src_dir
+            .children
+            .read()
+            .get(src_name)
+            .cloned()
+            .ok_or(VfsError::NotFound)?;
+        let is_dir = node.get_attr()?.is_dir();
+        // a directory cannot be moved into itself
+        if is_dir && is_ancestor(&node, &dst_dir_node) {
+            return Err(VfsError::InvalidInput);
+        }
+        if let Some(old) = dst_dir.children.read().get(dst_name) {
+            if same_node(old, &node) {
+                return Ok(());
+            }
+            match (is_dir, old.get_attr()?.is_dir()) {
+                (true, true) if !old.is_empty()? => return Err(VfsError::DirectoryNotEmpty),
+                (true, false) => return Err(VfsError::NotADirectory),
+                (false, true) => return Err(VfsError::IsADirectory),
+                _ => {}
+            }
+        }
+        src_dir.children.write().remove(src_name);
+        dst_dir
+            .children
+            .write()
+            .insert(dst_name.into(), node.clone());
+        if let Some(dir) = node.as_any().downcast_ref::<DirNode>() {
+            dir.set_parent(Some(&dst_dir_node));
+        }
+        Ok(())
+    }
+
+    fn exchange(&self, src_path: &RelPath, dst_path: &RelPath) -> VfsResult {
+        let this = self.this.upgrade().ok_or(VfsError::NotFound)?;
+        let (src_parent, src_name) = split_parent(src_path);
+        let (dst_parent, dst_name) = split_parent(dst_path);
+        if [src_name, dst_name]
+            .iter()
+            .any(|name| name.is_empty() || *name == "." || *name == "..")
+        {
+            return Err(VfsError::InvalidInput);
+        }
+        let src_dir_node = this.clone().lookup(&src_parent)?;
+        let dst_dir_node = this.lookup(&dst_parent)?;
+        let src_dir = src_dir_node
+            .as_any()
+            .downcast_ref::<DirNode>()
+            .ok_or(VfsError::NotADirectory)?;
+        let dst_dir = dst_dir_node
+            .as_any()
+            .downcast_ref::<DirNode>()
+            .ok_or(VfsError::NotADirectory)?;
+
+        if same_node(&src_dir_node, &dst_dir_node) {
+            let mut children = src_dir.children.write();
+            let src = children.get(src_name).cloned().ok_or(VfsError::NotFound)?;
+            let dst = children.get(dst_name).cloned().ok_or(VfsError::NotFound)?;
+            children.insert(src_name.into(), dst);
+            children.insert(dst_name.into(), src);
+            return Ok(());
+        }
+        // lookups lock a directory before its children, so an ancestor is
+        // locked first
+        let (mut src_children, mut dst_children) = if is_ancestor(&dst_dir_node, &src_dir_node) {
+            let dst_children = dst_dir.children.write();
+            (src_dir.children.write(), dst_children)
+        } else {
+            let src_children = src_dir.children.write();
+            (src_children, dst_dir.children.write())
+        };
+        let src = src_children
+            .get(src_name)
+            .cloned()
+            .ok_or(VfsError::NotFound)?;
+        let dst = dst_children
+            .get(dst_name)
+            .cloned()
+            .ok_or(VfsError::NotFound)?;
+        // a directory cannot be moved into itself
+        if is_ancestor(&src, &dst_dir_node) || is_ancestor(&dst, &src_dir_node) {
+            return Err(VfsError::InvalidInput);
+        }
+        src_children.insert(src_name.into(), dst.clone());
+        dst_children.insert(dst_name.into(), src.clone());
+        drop((src_children, dst_children));
+        if let Some(dir) = src.as_any().downcast_ref::<DirNode>() {
+            dir.set_parent(Some(&dst_dir_node));
+        }
+        if let Some(dir) = dst.as_any().downcast_ref::<DirNode>() {
+            dir.set_parent(Some(&src_dir_node));
+        }
+        Ok(())
+    }
+
     fn unlink(&self, path: &RelPath) -> VfsResult {
         let (name, rest) = split_path(path);
         if let Some(rest) = rest {
@@ -188,6 +425,31 @@ impl VfsNodeOps for DirNode {
     axfs_vfs::impl_vfs_dir_default! {}
 }
 
+/// Splits `path` into the path of its parent directory and its last
+/// component.
+fn split_parent<'a>(path: &'a RelPath) -> (RelPath<'a>, &'a str) {
+    path.rfind('/')
+        .map_or((RelPath::new(""), path.as_str()), |n| {
+            (RelPath::new(&path[..n]), &path[n + 1..])
+        })
+}
+
+fn same_node(a: &VfsNodeRef, b: &VfsNodeRef) -> bool {
+    Arc::as_ptr(a) as *const () == Arc::as_ptr(b) as *const ()
+}
+
+/// Whether `node` is `dir` or one of its ancestors.
+fn is_ancestor(node: &VfsNodeRef, dir: &VfsNodeRef) -> bool {
+    let mut dir = Some(dir.clone());
+    while let Some(d) = dir {
+        if same_node(&d, node) {
+            return true;
+        }
+        dir = d.parent();
+    }
+    false
+}
+
 fn split_path<'a>(path: &'a RelPath) -> (&'a str, Option<RelPath<'a>>) {
     path.find('/').map_or((path, None), |n| {
         (&path[..n], Some(RelPath::new(&path[n + 1..])))
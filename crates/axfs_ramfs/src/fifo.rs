@@ -1,157 +1,185 @@
-use alloc::sync::Arc;
-use axerrno::{LinuxError, LinuxResult};
-use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult};
+use alloc::sync::Weak;
+use alloc::{vec, vec::Vec};
+use axerrno::AxError;
+use axfs_vfs::{
+    impl_vfs_non_dir_default, PollObserver, PollObservers, VfsNodeAttr, VfsNodeOps, VfsResult,
+    PIPE_BUF,
+};
+use axio::PollState;
 use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
 use log::debug;
 use spin::Mutex;
 
-#[derive(Copy, Clone, PartialEq)]
-enum RingBufferStatus {
-    Full,
-    Empty,
-    Normal,
-}
+use crate::meta::NodeMeta;
+
+const RING_BUFFER_SIZE: usize = ruxconfig::PIPE_BUFFER_SIZE;
 
-const RING_BUFFER_SIZE: usize = 1024;
+/// Upper bound of the capacity set by `F_SETPIPE_SZ`.
+const PIPE_MAX_SIZE: usize = 0x100000;
 
 pub struct PipeRingBuffer {
-    arr: [u8; RING_BUFFER_SIZE],
+    arr: Vec<u8>,
     head: usize,
-    tail: usize,
-    status: RingBufferStatus,
+    len: usize,
 }
 
 impl PipeRingBuffer {
-    pub const fn new() -> Self {
+    pub fn new(capacity: usize) -> Self {
         Self {
-            arr: [0; RING_BUFFER_SIZE],
+            arr: vec![0; capacity],
             head: 0,
-            tail: 0,
-            status: RingBufferStatus::Empty,
+            len: 0,
         }
     }
 
-    pub fn write_byte(&mut self, byte: u8) {
-        self.status = RingBufferStatus::Normal;
-        self.arr[self.tail] = byte;
-        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
-        if self.tail == self.head {
-            self.status = RingBufferStatus::Full;
-        }
-    }
-
-    pub fn read_byte(&mut self) -> u8 {
-        self.status = RingBufferStatus::Normal;
-        let c = self.arr[self.head];
-        self.head = (self.head + 1) % RING_BUFFER_SIZE;
-        if self.head == self.tail {
-            self.status = RingBufferStatus::Empty;
-        }
-        c
+    pub fn capacity(&self) -> usize {
+        self.arr.len()
     }
 
     pub fn available_read(&self) -> usize {
-        if self.status == RingBufferStatus::Empty {
-            0
-        } else if self.tail > self.head {
-            self.tail - self.head
-        } else {
-            self.tail + RING_BUFFER_SIZE - self.head
-        }
+        self.len
     }
 
     pub fn available_write(&self) -> usize {
-        if self.status == RingBufferStatus::Full {
-            0
-        } else {
-            RING_BUFFER_SIZE - self.available_read()
+        self.capacity() - self.len
+    }
+
+    /// Moves data out of the buffer, returns the read length.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let capacity = self.capacity();
+        let len = self.len.min(buf.len());
+        let first = len.min(capacity - self.head);
+        buf[..first].copy_from_slice(&self.arr[self.head..self.head + first]);
+        buf[first..len].copy_from_slice(&self.arr[..len - first]);
+        self.head = (self.head + len) % capacity;
+        self.len -= len;
+        len
+    }
+
+    /// Appends as much of `buf` as fits, returns the written length.
+    pub fn write(&mut self, buf: &[u8]) -> usize {
+        let capacity = self.capacity();
+        let len = self.available_write().min(buf.len());
+        let tail = (self.head + self.len) % capacity;
+        let first = len.min(capacity - tail);
+        self.arr[tail..tail + first].copy_from_slice(&buf[..first]);
+        self.arr[..len - first].copy_from_slice(&buf[first..len]);
+        self.len += len;
+        len
+    }
+
+    /// Changes the capacity, fails with `ResourceBusy` if the data would not fit.
+    pub fn resize(&mut self, capacity: usize) -> VfsResult {
+        if self.len > capacity {
+            return Err(AxError::ResourceBusy);
         }
+        let mut arr = vec![0; capacity];
+        let len = self.len;
+        self.read(&mut arr[..len]);
+        self.arr = arr;
+        self.head = 0;
+        self.len = len;
+        Ok(())
     }
 }
 
+/// The buffer of a FIFO and its ends.
+///
+/// It never blocks: reads and writes that can't make progress fail with
+/// `WouldBlock`, and the observers are notified when they may succeed.
 pub struct Fifo {
-    buffer: Arc<Mutex<PipeRingBuffer>>,
+    buffer: Mutex<PipeRingBuffer>,
     readers: AtomicUsize,
     writers: AtomicUsize,
+    /// Notified on data, space, or an end being opened or closed.
+    observers: PollObservers,
 }
 
 impl Fifo {
     pub fn new() -> Self {
         Self {
-            buffer: Arc::new(Mutex::new(PipeRingBuffer::new())),
+            buffer: Mutex::new(PipeRingBuffer::new(RING_BUFFER_SIZE)),
             readers: AtomicUsize::new(0),
             writers: AtomicUsize::new(0),
+            observers: PollObservers::new(),
         }
     }
 
-    pub fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        debug!("read data from fifo");
-        let mut read_size = 0usize;
-        let max_len = buf.len();
-
-        loop {
-            let mut ring_buffer = self.buffer.lock();
-            let available = ring_buffer.available_read();
-            if available == 0 {
-                if self.writers.load(Ordering::SeqCst) == 0 {
-                    // only EOF when no writer and no data
-                    return Ok(0);
-                } else {
-                    drop(ring_buffer);
-                    sched_yield();
-                    // must continue to wait for data
-                    continue;
-                }
-            }
-            for _ in 0..available {
-                if read_size == max_len {
-                    return Ok(read_size);
-                }
-                buf[read_size] = ring_buffer.read_byte();
-                read_size += 1;
-            }
-            break;
-        }
-        Ok(read_size)
-    }
-
-    pub fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
-        debug!("write data to fifo");
-        let mut write_size = 0usize;
-        let max_len = buf.len();
+    fn has_readers(&self) -> bool {
+        self.readers.load(Ordering::SeqCst) > 0
+    }
 
-        loop {
-            let mut ring_buffer = self.buffer.lock();
+    fn has_writers(&self) -> bool {
+        self.writers.load(Ordering::SeqCst) > 0
+    }
 
-            if self.readers.load(Ordering::SeqCst) == 0 {
-                return Err(LinuxError::EPIPE);
-            }
+    pub fn read(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        debug!("read data from fifo");
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let read_size = self.buffer.lock().read(buf);
+        if read_size > 0 {
+            self.observers.notify();
+            return Ok(read_size);
+        }
+        if !self.has_writers() {
+            // only EOF when no writer and no data
+            return Ok(0);
+        }
+        Err(AxError::WouldBlock)
+    }
 
-            let available = ring_buffer.available_write();
-            if available == 0 {
-                drop(ring_buffer);
-                sched_yield();
-                continue;
-            }
+    /// Writes of at most `PIPE_BUF` bytes are never interleaved with other
+    /// writes, larger ones may be split.
+    pub fn write(&self, buf: &[u8]) -> VfsResult<usize> {
+        debug!("write data to fifo");
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if !self.has_readers() {
+            return Err(AxError::BrokenPipe);
+        }
+        // space needed before writing anything
+        let min_space = if buf.len() <= PIPE_BUF { buf.len() } else { 1 };
+        let mut ring_buffer = self.buffer.lock();
+        if ring_buffer.available_write() < min_space {
+            return Err(AxError::WouldBlock);
+        }
+        let write_size = ring_buffer.write(buf);
+        drop(ring_buffer);
+        self.observers.notify();
+        Ok(write_size)
+    }
 
-            for _ in 0..available {
-                if write_size == max_len {
-                    break;
-                }
-                ring_buffer.write_byte(buf[write_size]);
-                write_size += 1;
-            }
+    fn poll(&self) -> PollState {
+        let buffer = self.buffer.lock();
+        PollState {
+            readable: buffer.available_read() > 0,
+            // a writer without readers is woken up to get `EPIPE`
+            writable: buffer.available_write() >= PIPE_BUF || !self.has_readers(),
+            pollhup: !self.has_writers(),
+        }
+    }
 
-            if write_size > 0 {
-                return Ok(write_size);
-            }
+    /// Resizes the buffer to at least `size` bytes, rounded up to a power of
+    /// two. Returns the new capacity.
+    fn set_capacity(&self, size: usize) -> VfsResult<usize> {
+        if size > PIPE_MAX_SIZE {
+            return Err(AxError::OperationNotPermitted);
         }
+        let capacity = size.max(PIPE_BUF).next_power_of_two();
+        self.buffer.lock().resize(capacity)?;
+        self.observers.notify();
+        Ok(capacity)
     }
 }
 
 pub struct FifoNode {
     ino: u64,
     fifo: Fifo,
+    meta: NodeMeta,
 }
 
 impl FifoNode {
@@ -159,49 +187,82 @@ impl FifoNode {
         Self {
             ino,
             fifo: Fifo::new(),
+            meta: NodeMeta::default(),
         }
     }
 }
 
 impl VfsNodeOps for FifoNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_fifo(self.ino, 0, 0))
+        Ok(self.meta.apply(VfsNodeAttr::new_fifo(self.ino, 0, 0)))
+    }
+
+    fn set_owner(&self, uid: u32, gid: u32) -> VfsResult {
+        self.meta.set_owner(uid, gid);
+        Ok(())
+    }
+
+    fn set_times(&self, atime: Duration, mtime: Duration) -> VfsResult {
+        self.meta.set_times(atime, mtime);
+        Ok(())
     }
 
     // for fifo, offset is useless and ignored
     fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
-        Ok(self.fifo.read(buf).unwrap_or(0))
+        self.fifo.read(buf)
     }
 
     // for fifo, offset is useless and ignored
     fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
-        Ok(self.fifo.write(buf).unwrap_or(0))
+        self.fifo.write(buf)
+    }
+
+    fn poll(&self) -> VfsResult<PollState> {
+        Ok(self.fifo.poll())
+    }
+
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        self.fifo.observers.add(observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        self.fifo.observers.remove(observer, key);
     }
 
     // check if there are any readers
     fn fifo_has_readers(&self) -> bool {
-        self.fifo.readers.load(Ordering::SeqCst) > 0
+        self.fifo.has_readers()
+    }
+
+    fn fifo_has_writers(&self) -> bool {
+        self.fifo.has_writers()
+    }
+
+    fn fifo_capacity(&self) -> VfsResult<usize> {
+        Ok(self.fifo.buffer.lock().capacity())
+    }
+
+    fn set_fifo_capacity(&self, size: usize) -> VfsResult<usize> {
+        self.fifo.set_capacity(size)
     }
 
-    // when open a fifo node, we need to check mode and decide whether to block
-    fn open_fifo(&self, read: bool, write: bool, non_blocking: bool) -> VfsResult {
+    // waiting for the other end is left to the caller, which is woken up by
+    // the observers
+    fn open_fifo(&self, read: bool, write: bool, _non_blocking: bool) -> VfsResult {
         debug!("open a fifo node");
         if read {
             self.fifo.readers.fetch_add(1, Ordering::SeqCst);
-            if !non_blocking {
-                while self.fifo.writers.load(Ordering::SeqCst) == 0 {
-                    sched_yield();
-                }
-            }
         }
         if write {
             self.fifo.writers.fetch_add(1, Ordering::SeqCst);
-            if !non_blocking {
-                while self.fifo.readers.load(Ordering::SeqCst) == 0 {
-                    sched_yield();
-                }
-            }
         }
+        self.fifo.observers.notify();
         Ok(())
     }
 
@@ -213,6 +274,8 @@ impl VfsNodeOps for FifoNode {
         if write {
             self.fifo.writers.fetch_sub(1, Ordering::SeqCst);
         }
+        // closing an end hangs up the other
+        self.fifo.observers.notify();
         Ok(())
     }
 
@@ -228,16 +291,3 @@ impl VfsNodeOps for FifoNode {
 
     impl_vfs_non_dir_default! {}
 }
-
-// here, we cannot use ruxtask for possible cyclic package dependency.
-// so we use the following code to simulate the behavior of ruxtask::sched_yield.
-// actually, sched_yield() is similar to sys_sched_yield() in ruxos_posix_api,
-// the only difference is that we deleted the `#[cfg(feature = "multitask")]` attribute.
-fn sched_yield() {
-    #[cfg(not(feature = "multitask"))]
-    if cfg!(feature = "irq") {
-        ruxhal::arch::wait_for_irqs();
-    } else {
-        core::hint::spin_loop();
-    }
-}
@@ -7,65 +7,261 @@
  *   See the Mulan PSL v2 for more details.
  */
 
-use alloc::vec::Vec;
-use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult};
+use alloc::{collections::BTreeMap, sync::Arc};
+use axfs_vfs::{
+    impl_vfs_non_dir_default, VfsError, VfsFileSeals, VfsNodeAttr, VfsNodeOps, VfsNodePerm,
+    VfsNodeType, VfsResult,
+};
+use core::sync::atomic::{AtomicU16, AtomicU32, Ordering};
+use core::time::Duration;
 use spin::rwlock::RwLock;
 
+use crate::meta::NodeMeta;
+use crate::{PageUsage, PAGE_SIZE};
+
+#[cfg(feature = "axalloc")]
+use axalloc::GlobalPage as Page;
+
+/// A page of file data from the heap, when there is no page allocator.
+#[cfg(not(feature = "axalloc"))]
+struct Page(alloc::boxed::Box<[u8; PAGE_SIZE]>);
+
+#[cfg(not(feature = "axalloc"))]
+impl Page {
+    fn alloc_zero() -> VfsResult<Self> {
+        Ok(Self(alloc::boxed::Box::new([0; PAGE_SIZE])))
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+
+    fn as_slice_mut(&mut self) -> &mut [u8] {
+        &mut self.0[..]
+    }
+}
+
+/// The data of a file, whose pages never written are holes of zeros.
+#[derive(Default)]
+struct FileData {
+    size: u64,
+    pages: BTreeMap<u64, Page>,
+}
+
+impl FileData {
+    /// Drops the pages after `size`, and zeroes the tail of the last page.
+    /// Returns the number of pages dropped.
+    fn shrink(&mut self, size: u64) -> usize {
+        let dropped = self.pages.split_off(&size.div_ceil(PAGE_SIZE as u64));
+        let tail = (size % PAGE_SIZE as u64) as usize;
+        if tail != 0 {
+            if let Some(page) = self.pages.get_mut(&(size / PAGE_SIZE as u64)) {
+                page.as_slice_mut()[tail..].fill(0);
+            }
+        }
+        self.size = size;
+        dropped.len()
+    }
+}
+
 /// The file node in the RAM filesystem.
 ///
 /// It implements [`axfs_vfs::VfsNodeOps`].
 pub struct FileNode {
     ino: u64,
-    content: RwLock<Vec<u8>>,
+    /// Bits of [`VfsNodePerm`].
+    perm: AtomicU16,
+    meta: NodeMeta,
+    content: RwLock<FileData>,
+    /// Bits of [`VfsFileSeals`], changed only with `content` locked.
+    seals: AtomicU32,
+    usage: Arc<PageUsage>,
 }
 
 impl FileNode {
-    pub(super) const fn new(ino: u64) -> Self {
+    pub(super) fn new(ino: u64, usage: Arc<PageUsage>) -> Self {
         Self {
             ino,
-            content: RwLock::new(Vec::new()),
+            perm: AtomicU16::new(VfsNodePerm::default_file().bits()),
+            meta: NodeMeta::default(),
+            content: RwLock::new(FileData::default()),
+            // like tmpfs, files cannot be sealed unless allowed
+            seals: AtomicU32::new(VfsFileSeals::SEAL.bits()),
+            usage,
+        }
+    }
+
+    /// Allows seals to be added to the file, as for a `memfd` created with
+    /// `MFD_ALLOW_SEALING`.
+    pub fn allow_sealing(&self) {
+        let _content = self.content.write();
+        self.seals.store(0, Ordering::Release);
+    }
+
+    fn seals(&self) -> VfsFileSeals {
+        VfsFileSeals::from_bits_truncate(self.seals.load(Ordering::Acquire))
+    }
+
+    /// Checks the seals before the file changes from `old_size` to `new_size`.
+    fn check_resize(&self, old_size: u64, new_size: u64) -> VfsResult {
+        let seals = self.seals();
+        if (new_size < old_size && seals.contains(VfsFileSeals::SHRINK))
+            || (new_size > old_size && seals.contains(VfsFileSeals::GROW))
+        {
+            return Err(VfsError::OperationNotPermitted);
         }
+        Ok(())
+    }
+
+    /// Takes a zeroed page within the size limit of the filesystem.
+    fn alloc_page(&self) -> VfsResult<Page> {
+        self.usage.charge()?;
+        Page::alloc_zero().map_err(|e| {
+            self.usage.uncharge(1);
+            e
+        })
     }
 }
 
 impl VfsNodeOps for FileNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_file(
+        let content = self.content.read();
+        Ok(self.meta.apply(VfsNodeAttr::new(
             self.ino,
-            self.content.read().len() as _,
-            0,
-        ))
+            VfsNodePerm::from_bits_truncate(self.perm.load(Ordering::Relaxed)),
+            VfsNodeType::File,
+            content.size,
+            (content.pages.len() * PAGE_SIZE / 512) as _,
+        )))
+    }
+
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        self.perm.store(perm.bits(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn set_owner(&self, uid: u32, gid: u32) -> VfsResult {
+        self.meta.set_owner(uid, gid);
+        Ok(())
+    }
+
+    fn set_times(&self, atime: Duration, mtime: Duration) -> VfsResult {
+        self.meta.set_times(atime, mtime);
+        Ok(())
     }
 
     fn truncate(&self, size: u64) -> VfsResult {
         let mut content = self.content.write();
-        if size < content.len() as u64 {
-            content.truncate(size as _);
+        self.check_resize(content.size, size)?;
+        if size < content.size {
+            let dropped = content.shrink(size);
+            self.usage.uncharge(dropped);
         } else {
-            content.resize(size as _, 0);
+            // the new part is a hole
+            content.size = size;
         }
         Ok(())
     }
 
     fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
         let content = self.content.read();
-        let start = content.len().min(offset as usize);
-        let end = content.len().min(offset as usize + buf.len());
-        let src = &content[start..end];
-        buf[..src.len()].copy_from_slice(src);
-        Ok(src.len())
+        let end = content.size.min(offset.saturating_add(buf.len() as u64));
+        let mut pos = offset;
+        while pos < end {
+            let index = pos / PAGE_SIZE as u64;
+            let start = (pos % PAGE_SIZE as u64) as usize;
+            let len = (PAGE_SIZE - start).min((end - pos) as usize);
+            let dst = &mut buf[(pos - offset) as usize..][..len];
+            match content.pages.get(&index) {
+                Some(page) => dst.copy_from_slice(&page.as_slice()[start..start + len]),
+                None => dst.fill(0),
+            }
+            pos += len as u64;
+        }
+        Ok(end.saturating_sub(offset) as usize)
     }
 
     fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
-        let offset = offset as usize;
+        let end = offset
+            .checked_add(buf.len() as u64)
+            .ok_or(VfsError::InvalidInput)?;
         let mut content = self.content.write();
-        if offset + buf.len() > content.len() {
-            content.resize(offset + buf.len(), 0);
+        // `FUTURE_WRITE` is left to the callers, as it still allows the
+        // write-back of existing shared mappings
+        if self.seals().contains(VfsFileSeals::WRITE) {
+            return Err(VfsError::OperationNotPermitted);
+        }
+        self.check_resize(content.size, content.size.max(end))?;
+        let mut pos = offset;
+        while pos < end {
+            let index = pos / PAGE_SIZE as u64;
+            let start = (pos % PAGE_SIZE as u64) as usize;
+            let len = (PAGE_SIZE - start).min((end - pos) as usize);
+            if !content.pages.contains_key(&index) {
+                match self.alloc_page() {
+                    Ok(page) => content.pages.insert(index, page),
+                    // a short write if some data fit
+                    Err(_) if pos > offset => break,
+                    Err(e) => return Err(e),
+                };
+            }
+            let page = content.pages.get_mut(&index).unwrap();
+            let src = &buf[(pos - offset) as usize..][..len];
+            page.as_slice_mut()[start..start + len].copy_from_slice(src);
+            pos += len as u64;
+        }
+        content.size = content.size.max(pos);
+        Ok((pos - offset) as usize)
+    }
+
+    fn seek_data(&self, offset: u64) -> VfsResult<u64> {
+        let content = self.content.read();
+        let first = offset / PAGE_SIZE as u64;
+        match content.pages.range(first..).next() {
+            Some((&index, _)) => {
+                let pos = offset.max(index * PAGE_SIZE as u64);
+                if pos < content.size {
+                    Ok(pos)
+                } else {
+                    Err(VfsError::NoSuchDevice)
+                }
+            }
+            None => Err(VfsError::NoSuchDevice),
         }
-        let dst = &mut content[offset..offset + buf.len()];
-        dst.copy_from_slice(&buf[..dst.len()]);
-        Ok(buf.len())
+    }
+
+    fn seek_hole(&self, offset: u64) -> VfsResult<u64> {
+        let content = self.content.read();
+        if offset >= content.size {
+            return Err(VfsError::NoSuchDevice);
+        }
+        let mut index = offset / PAGE_SIZE as u64;
+        while content.pages.contains_key(&index) {
+            index += 1;
+        }
+        // there is always a hole at the end
+        Ok(offset.max(index * PAGE_SIZE as u64).min(content.size))
+    }
+
+    fn get_seals(&self) -> VfsResult<VfsFileSeals> {
+        Ok(self.seals())
+    }
+
+    fn add_seals(&self, seals: VfsFileSeals) -> VfsResult {
+        let _content = self.content.write();
+        let old = self.seals();
+        if old.contains(VfsFileSeals::SEAL) {
+            return Err(VfsError::OperationNotPermitted);
+        }
+        self.seals.store((old | seals).bits(), Ordering::Release);
+        Ok(())
     }
 
     impl_vfs_non_dir_default! {}
 }
+
+impl Drop for FileNode {
+    fn drop(&mut self) {
+        self.usage.uncharge(self.content.get_mut().pages.len());
+    }
+}
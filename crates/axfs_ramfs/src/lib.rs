@@ -10,26 +10,43 @@
 //! RAM filesystem used by [ArceOS](https://github.com/rcore-os/arceos).
 //!
 //! The implementation is based on [`axfs_vfs`].
+//!
+//! With the `axalloc` feature, file data is kept in pages of the page
+//! allocator of `axalloc`, otherwise in the heap.
 
 #![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
+mod device;
 mod dir;
 mod fifo;
 mod file;
+mod meta;
+mod symlink;
 
 #[cfg(test)]
 mod tests;
 
+pub use self::device::DeviceNode;
 pub use self::dir::DirNode;
 pub use self::file::FileNode;
+pub use self::symlink::SymlinkNode;
 
 use alloc::sync::Arc;
-use axfs_vfs::{AbsPath, VfsNodeRef, VfsOps, VfsResult};
-use core::sync::atomic::AtomicU64;
+use axfs_vfs::{AbsPath, FileSystemInfo, VfsError, VfsNodeRef, VfsOps, VfsResult};
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use spin::once::Once;
 
+/// Size of a page of file data.
+const PAGE_SIZE: usize = 4096;
+
+/// Magic number of tmpfs, reported by `statfs`.
+const TMPFS_MAGIC: u64 = 0x0102_1994;
+
+/// Max length of file names.
+const NAME_MAX: u64 = 255;
+
 /// An auto-increasing inode number allocator.
 pub struct InoAllocator {
     current: AtomicU64,
@@ -50,21 +67,72 @@ impl InoAllocator {
     }
 }
 
+/// The pages taken by the files of a filesystem, within its size limit.
+pub(crate) struct PageUsage {
+    used: AtomicUsize,
+    max: usize,
+}
+
+impl PageUsage {
+    const fn new(max: usize) -> Self {
+        Self {
+            used: AtomicUsize::new(0),
+            max,
+        }
+    }
+
+    /// Takes a page, or fails with `StorageFull` if all are taken.
+    pub(crate) fn charge(&self) -> VfsResult {
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < self.max).then_some(n + 1)
+            })
+            .map(|_| ())
+            .map_err(|_| VfsError::StorageFull)
+    }
+
+    /// Gives back `count` pages.
+    pub(crate) fn uncharge(&self, count: usize) {
+        self.used.fetch_sub(count, Ordering::AcqRel);
+    }
+
+    fn used(&self) -> usize {
+        self.used.load(Ordering::Acquire)
+    }
+}
+
 /// A RAM filesystem that implements [`axfs_vfs::VfsOps`].
+///
+/// File data is kept in pages, which are taken when first written, so that
+/// files may have holes.
 pub struct RamFileSystem {
     parent: Once<VfsNodeRef>,
     root: Arc<DirNode>,
     _ialloc: Arc<InoAllocator>,
+    usage: Arc<PageUsage>,
 }
 
 impl RamFileSystem {
-    /// Create a new instance.
+    /// Create a new instance, whose size is only limited by the memory.
     pub fn new() -> Self {
+        Self::with_max_pages(usize::MAX)
+    }
+
+    /// Create a new instance holding at most `max_size` bytes of file data,
+    /// rounded up to pages. Writes beyond it fail with `StorageFull`.
+    pub fn with_max_size(max_size: u64) -> Self {
+        let max_pages = max_size.div_ceil(PAGE_SIZE as u64);
+        Self::with_max_pages(max_pages.try_into().unwrap_or(usize::MAX))
+    }
+
+    fn with_max_pages(max_pages: usize) -> Self {
         let ialloc = Arc::new(InoAllocator::new(0));
+        let usage = Arc::new(PageUsage::new(max_pages));
         Self {
             parent: Once::new(),
-            root: DirNode::new(ialloc.alloc(), None, Arc::downgrade(&ialloc)),
+            root: DirNode::new(ialloc.alloc(), None, Arc::downgrade(&ialloc), usage.clone()),
             _ialloc: ialloc,
+            usage,
         }
     }
 
@@ -84,11 +152,42 @@ impl VfsOps for RamFileSystem {
         Ok(())
     }
 
+    fn statfs(&self) -> VfsResult<FileSystemInfo> {
+        let used = self.usage.used() as u64;
+        let blocks = if self.usage.max == usize::MAX {
+            used + free_pages() as u64
+        } else {
+            self.usage.max as u64
+        };
+        let free = blocks.saturating_sub(used);
+        Ok(FileSystemInfo {
+            fs_type: TMPFS_MAGIC,
+            block_size: PAGE_SIZE as u64,
+            blocks,
+            blocks_free: free,
+            blocks_avail: free,
+            name_len: NAME_MAX,
+            ..Default::default()
+        })
+    }
+
     fn root_dir(&self) -> VfsNodeRef {
         self.root.clone()
     }
 }
 
+/// Number of pages left in the page allocator.
+#[cfg(feature = "axalloc")]
+fn free_pages() -> usize {
+    axalloc::global_allocator().available_pages()
+}
+
+/// Number of pages left, unknown without the page allocator.
+#[cfg(not(feature = "axalloc"))]
+fn free_pages() -> usize {
+    0
+}
+
 impl Default for RamFileSystem {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,41 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+use axfs_vfs::VfsNodeAttr;
+use core::time::Duration;
+use spin::RwLock;
+
+/// The owner and times of a node, which are reported only once they are set,
+/// e.g. when a file is copied from another filesystem.
+#[derive(Default)]
+pub(crate) struct NodeMeta {
+    owner: RwLock<Option<(u32, u32)>>,
+    times: RwLock<Option<(Duration, Duration)>>,
+}
+
+impl NodeMeta {
+    /// Adds the owner and times set to `attr`.
+    pub fn apply(&self, mut attr: VfsNodeAttr) -> VfsNodeAttr {
+        if let Some((uid, gid)) = *self.owner.read() {
+            attr = attr.with_owner(uid, gid);
+        }
+        if let Some((atime, mtime)) = *self.times.read() {
+            attr = attr.with_times(atime, mtime);
+        }
+        attr
+    }
+
+    pub fn set_owner(&self, uid: u32, gid: u32) {
+        *self.owner.write() = Some((uid, gid));
+    }
+
+    pub fn set_times(&self, atime: Duration, mtime: Duration) {
+        *self.times.write() = Some((atime, mtime));
+    }
+}
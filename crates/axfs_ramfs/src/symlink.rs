@@ -0,0 +1,64 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+use alloc::string::String;
+use axfs_vfs::VfsResult;
+use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType};
+use core::time::Duration;
+
+use crate::meta::NodeMeta;
+
+/// The symbolic link node in the RAM filesystem.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct SymlinkNode {
+    ino: u64,
+    target: String,
+    meta: NodeMeta,
+}
+
+impl SymlinkNode {
+    pub(super) fn new(ino: u64, target: &str) -> Self {
+        Self {
+            ino,
+            target: target.into(),
+            meta: NodeMeta::default(),
+        }
+    }
+}
+
+impl VfsNodeOps for SymlinkNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(self.meta.apply(VfsNodeAttr::new(
+            self.ino,
+            VfsNodePerm::from_bits_truncate(0o777),
+            VfsNodeType::SymLink,
+            self.target.len() as _,
+            0,
+        )))
+    }
+
+    fn set_owner(&self, uid: u32, gid: u32) -> VfsResult {
+        self.meta.set_owner(uid, gid);
+        Ok(())
+    }
+
+    fn set_times(&self, atime: Duration, mtime: Duration) -> VfsResult {
+        self.meta.set_times(atime, mtime);
+        Ok(())
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        let len = buf.len().min(self.target.len());
+        buf[..len].copy_from_slice(&self.target.as_bytes()[..len]);
+        Ok(len)
+    }
+
+    impl_vfs_non_dir_default! {}
+}
@@ -9,7 +9,7 @@
 
 use std::sync::Arc;
 
-use axfs_vfs::{RelPath, VfsError, VfsNodeType, VfsResult};
+use axfs_vfs::{RelPath, VfsError, VfsNodeRef, VfsNodeType, VfsResult, PIPE_BUF};
 
 use crate::*;
 
@@ -195,3 +195,166 @@ fn test_ramfs() {
     assert_eq!(root.unlink(&RelPath::new_canonicalized("./foo")), Ok(()));
     assert!(ramfs.root_dir_node().get_entries().is_empty());
 }
+
+#[test]
+fn test_ramfs_holes() {
+    let ramfs = RamFileSystem::new();
+    let root = ramfs.root_dir();
+    root.create(&RelPath::new_canonicalized("f"), VfsNodeType::File)
+        .unwrap();
+    let file = root.lookup(&RelPath::new_canonicalized("f")).unwrap();
+
+    // [hole][data][hole], the last one made by truncate
+    let data = [1; PAGE_SIZE];
+    assert_eq!(file.write_at(PAGE_SIZE as u64, &data), Ok(PAGE_SIZE));
+    file.truncate(3 * PAGE_SIZE as u64).unwrap();
+    assert_eq!(file.get_attr().unwrap().size(), 3 * PAGE_SIZE as u64);
+
+    let mut buf = [2; 3 * PAGE_SIZE];
+    assert_eq!(file.read_at(0, &mut buf), Ok(3 * PAGE_SIZE));
+    assert_eq!(buf[..PAGE_SIZE], [0; PAGE_SIZE]);
+    assert_eq!(buf[PAGE_SIZE..2 * PAGE_SIZE], data);
+    assert_eq!(buf[2 * PAGE_SIZE..], [0; PAGE_SIZE]);
+
+    let page = PAGE_SIZE as u64;
+    assert_eq!(file.seek_data(0), Ok(page));
+    assert_eq!(file.seek_data(page + 10), Ok(page + 10));
+    assert_eq!(file.seek_data(2 * page).err(), Some(VfsError::NoSuchDevice));
+    assert_eq!(file.seek_hole(0), Ok(0));
+    assert_eq!(file.seek_hole(page), Ok(2 * page));
+    assert_eq!(file.seek_hole(2 * page + 10), Ok(2 * page + 10));
+    assert_eq!(file.seek_hole(3 * page).err(), Some(VfsError::NoSuchDevice));
+
+    // shrinking into the data leaves only its start
+    file.truncate(page + 10).unwrap();
+    assert_eq!(file.seek_hole(page), Ok(page + 10));
+    assert_eq!(file.read_at(0, &mut buf), Ok(PAGE_SIZE + 10));
+    assert_eq!(buf[PAGE_SIZE..PAGE_SIZE + 10], [1; 10]);
+}
+
+#[test]
+fn test_ramfs_size_limit() {
+    let ramfs = RamFileSystem::with_max_size(2 * PAGE_SIZE as u64 - 1);
+    let info = ramfs.statfs().unwrap();
+    assert_eq!(info.fs_type, TMPFS_MAGIC);
+    assert_eq!(info.block_size, PAGE_SIZE as u64);
+    assert_eq!(
+        (info.blocks, info.blocks_free, info.blocks_avail),
+        (2, 2, 2)
+    );
+
+    let root = ramfs.root_dir();
+    root.create(&RelPath::new_canonicalized("f1"), VfsNodeType::File)
+        .unwrap();
+    root.create(&RelPath::new_canonicalized("f2"), VfsNodeType::File)
+        .unwrap();
+    let f1 = root.lookup(&RelPath::new_canonicalized("f1")).unwrap();
+    let f2 = root.lookup(&RelPath::new_canonicalized("f2")).unwrap();
+
+    // a short write up to the limit, then no space at all
+    let data = [1; 3 * PAGE_SIZE];
+    assert_eq!(f1.write_at(10, &data), Ok(2 * PAGE_SIZE - 10));
+    assert_eq!(f1.get_attr().unwrap().size(), 2 * PAGE_SIZE as u64);
+    assert_eq!(ramfs.statfs().unwrap().blocks_free, 0);
+    assert_eq!(
+        f1.write_at(2 * PAGE_SIZE as u64, &data).err(),
+        Some(VfsError::StorageFull)
+    );
+    assert_eq!(f2.write_at(0, &data).err(), Some(VfsError::StorageFull));
+
+    // holes and pages already taken need no space
+    assert_eq!(f1.write_at(0, &data[..PAGE_SIZE]), Ok(PAGE_SIZE));
+    f2.truncate(4 * PAGE_SIZE as u64).unwrap();
+
+    // freed pages can be taken again
+    f1.truncate(PAGE_SIZE as u64).unwrap();
+    assert_eq!(ramfs.statfs().unwrap().blocks_free, 1);
+    assert_eq!(f2.write_at(0, &data), Ok(PAGE_SIZE));
+    root.unlink(&RelPath::new_canonicalized("f1")).unwrap();
+    drop(f1);
+    assert_eq!(ramfs.statfs().unwrap().blocks_free, 1);
+}
+
+fn create_fifo(ramfs: &RamFileSystem) -> VfsNodeRef {
+    let root = ramfs.root_dir();
+    root.create(&RelPath::new_canonicalized("p"), VfsNodeType::Fifo)
+        .unwrap();
+    let fifo = root.lookup(&RelPath::new_canonicalized("p")).unwrap();
+    assert_eq!(fifo.get_attr().unwrap().file_type(), VfsNodeType::Fifo);
+    fifo
+}
+
+#[test]
+fn test_fifo_pipe_buf() {
+    let ramfs = RamFileSystem::new();
+    let fifo = create_fifo(&ramfs);
+    fifo.open_fifo(true, true, true).unwrap();
+    let capacity = fifo.fifo_capacity().unwrap();
+
+    // leave less than `PIPE_BUF` bytes of space
+    let data = vec![1; capacity];
+    let filled = capacity - PIPE_BUF + 1;
+    assert_eq!(fifo.write_at(0, &data[..filled]), Ok(filled));
+    assert!(!fifo.poll().unwrap().writable);
+
+    // a write of at most `PIPE_BUF` bytes is all or nothing
+    assert_eq!(
+        fifo.write_at(0, &data[..PIPE_BUF]).err(),
+        Some(VfsError::WouldBlock)
+    );
+    assert_eq!(fifo.write_at(0, &[2; 10]), Ok(10));
+    // a larger one is split
+    assert_eq!(fifo.write_at(0, &data[..PIPE_BUF + 1]), Ok(PIPE_BUF - 11));
+    assert_eq!(fifo.write_at(0, &data).err(), Some(VfsError::WouldBlock));
+
+    let mut buf = vec![0; capacity];
+    assert_eq!(fifo.read_at(0, &mut buf[..filled]), Ok(filled));
+    assert_eq!(fifo.read_at(0, &mut buf), Ok(capacity - filled));
+    assert_eq!(buf[..10], [2; 10]);
+    assert_eq!(fifo.read_at(0, &mut buf).err(), Some(VfsError::WouldBlock));
+    assert!(fifo.poll().unwrap().writable);
+
+    // no readers, then no writers
+    fifo.release_fifo(true, false).unwrap();
+    assert_eq!(fifo.write_at(0, &[1]).err(), Some(VfsError::BrokenPipe));
+    assert!(fifo.poll().unwrap().writable);
+    fifo.release_fifo(false, true).unwrap();
+    assert!(fifo.poll().unwrap().pollhup);
+    assert_eq!(fifo.read_at(0, &mut buf), Ok(0));
+}
+
+#[test]
+fn test_fifo_resize() {
+    let ramfs = RamFileSystem::new();
+    let fifo = create_fifo(&ramfs);
+    fifo.open_fifo(true, true, true).unwrap();
+
+    // the capacity is at least `PIPE_BUF` and a power of two
+    assert_eq!(fifo.set_fifo_capacity(1), Ok(PIPE_BUF));
+    assert_eq!(fifo.set_fifo_capacity(PIPE_BUF + 1), Ok(2 * PIPE_BUF));
+    assert_eq!(
+        fifo.set_fifo_capacity(0x100001).err(),
+        Some(VfsError::OperationNotPermitted)
+    );
+    assert_eq!(fifo.fifo_capacity(), Ok(2 * PIPE_BUF));
+
+    // data wrapped around the end of the buffer is kept in order
+    let data: Vec<u8> = (0..2 * PIPE_BUF).map(|i| i as u8).collect();
+    let mut buf = vec![0; 4 * PIPE_BUF];
+    assert_eq!(
+        fifo.write_at(0, &data[..PIPE_BUF + 100]),
+        Ok(PIPE_BUF + 100)
+    );
+    assert_eq!(fifo.read_at(0, &mut buf[..PIPE_BUF]), Ok(PIPE_BUF));
+    assert_eq!(fifo.write_at(0, &data[..PIPE_BUF]), Ok(PIPE_BUF));
+
+    // the data doesn't fit in a smaller buffer
+    assert_eq!(
+        fifo.set_fifo_capacity(PIPE_BUF).err(),
+        Some(VfsError::ResourceBusy)
+    );
+    assert_eq!(fifo.set_fifo_capacity(4 * PIPE_BUF), Ok(4 * PIPE_BUF));
+    assert_eq!(fifo.read_at(0, &mut buf), Ok(PIPE_BUF + 100));
+    assert_eq!(buf[..100], data[PIPE_BUF..PIPE_BUF + 100]);
+    assert_eq!(buf[100..PIPE_BUF + 100], data[..PIPE_BUF]);
+}
@@ -9,8 +9,8 @@
 
 //! Virtual filesystem interfaces used by [ArceOS](https://github.com/rcore-os/arceos).
 //!
-//! A filesystem is a set of files and directories (symbol links are not
-//! supported currently), collectively referred to as **nodes**, which are
+//! A filesystem is a set of files, directories and symbolic links,
+//! collectively referred to as **nodes**, which are
 //! conceptually similar to [inodes] in Linux. A file system needs to implement
 //! the [`VfsOps`] trait, its files and directories need to implement the
 //! [`VfsNodeOps`] trait.
@@ -31,33 +31,58 @@
 //! | [`open()`](VfsNodeOps::open) | Do something when the node is opened | both |
 //! | [`release()`](VfsNodeOps::release) | Do something when the node is closed | both |
 //! | [`get_attr()`](VfsNodeOps::get_attr) | Get the attributes of the node | both |
+//! | [`set_perm()`](VfsNodeOps::set_perm) | Set the permission of the node | both |
+//! | [`set_owner()`](VfsNodeOps::set_owner) | Set the owner of the node | both |
+//! | [`set_times()`](VfsNodeOps::set_times) | Set the access and modification times | both |
 //! | [`read_at()`](VfsNodeOps::read_at) | Read data from the file | file |
 //! | [`write_at()`](VfsNodeOps::write_at) | Write data to the file | file |
 //! | [`fsync()`](VfsNodeOps::fsync) | Synchronize the file data to disk | file |
 //! | [`truncate()`](VfsNodeOps::truncate) | Truncate the file | file |
+//! | [`seek_data()`](VfsNodeOps::seek_data) | Find the next data of the file | file |
+//! | [`seek_hole()`](VfsNodeOps::seek_hole) | Find the next hole of the file | file |
+//! | [`ioctl()`](VfsNodeOps::ioctl) | Perform a device-specific request | file |
+//! | [`device_memory()`](VfsNodeOps::device_memory) | Get the memory to map of a device | file |
+//! | [`device_unmapped()`](VfsNodeOps::device_unmapped) | Release a mapping of the device memory | file |
+//! | [`poll()`](VfsNodeOps::poll) | Check whether the file is ready for I/O | file |
+//! | [`add_poll_observer()`](VfsNodeOps::add_poll_observer) | Get notified of readiness changes | file |
 //! | [`parent()`](VfsNodeOps::parent) | Get the parent directory | directory |
 //! | [`lookup()`](VfsNodeOps::lookup) | Lookup the node with the given path | directory |
 //! | [`create()`](VfsNodeOps::create) | Create a new node with the given path | directory |
 //! | [`link()`](VfsNodeOps::link) | Create a hard link with the given path | directory |
+//! | [`symlink()`](VfsNodeOps::symlink) | Create a symbolic link with the given path | directory |
 //! | [`unlink()`](VfsNodeOps::unlink) | Remove the node with the given path | directory |
 //! | [`read_dir()`](VfsNodeOps::read_dir) | Read directory entries | directory |
 //! | [`is_empty()`](VfsNodeOps::is_empty) | Check if the directory is empty | directory |
+//! | [`readlink()`](VfsNodeOps::readlink) | Read the target of the symbolic link | symbolic link |
 //!
 //! [inodes]: https://en.wikipedia.org/wiki/Inode
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
 mod macros;
 mod path;
+mod poll;
 mod structs;
 
-use alloc::sync::Arc;
+#[cfg(test)]
+mod tests;
+
+use alloc::sync::{Arc, Weak};
 use axerrno::{ax_err, AxError, AxResult};
+use axio::PollState;
+use core::time::Duration;
 
 pub use self::path::{AbsPath, RelPath};
-pub use self::structs::{FileSystemInfo, VfsDirEntry, VfsNodeAttr, VfsNodePerm, VfsNodeType};
+pub use self::poll::{PollObserver, PollObservers};
+pub use self::structs::{
+    FileSystemInfo, VfsDirEntry, VfsFileLock, VfsFileSeals, VfsLockType, VfsNodeAttr, VfsNodePerm,
+    VfsNodeType,
+};
+
+/// Writes of at most this many bytes to a pipe or FIFO are atomic.
+pub const PIPE_BUF: usize = 4096;
 
 /// A wrapper of [`Arc<dyn VfsNodeOps>`].
 pub type VfsNodeRef = Arc<dyn VfsNodeOps>;
@@ -124,6 +149,21 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Set the permission of the node.
+    fn set_perm(&self, _perm: VfsNodePerm) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Set the user and group IDs of the owner of the node.
+    fn set_owner(&self, _uid: u32, _gid: u32) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Set the last access and modification times of the node, since the epoch.
+    fn set_times(&self, _atime: Duration, _mtime: Duration) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
     // file operations:
 
     /// Read data from the file at the given offset.
@@ -136,6 +176,22 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(InvalidInput)
     }
 
+    /// Read data from the file at the given offset around the caches of the
+    /// filesystem, for direct I/O.
+    ///
+    /// Filesystems without caches read as [`read_at`](Self::read_at).
+    fn read_at_direct(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.read_at(offset, buf)
+    }
+
+    /// Write data to the file at the given offset around the caches of the
+    /// filesystem, for direct I/O.
+    ///
+    /// Filesystems without caches write as [`write_at`](Self::write_at).
+    fn write_at_direct(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.write_at(offset, buf)
+    }
+
     /// Flush the file, synchronize the data to disk.
     fn fsync(&self) -> VfsResult {
         ax_err!(InvalidInput)
@@ -146,6 +202,87 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(InvalidInput)
     }
 
+    /// Find the start of the first data at or after `offset`, for `SEEK_DATA`.
+    ///
+    /// Files without holes are data up to the end.
+    fn seek_data(&self, offset: u64) -> VfsResult<u64> {
+        if offset >= self.get_attr()?.size() {
+            return ax_err!(NoSuchDevice);
+        }
+        Ok(offset)
+    }
+
+    /// Find the start of the first hole at or after `offset`, for `SEEK_HOLE`.
+    ///
+    /// Files without holes have one at the end.
+    fn seek_hole(&self, offset: u64) -> VfsResult<u64> {
+        let size = self.get_attr()?.size();
+        if offset >= size {
+            return ax_err!(NoSuchDevice);
+        }
+        Ok(size)
+    }
+
+    /// Perform the device-specific request `cmd` with the argument `arg`,
+    /// usually a pointer to the data of the request.
+    ///
+    /// Return the non-negative result of the request.
+    fn ioctl(&self, _cmd: usize, _arg: usize) -> VfsResult<usize> {
+        ax_err!(Unsupported)
+    }
+
+    /// Get the kernel virtual address and the size of the device memory that
+    /// `mmap` maps directly instead of the file data, e.g. a framebuffer.
+    ///
+    /// Each call made by `mmap` is paired with a call of
+    /// [`device_unmapped()`](Self::device_unmapped) once the mapping is gone.
+    fn device_memory(&self) -> Option<(usize, usize)> {
+        None
+    }
+
+    /// Release a mapping of the device memory got by
+    /// [`device_memory()`](Self::device_memory).
+    fn device_unmapped(&self) {}
+
+    /// Check whether the file can be read or written without blocking.
+    ///
+    /// Only device nodes whose data come and go need to override it.
+    fn poll(&self) -> VfsResult<PollState> {
+        Ok(PollState {
+            readable: true,
+            writable: true,
+            pollhup: false,
+        })
+    }
+
+    /// Register `observer` to be notified with `key` whenever the result of
+    /// [`poll()`](VfsNodeOps::poll) may change, to wake up the tasks that got
+    /// `WouldBlock`.
+    ///
+    /// Returns `false` if the node doesn't push readiness changes, in which
+    /// case it must be polled instead.
+    fn add_poll_observer(
+        &self,
+        _observer: Weak<dyn PollObserver>,
+        _key: usize,
+        _exclusive: bool,
+    ) -> bool {
+        false
+    }
+
+    /// Unregister an observer added by [`add_poll_observer()`](VfsNodeOps::add_poll_observer).
+    fn remove_poll_observer(&self, _observer: &Weak<dyn PollObserver>, _key: usize) {}
+
+    // symbolic link operations:
+
+    /// Read the target of the symbolic link into `buf`.
+    ///
+    /// Return the number of bytes read, the target is truncated if `buf` is
+    /// too small.
+    fn readlink(&self, _buf: &mut [u8]) -> VfsResult<usize> {
+        ax_err!(InvalidInput)
+    }
+
     // directory operations:
 
     /// Get the parent directory of this directory.
@@ -174,6 +311,12 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Create a symbolic link with the given `path` in the directory, which
+    /// points to `target`.
+    fn symlink(&self, _path: &RelPath, _target: &str) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
     /// Remove (the hard link of) the node with the given `path` in the directory.
     fn unlink(&self, _path: &RelPath) -> VfsResult {
         ax_err!(Unsupported)
@@ -184,6 +327,12 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Atomically exchange the nodes `src_path` and `dst_path` in the
+    /// directory, which must both exist.
+    fn exchange(&self, _src_path: &RelPath, _dst_path: &RelPath) -> VfsResult<()> {
+        ax_err!(Unsupported)
+    }
+
     /// Read directory entries into `dirents`, starting from `start_idx`.
     fn read_dir(&self, _start_idx: usize, _dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
         ax_err!(Unsupported)
@@ -247,6 +396,50 @@ pub trait VfsNodeOps: Send + Sync {
     fn fifo_has_readers(&self) -> bool {
         false
     }
+
+    /// if the node is a fifo, check if there are writers
+    fn fifo_has_writers(&self) -> bool {
+        false
+    }
+
+    /// Get the buffer capacity of a fifo, used by `F_GETPIPE_SZ`.
+    fn fifo_capacity(&self) -> VfsResult<usize> {
+        ax_err!(Unsupported)
+    }
+
+    /// Resize the buffer of a fifo to at least `size` bytes, used by
+    /// `F_SETPIPE_SZ`. Returns the new capacity.
+    fn set_fifo_capacity(&self, _size: usize) -> VfsResult<usize> {
+        ax_err!(Unsupported)
+    }
+
+    /// Places or removes a byte-range lock on the server, for filesystems
+    /// whose locks are shared with other clients.
+    ///
+    /// Returns `WouldBlock` if the lock is held by someone else, and
+    /// `Unsupported` if locks are only managed locally.
+    fn set_lock(&self, _lock: VfsFileLock) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Gets the first lock on the server that conflicts with `lock`.
+    fn get_lock(&self, _lock: VfsFileLock) -> VfsResult<Option<VfsFileLock>> {
+        ax_err!(Unsupported)
+    }
+
+    /// Gets the seals of the file.
+    ///
+    /// Returns `InvalidInput` if the file cannot be sealed.
+    fn get_seals(&self) -> VfsResult<VfsFileSeals> {
+        ax_err!(InvalidInput)
+    }
+
+    /// Adds seals to the file.
+    ///
+    /// Returns `OperationNotPermitted` if the file is sealed against sealing.
+    fn add_seals(&self, _seals: VfsFileSeals) -> VfsResult {
+        ax_err!(InvalidInput)
+    }
 }
 
 #[doc(hidden)]
@@ -0,0 +1,90 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+use alloc::sync::Weak;
+use alloc::vec::Vec;
+use spinlock::SpinNoIrq;
+
+/// Receiver of readiness changes pushed by nodes and file-like objects.
+pub trait PollObserver: Send + Sync {
+    /// Called when the readiness of the object registered with `key` may have
+    /// changed, possibly in interrupt context.
+    ///
+    /// Returns `true` if a task waiting for the readiness is woken up.
+    fn on_ready(&self, key: usize) -> bool;
+}
+
+struct ObserverEntry {
+    observer: Weak<dyn PollObserver>,
+    key: usize,
+    exclusive: bool,
+}
+
+/// Observers registered on an object that pushes its readiness changes.
+pub struct PollObservers {
+    list: SpinNoIrq<Vec<ObserverEntry>>,
+}
+
+impl PollObservers {
+    /// Creates an empty observer list.
+    pub const fn new() -> Self {
+        Self {
+            list: SpinNoIrq::new(Vec::new()),
+        }
+    }
+
+    /// Adds an observer to be notified with `key`.
+    ///
+    /// Of the exclusive observers, only the first one that wakes up a task is
+    /// notified.
+    pub fn add(&self, observer: Weak<dyn PollObserver>, key: usize, exclusive: bool) {
+        self.list.lock().push(ObserverEntry {
+            observer,
+            key,
+            exclusive,
+        });
+    }
+
+    /// Removes an observer added with the same `key`.
+    pub fn remove(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        self.list
+            .lock()
+            .retain(|e| !(e.key == key && Weak::ptr_eq(&e.observer, observer)));
+    }
+
+    /// Notifies the observers that the readiness may have changed.
+    ///
+    /// Exclusive observers are notified in order until one of them wakes up
+    /// a task, others are always notified.
+    pub fn notify(&self) {
+        // Call the observers without the lock, as dropping the last reference
+        // to an observer may unregister it.
+        let observers: Vec<_> = {
+            let mut list = self.list.lock();
+            list.retain(|e| e.observer.strong_count() > 0);
+            list.iter()
+                .filter_map(|e| Some((e.observer.upgrade()?, e.key, e.exclusive)))
+                .collect()
+        };
+        let mut exclusive_woken = false;
+        for (observer, key, exclusive) in observers.iter() {
+            if *exclusive && exclusive_woken {
+                continue;
+            }
+            let woken = observer.on_ready(*key);
+            exclusive_woken |= *exclusive && woken;
+        }
+    }
+}
+
+impl Default for PollObservers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
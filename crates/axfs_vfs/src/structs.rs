@@ -7,11 +7,28 @@
  *   See the Mulan PSL v2 for more details.
  */
 
-/// Filesystem attributes.
-///
-/// Currently not used.
-#[non_exhaustive]
-pub struct FileSystemInfo;
+use core::time::Duration;
+
+/// Filesystem attributes, as reported by `statfs`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSystemInfo {
+    /// Magic number of the filesystem type.
+    pub fs_type: u64,
+    /// Size of a block, in bytes.
+    pub block_size: u64,
+    /// Total number of blocks.
+    pub blocks: u64,
+    /// Number of free blocks.
+    pub blocks_free: u64,
+    /// Number of free blocks available to unprivileged users.
+    pub blocks_avail: u64,
+    /// Total number of inodes, 0 if not limited.
+    pub files: u64,
+    /// Number of free inodes.
+    pub files_free: u64,
+    /// Max length of file names.
+    pub name_len: u64,
+}
 
 /// Node (file/directory) attributes.
 #[allow(dead_code)]
@@ -27,6 +44,31 @@ pub struct VfsNodeAttr {
     size: u64,
     /// Number of 512B blocks allocated.
     blocks: u64,
+    /// Device number, for device nodes.
+    rdev: u64,
+    /// User and group IDs of the owner, `None` if the filesystem doesn't keep them.
+    owner: Option<(u32, u32)>,
+    /// Last access and modification times since the epoch, `None` if the
+    /// filesystem doesn't keep them.
+    times: Option<(Duration, Duration)>,
+}
+
+bitflags::bitflags! {
+    /// Seals restricting the changes allowed to a file, with the same values
+    /// as the `F_SEAL_*` flags of `fcntl`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct VfsFileSeals: u32 {
+        /// No more seals can be added.
+        const SEAL = 0x1;
+        /// The file cannot shrink.
+        const SHRINK = 0x2;
+        /// The file cannot grow.
+        const GROW = 0x4;
+        /// The content of the file cannot be modified.
+        const WRITE = 0x8;
+        /// Like `WRITE`, but existing writable shared mappings are kept.
+        const FUTURE_WRITE = 0x10;
+    }
 }
 
 bitflags::bitflags! {
@@ -82,6 +124,30 @@ impl Default for VfsNodeType {
     }
 }
 
+/// Type of a byte-range file lock.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum VfsLockType {
+    /// Shared lock, for reading.
+    Read,
+    /// Exclusive lock, for writing.
+    Write,
+    /// Removes a lock.
+    Unlock,
+}
+
+/// A byte-range file lock.
+#[derive(Debug, Clone, Copy)]
+pub struct VfsFileLock {
+    /// Lock type.
+    pub ty: VfsLockType,
+    /// Offset of the first locked byte.
+    pub start: u64,
+    /// Number of locked bytes, 0 means up to the end of the file.
+    pub len: u64,
+    /// Id of the lock owner, as seen by other clients of the filesystem.
+    pub pid: u32,
+}
+
 /// Directory entry.
 #[derive(Clone)]
 pub struct VfsDirEntry {
@@ -231,6 +297,9 @@ impl VfsNodeAttr {
             ty,
             size,
             blocks,
+            rdev: 0,
+            owner: None,
+            times: None,
         }
     }
 
@@ -242,6 +311,9 @@ impl VfsNodeAttr {
             ty: VfsNodeType::File,
             size,
             blocks,
+            rdev: 0,
+            owner: None,
+            times: None,
         }
     }
 
@@ -253,6 +325,9 @@ impl VfsNodeAttr {
             ty: VfsNodeType::Socket,
             size,
             blocks,
+            rdev: 0,
+            owner: None,
+            times: None,
         }
     }
 
@@ -264,6 +339,9 @@ impl VfsNodeAttr {
             ty: VfsNodeType::Fifo,
             size,
             blocks,
+            rdev: 0,
+            owner: None,
+            times: None,
         }
     }
 
@@ -276,13 +354,49 @@ impl VfsNodeAttr {
             ty: VfsNodeType::Dir,
             size,
             blocks,
+            rdev: 0,
+            owner: None,
+            times: None,
         }
     }
 
+    /// Sets the device number of a device node.
+    pub const fn with_rdev(mut self, rdev: u64) -> Self {
+        self.rdev = rdev;
+        self
+    }
+
+    /// Sets the user and group IDs of the owner.
+    pub const fn with_owner(mut self, uid: u32, gid: u32) -> Self {
+        self.owner = Some((uid, gid));
+        self
+    }
+
+    /// Sets the last access and modification times.
+    pub const fn with_times(mut self, atime: Duration, mtime: Duration) -> Self {
+        self.times = Some((atime, mtime));
+        self
+    }
+
     /// Returns the inode number of the node.
     pub const fn ino(&self) -> u64 {
         self.ino
     }
+
+    /// Returns the device number of the node, 0 if it is not a device.
+    pub const fn rdev(&self) -> u64 {
+        self.rdev
+    }
+
+    /// Returns the user and group IDs of the owner, if the filesystem keeps them.
+    pub const fn owner(&self) -> Option<(u32, u32)> {
+        self.owner
+    }
+
+    /// Returns the last access and modification times, if the filesystem keeps them.
+    pub const fn times(&self) -> Option<(Duration, Duration)> {
+        self.times
+    }
     /// Returns the size of the node.
     pub const fn size(&self) -> u64 {
         self.size
@@ -323,10 +437,25 @@ impl VfsNodeAttr {
         self.ty.is_fifo()
     }
 
+    /// Whether the node is a symbolic link.
+    pub const fn is_symlink(&self) -> bool {
+        self.ty.is_symlink()
+    }
+
     ///Whether the node is a socket.
     pub const fn is_socket(&self) -> bool {
         self.ty.is_socket()
     }
+
+    /// Whether the node is a block device.
+    pub const fn is_block_device(&self) -> bool {
+        self.ty.is_block_device()
+    }
+
+    /// Whether the node is a char device.
+    pub const fn is_char_device(&self) -> bool {
+        self.ty.is_char_device()
+    }
 }
 
 impl VfsDirEntry {
@@ -0,0 +1,109 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::*;
+
+/// Records the keys it is notified with, and wakes up a task if `wakes`.
+struct Recorder {
+    keys: Mutex<Vec<usize>>,
+    wakes: bool,
+}
+
+impl Recorder {
+    fn new(wakes: bool) -> Arc<Self> {
+        Arc::new(Self {
+            keys: Mutex::new(Vec::new()),
+            wakes,
+        })
+    }
+
+    fn keys(&self) -> Vec<usize> {
+        self.keys.lock().unwrap().clone()
+    }
+}
+
+impl PollObserver for Recorder {
+    fn on_ready(&self, key: usize) -> bool {
+        self.keys.lock().unwrap().push(key);
+        self.wakes
+    }
+}
+
+fn weak(observer: &Arc<Recorder>) -> Weak<dyn PollObserver> {
+    let observer: Arc<dyn PollObserver> = observer.clone();
+    Arc::downgrade(&observer)
+}
+
+#[test]
+fn test_poll_observers_notify() {
+    let observers = PollObservers::new();
+    let a = Recorder::new(false);
+    let b = Recorder::new(true);
+    observers.add(weak(&a), 1, false);
+    observers.add(weak(&a), 2, false);
+    observers.add(weak(&b), 3, false);
+
+    observers.notify();
+    assert_eq!(a.keys(), [1, 2]);
+    assert_eq!(b.keys(), [3]);
+
+    // only the entry with the same key is removed
+    observers.remove(&weak(&a), 1);
+    observers.notify();
+    assert_eq!(a.keys(), [1, 2, 2]);
+    assert_eq!(b.keys(), [3, 3]);
+    observers.remove(&weak(&b), 1);
+    observers.notify();
+    assert_eq!(b.keys(), [3, 3, 3]);
+}
+
+#[test]
+fn test_poll_observers_exclusive() {
+    let observers = PollObservers::new();
+    let idle = Recorder::new(false);
+    let first = Recorder::new(true);
+    let second = Recorder::new(true);
+    let shared = Recorder::new(false);
+    observers.add(weak(&idle), 0, true);
+    observers.add(weak(&first), 0, true);
+    observers.add(weak(&second), 0, true);
+    observers.add(weak(&shared), 0, false);
+
+    // exclusive observers are tried in order until one wakes up a task,
+    // others are always notified
+    observers.notify();
+    assert_eq!(idle.keys(), [0]);
+    assert_eq!(first.keys(), [0]);
+    assert!(second.keys().is_empty());
+    assert_eq!(shared.keys(), [0]);
+
+    observers.remove(&weak(&first), 0);
+    observers.notify();
+    assert_eq!(idle.keys(), [0, 0]);
+    assert_eq!(second.keys(), [0]);
+    assert_eq!(shared.keys(), [0, 0]);
+}
+
+#[test]
+fn test_poll_observers_dropped() {
+    let observers = PollObservers::new();
+    let kept = Recorder::new(false);
+    let dropped = Recorder::new(true);
+    observers.add(weak(&dropped), 0, true);
+    observers.add(weak(&kept), 0, true);
+    drop(dropped);
+
+    // a dropped observer is skipped and does not count as waking up a task
+    observers.notify();
+    assert_eq!(kept.keys(), [0]);
+    observers.notify();
+    assert_eq!(kept.keys(), [0, 0]);
+}
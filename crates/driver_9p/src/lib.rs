@@ -23,4 +23,14 @@ pub trait _9pDriverOps: BaseDriverOps {
 
     /// send bytes of inputs as request and receive  get answer in outputs
     fn send_with_recv(&mut self, inputs: &[u8], outputs: &mut [u8]) -> Result<u32, u8>; // Ok(length)/Err()
+
+    /// the max size of a 9P message the transport can carry, which limits the negotiated msize.
+    fn max_msize(&self) -> u32 {
+        8192 + 1
+    }
+
+    /// the tag naming the exported filesystem, with which a mount selects the device.
+    fn mount_tag(&self) -> Option<&str> {
+        None
+    }
 }
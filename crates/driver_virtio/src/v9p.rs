@@ -7,13 +7,26 @@
  *   See the Mulan PSL v2 for more details.
  */
 
+use core::ptr::{addr_of, read_volatile};
 use driver_9p::_9pDriverOps;
 use driver_common::{BaseDriverOps, DevResult, DeviceType};
 use virtio_drivers::{device::v9p::VirtIO9p as InnerDev, transport::Transport, Hal};
 
+/// The max length of a mount tag kept by the driver.
+const MAX_TAG_LEN: usize = 64;
+
+/// The head of the device configuration, followed by `tag_len` bytes of tag.
+#[repr(C)]
+struct Config {
+    tag_len: u16,
+    tag: [u8; 0],
+}
+
 /// The VirtIO 9p device driver.
 pub struct VirtIo9pDev<H: Hal, T: Transport> {
     inner: InnerDev<H, T>,
+    tag: [u8; MAX_TAG_LEN],
+    tag_len: usize,
 }
 
 unsafe impl<H: Hal, T: Transport> Send for VirtIo9pDev<H, T> {}
@@ -23,8 +36,23 @@ impl<H: Hal, T: Transport> VirtIo9pDev<H, T> {
     /// Creates a new driver instance and initializes the device, or returns
     /// an error if any step fails.
     pub fn try_new(transport: T) -> DevResult<Self> {
+        let mut tag = [0; MAX_TAG_LEN];
+        let mut tag_len = 0;
+        if let Ok(config) = transport.config_space::<Config>() {
+            let config = config.as_ptr();
+            // SAFETY: the config space is mapped, and holds `tag_len` bytes of tag.
+            unsafe {
+                tag_len = (read_volatile(addr_of!((*config).tag_len)) as usize).min(MAX_TAG_LEN);
+                let src = addr_of!((*config).tag) as *const u8;
+                for (i, byte) in tag[..tag_len].iter_mut().enumerate() {
+                    *byte = read_volatile(src.add(i));
+                }
+            }
+        }
         Ok(Self {
             inner: InnerDev::new(transport).unwrap(),
+            tag,
+            tag_len,
         })
     }
 }
@@ -49,4 +77,14 @@ impl<H: Hal, T: Transport> _9pDriverOps for VirtIo9pDev<H, T> {
     fn send_with_recv(&mut self, inputs: &[u8], outputs: &mut [u8]) -> Result<u32, u8> {
         self.inner.request(inputs, outputs)
     }
+
+    fn max_msize(&self) -> u32 {
+        512 * 1024
+    }
+
+    fn mount_tag(&self) -> Option<&str> {
+        core::str::from_utf8(&self.tag[..self.tag_len])
+            .ok()
+            .filter(|tag| !tag.is_empty())
+    }
 }
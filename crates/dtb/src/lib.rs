@@ -54,6 +54,14 @@ impl<'a> DeviceProp<'a> {
         self.0.u64(index).unwrap()
     }
 
+    /// Assume the prop is a single u32 or u64, by its length. Get it.
+    pub fn uint(&self) -> u64 {
+        match self.0.length() {
+            8 => self.u64(0),
+            _ => self.u32(0) as u64,
+        }
+    }
+
     /// Assume the prop is a str. Get the whole str.
     pub fn str(&self) -> &'static str {
         self.0.str().unwrap()
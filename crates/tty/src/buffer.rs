@@ -61,14 +61,17 @@ impl TtyBuffer {
     }
 
     /// push a char to tail.
-    pub fn push(&self, ch: u8) {
+    /// return false if the buffer is full, where the char is dropped.
+    pub fn push(&self, ch: u8) -> bool {
         let mut buf = self.buffer.lock();
-        if buf.len != TTY_BUF_SIZE {
-            buf.len += 1;
-            let idx = buf.tail;
-            buf.buf[idx] = ch;
-            buf.tail = (buf.tail + 1) % TTY_BUF_SIZE;
+        if buf.len == TTY_BUF_SIZE {
+            return false;
         }
+        buf.len += 1;
+        let idx = buf.tail;
+        buf.buf[idx] = ch;
+        buf.tail = (buf.tail + 1) % TTY_BUF_SIZE;
+        true
     }
 
     /// delete and return the heading char.
@@ -115,17 +118,30 @@ impl TtyBuffer {
 
             // len -= 1
             buf.len -= 1;
-            buf.tail -= 1;
+            buf.tail = (buf.tail + TTY_BUF_SIZE - 1) % TTY_BUF_SIZE;
             ret
         } else {
             0
         }
     }
 
+    /// delete all chars.
+    pub fn clear(&self) {
+        let mut buf = self.buffer.lock();
+        buf.head = 0;
+        buf.tail = 0;
+        buf.len = 0;
+    }
+
     /// get current length of buffer.
     pub fn len(&self) -> usize {
         self.buffer.lock().len
     }
+
+    /// get the number of chars that can be pushed before it is full.
+    pub fn room(&self) -> usize {
+        TTY_BUF_SIZE - self.len()
+    }
 }
 
 /// a buffer for echo of line discipline.
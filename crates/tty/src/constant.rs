@@ -27,3 +27,29 @@ pub const ARROW_PREFIX: [u8; 2] = [ESC, LEFT_BRACKET];
 // const DOWN: u8 = 66;
 pub const RIGHT: u8 = 67;
 pub const LEFT: u8 = 68;
+
+/// signals raised by control chars.
+pub const SIGINT: u32 = 2;
+pub const SIGQUIT: u32 = 3;
+pub const SIGTSTP: u32 = 20;
+/// signal raised when the window size changes.
+pub const SIGWINCH: u32 = 28;
+
+/// tty requests of ioctl.
+pub const TCGETS: usize = 0x5401;
+pub const TCSETS: usize = 0x5402;
+pub const TCSETSW: usize = 0x5403;
+pub const TCSETSF: usize = 0x5404;
+pub const TCSBRK: usize = 0x5409;
+pub const TCFLSH: usize = 0x540B;
+pub const TIOCSCTTY: usize = 0x540E;
+pub const TIOCGPGRP: usize = 0x540F;
+pub const TIOCSPGRP: usize = 0x5410;
+pub const TIOCGWINSZ: usize = 0x5413;
+pub const TIOCSWINSZ: usize = 0x5414;
+pub const FIONREAD: usize = 0x541B;
+pub const TIOCNOTTY: usize = 0x5422;
+
+/// queues flushed by `TCFLSH`.
+pub const TCIFLUSH: usize = 0;
+pub const TCIOFLUSH: usize = 2;
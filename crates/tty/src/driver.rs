@@ -15,15 +15,15 @@
 
 use crate::tty::TtyStruct;
 use alloc::collections::BTreeMap;
+use alloc::format;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::{vec, vec::Vec};
-use lazy_init::LazyInit;
 use spinlock::SpinNoIrq;
 
 /// all tty drivers.
 /// only be written when registering a driver.
-pub(super) static ALL_DRIVERS: LazyInit<SpinNoIrq<Vec<Arc<TtyDriver>>>> = LazyInit::new();
+pub(super) static ALL_DRIVERS: SpinNoIrq<Vec<Arc<TtyDriver>>> = SpinNoIrq::new(Vec::new());
 
 /// the operations a tty driver must implement.
 /// passed by driver when registering itself.
@@ -31,6 +31,15 @@ pub(super) static ALL_DRIVERS: LazyInit<SpinNoIrq<Vec<Arc<TtyDriver>>>> = LazyIn
 pub struct TtyDriverOps {
     /// push a char to device.
     pub putchar: fn(u8),
+
+    /// push chars to the device of the given index.
+    /// used instead of `putchar` by drivers whose devices differ in where
+    /// their output goes, such as ptys.
+    pub write: Option<fn(usize, &[u8])>,
+
+    /// get the number of chars the device of the given index can take
+    /// without dropping any. devices without it take all.
+    pub write_room: Option<fn(usize) -> usize>,
 }
 
 /// tty driver.
@@ -62,26 +71,29 @@ impl TtyDriver {
 
     /// add a device, return its index, -1 means failure.
     fn add_one_device(&self, tty: Arc<TtyStruct>) -> isize {
-        let mut index = 0;
-        if let Some(k) = self.ttys.lock().last_key_value() {
-            index = *k.0;
-        }
+        let mut ttys = self.ttys.lock();
+
+        // the first free index
+        let index = (0..).find(|i| !ttys.contains_key(i)).unwrap();
 
         // set index of device
         tty.set_index(index);
 
         // set name of device
-        let mut name = self.name.clone();
-        name.push(core::char::from_digit(index as _, 16).unwrap());
-        tty.set_name(&name);
+        tty.set_name(&format!("{}{}", self.name, index));
 
         // save this device
-        self.ttys.lock().insert(index, tty.clone());
+        ttys.insert(index, tty.clone());
 
         // return device's index
         index as _
     }
 
+    /// remove a device, return it if found.
+    fn remove_one_device(&self, index: usize) -> Option<Arc<TtyStruct>> {
+        self.ttys.lock().remove(&index)
+    }
+
     pub fn name(&self) -> String {
         self.name.clone()
     }
@@ -115,10 +127,6 @@ impl TtyDriver {
     }
 }
 
-pub fn init() {
-    ALL_DRIVERS.init_by(SpinNoIrq::new(vec![]));
-}
-
 /// get driver by index.
 pub fn get_driver_by_index(index: usize) -> Option<Arc<TtyDriver>> {
     let lock = ALL_DRIVERS.lock();
@@ -165,3 +173,12 @@ pub fn register_device(driver_index: usize) -> isize {
     }
     index
 }
+
+/// called by driver to unregister a device when it is gone.
+pub fn unregister_device(driver_index: usize, device_index: usize) {
+    if let Some(driver) = get_driver_by_index(driver_index) {
+        if let Some(tty) = driver.remove_one_device(device_index) {
+            crate::tty::remove_one_device(&tty);
+        }
+    }
+}
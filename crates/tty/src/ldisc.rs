@@ -11,11 +11,13 @@
 //! the currently implemented line discipline is N_TTY.
 //! line disciplines are registered when a device is registered.
 
-use alloc::sync::Arc;
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
+use core::time::Duration;
 use spinlock::SpinNoIrq;
 
 use crate::{
     buffer::{EchoBuffer, TtyBuffer},
+    termios::*,
     tty::TtyStruct,
 };
 
@@ -25,11 +27,21 @@ pub struct TtyLdisc {
     /// chars that can be read by kernel.
     read_buf: TtyBuffer,
 
+    /// lengths of the lines in `read_buf` in canonical mode.
+    /// a line ended by `VEOF` has no terminator, so it may be empty.
+    lines: SpinNoIrq<VecDeque<usize>>,
+
     /// chars being echoed on the screen.
     echo_buf: SpinNoIrq<EchoBuffer>,
 
     /// chars from driver, and not yet been processed.
     rec_buf: TtyBuffer,
+
+    /// when the last char was received, for `VTIME`.
+    last_received: SpinNoIrq<Duration>,
+
+    /// when a read with `VMIN` of 0 started waiting, for `VTIME`.
+    read_started: SpinNoIrq<Option<Duration>>,
 }
 
 /// implement N_TTY.
@@ -37,29 +49,130 @@ impl TtyLdisc {
     pub fn new() -> Self {
         Self {
             read_buf: TtyBuffer::new(),
+            lines: SpinNoIrq::new(VecDeque::new()),
             echo_buf: SpinNoIrq::new(EchoBuffer::new()),
             rec_buf: TtyBuffer::new(),
+            last_received: SpinNoIrq::new(Duration::ZERO),
+            read_started: SpinNoIrq::new(None),
         }
     }
 
-    /// kernel reads data.
-    pub fn read(&self, buf: &mut [u8]) -> usize {
-        let read_buf = &self.read_buf;
+    /// number of chars kernel can read.
+    pub fn available(&self, tty: &TtyStruct) -> usize {
+        if tty.termios().lflag(ICANON) {
+            self.lines.lock().iter().sum()
+        } else {
+            self.read_buf.len()
+        }
+    }
 
-        // len of this reading
-        let len = buf.len().min(read_buf.len());
+    /// whether there are data for kernel to read.
+    pub fn readable(&self, tty: &TtyStruct) -> bool {
+        let termios = tty.termios();
+        if termios.lflag(ICANON) {
+            return !self.lines.lock().is_empty();
+        }
+        // without a timer, a read waits for `VMIN` chars
+        let min = match termios.c_cc[VTIME] {
+            0 => termios.c_cc[VMIN].max(1),
+            _ => 1,
+        };
+        self.read_buf.len() >= min as usize
+    }
+
+    /// whether a read may end after a `VTIME` timer, without more input.
+    pub fn reads_with_timer(&self, tty: &TtyStruct) -> bool {
+        let termios = tty.termios();
+        !termios.lflag(ICANON) && termios.c_cc[VTIME] != 0
+    }
 
-        // return if nothing can be read
-        if len == 0 {
-            return 0;
+    /// kernel reads data, return `None` if the read should wait for more.
+    ///
+    /// in canonical mode, a read returns at most one line.
+    /// otherwise it follows `VMIN` and `VTIME`.
+    pub fn read(&self, tty: &TtyStruct, buf: &mut [u8]) -> Option<usize> {
+        if buf.is_empty() {
+            return Some(0);
+        }
+        let termios = tty.termios();
+        if termios.lflag(ICANON) {
+            let mut lines = self.lines.lock();
+            let line = lines.front_mut()?;
+            let len = buf.len().min(*line);
+            self.pop_to(&mut buf[..len]);
+            *line -= len;
+            if *line == 0 {
+                lines.pop_front();
+            }
+            return Some(len);
         }
 
-        // copy data from read_buf to `buf`
-        for ch in buf.iter_mut().take(len) {
-            *ch = read_buf.pop();
+        let avail = self.read_buf.len();
+        let min = termios.c_cc[VMIN] as usize;
+        let time = Duration::from_millis(termios.c_cc[VTIME] as u64 * 100);
+        let ready = if min == 0 {
+            // wait for any char, at most `VTIME` since the read started
+            avail > 0 || time.is_zero() || {
+                let now = crate::current_time();
+                let start = *self.read_started.lock().get_or_insert(now);
+                now.saturating_sub(start) >= time
+            }
+        } else {
+            // wait for `VMIN` chars, or `VTIME` after the last char
+            avail >= min.min(buf.len())
+                || (avail > 0
+                    && !time.is_zero()
+                    && crate::current_time().saturating_sub(*self.last_received.lock()) >= time)
+        };
+        if !ready {
+            return None;
         }
+        *self.read_started.lock() = None;
+        let len = buf.len().min(avail);
+        self.pop_to(&mut buf[..len]);
+        Some(len)
+    }
+
+    /// take chars from read buffer to `buf`.
+    fn pop_to(&self, buf: &mut [u8]) {
+        for ch in buf.iter_mut() {
+            *ch = self.read_buf.pop();
+        }
+    }
 
-        len
+    /// discard all input not read yet.
+    pub fn flush_input(&self) {
+        let mut echo_buf = self.echo_buf.lock();
+        echo_buf.buffer.clear();
+        echo_buf.col = 0;
+        self.lines.lock().clear();
+        self.read_buf.clear();
+        self.rec_buf.clear();
+    }
+
+    /// called when the terminal settings change.
+    pub fn termios_changed(&self, old: &Termios, new: &Termios) {
+        if old.lflag(ICANON) == new.lflag(ICANON) {
+            return;
+        }
+        let mut echo_buf = self.echo_buf.lock();
+        if new.lflag(ICANON) {
+            // what was received becomes the start of a line
+            let len = self.read_buf.len();
+            for _ in 0..len {
+                let ch = self.read_buf.pop();
+                echo_buf.buffer.push(ch);
+            }
+            echo_buf.col = echo_buf.buffer.len();
+        } else {
+            // the line being edited and all lines can be read at once
+            self.lines.lock().clear();
+            let len = echo_buf.buffer.len();
+            for _ in 0..len {
+                self.read_buf.push(echo_buf.buffer.pop());
+            }
+            echo_buf.col = 0;
+        }
     }
 
     /// driver sends data from device for processing and echoing.
@@ -67,6 +180,9 @@ impl TtyLdisc {
     pub fn receive_buf(&self, tty: Arc<TtyStruct>, buf: &[u8]) {
         use crate::constant::*;
 
+        let termios = tty.termios();
+        *self.last_received.lock() = crate::current_time();
+
         let rec_buf = &self.rec_buf;
 
         // save data to receive buffer
@@ -78,8 +194,8 @@ impl TtyLdisc {
         while rec_buf.len() > 0 {
             let ch = rec_buf.see(0);
 
-            // if char may be arrow char
-            if ch == ARROW_PREFIX[0] {
+            // if char may be arrow char, which moves the cursor in a line
+            if termios.lflag(ICANON) && ch == ARROW_PREFIX[0] {
                 // no enough len, just break, waitting for next time
                 if rec_buf.len() < 3 {
                     break;
@@ -96,6 +212,7 @@ impl TtyLdisc {
                 rec_buf.pop();
                 rec_buf.pop();
                 let ch = rec_buf.pop();
+                let echo = termios.lflag(ECHO);
 
                 // deal with arrow char
                 match ch {
@@ -103,7 +220,9 @@ impl TtyLdisc {
                         let mut lock = self.echo_buf.lock();
                         // if can go left
                         if lock.col > 0 {
-                            self.write(tty.clone(), &[ARROW_PREFIX[0], ARROW_PREFIX[1], ch]);
+                            if echo {
+                                self.write(tty.clone(), &[ARROW_PREFIX[0], ARROW_PREFIX[1], ch]);
+                            }
                             lock.col -= 1;
                         }
                     }
@@ -111,7 +230,9 @@ impl TtyLdisc {
                         let mut lock = self.echo_buf.lock();
                         // if can go right
                         if lock.col < lock.buffer.len() {
-                            self.write(tty.clone(), &[ARROW_PREFIX[0], ARROW_PREFIX[1], ch]);
+                            if echo {
+                                self.write(tty.clone(), &[ARROW_PREFIX[0], ARROW_PREFIX[1], ch]);
+                            }
                             lock.col += 1;
                         }
                     }
@@ -122,95 +243,210 @@ impl TtyLdisc {
             // not a arrow char, handle it as a normal char
             } else {
                 let ch = rec_buf.pop();
-                match ch {
-                    CR | LF => {
-                        // always '\n'
-                        let ch = LF;
+                self.receive_char(&tty, &termios, ch);
+            }
+        }
+        crate::wake_readers(&tty.name());
+    }
 
-                        // echo
-                        self.write(tty.clone(), &[ch]);
+    /// process a char received.
+    fn receive_char(&self, tty: &Arc<TtyStruct>, termios: &Termios, ch: u8) {
+        use crate::constant::*;
 
-                        // push this char to echo buffer
-                        let mut lock = self.echo_buf.lock();
-                        lock.buffer.push(ch);
+        // input translation
+        let ch = match ch {
+            CR if termios.iflag(IGNCR) => return,
+            CR if termios.iflag(ICRNL) => LF,
+            LF if termios.iflag(INLCR) => CR,
+            ch => ch,
+        };
+        let echo = termios.lflag(ECHO);
 
-                        // copy echo buffer to read buffer
-                        // FIXME: currently will push all data to read_buf
-                        let len = lock.buffer.len();
-                        for _ in 0..len {
-                            self.read_buf.push(lock.buffer.pop());
-                        }
+        // control chars raising signals
+        if termios.lflag(ISIG) {
+            let sig = if termios.is_cc(VINTR, ch) {
+                Some(SIGINT)
+            } else if termios.is_cc(VQUIT, ch) {
+                Some(SIGQUIT)
+            } else if termios.is_cc(VSUSP, ch) {
+                Some(SIGTSTP)
+            } else {
+                None
+            };
+            if let Some(sig) = sig {
+                if !termios.lflag(NOFLSH) {
+                    self.flush_input();
+                }
+                if echo {
+                    self.echo_char(tty, termios, ch);
+                }
+                crate::send_signal(&tty.name(), tty.pgrp(), sig);
+                return;
+            }
+        }
 
-                        // echo buffer's column is set to 0
-                        lock.col = 0;
-                    }
-                    BS | DEL => {
-                        let mut lock = self.echo_buf.lock();
-                        let col = lock.col;
-                        let len = lock.buffer.len();
-                        // if can delete
-                        if col > 0 {
-                            // perform a backspace
-                            self.write(tty.clone(), &[BS, SPACE, BS]);
-
-                            // if cursor is not on the rightmost
-                            if col != len {
-                                for i in col..len {
-                                    let ch = lock.buffer.see(i);
-                                    self.write(tty.clone(), &[ch]);
-                                }
-                                self.write(tty.clone(), &[SPACE]);
-                                for _ in 0..(len - col + 1) {
-                                    self.write(
-                                        tty.clone(),
-                                        &[ARROW_PREFIX[0], ARROW_PREFIX[1], LEFT],
-                                    );
-                                }
-                            }
+        // non-canonical mode, chars can be read at once
+        if !termios.lflag(ICANON) {
+            self.read_buf.push(ch);
+            if echo {
+                self.echo_char(tty, termios, ch);
+            }
+            return;
+        }
 
-                            // modify echo buffer
-                            lock.buffer.delete(col - 1);
-                            lock.col -= 1;
+        if termios.is_cc(VERASE, ch) || ch == BS {
+            let mut lock = self.echo_buf.lock();
+            let col = lock.col;
+            let len = lock.buffer.len();
+            // if can delete
+            if col > 0 {
+                if echo {
+                    // perform a backspace
+                    self.write(tty.clone(), &[BS, SPACE, BS]);
+
+                    // if cursor is not on the rightmost
+                    if col != len {
+                        for i in col..len {
+                            let ch = lock.buffer.see(i);
+                            self.write(tty.clone(), &[ch]);
                         }
-                    }
-                    _ => {
-                        // process normal chars.
-                        let mut echo_buf = self.echo_buf.lock();
-                        let col = echo_buf.col;
-                        let len = echo_buf.buffer.len();
-
-                        // echo
-                        self.write(tty.clone(), &[ch]);
-
-                        // if cursor is not on the rightmost
-                        if col != len {
-                            for i in col..len {
-                                self.write(tty.clone(), &[echo_buf.buffer.see(i)]);
-                            }
-                            for _ in 0..(len - col) {
-                                self.write(tty.clone(), &[ARROW_PREFIX[0], ARROW_PREFIX[1], LEFT]);
-                            }
+                        self.write(tty.clone(), &[SPACE]);
+                        for _ in 0..(len - col + 1) {
+                            self.write(tty.clone(), &[ARROW_PREFIX[0], ARROW_PREFIX[1], LEFT]);
                         }
+                    }
+                }
+
+                // modify echo buffer
+                lock.buffer.delete(col - 1);
+                lock.col -= 1;
+            }
+        } else if termios.is_cc(VKILL, ch) {
+            let mut lock = self.echo_buf.lock();
+            let len = lock.buffer.len();
+            if echo {
+                // go to the rightmost, then erase the whole line
+                for i in lock.col..len {
+                    self.write(tty.clone(), &[lock.buffer.see(i)]);
+                }
+                for _ in 0..len {
+                    self.write(tty.clone(), &[BS, SPACE, BS]);
+                }
+            }
+            lock.buffer.clear();
+            lock.col = 0;
+        } else if termios.is_cc(VEOF, ch) {
+            // the line is ended without a terminator
+            self.commit_line(&mut self.echo_buf.lock());
+        } else if ch == LF || termios.is_cc(VEOL, ch) {
+            // echo
+            if echo || termios.lflag(ECHONL) {
+                self.write(tty.clone(), &[ch]);
+            }
 
-                        // modify echo buffer
-                        echo_buf.buffer.insert(ch, col);
-                        echo_buf.col += 1;
+            // push this char to echo buffer
+            let mut lock = self.echo_buf.lock();
+            lock.buffer.push(ch);
+
+            // copy echo buffer to read buffer
+            self.commit_line(&mut lock);
+        } else {
+            // process normal chars.
+            let mut echo_buf = self.echo_buf.lock();
+            let col = echo_buf.col;
+            let len = echo_buf.buffer.len();
+
+            if echo {
+                self.echo_char(tty, termios, ch);
+
+                // if cursor is not on the rightmost
+                if col != len {
+                    for i in col..len {
+                        self.write(tty.clone(), &[echo_buf.buffer.see(i)]);
+                    }
+                    for _ in 0..(len - col) {
+                        self.write(tty.clone(), &[ARROW_PREFIX[0], ARROW_PREFIX[1], LEFT]);
                     }
                 }
             }
+
+            // modify echo buffer
+            echo_buf.buffer.insert(ch, col);
+            echo_buf.col += 1;
+        }
+    }
+
+    /// move the line in echo buffer to read buffer.
+    fn commit_line(&self, echo_buf: &mut EchoBuffer) {
+        let len = echo_buf.buffer.len();
+        for _ in 0..len {
+            self.read_buf.push(echo_buf.buffer.pop());
+        }
+        self.lines.lock().push_back(len);
+
+        // echo buffer's column is set to 0
+        echo_buf.col = 0;
+    }
+
+    /// echo a char, control chars are shown as `^X` if `ECHOCTL` is set.
+    fn echo_char(&self, tty: &Arc<TtyStruct>, termios: &Termios, ch: u8) {
+        use crate::constant::*;
+
+        let is_ctl = (ch < SPACE && ch != b'\t' && ch != LF) || ch == DEL;
+        if is_ctl && termios.lflag(ECHOCTL) {
+            self.write(tty.clone(), &[b'^', ch ^ 0x40]);
+        } else {
+            self.write(tty.clone(), &[ch]);
         }
     }
 
     /// kernel writes data to device.
+    /// return the number of chars written, which is short if the device has
+    /// no room for the rest.
     pub fn write(&self, tty: Arc<TtyStruct>, buf: &[u8]) -> usize {
-        let mut len = 0;
+        use crate::constant::*;
+
+        let termios = tty.termios();
+        let driver = tty.driver();
+        let room = driver
+            .ops
+            .write_room
+            .map_or(usize::MAX, |write_room| write_room(tty.index()));
+        // output translation of '\n' to "\r\n"
+        if termios.oflag(OPOST) && termios.oflag(ONLCR) && buf.contains(&LF) {
+            let mut out = Vec::with_capacity((buf.len() * 2).min(room));
+            let mut len = 0;
+            for &ch in buf {
+                let need = if ch == LF { 2 } else { 1 };
+                if out.len() + need > room {
+                    break;
+                }
+                if ch == LF {
+                    out.push(CR);
+                }
+                out.push(ch);
+                len += 1;
+            }
+            self.output(&tty, &out);
+            len
+        } else {
+            let len = buf.len().min(room);
+            self.output(&tty, &buf[..len]);
+            len
+        }
+    }
+
+    /// push chars to device through driver.
+    fn output(&self, tty: &TtyStruct, buf: &[u8]) {
         let driver = tty.driver();
+        if let Some(write) = driver.ops.write {
+            write(tty.index(), buf);
+            return;
+        }
         for ch in buf {
-            len += 1;
             // call driver's method
             (driver.ops.putchar)(*ch);
         }
-        len
     }
 }
 
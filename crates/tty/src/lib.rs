@@ -22,7 +22,7 @@
 //!
 //! kernel writes data to a device using its name.
 
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
@@ -30,12 +30,75 @@ mod buffer;
 mod constant;
 mod driver;
 mod ldisc;
+pub mod pty;
+pub mod termios;
 mod tty;
 
+#[cfg(test)]
+mod tests;
+
+use core::time::Duration;
+
 use driver::get_driver_by_index;
+use spinlock::SpinNoIrq;
+
+pub use driver::{register_device, register_driver, unregister_device, TtyDriverOps};
+pub use termios::Termios;
+pub use tty::{get_all_device_names, get_device_by_name, TtyStruct, WinSize};
+
+/// services of the kernel used by line disciplines.
+#[derive(Clone, Copy)]
+pub struct TtyKernelOps {
+    /// get the current monotonic time, for `VTIME`.
+    pub current_time: fn() -> Duration,
+
+    /// send a signal to the process group `pgrp` of the processes the device
+    /// called `dev_name` is the controlling tty of, for `ISIG`.
+    pub send_signal: fn(dev_name: &str, pgrp: i32, sig: u32),
 
-pub use driver::{register_device, register_driver, TtyDriverOps};
-pub use tty::{get_all_device_names, get_device_by_name};
+    /// make the device called `dev_name` the controlling tty of the current
+    /// process if `attach` is set, or else detach the process from it if it
+    /// is its controlling tty, for `TIOCSCTTY` and `TIOCNOTTY`.
+    pub control_tty: fn(dev_name: &str, attach: bool),
+
+    /// wake up the readers of a device after it received input or hung up,
+    /// instead of having them poll, and for the slave of a pty its writers
+    /// after the master read. may run in irq.
+    pub wake_readers: fn(dev_name: &str),
+}
+
+static KERNEL_OPS: SpinNoIrq<Option<TtyKernelOps>> = SpinNoIrq::new(None);
+
+/// init, called by kernel to provide its services.
+pub fn init(ops: TtyKernelOps) {
+    *KERNEL_OPS.lock() = Some(ops);
+}
+
+pub(crate) fn current_time() -> Duration {
+    let ops = *KERNEL_OPS.lock();
+    ops.map_or(Duration::ZERO, |ops| (ops.current_time)())
+}
+
+pub(crate) fn send_signal(dev_name: &str, pgrp: i32, sig: u32) {
+    let ops = *KERNEL_OPS.lock();
+    if let Some(ops) = ops {
+        (ops.send_signal)(dev_name, pgrp, sig);
+    }
+}
+
+pub(crate) fn control_tty(dev_name: &str, attach: bool) {
+    let ops = *KERNEL_OPS.lock();
+    if let Some(ops) = ops {
+        (ops.control_tty)(dev_name, attach);
+    }
+}
+
+pub(crate) fn wake_readers(dev_name: &str) {
+    let ops = *KERNEL_OPS.lock();
+    if let Some(ops) = ops {
+        (ops.wake_readers)(dev_name);
+    }
+}
 
 /// called by driver when irq, to send data from hardware.
 pub fn tty_receive_buf(driver_index: usize, device_index: usize, buf: &[u8]) {
@@ -48,15 +111,34 @@ pub fn tty_receive_buf(driver_index: usize, device_index: usize, buf: &[u8]) {
 }
 
 /// called by kernel to read a tty device.
-pub fn tty_read(buf: &mut [u8], dev_name: &str) -> usize {
-    if let Some(tty) = get_device_by_name(dev_name) {
-        tty.ldisc().read(buf)
-    } else {
-        0
-    }
+/// return `None` if the read should wait for more input, and `Some(0)` at
+/// the end of file.
+pub fn tty_read(buf: &mut [u8], dev_name: &str) -> Option<usize> {
+    let tty = get_device_by_name(dev_name)?;
+    tty.ldisc().read(&tty, buf)
+}
+
+/// called by kernel to know whether a tty device has data to read.
+pub fn tty_readable(dev_name: &str) -> bool {
+    get_device_by_name(dev_name).is_some_and(|tty| tty.ldisc().readable(&tty))
+}
+
+/// called by kernel to know whether a blocked read of a tty device is woken
+/// up by [`TtyKernelOps::wake_readers`]. it is not with a `VTIME` timer in
+/// non-canonical mode, where the read may end without input.
+pub fn tty_wakes_readers(dev_name: &str) -> bool {
+    get_device_by_name(dev_name).is_some_and(|tty| !tty.ldisc().reads_with_timer(&tty))
+}
+
+/// called by kernel to perform a request of ioctl on a tty device.
+/// return `None` if it is not a tty request.
+pub fn tty_ioctl(dev_name: &str, cmd: usize, arg: usize) -> Option<usize> {
+    get_device_by_name(dev_name)?.ioctl(cmd, arg)
 }
 
 /// called by kernel to write a tty device.
+/// return the number of bytes written, which is short if the device has no
+/// room for the rest.
 pub fn tty_write(buf: &[u8], dev_name: &str) -> usize {
     if let Some(tty) = get_device_by_name(dev_name) {
         tty.ldisc().write(tty.clone(), buf)
@@ -64,9 +146,3 @@ pub fn tty_write(buf: &[u8], dev_name: &str) -> usize {
         0
     }
 }
-
-/// init
-pub fn init() {
-    driver::init();
-    tty::init();
-}
@@ -0,0 +1,185 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! pseudo-terminal driver.
+//!
+//! a pty is a pair of a master and a slave. the slave is a tty device named
+//! `pts<index>`, registered to the driver `pts` like the devices of a real
+//! driver. what the master writes is received by the slave as if typed, and
+//! what the slave outputs goes to the master for reading.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec::Vec};
+use spinlock::SpinNoIrq;
+
+use crate::{
+    buffer::TtyBuffer,
+    driver::{get_driver_by_index, register_device, register_driver, unregister_device},
+    tty::TtyStruct,
+    TtyDriverOps,
+};
+
+/// index of the pty driver, registered when the first pty is opened.
+static PTY_DRIVER: SpinNoIrq<Option<usize>> = SpinNoIrq::new(None);
+
+/// all opened ptys.
+static ALL_PTYS: SpinNoIrq<BTreeMap<usize, Arc<Pty>>> = SpinNoIrq::new(BTreeMap::new());
+
+/// a pty pair.
+#[derive(Debug)]
+struct Pty {
+    /// the slave device.
+    tty: Arc<TtyStruct>,
+
+    /// chars output by the slave, to be read from the master.
+    output: TtyBuffer,
+
+    /// whether the slave can't be opened yet.
+    locked: AtomicBool,
+}
+
+/// get the index of the pty driver, registering it first if needed.
+fn driver_index() -> usize {
+    let mut driver = PTY_DRIVER.lock();
+    *driver.get_or_insert_with(|| {
+        let ops = TtyDriverOps {
+            putchar: |_| {},
+            write: Some(pty_write),
+            write_room: Some(pty_write_room),
+        };
+        register_driver(ops, "pts")
+    })
+}
+
+/// called by the slave's line discipline to output chars.
+fn pty_write(index: usize, buf: &[u8]) {
+    if let Some(pty) = get_pty(index) {
+        for ch in buf {
+            if !pty.output.push(*ch) {
+                break;
+            }
+        }
+        // readers of the master see the output
+        crate::wake_readers(&master_name(index));
+    }
+}
+
+/// called by the slave's line discipline before output, so that what the
+/// master hasn't read is never dropped.
+fn pty_write_room(index: usize) -> usize {
+    get_pty(index).map_or(0, |pty| pty.output.room())
+}
+
+fn get_pty(index: usize) -> Option<Arc<Pty>> {
+    ALL_PTYS.lock().get(&index).cloned()
+}
+
+/// open a new pty, return its index, which is `None` if it can't be created.
+///
+/// the slave is locked until unlocked by [`set_locked`].
+pub fn open() -> Option<usize> {
+    let driver_index = driver_index();
+    let index = register_device(driver_index);
+    if index < 0 {
+        return None;
+    }
+    let index = index as usize;
+    let tty = get_driver_by_index(driver_index)?.get_device_by_index(index)?;
+    let pty = Pty {
+        tty,
+        output: TtyBuffer::new(),
+        locked: AtomicBool::new(true),
+    };
+    ALL_PTYS.lock().insert(index, Arc::new(pty));
+    Some(index)
+}
+
+/// close the master of a pty, and the slave is hung up.
+pub fn close(index: usize) {
+    if ALL_PTYS.lock().remove(&index).is_some() {
+        unregister_device(driver_index(), index);
+        // readers of the slave see the hang up
+        crate::wake_readers(&slave_name(index));
+    }
+}
+
+/// whether the pty of `index` is open.
+pub fn exists(index: usize) -> bool {
+    ALL_PTYS.lock().contains_key(&index)
+}
+
+/// indices of all open ptys.
+pub fn all_indices() -> Vec<usize> {
+    ALL_PTYS.lock().keys().copied().collect()
+}
+
+/// name of the slave device of a pty.
+pub fn slave_name(index: usize) -> String {
+    format!("pts{}", index)
+}
+
+/// name of the master of a pty, whose readers are woken up by
+/// [`TtyKernelOps::wake_readers`](crate::TtyKernelOps::wake_readers) when
+/// the slave outputs.
+pub fn master_name(index: usize) -> String {
+    format!("ptm{}", index)
+}
+
+/// get the slave device of a pty.
+pub fn slave(index: usize) -> Option<Arc<TtyStruct>> {
+    get_pty(index).map(|pty| pty.tty.clone())
+}
+
+/// lock or unlock the slave of a pty.
+pub fn set_locked(index: usize, locked: bool) {
+    if let Some(pty) = get_pty(index) {
+        pty.locked.store(locked, Ordering::Release);
+    }
+}
+
+/// whether the slave of a pty is locked.
+pub fn is_locked(index: usize) -> bool {
+    get_pty(index).is_some_and(|pty| pty.locked.load(Ordering::Acquire))
+}
+
+/// master reads the output of the slave.
+pub fn master_read(index: usize, buf: &mut [u8]) -> usize {
+    let Some(pty) = get_pty(index) else {
+        return 0;
+    };
+    let len = buf.len().min(pty.output.len());
+    for ch in buf.iter_mut().take(len) {
+        *ch = pty.output.pop();
+    }
+    if len > 0 {
+        // writers of the slave have room again
+        crate::wake_readers(&slave_name(index));
+    }
+    len
+}
+
+/// master writes chars as the input of the slave.
+pub fn master_write(index: usize, buf: &[u8]) -> usize {
+    let Some(pty) = get_pty(index) else {
+        return 0;
+    };
+    pty.tty.ldisc().receive_buf(pty.tty.clone(), buf);
+    buf.len()
+}
+
+/// whether the master has output of the slave to read.
+pub fn master_readable(index: usize) -> bool {
+    get_pty(index).is_some_and(|pty| pty.output.len() > 0)
+}
+
+/// whether the slave can output without waiting for the master to read.
+pub fn slave_writable(index: usize) -> bool {
+    get_pty(index).map_or(true, |pty| pty.output.room() > 0)
+}
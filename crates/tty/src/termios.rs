@@ -0,0 +1,132 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! terminal settings of a tty, the same as linux's `struct termios`.
+
+/// number of control chars.
+pub const NCCS: usize = 19;
+
+// indices of control chars.
+pub const VINTR: usize = 0;
+pub const VQUIT: usize = 1;
+pub const VERASE: usize = 2;
+pub const VKILL: usize = 3;
+pub const VEOF: usize = 4;
+pub const VTIME: usize = 5;
+pub const VMIN: usize = 6;
+pub const VSTART: usize = 8;
+pub const VSTOP: usize = 9;
+pub const VSUSP: usize = 10;
+pub const VEOL: usize = 11;
+pub const VREPRINT: usize = 12;
+pub const VDISCARD: usize = 13;
+pub const VWERASE: usize = 14;
+pub const VLNEXT: usize = 15;
+
+// input flags.
+pub const INLCR: u32 = 0o100;
+pub const IGNCR: u32 = 0o200;
+pub const ICRNL: u32 = 0o400;
+pub const IXON: u32 = 0o2000;
+
+// output flags.
+pub const OPOST: u32 = 0o1;
+pub const ONLCR: u32 = 0o4;
+
+// control flags.
+pub const B38400: u32 = 0o17;
+pub const CS8: u32 = 0o60;
+pub const CREAD: u32 = 0o200;
+pub const HUPCL: u32 = 0o2000;
+
+// local flags.
+pub const ISIG: u32 = 0o1;
+pub const ICANON: u32 = 0o2;
+pub const ECHO: u32 = 0o10;
+pub const ECHOE: u32 = 0o20;
+pub const ECHOK: u32 = 0o40;
+pub const ECHONL: u32 = 0o100;
+pub const NOFLSH: u32 = 0o200;
+pub const ECHOCTL: u32 = 0o1000;
+pub const ECHOKE: u32 = 0o4000;
+pub const IEXTEN: u32 = 0o100000;
+
+/// terminal settings.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Termios {
+    /// input flags.
+    pub c_iflag: u32,
+
+    /// output flags.
+    pub c_oflag: u32,
+
+    /// control flags.
+    pub c_cflag: u32,
+
+    /// local flags.
+    pub c_lflag: u32,
+
+    /// line discipline.
+    pub c_line: u8,
+
+    /// control chars.
+    pub c_cc: [u8; NCCS],
+}
+
+impl Termios {
+    /// whether an input flag is set.
+    pub fn iflag(&self, flag: u32) -> bool {
+        self.c_iflag & flag != 0
+    }
+
+    /// whether an output flag is set.
+    pub fn oflag(&self, flag: u32) -> bool {
+        self.c_oflag & flag != 0
+    }
+
+    /// whether a local flag is set.
+    pub fn lflag(&self, flag: u32) -> bool {
+        self.c_lflag & flag != 0
+    }
+
+    /// whether `ch` is the control char of `index`, which is disabled if 0.
+    pub fn is_cc(&self, index: usize, ch: u8) -> bool {
+        self.c_cc[index] != 0 && self.c_cc[index] == ch
+    }
+}
+
+/// the settings of a newly opened tty, the same as linux's.
+impl Default for Termios {
+    fn default() -> Self {
+        let mut c_cc = [0; NCCS];
+        c_cc[VINTR] = 0x03; // ^C
+        c_cc[VQUIT] = 0x1c; // ^\
+        c_cc[VERASE] = 0x7f; // DEL
+        c_cc[VKILL] = 0x15; // ^U
+        c_cc[VEOF] = 0x04; // ^D
+        c_cc[VTIME] = 0;
+        c_cc[VMIN] = 1;
+        c_cc[VSTART] = 0x11; // ^Q
+        c_cc[VSTOP] = 0x13; // ^S
+        c_cc[VSUSP] = 0x1a; // ^Z
+        c_cc[VREPRINT] = 0x12; // ^R
+        c_cc[VDISCARD] = 0x0f; // ^O
+        c_cc[VWERASE] = 0x17; // ^W
+        c_cc[VLNEXT] = 0x16; // ^V
+        Self {
+            c_iflag: ICRNL | IXON,
+            c_oflag: OPOST | ONLCR,
+            c_cflag: B38400 | CS8 | CREAD | HUPCL,
+            c_lflag: ISIG | ICANON | ECHO | ECHOE | ECHOK | ECHOCTL | ECHOKE | IEXTEN,
+            c_line: 0,
+            c_cc,
+        }
+    }
+}
@@ -0,0 +1,232 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::constant::{SIGINT, SIGQUIT};
+use crate::driver::TtyDriver;
+use crate::termios::*;
+use crate::*;
+
+/// the fake monotonic time in milliseconds, only advanced by the `VTIME` test.
+static NOW_MS: AtomicU64 = AtomicU64::new(0);
+
+/// the signals sent, with the names of the devices sending them.
+static SIGNALS: Mutex<Vec<(String, u32)>> = Mutex::new(Vec::new());
+
+fn current_time() -> Duration {
+    Duration::from_millis(NOW_MS.load(Ordering::SeqCst))
+}
+
+fn send_signal(dev_name: &str, _pgrp: i32, sig: u32) {
+    SIGNALS.lock().unwrap().push((dev_name.into(), sig));
+}
+
+fn control_tty(_dev_name: &str, _attach: bool) {}
+
+fn wake_readers(_dev_name: &str) {}
+
+fn signals_of(dev_name: &str) -> Vec<u32> {
+    let signals = SIGNALS.lock().unwrap();
+    signals
+        .iter()
+        .filter(|(name, _)| name == dev_name)
+        .map(|(_, sig)| *sig)
+        .collect()
+}
+
+/// create a device, not registered, with the default settings.
+/// its name keys the signals it sends.
+fn new_tty(name: &str) -> Arc<TtyStruct> {
+    init(TtyKernelOps {
+        current_time,
+        send_signal,
+        control_tty,
+        wake_readers,
+    });
+    let ops = TtyDriverOps {
+        putchar: |_| {},
+        write: None,
+        write_room: None,
+    };
+    let tty = Arc::new(TtyStruct::new(Arc::new(TtyDriver::new(ops, "ttyT"))));
+    tty.set_name(name);
+    tty
+}
+
+fn set_raw(tty: &TtyStruct, min: u8, time: u8) {
+    let mut termios = tty.termios();
+    termios.c_lflag &= !(ICANON | ECHO);
+    termios.c_cc[VMIN] = min;
+    termios.c_cc[VTIME] = time;
+    tty.set_termios(termios);
+}
+
+fn receive(tty: &Arc<TtyStruct>, buf: &[u8]) {
+    tty.ldisc().receive_buf(tty.clone(), buf);
+}
+
+/// read at most `len` chars, `None` if the read should wait.
+fn read(tty: &TtyStruct, len: usize) -> Option<Vec<u8>> {
+    let mut buf = vec![0; len];
+    let n = tty.ldisc().read(tty, &mut buf)?;
+    buf.truncate(n);
+    Some(buf)
+}
+
+#[test]
+fn test_canonical_lines() {
+    let tty = new_tty("canon");
+    receive(&tty, b"ab\ncd");
+    assert!(tty.ldisc().readable(&tty));
+    assert_eq!(tty.ldisc().available(&tty), 3);
+
+    // one line at a time, and a short read leaves the rest of it
+    assert_eq!(read(&tty, 2).unwrap(), b"ab");
+    assert_eq!(read(&tty, 16).unwrap(), b"\n");
+
+    // the line being edited cannot be read
+    assert_eq!(read(&tty, 16), None);
+    assert!(!tty.ldisc().readable(&tty));
+
+    // ICRNL turns CR into LF
+    receive(&tty, b"\r");
+    assert_eq!(read(&tty, 16).unwrap(), b"cd\n");
+}
+
+#[test]
+fn test_canonical_editing() {
+    let tty = new_tty("edit");
+
+    // VERASE deletes the last char, VKILL the whole line
+    receive(&tty, b"abx\x7fc\n");
+    assert_eq!(read(&tty, 16).unwrap(), b"abc\n");
+    receive(&tty, b"junk\x15ok\n");
+    assert_eq!(read(&tty, 16).unwrap(), b"ok\n");
+
+    // nothing to erase at the start of a line
+    receive(&tty, b"\x7f\x7fz\n");
+    assert_eq!(read(&tty, 16).unwrap(), b"z\n");
+}
+
+#[test]
+fn test_canonical_eof() {
+    let tty = new_tty("eof");
+
+    // VEOF ends a line without a terminator
+    receive(&tty, b"xy\x04");
+    assert_eq!(read(&tty, 16).unwrap(), b"xy");
+
+    // and at the start of a line, it is the end of file
+    receive(&tty, b"\x04");
+    assert_eq!(read(&tty, 16).unwrap(), b"");
+    assert_eq!(read(&tty, 16), None);
+}
+
+#[test]
+fn test_raw_vmin() {
+    let tty = new_tty("vmin");
+    set_raw(&tty, 3, 0);
+
+    receive(&tty, b"ab");
+    assert!(!tty.ldisc().readable(&tty));
+    assert_eq!(read(&tty, 16), None);
+
+    // a read shorter than VMIN does not wait for more than it takes
+    assert_eq!(read(&tty, 2).unwrap(), b"ab");
+
+    // no line editing or translation but ICRNL
+    receive(&tty, b"\x7f\x15\r");
+    assert!(tty.ldisc().readable(&tty));
+    assert_eq!(read(&tty, 16).unwrap(), b"\x7f\x15\n");
+}
+
+#[test]
+fn test_raw_polling() {
+    let tty = new_tty("poll");
+    set_raw(&tty, 0, 0);
+
+    // VMIN and VTIME of 0 never wait
+    assert!(!tty.ldisc().reads_with_timer(&tty));
+    assert_eq!(read(&tty, 16).unwrap(), b"");
+    receive(&tty, b"q");
+    assert_eq!(read(&tty, 16).unwrap(), b"q");
+}
+
+#[test]
+fn test_raw_vtime() {
+    let tty = new_tty("vtime");
+    set_raw(&tty, 0, 2);
+    assert!(tty.ldisc().reads_with_timer(&tty));
+
+    // the read ends after VTIME without input
+    assert_eq!(read(&tty, 16), None);
+    NOW_MS.fetch_add(100, Ordering::SeqCst);
+    assert_eq!(read(&tty, 16), None);
+    NOW_MS.fetch_add(100, Ordering::SeqCst);
+    assert_eq!(read(&tty, 16).unwrap(), b"");
+
+    // or at once with any input
+    receive(&tty, b"r");
+    assert_eq!(read(&tty, 16).unwrap(), b"r");
+
+    // with VMIN, VTIME is the time since the last char received
+    set_raw(&tty, 4, 2);
+    receive(&tty, b"st");
+    assert_eq!(read(&tty, 16), None);
+    NOW_MS.fetch_add(200, Ordering::SeqCst);
+    assert_eq!(read(&tty, 16).unwrap(), b"st");
+}
+
+#[test]
+fn test_mode_switch() {
+    let tty = new_tty("switch");
+
+    // the line being edited can be read at once in non-canonical mode
+    receive(&tty, b"abc");
+    set_raw(&tty, 1, 0);
+    assert_eq!(read(&tty, 16).unwrap(), b"abc");
+
+    // and what was received is the start of a line in canonical mode
+    receive(&tty, b"de");
+    let mut termios = tty.termios();
+    termios.c_lflag |= ICANON;
+    tty.set_termios(termios);
+    assert_eq!(read(&tty, 16), None);
+    receive(&tty, b"f\n");
+    assert_eq!(read(&tty, 16).unwrap(), b"def\n");
+}
+
+#[test]
+fn test_isig() {
+    let tty = new_tty("isig");
+
+    // VINTR flushes the input and sends SIGINT
+    receive(&tty, b"one\ntwo\x03three\n");
+    assert_eq!(signals_of("isig"), [SIGINT]);
+    assert_eq!(read(&tty, 16).unwrap(), b"three\n");
+    assert_eq!(read(&tty, 16), None);
+
+    // unless NOFLSH is set
+    let mut termios = tty.termios();
+    termios.c_lflag |= NOFLSH;
+    tty.set_termios(termios);
+    receive(&tty, b"four\x1cfive\n");
+    assert_eq!(signals_of("isig"), [SIGINT, SIGQUIT]);
+    assert_eq!(read(&tty, 16).unwrap(), b"fourfive\n");
+
+    // without ISIG, control chars are input
+    termios.c_lflag &= !ISIG;
+    tty.set_termios(termios);
+    receive(&tty, b"\x03\n");
+    assert_eq!(signals_of("isig"), [SIGINT, SIGQUIT]);
+    assert_eq!(read(&tty, 16).unwrap(), b"\x03\n");
+}
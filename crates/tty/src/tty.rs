@@ -7,16 +7,25 @@
  *   See the Mulan PSL v2 for more details.
  */
 
-use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
 
 use alloc::{string::String, sync::Arc, vec, vec::Vec};
-use lazy_init::LazyInit;
 use spinlock::SpinNoIrq;
 
-use crate::{driver::TtyDriver, ldisc::TtyLdisc};
+use crate::{driver::TtyDriver, ldisc::TtyLdisc, termios::Termios};
 
 /// all registered devices.
-pub(super) static ALL_DEVICES: LazyInit<SpinNoIrq<Vec<Arc<TtyStruct>>>> = LazyInit::new();
+pub(super) static ALL_DEVICES: SpinNoIrq<Vec<Arc<TtyStruct>>> = SpinNoIrq::new(Vec::new());
+
+/// window size of a tty, the same as `struct winsize`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WinSize {
+    pub ws_row: u16,
+    pub ws_col: u16,
+    pub ws_xpixel: u16,
+    pub ws_ypixel: u16,
+}
 
 /// tty device.
 #[derive(Debug)]
@@ -32,6 +41,15 @@ pub struct TtyStruct {
 
     /// name of device.
     name: SpinNoIrq<String>,
+
+    /// window size of device.
+    winsize: SpinNoIrq<WinSize>,
+
+    /// foreground process group of device, 0 if not set.
+    pgrp: AtomicI32,
+
+    /// terminal settings of device.
+    termios: SpinNoIrq<Termios>,
 }
 
 impl TtyStruct {
@@ -41,6 +59,9 @@ impl TtyStruct {
             ldisc: Arc::new(TtyLdisc::new()),
             index: AtomicUsize::new(0),
             name: SpinNoIrq::new(String::new()),
+            winsize: SpinNoIrq::new(WinSize::default()),
+            pgrp: AtomicI32::new(0),
+            termios: SpinNoIrq::new(Termios::default()),
         }
     }
 
@@ -51,8 +72,82 @@ impl TtyStruct {
 
     /// set device index.
     pub fn set_index(&self, index: usize) {
-        self.index
-            .store(index, core::sync::atomic::Ordering::Relaxed);
+        self.index.store(index, Ordering::Relaxed);
+    }
+
+    /// get device index.
+    pub fn index(&self) -> usize {
+        self.index.load(Ordering::Relaxed)
+    }
+
+    /// get window size.
+    pub fn winsize(&self) -> WinSize {
+        *self.winsize.lock()
+    }
+
+    /// set window size, return whether it is changed.
+    pub fn set_winsize(&self, winsize: WinSize) -> bool {
+        let mut lock = self.winsize.lock();
+        let changed = *lock != winsize;
+        *lock = winsize;
+        changed
+    }
+
+    /// get foreground process group.
+    pub fn pgrp(&self) -> i32 {
+        self.pgrp.load(Ordering::Relaxed)
+    }
+
+    /// set foreground process group.
+    pub fn set_pgrp(&self, pgrp: i32) {
+        self.pgrp.store(pgrp, Ordering::Relaxed);
+    }
+
+    /// get terminal settings.
+    pub fn termios(&self) -> Termios {
+        *self.termios.lock()
+    }
+
+    /// set terminal settings.
+    pub fn set_termios(&self, termios: Termios) {
+        let old = core::mem::replace(&mut *self.termios.lock(), termios);
+        self.ldisc.termios_changed(&old, &termios);
+    }
+
+    /// perform a tty request, `arg` points to its argument.
+    /// return `None` if `cmd` is not a tty request.
+    pub fn ioctl(&self, cmd: usize, arg: usize) -> Option<usize> {
+        use crate::constant::*;
+
+        match cmd {
+            TCGETS => unsafe { *(arg as *mut Termios) = self.termios() },
+            // output is never queued, so there is nothing to wait for
+            TCSETS | TCSETSW => self.set_termios(unsafe { *(arg as *const Termios) }),
+            TCSETSF => {
+                self.ldisc.flush_input();
+                self.set_termios(unsafe { *(arg as *const Termios) });
+            }
+            TCFLSH => {
+                if arg == TCIFLUSH || arg == TCIOFLUSH {
+                    self.ldisc.flush_input();
+                }
+            }
+            // sending a break or draining output does nothing
+            TCSBRK => {}
+            FIONREAD => unsafe { *(arg as *mut i32) = self.ldisc.available(self) as i32 },
+            TIOCGWINSZ => unsafe { *(arg as *mut WinSize) = self.winsize() },
+            TIOCSWINSZ => {
+                if self.set_winsize(unsafe { *(arg as *const WinSize) }) {
+                    crate::send_signal(&self.name(), self.pgrp(), SIGWINCH);
+                }
+            }
+            TIOCGPGRP => unsafe { *(arg as *mut i32) = self.pgrp() },
+            TIOCSPGRP => self.set_pgrp(unsafe { *(arg as *const i32) }),
+            TIOCSCTTY => crate::control_tty(&self.name(), true),
+            TIOCNOTTY => crate::control_tty(&self.name(), false),
+            _ => return None,
+        }
+        Some(0)
     }
 
     /// set name of device
@@ -99,6 +194,7 @@ pub fn add_one_device(tty: Arc<TtyStruct>) {
     ALL_DEVICES.lock().push(tty);
 }
 
-pub fn init() {
-    ALL_DEVICES.init_by(SpinNoIrq::new(vec![]));
+/// forget a device when unregistered.
+pub fn remove_one_device(tty: &Arc<TtyStruct>) {
+    ALL_DEVICES.lock().retain(|t| !Arc::ptr_eq(t, tty));
 }
@@ -0,0 +1,298 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Client-side caches of a 9P mount, selected by the `cache=` option.
+//!
+//! - `none`: every operation is a round trip to the server.
+//! - `loose`: attributes and lookups (including the missing names) are kept
+//!   for `actimeo` seconds, and file contents are kept in a page cache.
+//! - `mmap`: only file contents are cached, and the clean pages are dropped
+//!   on each open, so a file is re-read after another client changes it.
+//!
+//! With a cache, the nodes of the same file are shared, so that all the
+//! opens and shared mappings of a file see the same pages. Dirty pages are
+//! written back on `fsync`, on close, and when too many of them pile up, and
+//! the least recently used clean pages are dropped when too many are kept.
+
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use spin::Mutex;
+
+use crate::fs::CommonNode;
+use crate::CacheMode;
+
+/// Size of a page of the page cache.
+pub const PAGE_SIZE: usize = 4096;
+
+/// Max number of dirty pages of a file before they are written back.
+pub const MAX_DIRTY_PAGES: usize = 256;
+
+/// Max number of clean pages kept for a file.
+pub const MAX_CLEAN_PAGES: usize = 1024;
+
+/// Max number of nodes kept by lookups, each of which holds a fid.
+const MAX_CACHED_DENTRIES: usize = 1024;
+
+/// The caches shared by all the nodes of a mount.
+pub struct FsCache {
+    mode: CacheMode,
+    /// How long attributes and lookups are valid, in nanoseconds.
+    timeout: u64,
+    /// Bumped by each change of names, which invalidates all the lookups.
+    generation: AtomicU64,
+    /// Number of nodes kept by the lookups of all the directories.
+    dentries: AtomicUsize,
+    /// The live nodes by the qid path of their files.
+    nodes: Mutex<BTreeMap<u64, Weak<CommonNode>>>,
+}
+
+impl FsCache {
+    pub fn new(mode: CacheMode, timeout_secs: u64) -> Self {
+        Self {
+            mode,
+            timeout: timeout_secs.saturating_mul(1_000_000_000),
+            generation: AtomicU64::new(0),
+            dentries: AtomicUsize::new(0),
+            nodes: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Whether the nodes of the same file are shared and file contents are cached.
+    pub fn caches_data(&self) -> bool {
+        self.mode != CacheMode::None
+    }
+
+    /// Whether attributes and lookups are cached.
+    pub fn caches_meta(&self) -> bool {
+        self.mode == CacheMode::Loose
+    }
+
+    /// Whether the clean pages are dropped on open.
+    pub fn revalidates_on_open(&self) -> bool {
+        self.mode == CacheMode::Mmap
+    }
+
+    /// The time at which something cached now expires.
+    pub fn expiry(&self) -> u64 {
+        ruxhal::time::current_time_nanos().saturating_add(self.timeout)
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Invalidates all the lookups, after names are created, removed or renamed.
+    pub fn names_changed(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Reserves a place for a node kept by a lookup, or fails if too many are kept.
+    pub fn reserve_dentry(&self) -> bool {
+        self.dentries
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < MAX_CACHED_DENTRIES).then_some(n + 1)
+            })
+            .is_ok()
+    }
+
+    /// Releases the places of `count` nodes kept by lookups.
+    pub fn release_dentries(&self, count: usize) {
+        self.dentries.fetch_sub(count, Ordering::AcqRel);
+    }
+
+    /// Gets the live node of the file with the qid path `qid`.
+    pub fn node(&self, qid: u64) -> Option<Arc<CommonNode>> {
+        self.nodes.lock().get(&qid).and_then(Weak::upgrade)
+    }
+
+    /// Records `node` as the node of the file with the qid path `qid`, and returns it, or the
+    /// live node recorded by another lookup in the meantime.
+    pub fn share_node(&self, qid: u64, node: Arc<CommonNode>) -> Arc<CommonNode> {
+        let mut nodes = self.nodes.lock();
+        if let Some(shared) = nodes.get(&qid).and_then(Weak::upgrade) {
+            drop(nodes);
+            return shared;
+        }
+        nodes.retain(|_, n| n.strong_count() > 0);
+        nodes.insert(qid, Arc::downgrade(&node));
+        node
+    }
+
+    /// Forgets the node of a removed file, whose qid path may be reused.
+    pub fn forget_node(&self, qid: u64) {
+        self.nodes.lock().remove(&qid);
+    }
+}
+
+/// Whether something cached at `expiry` is still valid.
+pub fn is_valid(expiry: u64) -> bool {
+    ruxhal::time::current_time_nanos() < expiry
+}
+
+/// The result of a lookup in a directory, kept until `expiry` or until names change.
+pub struct Dentry {
+    /// The node found, or `None` if the name doesn't exist.
+    pub node: Option<Arc<CommonNode>>,
+    pub generation: u64,
+    pub expiry: u64,
+}
+
+/// A page of file contents.
+struct Page {
+    data: Box<[u8; PAGE_SIZE]>,
+    dirty: bool,
+    /// The value of [`PageCache::clock`] when the page was last used.
+    used: u64,
+}
+
+/// The cached contents of a file.
+#[derive(Default)]
+pub struct PageCache {
+    pages: BTreeMap<u64, Page>,
+    /// The size of the file seen by this client, `None` if not known yet.
+    size: Option<u64>,
+    /// Bumped by each use of a page.
+    clock: u64,
+}
+
+impl PageCache {
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    pub fn set_size(&mut self, size: u64) {
+        self.size = Some(size);
+    }
+
+    /// Whether too many pages are not written back yet.
+    pub fn too_dirty(&self) -> bool {
+        self.pages.values().filter(|page| page.dirty).count() >= MAX_DIRTY_PAGES
+    }
+
+    /// Drops the clean pages, and forgets the size if nothing is dirty.
+    pub fn invalidate(&mut self) {
+        self.pages.retain(|_, page| page.dirty);
+        if self.pages.is_empty() {
+            self.size = None;
+        }
+    }
+
+    /// Gets the page at `index`, filling it by `fill` if it's not cached.
+    pub fn page<E>(
+        &mut self,
+        index: u64,
+        fill: impl FnOnce(&mut [u8]) -> Result<(), E>,
+    ) -> Result<&mut [u8; PAGE_SIZE], E> {
+        self.clock += 1;
+        if !self.pages.contains_key(&index) {
+            let mut data = Box::new([0; PAGE_SIZE]);
+            fill(&mut data[..])?;
+            self.evict_clean();
+            let page = Page {
+                data,
+                dirty: false,
+                used: self.clock,
+            };
+            self.pages.insert(index, page);
+        }
+        let page = self.pages.get_mut(&index).unwrap();
+        page.used = self.clock;
+        Ok(&mut page.data)
+    }
+
+    /// Drops the least recently used clean page if there are too many of them.
+    fn evict_clean(&mut self) {
+        let mut clean = self.pages.iter().filter(|(_, page)| !page.dirty);
+        if clean.clone().count() < MAX_CLEAN_PAGES {
+            return;
+        }
+        if let Some((&index, _)) = clean.min_by_key(|(_, page)| page.used) {
+            self.pages.remove(&index);
+        }
+    }
+
+    /// Marks the page at `index` dirty.
+    pub fn mark_dirty(&mut self, index: u64) {
+        if let Some(page) = self.pages.get_mut(&index) {
+            page.dirty = true;
+        }
+    }
+
+    /// Drops the pages after `size`, and zeroes the tail of the last page.
+    pub fn truncate(&mut self, size: u64) {
+        let first_dropped = size.div_ceil(PAGE_SIZE as u64);
+        self.pages.retain(|&index, _| index < first_dropped);
+        let tail = (size % PAGE_SIZE as u64) as usize;
+        if tail != 0 {
+            if let Some(page) = self.pages.get_mut(&(size / PAGE_SIZE as u64)) {
+                page.data[tail..].fill(0);
+            }
+        }
+        self.size = Some(size);
+    }
+
+    /// Drops the pages overlapping `start..end`, written to the server around
+    /// the cache, and grows the size up to `end`.
+    pub fn written_around(&mut self, start: u64, end: u64) {
+        if start >= end {
+            return;
+        }
+        let first = start / PAGE_SIZE as u64;
+        let last = (end - 1) / PAGE_SIZE as u64;
+        self.pages.retain(|&index, _| index < first || index > last);
+        if let Some(size) = self.size {
+            self.size = Some(size.max(end));
+        }
+    }
+
+    /// Writes back the dirty pages by `write(offset, data)`, up to the size of the file.
+    ///
+    /// Contiguous dirty pages are written together, up to `max_len` bytes at a time.
+    pub fn write_back<E>(
+        &mut self,
+        max_len: usize,
+        mut write: impl FnMut(u64, &[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let size = self.size.unwrap_or(0);
+        let max_pages = (max_len / PAGE_SIZE).max(1);
+        let dirty: Vec<u64> = self
+            .pages
+            .iter()
+            .filter(|(_, page)| page.dirty)
+            .map(|(&index, _)| index)
+            .collect();
+        let mut buf = Vec::new();
+        let mut rest = &dirty[..];
+        while let Some(&first) = rest.first() {
+            let offset = first * PAGE_SIZE as u64;
+            let mut count = 1;
+            while count < max_pages && rest.get(count) == Some(&(first + count as u64)) {
+                count += 1;
+            }
+            buf.clear();
+            for index in first..first + count as u64 {
+                buf.extend_from_slice(&self.pages[&index].data[..]);
+            }
+            buf.truncate(size.saturating_sub(offset).min(buf.len() as u64) as usize);
+            if !buf.is_empty() {
+                write(offset, &buf)?;
+            }
+            for index in first..first + count as u64 {
+                self.pages.get_mut(&index).unwrap().dirty = false;
+            }
+            rest = &rest[count..];
+        }
+        Ok(())
+    }
+}
@@ -10,15 +10,16 @@
 #![allow(dead_code)]
 
 use alloc::{string::String, string::ToString, sync::Arc, vec, vec::Vec};
+use core::time::Duration;
 use log::*;
 use ruxdriver::prelude::*;
 use spin::RwLock;
 
-const EIO: u8 = 5;
-const EINVAL: u8 = 90;
+const EIO: u32 = 5;
+const EINVAL: u32 = 22;
 const _9P_LEAST_QLEN: u32 = 7; // size[4] type_id[1] tag[2]
-pub const _9P_MAX_QSIZE: u32 = 8192 + 1; // it should larger than 8192, or virtio-9p may raise a warning.
-pub const _9P_MAX_PSIZE: u32 = 8192 + 1; // it should larger than 8192, or virtio-9p may raise a warning.
+const _9P_MIN_MSIZE: u32 = 4096;
+const _9P_IOHDRSZ: u32 = 24; // size[4] type_id[1] tag[2] fid[4] offset[8] count[4] and padding
 const _9P_NONUNAME: u32 = 0;
 
 pub const _9P_SETATTR_MODE: u64 = 0x00000001;
@@ -31,51 +32,77 @@ pub const _9P_SETATTR_CTIME: u64 = 0x00000040;
 pub const _9P_SETATTR_ATIME_SET: u64 = 0x00000080;
 pub const _9P_SETATTR_MTIME_SET: u64 = 0x00000100;
 
+pub const _9P_LOCK_TYPE_RDLCK: u8 = 0;
+pub const _9P_LOCK_TYPE_WRLCK: u8 = 1;
+pub const _9P_LOCK_TYPE_UNLCK: u8 = 2;
+pub const _9P_LOCK_SUCCESS: u8 = 0;
+pub const _9P_LOCK_BLOCKED: u8 = 1;
+pub const _9P_LOCK_ERROR: u8 = 2;
+pub const _9P_LOCK_GRACE: u8 = 3;
+
 const FID_MAX: u32 = 4096;
 
 pub struct Drv9pOps {
     transport: Arc<RwLock<Ax9pDevice>>,
     fid_gen: RwLock<Vec<u32>>,
+    msize: u32,
+    response: Vec<u8>,
 }
 
 impl Drv9pOps {
-    pub fn new(transport: Ax9pDevice) -> Self {
-        match transport.init() {
+    /// `msize` is the max message size to propose, limited by the transport, and 0 for the transport's max.
+    ///
+    /// Fails with the transport's error code if it can't be initialized.
+    pub fn new(transport: Arc<RwLock<Ax9pDevice>>, msize: u32) -> Result<Self, u8> {
+        let msize = proposed_msize(msize, transport.read().max_msize());
+        match transport.read().init() {
             Ok(_) => {
                 info!("9p dev init success");
             }
             Err(ecode) => {
                 error!("9p dev init fail! error code:{}", ecode);
+                return Err(ecode);
             }
         }
-        Self {
-            transport: Arc::new(RwLock::new(transport)),
+        Ok(Self {
+            transport,
             fid_gen: RwLock::new((0..=FID_MAX).rev().collect::<Vec<u32>>()),
-        }
+            msize,
+            response: vec![0; msize as usize],
+        })
+    }
+
+    /// The negotiated max message size.
+    pub fn msize(&self) -> u32 {
+        self.msize
+    }
+
+    /// The max count of data in a single read or write.
+    pub fn io_unit(&self) -> u32 {
+        self.msize - _9P_IOHDRSZ
     }
 
-    // Send request and receive response
-    pub fn request(&mut self, request: &[u8], response: &mut [u8]) -> Result<(), u8> {
-        if request.len() as u32 > _9P_MAX_QSIZE {
+    // Send request and receive response in `self.response`, return errno of server if failed.
+    pub fn request(&mut self, request: &[u8]) -> Result<(), u32> {
+        if request.len() as u32 > self.msize {
             return Err(EINVAL);
         }
-        let enqueue_try = self.transport.write().send_with_recv(request, response);
+        let enqueue_try = self
+            .transport
+            .write()
+            .send_with_recv(request, &mut self.response);
         match enqueue_try {
-            Ok(_) => {
-                const RTYPE_INDEX: usize = 4;
-                const ECODE_INDEX: usize = 7;
-                const ERROR_RESP: u8 = _9PType::Rlerror as u8;
-                match response[RTYPE_INDEX] {
-                    ERROR_RESP => {
-                        debug!(
-                            "9pfs request({}) occurs a error, errcode: {}",
-                            request[RTYPE_INDEX], response[ECODE_INDEX]
-                        );
-                        Err(response[ECODE_INDEX])
-                    }
-                    _ => Ok(()),
+            Ok(_) => match response_errno(&self.response) {
+                Some(ecode) => {
+                    const RTYPE_INDEX: usize = 4;
+                    debug!(
+                        "9pfs request({}) occurs a error, errcode: {}",
+                        request[RTYPE_INDEX], ecode
+                    );
+                    Err(ecode)
                 }
-            }
+                None => Ok(()),
+            },
             Err(_) => Err(EIO),
         }
     }
@@ -97,84 +124,87 @@ impl Drv9pOps {
     }
 
     /// The Terror implement in 9P2000.L, Terror is usually not needed(So it is not implement).
-    pub fn l_terror(&mut self, ecode: u32) -> Result<(), u8> {
+    pub fn l_terror(&mut self, ecode: u32) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tlerror);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(ecode);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
-    pub fn tversion(&mut self, protocol: &str) -> Result<String, u8> {
+    /// Negotiate the protocol version and msize, return the version replied by the server.
+    ///
+    /// msize is lowered to the server's if it is smaller.
+    pub fn tversion(&mut self, protocol: &str) -> Result<String, u32> {
         let mut request = _9PReq::new(_9PType::Tversion);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
-        request.write_u32(_9P_MAX_QSIZE);
-        // TODO: use version selected by configure
+        request.write_u32(self.msize);
         request.write_str(protocol);
         request.finish();
-        match self.request(&request.buffer, &mut response_buffer) {
+        match self.request(&request.buffer) {
             Ok(_) => {
-                const START: usize = 13;
-                let length: usize =
-                    (response_buffer[12] as usize * 256) + response_buffer[11] as usize;
-                Ok(String::from_utf8_lossy(&response_buffer[START..START + length]).to_string())
+                // size[4] Rversion tag[2] msize[4] version[s]
+                let msize = lbytes2u64(&self.response[7..11]) as u32;
+                self.msize = negotiated_msize(self.msize, msize);
+                Ok(lbytes2str(&self.response[11..]))
             }
             Err(err_code) => Err(err_code),
         }
     }
 
-    fn tflush(&mut self, oldtag: u16) -> Result<(), u8> {
+    fn tflush(&mut self, oldtag: u16) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tflush);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u16(oldtag);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
-    pub fn tfsync(&mut self, fid: u32) -> Result<(), u8> {
+    pub fn tfsync(&mut self, fid: u32) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tfsync);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u32(0_u32); //0: full data sync; 1: file data only.
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
-    pub fn tclunk(&mut self, fid: u32) -> Result<(), u8> {
+    pub fn tclunk(&mut self, fid: u32) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tclunk);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
-    pub fn tauth(&mut self, afid: u32, uname: &str, aname: &str) -> Result<(), u8> {
+    pub fn tauth(&mut self, afid: u32, uname: &str, aname: &str) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tauth);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(afid);
         request.write_str(uname);
         request.write_str(aname);
         request.write_u32(_9P_NONUNAME);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
-    pub fn tattach(&mut self, fid: u32, afid: u32, uname: &str, aname: &str) -> Result<(), u8> {
+    pub fn tattach(&mut self, fid: u32, afid: u32, uname: &str, aname: &str) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tattach);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u32(afid);
         request.write_str(uname);
         request.write_str(aname);
         request.write_u32(_9P_NONUNAME);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     /// `twalk()`: Pay attention to the max_size of request buffer, wnames should not be too long usually.
-    pub fn twalk(&mut self, fid: u32, newfid: u32, nwname: u16, wnames: &[&str]) -> Result<(), u8> {
+    ///
+    /// Returns the qid of the last walked name, or `None` if `nwname` is 0. A partial walk fails
+    /// with ENOENT, and `newfid` isn't used then.
+    pub fn twalk(
+        &mut self,
+        fid: u32,
+        newfid: u32,
+        nwname: u16,
+        wnames: &[&str],
+    ) -> Result<Option<_9PQid>, u32> {
         let mut request = _9PReq::new(_9PType::Twalk);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u32(newfid);
         request.write_u16(nwname);
@@ -182,18 +212,31 @@ impl Drv9pOps {
             request.write_str(s);
         }
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)?;
+
+        // size[4] Rwalk tag[2] nwqid[2] nwqid*(wqid[13])
+        const ENOENT: u32 = 2;
+        let nwqid = lbytes2u64(&self.response[7..9]) as u16;
+        if nwqid < nwname {
+            return Err(ENOENT);
+        }
+        Ok(match nwqid {
+            0 => None,
+            _ => {
+                let qid_index = 9 + (nwqid as usize - 1) * 13;
+                Some(_9PQid::new(&self.response[qid_index..qid_index + 13]))
+            }
+        })
     }
 
-    pub fn tcreate(&mut self, fid: u32, name: &str, perm: u32, mode: u8) -> Result<(), u8> {
+    pub fn tcreate(&mut self, fid: u32, name: &str, perm: u32, mode: u8) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tcreate);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_str(name);
         request.write_u32(perm);
         request.write_u8(mode);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     pub fn u_tcreate(
@@ -203,16 +246,15 @@ impl Drv9pOps {
         perm: u32,
         mode: u8,
         extension: &str,
-    ) -> Result<(), u8> {
+    ) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tcreate);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_str(name);
         request.write_u32(perm);
         request.write_u8(mode);
         request.write_str(extension);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     /// lcreate creates a regular file name in directory fid and prepares it for I/O.
@@ -223,27 +265,25 @@ impl Drv9pOps {
         flags: u32,
         mode: u32,
         gid: u32,
-    ) -> Result<(), u8> {
+    ) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tlcreate);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_str(name);
         request.write_u32(flags);
         request.write_u32(mode);
         request.write_u32(gid);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     /// This operation will eventually be replaced by renameat (see below).
-    pub fn trename(&mut self, fid: u32, dfid: u32, new_name: &str) -> Result<(), u8> {
+    pub fn trename(&mut self, fid: u32, dfid: u32, new_name: &str) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::TrenameAt);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u32(dfid);
         request.write_str(new_name);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     /// Change the name of a file from oldname to newname,
@@ -255,82 +295,72 @@ impl Drv9pOps {
         oldname: &str,
         newdirfid: u32,
         new_name: &str,
-    ) -> Result<(), u8> {
+    ) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::TrenameAt);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(olddirfid);
         request.write_str(oldname);
         request.write_u32(newdirfid);
         request.write_str(new_name);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     /// open file or dir in 9P2000(.U) Operation
-    pub fn topen(&mut self, fid: u32, mode: u8) -> Result<(), u8> {
+    pub fn topen(&mut self, fid: u32, mode: u8) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Topen);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u8(mode);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     /// open file or dir in 9P2000.L Operation
-    pub fn l_topen(&mut self, fid: u32, flags: u32) -> Result<(), u8> {
+    pub fn l_topen(&mut self, fid: u32, flags: u32) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tlopen);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u32(flags);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     /// read perform I/O on the file represented by fid.
     /// Note that in v9fs, a read(2) or write(2) system call for a chunk of the file that won't fit in a single request is broken up into multiple requests.
-    /// `tread()` can only read _9P_MAX_PSIZE-25 bytes if counter is larger than _9P_MAX_PSIZE.
-    pub fn tread(&mut self, fid: u32, offset: u64, count: u32) -> Result<Vec<u8>, u8> {
-        // check if `count` larger than MAX_READ_LEN
-        const MAX_READ_LEN: u32 = _9P_MAX_PSIZE - 32;
-        let mut reading_len = count;
-        if reading_len > MAX_READ_LEN {
-            reading_len = MAX_READ_LEN;
-        }
+    /// `tread()` reads at most `io_unit()` bytes into `buf`, and returns the length read.
+    pub fn tread(&mut self, fid: u32, offset: u64, buf: &mut [u8]) -> Result<usize, u32> {
+        let reading_len = (buf.len() as u32).min(self.io_unit());
         let mut request = _9PReq::new(_9PType::Tread);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u64(offset);
         request.write_u32(reading_len);
         request.finish();
-        match self.request(&request.buffer, &mut response_buffer) {
+        match self.request(&request.buffer) {
             Ok(_) => {
                 const COUNT_START: usize = 7;
                 const COUNT_END: usize = 11;
-                let length = lbytes2u64(&response_buffer[COUNT_START..COUNT_START + 4]) as usize;
-                Ok(response_buffer[COUNT_END..COUNT_END + length].to_vec())
+                let length = lbytes2u64(&self.response[COUNT_START..COUNT_START + 4]) as usize;
+                let length = length.min(reading_len as usize);
+                buf[..length].copy_from_slice(&self.response[COUNT_END..COUNT_END + length]);
+                Ok(length)
             }
             Err(err_code) => Err(err_code),
         }
     }
 
-    pub fn treaddir(&mut self, fid: u32) -> Result<Vec<DirEntry>, u8> {
+    pub fn treaddir(&mut self, fid: u32) -> Result<Vec<DirEntry>, u32> {
         let mut dir_entries: Vec<DirEntry> = Vec::new();
         let mut offptr = 0_u64;
         loop {
-            // check if `count` larger than MAX_READ_LEN
-            const MAX_READ_LEN: u32 = _9P_MAX_PSIZE - 32;
-
+            let max_read_len = self.io_unit();
             let mut request = _9PReq::new(_9PType::Treaddir);
-            let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
             request.write_u32(fid);
             request.write_u64(offptr);
-            request.write_u32(MAX_READ_LEN);
+            request.write_u32(max_read_len);
             request.finish();
-            match self.request(&request.buffer, &mut response_buffer) {
+            match self.request(&request.buffer) {
                 Ok(_) => {
                     const COUNT_START: usize = 7;
                     const COUNT_END: usize = 11;
-                    let length: u64 = lbytes2u64(&response_buffer[COUNT_START..COUNT_START + 4]);
+                    let length: u64 = lbytes2u64(&self.response[COUNT_START..COUNT_START + 4]);
                     if length == 0 {
                         break;
                     }
@@ -339,10 +369,10 @@ impl Drv9pOps {
                     while resp_ptr < length as usize {
                         // qid[13] offset[8] type[1] name[s]
                         let dir_entry = DirEntry {
-                            qid: _9PQid::new(&response_buffer[resp_ptr..resp_ptr + 13]),
-                            offset: lbytes2u64(&response_buffer[resp_ptr + 13..resp_ptr + 21]),
-                            dtype: response_buffer[resp_ptr + 21],
-                            name: lbytes2str(&response_buffer[resp_ptr + 22..]),
+                            qid: _9PQid::new(&self.response[resp_ptr..resp_ptr + 13]),
+                            offset: lbytes2u64(&self.response[resp_ptr + 13..resp_ptr + 21]),
+                            dtype: self.response[resp_ptr + 21],
+                            name: lbytes2str(&self.response[resp_ptr + 22..]),
                         };
                         resp_ptr += 24 + dir_entry.name.len();
                         offptr = dir_entry.offset;
@@ -356,24 +386,21 @@ impl Drv9pOps {
     }
 
     /// read directory represented by fid in 9P2000.u. In 9P2000.L, using treaddir() instead.
-    pub fn u_treaddir(&mut self, fid: u32) -> Result<Vec<DirEntry>, u8> {
+    pub fn u_treaddir(&mut self, fid: u32) -> Result<Vec<DirEntry>, u32> {
         let mut dir_entries: Vec<DirEntry> = Vec::new();
         let mut offptr = 0_u64;
         loop {
-            // check if `count` larger than MAX_READ_LEN
-            const MAX_READ_LEN: u32 = _9P_MAX_PSIZE - 32;
-
+            let max_read_len = self.io_unit();
             let mut request = _9PReq::new(_9PType::Tread);
-            let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
             request.write_u32(fid);
             request.write_u64(offptr);
-            request.write_u32(MAX_READ_LEN);
+            request.write_u32(max_read_len);
             request.finish();
-            match self.request(&request.buffer, &mut response_buffer) {
+            match self.request(&request.buffer) {
                 Ok(_) => {
                     const COUNT_START: usize = 7;
                     const COUNT_END: usize = 11;
-                    let length: u64 = lbytes2u64(&response_buffer[COUNT_START..COUNT_START + 4]);
+                    let length: u64 = lbytes2u64(&self.response[COUNT_START..COUNT_START + 4]);
                     if length == 0 {
                         break;
                     }
@@ -381,7 +408,7 @@ impl Drv9pOps {
                     let mut resp_ptr = COUNT_END;
                     while resp_ptr < length as usize {
                         // qid[13] offset[8] type[1] name[s]
-                        let state = UStatFs::parse_u_from(&response_buffer[resp_ptr..]);
+                        let state = UStatFs::parse_u_from(&self.response[resp_ptr..]);
                         let dir_entry = DirEntry {
                             qid: state.get_qid(),
                             offset: resp_ptr as u64,
@@ -401,84 +428,73 @@ impl Drv9pOps {
 
     /// write perform I/O on the file represented by fid.
     /// Note that in v9fs, a read(2) or write(2) system call for a chunk of the file that won't fit in a single request is broken up into multiple requests.
-    pub fn twrite(&mut self, fid: u32, offset: u64, data: &[u8]) -> Result<usize, u8> {
-        const MAX_READ_LEN: u32 = _9P_MAX_PSIZE - 32;
-        let mut writing_len = data.len() as u32;
-        if writing_len > MAX_READ_LEN {
-            writing_len = MAX_READ_LEN;
-        }
+    pub fn twrite(&mut self, fid: u32, offset: u64, data: &[u8]) -> Result<usize, u32> {
+        let writing_len = (data.len() as u32).min(self.io_unit());
         let mut request = _9PReq::new(_9PType::Twrite);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u64(offset);
         request.write_u32(writing_len);
-        for value in &data[..writing_len as usize] {
-            request.write_u8(*value);
-        }
+        request.write_bytes(&data[..writing_len as usize]);
         request.finish();
-        match self.request(&request.buffer, &mut response_buffer) {
-            Ok(_) => Ok(lbytes2u64(&response_buffer[7..11]) as usize), // index from 7 to 11 corresponing to total count of writed byte
+        match self.request(&request.buffer) {
+            Ok(_) => Ok(lbytes2u64(&self.response[7..11]) as usize), // index from 7 to 11 corresponing to total count of writed byte
             Err(ecode) => Err(ecode),
         }
     }
 
-    pub fn tmkdir(&mut self, dfid: u32, name: &str, mode: u32, gid: u32) -> Result<(), u8> {
+    pub fn tmkdir(&mut self, dfid: u32, name: &str, mode: u32, gid: u32) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tmkdir);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(dfid);
         request.write_str(name);
         request.write_u32(mode);
         request.write_u32(gid);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
-    pub fn tremove(&mut self, fid: u32) -> Result<(), u8> {
+    pub fn tremove(&mut self, fid: u32) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tremove);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     /// size[4] Rgetattr tag[2] valid[8] qid[13] mode[4] uid[4] gid[4] nlink[8] rdev[8] size[8] blksize[8] blocks[8] atime_sec[8]
     /// atime_nsec[8] mtime_sec[8] mtime_nsec[8] ctime_sec[8] ctime_nsec[8] btime_sec[8] btime_nsec[8] gen[8] data_version[8]
-    pub fn tgetattr(&mut self, fid: u32, request_mask: u64) -> Result<FileAttr, u8> {
+    pub fn tgetattr(&mut self, fid: u32, request_mask: u64) -> Result<FileAttr, u32> {
         let mut request = _9PReq::new(_9PType::Tgetattr);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u64(request_mask);
         request.finish();
-        match self.request(&request.buffer, &mut response_buffer) {
+        match self.request(&request.buffer) {
             Ok(_) => Ok(FileAttr {
-                vaild: lbytes2u64(&response_buffer[7..15]),
-                qid: _9PQid::new(&response_buffer[15..28]),
-                mode: lbytes2u64(&response_buffer[28..32]) as u32,
-                uid: lbytes2u64(&response_buffer[32..36]) as u32,
-                gid: lbytes2u64(&response_buffer[36..40]) as u32,
-                n_link: lbytes2u64(&response_buffer[40..48]),
-                rdev: lbytes2u64(&response_buffer[48..56]),
-                size: lbytes2u64(&response_buffer[56..64]),
-                blk_size: lbytes2u64(&response_buffer[64..72]),
-                n_blk: lbytes2u64(&response_buffer[72..80]),
-                atime_sec: lbytes2u64(&response_buffer[80..88]),
-                atime_ns: lbytes2u64(&response_buffer[88..96]),
-                mtime_sec: lbytes2u64(&response_buffer[96..104]),
-                mtime_ns: lbytes2u64(&response_buffer[104..112]),
-                ctime_sec: lbytes2u64(&response_buffer[112..120]),
-                ctime_ns: lbytes2u64(&response_buffer[120..128]),
-                btime_sec: lbytes2u64(&response_buffer[128..136]),
-                btime_ns: lbytes2u64(&response_buffer[136..144]),
-                gen: lbytes2u64(&response_buffer[144..152]),
-                date_version: lbytes2u64(&response_buffer[152..160]),
+                vaild: lbytes2u64(&self.response[7..15]),
+                qid: _9PQid::new(&self.response[15..28]),
+                mode: lbytes2u64(&self.response[28..32]) as u32,
+                uid: lbytes2u64(&self.response[32..36]) as u32,
+                gid: lbytes2u64(&self.response[36..40]) as u32,
+                n_link: lbytes2u64(&self.response[40..48]),
+                rdev: lbytes2u64(&self.response[48..56]),
+                size: lbytes2u64(&self.response[56..64]),
+                blk_size: lbytes2u64(&self.response[64..72]),
+                n_blk: lbytes2u64(&self.response[72..80]),
+                atime_sec: lbytes2u64(&self.response[80..88]),
+                atime_ns: lbytes2u64(&self.response[88..96]),
+                mtime_sec: lbytes2u64(&self.response[96..104]),
+                mtime_ns: lbytes2u64(&self.response[104..112]),
+                ctime_sec: lbytes2u64(&self.response[112..120]),
+                ctime_ns: lbytes2u64(&self.response[120..128]),
+                btime_sec: lbytes2u64(&self.response[128..136]),
+                btime_ns: lbytes2u64(&self.response[136..144]),
+                gen: lbytes2u64(&self.response[144..152]),
+                date_version: lbytes2u64(&self.response[152..160]),
             }),
             Err(err_code) => Err(err_code),
         }
     }
 
-    pub fn tsetattr(&mut self, fid: u32, attr: FileAttr) -> Result<(), u8> {
+    pub fn tsetattr(&mut self, fid: u32, attr: FileAttr) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tsetattr);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u32(attr.vaild as u32);
         request.write_u32(attr.mode);
@@ -490,19 +506,18 @@ impl Drv9pOps {
         request.write_u64(attr.mtime_sec);
         request.write_u64(attr.mtime_ns);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     /// xattrwalk gets a newfid pointing to xattr name. This fid can later be used to read the xattr value.
     /// If name is NULL newfid can be used to get the list of extended attributes associated with the file system object.
-    pub fn t_xattr_walk(&mut self, fid: u32, new_fid: u32, name: &str) -> Result<(), u8> {
+    pub fn t_xattr_walk(&mut self, fid: u32, new_fid: u32, name: &str) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::TxattrWalk);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u32(new_fid);
         request.write_str(name);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     pub fn t_xattr_create(
@@ -511,26 +526,24 @@ impl Drv9pOps {
         name: &str,
         attr_size: u64,
         flags: u32,
-    ) -> Result<(), u8> {
+    ) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::TxattrCreate);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_str(name);
         request.write_u64(attr_size);
         request.write_u32(flags);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
-    pub fn tsymlink(&mut self, fid: u32, name: &str, symtgt: &str, gid: u32) -> Result<(), u8> {
+    pub fn tsymlink(&mut self, fid: u32, name: &str, symtgt: &str, gid: u32) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tsymlink);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_str(name);
         request.write_str(symtgt);
         request.write_u32(gid);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     pub fn tmknod(
@@ -541,9 +554,8 @@ impl Drv9pOps {
         major: u32,
         minor: u32,
         gid: u32,
-    ) -> Result<(), u8> {
+    ) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tmknod);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(dfid);
         request.write_str(name);
         request.write_u32(mode);
@@ -551,46 +563,42 @@ impl Drv9pOps {
         request.write_u32(minor);
         request.write_u32(gid);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
-    pub fn treadlink(&mut self, fid: u32) -> Result<String, u8> {
+    pub fn treadlink(&mut self, fid: u32) -> Result<String, u32> {
         let mut request = _9PReq::new(_9PType::Treadlink);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.finish();
-        match self.request(&request.buffer, &mut response_buffer) {
-            Ok(_) => Ok(lbytes2str(&response_buffer[7..])),
+        match self.request(&request.buffer) {
+            Ok(_) => Ok(lbytes2str(&self.response[7..])),
             Err(err_code) => Err(err_code),
         }
     }
 
-    pub fn tlink(&mut self, dfid: u32, fid: u32, name: &str) -> Result<(), u8> {
+    pub fn tlink(&mut self, dfid: u32, fid: u32, name: &str) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Tlink);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(dfid);
         request.write_u32(fid);
         request.write_str(name);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
-    pub fn tunlink(&mut self, dirfid: u32, name: &str, flags: u32) -> Result<(), u8> {
+    pub fn tunlink(&mut self, dirfid: u32, name: &str, flags: u32) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::TunlinkAT);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(dirfid);
         request.write_str(name);
         request.write_u32(flags);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
     }
 
     /// create or delete a lock on a fid, similar to fcntl(F_SETLK)
     /// bits of flags: BLOCK 1, RESERVED 1<<1;
     /// return status if ok: SUCCESS 0; BLOCKED 1; ERROR 2; GRACE 3.  
-    pub fn tlock(&mut self, fid: u32, flags: u32, locker: PosLock) -> Result<u8, u8> {
+    pub fn tlock(&mut self, fid: u32, flags: u32, locker: PosLock) -> Result<u8, u32> {
         let mut request = _9PReq::new(_9PType::Tlock);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u8(locker.lock_type);
         request.write_u32(flags);
@@ -599,16 +607,15 @@ impl Drv9pOps {
         request.write_u32(locker.proc_id);
         request.write_str(&locker.client_id);
         request.finish();
-        match self.request(&request.buffer, &mut response_buffer) {
-            Ok(_) => Ok(response_buffer[7]),
+        match self.request(&request.buffer) {
+            Ok(_) => Ok(self.response[7]),
             Err(ecode) => Err(ecode),
         }
     }
 
     /// check if lock existing.
-    pub fn tgetlock(&mut self, fid: u32, locker: PosLock) -> Result<PosLock, u8> {
+    pub fn tgetlock(&mut self, fid: u32, locker: PosLock) -> Result<PosLock, u32> {
         let mut request = _9PReq::new(_9PType::Tgetlock);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.write_u8(locker.lock_type);
         request.write_u64(locker.start);
@@ -616,58 +623,55 @@ impl Drv9pOps {
         request.write_u32(locker.proc_id);
         request.write_str(&locker.client_id);
         request.finish();
-        match self.request(&request.buffer, &mut response_buffer) {
+        match self.request(&request.buffer) {
             Ok(_) => Ok(PosLock {
-                lock_type: response_buffer[7],
-                start: lbytes2u64(&response_buffer[8..16]),
-                length: lbytes2u64(&response_buffer[16..24]),
-                proc_id: lbytes2u64(&response_buffer[24..28]) as u32,
-                client_id: lbytes2str(&response_buffer[28..]),
+                lock_type: self.response[7],
+                start: lbytes2u64(&self.response[8..16]),
+                length: lbytes2u64(&self.response[16..24]),
+                proc_id: lbytes2u64(&self.response[24..28]) as u32,
+                client_id: lbytes2str(&self.response[28..]),
             }),
             Err(ecode) => Err(ecode),
         }
     }
 
     /// get information of filesystem (in 9P2000.L protocol)
-    pub fn tstatfs(&mut self, fid: u32) -> Result<LStatFs, u8> {
+    pub fn tstatfs(&mut self, fid: u32) -> Result<LStatFs, u32> {
         let mut request = _9PReq::new(_9PType::Tstatfs);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.finish();
-        match self.request(&request.buffer, &mut response_buffer) {
+        match self.request(&request.buffer) {
             Ok(_) => Ok({
                 LStatFs {
-                    fs_type: lbytes2u64(&response_buffer[7..11]) as u32,
-                    blk_size: lbytes2u64(&response_buffer[11..15]) as u32,
-                    n_blk: lbytes2u64(&response_buffer[15..23]),
-                    blk_free: lbytes2u64(&response_buffer[23..31]),
-                    blk_avail: lbytes2u64(&response_buffer[31..39]),
-                    n_files: lbytes2u64(&response_buffer[39..47]),
-                    file_free: lbytes2u64(&response_buffer[47..55]),
-                    fs_id: lbytes2u64(&response_buffer[55..63]),
-                    len_name: lbytes2u64(&response_buffer[63..67]) as u32,
+                    fs_type: lbytes2u64(&self.response[7..11]) as u32,
+                    blk_size: lbytes2u64(&self.response[11..15]) as u32,
+                    n_blk: lbytes2u64(&self.response[15..23]),
+                    blk_free: lbytes2u64(&self.response[23..31]),
+                    blk_avail: lbytes2u64(&self.response[31..39]),
+                    n_files: lbytes2u64(&self.response[39..47]),
+                    file_free: lbytes2u64(&self.response[47..55]),
+                    fs_id: lbytes2u64(&self.response[55..63]),
+                    len_name: lbytes2u64(&self.response[63..67]) as u32,
                 }
             }),
             Err(ecode) => Err(ecode),
         }
     }
 
-    pub fn tstat(&mut self, fid: u32) -> Result<UStatFs, u8> {
+    pub fn tstat(&mut self, fid: u32) -> Result<UStatFs, u32> {
         let mut request = _9PReq::new(_9PType::Tstat);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         request.write_u32(fid);
         request.finish();
-        match self.request(&request.buffer, &mut response_buffer) {
+        match self.request(&request.buffer) {
             // Note: Tstat should start stat[n] from index 7 in respone_buffer, but QEMU's start at index 9.
             // see more at: http://ericvh.github.io/9p-rfc/rfc9p2000.u.html
-            Ok(_) => Ok(UStatFs::parse_u_from(&response_buffer[9..])),
+            Ok(_) => Ok(UStatFs::parse_u_from(&self.response[9..])),
             Err(ecode) => Err(ecode),
         }
     }
 
-    pub fn twstat(&mut self, fid: u32, stat: UStatFs) -> Result<(), u8> {
+    pub fn twstat(&mut self, fid: u32, stat: UStatFs) -> Result<(), u32> {
         let mut request = _9PReq::new(_9PType::Twstat);
-        let mut response_buffer: [u8; _9P_MAX_PSIZE as usize] = [0; _9P_MAX_PSIZE as usize];
         // Note: twstat should start stat[n] from index 9 in respone_buffer, but QEMU's implement start at index 11.
         // see more at:http://ericvh.github.io/9p-rfc/rfc9p2000.u.html
         request.write_u16(0);
@@ -691,7 +695,48 @@ impl Drv9pOps {
         request.write_u32(stat.n_gid);
         request.write_u32(stat.n_muid);
         request.finish();
-        self.request(&request.buffer, &mut response_buffer)
+        self.request(&request.buffer)
+    }
+}
+
+/// The msize to propose for the configured `msize`, limited by the transport's `max_msize`,
+/// and 0 for `max_msize`.
+pub fn proposed_msize(msize: u32, max_msize: u32) -> u32 {
+    match msize {
+        0 => max_msize,
+        _ => msize.clamp(_9P_MIN_MSIZE, max_msize),
+    }
+}
+
+/// The msize to use after proposing `proposed`, lowered to the `replied` one of the server if
+/// it is smaller.
+pub fn negotiated_msize(proposed: u32, replied: u32) -> u32 {
+    if replied < proposed {
+        replied.max(_9P_MIN_MSIZE)
+    } else {
+        proposed
+    }
+}
+
+/// The errno of an Rlerror or Rerror `response`, or `None` if it is not an error.
+pub fn response_errno(response: &[u8]) -> Option<u32> {
+    const RTYPE_INDEX: usize = 4;
+    const ECODE_INDEX: usize = 7;
+    const LERROR_RESP: u8 = _9PType::Rlerror as u8;
+    const ERROR_RESP: u8 = _9PType::Rerror as u8;
+    match response[RTYPE_INDEX] {
+        // size[4] Rlerror tag[2] ecode[4]
+        LERROR_RESP => Some(lbytes2u64(&response[ECODE_INDEX..ECODE_INDEX + 4]) as u32),
+        // size[4] Rerror tag[2] ename[s] errno[4], errno is 0 if the server is not 9P2000.u
+        ERROR_RESP => {
+            let ename = lbytes2str(&response[ECODE_INDEX..]);
+            let errno_index = ECODE_INDEX + 2 + ename.len();
+            match lbytes2u64(&response[errno_index..errno_index + 4]) as u32 {
+                0 => Some(EIO),
+                errno => Some(errno),
+            }
+        }
+        _ => None,
     }
 }
 
@@ -756,6 +801,10 @@ impl _9PReq {
         }
     }
 
+    fn write_bytes(&mut self, value: &[u8]) {
+        self.buffer.extend_from_slice(value);
+    }
+
     fn write_str(&mut self, value: &str) {
         let str_size: u32 = value.as_bytes().len() as u32;
         self.write_u16(str_size as u16);
@@ -819,6 +868,22 @@ impl PosLock {
             client_id: client_id.to_string(),
         }
     }
+
+    pub fn get_type(&self) -> u8 {
+        self.lock_type
+    }
+
+    pub fn get_start(&self) -> u64 {
+        self.start
+    }
+
+    pub fn get_length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn get_proc_id(&self) -> u32 {
+        self.proc_id
+    }
 }
 
 pub struct DirEntry {
@@ -927,6 +992,19 @@ impl FileAttr {
     pub fn get_blk_num(&self) -> u64 {
         self.n_blk
     }
+
+    /// The user and group IDs of the owner.
+    pub fn get_owner(&self) -> (u32, u32) {
+        (self.uid, self.gid)
+    }
+
+    /// The last access and modification times.
+    pub fn get_times(&self) -> (Duration, Duration) {
+        (
+            Duration::new(self.atime_sec, self.atime_ns as u32),
+            Duration::new(self.mtime_sec, self.mtime_ns as u32),
+        )
+    }
 }
 
 pub struct LStatFs {
@@ -1026,6 +1104,19 @@ impl UStatFs {
         0
     }
 
+    /// The numeric user and group IDs of the owner.
+    pub fn get_owner(&self) -> (u32, u32) {
+        (self.n_uid, self.n_gid)
+    }
+
+    /// The last access and modification times.
+    pub fn get_times(&self) -> (Duration, Duration) {
+        (
+            Duration::from_secs(self.atime as u64),
+            Duration::from_secs(self.mtime as u64),
+        )
+    }
+
     pub fn get_name(&self) -> String {
         self.name.clone()
     }
@@ -10,14 +10,18 @@
 //! 9P filesystem used by [Ruxos](https://github.com/syswonder/ruxos).
 //!
 //! The implementation is based on [`axfs_vfs`].
+use crate::cache::{self, Dentry, FsCache, PageCache, PAGE_SIZE};
 use crate::drv::{self, Drv9pOps};
-use alloc::{string::String, string::ToString, sync::Arc, sync::Weak, vec::Vec};
+use crate::CacheMode;
+use alloc::{
+    collections::BTreeMap, string::String, string::ToString, sync::Arc, sync::Weak, vec::Vec,
+};
 use axfs_vfs::{
-    AbsPath, RelPath, VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef,
-    VfsNodeType, VfsOps, VfsResult,
+    AbsPath, RelPath, VfsDirEntry, VfsError, VfsFileLock, VfsLockType, VfsNodeAttr, VfsNodeOps,
+    VfsNodePerm, VfsNodeRef, VfsNodeType, VfsOps, VfsResult,
 };
 use log::*;
-use spin::{once::Once, RwLock};
+use spin::{once::Once, Mutex, RwLock};
 
 macro_rules! handle_result {
     ($result:expr, $error_msg:expr) => {
@@ -39,49 +43,93 @@ pub struct _9pFileSystem {
 }
 
 impl _9pFileSystem {
-    /// Create a new instance.
-    pub fn new(dev: Arc<RwLock<Drv9pOps>>, aname: &str, protocol: &str) -> Self {
+    /// Create a new instance, caching as `cache` with attributes and lookups valid for `actimeo`
+    /// seconds.
+    pub fn new(
+        dev: Arc<RwLock<Drv9pOps>>,
+        aname: &str,
+        uname: &str,
+        protocol: &str,
+        cache: CacheMode,
+        actimeo: u64,
+    ) -> VfsResult<Self> {
         // Initialize 9pfs version to make sure protocol is right.
-        // Select 9P2000.L at defealt first trial.
-        let mut protocol = protocol.to_string();
-        match dev.write().tversion(&protocol) {
-            Ok(protocol_server) => {
-                info!("9pfs server's protocol: {}", protocol_server);
-                if protocol != protocol_server {
-                    protocol = protocol_server;
-                }
-            }
-            Err(errcode) => {
-                error!("9pfs tversion failed! error code: {}", errcode);
-            }
-        }
+        let protocol = Self::negotiate_version(&dev, protocol)?;
 
         const AFID: u32 = 0xFFFF_FFFF;
 
         let fid = match dev.write().get_fid() {
             Some(id) => id,
             None => {
-                panic!("9pfs: No enough fids! Check fid_MAX constrant or fid leaky.");
+                error!("9pfs: No enough fids! Check fid_MAX constrant or fid leaky.");
+                return Err(VfsError::NoMemory);
             }
         };
 
         // AUTH afid
         #[cfg(feature = "need_auth")]
-        handle_result!(
-            dev.write().tauth(AFID, "ruxos", "/"),
-            "9pfs auth failed! error code: {}"
-        );
+        if let Err(errcode) = dev.write().tauth(AFID, uname, "/") {
+            error!("9pfs auth failed! error code: {}", errcode);
+            dev.write().recycle_fid(fid);
+            return Err(to_vfs_error(errcode));
+        }
 
         // attach dir to fid
-        handle_result!(
-            dev.write().tattach(fid, AFID, "ruxos", aname),
-            "9pfs attach failed! error code: {}"
-        );
+        if let Err(errcode) = dev.write().tattach(fid, AFID, uname, aname) {
+            error!("9pfs attach failed! error code: {}", errcode);
+            dev.write().recycle_fid(fid);
+            return Err(to_vfs_error(errcode));
+        }
 
-        Self {
+        Ok(Self {
             parent: Once::new(),
-            root: CommonNode::new(fid, None, dev.clone(), Arc::new(protocol.clone())),
+            root: CommonNode::new(
+                fid,
+                None,
+                None,
+                dev.clone(),
+                Arc::new(protocol.clone()),
+                Arc::new(FsCache::new(cache, actimeo)),
+            ),
+        })
+    }
+
+    /// Negotiates the protocol version and msize with the server.
+    ///
+    /// The configured protocol is tried first (9P2000.L if it's neither 9P2000.L nor 9P2000.u),
+    /// and the other one is tried if the server doesn't accept it.
+    fn negotiate_version(dev: &RwLock<Drv9pOps>, protocol: &str) -> VfsResult<String> {
+        let versions = match protocol {
+            "9P2000.u" => ["9P2000.u", "9P2000.L"],
+            "9P2000.L" => ["9P2000.L", "9P2000.u"],
+            _ => {
+                warn!("9pfs: unsupported protocol {:?}, try 9P2000.L", protocol);
+                ["9P2000.L", "9P2000.u"]
+            }
+        };
+        let mut err = VfsError::Unsupported;
+        for version in versions {
+            let result = dev.write().tversion(version);
+            match result {
+                Ok(protocol_server) if protocol_server == version => {
+                    info!(
+                        "9pfs server's protocol: {}, msize: {}",
+                        protocol_server,
+                        dev.read().msize()
+                    );
+                    return Ok(protocol_server);
+                }
+                Ok(protocol_server) => {
+                    warn!("9pfs server replied {} to {}", protocol_server, version)
+                }
+                Err(errcode) => {
+                    error!("9pfs tversion failed! error code: {}", errcode);
+                    err = to_vfs_error(errcode);
+                }
+            }
         }
+        error!("9pfs: no protocol version accepted by server");
+        Err(err)
     }
 }
 
@@ -109,19 +157,30 @@ pub struct CommonNode {
     inner: Arc<RwLock<Drv9pOps>>,
     fid: Arc<u32>,
     protocol: Arc<String>,
+    /// The qid path of the file, `None` for the root.
+    qid: Option<u64>,
+    cache: Arc<FsCache>,
+    /// The cached attributes and their expiry.
+    attr: Mutex<Option<(VfsNodeAttr, u64)>>,
+    /// The cached lookups of a directory.
+    dentries: Mutex<BTreeMap<String, Dentry>>,
+    /// The cached contents of a file.
+    data: Mutex<PageCache>,
 }
 
 impl CommonNode {
     pub(super) fn new(
         fid: u32,
+        qid: Option<u64>,
         parent: Option<Weak<dyn VfsNodeOps>>,
         dev: Arc<RwLock<Drv9pOps>>,
         protocol: Arc<String>,
+        cache: Arc<FsCache>,
     ) -> Arc<Self> {
         const O_RDWR: u8 = 0x02;
         const O_RDONLY: u8 = 0x00;
-        const EISDIR: u8 = 21;
-        const ELOOP: u8 = 40;
+        const EISDIR: u32 = 21;
+        const ELOOP: u32 = 40;
 
         let result = if *protocol == "9P2000.L" {
             dev.write().l_topen(fid, O_RDWR as u32)
@@ -152,7 +211,7 @@ impl CommonNode {
                     splited.insert(0, "..");
                     let try_walk = dev.write().twalk(fid, fid, splited.len() as u16, &splited);
                     match try_walk {
-                        Ok(_) => return Self::new(fid, parent, dev, protocol),
+                        Ok(_) => return Self::new(fid, qid, parent, dev, protocol, cache),
                         Err(ecode) => error!("9pfs twalk failed! error code: {}", ecode),
                     }
                 } else {
@@ -169,6 +228,11 @@ impl CommonNode {
             fid: Arc::new(fid),
             protocol,
             parent: RwLock::new(parent.unwrap_or_else(|| Weak::<Self>::new())),
+            qid,
+            cache,
+            attr: Mutex::new(None),
+            dentries: Mutex::new(BTreeMap::new()),
+            data: Mutex::new(PageCache::default()),
         })
     }
 
@@ -193,46 +257,42 @@ impl CommonNode {
                 panic!("9pfs: No enough fids! Check fid_MAX constrant or fid leaky.");
             }
         };
-        match ty {
+        let result = match ty {
             VfsNodeType::File => {
                 handle_result!(
                     self.inner.write().twalk(*self.fid, fid, 0, &[]),
                     "9pfs twalk failed! error code: {}"
                 );
                 if *self.protocol == "9P2000.L" {
-                    handle_result!(
-                        self.inner.write().l_tcreate(fid, name, 0x02, 0o100644, 500),
-                        "9pfs l_create failed! error code: {}"
-                    );
+                    self.inner.write().l_tcreate(fid, name, 0x02, 0o100644, 500)
                 } else if *self.protocol == "9P2000.u" {
-                    handle_result!(
-                        self.inner.write().u_tcreate(fid, name, 0o777, 0o02, ""),
-                        "9pfs create failed! error code: {}"
-                    );
+                    self.inner.write().u_tcreate(fid, name, 0o777, 0o02, "")
                 } else {
                     return Err(VfsError::Unsupported);
                 }
             }
             VfsNodeType::Dir => {
-                handle_result!(
-                    self.inner.write().tmkdir(*self.fid, name, 0o40755, 500),
-                    "9pfs mkdir failed! error code: {}"
-                );
+                let result = self.inner.write().tmkdir(*self.fid, name, 0o40755, 500);
                 handle_result!(
                     self.inner.write().twalk(*self.fid, fid, 1, &[&name]),
                     "9pfs twalk failed! error code: {}"
                 );
+                result
             }
             _ => return Err(VfsError::Unsupported),
-        }
+        };
 
         handle_result!(
             self.inner.write().tclunk(fid),
             "9pfs tclunk failed! error code: {}"
         );
         self.inner.write().recycle_fid(fid);
+        self.cache.names_changed();
 
-        Ok(())
+        result.map_err(|ecode| {
+            error!("9pfs create {} failed! error code: {}", name, ecode);
+            to_vfs_error(ecode)
+        })
     }
 
     fn try_get(&self, path: &RelPath) -> VfsResult<VfsNodeRef> {
@@ -249,6 +309,45 @@ impl CommonNode {
             return self.try_get(&rest.unwrap_or(RelPath::new("")));
         }
 
+        let node = self.lookup_name(name).map_err(|err| {
+            debug!("try_get failed {:?}=={}+{:?}", path, name, rest);
+            err
+        })?;
+        match rest {
+            Some(rpath) => node.try_get(&rpath),
+            None => Ok(node),
+        }
+    }
+
+    /// Looks up `name` in this directory, or this directory itself if `name` is empty.
+    fn lookup_name(&self, name: &str) -> VfsResult<Arc<CommonNode>> {
+        if !self.cache.caches_data() {
+            return self.walk(name);
+        }
+        if name.is_empty() {
+            return self.this.upgrade().ok_or(VfsError::BadState);
+        }
+        if !self.cache.caches_meta() {
+            return self.walk(name);
+        }
+
+        let generation = self.cache.generation();
+        if let Some(dentry) = self.dentries.lock().get(name) {
+            if dentry.generation == generation && cache::is_valid(dentry.expiry) {
+                return dentry.node.clone().ok_or(VfsError::NotFound);
+            }
+        }
+        let result = self.walk(name);
+        match &result {
+            Ok(node) => self.add_dentry(name, generation, Some(node)),
+            Err(VfsError::NotFound) => self.add_dentry(name, generation, None),
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// Walks to `name` in this directory on the server.
+    fn walk(&self, name: &str) -> VfsResult<Arc<CommonNode>> {
         let fid = match self.inner.write().get_fid() {
             Some(id) => id,
             None => {
@@ -257,7 +356,7 @@ impl CommonNode {
         };
 
         // get two new dfid for old dir and new dir.
-        const ENOENT: u8 = 2;
+        const ENOENT: u32 = 2;
         let result = if name.is_empty() {
             self.inner.write().twalk(*self.fid, fid, 0, &[])
         } else {
@@ -265,32 +364,72 @@ impl CommonNode {
         };
 
         match result {
-            Ok(_) => {
+            Ok(qid) => {
+                let qid = qid.map(|qid| qid.path());
+                let shared = qid.filter(|_| self.cache.caches_data());
+                // the file is already opened by another node
+                if let Some(node) = shared.and_then(|qid| self.cache.node(qid)) {
+                    handle_result!(
+                        self.inner.write().tclunk(fid),
+                        "9pfs tclunk failed! error code: {}"
+                    );
+                    self.inner.write().recycle_fid(fid);
+                    return Ok(node);
+                }
                 let node = CommonNode::new(
                     fid,
+                    qid,
                     Some(self.this.clone()),
                     self.inner.clone(),
                     self.protocol.clone(),
+                    self.cache.clone(),
                 );
-                match rest {
-                    Some(rpath) => node.try_get(&rpath),
-                    None => Ok(node),
-                }
+                Ok(match shared {
+                    Some(qid) => self.cache.share_node(qid, node),
+                    None => node,
+                })
             }
             // No such file or directory
             Err(ENOENT) => {
                 self.inner.write().recycle_fid(fid);
-                debug!("try_get failed {:?}=={}+{:?}", path, name, rest);
                 Err(VfsError::NotFound)
             }
             Err(ecode) => {
                 self.inner.write().recycle_fid(fid);
                 error!("Failed when getting node in 9pfs, ecode:{}", ecode);
-                Err(VfsError::BadState)
+                Err(to_vfs_error(ecode))
             }
         }
     }
 
+    /// Caches the lookup of `name`, which found `node` or nothing.
+    fn add_dentry(&self, name: &str, generation: u64, node: Option<&Arc<CommonNode>>) {
+        let node = match node {
+            Some(node) if self.cache.reserve_dentry() => Some(node.clone()),
+            Some(_) => return,
+            None => None,
+        };
+        let dentry = Dentry {
+            node,
+            generation,
+            expiry: self.cache.expiry(),
+        };
+        // the nodes dropped with the stale lookups are released after unlocking
+        let mut stale = Vec::new();
+        {
+            let mut dentries = self.dentries.lock();
+            let expired: Vec<String> = dentries
+                .iter()
+                .filter(|(_, d)| d.generation != generation || !cache::is_valid(d.expiry))
+                .map(|(name, _)| name.clone())
+                .collect();
+            stale.extend(expired.iter().filter_map(|name| dentries.remove(name)));
+            stale.extend(dentries.insert(name.to_string(), dentry));
+        }
+        let released = stale.iter().filter(|d| d.node.is_some()).count();
+        self.cache.release_dentries(released);
+    }
+
     fn get_in_9pfs(&self, path: &str) -> VfsResult<Arc<CommonNode>> {
         let splited: Vec<&str> = path
             .split('/')
@@ -312,22 +451,275 @@ impl CommonNode {
             .twalk(*self.fid, new_fid, splited.len() as u16, &splited);
 
         match result {
-            Ok(_) => Ok(CommonNode::new(
+            Ok(qid) => Ok(CommonNode::new(
                 new_fid,
+                qid.map(|qid| qid.path()),
                 Some(self.this.clone()),
                 self.inner.clone(),
                 self.protocol.clone(),
+                self.cache.clone(),
             )),
-            Err(_) => Err(VfsError::BadState),
+            Err(ecode) => {
+                self.inner.write().recycle_fid(new_fid);
+                Err(to_vfs_error(ecode))
+            }
+        }
+    }
+    /// Gets the attributes from the server.
+    fn fetch_attr(&self) -> VfsResult<VfsNodeAttr> {
+        if *self.protocol == "9P2000.L" {
+            let resp = self.inner.write().tgetattr(*self.fid, 0x3fff_u64);
+            debug!("get_attr {:?}", resp);
+            match resp {
+                Ok(stat) if stat.get_ftype() == 0o4 => {
+                    let mut attr = VfsNodeAttr::new_dir(
+                        stat.get_qid().path(),
+                        stat.get_size(),
+                        stat.get_blk_num(),
+                    );
+                    let mode = stat.get_perm() as u16 & 0o777_u16;
+                    attr.set_perm(VfsNodePerm::from_bits(mode).unwrap());
+                    let (uid, gid) = stat.get_owner();
+                    let (atime, mtime) = stat.get_times();
+                    Ok(attr.with_owner(uid, gid).with_times(atime, mtime))
+                }
+                Ok(stat) if stat.get_ftype() == 0o10 => {
+                    let mut attr = VfsNodeAttr::new_file(
+                        stat.get_qid().path(),
+                        stat.get_size(),
+                        stat.get_blk_num(),
+                    );
+                    let mode = stat.get_perm() as u16 & 0o777_u16;
+                    attr.set_perm(VfsNodePerm::from_bits(mode).unwrap());
+                    let (uid, gid) = stat.get_owner();
+                    let (atime, mtime) = stat.get_times();
+                    Ok(attr.with_owner(uid, gid).with_times(atime, mtime))
+                }
+                Err(ecode) => Err(to_vfs_error(ecode)),
+                _ => Err(VfsError::BadState),
+            }
+        } else if *self.protocol == "9P2000.u" {
+            let resp = self.inner.write().tstat(*self.fid);
+            match resp {
+                Ok(stat) if stat.get_ftype() == 0o4 => {
+                    let mut attr = VfsNodeAttr::new_dir(
+                        stat.get_qid().path(),
+                        stat.get_length(),
+                        stat.get_blk_num(),
+                    );
+                    let mode = stat.get_perm() as u16 & 0o777_u16;
+                    attr.set_perm(VfsNodePerm::from_bits(mode).unwrap());
+                    let (uid, gid) = stat.get_owner();
+                    let (atime, mtime) = stat.get_times();
+                    Ok(attr.with_owner(uid, gid).with_times(atime, mtime))
+                }
+                Ok(stat) if stat.get_ftype() == 0o10 => {
+                    let mut attr = VfsNodeAttr::new_file(
+                        stat.get_qid().path(),
+                        stat.get_length(),
+                        stat.get_blk_num(),
+                    );
+                    let mode = stat.get_perm() as u16 & 0o777_u16;
+                    attr.set_perm(VfsNodePerm::from_bits(mode).unwrap());
+                    let (uid, gid) = stat.get_owner();
+                    let (atime, mtime) = stat.get_times();
+                    Ok(attr.with_owner(uid, gid).with_times(atime, mtime))
+                }
+                Err(ecode) => Err(to_vfs_error(ecode)),
+                _ => Err(VfsError::BadState),
+            }
+        } else {
+            Err(VfsError::Unsupported)
+        }
+    }
+
+    /// Overrides the size in `attr` by the one seen through the page cache.
+    fn with_cached_size(&self, attr: VfsNodeAttr) -> VfsNodeAttr {
+        if !attr.is_file() {
+            return attr;
+        }
+        match self.data.lock().size() {
+            Some(size) => {
+                let mut cached = VfsNodeAttr::new(
+                    attr.ino(),
+                    attr.perm(),
+                    attr.file_type(),
+                    size,
+                    attr.blocks(),
+                );
+                if let Some((uid, gid)) = attr.owner() {
+                    cached = cached.with_owner(uid, gid);
+                }
+                if let Some((atime, mtime)) = attr.times() {
+                    cached = cached.with_times(atime, mtime);
+                }
+                cached
+            }
+            None => attr,
+        }
+    }
+
+    /// Gets the size of the file in the page cache, from the server if it's not known yet.
+    fn cached_size(&self, data: &mut PageCache) -> VfsResult<u64> {
+        match data.size() {
+            Some(size) => Ok(size),
+            None => {
+                let size = self.fetch_attr()?.size();
+                data.set_size(size);
+                Ok(size)
+            }
+        }
+    }
+
+    /// Reads the page at `index` from the server, leaving zeros after the end of the file.
+    fn read_page(&self, index: u64, page: &mut [u8]) -> VfsResult {
+        self.read_direct(index * PAGE_SIZE as u64, page).map(|_| ())
+    }
+
+    /// Reads through the page cache.
+    fn cached_read(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut data = self.data.lock();
+        let size = self.cached_size(&mut data)?;
+        if offset >= size {
+            return Ok(0);
+        }
+        let end = size.min(offset + buf.len() as u64);
+        let mut pos = offset;
+        while pos < end {
+            let index = pos / PAGE_SIZE as u64;
+            let in_page = (pos % PAGE_SIZE as u64) as usize;
+            let len = (PAGE_SIZE - in_page).min((end - pos) as usize);
+            let page = data.page(index, |page| self.read_page(index, page))?;
+            let buf_pos = (pos - offset) as usize;
+            buf[buf_pos..buf_pos + len].copy_from_slice(&page[in_page..in_page + len]);
+            pos += len as u64;
+        }
+        Ok((end - offset) as usize)
+    }
+
+    /// Writes into the page cache, which is written back later.
+    fn cached_write(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut data = self.data.lock();
+        let size = self.cached_size(&mut data)?;
+        let end = offset + buf.len() as u64;
+        let mut pos = offset;
+        while pos < end {
+            let index = pos / PAGE_SIZE as u64;
+            let in_page = (pos % PAGE_SIZE as u64) as usize;
+            let len = (PAGE_SIZE - in_page).min((end - pos) as usize);
+            // a page that is overwritten or after the end of the file needs not be read
+            let page = if len == PAGE_SIZE || index * PAGE_SIZE as u64 >= size {
+                data.page(index, |_| Ok::<_, VfsError>(()))?
+            } else {
+                data.page(index, |page| self.read_page(index, page))?
+            };
+            let buf_pos = (pos - offset) as usize;
+            page[in_page..in_page + len].copy_from_slice(&buf[buf_pos..buf_pos + len]);
+            data.mark_dirty(index);
+            pos += len as u64;
+        }
+        if end > size {
+            data.set_size(end);
         }
+        if data.too_dirty() {
+            self.write_back(&mut data)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Writes the dirty pages back to the server.
+    fn write_back(&self, data: &mut PageCache) -> VfsResult {
+        let io_unit = self.inner.read().io_unit() as usize;
+        data.write_back(io_unit, |offset, buf| {
+            match self.write_direct(offset, buf)? {
+                len if len == buf.len() => Ok(()),
+                _ => Err(VfsError::Io),
+            }
+        })
+    }
+
+    /// Truncates the file on the server.
+    fn truncate_direct(&self, size: u64) -> VfsResult {
+        if *self.protocol == "9P2000.L" {
+            let mut attr = drv::FileAttr::new();
+            attr.set_size(size);
+            self.inner
+                .write()
+                .tsetattr(*self.fid, attr)
+                .map_err(to_vfs_error)
+        } else if *self.protocol == "9P2000.u" {
+            let resp = self.inner.write().tstat(*self.fid);
+            let mut stat = resp.map_err(to_vfs_error)?;
+            stat.set_length(size);
+            self.inner
+                .write()
+                .twstat(*self.fid, stat)
+                .map_err(to_vfs_error)
+        } else {
+            error!("{} is not supported", self.protocol);
+            Err(VfsError::Unsupported)
+        }
+    }
+
+    /// Read data from the file at the given offset, split into requests of at most msize.
+    fn read_direct(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut dev = self.inner.write();
+        let mut read_len = buf.len();
+        let mut offset_ptr = 0;
+        while read_len > 0 {
+            let target_buf = &mut buf[offset_ptr..];
+            let rlen = match dev.tread(*self.fid, offset + offset_ptr as u64, target_buf) {
+                Ok(rlen) => rlen,
+                Err(ecode) => return Err(to_vfs_error(ecode)),
+            };
+            if rlen == 0 {
+                return Ok(offset_ptr);
+            }
+            read_len -= rlen;
+            offset_ptr += rlen;
+        }
+        Ok(buf.len())
+    }
+
+    /// Write data to the file at the given offset, split into requests of at most msize.
+    fn write_direct(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut dev = self.inner.write();
+        let mut write_len = buf.len();
+        let mut offset_ptr = 0;
+        while write_len > 0 {
+            let target_buf = &buf[offset_ptr..];
+            let wlen = match dev.twrite(*self.fid, offset + offset_ptr as u64, target_buf) {
+                Ok(writed_length) => writed_length,
+                Err(ecode) => return Err(to_vfs_error(ecode)),
+            };
+            if wlen == 0 {
+                return Ok(offset_ptr);
+            }
+            write_len -= wlen;
+            offset_ptr += wlen;
+        }
+
+        Ok(buf.len())
     }
 }
 
 impl Drop for CommonNode {
     fn drop(&mut self) {
+        // e.g. the pages of a shared mapping written back after the file is closed
+        if let Err(err) = self.write_back(&mut self.data.lock()) {
+            error!("9pfs(fid={}) write back failed: {:?}", *self.fid, err);
+        }
+        let kept = self
+            .dentries
+            .lock()
+            .values()
+            .filter(|d| d.node.is_some())
+            .count();
+        self.cache.release_dentries(kept);
+
         // pay attention to AA-deadlock
         let result = self.inner.write().tclunk(*self.fid);
-        const ENOENT: u8 = 2;
+        const ENOENT: u32 = 2;
         match result {
             Ok(_) | Err(ENOENT) => {
                 self.inner.write().recycle_fid(*self.fid);
@@ -392,48 +784,24 @@ impl VfsNodeOps for CommonNode {
             }
             src_fnode.unlink(&RelPath::new(""))?;
         }
+        self.cache.names_changed();
         Ok(())
     }
 
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        if *self.protocol == "9P2000.L" {
-            let resp = self.inner.write().tgetattr(*self.fid, 0x3fff_u64);
-            debug!("get_attr {:?}", resp);
-            match resp {
-                Ok(stat) if stat.get_ftype() == 0o4 => {
-                    let mut attr = VfsNodeAttr::new_dir(0, stat.get_size(), stat.get_blk_num());
-                    let mode = stat.get_perm() as u16 & 0o777_u16;
-                    attr.set_perm(VfsNodePerm::from_bits(mode).unwrap());
-                    Ok(attr)
-                }
-                Ok(stat) if stat.get_ftype() == 0o10 => {
-                    let mut attr = VfsNodeAttr::new_file(0, stat.get_size(), stat.get_blk_num());
-                    let mode = stat.get_perm() as u16 & 0o777_u16;
-                    attr.set_perm(VfsNodePerm::from_bits(mode).unwrap());
-                    Ok(attr)
-                }
-                _ => Err(VfsError::BadState),
-            }
-        } else if *self.protocol == "9P2000.u" {
-            let resp = self.inner.write().tstat(*self.fid);
-            match resp {
-                Ok(stat) if stat.get_ftype() == 0o4 => {
-                    let mut attr = VfsNodeAttr::new_dir(0, stat.get_length(), stat.get_blk_num());
-                    let mode = stat.get_perm() as u16 & 0o777_u16;
-                    attr.set_perm(VfsNodePerm::from_bits(mode).unwrap());
-                    Ok(attr)
-                }
-                Ok(stat) if stat.get_ftype() == 0o10 => {
-                    let mut attr = VfsNodeAttr::new_file(0, stat.get_length(), stat.get_blk_num());
-                    let mode = stat.get_perm() as u16 & 0o777_u16;
-                    attr.set_perm(VfsNodePerm::from_bits(mode).unwrap());
-                    Ok(attr)
+        if self.cache.caches_meta() {
+            let cached = *self.attr.lock();
+            if let Some((attr, expiry)) = cached {
+                if cache::is_valid(expiry) {
+                    return Ok(self.with_cached_size(attr));
                 }
-                _ => Err(VfsError::BadState),
             }
-        } else {
-            Err(VfsError::Unsupported)
         }
+        let attr = self.fetch_attr()?;
+        if self.cache.caches_meta() {
+            *self.attr.lock() = Some((attr, self.cache.expiry()));
+        }
+        Ok(self.with_cached_size(attr))
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
@@ -453,14 +821,14 @@ impl VfsNodeOps for CommonNode {
                 Ok(contents) => contents,
                 Err(errcode) => {
                     error!("9pfs treaddir failed! error code: {}", errcode);
-                    return Err(VfsError::BadState);
+                    return Err(to_vfs_error(errcode));
                 }
             },
             "9P2000.u" => match self.inner.write().u_treaddir(*self.fid) {
                 Ok(contents) => contents,
                 Err(errcode) => {
                     error!("9pfs u_treaddir failed! error code: {}", errcode);
-                    return Err(VfsError::BadState);
+                    return Err(to_vfs_error(errcode));
                 }
             },
             _ => {
@@ -515,10 +883,18 @@ impl VfsNodeOps for CommonNode {
     fn unlink(&self, path: &RelPath) -> VfsResult {
         debug!("unlink at 9pfs: {}", path);
         match split_path(path) {
-            ("", None) | (".", None) => match self.inner.write().tremove(*self.fid) {
-                Ok(_) => Ok(()),
-                Err(_) => Err(VfsError::BadState),
-            },
+            ("", None) | (".", None) => {
+                let result = self.inner.write().tremove(*self.fid);
+                if result.is_ok() {
+                    // nothing to write back, and the qid may be reused by a new file
+                    *self.data.lock() = PageCache::default();
+                    if let Some(qid) = self.qid {
+                        self.cache.forget_node(qid);
+                    }
+                }
+                self.cache.names_changed();
+                result.map_err(to_vfs_error)
+            }
             _ => self.try_get(path)?.unlink(&RelPath::new("")),
         }
     }
@@ -527,84 +903,135 @@ impl VfsNodeOps for CommonNode {
     /// Truncate the file to the given size.
     fn truncate(&self, size: u64) -> VfsResult {
         debug!("9pfs truncating, size:{}", size);
-        if *self.protocol == "9P2000.L" {
-            let mut attr = drv::FileAttr::new();
-            attr.set_size(size);
-            match self.inner.write().tsetattr(*self.fid, attr) {
-                Ok(_) => Ok(()),
-                Err(_) => Err(VfsError::BadState),
-            }
-        } else if *self.protocol == "9P2000.u" {
-            let resp = self.inner.write().tstat(*self.fid);
-            let mut stat = match resp {
-                Ok(state) => state,
-                Err(_) => return Err(VfsError::BadState),
-            };
-            stat.set_length(size);
-            match self.inner.write().twstat(*self.fid, stat) {
-                Ok(_) => Ok(()),
-                Err(_) => Err(VfsError::BadState),
-            }
-        } else {
-            error!("{} is not supported", self.protocol);
-            Err(VfsError::Unsupported)
+        let mut data = self.data.lock();
+        self.truncate_direct(size)?;
+        if self.cache.caches_data() {
+            data.truncate(size);
         }
+        *self.attr.lock() = None;
+        Ok(())
     }
 
-    /// Read data from the file at the given offset.
+    /// Read data from the file at the given offset, from the page cache if any.
     fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
         debug!("read 9pid:{} length: {}", self.fid, buf.len());
-        let mut dev = self.inner.write();
-        let mut read_len = buf.len();
-        let mut offset_ptr = 0;
-        while read_len > 0 {
-            let target_buf = &mut buf[offset_ptr..];
-            let rlen = match dev.tread(*self.fid, offset + offset_ptr as u64, read_len as u32) {
-                Ok(content) => {
-                    let read_len = content.len();
-                    target_buf[..read_len].copy_from_slice(&content);
-                    read_len
-                }
-                Err(_) => return Err(VfsError::BadState),
-            };
-            if rlen == 0 {
-                return Ok(offset_ptr);
-            }
-            read_len -= rlen;
-            offset_ptr += rlen;
+        if self.cache.caches_data() {
+            self.cached_read(offset, buf)
+        } else {
+            self.read_direct(offset, buf)
         }
-        Ok(buf.len())
     }
 
-    /// Write data to the file at the given offset.
+    /// Write data to the file at the given offset, into the page cache if any.
     fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
         debug!("write 9pid:{} length: {}", self.fid, buf.len());
-        let mut dev = self.inner.write();
-        let mut write_len = buf.len();
-        let mut offset_ptr = 0;
-        while write_len > 0 {
-            let target_buf = &buf[offset_ptr..];
-            let wlen = match dev.twrite(*self.fid, offset + offset_ptr as u64, target_buf) {
-                Ok(writed_length) => writed_length,
-                Err(_) => return Err(VfsError::BadState),
-            };
-            if wlen == 0 {
-                return Ok(offset_ptr);
-            }
-            write_len -= wlen;
-            offset_ptr += wlen;
+        if self.cache.caches_data() {
+            self.cached_write(offset, buf)
+        } else {
+            self.write_direct(offset, buf)
         }
+    }
 
-        Ok(buf.len())
+    /// Read data from the server, after writing back the dirty pages so that it sees them.
+    fn read_at_direct(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.write_back(&mut self.data.lock())?;
+        self.read_direct(offset, buf)
+    }
+
+    /// Write data to the server, dropping the cached pages it overwrites.
+    fn write_at_direct(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut data = self.data.lock();
+        self.write_back(&mut data)?;
+        let len = self.write_direct(offset, buf)?;
+        data.written_around(offset, offset + len as u64);
+        Ok(len)
     }
 
     /// Flush the file, synchronize the data to disk.
     fn fsync(&self) -> VfsResult {
+        self.write_back(&mut self.data.lock())?;
         let mut dev = self.inner.write();
-        match dev.tfsync(*self.fid) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(VfsError::BadState),
+        dev.tfsync(*self.fid).map_err(to_vfs_error)
+    }
+
+    /// Drops the clean pages in `cache=mmap`, to see the changes by other clients.
+    fn open(&self) -> VfsResult {
+        if self.cache.revalidates_on_open() {
+            self.data.lock().invalidate();
         }
+        Ok(())
+    }
+
+    /// Writes back the dirty pages when the file is closed.
+    fn release(&self) -> VfsResult {
+        self.write_back(&mut self.data.lock())
+    }
+
+    /// Places or removes a byte-range lock on the server, only in 9P2000.L.
+    fn set_lock(&self, lock: VfsFileLock) -> VfsResult {
+        if *self.protocol != "9P2000.L" {
+            return Err(VfsError::Unsupported);
+        }
+        // never ask the server to block, waiting is done by the caller
+        let resp = self.inner.write().tlock(*self.fid, 0, to_pos_lock(&lock));
+        match resp {
+            Ok(drv::_9P_LOCK_SUCCESS) => Ok(()),
+            Ok(drv::_9P_LOCK_BLOCKED) | Ok(drv::_9P_LOCK_GRACE) => Err(VfsError::WouldBlock),
+            Ok(_) => Err(VfsError::BadState),
+            Err(ecode) => Err(to_vfs_error(ecode)),
+        }
+    }
+
+    /// Gets the first conflicting lock on the server, only in 9P2000.L.
+    fn get_lock(&self, lock: VfsFileLock) -> VfsResult<Option<VfsFileLock>> {
+        if *self.protocol != "9P2000.L" {
+            return Err(VfsError::Unsupported);
+        }
+        let resp = self.inner.write().tgetlock(*self.fid, to_pos_lock(&lock));
+        let locker = resp.map_err(to_vfs_error)?;
+        let ty = match locker.get_type() {
+            drv::_9P_LOCK_TYPE_RDLCK => VfsLockType::Read,
+            drv::_9P_LOCK_TYPE_WRLCK => VfsLockType::Write,
+            _ => return Ok(None),
+        };
+        Ok(Some(VfsFileLock {
+            ty,
+            start: locker.get_start(),
+            len: locker.get_length(),
+            pid: locker.get_proc_id(),
+        }))
+    }
+}
+
+/// Client id sent with lock requests.
+const LOCK_CLIENT_ID: &str = "ruxos";
+
+fn to_pos_lock(lock: &VfsFileLock) -> drv::PosLock {
+    let lock_type = match lock.ty {
+        VfsLockType::Read => drv::_9P_LOCK_TYPE_RDLCK,
+        VfsLockType::Write => drv::_9P_LOCK_TYPE_WRLCK,
+        VfsLockType::Unlock => drv::_9P_LOCK_TYPE_UNLCK,
+    };
+    drv::PosLock::new(lock_type, lock.start, lock.len, lock.pid, LOCK_CLIENT_ID)
+}
+
+/// Maps the errno replied by the 9P server to [`VfsError`].
+pub(super) fn to_vfs_error(ecode: u32) -> VfsError {
+    match ecode {
+        1 => VfsError::OperationNotPermitted, // EPERM
+        2 => VfsError::NotFound,              // ENOENT
+        11 => VfsError::WouldBlock,           // EAGAIN
+        12 => VfsError::NoMemory,             // ENOMEM
+        13 => VfsError::PermissionDenied,     // EACCES
+        16 => VfsError::ResourceBusy,         // EBUSY
+        17 => VfsError::AlreadyExists,        // EEXIST
+        20 => VfsError::NotADirectory,        // ENOTDIR
+        21 => VfsError::IsADirectory,         // EISDIR
+        22 => VfsError::InvalidInput,         // EINVAL
+        28 => VfsError::StorageFull,          // ENOSPC
+        38 | 95 => VfsError::Unsupported,     // ENOSYS, EOPNOTSUPP
+        39 => VfsError::DirectoryNotEmpty,    // ENOTEMPTY
+        _ => VfsError::Io,
     }
 }
 
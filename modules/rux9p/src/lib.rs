@@ -22,74 +22,128 @@
 extern crate alloc;
 extern crate log;
 
+mod cache;
 mod drv;
 mod fs;
 #[cfg(feature = "net-9p")]
 mod netdev;
+mod options;
 
-use alloc::{borrow::ToOwned, sync::Arc};
+#[cfg(test)]
+mod tests;
+
+pub use options::CacheMode;
+
+use alloc::{sync::Arc, vec::Vec};
+use axerrno::{ax_err, AxResult};
+use axfs_vfs::VfsOps;
 use log::*;
-use ruxfs::{root::MountPoint, AbsPath};
+use ruxdriver::prelude::*;
 use spin::RwLock;
 
-#[cfg(feature = "virtio-9p")]
-use ruxdriver::{prelude::*, AxDeviceContainer};
-#[cfg(feature = "net-9p")]
-use {
-    alloc::{boxed::Box, vec::Vec},
-    core::option::Option::Some,
-    driver_common::BaseDriverOps,
-};
+use self::options::{MountOptions, Trans};
 
+#[cfg(feature = "net-9p")]
+use alloc::boxed::Box;
 #[cfg(feature = "virtio-9p")]
-/// Initializes filesystems by 9pfs devices.
-pub fn init_virtio_9pfs(
-    mut v9p_devs: AxDeviceContainer<Ax9pDevice>,
-    aname: &str,
-    protocol: &str,
-) -> MountPoint {
-    info!("Initialize virtio 9pfs...");
+use {ruxdriver::AxDeviceContainer, spin::Mutex};
 
-    let v9p = v9p_devs.take_one().expect("No 9pfs device found!");
-    info!("  use 9pfs device 0: {:?}", v9p.device_name());
-
-    let v9p_driver = self::drv::Drv9pOps::new(v9p);
-    let v9p_fs = self::fs::_9pFileSystem::new(Arc::new(RwLock::new(v9p_driver)), aname, protocol);
+/// The virtio-9p devices, which are in use while a mount holds another reference to them.
+#[cfg(feature = "virtio-9p")]
+static VIRTIO_DEVS: Mutex<Vec<Arc<RwLock<Ax9pDevice>>>> = Mutex::new(Vec::new());
+
+/// Registers the `9p` filesystem type, which mounts like
+/// `mount -t 9p -o <options> <source> <target>`.
+///
+/// With `trans=virtio`, the source is the mount tag of a virtio-9p device (any device if it's
+/// empty), which can be mounted only once at a time. With `trans=tcp`, the source is the IPv4
+/// address of the server, optionally followed by `:<port>`.
+///
+/// The options are `trans`, `port`, `aname`, `uname`, `msize`, `version`, and `cache` with
+/// `actimeo` (see [`CacheMode`]).
+pub fn init() {
+    ruxfs::fstype::register("9p", new_9pfs);
+}
 
-    MountPoint::new(AbsPath::new_owned("/v9fs".to_owned()), Arc::new(v9p_fs))
+#[cfg(feature = "virtio-9p")]
+/// Keeps the virtio-9p devices for the mounts with `trans=virtio`.
+pub fn add_virtio_devices(mut v9p_devs: AxDeviceContainer<Ax9pDevice>) {
+    let mut devs = VIRTIO_DEVS.lock();
+    while let Some(dev) = v9p_devs.take_one() {
+        info!(
+            "  found 9pfs device {:?} with mount tag {:?}",
+            dev.device_name(),
+            dev.mount_tag()
+        );
+        devs.push(Arc::new(RwLock::new(dev)));
+    }
 }
 
-#[cfg(feature = "net-9p")]
-/// Initializes filesystems by 9pfs devices.
-pub fn init_net_9pfs(ip_port: &str, aname: &str, protocol: &str) -> MountPoint {
-    info!("Initialize net 9pfs...");
+#[cfg(feature = "virtio-9p")]
+/// Takes the free virtio-9p device whose mount tag is `tag`.
+///
+/// The device is free again once the mount using it fails or is unmounted, and the
+/// filesystem drops its reference.
+fn take_virtio_device(tag: &str) -> AxResult<Arc<RwLock<Ax9pDevice>>> {
+    let devs = VIRTIO_DEVS.lock();
+    match devs.iter().find(|dev| {
+        Arc::strong_count(dev) == 1 && (tag.is_empty() || dev.read().mount_tag() == Some(tag))
+    }) {
+        Some(dev) => Ok(dev.clone()),
+        None => ax_err!(
+            NotFound,
+            "9pfs: no free virtio-9p device with the mount tag"
+        ),
+    }
+}
 
-    let net9p = match parse_address(ip_port) {
-        Some((ip, port)) => self::netdev::Net9pDev::new(&ip, port),
-        None => self::netdev::Net9pDev::new(&[127, 0, 0, 1], 564_u16), // use 127.0.0.1:564 in defealt
+fn new_9pfs(source: &str, options: &str) -> AxResult<Arc<dyn VfsOps>> {
+    let opts = MountOptions::parse(options)?;
+    let dev: Arc<RwLock<Ax9pDevice>> = match opts.trans {
+        #[cfg(feature = "virtio-9p")]
+        Trans::Virtio => take_virtio_device(source)?,
+        #[cfg(feature = "net-9p")]
+        Trans::Tcp => {
+            let (ip, port) = match parse_address(source, opts.port) {
+                Some(address) => address,
+                None => return ax_err!(InvalidInput, "9pfs: invalid server address"),
+            };
+            let net9p = self::netdev::Net9pDev::new(&ip, port);
+            // Enabling `dyn` feature in ruxdriver, pub type Ax9pDevice = Box<dyn _9pDriverOps>;
+            let net9p: Ax9pDevice = Box::new(net9p);
+            Arc::new(RwLock::new(net9p))
+        }
+        #[allow(unreachable_patterns)]
+        _ => return ax_err!(Unsupported, "9pfs: transport not enabled"),
     };
-    info!("use 9pfs device 0: {:?}", net9p.device_name());
+    info!("Mount 9pfs {:?} by {:?}", source, dev.read().device_name());
 
-    // Enabling `dyn` feature in ruxdriver, pub type Ax9pDevice = Box<dyn _9pDriverOps>;
-    // TODO: consider a more elegant implement.
-    let net9p_driver = self::drv::Drv9pOps::new(Box::new(net9p));
-    let n9p_fs = self::fs::_9pFileSystem::new(Arc::new(RwLock::new(net9p_driver)), aname, protocol);
-
-    MountPoint::new(AbsPath::new_owned("/n9fs".to_owned()), Arc::new(n9p_fs))
+    let driver = match self::drv::Drv9pOps::new(dev, opts.msize) {
+        Ok(driver) => driver,
+        Err(_) => return ax_err!(ConnectionRefused, "9pfs: transport init failed"),
+    };
+    let fs = self::fs::_9pFileSystem::new(
+        Arc::new(RwLock::new(driver)),
+        &opts.aname,
+        &opts.uname,
+        opts.version,
+        opts.cache,
+        opts.actimeo,
+    )?;
+    Ok(Arc::new(fs))
 }
 
+/// Parses `<ip>[:<port>]`, with `default_port` if the port is omitted.
 #[cfg(feature = "net-9p")]
-fn parse_address(s: &str) -> Option<(Vec<u8>, u16)> {
-    let mut parts = s.split(':');
-    if let (Some(address), Some(port)) = (parts.next(), parts.next()) {
-        let parsed_address: Result<Vec<u8>, _> =
-            address.split('.').map(|part| part.parse::<u8>()).collect();
-
-        if let Ok(address_parts) = parsed_address {
-            if let Ok(port) = port.parse::<u16>() {
-                return Some((address_parts, port));
-            }
-        }
-    }
-    None
+fn parse_address(s: &str, default_port: u16) -> Option<(Vec<u8>, u16)> {
+    let (address, port) = match s.split_once(':') {
+        Some((address, port)) => (address, port.parse::<u16>().ok()?),
+        None => (s, default_port),
+    };
+    let address: Vec<u8> = address
+        .split('.')
+        .map(|part| part.parse::<u8>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+    Some((address, port))
 }
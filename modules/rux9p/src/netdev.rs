@@ -71,15 +71,32 @@ impl _9pDriverOps for Net9pDev {
                 return Err(0);
             }
         }
-        match self.socket.lock().recv(outputs, 0) {
-            Ok(length) => {
-                debug!("net9p recv successfully,length = {}", length);
-                Ok(length as u32)
+        // a response may arrive in several segments, receive until its size[4] is satisfied.
+        let mut length = 0;
+        loop {
+            match self.socket.lock().recv(&mut outputs[length..], 0) {
+                Ok(0) => {
+                    error!("net9p connection closed");
+                    return Err(0);
+                }
+                Ok(recv_len) => length += recv_len,
+                Err(_) => {
+                    error!("net9p recv failed");
+                    return Err(0);
+                }
             }
-            Err(_) => {
-                error!("net9p recv failed");
-                Err(0)
+            if length >= 4 {
+                let size = u32::from_le_bytes([outputs[0], outputs[1], outputs[2], outputs[3]]);
+                if length >= (size as usize).min(outputs.len()) {
+                    break;
+                }
             }
         }
+        debug!("net9p recv successfully,length = {}", length);
+        Ok(length as u32)
+    }
+
+    fn max_msize(&self) -> u32 {
+        64 * 1024
     }
 }
@@ -0,0 +1,121 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Options of a 9P mount, named as the ones of Linux's v9fs.
+
+use alloc::string::{String, ToString};
+use axerrno::{ax_err, AxResult};
+use log::*;
+
+/// The transport carrying 9P messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trans {
+    /// A virtio-9p device, selected by its mount tag.
+    Virtio,
+    /// A TCP connection to a server.
+    Tcp,
+}
+
+/// How file data and metadata are cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Every operation goes to the server.
+    None,
+    /// Attributes and lookups are cached for `actimeo` seconds, and file data until the node is
+    /// dropped.
+    Loose,
+    /// Only file data is cached, so that shared mappings are coherent, and it's re-read after
+    /// each open.
+    Mmap,
+}
+
+/// The parsed options of a 9P mount.
+#[derive(Debug, Clone)]
+pub struct MountOptions {
+    pub trans: Trans,
+    pub port: u16,
+    pub aname: String,
+    pub uname: String,
+    /// The msize to propose, 0 for the max of the transport.
+    pub msize: u32,
+    pub version: &'static str,
+    pub cache: CacheMode,
+    /// How long attributes and lookups are cached in `cache=loose`, in seconds.
+    pub actimeo: u64,
+}
+
+impl Default for MountOptions {
+    fn default() -> Self {
+        Self {
+            trans: if cfg!(feature = "virtio-9p") {
+                Trans::Virtio
+            } else {
+                Trans::Tcp
+            },
+            port: 564,
+            aname: String::new(),
+            uname: "ruxos".to_string(),
+            msize: 0,
+            version: "9P2000.L",
+            cache: CacheMode::None,
+            actimeo: 1,
+        }
+    }
+}
+
+impl MountOptions {
+    /// Parses comma-separated options like `trans=virtio,version=9p2000.L,msize=65536`.
+    ///
+    /// Options that have no effect here are ignored with a warning.
+    pub fn parse(options: &str) -> AxResult<Self> {
+        let mut opts = Self::default();
+        for (key, value) in ruxfs::fstype::parse_options(options) {
+            match key {
+                "trans" => {
+                    opts.trans = match value {
+                        "virtio" => Trans::Virtio,
+                        "tcp" => Trans::Tcp,
+                        _ => return ax_err!(InvalidInput, "9pfs: unknown transport"),
+                    }
+                }
+                "port" => opts.port = parse_num(key, value)?,
+                "aname" => opts.aname = value.to_string(),
+                "uname" => opts.uname = value.to_string(),
+                "msize" => opts.msize = parse_num(key, value)?,
+                "version" => {
+                    opts.version = if value.eq_ignore_ascii_case("9p2000.L") {
+                        "9P2000.L"
+                    } else if value.eq_ignore_ascii_case("9p2000.u") {
+                        "9P2000.u"
+                    } else {
+                        return ax_err!(InvalidInput, "9pfs: unsupported protocol version");
+                    }
+                }
+                "cache" => {
+                    opts.cache = match value {
+                        "none" => CacheMode::None,
+                        "loose" => CacheMode::Loose,
+                        "mmap" => CacheMode::Mmap,
+                        _ => return ax_err!(InvalidInput, "9pfs: unknown cache mode"),
+                    }
+                }
+                "actimeo" => opts.actimeo = parse_num(key, value)?,
+                _ => warn!("9pfs: ignore mount option {:?}", key),
+            }
+        }
+        Ok(opts)
+    }
+}
+
+fn parse_num<T: core::str::FromStr>(key: &str, value: &str) -> AxResult<T> {
+    value.parse().or_else(|_| {
+        error!("9pfs: invalid value {:?} of {}", value, key);
+        ax_err!(InvalidInput)
+    })
+}
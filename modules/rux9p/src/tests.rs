@@ -0,0 +1,319 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+use axfs_vfs::VfsError;
+
+use crate::cache::{PageCache, MAX_CLEAN_PAGES, MAX_DIRTY_PAGES, PAGE_SIZE};
+use crate::drv::{negotiated_msize, proposed_msize, response_errno};
+use crate::fs::to_vfs_error;
+use crate::options::MountOptions;
+
+const RLERROR: u8 = 7;
+const RERROR: u8 = 107;
+const RREAD: u8 = 117;
+
+/// Builds a response of type `rtype` with tag 0 and the body `body`.
+fn response(rtype: u8, body: &[u8]) -> Vec<u8> {
+    let size = (7 + body.len()) as u32;
+    let mut buf = size.to_le_bytes().to_vec();
+    buf.push(rtype);
+    buf.extend_from_slice(&[0, 0]);
+    buf.extend_from_slice(body);
+    buf
+}
+
+/// The body of an Rerror: ename[s] errno[4].
+fn rerror_body(ename: &str, errno: u32) -> Vec<u8> {
+    let mut body = (ename.len() as u16).to_le_bytes().to_vec();
+    body.extend_from_slice(ename.as_bytes());
+    body.extend_from_slice(&errno.to_le_bytes());
+    body
+}
+
+#[test]
+fn test_msize_proposed() {
+    const MAX: u32 = 512 * 1024;
+    assert_eq!(proposed_msize(0, MAX), MAX);
+    assert_eq!(proposed_msize(65536, MAX), 65536);
+    assert_eq!(proposed_msize(1 << 30, MAX), MAX);
+    assert_eq!(proposed_msize(100, MAX), 4096);
+}
+
+#[test]
+fn test_msize_negotiated() {
+    // the server can only lower the msize
+    assert_eq!(negotiated_msize(65536, 8192), 8192);
+    assert_eq!(negotiated_msize(65536, 65536), 65536);
+    assert_eq!(negotiated_msize(65536, 1 << 20), 65536);
+
+    // but not below the min, which any server supports
+    assert_eq!(negotiated_msize(65536, 512), 4096);
+}
+
+#[test]
+fn test_response_errno() {
+    // size[4] Rlerror tag[2] ecode[4]
+    assert_eq!(
+        response_errno(&response(RLERROR, &2u32.to_le_bytes())),
+        Some(2)
+    );
+
+    // size[4] Rerror tag[2] ename[s] errno[4]
+    let resp = response(RERROR, &rerror_body("file not found", 2));
+    assert_eq!(response_errno(&resp), Some(2));
+
+    // without the errno of 9P2000.u, it is EIO
+    let resp = response(RERROR, &rerror_body("permission denied", 0));
+    assert_eq!(response_errno(&resp), Some(5));
+
+    // size[4] Rread tag[2] count[4] data[count]
+    let resp = response(RREAD, &[3, 0, 0, 0, 1, 2, 3]);
+    assert_eq!(response_errno(&resp), None);
+}
+
+#[test]
+fn test_errno_to_vfs_error() {
+    assert_eq!(to_vfs_error(1), VfsError::OperationNotPermitted);
+    assert_eq!(to_vfs_error(2), VfsError::NotFound);
+    assert_eq!(to_vfs_error(13), VfsError::PermissionDenied);
+    assert_eq!(to_vfs_error(17), VfsError::AlreadyExists);
+    assert_eq!(to_vfs_error(20), VfsError::NotADirectory);
+    assert_eq!(to_vfs_error(21), VfsError::IsADirectory);
+    assert_eq!(to_vfs_error(28), VfsError::StorageFull);
+    assert_eq!(to_vfs_error(38), VfsError::Unsupported);
+    assert_eq!(to_vfs_error(95), VfsError::Unsupported);
+    assert_eq!(to_vfs_error(39), VfsError::DirectoryNotEmpty);
+
+    // errnos without a match are I/O errors
+    assert_eq!(to_vfs_error(5), VfsError::Io);
+    assert_eq!(to_vfs_error(1000), VfsError::Io);
+}
+
+#[test]
+fn test_mount_options() {
+    let opts = MountOptions::parse("msize=65536,version=9p2000.u,foo=bar").unwrap();
+    assert_eq!(opts.msize, 65536);
+    assert_eq!(opts.version, "9P2000.u");
+
+    let opts = MountOptions::parse("").unwrap();
+    assert_eq!(opts.msize, 0);
+    assert_eq!(opts.version, "9P2000.L");
+
+    assert!(MountOptions::parse("msize=big").is_err());
+    assert!(MountOptions::parse("version=9P2000").is_err());
+}
+
+fn fill(byte: u8) -> impl FnOnce(&mut [u8]) -> Result<(), ()> {
+    move |page| {
+        page.fill(byte);
+        Ok(())
+    }
+}
+
+/// Whether the page at `index` is cached, without filling it.
+fn is_cached(cache: &mut PageCache, index: u64) -> bool {
+    cache.page(index, |_| Err(())).is_ok()
+}
+
+/// Caches the page at `index` filled with `byte`, and marks it dirty.
+fn dirty_page(cache: &mut PageCache, index: u64, byte: u8) {
+    cache.page(index, fill(byte)).unwrap();
+    cache.mark_dirty(index);
+}
+
+/// Writes back `cache` in writes of at most `max_len`, and returns them.
+fn write_back(cache: &mut PageCache, max_len: usize) -> Vec<(u64, Vec<u8>)> {
+    let mut writes = Vec::new();
+    cache
+        .write_back(max_len, |offset, data| {
+            writes.push((offset, data.to_vec()));
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+    writes
+}
+
+#[test]
+fn test_page_cache_fill() {
+    let mut cache = PageCache::default();
+    assert_eq!(cache.size(), None);
+
+    // a page is filled once
+    assert_eq!(cache.page(2, fill(7)).unwrap()[..], [7; PAGE_SIZE]);
+    cache.page(2, fill(8)).unwrap()[0] = 1;
+    assert_eq!(cache.page(2, |_| Err(())).unwrap()[..2], [1, 7]);
+
+    // and not cached if filling it fails
+    assert_eq!(cache.page(3, |_| Err(5)), Err(5));
+    assert!(!is_cached(&mut cache, 3));
+}
+
+#[test]
+fn test_page_cache_write_back() {
+    let mut cache = PageCache::default();
+    cache.set_size(3 * PAGE_SIZE as u64 + 100);
+    dirty_page(&mut cache, 0, 0xa);
+    dirty_page(&mut cache, 1, 0xb);
+    cache.page(2, fill(0xc)).unwrap();
+    dirty_page(&mut cache, 3, 0xd);
+    // past the end of the file, so it is not written
+    dirty_page(&mut cache, 5, 0xf);
+
+    // contiguous dirty pages are written together, up to the size of the file
+    let mut data = vec![0xa; PAGE_SIZE];
+    data.extend_from_slice(&[0xb; PAGE_SIZE]);
+    let writes = write_back(&mut cache, 64 * PAGE_SIZE);
+    assert_eq!(writes, [(0, data), (3 * PAGE_SIZE as u64, vec![0xd; 100])]);
+
+    // and are clean after that
+    assert!(write_back(&mut cache, 64 * PAGE_SIZE).is_empty());
+    assert!(is_cached(&mut cache, 0));
+    assert!(is_cached(&mut cache, 2));
+}
+
+#[test]
+fn test_page_cache_write_back_split() {
+    let mut cache = PageCache::default();
+    cache.set_size(3 * PAGE_SIZE as u64);
+    for index in 0..3 {
+        dirty_page(&mut cache, index, index as u8);
+    }
+
+    // a failed write leaves the pages dirty
+    assert_eq!(cache.write_back(PAGE_SIZE, |_, _| Err(())), Err(()));
+
+    // writes are split at the max length, and at least a page each
+    let offsets = |writes: Vec<(u64, Vec<u8>)>| -> Vec<(u64, usize)> {
+        writes
+            .iter()
+            .map(|(offset, data)| (*offset, data.len()))
+            .collect()
+    };
+    let writes = write_back(&mut cache, 2 * PAGE_SIZE + 1);
+    assert_eq!(
+        offsets(writes),
+        [(0, 2 * PAGE_SIZE), (2 * PAGE_SIZE as u64, PAGE_SIZE)]
+    );
+
+    for index in 0..3 {
+        cache.mark_dirty(index);
+    }
+    let writes = write_back(&mut cache, 100);
+    assert_eq!(
+        offsets(writes),
+        [
+            (0, PAGE_SIZE),
+            (PAGE_SIZE as u64, PAGE_SIZE),
+            (2 * PAGE_SIZE as u64, PAGE_SIZE)
+        ]
+    );
+}
+
+#[test]
+fn test_page_cache_invalidate() {
+    let mut cache = PageCache::default();
+    cache.set_size(2 * PAGE_SIZE as u64);
+    cache.page(0, fill(1)).unwrap();
+    dirty_page(&mut cache, 1, 2);
+
+    // dirty pages and the size are kept
+    cache.invalidate();
+    assert!(!is_cached(&mut cache, 0));
+    assert!(is_cached(&mut cache, 1));
+    assert_eq!(cache.size(), Some(2 * PAGE_SIZE as u64));
+
+    // the size is forgotten with nothing dirty
+    write_back(&mut cache, PAGE_SIZE);
+    cache.invalidate();
+    assert!(!is_cached(&mut cache, 1));
+    assert_eq!(cache.size(), None);
+}
+
+#[test]
+fn test_page_cache_truncate() {
+    let mut cache = PageCache::default();
+    cache.set_size(3 * PAGE_SIZE as u64);
+    for index in 0..3 {
+        dirty_page(&mut cache, index, 9);
+    }
+
+    // the pages after the size are dropped, and the tail of the last one is zeroed
+    cache.truncate(PAGE_SIZE as u64 + 10);
+    assert_eq!(cache.size(), Some(PAGE_SIZE as u64 + 10));
+    assert!(!is_cached(&mut cache, 2));
+    let page = cache.page(1, |_| Err(())).unwrap();
+    assert_eq!(page[..10], [9; 10]);
+    assert!(page[10..].iter().all(|&b| b == 0));
+
+    // a size at a page boundary keeps the last page whole
+    cache.truncate(PAGE_SIZE as u64);
+    assert!(!is_cached(&mut cache, 1));
+    assert_eq!(cache.page(0, |_| Err(())).unwrap()[..], [9; PAGE_SIZE]);
+}
+
+#[test]
+fn test_page_cache_written_around() {
+    let mut cache = PageCache::default();
+    for index in 0..4 {
+        cache.page(index, fill(1)).unwrap();
+    }
+
+    // the size is only grown if it is known
+    cache.written_around(PAGE_SIZE as u64 + 1, 2 * PAGE_SIZE as u64 + 1);
+    assert_eq!(cache.size(), None);
+    assert!(is_cached(&mut cache, 0));
+    assert!(!is_cached(&mut cache, 1));
+    assert!(!is_cached(&mut cache, 2));
+    assert!(is_cached(&mut cache, 3));
+
+    cache.set_size(4 * PAGE_SIZE as u64);
+    cache.written_around(3 * PAGE_SIZE as u64, 5 * PAGE_SIZE as u64);
+    assert_eq!(cache.size(), Some(5 * PAGE_SIZE as u64));
+    assert!(!is_cached(&mut cache, 3));
+
+    // an empty range drops nothing
+    cache.written_around(0, 0);
+    assert!(is_cached(&mut cache, 0));
+}
+
+#[test]
+fn test_page_cache_too_dirty() {
+    let mut cache = PageCache::default();
+    for index in 0..MAX_DIRTY_PAGES as u64 - 1 {
+        dirty_page(&mut cache, index, 0);
+    }
+    assert!(!cache.too_dirty());
+    dirty_page(&mut cache, MAX_DIRTY_PAGES as u64, 0);
+    assert!(cache.too_dirty());
+
+    cache.set_size(u64::MAX);
+    write_back(&mut cache, 64 * PAGE_SIZE);
+    assert!(!cache.too_dirty());
+}
+
+#[test]
+fn test_page_cache_evict() {
+    let max = MAX_CLEAN_PAGES as u64;
+    let mut cache = PageCache::default();
+    dirty_page(&mut cache, 0, 0);
+    for index in 1..=max {
+        cache.page(index, fill(0)).unwrap();
+    }
+
+    // the least recently used clean page is dropped, and never a dirty one
+    cache.page(1, fill(0)).unwrap();
+    cache.page(max + 1, fill(0)).unwrap();
+    assert!(!is_cached(&mut cache, 2));
+    assert!(is_cached(&mut cache, 0));
+    assert!(is_cached(&mut cache, 1));
+    assert!(is_cached(&mut cache, max + 1));
+
+    cache.page(max + 2, fill(0)).unwrap();
+    assert!(!is_cached(&mut cache, 3));
+}
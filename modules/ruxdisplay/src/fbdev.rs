@@ -0,0 +1,301 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! The framebuffer device node `/dev/fb0`, with the Linux fbdev interface.
+
+use axerrno::{ax_err, AxError};
+use axfs_devfs::makedev;
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use ruxdriver::prelude::*;
+use ruxhal::mem::{direct_virt_to_phys, VirtAddr};
+use spinlock::SpinNoIrq;
+
+use crate::{DisplayInfo, MAIN_DISPLAY};
+
+/// Major number of the framebuffer devices.
+const FB_MAJOR: u32 = 29;
+
+const FBIOGET_VSCREENINFO: usize = 0x4600;
+const FBIOPUT_VSCREENINFO: usize = 0x4601;
+const FBIOGET_FSCREENINFO: usize = 0x4602;
+const FBIOPAN_DISPLAY: usize = 0x4606;
+const FBIOBLANK: usize = 0x4611;
+
+const FB_TYPE_PACKED_PIXELS: u32 = 0;
+const FB_VISUAL_TRUECOLOR: u32 = 2;
+
+/// The mappings of the framebuffer by `mmap`, whose writes can only be seen
+/// by flushing periodically.
+static MAPPINGS: SpinNoIrq<Mappings> = SpinNoIrq::new(Mappings {
+    count: 0,
+    flushing: false,
+});
+
+struct Mappings {
+    /// The number of the mappings.
+    count: usize,
+    /// Whether a task calls [`flush_mapped_framebuffer`] periodically.
+    flushing: bool,
+}
+
+/// The position of a color channel in a pixel.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct FbBitfield {
+    offset: u32,
+    length: u32,
+    msb_right: u32,
+}
+
+/// `struct fb_var_screeninfo`, the variable screen information.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct FbVarScreenInfo {
+    xres: u32,
+    yres: u32,
+    xres_virtual: u32,
+    yres_virtual: u32,
+    xoffset: u32,
+    yoffset: u32,
+    bits_per_pixel: u32,
+    grayscale: u32,
+    red: FbBitfield,
+    green: FbBitfield,
+    blue: FbBitfield,
+    transp: FbBitfield,
+    nonstd: u32,
+    activate: u32,
+    height: u32,
+    width: u32,
+    accel_flags: u32,
+    pixclock: u32,
+    left_margin: u32,
+    right_margin: u32,
+    upper_margin: u32,
+    lower_margin: u32,
+    hsync_len: u32,
+    vsync_len: u32,
+    sync: u32,
+    vmode: u32,
+    rotate: u32,
+    colorspace: u32,
+    reserved: [u32; 4],
+}
+
+/// `struct fb_fix_screeninfo`, the fixed screen information.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct FbFixScreenInfo {
+    id: [u8; 16],
+    smem_start: usize,
+    smem_len: u32,
+    type_: u32,
+    type_aux: u32,
+    visual: u32,
+    xpanstep: u16,
+    ypanstep: u16,
+    ywrapstep: u16,
+    line_length: u32,
+    mmio_start: usize,
+    mmio_len: u32,
+    accel: u32,
+    capabilities: u16,
+    reserved: [u16; 2],
+}
+
+impl FbVarScreenInfo {
+    /// The screen of `info`, whose pixels are 32-bit XRGB.
+    fn new(info: &DisplayInfo) -> Self {
+        let channel = |offset| FbBitfield {
+            offset,
+            length: 8,
+            msb_right: 0,
+        };
+        Self {
+            xres: info.width,
+            yres: info.height,
+            xres_virtual: info.width,
+            yres_virtual: info.height,
+            bits_per_pixel: 32,
+            red: channel(16),
+            green: channel(8),
+            blue: channel(0),
+            transp: channel(24),
+            // the physical size is unknown
+            height: u32::MAX,
+            width: u32::MAX,
+            ..Default::default()
+        }
+    }
+}
+
+impl FbFixScreenInfo {
+    fn new(info: &DisplayInfo, name: &str) -> Self {
+        let mut id = [0; 16];
+        let len = name.len().min(id.len() - 1);
+        id[..len].copy_from_slice(&name.as_bytes()[..len]);
+        Self {
+            id,
+            smem_start: direct_virt_to_phys(VirtAddr::from(info.fb_base_vaddr)).as_usize(),
+            smem_len: info.fb_size as u32,
+            type_: FB_TYPE_PACKED_PIXELS,
+            visual: FB_VISUAL_TRUECOLOR,
+            line_length: info.width * 4,
+            ..Default::default()
+        }
+    }
+}
+
+/// Flushes the framebuffer if it is mapped by `mmap` and the device needs
+/// flushing to show the changes.
+///
+/// It's called periodically by the task started when the framebuffer is
+/// first mapped, as the writes to the mapping are not noticed. Returns
+/// `false` once the last mapping is gone, when the task should stop.
+pub fn flush_mapped_framebuffer() -> bool {
+    let mut mappings = MAPPINGS.lock();
+    if mappings.count == 0 {
+        mappings.flushing = false;
+        return false;
+    }
+    drop(mappings);
+    flush_if_needed().ok();
+    true
+}
+
+fn flush_if_needed() -> VfsResult {
+    let mut display = MAIN_DISPLAY.lock();
+    if display.need_flush() {
+        display.flush().map_err(|_| AxError::Io)?;
+    }
+    Ok(())
+}
+
+/// The framebuffer of the main display as a character device.
+///
+/// Reads and writes access the framebuffer memory, and the screen is flushed
+/// after each write if the device needs it. `mmap` maps the framebuffer
+/// memory itself.
+pub struct FbDev {
+    ino: u64,
+    start_flusher: fn(),
+}
+
+impl FbDev {
+    /// Creates the node of the main display, with the inode number `ino`.
+    ///
+    /// `start_flusher` starts a task calling [`flush_mapped_framebuffer`]
+    /// periodically until it returns `false`, when the framebuffer is mapped
+    /// and no such task is running.
+    pub fn new(ino: u64, start_flusher: fn()) -> Self {
+        Self { ino, start_flusher }
+    }
+
+    fn framebuffer() -> &'static mut [u8] {
+        let info = MAIN_DISPLAY.lock().info();
+        // Safety: the framebuffer memory lives as long as the display device.
+        unsafe { core::slice::from_raw_parts_mut(info.fb_base_vaddr as *mut u8, info.fb_size) }
+    }
+}
+
+impl VfsNodeOps for FbDev {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = Self::framebuffer().len() as u64;
+        Ok(VfsNodeAttr::new(
+            self.ino,
+            VfsNodePerm::from_bits_truncate(0o660),
+            VfsNodeType::CharDevice,
+            size,
+            0,
+        )
+        .with_rdev(makedev(FB_MAJOR, 0)))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let fb = Self::framebuffer();
+        let start = fb.len().min(offset as usize);
+        let len = buf.len().min(fb.len() - start);
+        buf[..len].copy_from_slice(&fb[start..start + len]);
+        Ok(len)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let fb = Self::framebuffer();
+        let start = fb.len().min(offset as usize);
+        let len = buf.len().min(fb.len() - start);
+        if len == 0 && !buf.is_empty() {
+            return ax_err!(StorageFull);
+        }
+        fb[start..start + len].copy_from_slice(&buf[..len]);
+        flush_if_needed()?;
+        Ok(len)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        flush_if_needed()
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn ioctl(&self, cmd: usize, arg: usize) -> VfsResult<usize> {
+        let info = MAIN_DISPLAY.lock().info();
+        match cmd {
+            FBIOGET_VSCREENINFO => unsafe {
+                *(arg as *mut FbVarScreenInfo) = FbVarScreenInfo::new(&info);
+            },
+            FBIOGET_FSCREENINFO => {
+                let fix = FbFixScreenInfo::new(&info, MAIN_DISPLAY.lock().device_name());
+                unsafe { *(arg as *mut FbFixScreenInfo) = fix };
+            }
+            FBIOPUT_VSCREENINFO => {
+                // the mode can't be changed, only the current one is accepted
+                let var = unsafe { &mut *(arg as *mut FbVarScreenInfo) };
+                let current = FbVarScreenInfo::new(&info);
+                if var.xres != current.xres
+                    || var.yres != current.yres
+                    || var.bits_per_pixel != current.bits_per_pixel
+                {
+                    return ax_err!(InvalidInput);
+                }
+                *var = current;
+            }
+            // there is a single buffer, so panning shows its content
+            FBIOPAN_DISPLAY => {
+                let var = unsafe { &*(arg as *const FbVarScreenInfo) };
+                if var.xoffset != 0 || var.yoffset != 0 {
+                    return ax_err!(InvalidInput);
+                }
+                flush_if_needed()?;
+            }
+            FBIOBLANK => {}
+            _ => return ax_err!(Unsupported),
+        }
+        Ok(0)
+    }
+
+    fn device_memory(&self) -> Option<(usize, usize)> {
+        let info = MAIN_DISPLAY.lock().info();
+        let mut mappings = MAPPINGS.lock();
+        mappings.count += 1;
+        let start = !core::mem::replace(&mut mappings.flushing, true);
+        drop(mappings);
+        if start {
+            (self.start_flusher)();
+        }
+        Some((info.fb_base_vaddr, info.fb_size))
+    }
+
+    fn device_unmapped(&self) {
+        MAPPINGS.lock().count -= 1;
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
@@ -10,14 +10,23 @@
 //! [Ruxos](https://github.com/syswonder/ruxos) graphics module.
 //!
 //! Currently only supports direct writing to the framebuffer.
+//!
+//! # Cargo Features
+//!
+//! - `fs`: Provide [`FbDev`], the framebuffer as the device node `/dev/fb0`.
 
 #![no_std]
 
 #[macro_use]
 extern crate log;
 
+#[cfg(feature = "fs")]
+mod fbdev;
+
 #[doc(no_inline)]
 pub use driver_display::DisplayInfo;
+#[cfg(feature = "fs")]
+pub use fbdev::{flush_mapped_framebuffer, FbDev};
 
 use axsync::Mutex;
 use lazy_init::LazyInit;
@@ -10,15 +10,17 @@
 //! fd table and FileLike trait for file system
 #![no_std]
 extern crate alloc;
-use alloc::sync::Arc;
+use alloc::sync::{Arc, Weak};
 use axfs_vfs::VfsNodeAttr;
 use core::marker::Send;
 use core::marker::Sync;
 
-use axerrno::LinuxResult;
+use axerrno::{LinuxError, LinuxResult};
 use axfs_vfs::AbsPath;
 use axio::PollState;
 
+pub use axfs_vfs::{PollObserver, PollObservers, PIPE_BUF};
+
 #[derive(Default)]
 ///Rust version for struct timespec in ctypes. Represents a high-resolution time specification.
 pub struct RuxTimeSpec {
@@ -112,7 +114,7 @@ impl From<VfsNodeAttr> for RuxStat {
             st_mode: ((attr.file_type() as u32) << 12) | attr.perm().bits() as u32,
             st_uid: 1000,
             st_gid: 1000,
-            st_rdev: 0,
+            st_rdev: attr.rdev(),
             __pad: 0,
             st_size: attr.size() as _,
             st_blksize: 512,
@@ -146,7 +148,7 @@ impl From<VfsNodeAttr> for RuxStat {
             st_uid: 1000,
             st_gid: 1000,
             __pad0: 0,
-            st_rdev: 0,
+            st_rdev: attr.rdev(),
             st_size: attr.size() as _,
             st_blksize: 512,
             st_blocks: attr.blocks() as _,
@@ -196,4 +198,34 @@ pub trait FileLike: Send + Sync {
 
     /// Sets or clears the non-blocking I/O mode for the file-like object.
     fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult;
+
+    /// Whether the file-like object is in non-blocking I/O mode.
+    fn is_nonblocking(&self) -> bool {
+        false
+    }
+
+    /// Performs the device-specific `ioctl` request `cmd` with the argument
+    /// `arg`.
+    ///
+    /// Returns `ENOTTY` if the object has no such request.
+    fn ioctl(&self, _cmd: usize, _arg: usize) -> LinuxResult<usize> {
+        Err(LinuxError::ENOTTY)
+    }
+
+    /// Registers `observer` to be notified with `key` whenever the readiness
+    /// of the file-like object may have changed.
+    ///
+    /// Returns `false` if the object doesn't push readiness changes, in which
+    /// case it must be polled instead.
+    fn add_poll_observer(
+        &self,
+        _observer: Weak<dyn PollObserver>,
+        _key: usize,
+        _exclusive: bool,
+    ) -> bool {
+        false
+    }
+
+    /// Unregisters an observer added by [`FileLike::add_poll_observer`].
+    fn remove_poll_observer(&self, _observer: &Weak<dyn PollObserver>, _key: usize) {}
 }
@@ -0,0 +1,400 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Device nodes under `/dev`.
+//!
+//! Block devices probed by `ruxdriver` and the ttys of the `tty` crate get a
+//! node each, with the major and minor numbers Linux would give them.
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::String,
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use axerrno::{ax_err, AxError};
+use axfs_devfs::{makedev, DeviceFileSystem};
+use axfs_vfs::{
+    PollObserver, PollObservers, RelPath, VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodePerm,
+    VfsNodeRef, VfsNodeType, VfsOps, VfsResult,
+};
+use axio::PollState;
+use lazy_init::LazyInit;
+use ruxdriver::{prelude::*, AxDeviceContainer};
+use spin::Mutex;
+use spinlock::SpinNoIrq;
+
+/// Major number of the `virtio-blk` disks (`vda`, `vdb`, ...).
+const VIRTIO_BLK_MAJOR: u32 = 254;
+/// Major number of the RAM disks (`ram0`, `ram1`, ...).
+const RAMDISK_MAJOR: u32 = 1;
+/// Major number of the SD/MMC cards (`mmcblk0`, `mmcblk1`, ...).
+const MMC_BLOCK_MAJOR: u32 = 179;
+/// Major number of the serial ttys (`ttyS0`, `ttyS1`, ...).
+const TTY_MAJOR: u32 = 4;
+/// Major number of `/dev/tty` and `/dev/console`.
+const TTYAUX_MAJOR: u32 = 5;
+
+const BLKGETSIZE: usize = 0x1260;
+const BLKFLSBUF: usize = 0x1261;
+const BLKSSZGET: usize = 0x1268;
+const BLKGETSIZE64: usize = 0x8008_1272;
+
+static DEVFS: LazyInit<Arc<DeviceFileSystem>> = LazyInit::new();
+
+/// The number of disks named so far for each driver.
+static BLOCK_INDEX: Mutex<BTreeMap<&'static str, u32>> = Mutex::new(BTreeMap::new());
+
+/// The readers of each tty device by name, shared by the nodes of a device.
+static TTY_OBSERVERS: SpinNoIrq<BTreeMap<String, Arc<PollObservers>>> =
+    SpinNoIrq::new(BTreeMap::new());
+
+pub(crate) fn init_devfs(devfs: Arc<DeviceFileSystem>) {
+    DEVFS.init_by(devfs);
+}
+
+/// Adds `node` as `/dev/<name>`.
+pub fn register(name: &str, node: VfsNodeRef) {
+    DEVFS.add(name, node);
+}
+
+/// Allocates an inode number for a node to be registered.
+pub fn alloc_ino() -> u64 {
+    DEVFS.alloc_ino()
+}
+
+/// Finds the node under `/dev` of the device of type `ty` and number `rdev`,
+/// for the device nodes of other filesystems.
+pub fn find(ty: VfsNodeType, rdev: u64) -> Option<VfsNodeRef> {
+    let root = DEVFS.root_dir();
+    let mut dirents: Vec<VfsDirEntry> = (0..16).map(|_| VfsDirEntry::default()).collect();
+    let mut start = 0;
+    loop {
+        let n = root.read_dir(start, &mut dirents).ok()?;
+        if n == 0 {
+            return None;
+        }
+        for ent in &dirents[..n] {
+            if ent.entry_type() != ty {
+                continue;
+            }
+            let name = core::str::from_utf8(ent.name_as_bytes()).ok()?;
+            let Ok(node) = root.clone().lookup(&RelPath::new(name)) else {
+                continue;
+            };
+            if node.get_attr().is_ok_and(|attr| attr.rdev() == rdev) {
+                return Some(node);
+            }
+        }
+        start += n;
+    }
+}
+
+/// Takes the next disk name and device number for a block device of the
+/// given driver.
+pub(crate) fn next_block_name(driver: &'static str) -> (String, u64) {
+    let mut indexes = BLOCK_INDEX.lock();
+    let index = indexes.entry(driver).or_insert(0);
+    let i = *index;
+    *index += 1;
+    match driver {
+        "ramdisk" => (format!("ram{}", i), makedev(RAMDISK_MAJOR, i)),
+        "bcm2835_sdhci" => (format!("mmcblk{}", i), makedev(MMC_BLOCK_MAJOR, i * 8)),
+        _ => (disk_name("vd", i), makedev(VIRTIO_BLK_MAJOR, i * 16)),
+    }
+}
+
+/// Names the `index`-th disk like Linux: `vda`, ..., `vdz`, `vdaa`, ...
+fn disk_name(prefix: &str, mut index: u32) -> String {
+    let mut suffix = Vec::new();
+    loop {
+        suffix.push(b'a' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    suffix.reverse();
+    format!("{}{}", prefix, String::from_utf8(suffix).unwrap())
+}
+
+/// The readers of the tty device called `name`.
+pub(crate) fn tty_observers(name: &str) -> Arc<PollObservers> {
+    TTY_OBSERVERS
+        .lock()
+        .entry(String::from(name))
+        .or_insert_with(|| Arc::new(PollObservers::new()))
+        .clone()
+}
+
+/// Wakes up the readers of the tty device called `name`, after it received
+/// input or hung up. It may run in IRQ context.
+pub fn tty_wake_readers(name: &str) {
+    let observers = TTY_OBSERVERS.lock().get(name).cloned();
+    if let Some(observers) = observers {
+        observers.notify();
+    }
+}
+
+/// Creates the nodes of all block devices not used by the root filesystem.
+pub fn init_block_devices(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
+    while let Some(dev) = blk_devs.take_one() {
+        let (name, rdev) = next_block_name(dev.device_name());
+        info!(
+            "  add block device {:?} as /dev/{}",
+            dev.device_name(),
+            name
+        );
+        register(&name, Arc::new(BlockDev::new(alloc_ino(), rdev, dev)));
+    }
+}
+
+/// Creates `/dev/console`, `/dev/tty` and a node for each tty device.
+pub fn init_tty_devices() {
+    let names = ruxhal::get_all_device_names();
+    let Some(console) = names.first() else {
+        return;
+    };
+    for (name, rdev) in [
+        ("console", makedev(TTYAUX_MAJOR, 1)),
+        ("tty", makedev(TTYAUX_MAJOR, 0)),
+    ] {
+        let node = TtyDev::new(alloc_ino(), rdev, console.clone());
+        register(name, Arc::new(node));
+    }
+    for name in names.iter().filter(|name| name.as_str() != "notty") {
+        let rdev = match name
+            .strip_prefix("ttyS")
+            .and_then(|n| n.parse::<u32>().ok())
+        {
+            Some(n) => makedev(TTY_MAJOR, 64 + n),
+            None => makedev(TTY_MAJOR, 0),
+        };
+        register(name, Arc::new(TtyDev::new(alloc_ino(), rdev, name.clone())));
+    }
+}
+
+fn dev_err(err: DevError) -> AxError {
+    match err {
+        DevError::AlreadyExists => AxError::AlreadyExists,
+        DevError::Again => AxError::WouldBlock,
+        DevError::BadState => AxError::BadState,
+        DevError::InvalidParam => AxError::InvalidInput,
+        DevError::Io => AxError::Io,
+        DevError::NoMemory => AxError::NoMemory,
+        DevError::ResourceBusy => AxError::ResourceBusy,
+        DevError::Unsupported => AxError::Unsupported,
+    }
+}
+
+/// A block device node, reading and writing the disk by bytes.
+pub struct BlockDev {
+    ino: u64,
+    rdev: u64,
+    dev: Mutex<AxBlockDevice>,
+}
+
+impl BlockDev {
+    /// Creates a node of the block device `dev`.
+    pub fn new(ino: u64, rdev: u64, dev: AxBlockDevice) -> Self {
+        Self {
+            ino,
+            rdev,
+            dev: Mutex::new(dev),
+        }
+    }
+
+    fn size(dev: &AxBlockDevice) -> u64 {
+        dev.num_blocks() * dev.block_size() as u64
+    }
+
+    /// Accesses the bytes at `offset` block by block, calling `f` with the
+    /// block id, the range within that block and the range within `len`.
+    fn for_each_block(
+        dev: &mut AxBlockDevice,
+        offset: u64,
+        len: usize,
+        mut f: impl FnMut(&mut AxBlockDevice, u64, usize, usize, usize) -> VfsResult,
+    ) -> VfsResult<usize> {
+        let size = Self::size(dev);
+        if offset >= size {
+            return Ok(0);
+        }
+        let len = len.min((size - offset) as usize);
+        let bs = dev.block_size();
+        let mut done = 0;
+        while done < len {
+            let pos = offset + done as u64;
+            let block_id = pos / bs as u64;
+            let start = (pos % bs as u64) as usize;
+            let count = if start == 0 {
+                // as many whole blocks as possible at once
+                let whole = (len - done) / bs * bs;
+                if whole > 0 {
+                    whole
+                } else {
+                    len - done
+                }
+            } else {
+                (bs - start).min(len - done)
+            };
+            f(dev, block_id, start, done, count)?;
+            done += count;
+        }
+        Ok(done)
+    }
+}
+
+impl VfsNodeOps for BlockDev {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let dev = self.dev.lock();
+        let size = Self::size(&dev);
+        Ok(VfsNodeAttr::new(
+            self.ino,
+            VfsNodePerm::from_bits_truncate(0o660),
+            VfsNodeType::BlockDevice,
+            size,
+            size / 512,
+        )
+        .with_rdev(self.rdev))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut dev = self.dev.lock();
+        let bs = dev.block_size();
+        let mut block = vec![0; bs];
+        Self::for_each_block(&mut dev, offset, buf.len(), |dev, id, start, pos, count| {
+            if start == 0 && count % bs == 0 {
+                return dev
+                    .read_block(id, &mut buf[pos..pos + count])
+                    .map_err(dev_err);
+            }
+            dev.read_block(id, &mut block).map_err(dev_err)?;
+            buf[pos..pos + count].copy_from_slice(&block[start..start + count]);
+            Ok(())
+        })
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut dev = self.dev.lock();
+        if !buf.is_empty() && offset >= Self::size(&dev) {
+            return ax_err!(StorageFull);
+        }
+        let bs = dev.block_size();
+        let mut block = vec![0; bs];
+        Self::for_each_block(&mut dev, offset, buf.len(), |dev, id, start, pos, count| {
+            if start == 0 && count % bs == 0 {
+                return dev.write_block(id, &buf[pos..pos + count]).map_err(dev_err);
+            }
+            // read-modify-write of a partial block
+            dev.read_block(id, &mut block).map_err(dev_err)?;
+            block[start..start + count].copy_from_slice(&buf[pos..pos + count]);
+            dev.write_block(id, &block).map_err(dev_err)
+        })
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.dev.lock().flush().map_err(dev_err)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn ioctl(&self, cmd: usize, arg: usize) -> VfsResult<usize> {
+        let mut dev = self.dev.lock();
+        match cmd {
+            BLKGETSIZE64 => unsafe { *(arg as *mut u64) = Self::size(&dev) },
+            BLKGETSIZE => unsafe { *(arg as *mut usize) = (Self::size(&dev) / 512) as usize },
+            BLKSSZGET => unsafe { *(arg as *mut i32) = dev.block_size() as i32 },
+            BLKFLSBUF => dev.flush().map_err(dev_err)?,
+            _ => return ax_err!(Unsupported),
+        }
+        Ok(0)
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// A tty device node, backed by a driver of the `tty` crate.
+pub struct TtyDev {
+    ino: u64,
+    rdev: u64,
+    name: String,
+}
+
+impl TtyDev {
+    /// Creates a node of the tty device called `name`.
+    pub fn new(ino: u64, rdev: u64, name: String) -> Self {
+        Self { ino, rdev, name }
+    }
+}
+
+impl VfsNodeOps for TtyDev {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            self.ino,
+            VfsNodePerm::from_bits_truncate(0o666),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        )
+        .with_rdev(self.rdev))
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        ruxhal::tty_read(buf, &self.name).ok_or(AxError::WouldBlock)
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        Ok(ruxhal::tty_write(buf, &self.name))
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn ioctl(&self, cmd: usize, arg: usize) -> VfsResult<usize> {
+        ruxhal::tty_ioctl(&self.name, cmd, arg).ok_or(AxError::Unsupported)
+    }
+
+    fn poll(&self) -> VfsResult<PollState> {
+        Ok(PollState {
+            readable: ruxhal::tty_readable(&self.name),
+            writable: true,
+            pollhup: false,
+        })
+    }
+
+    /// Readers are woken up by the `tty` crate when input arrives, unless
+    /// the console is polled or a `VTIME` timer may end the read.
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        if !ruxhal::tty_wakes_readers(&self.name) {
+            return false;
+        }
+        tty_observers(&self.name).add(observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        tty_observers(&self.name).remove(observer, key);
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
@@ -0,0 +1,340 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Pseudo-terminals: the multiplexer `/dev/ptmx` and the `devpts`
+//! filesystem on `/dev/pts`.
+//!
+//! Each open of `/dev/ptmx` creates a pty of the `tty` crate and returns its
+//! master, whose slave appears as `/dev/pts/<index>` until the master is
+//! closed.
+
+use alloc::{
+    format,
+    sync::{Arc, Weak},
+};
+use axerrno::{ax_err, AxError, AxResult};
+use axfs_devfs::makedev;
+use axfs_vfs::{
+    AbsPath, PollObserver, RelPath, VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef,
+    VfsNodeType, VfsOps, VfsResult,
+};
+use axio::PollState;
+use spin::Once;
+use tty::{pty, TtyStruct};
+
+/// Device number of `/dev/ptmx`.
+pub const PTMX_RDEV: u64 = makedev(5, 2);
+/// Major number of the pty slaves.
+const PTS_MAJOR: u32 = 136;
+
+const TIOCGPTN: usize = 0x8004_5430;
+const TIOCSPTLCK: usize = 0x4004_5431;
+
+/// Creates a new pty and returns its master, for an open of `/dev/ptmx`.
+pub(crate) fn open_ptmx(ino: u64) -> AxResult<VfsNodeRef> {
+    let index = pty::open().ok_or(AxError::NoMemory)?;
+    Ok(Arc::new(PtyMaster { ino, index }))
+}
+
+/// The node `/dev/ptmx` in devfs, replaced by a new master on each open.
+pub struct PtmxDev {
+    ino: u64,
+}
+
+impl PtmxDev {
+    /// Creates the node `/dev/ptmx`.
+    pub fn new(ino: u64) -> Self {
+        Self { ino }
+    }
+}
+
+impl VfsNodeOps for PtmxDev {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            self.ino,
+            VfsNodePerm::from_bits_truncate(0o666),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        )
+        .with_rdev(PTMX_RDEV))
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// The master of a pty, which is closed when the node is dropped.
+struct PtyMaster {
+    ino: u64,
+    index: usize,
+}
+
+impl Drop for PtyMaster {
+    fn drop(&mut self) {
+        pty::close(self.index);
+    }
+}
+
+impl VfsNodeOps for PtyMaster {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            self.ino,
+            VfsNodePerm::from_bits_truncate(0o666),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        )
+        .with_rdev(PTMX_RDEV))
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match pty::master_read(self.index, buf) {
+            0 => ax_err!(WouldBlock),
+            len => Ok(len),
+        }
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        Ok(pty::master_write(self.index, buf))
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn ioctl(&self, cmd: usize, arg: usize) -> VfsResult<usize> {
+        match cmd {
+            TIOCGPTN => unsafe { *(arg as *mut u32) = self.index as u32 },
+            TIOCSPTLCK => pty::set_locked(self.index, unsafe { *(arg as *const i32) } != 0),
+            _ => {
+                let tty = pty::slave(self.index).ok_or(AxError::BadState)?;
+                return tty.ioctl(cmd, arg).ok_or(AxError::Unsupported);
+            }
+        }
+        Ok(0)
+    }
+
+    fn poll(&self) -> VfsResult<PollState> {
+        Ok(PollState {
+            readable: pty::master_readable(self.index),
+            writable: true,
+            pollhup: false,
+        })
+    }
+
+    /// Readers are woken up by the `tty` crate when the slave outputs.
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        crate::devices::tty_observers(&pty::master_name(self.index)).add(observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        crate::devices::tty_observers(&pty::master_name(self.index)).remove(observer, key);
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// The slave of a pty, `/dev/pts/<index>`.
+struct PtySlave {
+    index: usize,
+}
+
+impl PtySlave {
+    fn tty(&self) -> VfsResult<Arc<TtyStruct>> {
+        // the master is closed
+        pty::slave(self.index).ok_or(AxError::Io)
+    }
+}
+
+impl VfsNodeOps for PtySlave {
+    fn open(&self) -> VfsResult {
+        if pty::is_locked(self.index) {
+            return ax_err!(Io);
+        }
+        Ok(())
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            self.index as u64 + 3,
+            VfsNodePerm::from_bits_truncate(0o620),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        )
+        .with_rdev(makedev(PTS_MAJOR, self.index as u32)))
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let tty = self.tty()?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        tty::tty_read(buf, &tty.name()).ok_or(AxError::WouldBlock)
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let tty = self.tty()?;
+        // the master hasn't read the output yet
+        match tty::tty_write(buf, &tty.name()) {
+            0 if !buf.is_empty() => ax_err!(WouldBlock),
+            len => Ok(len),
+        }
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn ioctl(&self, cmd: usize, arg: usize) -> VfsResult<usize> {
+        self.tty()?.ioctl(cmd, arg).ok_or(AxError::Unsupported)
+    }
+
+    fn poll(&self) -> VfsResult<PollState> {
+        let tty = pty::slave(self.index);
+        Ok(PollState {
+            readable: tty
+                .as_ref()
+                .map_or(true, |tty| tty::tty_readable(&tty.name())),
+            writable: pty::slave_writable(self.index),
+            pollhup: tty.is_none(),
+        })
+    }
+
+    /// Readers are woken up by the `tty` crate when input arrives, unless a
+    /// `VTIME` timer may end the read, and writers when the master reads.
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        let name = pty::slave_name(self.index);
+        if pty::exists(self.index) && !tty::tty_wakes_readers(&name) {
+            return false;
+        }
+        crate::devices::tty_observers(&name).add(observer, key, exclusive);
+        true
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        crate::devices::tty_observers(&pty::slave_name(self.index)).remove(observer, key);
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// The root directory of devpts, listing the slaves of the open ptys.
+struct PtsDir {
+    parent: Once<VfsNodeRef>,
+}
+
+impl VfsNodeOps for PtsDir {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_dir(1, 0, 0))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.parent.get().cloned()
+    }
+
+    fn lookup(self: Arc<Self>, path: &RelPath) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = path.find('/').map_or((&**path, None), |n| {
+            (&path[..n], Some(RelPath::new(&path[n + 1..])))
+        });
+        if let Some(rest) = rest {
+            match name {
+                ".." => self.parent().ok_or(AxError::NotFound)?.lookup(&rest),
+                _ => ax_err!(NotADirectory),
+            }
+        } else if name.is_empty() {
+            Ok(self.clone() as VfsNodeRef)
+        } else if name == ".." {
+            self.parent().ok_or(AxError::NotFound)
+        } else {
+            let index = name.parse().map_err(|_| AxError::NotFound)?;
+            if !pty::exists(index) {
+                return ax_err!(NotFound);
+            }
+            Ok(Arc::new(PtySlave { index }))
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let indices = pty::all_indices();
+        let mut slaves = indices.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => match slaves.next() {
+                    Some(index) => {
+                        *ent = VfsDirEntry::new(&format!("{}", index), VfsNodeType::CharDevice)
+                    }
+                    None => return Ok(i),
+                },
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn create(&self, _path: &RelPath, _ty: VfsNodeType) -> VfsResult {
+        ax_err!(PermissionDenied)
+    }
+
+    fn unlink(&self, _path: &RelPath) -> VfsResult {
+        ax_err!(PermissionDenied)
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+/// The devpts filesystem.
+pub struct DevPtsFileSystem {
+    root: Arc<PtsDir>,
+}
+
+impl DevPtsFileSystem {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(PtsDir {
+                parent: Once::new(),
+            }),
+        }
+    }
+}
+
+impl Default for DevPtsFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VfsOps for DevPtsFileSystem {
+    fn mount(&self, _path: &AbsPath, mount_point: VfsNodeRef) -> VfsResult {
+        if let Some(parent) = mount_point.parent() {
+            self.root.parent.call_once(|| parent);
+        }
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
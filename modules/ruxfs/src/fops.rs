@@ -14,12 +14,19 @@
 //!
 //! The interface is designed with low coupling to avoid repetitive error handling.
 
-use alloc::{sync::Arc, vec::Vec};
-use axerrno::{ax_err, ax_err_type, AxResult};
-use axfs_vfs::{AbsPath, RelPath, VfsNodeOps, VfsNodeRef, VfsNodeType};
-use axio::SeekFrom;
+use alloc::{
+    string::String,
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use axerrno::{ax_err, ax_err_type, AxError, AxResult};
+use axfs_vfs::{AbsPath, PollObserver, RelPath, VfsFileSeals, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axio::{PollState, SeekFrom};
 use capability::{Cap, WithCap};
 
+use crate::lock::{self, FileKey, FileLock, LockOwner, LockType};
+use crate::notify::{self, FsEvent};
 use crate::root::{MountPoint, RootDirectory};
 
 /// Alias of [`axfs_vfs::VfsNodeType`].
@@ -30,6 +37,13 @@ pub type DirEntry = axfs_vfs::VfsDirEntry;
 pub type FileAttr = axfs_vfs::VfsNodeAttr;
 /// Alias of [`axfs_vfs::VfsNodePerm`].
 pub type FilePerm = axfs_vfs::VfsNodePerm;
+/// Alias of [`axfs_vfs::VfsFileSeals`].
+pub type FileSeals = axfs_vfs::VfsFileSeals;
+/// Alias of [`axfs_vfs::FileSystemInfo`].
+pub type FileSystemInfo = axfs_vfs::FileSystemInfo;
+
+/// Maximum length of the target of a symbolic link.
+const SYMLINK_MAX: usize = 4096;
 
 /// An opened file object, with open permissions and a cursor.
 ///
@@ -37,7 +51,17 @@ pub type FilePerm = axfs_vfs::VfsNodePerm;
 pub struct File {
     path: AbsPath<'static>,
     node: WithCap<VfsNodeRef>,
+    /// The root directory the file was opened in, as files may be released
+    /// while the file system of the current task is locked.
+    root: Arc<RootDirectory>,
     append: bool,
+    sync: bool,
+    dsync: bool,
+    direct: bool,
+    noatime: bool,
+    nonblocking: bool,
+    /// opened with `O_PATH`, only usable as a reference to the file
+    path_only: bool,
     offset: u64,
 }
 
@@ -47,16 +71,105 @@ impl File {
         Self {
             path,
             node: WithCap::new(node, cap),
+            root: root_dir(),
             offset: 0,
             append,
+            sync: false,
+            dsync: false,
+            direct: false,
+            noatime: false,
+            nonblocking: false,
+            path_only: false,
         }
     }
 
+    /// Takes the status flags of the file from the open options.
+    fn with_options(mut self, opt: &OpenOptions) -> Self {
+        self.sync = opt.sync;
+        self.dsync = opt.dsync;
+        self.direct = opt.direct;
+        self.noatime = opt.noatime;
+        self.nonblocking = opt.non_blocking;
+        self.path_only = opt.path;
+        self
+    }
+
     /// Get the abcolute path of the file.
     pub fn path(&self) -> AbsPath {
         self.path.clone()
     }
 
+    /// Whether the file is opened in append mode, where writes at the
+    /// current position go to the end of the file.
+    pub fn is_append(&self) -> bool {
+        self.append
+    }
+
+    /// Sets or clears the append mode.
+    pub fn set_append(&mut self, append: bool) {
+        self.append = append;
+    }
+
+    /// Whether the file can be read.
+    pub fn is_readable(&self) -> bool {
+        self.node.can_access(Cap::READ)
+    }
+
+    /// Whether the file can be written.
+    pub fn is_writable(&self) -> bool {
+        self.node.can_access(Cap::WRITE)
+    }
+
+    /// Whether every write is synced to the device with the file metadata
+    /// before returning (`O_SYNC`).
+    pub fn is_sync(&self) -> bool {
+        self.sync
+    }
+
+    /// Whether the data of every write is synced to the device before
+    /// returning (`O_DSYNC`).
+    pub fn is_dsync(&self) -> bool {
+        self.dsync
+    }
+
+    /// Whether the file is opened for direct I/O.
+    pub fn is_direct(&self) -> bool {
+        self.direct
+    }
+
+    /// Sets or clears direct I/O, which reads and writes around the caches of
+    /// the filesystem.
+    pub fn set_direct(&mut self, direct: bool) {
+        self.direct = direct;
+    }
+
+    /// Whether reads should not update the access time of the file.
+    pub fn is_noatime(&self) -> bool {
+        self.noatime
+    }
+
+    /// Sets or clears `O_NOATIME`. Access times are not tracked, so it only
+    /// shows in the status flags.
+    pub fn set_noatime(&mut self, noatime: bool) {
+        self.noatime = noatime;
+    }
+
+    /// Whether the file is opened in non-blocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+
+    /// Sets or clears the non-blocking mode.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    /// Whether the file is opened with `O_PATH`, so it can neither be read
+    /// nor written.
+    pub fn is_path_only(&self) -> bool {
+        self.path_only
+    }
+
     /// Gets the file attributes.
     pub fn get_attr(&self) -> AxResult<FileAttr> {
         self.node.access(Cap::empty())?.get_attr()
@@ -64,7 +177,75 @@ impl File {
 
     /// Truncates the file to the specified size.
     pub fn truncate(&self, size: u64) -> AxResult {
-        self.node.access(Cap::WRITE)?.truncate(size)
+        self.node.access(Cap::WRITE)?.truncate(size)?;
+        self.notify(FsEvent::MODIFY);
+        Ok(())
+    }
+
+    /// Performs the device-specific request `cmd` with the argument `arg`.
+    pub fn ioctl(&self, cmd: usize, arg: usize) -> AxResult<usize> {
+        self.node.access(Cap::empty())?.ioctl(cmd, arg)
+    }
+
+    /// Gets the kernel address and the size of the device memory mapped by
+    /// `mmap`, if the file is such a device.
+    pub fn device_memory(&self) -> Option<(usize, usize)> {
+        self.node.access(Cap::empty()).ok()?.device_memory()
+    }
+
+    /// Releases a mapping of the device memory got by [`Self::device_memory`].
+    pub fn device_unmapped(&self) {
+        if let Ok(node) = self.node.access(Cap::empty()) {
+            node.device_unmapped();
+        }
+    }
+
+    /// Checks whether the file can be read or written without blocking.
+    pub fn poll(&self) -> AxResult<PollState> {
+        self.node.access(Cap::empty())?.poll()
+    }
+
+    /// Registers `observer` to be notified when the result of [`File::poll`]
+    /// may change. Returns `false` if the node does not push its readiness.
+    pub fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        self.node.access(Cap::empty()).map_or(false, |node| {
+            node.add_poll_observer(observer, key, exclusive)
+        })
+    }
+
+    /// Unregisters an observer added by [`File::add_poll_observer`].
+    pub fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        if let Ok(node) = self.node.access(Cap::empty()) {
+            node.remove_poll_observer(observer, key);
+        }
+    }
+
+    /// Whether the other end of a FIFO opened only for reading or only for
+    /// writing is open.
+    pub fn fifo_peer_opened(&self) -> bool {
+        let Ok(node) = self.node.access(Cap::empty()) else {
+            return true;
+        };
+        match (self.is_readable(), self.is_writable()) {
+            (true, false) => node.fifo_has_writers(),
+            (false, true) => node.fifo_has_readers(),
+            _ => true,
+        }
+    }
+
+    /// Gets the capacity of the FIFO buffer.
+    pub fn fifo_capacity(&self) -> AxResult<usize> {
+        self.node.access(Cap::empty())?.fifo_capacity()
+    }
+
+    /// Resizes the FIFO buffer, returning the new capacity.
+    pub fn set_fifo_capacity(&self, size: usize) -> AxResult<usize> {
+        self.node.access(Cap::empty())?.set_fifo_capacity(size)
     }
 
     /// Reads the file at the current position. Returns the number of bytes
@@ -72,7 +253,7 @@ impl File {
     ///
     /// After the read, the cursor will be advanced by the number of bytes read.
     pub fn read(&mut self, buf: &mut [u8]) -> AxResult<usize> {
-        let read_len = self.node.access(Cap::READ)?.read_at(self.offset, buf)?;
+        let read_len = self.read_node(self.node.access(Cap::READ)?, self.offset, buf)?;
         self.offset += read_len as u64;
         Ok(read_len)
     }
@@ -81,7 +262,7 @@ impl File {
     ///
     /// It does not update the file cursor.
     pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> AxResult<usize> {
-        self.node.access(Cap::READ)?.read_at(offset, buf)
+        self.read_node(self.node.access(Cap::READ)?, offset, buf)
     }
 
     /// Writes the file at the current position. Returns the number of bytes
@@ -89,13 +270,28 @@ impl File {
     ///
     /// After the write, the cursor will be advanced by the number of bytes
     /// written.
+    ///
+    /// In append mode, it fails with `WouldBlock` while another file opened
+    /// on the same node appends to it, see [`lock::append_locked`].
     pub fn write(&mut self, buf: &[u8]) -> AxResult<usize> {
         let node = self.node.access(Cap::WRITE)?;
-        if self.append {
-            self.offset = self.get_attr()?.size();
+        check_future_write(node)?;
+        let write_len = if self.append && node.get_attr()?.is_file() {
+            let key = file_key(&self.root, &self.path, node)?;
+            let Some(_guard) = lock::try_lock_append(key) else {
+                return ax_err!(WouldBlock);
+            };
+            let offset = node.get_attr()?.size();
+            let write_len = self.write_node(node, offset, buf)?;
+            self.offset = offset + write_len as u64;
+            write_len
+        } else {
+            let write_len = self.write_node(node, self.offset, buf)?;
+            self.offset += write_len as u64;
+            write_len
         };
-        let write_len = node.write_at(self.offset, buf)?;
-        self.offset += write_len as u64;
+        self.sync_written(node)?;
+        self.notify(FsEvent::MODIFY);
         Ok(write_len)
     }
 
@@ -104,7 +300,87 @@ impl File {
     ///
     /// It does not update the file cursor.
     pub fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
-        self.node.access(Cap::WRITE)?.write_at(offset, buf)
+        let node = self.node.access(Cap::WRITE)?;
+        check_future_write(node)?;
+        let write_len = self.write_node(node, offset, buf)?;
+        self.sync_written(node)?;
+        self.notify(FsEvent::MODIFY);
+        Ok(write_len)
+    }
+
+    /// Reads `node`, around the caches of the filesystem for direct I/O.
+    fn read_node(&self, node: &VfsNodeRef, offset: u64, buf: &mut [u8]) -> AxResult<usize> {
+        if self.direct {
+            node.read_at_direct(offset, buf)
+        } else {
+            node.read_at(offset, buf)
+        }
+    }
+
+    /// Writes `node`, around the caches of the filesystem for direct I/O.
+    fn write_node(&self, node: &VfsNodeRef, offset: u64, buf: &[u8]) -> AxResult<usize> {
+        if self.direct {
+            node.write_at_direct(offset, buf)
+        } else {
+            node.write_at(offset, buf)
+        }
+    }
+
+    /// Syncs the written data if the file is opened with `O_SYNC` or
+    /// `O_DSYNC`. Filesystems that keep no cache have nothing to sync.
+    fn sync_written(&self, node: &VfsNodeRef) -> AxResult {
+        if !(self.sync || self.dsync) {
+            return Ok(());
+        }
+        match node.fsync() {
+            Ok(()) | Err(AxError::InvalidInput) | Err(AxError::Unsupported) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes back the content of a shared mapping at the given position.
+    /// Returns the number of bytes written.
+    ///
+    /// Unlike [`write_at`](Self::write_at), it never grows the file, and it
+    /// is still allowed by `F_SEAL_FUTURE_WRITE`.
+    pub fn write_back_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
+        let node = self.node.access(Cap::WRITE)?;
+        let size = node.get_attr()?.size();
+        let len = buf.len().min(size.saturating_sub(offset) as usize);
+        if len == 0 {
+            return Ok(0);
+        }
+        let write_len = node.write_at(offset, &buf[..len])?;
+        self.notify(FsEvent::MODIFY);
+        Ok(write_len)
+    }
+
+    /// Creates a hard link `new` to the opened file, by its node rather than
+    /// its path, which may be removed already.
+    pub fn link_to(&self, new: &AbsPath) -> AxResult {
+        let node = self.node.access(Cap::empty())?;
+        self.root.link(&new.to_rel(), node.clone())?;
+        self.notify(FsEvent::ATTRIB);
+        notify::notify(new, FsEvent::CREATE);
+        Ok(())
+    }
+
+    /// Gets the seals of the file.
+    pub fn get_seals(&self) -> AxResult<FileSeals> {
+        self.node.access(Cap::empty())?.get_seals()
+    }
+
+    /// Adds seals to the file, which requires it to be opened for writing.
+    pub fn add_seals(&self, seals: FileSeals) -> AxResult {
+        self.node
+            .access_or_err(Cap::WRITE, AxError::OperationNotPermitted)?
+            .add_seals(seals)
+    }
+
+    /// Gets an identifier of the underlying node, the same for all the
+    /// opened files that share it.
+    pub fn node_id(&self) -> usize {
+        Arc::as_ptr(unsafe { self.node.access_unchecked() }) as *const () as usize
     }
 
     /// Flushes the file, writes all buffered data to the underlying device.
@@ -125,6 +401,89 @@ impl File {
         self.offset = new_offset;
         Ok(new_offset)
     }
+
+    /// Sets the cursor of the file to the start of the first data at or
+    /// after `offset`. Returns the new position after the seek.
+    pub fn seek_data(&mut self, offset: u64) -> AxResult<u64> {
+        self.offset = self.node.access(Cap::empty())?.seek_data(offset)?;
+        Ok(self.offset)
+    }
+
+    /// Sets the cursor of the file to the start of the first hole at or
+    /// after `offset`, where the end of the file counts as a hole. Returns
+    /// the new position after the seek.
+    pub fn seek_hole(&mut self, offset: u64) -> AxResult<u64> {
+        self.offset = self.node.access(Cap::empty())?.seek_hole(offset)?;
+        Ok(self.offset)
+    }
+
+    /// Identifies the file in the lock table.
+    pub fn lock_key(&self) -> AxResult<FileKey> {
+        file_key(&self.root, &self.path, self.node.access(Cap::empty())?)
+    }
+
+    /// Reports a change of the file to the listeners of [`notify`].
+    fn notify(&self, event: FsEvent) {
+        let node = unsafe { self.node.access_unchecked() };
+        notify::notify_node(&self.root, &self.path, node, event);
+    }
+
+    /// Gets the first record lock that conflicts with `lock`, including the
+    /// locks of other clients if the filesystem shares them.
+    ///
+    /// `pid` is the calling process.
+    pub fn get_lock(&self, lock: &FileLock, pid: u64) -> AxResult<Option<FileLock>> {
+        if let Some(conflict) = lock::test_lock(&self.lock_key()?, lock) {
+            return Ok(Some(conflict));
+        }
+        match self.node.access(Cap::empty())?.get_lock(lock.to_vfs(pid)) {
+            Ok(conflict) => Ok(conflict.map(|l| FileLock::from_vfs(&l))),
+            Err(AxError::Unsupported) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Places or removes a record lock for the process `pid`. Returns
+    /// `WouldBlock` on conflict.
+    ///
+    /// A read lock requires the file to be opened for reading, and a write
+    /// lock for writing.
+    pub fn set_lock(&self, lock: FileLock, pid: u64) -> AxResult {
+        let node = match lock.ty {
+            LockType::Read => self.node.access(Cap::READ)?,
+            LockType::Write => self.node.access(Cap::WRITE)?,
+            LockType::Unlock => self.node.access(Cap::empty())?,
+        };
+        let key = self.lock_key()?;
+        let saved = lock::owner_locks(&key, lock.owner);
+        lock::set_lock(&key, lock)?;
+        match node.set_lock(lock.to_vfs(pid)) {
+            Ok(()) | Err(AxError::Unsupported) => Ok(()),
+            Err(e) => {
+                // the server refused, undo the local change
+                lock::restore_locks(&key, lock.owner, saved);
+                Err(e)
+            }
+        }
+    }
+
+    /// Places or removes a `flock` lock. Returns `WouldBlock` on conflict.
+    pub fn set_flock(&self, lock: FileLock) -> AxResult {
+        lock::set_flock(&self.lock_key()?, lock)
+    }
+
+    /// Removes all locks `owner` holds on the file, on behalf of the process
+    /// `pid`.
+    pub fn release_locks(&self, owner: LockOwner, pid: u64) -> AxResult {
+        if lock::release_locks(&self.lock_key()?, owner) {
+            let unlock = FileLock::whole_file(owner, LockType::Unlock);
+            match unsafe { self.node.access_unchecked() }.set_lock(unlock.to_vfs(pid)) {
+                Ok(()) | Err(AxError::Unsupported) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Drop for File {
@@ -134,6 +493,9 @@ impl Drop for File {
             match attr.file_type() {
                 FileType::File => {
                     self.node.access_unchecked().release().ok();
+                    if self.node.can_access(Cap::WRITE) {
+                        self.notify(FsEvent::CLOSE_WRITE);
+                    }
                 }
                 FileType::Fifo => {
                     let (read, write) = (
@@ -142,18 +504,34 @@ impl Drop for File {
                     );
                     self.node.access_unchecked().release_fifo(read, write).ok();
                 }
-                _ => unimplemented!(),
+                _ => {
+                    self.node.access_unchecked().release().ok();
+                }
             }
         }
     }
 }
 
+/// Writing through the file is not allowed once `F_SEAL_FUTURE_WRITE` is set.
+fn check_future_write(node: &VfsNodeRef) -> AxResult {
+    match node.get_seals() {
+        Ok(seals) if seals.contains(VfsFileSeals::FUTURE_WRITE) => {
+            ax_err!(OperationNotPermitted)
+        }
+        _ => Ok(()),
+    }
+}
+
 /// An opened directory object, with open permissions and a cursor for entry reading.
 ///
 /// Providing entry reading operations.
 pub struct Directory {
     path: AbsPath<'static>,
     node: WithCap<VfsNodeRef>,
+    noatime: bool,
+    nonblocking: bool,
+    /// opened with `O_PATH`, only usable as a reference to the directory
+    path_only: bool,
     entry_idx: usize,
 }
 
@@ -163,10 +541,39 @@ impl Directory {
         Self {
             path,
             node: WithCap::new(node, cap),
+            noatime: false,
+            nonblocking: false,
+            path_only: false,
             entry_idx: 0,
         }
     }
 
+    /// Whether reads should not update the access time of the directory.
+    pub fn is_noatime(&self) -> bool {
+        self.noatime
+    }
+
+    /// Sets or clears `O_NOATIME`.
+    pub fn set_noatime(&mut self, noatime: bool) {
+        self.noatime = noatime;
+    }
+
+    /// Whether the directory is opened in non-blocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+
+    /// Sets or clears the non-blocking mode.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    /// Whether the directory is opened with `O_PATH`, so its entries cannot
+    /// be read.
+    pub fn is_path_only(&self) -> bool {
+        self.path_only
+    }
+
     /// Gets the absolute path of the directory.
     pub fn path(&self) -> AbsPath {
         self.path.clone()
@@ -227,6 +634,16 @@ pub struct OpenOptions {
     pub cloexec: bool,
     /// whether the file is non-blocking(for fifo)
     pub non_blocking: bool,
+    /// Sync every write and the file metadata to the device.
+    pub sync: bool,
+    /// Sync the data of every write to the device.
+    pub dsync: bool,
+    /// Direct I/O, bypassing any cache.
+    pub direct: bool,
+    /// Don't update the access time on reads.
+    pub noatime: bool,
+    /// Only obtain a reference to the file, without opening it for I/O.
+    pub path: bool,
     // system-specific
     _custom_flags: i32,
     _mode: u32,
@@ -245,6 +662,11 @@ impl OpenOptions {
             create_new: false,
             cloexec: false,
             non_blocking: false,
+            sync: false,
+            dsync: false,
+            direct: false,
+            noatime: false,
+            path: false,
             // system-specific
             _custom_flags: 0,
             _mode: 0o666,
@@ -283,9 +705,33 @@ impl OpenOptions {
     pub fn non_blocking(&mut self, non_blocking: bool) {
         self.non_blocking = non_blocking;
     }
+    /// Sets the option to sync every write and the file metadata to the
+    /// device.
+    pub fn sync(&mut self, sync: bool) {
+        self.sync = sync;
+    }
+    /// Sets the option to sync the data of every write to the device.
+    pub fn dsync(&mut self, dsync: bool) {
+        self.dsync = dsync;
+    }
+    /// Sets the option for direct I/O.
+    pub fn direct(&mut self, direct: bool) {
+        self.direct = direct;
+    }
+    /// Sets the option not to update the access time on reads.
+    pub fn noatime(&mut self, noatime: bool) {
+        self.noatime = noatime;
+    }
+    /// Sets the option to only obtain a reference to the file.
+    pub fn path(&mut self, path: bool) {
+        self.path = path;
+    }
     /// Convert to capability.
     pub fn to_cap(&self) -> Cap {
         let mut cap = Cap::empty();
+        if self.path {
+            return cap;
+        }
         if self.read {
             cap |= Cap::READ;
         }
@@ -296,6 +742,9 @@ impl OpenOptions {
     }
     /// Check if the options are valid.
     pub const fn is_valid(&self) -> bool {
+        if self.path {
+            return !self.truncate && !self.create && !self.create_new;
+        }
         if !self.read && !self.write && !self.append {
             return false;
         }
@@ -355,6 +804,23 @@ pub(crate) fn root_dir() -> Arc<RootDirectory> {
     crate_interface::call_interface!(CurrentWorkingDirectoryOps::root_dir)
 }
 
+/// Identifies the file `node` found at `path` in `root` by its filesystem and
+/// inode number, or by `path` if the filesystem has no inode numbers.
+pub(crate) fn file_key(
+    root: &RootDirectory,
+    path: &AbsPath,
+    node: &VfsNodeRef,
+) -> AxResult<FileKey> {
+    let ino = node.get_attr()?.ino();
+    if ino == 0 {
+        return Ok(FileKey::Path(String::from(&**path)));
+    }
+    Ok(FileKey::Inode {
+        fs: root.fs_id(path),
+        ino,
+    })
+}
+
 /* File operations with absolute path. */
 
 /// Look up a file given an absolute path.
@@ -376,8 +842,26 @@ pub fn open_file(path: &AbsPath, node: VfsNodeRef, opt: &OpenOptions) -> AxResul
     if !perm_to_cap(attr.perm()).contains(opt.to_cap()) {
         return ax_err!(PermissionDenied);
     }
+    // a device node of a ramfs, e.g. one unpacked from an initramfs, opens
+    // the device with the same number under `/dev`
+    #[cfg(all(feature = "ramfs", feature = "devfs"))]
+    let node = if (attr.is_char_device() || attr.is_block_device())
+        && node.as_any().is::<axfs_ramfs::DeviceNode>()
+        && !opt.path
+    {
+        crate::devices::find(attr.file_type(), attr.rdev()).ok_or(AxError::NoSuchDevice)?
+    } else {
+        node
+    };
+    // each open of `/dev/ptmx` gets the master of a new pty
+    #[cfg(feature = "devpts")]
+    let node = if attr.is_char_device() && attr.rdev() == crate::devpts::PTMX_RDEV && !opt.path {
+        crate::devpts::open_ptmx(attr.ino())?
+    } else {
+        node
+    };
     node.open()?;
-    Ok(File::new(path.to_owned(), node, opt.to_cap(), opt.append))
+    Ok(File::new(path.to_owned(), node, opt.to_cap(), opt.append).with_options(opt))
 }
 
 /// Open a node as a directory, with permission checked.
@@ -390,11 +874,16 @@ pub fn open_dir(path: &AbsPath, node: VfsNodeRef, opt: &OpenOptions) -> AxResult
         return ax_err!(PermissionDenied);
     }
     node.open()?;
-    Ok(Directory::new(
-        path.to_owned(),
-        node,
-        opt.to_cap() | Cap::EXECUTE,
-    ))
+    let cap = if opt.path {
+        Cap::empty()
+    } else {
+        opt.to_cap() | Cap::EXECUTE
+    };
+    let mut dir = Directory::new(path.to_owned(), node, cap);
+    dir.noatime = opt.noatime;
+    dir.nonblocking = opt.non_blocking;
+    dir.path_only = opt.path;
+    Ok(dir)
 }
 
 pub fn open_fifo(path: &AbsPath, node: VfsNodeRef, opt: &OpenOptions) -> AxResult<File> {
@@ -406,7 +895,7 @@ pub fn open_fifo(path: &AbsPath, node: VfsNodeRef, opt: &OpenOptions) -> AxResul
         return ax_err!(PermissionDenied);
     }
     node.open_fifo(opt.read, opt.write, opt.non_blocking)?;
-    Ok(File::new(path.to_owned(), node, opt.to_cap(), opt.append))
+    Ok(File::new(path.to_owned(), node, opt.to_cap(), opt.append).with_options(opt))
 }
 
 /// Lookup and open a file at an arbitrary path.
@@ -423,25 +912,68 @@ pub fn open(path: &str, opt: &OpenOptions) -> AxResult<File> {
 ///
 /// This function will not check if the file exists, check it with [`lookup`] first.
 pub fn create_file(path: &AbsPath) -> AxResult {
-    root_dir().create(&path.to_rel(), VfsNodeType::File)
+    root_dir().create(&path.to_rel(), VfsNodeType::File)?;
+    notify::notify(path, FsEvent::CREATE);
+    Ok(())
 }
 
 pub fn create_fifo(path: &AbsPath) -> AxResult {
-    root_dir().create(&path.to_rel(), VfsNodeType::Fifo)
+    root_dir().create(&path.to_rel(), VfsNodeType::Fifo)?;
+    notify::notify(path, FsEvent::CREATE);
+    Ok(())
+}
+
+/// Create a symbolic link pointing to `target` given an absolute path.
+///
+/// This function will not check if the file exists, check it with [`lookup`] first.
+pub fn create_symlink(path: &AbsPath, target: &str) -> AxResult {
+    root_dir().symlink(&path.to_rel(), target)?;
+    notify::notify(path, FsEvent::CREATE);
+    Ok(())
+}
+
+/// Create a hard link `new` to the file at `old`, given absolute paths.
+///
+/// This function will not check if `new` exists, check it with [`lookup`] first.
+pub fn create_link(old: &AbsPath, new: &AbsPath) -> AxResult {
+    let node = lookup(old)?;
+    root_dir().link(&new.to_rel(), node)?;
+    notify::notify(old, FsEvent::ATTRIB);
+    notify::notify(new, FsEvent::CREATE);
+    Ok(())
+}
+
+/// Read the target of the symbolic link `node`.
+pub fn read_link(node: &VfsNodeRef) -> AxResult<String> {
+    let mut buf = vec![0; SYMLINK_MAX];
+    let len = node.readlink(&mut buf)?;
+    buf.truncate(len);
+    String::from_utf8(buf).map_err(|_| AxError::InvalidData)
+}
+
+/// Set the permission of a file given an absolute path.
+pub fn set_perm(path: &AbsPath, perm: FilePerm) -> AxResult {
+    lookup(path)?.set_perm(perm)?;
+    notify::notify(path, FsEvent::ATTRIB);
+    Ok(())
 }
 
 /// Create a directory given an absolute path.
 ///
 /// This function will not check if the directory exists, check it with [`lookup`] first.
 pub fn create_dir(path: &AbsPath) -> AxResult {
-    root_dir().create(&path.to_rel(), VfsNodeType::Dir)
+    root_dir().create(&path.to_rel(), VfsNodeType::Dir)?;
+    notify::notify(path, FsEvent::CREATE | FsEvent::ISDIR);
+    Ok(())
 }
 
 /// Create a directory recursively given an absolute path.
 ///
 /// This function will not check if the directory exists, check it with [`lookup`] first.
 pub fn create_dir_all(path: &AbsPath) -> AxResult {
-    root_dir().create_recursive(&path.to_rel(), VfsNodeType::Dir)
+    root_dir().create_recursive(&path.to_rel(), VfsNodeType::Dir)?;
+    notify::notify(path, FsEvent::CREATE | FsEvent::ISDIR);
+    Ok(())
 }
 
 /// Remove a file given an absolute path.
@@ -449,7 +981,10 @@ pub fn create_dir_all(path: &AbsPath) -> AxResult {
 /// This function will not check if the file exits or removeable,
 /// check it with [`lookup`] first.
 pub fn remove_file(path: &AbsPath) -> AxResult {
-    root_dir().unlink(&path.to_rel())
+    let file = notify::key_before_change(path);
+    root_dir().unlink(&path.to_rel())?;
+    notify::notify_file(path, file.as_ref(), FsEvent::DELETE);
+    Ok(())
 }
 
 /// Remove a directory given an absolute path.
@@ -457,7 +992,15 @@ pub fn remove_file(path: &AbsPath) -> AxResult {
 /// This function will not check if the directory exists or is empty,
 /// check it with [`lookup`] first.
 pub fn remove_dir(path: &AbsPath) -> AxResult {
-    root_dir().unlink(&path.to_rel())
+    let dir = notify::key_before_change(path);
+    root_dir().unlink(&path.to_rel())?;
+    notify::notify_file(path, dir.as_ref(), FsEvent::DELETE | FsEvent::ISDIR);
+    Ok(())
+}
+
+/// Check if two absolute paths are on the same filesystem.
+pub fn same_fs(a: &AbsPath, b: &AbsPath) -> bool {
+    root_dir().same_fs(a, b)
 }
 
 /// Check if a directory is a mount point.
@@ -465,12 +1008,48 @@ pub fn is_mount_point(path: &AbsPath) -> bool {
     root_dir().contains(path)
 }
 
+/// Get the usage of the filesystem that holds `path`.
+///
+/// This function will not check if the path exists, check it with [`lookup`]
+/// first.
+pub fn statfs(path: &AbsPath) -> AxResult<FileSystemInfo> {
+    root_dir().statfs(path)
+}
+
+/// Mount a filesystem of type `fstype` created from `source` and `options`
+/// (see [`fstype::new_fs`](crate::fstype::new_fs)) on the directory `target`.
+pub fn mount(source: &str, target: &AbsPath, fstype: &str, options: &str) -> AxResult {
+    let fs = crate::fstype::new_fs(fstype, source, options)?;
+    root_dir().mount(target.to_owned(), fs)
+}
+
+/// Unmount the filesystem mounted on `target`.
+pub fn umount(target: &AbsPath) -> AxResult {
+    root_dir().umount(target)
+}
+
 /// Rename a file given an old and a new absolute path.
 ///
 /// This function will not check if the old path or new path exists, check it with
 /// [`lookup`] first.
 pub fn rename(old: &AbsPath, new: &AbsPath) -> AxResult {
-    root_dir().rename(&old.to_rel(), &new.to_rel())
+    let is_dir = get_attr(old).is_ok_and(|attr| attr.is_dir());
+    let file = notify::key_before_change(old);
+    root_dir().rename(&old.to_rel(), &new.to_rel())?;
+    notify::notify_rename(old, new, file.as_ref(), is_dir);
+    Ok(())
+}
+
+/// Atomically exchange the files at `old` and `new`, which must both exist.
+pub fn exchange(old: &AbsPath, new: &AbsPath) -> AxResult {
+    let old_is_dir = get_attr(old).is_ok_and(|attr| attr.is_dir());
+    let new_is_dir = get_attr(new).is_ok_and(|attr| attr.is_dir());
+    let old_file = notify::key_before_change(old);
+    let new_file = notify::key_before_change(new);
+    root_dir().exchange(&old.to_rel(), &new.to_rel())?;
+    notify::notify_rename(old, new, old_file.as_ref(), old_is_dir);
+    notify::notify_rename(new, old, new_file.as_ref(), new_is_dir);
+    Ok(())
 }
 
 fn perm_to_cap(perm: FilePerm) -> Cap {
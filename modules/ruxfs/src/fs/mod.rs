@@ -25,5 +25,5 @@ cfg_if::cfg_if! {
 #[cfg(feature = "devfs")]
 pub use axfs_devfs as devfs;
 
-#[cfg(feature = "ramfs")]
+#[cfg(any(feature = "ramfs", feature = "shmfs", feature = "mqueuefs"))]
 pub use axfs_ramfs as ramfs;
@@ -0,0 +1,67 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Filesystem types, which create filesystems by name for the mounts given
+//! at boot or by `mount(2)`.
+//!
+//! Modules providing a filesystem (e.g. `rux9p`) register its type with
+//! [`register`]. `tmpfs` (also named `ramfs`) and `overlay` are built in,
+//! and the size of a `tmpfs` is limited by its `size=` option, e.g.
+//! `mount=tmpfs:tmpfs:/tmp:size=64m` at boot.
+
+use alloc::{collections::BTreeMap, sync::Arc};
+use axerrno::{ax_err, AxResult};
+use axfs_vfs::VfsOps;
+use spin::RwLock;
+
+/// Creates a filesystem from the `source` and the comma-separated `options`
+/// of a mount, like `mount -t <type> -o <options> <source> <target>`.
+pub type FsConstructor = fn(source: &str, options: &str) -> AxResult<Arc<dyn VfsOps>>;
+
+static FS_TYPES: RwLock<BTreeMap<&'static str, FsConstructor>> = RwLock::new(BTreeMap::new());
+
+/// Registers a filesystem type, replacing the one of the same name.
+pub fn register(name: &'static str, new_fs: FsConstructor) {
+    FS_TYPES.write().insert(name, new_fs);
+}
+
+/// Whether a filesystem type is known.
+pub fn exists(name: &str) -> bool {
+    builtin(name).is_some() || FS_TYPES.read().contains_key(name)
+}
+
+/// Creates a filesystem of the type `name`, failing with `NotFound` if the
+/// type is unknown.
+pub fn new_fs(name: &str, source: &str, options: &str) -> AxResult<Arc<dyn VfsOps>> {
+    let new_fs = builtin(name).or_else(|| FS_TYPES.read().get(name).copied());
+    match new_fs {
+        Some(new_fs) => new_fs(source, options),
+        None => ax_err!(NotFound, "unknown filesystem type"),
+    }
+}
+
+/// Splits mount options into `(key, value)` pairs, where the value is empty
+/// for flags like `ro`.
+pub fn parse_options(options: &str) -> impl Iterator<Item = (&str, &str)> {
+    options
+        .split(',')
+        .filter(|opt| !opt.is_empty())
+        .map(|opt| opt.split_once('=').unwrap_or((opt, "")))
+}
+
+fn builtin(name: &str) -> Option<FsConstructor> {
+    match name {
+        #[cfg(feature = "ramfs")]
+        "tmpfs" | "ramfs" => Some(|_, options| Ok(crate::mounts::tmpfs(options)?)),
+        "overlay" => Some(|_, options| {
+            crate::overlay::new_overlay(options, crate::fops::lookup).map(|fs| fs as _)
+        }),
+        _ => None,
+    }
+}
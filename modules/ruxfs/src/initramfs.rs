@@ -0,0 +1,266 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Unpacking an initramfs, a `newc` cpio archive as made by
+//! `find . | cpio -o -H newc`, into a ramfs.
+//!
+//! Several archives may be concatenated, with NUL padding in between, and the
+//! later entries replace the earlier ones like in Linux. Compressed archives
+//! are not supported.
+//!
+//! Modes, owners, modification times, directories, regular files, symlinks,
+//! FIFOs, hard links and device nodes are kept.
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+};
+use axerrno::{ax_err, AxError, AxResult};
+use axfs_vfs::{RelPath, VfsNodePerm, VfsNodeRef, VfsNodeType};
+use core::time::Duration;
+
+/// Size of the header of an entry.
+const HEADER_SIZE: usize = 110;
+/// Name of the entry ending an archive.
+const TRAILER: &str = "TRAILER!!!";
+/// Magic numbers of the compressions Linux supports for an initramfs: gzip,
+/// bzip2, xz, lzma, lzo, lz4 and zstd.
+const COMPRESSED_MAGICS: [&[u8]; 7] = [
+    b"\x1f\x8b",
+    b"BZh",
+    b"\xfd7zXZ\x00",
+    b"\x5d\x00\x00",
+    b"\x89LZO",
+    b"\x02\x21\x4c\x18",
+    b"\x28\xb5\x2f\xfd",
+];
+
+const S_IFMT: u32 = 0o170000;
+const S_IFSOCK: u32 = 0o140000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFREG: u32 = 0o100000;
+const S_IFBLK: u32 = 0o060000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFIFO: u32 = 0o010000;
+
+/// The header of an entry, whose fields are 8 hex digits each.
+struct Header {
+    ino: u32,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    nlink: u32,
+    mtime: u32,
+    filesize: u32,
+    devmajor: u32,
+    devminor: u32,
+    rdevmajor: u32,
+    rdevminor: u32,
+    namesize: u32,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> AxResult<Self> {
+        if COMPRESSED_MAGICS
+            .iter()
+            .any(|magic| data.starts_with(magic))
+        {
+            return ax_err!(
+                Unsupported,
+                "initramfs: compressed archives are not supported"
+            );
+        }
+        if data.len() < HEADER_SIZE {
+            return ax_err!(InvalidData, "initramfs: truncated header");
+        }
+        if &data[..6] != b"070701" && &data[..6] != b"070702" {
+            return ax_err!(InvalidData, "initramfs: not a newc cpio archive");
+        }
+        let field = |i: usize| {
+            let hex = &data[6 + i * 8..6 + (i + 1) * 8];
+            core::str::from_utf8(hex)
+                .ok()
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .ok_or(AxError::InvalidData)
+        };
+        Ok(Self {
+            ino: field(0)?,
+            mode: field(1)?,
+            uid: field(2)?,
+            gid: field(3)?,
+            nlink: field(4)?,
+            mtime: field(5)?,
+            filesize: field(6)?,
+            devmajor: field(7)?,
+            devminor: field(8)?,
+            rdevmajor: field(9)?,
+            rdevminor: field(10)?,
+            namesize: field(11)?,
+        })
+    }
+}
+
+/// Rounds `pos` up to the 4-byte boundary the fields of an archive are
+/// aligned to.
+const fn align4(pos: usize) -> usize {
+    (pos + 3) & !3
+}
+
+/// Unpacks the concatenated `newc` cpio archives in `data` into the directory
+/// `root` of a ramfs.
+pub fn unpack(root: &VfsNodeRef, data: &[u8]) -> AxResult {
+    // the first path of each file with hard links, by its device and inode
+    let mut links: BTreeMap<(u32, u32, u32), String> = BTreeMap::new();
+    let mut pos = 0;
+    loop {
+        // skip the padding between archives
+        while pos < data.len() && data[pos] == 0 {
+            pos += 1;
+        }
+        if pos >= data.len() {
+            return Ok(());
+        }
+        let header = Header::parse(&data[pos..])?;
+        let name_start = pos + HEADER_SIZE;
+        let name_end = name_start + header.namesize as usize;
+        let data_start = align4(name_end);
+        let data_end = data_start + header.filesize as usize;
+        if header.namesize == 0 || data_end > data.len() {
+            return ax_err!(InvalidData, "initramfs: truncated entry");
+        }
+        // the name size counts the trailing NUL
+        let name = core::str::from_utf8(&data[name_start..name_end - 1])
+            .map_err(|_| AxError::InvalidData)?;
+        pos = align4(data_end);
+        if name == TRAILER {
+            links.clear();
+            continue;
+        }
+        let path = name.trim_start_matches("./").trim_start_matches('/');
+        if path.is_empty() || path == "." {
+            // the root itself
+            if header.mode & S_IFMT == S_IFDIR {
+                set_attr(root, &header)?;
+            }
+            continue;
+        }
+        let contents = &data[data_start..data_end];
+        if let Err(e) = unpack_entry(root, path, &header, contents, &mut links) {
+            warn!("initramfs: failed to unpack {:?}: {:?}", path, e);
+        }
+    }
+}
+
+fn unpack_entry(
+    root: &VfsNodeRef,
+    path: &str,
+    header: &Header,
+    contents: &[u8],
+    links: &mut BTreeMap<(u32, u32, u32), String>,
+) -> AxResult {
+    let rel_path = RelPath::new_canonicalized(path);
+    let ty = match header.mode & S_IFMT {
+        S_IFDIR => VfsNodeType::Dir,
+        S_IFREG => VfsNodeType::File,
+        S_IFLNK => VfsNodeType::SymLink,
+        S_IFIFO => VfsNodeType::Fifo,
+        S_IFCHR => VfsNodeType::CharDevice,
+        S_IFBLK => VfsNodeType::BlockDevice,
+        S_IFSOCK => {
+            warn!("initramfs: skip socket {:?}", path);
+            return Ok(());
+        }
+        _ => return ax_err!(InvalidData, "initramfs: unknown file type"),
+    };
+    // a later entry replaces an earlier one, except merging directories
+    match root.clone().lookup(&rel_path) {
+        Ok(old) if ty == VfsNodeType::Dir && old.get_attr()?.is_dir() => {
+            return set_attr(&old, header);
+        }
+        Ok(_) => root.unlink(&rel_path)?,
+        Err(AxError::NotFound) => {}
+        Err(e) => return Err(e),
+    }
+    let (parent, name) = match rel_path.rsplit_once('/') {
+        Some((parent, name)) => {
+            root.create_recursive(&RelPath::new(parent), VfsNodeType::Dir)
+                .or_else(|e| match e {
+                    AxError::AlreadyExists => Ok(()),
+                    e => Err(e),
+                })?;
+            (root.clone().lookup(&RelPath::new(parent))?, name)
+        }
+        None => (root.clone(), rel_path.as_str()),
+    };
+    let name = RelPath::new(name);
+
+    // the data of a file with hard links comes with its last entry
+    let key = (header.devmajor, header.devminor, header.ino);
+    if ty == VfsNodeType::File && header.nlink > 1 {
+        if let Some(first) = links.get(&key) {
+            let node = root.clone().lookup(&RelPath::new(first))?;
+            parent.link(&name, node.clone())?;
+            if !contents.is_empty() {
+                node.truncate(0)?;
+                node.write_at(0, contents)?;
+            }
+            return set_attr(&node, header);
+        }
+        links.insert(key, rel_path.to_string());
+    }
+
+    match ty {
+        VfsNodeType::SymLink => {
+            let target = core::str::from_utf8(contents).map_err(|_| AxError::InvalidData)?;
+            parent.symlink(&name, target)?;
+        }
+        VfsNodeType::CharDevice | VfsNodeType::BlockDevice => {
+            let dir = parent
+                .as_any()
+                .downcast_ref::<axfs_ramfs::DirNode>()
+                .ok_or(AxError::Unsupported)?;
+            dir.create_device(&name, ty, makedev(header.rdevmajor, header.rdevminor))?;
+        }
+        _ => parent.create(&name, ty)?,
+    }
+    let node = parent.lookup(&name)?;
+    if ty == VfsNodeType::File && !contents.is_empty() {
+        node.write_at(0, contents)?;
+    }
+    set_attr(&node, header)
+}
+
+/// Sets the mode, owner and modification time of `node` from `header`.
+fn set_attr(node: &VfsNodeRef, header: &Header) -> AxResult {
+    // FIFOs and symlinks of the ramfs have fixed permissions
+    match node.set_perm(VfsNodePerm::from_bits_truncate(header.mode as u16 & 0o777)) {
+        Err(AxError::Unsupported) | Ok(()) => {}
+        Err(e) => return Err(e),
+    }
+    node.set_owner(header.uid, header.gid)?;
+    let mtime = Duration::from_secs(header.mtime as u64);
+    node.set_times(mtime, mtime)
+}
+
+/// Encodes a device number like Linux.
+#[cfg(feature = "devfs")]
+fn makedev(major: u32, minor: u32) -> u64 {
+    axfs_devfs::makedev(major, minor)
+}
+
+/// Encodes a device number like Linux.
+#[cfg(not(feature = "devfs"))]
+fn makedev(major: u32, minor: u32) -> u64 {
+    let (major, minor) = (major as u64, minor as u64);
+    ((major & 0xffff_f000) << 32)
+        | ((major & 0xfff) << 8)
+        | ((minor & 0xffff_ff00) << 12)
+        | (minor & 0xff)
+}
@@ -15,10 +15,20 @@
 //!
 //! - `fatfs`: Use [FAT] as the main filesystem and mount it on `/`. Requires
 //!    `blkfs` to be enabled.
-//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`. This feature is
+//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`, with the
+//!    nodes of the block and tty devices in [`devices`]. This feature is
 //!    **enabled** by default.
-//! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`. This feature is
+//! - `devpts`: Provide pseudo-terminals through `/dev/ptmx`, with the slaves
+//!    in the [`devpts`] filesystem mounted on `/dev/pts`. Requires `devfs` to
+//!    be enabled. This feature is **enabled** by default.
+//! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`, and allow the
+//!    root filesystem to be unpacked from an [`initramfs`]. This feature is
 //!    **enabled** by default.
+//! - `shmfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/dev/shm` for POSIX
+//!    shared memory objects, and provide the anonymous files of [`memfd`].
+//!    This feature is **enabled** by default.
+//! - `mqueuefs`: Mount [`axfs_ramfs::RamFileSystem`] on `/dev/mqueue`, where
+//!    POSIX message queues are named. This feature is **enabled** by default.
 //! - `myfs`: Allow users to define their custom filesystems to override the
 //!    default. In this case, [`MyFileSystemIf`] is required to be implemented
 //!    to create and initialize other filesystems. This feature is **disabled** by
@@ -42,9 +52,24 @@ mod mounts;
 pub mod api;
 #[cfg(feature = "blkfs")]
 pub mod dev;
+#[cfg(feature = "devfs")]
+pub mod devices;
+#[cfg(feature = "devpts")]
+pub mod devpts;
 pub mod fops;
+pub mod fstype;
+#[cfg(feature = "ramfs")]
+pub mod initramfs;
+pub mod lock;
+#[cfg(feature = "shmfs")]
+pub mod memfd;
+pub mod notify;
+pub mod overlay;
 pub mod root;
 
+#[cfg(all(test, feature = "ramfs"))]
+mod tests;
+
 // Re-export `axfs_vfs` path types.
 
 /// Canonicalized absolute path type. Requirements:
@@ -93,18 +118,31 @@ cfg_if::cfg_if! {
 use root::MountPoint;
 
 /// Initialize an empty filesystems by ramfs.
-#[cfg(not(any(feature = "blkfs", feature = "virtio-9p", feature = "net-9p")))]
+#[cfg(feature = "ramfs")]
 pub fn init_tempfs() -> MountPoint {
     MountPoint::new(AbsPath::new("/"), mounts::ramfs())
 }
 
+/// Initialize the root filesystem by a ramfs with the `newc` cpio archives of
+/// `initramfs` unpacked.
+#[cfg(feature = "ramfs")]
+pub fn init_initramfs(initramfs: &[&[u8]]) -> MountPoint {
+    info!("Unpack initramfs...");
+    MountPoint::new(AbsPath::new("/"), mounts::initramfs(initramfs))
+}
+
 /// Initializes filesystems by block devices.
 #[cfg(feature = "blkfs")]
-pub fn init_blkfs(mut blk_devs: AxDeviceContainer<AxBlockDevice>) -> MountPoint {
+///
+/// The first device holds the root filesystem and is taken out of `blk_devs`.
+pub fn init_blkfs(blk_devs: &mut AxDeviceContainer<AxBlockDevice>) -> MountPoint {
     info!("Initialize filesystems...");
 
     let dev = blk_devs.take_one().expect("No block device found!");
     info!("  use block device 0: {:?}", dev.device_name());
+    // keep the names of the other disks in `/dev` after the root disk's
+    #[cfg(feature = "devfs")]
+    devices::next_block_name(dev.device_name());
 
     let disk = self::dev::Disk::new(dev);
     cfg_if::cfg_if! {
@@ -160,6 +198,21 @@ pub fn prepare_commonfs(mount_points: &mut Vec<self::root::MountPoint>) {
     #[cfg(feature = "etcfs")]
     let mount_point = MountPoint::new(AbsPath::new("/etc"), mounts::etcfs().unwrap());
     mount_points.push(mount_point);
+
+    // Mount devpts for the slaves of pseudo-terminals
+    #[cfg(feature = "devpts")]
+    let mount_point = MountPoint::new(AbsPath::new("/dev/pts"), mounts::devpts());
+    mount_points.push(mount_point);
+
+    // Mount another ramfs for POSIX shared memory objects
+    #[cfg(feature = "shmfs")]
+    let mount_point = MountPoint::new(AbsPath::new("/dev/shm"), mounts::shmfs());
+    mount_points.push(mount_point);
+
+    // Mount another ramfs for the names of POSIX message queues
+    #[cfg(feature = "mqueuefs")]
+    let mount_point = MountPoint::new(AbsPath::new("/dev/mqueue"), mounts::mqueuefs());
+    mount_points.push(mount_point);
 }
 
 /// Initializes root filesystems.
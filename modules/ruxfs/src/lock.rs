@@ -0,0 +1,368 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Advisory file locks.
+//!
+//! Two independent kinds of locks are managed:
+//!
+//! - Record locks cover a byte range of a file. They are owned either by a
+//!   process (POSIX locks of `F_SETLK`) or by an open file (OFD locks of
+//!   `F_OFD_SETLK`), and the two conflict with each other.
+//! - Whole-file locks of `flock`, owned by an open file. They never conflict
+//!   with record locks.
+//!
+//! Besides, appends to a file are serialized by an append lock, so that
+//! finding the end of the file and writing there is atomic.
+//!
+//! Locks are keyed by the filesystem and inode number of the file, so they
+//! follow it across hard links and renames. Files of filesystems that do not
+//! report inode numbers fall back to their absolute path. Nothing here
+//! blocks: a conflicting request fails with `WouldBlock`, and the caller
+//! decides whether to wait.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    vec::Vec,
+};
+use axerrno::{ax_err, AxResult};
+use axfs_vfs::{VfsFileLock, VfsLockType};
+use spinlock::SpinNoIrq;
+
+/// Type of a file lock.
+pub type LockType = VfsLockType;
+
+/// Maximum length of a wait-for chain followed by deadlock detection.
+const MAX_DEADLOCK_ITERATIONS: usize = 64;
+
+/// The owner of a file lock.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum LockOwner {
+    /// A process, identified by its pid.
+    Process(u64),
+    /// An open file, identified by the address of the open file object.
+    OpenFile(usize),
+}
+
+/// Identifies a locked file.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum FileKey {
+    /// An inode, with the address of the filesystem object holding it.
+    Inode {
+        /// Address of the filesystem.
+        fs: usize,
+        /// Inode number.
+        ino: u64,
+    },
+    /// A file of a filesystem without inode numbers, by its absolute path.
+    Path(String),
+}
+
+/// A lock on the bytes `start..end` of a file.
+#[derive(Debug, Clone, Copy)]
+pub struct FileLock {
+    /// Owner of the lock.
+    pub owner: LockOwner,
+    /// Lock type.
+    pub ty: LockType,
+    /// Offset of the first locked byte.
+    pub start: u64,
+    /// Offset after the last locked byte, `u64::MAX` means up to the end of
+    /// the file.
+    pub end: u64,
+}
+
+impl FileLock {
+    /// Creates a lock on the whole file, as used by `flock`.
+    pub const fn whole_file(owner: LockOwner, ty: LockType) -> Self {
+        Self {
+            owner,
+            ty,
+            start: 0,
+            end: u64::MAX,
+        }
+    }
+
+    fn overlaps(&self, other: &FileLock) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    fn conflicts(&self, other: &FileLock) -> bool {
+        self.owner != other.owner
+            && self.overlaps(other)
+            && (self.ty == LockType::Write || other.ty == LockType::Write)
+    }
+
+    /// Converts to the lock passed to filesystems, placed by the process
+    /// `pid`, which other clients of the filesystem see as the owner.
+    pub fn to_vfs(&self, pid: u64) -> VfsFileLock {
+        let pid = match self.owner {
+            LockOwner::Process(owner) => owner,
+            LockOwner::OpenFile(_) => pid,
+        };
+        VfsFileLock {
+            ty: self.ty,
+            start: self.start,
+            len: if self.end == u64::MAX {
+                0
+            } else {
+                self.end - self.start
+            },
+            pid: pid as u32,
+        }
+    }
+
+    /// Converts a lock reported by a filesystem, owned by `pid`.
+    pub fn from_vfs(lock: &VfsFileLock) -> Self {
+        Self {
+            owner: LockOwner::Process(lock.pid as u64),
+            ty: lock.ty,
+            start: lock.start,
+            end: if lock.len == 0 {
+                u64::MAX
+            } else {
+                lock.start.saturating_add(lock.len)
+            },
+        }
+    }
+}
+
+struct LockTable {
+    records: BTreeMap<FileKey, Vec<FileLock>>,
+    flocks: BTreeMap<FileKey, Vec<FileLock>>,
+    /// The record lock each blocked process waits for.
+    waiting: BTreeMap<u64, (FileKey, FileLock)>,
+}
+
+static LOCKS: SpinNoIrq<LockTable> = SpinNoIrq::new(LockTable {
+    records: BTreeMap::new(),
+    flocks: BTreeMap::new(),
+    waiting: BTreeMap::new(),
+});
+
+fn find_conflict(locks: Option<&Vec<FileLock>>, lock: &FileLock) -> Option<FileLock> {
+    locks?.iter().find(|l| l.conflicts(lock)).copied()
+}
+
+/// Replaces the range of `lock` in the locks of its owner, merging adjacent
+/// locks of the same type. An unlock just clears the range.
+fn apply_record(locks: &mut Vec<FileLock>, lock: FileLock) {
+    locks.sort_unstable_by_key(|l| l.start);
+    let mut new = lock;
+    let mut result = Vec::with_capacity(locks.len() + 2);
+    for l in locks.drain(..) {
+        if l.owner != lock.owner {
+            result.push(l);
+            continue;
+        }
+        let touches = l.start <= new.end && new.start <= l.end;
+        if lock.ty != LockType::Unlock && l.ty == lock.ty && touches {
+            new.start = new.start.min(l.start);
+            new.end = new.end.max(l.end);
+        } else if l.overlaps(&lock) {
+            // keep the parts outside of the new range
+            if l.start < lock.start {
+                result.push(FileLock {
+                    end: lock.start,
+                    ..l
+                });
+            }
+            if lock.end < l.end {
+                result.push(FileLock {
+                    start: lock.end,
+                    ..l
+                });
+            }
+        } else {
+            result.push(l);
+        }
+    }
+    if lock.ty != LockType::Unlock {
+        result.push(new);
+    }
+    *locks = result;
+}
+
+/// Gets the first record lock of another owner that conflicts with `lock`.
+pub fn test_lock(key: &FileKey, lock: &FileLock) -> Option<FileLock> {
+    find_conflict(LOCKS.lock().records.get(key), lock)
+}
+
+/// Places or removes the record lock `lock`.
+///
+/// Returns `WouldBlock` if it conflicts with a lock of another owner.
+pub fn set_lock(key: &FileKey, lock: FileLock) -> AxResult {
+    let mut table = LOCKS.lock();
+    if lock.ty != LockType::Unlock && find_conflict(table.records.get(key), &lock).is_some() {
+        return ax_err!(WouldBlock);
+    }
+    let locks = table.records.entry(key.clone()).or_default();
+    apply_record(locks, lock);
+    if locks.is_empty() {
+        table.records.remove(key);
+    }
+    Ok(())
+}
+
+/// Places or removes the `flock` lock `lock`, converting any lock its owner
+/// already holds.
+///
+/// Returns `WouldBlock` if it conflicts with a lock of another owner.
+pub fn set_flock(key: &FileKey, lock: FileLock) -> AxResult {
+    let mut table = LOCKS.lock();
+    if lock.ty != LockType::Unlock && find_conflict(table.flocks.get(key), &lock).is_some() {
+        return ax_err!(WouldBlock);
+    }
+    let locks = table.flocks.entry(key.clone()).or_default();
+    locks.retain(|l| l.owner != lock.owner);
+    if lock.ty != LockType::Unlock {
+        locks.push(lock);
+    }
+    if locks.is_empty() {
+        table.flocks.remove(key);
+    }
+    Ok(())
+}
+
+/// Whether `lock` could be placed now, for wait conditions.
+///
+/// It must not spin, as wait conditions may run with the scheduler locked,
+/// so a busy table is reported as available and the caller simply retries.
+pub fn lock_available(key: &FileKey, lock: &FileLock, flock: bool) -> bool {
+    LOCKS.try_lock().map_or(true, |table| {
+        let locks = if flock {
+            table.flocks.get(key)
+        } else {
+            table.records.get(key)
+        };
+        find_conflict(locks, lock).is_none()
+    })
+}
+
+/// Gets the record locks `owner` holds on the file, to restore them later
+/// with [`restore_locks`].
+pub fn owner_locks(key: &FileKey, owner: LockOwner) -> Vec<FileLock> {
+    LOCKS.lock().records.get(key).map_or(Vec::new(), |locks| {
+        locks.iter().filter(|l| l.owner == owner).copied().collect()
+    })
+}
+
+/// Replaces the record locks `owner` holds on the file with `saved`.
+pub fn restore_locks(key: &FileKey, owner: LockOwner, saved: Vec<FileLock>) {
+    let mut table = LOCKS.lock();
+    let locks = table.records.entry(key.clone()).or_default();
+    locks.retain(|l| l.owner != owner);
+    locks.extend(saved);
+    if locks.is_empty() {
+        table.records.remove(key);
+    }
+}
+
+/// Records that the process owning `lock` is about to wait for it.
+///
+/// Returns false without recording anything if waiting would deadlock, that
+/// is if the holder of a conflicting lock waits, directly or through other
+/// processes, for a lock of this process.
+pub fn start_waiting(key: &FileKey, lock: &FileLock) -> bool {
+    let LockOwner::Process(pid) = lock.owner else {
+        // OFD locks do not take part in deadlock detection
+        return true;
+    };
+    let mut table = LOCKS.lock();
+    let mut blocker = find_conflict(table.records.get(key), lock);
+    for _ in 0..MAX_DEADLOCK_ITERATIONS {
+        let Some(LockOwner::Process(holder)) = blocker.map(|l| l.owner) else {
+            break;
+        };
+        if holder == pid {
+            return false;
+        }
+        blocker = table
+            .waiting
+            .get(&holder)
+            .and_then(|(key, wanted)| find_conflict(table.records.get(key), wanted));
+    }
+    table.waiting.insert(pid, (key.clone(), *lock));
+    true
+}
+
+/// Records that the process owning `lock` no longer waits.
+pub fn stop_waiting(lock: &FileLock) {
+    if let LockOwner::Process(pid) = lock.owner {
+        LOCKS.lock().waiting.remove(&pid);
+    }
+}
+
+/// Removes all locks `owner` holds on the file.
+///
+/// Returns whether it held any record lock.
+pub fn release_locks(key: &FileKey, owner: LockOwner) -> bool {
+    let mut table = LOCKS.lock();
+    if let Some(locks) = table.flocks.get_mut(key) {
+        locks.retain(|l| l.owner != owner);
+        if locks.is_empty() {
+            table.flocks.remove(key);
+        }
+    }
+    let Some(locks) = table.records.get_mut(key) else {
+        return false;
+    };
+    let count = locks.len();
+    locks.retain(|l| l.owner != owner);
+    let released = locks.len() != count;
+    if locks.is_empty() {
+        table.records.remove(key);
+    }
+    released
+}
+
+/// Removes all record locks of the process `pid`, when it exits.
+///
+/// Returns the files it held locks on.
+pub fn release_process_locks(pid: u64) -> Vec<FileKey> {
+    let owner = LockOwner::Process(pid);
+    let mut table = LOCKS.lock();
+    table.waiting.remove(&pid);
+    let mut released = Vec::new();
+    table.records.retain(|key, locks| {
+        let count = locks.len();
+        locks.retain(|l| l.owner != owner);
+        if locks.len() != count {
+            released.push(key.clone());
+        }
+        !locks.is_empty()
+    });
+    released
+}
+
+/// Files with an append in progress.
+static APPENDING: SpinNoIrq<BTreeSet<FileKey>> = SpinNoIrq::new(BTreeSet::new());
+
+/// The append lock of a file, released on drop.
+pub struct AppendGuard(FileKey);
+
+impl Drop for AppendGuard {
+    fn drop(&mut self) {
+        APPENDING.lock().remove(&self.0);
+    }
+}
+
+/// Takes the append lock of the file `key`, or returns `None` if another
+/// append to it is in progress.
+pub fn try_lock_append(key: FileKey) -> Option<AppendGuard> {
+    if !APPENDING.lock().insert(key.clone()) {
+        return None;
+    }
+    Some(AppendGuard(key))
+}
+
+/// Whether an append to the file `key` is in progress, for wait conditions.
+pub fn append_locked(key: &FileKey) -> bool {
+    APPENDING.lock().contains(key)
+}
@@ -0,0 +1,50 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Anonymous memory-backed files, as created by `memfd_create`.
+//!
+//! The files live in a ramfs that is not mounted anywhere, and are unlinked
+//! right after creation, so they are freed with their last opened file.
+
+use alloc::format;
+use axerrno::AxResult;
+use axfs_vfs::{AbsPath, RelPath, VfsNodeOps, VfsNodeType};
+use capability::Cap;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spin::Once;
+
+use crate::fops::File;
+use crate::fs::ramfs::{FileNode, RamFileSystem};
+
+static MEMFD_FS: Once<RamFileSystem> = Once::new();
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Creates an anonymous file, opened for reading and writing.
+///
+/// `name` only shows in the path of the file, which is `/memfd:<name> (deleted)`
+/// as on Linux. Unless `allow_sealing` is set, the file is sealed against
+/// adding seals.
+pub fn create(name: &str, allow_sealing: bool) -> AxResult<File> {
+    let root = MEMFD_FS.call_once(RamFileSystem::new).root_dir_node();
+    let entry = format!("{}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    root.create_node(&entry, VfsNodeType::File)?;
+    let node = root.clone().lookup(&RelPath::new(&entry));
+    root.remove_node(&entry)?;
+    let node = node?;
+    if allow_sealing {
+        node.as_any()
+            .downcast_ref::<FileNode>()
+            .expect("memfd should be a ramfs file")
+            .allow_sealing();
+    }
+    node.open()?;
+    let path = AbsPath::new_owned(format!("/memfd:{} (deleted)", name));
+    Ok(File::new(path, node, Cap::READ | Cap::WRITE, false))
+}
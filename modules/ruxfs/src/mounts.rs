@@ -8,7 +8,7 @@
  */
 
 use alloc::sync::Arc;
-use axfs_vfs::{RelPath, VfsNodeType, VfsOps, VfsResult};
+use axfs_vfs::{RelPath, VfsError, VfsNodeType, VfsOps, VfsResult};
 
 #[cfg(feature = "alloc")]
 use crate::arch::{get_cpuinfo, get_meminfo};
@@ -25,7 +25,20 @@ pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     devfs.add("zero", Arc::new(zero));
     devfs.add("random", Arc::new(random));
     devfs.add("urandom", Arc::new(urandom));
-    Arc::new(devfs)
+    let devfs = Arc::new(devfs);
+    crate::devices::init_devfs(devfs.clone());
+    crate::devices::init_tty_devices();
+    #[cfg(feature = "devpts")]
+    {
+        let ptmx = crate::devpts::PtmxDev::new(crate::devices::alloc_ino());
+        crate::devices::register("ptmx", Arc::new(ptmx));
+    }
+    devfs
+}
+
+#[cfg(feature = "devpts")]
+pub(crate) fn devpts() -> Arc<crate::devpts::DevPtsFileSystem> {
+    Arc::new(crate::devpts::DevPtsFileSystem::new())
 }
 
 #[cfg(feature = "ramfs")]
@@ -33,6 +46,82 @@ pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
     Arc::new(fs::ramfs::RamFileSystem::new())
 }
 
+/// Creates the ramfs of a `tmpfs` mount, holding at most the `size=` option
+/// of bytes, with an optional `k`, `m` or `g` suffix, or a `%` suffix for a
+/// percentage of the memory. A size of 0 means no limit.
+#[cfg(feature = "ramfs")]
+pub(crate) fn tmpfs(options: &str) -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
+    let mut max_size = 0;
+    for (key, value) in crate::fstype::parse_options(options) {
+        match key {
+            "size" => {
+                max_size = parse_size(value).ok_or_else(|| {
+                    error!("tmpfs: invalid size {:?}", value);
+                    VfsError::InvalidInput
+                })?
+            }
+            _ => warn!("tmpfs: ignore mount option {:?}", key),
+        }
+    }
+    Ok(Arc::new(if max_size > 0 {
+        fs::ramfs::RamFileSystem::with_max_size(max_size)
+    } else {
+        fs::ramfs::RamFileSystem::new()
+    }))
+}
+
+/// Parses a size like `64m` or `50%`.
+#[cfg(feature = "ramfs")]
+fn parse_size(value: &str) -> Option<u64> {
+    if let Some(percent) = value.strip_suffix('%') {
+        let percent = percent.parse::<u64>().ok()?;
+        return u64::try_from(total_memory()? as u128 * percent as u128 / 100).ok();
+    }
+    let (num, shift) = match value.as_bytes().last()? {
+        b'k' | b'K' => (&value[..value.len() - 1], 10),
+        b'm' | b'M' => (&value[..value.len() - 1], 20),
+        b'g' | b'G' => (&value[..value.len() - 1], 30),
+        _ => (value, 0),
+    };
+    num.parse::<u64>().ok()?.checked_mul(1 << shift)
+}
+
+/// Size of the memory of the page allocator.
+#[cfg(all(feature = "ramfs", feature = "alloc"))]
+fn total_memory() -> Option<u64> {
+    let allocator = axalloc::global_allocator();
+    let pages = allocator.used_pages() + allocator.available_pages();
+    Some((pages * memory_addr::PAGE_SIZE_4K) as u64)
+}
+
+/// Size of the memory, unknown without the page allocator.
+#[cfg(all(feature = "ramfs", not(feature = "alloc")))]
+fn total_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(feature = "ramfs")]
+pub(crate) fn initramfs(archives: &[&[u8]]) -> Arc<fs::ramfs::RamFileSystem> {
+    let ramfs = fs::ramfs::RamFileSystem::new();
+    let root = ramfs.root_dir();
+    for archive in archives {
+        if let Err(e) = crate::initramfs::unpack(&root, archive) {
+            error!("failed to unpack initramfs: {:?}", e);
+        }
+    }
+    Arc::new(ramfs)
+}
+
+#[cfg(feature = "shmfs")]
+pub(crate) fn shmfs() -> Arc<fs::ramfs::RamFileSystem> {
+    Arc::new(fs::ramfs::RamFileSystem::new())
+}
+
+#[cfg(feature = "mqueuefs")]
+pub(crate) fn mqueuefs() -> Arc<fs::ramfs::RamFileSystem> {
+    Arc::new(fs::ramfs::RamFileSystem::new())
+}
+
 #[cfg(feature = "procfs")]
 pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
     let procfs = fs::ramfs::RamFileSystem::new();
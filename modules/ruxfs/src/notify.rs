@@ -0,0 +1,173 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! File change notifications.
+//!
+//! Changes made through [`fops`](crate::fops) are reported to the registered
+//! [`FsEventListener`]s, which is how `inotify` is implemented. Changes made
+//! behind the back of RuxOS, such as by a 9P server, are not seen.
+//!
+//! Files are identified by their [`FileKey`], so a listener watching a file
+//! or a directory keeps seeing its changes after it is renamed.
+
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use axfs_vfs::{AbsPath, VfsNodeOps, VfsNodeRef};
+use spinlock::SpinNoIrq;
+
+use crate::fops;
+use crate::lock::FileKey;
+use crate::root::RootDirectory;
+
+bitflags::bitflags! {
+    /// Kinds of file change, with the same values as `inotify` events.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct FsEvent: u32 {
+        /// File was modified.
+        const MODIFY = 0x2;
+        /// Metadata changed.
+        const ATTRIB = 0x4;
+        /// File opened for writing was closed.
+        const CLOSE_WRITE = 0x8;
+        /// File was moved away from the path.
+        const MOVED_FROM = 0x40;
+        /// File was moved to the path.
+        const MOVED_TO = 0x80;
+        /// File was created at the path.
+        const CREATE = 0x100;
+        /// File was deleted from the path.
+        const DELETE = 0x200;
+        /// The object is a directory.
+        const ISDIR = 0x4000_0000;
+    }
+}
+
+/// A receiver of file change notifications.
+pub trait FsEventListener: Send + Sync {
+    /// Called after the entry `name` of the directory `dir` changed, `file`
+    /// being the changed file itself. Either is `None` if it is not found,
+    /// and `dir` is also `None` for the root directory.
+    ///
+    /// `cookie` is non-zero for the two events of a rename, and is the same
+    /// for both of them.
+    fn on_event(
+        &self,
+        dir: Option<&FileKey>,
+        name: &str,
+        file: Option<&FileKey>,
+        event: FsEvent,
+        cookie: u32,
+    );
+}
+
+static LISTENERS: SpinNoIrq<Vec<Weak<dyn FsEventListener>>> = SpinNoIrq::new(Vec::new());
+
+static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+/// Registers a listener, which is dropped from the list once it is freed.
+pub fn add_listener(listener: Weak<dyn FsEventListener>) {
+    let mut listeners = LISTENERS.lock();
+    listeners.retain(|l| l.strong_count() > 0);
+    listeners.push(listener);
+}
+
+/// Identifies the file at `path` as listeners see it, `None` if it does not
+/// exist.
+pub fn watch_key(path: &AbsPath) -> Option<FileKey> {
+    key_in(&fops::root_dir(), path)
+}
+
+fn key_in(root: &Arc<RootDirectory>, path: &AbsPath) -> Option<FileKey> {
+    let node = root.clone().lookup(&path.to_rel()).ok()?;
+    fops::file_key(root, path, &node).ok()
+}
+
+/// Identifies the file at `path` before a change that removes or moves it,
+/// `None` if nothing listens.
+pub(crate) fn key_before_change(path: &AbsPath) -> Option<FileKey> {
+    if LISTENERS.lock().is_empty() {
+        return None;
+    }
+    watch_key(path)
+}
+
+/// Reports that the file at `path` changed.
+pub fn notify(path: &AbsPath, event: FsEvent) {
+    if LISTENERS.lock().is_empty() {
+        return;
+    }
+    let root = fops::root_dir();
+    notify_with_cookie(&root, path, key_in(&root, path).as_ref(), event, 0);
+}
+
+/// Reports that the file `node`, opened at `path` in `root`, changed. It is
+/// identified by the node, as it may have been renamed since it was opened.
+pub(crate) fn notify_node(
+    root: &Arc<RootDirectory>,
+    path: &AbsPath,
+    node: &VfsNodeRef,
+    event: FsEvent,
+) {
+    if LISTENERS.lock().is_empty() {
+        return;
+    }
+    let file = fops::file_key(root, path, node).ok();
+    notify_with_cookie(root, path, file.as_ref(), event, 0);
+}
+
+/// Reports that `file`, found at `path` before the change, changed. Used when
+/// it is no longer there, such as after it was removed.
+pub(crate) fn notify_file(path: &AbsPath, file: Option<&FileKey>, event: FsEvent) {
+    if LISTENERS.lock().is_empty() {
+        return;
+    }
+    notify_with_cookie(&fops::root_dir(), path, file, event, 0);
+}
+
+/// Reports the rename of `file` from `old` to `new`.
+pub(crate) fn notify_rename(old: &AbsPath, new: &AbsPath, file: Option<&FileKey>, is_dir: bool) {
+    if LISTENERS.lock().is_empty() {
+        return;
+    }
+    let cookie = NEXT_COOKIE.fetch_add(1, Ordering::Relaxed).max(1);
+    let dir = if is_dir {
+        FsEvent::ISDIR
+    } else {
+        FsEvent::empty()
+    };
+    let root = fops::root_dir();
+    notify_with_cookie(&root, old, file, FsEvent::MOVED_FROM | dir, cookie);
+    notify_with_cookie(&root, new, file, FsEvent::MOVED_TO | dir, cookie);
+}
+
+fn notify_with_cookie(
+    root: &Arc<RootDirectory>,
+    path: &AbsPath,
+    file: Option<&FileKey>,
+    event: FsEvent,
+    cookie: u32,
+) {
+    // call the listeners without holding the list
+    let listeners: Vec<Arc<dyn FsEventListener>> =
+        LISTENERS.lock().iter().filter_map(Weak::upgrade).collect();
+    if listeners.is_empty() {
+        return;
+    }
+    let (dir, name) = match path.rfind('/') {
+        Some(0) if path.len() == 1 => (None, ""),
+        Some(0) => (key_in(root, &AbsPath::new("/")), &path[1..]),
+        Some(i) => (key_in(root, &AbsPath::new(&path[..i])), &path[i + 1..]),
+        None => return,
+    };
+    for listener in listeners {
+        listener.on_event(dir.as_ref(), name, file, event, cookie);
+    }
+}
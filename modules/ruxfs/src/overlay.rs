@@ -0,0 +1,853 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! The overlay filesystem, which merges a read-only lower directory tree
+//! with a writable upper one, e.g. an immutable rootfs shared by 9P with the
+//! changes of an instance kept in a ramfs.
+//!
+//! - A name in the upper layer hides the same name in the lower one, unless
+//!   both are directories, whose entries are then merged.
+//! - A file of the lower layer is copied up, along with its parent
+//!   directories, before it is changed.
+//! - Removing a name that exists in the lower layer leaves a whiteout, a
+//!   file `.wh.<name>` in the upper layer, which hides it.
+//! - A directory created in place of a whiteout is marked opaque by a file
+//!   `.wh..wh..opq` in it, so that the lower directory doesn't show through.
+//!
+//! The names starting with `.wh.` are reserved for these markers. Renaming a
+//! directory that exists in the lower layer fails with `CrossesDevices`, as
+//! on Linux without `redirect_dir`, and `mv` falls back to copying it.
+
+use alloc::{
+    collections::BTreeSet,
+    format,
+    string::{String, ToString},
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use axerrno::{ax_err, AxError, AxResult};
+use axfs_vfs::{
+    AbsPath, PollObserver, RelPath, VfsDirEntry, VfsFileLock, VfsFileSeals, VfsNodeAttr,
+    VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType, VfsOps, VfsResult,
+};
+use axio::PollState;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+use spin::{Mutex, Once, RwLock};
+
+use crate::fstype::parse_options;
+
+const WHITEOUT_PREFIX: &str = ".wh.";
+const OPAQUE_MARKER: &str = ".wh..wh..opq";
+
+/// Creates an overlay from the options `lowerdir=<path>[,upperdir=<path>]`,
+/// looking up the directories of the layers by `lookup`.
+///
+/// Without `upperdir`, the changes are kept in a new ramfs.
+pub fn new_overlay(
+    options: &str,
+    lookup: impl Fn(&AbsPath) -> AxResult<VfsNodeRef>,
+) -> AxResult<Arc<OverlayFileSystem>> {
+    let (mut lower, mut upper) = (None, None);
+    for (key, value) in parse_options(options) {
+        match key {
+            "lowerdir" => lower = Some(lookup(&AbsPath::new_canonicalized(value))?),
+            "upperdir" => upper = Some(lookup(&AbsPath::new_canonicalized(value))?),
+            // the changes are made in place in the upper layer
+            "workdir" => {}
+            _ => warn!("overlay: ignore mount option {:?}", key),
+        }
+    }
+    let lower = lower.ok_or(AxError::InvalidInput)?;
+    let upper = match upper {
+        Some(upper) => upper,
+        #[cfg(feature = "ramfs")]
+        None => crate::mounts::ramfs().root_dir(),
+        #[cfg(not(feature = "ramfs"))]
+        None => return ax_err!(InvalidInput, "overlay: upperdir is required"),
+    };
+    for layer in [&lower, &upper] {
+        if !layer.get_attr()?.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+    }
+    Ok(Arc::new(OverlayFileSystem::new(lower, upper)))
+}
+
+/// The state of the layers, shared by all the nodes.
+struct Layers {
+    /// The root directory of the upper layer.
+    upper: VfsNodeRef,
+    /// Bumped by each copy-up, after which the nodes of the lower layer look
+    /// for their copies in the upper one.
+    copy_ups: AtomicU64,
+    /// Serializes the renames of the copies into place.
+    copy_up_lock: Mutex<()>,
+    /// Numbers the temporary names of the copies being made.
+    next_temp: AtomicU64,
+}
+
+/// A node of the overlay, backed by a node of either layer or both.
+pub struct OverlayNode {
+    layers: Arc<Layers>,
+    /// The path from the root of the overlay, which is the same in both layers.
+    path: String,
+    ty: VfsNodeType,
+    upper: RwLock<Option<VfsNodeRef>>,
+    /// The value of `copy_ups` when the upper layer was last looked up.
+    upper_seen: AtomicU64,
+    /// The node in the lower layer, if it's merged with the upper one or
+    /// there isn't one.
+    lower: Option<VfsNodeRef>,
+    /// Whether a node of the same name is in the lower layer, which needs a
+    /// whiteout when this one is removed.
+    covers_lower: bool,
+    /// The parent directory, `None` for the root.
+    parent: Option<Arc<OverlayNode>>,
+    /// The parent of the mount point, for the root.
+    mount_parent: Once<VfsNodeRef>,
+    this: Weak<OverlayNode>,
+}
+
+impl OverlayNode {
+    fn new(
+        layers: Arc<Layers>,
+        path: String,
+        ty: VfsNodeType,
+        upper: Option<VfsNodeRef>,
+        lower: Option<VfsNodeRef>,
+        covers_lower: bool,
+        parent: Option<Arc<OverlayNode>>,
+    ) -> Arc<Self> {
+        let upper_seen = layers.copy_ups.load(Ordering::Acquire);
+        Arc::new_cyclic(|this| Self {
+            layers,
+            path,
+            ty,
+            upper: RwLock::new(upper),
+            upper_seen: AtomicU64::new(upper_seen),
+            lower,
+            covers_lower,
+            parent,
+            mount_parent: Once::new(),
+            this: this.clone(),
+        })
+    }
+
+    fn this(&self) -> VfsResult<Arc<Self>> {
+        self.this.upgrade().ok_or(AxError::NotFound)
+    }
+
+    fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or("")
+    }
+
+    fn child_path(&self, name: &str) -> String {
+        if self.path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.path, name)
+        }
+    }
+
+    /// The node in the upper layer, if any.
+    fn upper(&self) -> Option<VfsNodeRef> {
+        if let Some(upper) = self.upper.read().clone() {
+            return Some(upper);
+        }
+        // it may have been copied up through another node of the same path
+        let copy_ups = self.layers.copy_ups.load(Ordering::Acquire);
+        if self.upper_seen.swap(copy_ups, Ordering::AcqRel) == copy_ups {
+            return None;
+        }
+        let upper = self
+            .layers
+            .upper
+            .clone()
+            .lookup(&RelPath::new(&self.path))
+            .ok()
+            .filter(|upper| {
+                upper
+                    .get_attr()
+                    .is_ok_and(|attr| attr.file_type() == self.ty)
+            })?;
+        *self.upper.write() = Some(upper.clone());
+        Some(upper)
+    }
+
+    /// The node seen through the overlay, the upper one if any.
+    fn real(&self) -> VfsNodeRef {
+        match self.upper() {
+            Some(upper) => upper,
+            None => self.lower.clone().unwrap(),
+        }
+    }
+
+    /// Whether the lower directory is hidden by this one.
+    fn is_opaque(&self) -> bool {
+        self.upper()
+            .is_some_and(|upper| upper.lookup(&RelPath::new(OPAQUE_MARKER)).is_ok())
+    }
+
+    fn is_whiteout(&self, name: &str) -> bool {
+        self.upper().is_some_and(|upper| {
+            upper
+                .lookup(&RelPath::new(&format!("{}{}", WHITEOUT_PREFIX, name)))
+                .is_ok()
+        })
+    }
+
+    /// Looks up `name` in this directory.
+    fn lookup_child(self: &Arc<Self>, name: &str) -> VfsResult<Arc<Self>> {
+        if !self.ty.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+        if name.starts_with(WHITEOUT_PREFIX) {
+            return ax_err!(NotFound);
+        }
+        let upper = match self.upper() {
+            Some(dir) => match dir.lookup(&RelPath::new(name)) {
+                Ok(node) => Some(node),
+                Err(AxError::NotFound) => None,
+                Err(e) => return Err(e),
+            },
+            None => None,
+        };
+        let upper_ty = match &upper {
+            Some(node) => Some(node.get_attr()?.file_type()),
+            None => None,
+        };
+        let lower = match &self.lower {
+            Some(dir) if !self.is_whiteout(name) && !self.is_opaque() => {
+                match dir.clone().lookup(&RelPath::new(name)) {
+                    Ok(node) => Some(node),
+                    Err(AxError::NotFound) => None,
+                    Err(e) => return Err(e),
+                }
+            }
+            _ => None,
+        };
+        let covers_lower = lower.is_some();
+        // only directories are merged
+        let lower = match (upper_ty, lower) {
+            (Some(ty), Some(node)) if !ty.is_dir() || !node.get_attr()?.is_dir() => None,
+            (_, lower) => lower,
+        };
+        let ty = match (upper_ty, &lower) {
+            (Some(ty), _) => ty,
+            (None, Some(node)) => node.get_attr()?.file_type(),
+            (None, None) => return ax_err!(NotFound),
+        };
+        Ok(Self::new(
+            self.layers.clone(),
+            self.child_path(name),
+            ty,
+            upper,
+            lower,
+            covers_lower,
+            Some(self.clone()),
+        ))
+    }
+
+    /// Looks up the directory at `path`, relative to this one.
+    fn lookup_dir(self: &Arc<Self>, path: &RelPath) -> VfsResult<Arc<Self>> {
+        let mut dir = self.clone();
+        for name in path.split('/') {
+            dir = match name {
+                "" | "." => dir,
+                ".." => dir.parent.clone().ok_or(AxError::PermissionDenied)?,
+                _ => dir.lookup_child(name)?,
+            };
+        }
+        if !dir.ty.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+        Ok(dir)
+    }
+
+    /// Copies this node up to the upper layer, if it's not there yet, and
+    /// returns the upper node.
+    ///
+    /// The copy is made under a temporary name without the lock held, and
+    /// renamed into place by whoever finishes first.
+    fn copy_up(&self) -> VfsResult<VfsNodeRef> {
+        if let Some(upper) = self.upper() {
+            return Ok(upper);
+        }
+        // the root is always in the upper layer
+        let parent = self.parent.as_ref().ok_or(AxError::NotFound)?;
+        let upper_dir = parent.copy_up()?;
+
+        let lower = self.lower.as_ref().ok_or(AxError::NotFound)?;
+        let attr = lower.get_attr()?;
+        let temp = format!(
+            "{}copyup.{}",
+            WHITEOUT_PREFIX,
+            self.layers.next_temp.fetch_add(1, Ordering::Relaxed)
+        );
+        let temp = RelPath::new(&temp);
+        debug!("overlay: copy up {:?}", self.path);
+        let copy = match Self::copy_node(lower, &attr, &upper_dir, &temp) {
+            Ok(copy) => copy,
+            Err(e) => {
+                upper_dir.unlink(&temp).ok();
+                return Err(e);
+            }
+        };
+
+        let guard = self.layers.copy_up_lock.lock();
+        if let Some(upper) = self.upper() {
+            drop(guard);
+            upper_dir.unlink(&temp).ok();
+            return Ok(upper);
+        }
+        if let Err(e) = upper_dir.rename(&temp, &RelPath::new(self.name())) {
+            upper_dir.unlink(&temp).ok();
+            return Err(e);
+        }
+        *self.upper.write() = Some(copy.clone());
+        self.layers.copy_ups.fetch_add(1, Ordering::AcqRel);
+        Ok(copy)
+    }
+
+    /// Copies the node `lower` with the attributes `attr` to `name` in the
+    /// directory `upper_dir`, and returns the copy.
+    fn copy_node(
+        lower: &VfsNodeRef,
+        attr: &VfsNodeAttr,
+        upper_dir: &VfsNodeRef,
+        name: &RelPath,
+    ) -> VfsResult<VfsNodeRef> {
+        match attr.file_type() {
+            VfsNodeType::SymLink => {
+                let mut target = vec![0; 4096];
+                let len = lower.readlink(&mut target)?;
+                let target =
+                    core::str::from_utf8(&target[..len]).map_err(|_| AxError::InvalidData)?;
+                upper_dir.symlink(name, target)?;
+            }
+            ty @ (VfsNodeType::CharDevice | VfsNodeType::BlockDevice) => {
+                #[cfg(feature = "ramfs")]
+                upper_dir
+                    .as_any()
+                    .downcast_ref::<axfs_ramfs::DirNode>()
+                    .ok_or(AxError::Unsupported)?
+                    .create_device(name, ty, attr.rdev())?;
+                #[cfg(not(feature = "ramfs"))]
+                return ax_err!(Unsupported, "overlay: cannot copy up device {:?}", ty);
+            }
+            ty @ (VfsNodeType::File | VfsNodeType::Dir | VfsNodeType::Fifo) => {
+                upper_dir.create(name, ty)?
+            }
+            ty => return ax_err!(Unsupported, "overlay: cannot copy up {:?}", ty),
+        }
+        let upper = upper_dir.clone().lookup(name)?;
+        if attr.is_file() {
+            copy_data(lower, &upper)?;
+        }
+        upper.set_perm(attr.perm()).ok();
+        if let Some((uid, gid)) = attr.owner() {
+            upper.set_owner(uid, gid).ok();
+        }
+        // last, as the copy may change the times
+        if let Some((atime, mtime)) = attr.times() {
+            upper.set_times(atime, mtime).ok();
+        }
+        Ok(upper)
+    }
+
+    /// All the entries of this directory, besides `.` and `..`.
+    fn entries(&self) -> VfsResult<Vec<(String, VfsNodeType)>> {
+        let mut entries = Vec::new();
+        let mut hidden = BTreeSet::new();
+        let mut opaque = false;
+        if let Some(upper) = self.upper() {
+            for entry in read_all(&upper)? {
+                let name = entry_name(&entry);
+                if name == OPAQUE_MARKER {
+                    opaque = true;
+                } else if let Some(name) = name.strip_prefix(WHITEOUT_PREFIX) {
+                    hidden.insert(name.to_string());
+                } else {
+                    hidden.insert(name.to_string());
+                    entries.push((name.to_string(), entry.entry_type()));
+                }
+            }
+        }
+        if let Some(lower) = self.lower.as_ref().filter(|_| !opaque) {
+            for entry in read_all(lower)? {
+                let name = entry_name(&entry);
+                if !name.starts_with(WHITEOUT_PREFIX) && !hidden.contains(name) {
+                    entries.push((name.to_string(), entry.entry_type()));
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Removes the whiteouts and the opaque marker in the upper directory, before it's removed
+    /// or replaced.
+    fn clear_markers(upper: &VfsNodeRef) -> VfsResult {
+        for entry in read_all(upper)? {
+            let name = entry_name(&entry);
+            if name.starts_with(WHITEOUT_PREFIX) {
+                upper.unlink(&RelPath::new(name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates `name` in this directory by `create` in the upper layer, in
+    /// place of a whiteout if any.
+    fn create_child(
+        self: &Arc<Self>,
+        name: &str,
+        is_dir: bool,
+        create: impl FnOnce(&VfsNodeRef, &RelPath) -> VfsResult,
+    ) -> VfsResult {
+        if name.starts_with(WHITEOUT_PREFIX) {
+            return ax_err!(InvalidInput);
+        }
+        match self.lookup_child(name) {
+            Ok(_) => return ax_err!(AlreadyExists),
+            Err(AxError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+        let upper = self.copy_up()?;
+        let whiteout = format!("{}{}", WHITEOUT_PREFIX, name);
+        let whiteout = RelPath::new(&whiteout);
+        let replaces_lower = upper.clone().lookup(&whiteout).is_ok();
+        create(&upper, &RelPath::new(name))?;
+        if replaces_lower {
+            if is_dir {
+                upper.create(
+                    &RelPath::new(&format!("{}/{}", name, OPAQUE_MARKER)),
+                    VfsNodeType::File,
+                )?;
+            }
+            upper.unlink(&whiteout)?;
+        }
+        Ok(())
+    }
+}
+
+impl VfsNodeOps for OverlayNode {
+    fn open(&self) -> VfsResult {
+        self.real().open()
+    }
+
+    fn release(&self) -> VfsResult {
+        self.real().release()
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        self.real().get_attr()
+    }
+
+    fn set_owner(&self, uid: u32, gid: u32) -> VfsResult {
+        self.copy_up()?.set_owner(uid, gid)
+    }
+
+    fn set_times(&self, atime: Duration, mtime: Duration) -> VfsResult {
+        self.copy_up()?.set_times(atime, mtime)
+    }
+
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        self.copy_up()?.set_perm(perm)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.real().read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.copy_up()?.write_at(offset, buf)
+    }
+
+    fn read_at_direct(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.real().read_at_direct(offset, buf)
+    }
+
+    fn write_at_direct(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.copy_up()?.write_at_direct(offset, buf)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        match self.upper() {
+            Some(upper) => upper.fsync(),
+            None => Ok(()),
+        }
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        self.copy_up()?.truncate(size)
+    }
+
+    fn seek_data(&self, offset: u64) -> VfsResult<u64> {
+        self.real().seek_data(offset)
+    }
+
+    fn seek_hole(&self, offset: u64) -> VfsResult<u64> {
+        self.real().seek_hole(offset)
+    }
+
+    fn ioctl(&self, cmd: usize, arg: usize) -> VfsResult<usize> {
+        self.real().ioctl(cmd, arg)
+    }
+
+    fn device_memory(&self) -> Option<(usize, usize)> {
+        self.real().device_memory()
+    }
+
+    fn device_unmapped(&self) {
+        self.real().device_unmapped()
+    }
+
+    fn poll(&self) -> VfsResult<PollState> {
+        self.real().poll()
+    }
+
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        self.real().add_poll_observer(observer, key, exclusive)
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        self.real().remove_poll_observer(observer, key)
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        self.real().readlink(buf)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        if !self.ty.is_dir() {
+            return None;
+        }
+        match &self.parent {
+            Some(parent) => Some(parent.clone()),
+            None => self.mount_parent.get().cloned(),
+        }
+    }
+
+    fn lookup(self: Arc<Self>, path: &RelPath) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node: VfsNodeRef = match name {
+            "" | "." => self.clone(),
+            ".." => self.parent().ok_or(AxError::NotFound)?,
+            _ => self.lookup_child(name)?,
+        };
+        match rest {
+            Some(rest) => node.lookup(&rest),
+            None => Ok(node),
+        }
+    }
+
+    fn create(&self, path: &RelPath, ty: VfsNodeType) -> VfsResult {
+        let (parent, name) = split_parent(path);
+        if name.is_empty() || name == ".." {
+            return Ok(()); // already exists
+        }
+        let dir = self.this()?.lookup_dir(&parent)?;
+        dir.create_child(name, ty.is_dir(), |upper, name| upper.create(name, ty))
+    }
+
+    fn symlink(&self, path: &RelPath, target: &str) -> VfsResult {
+        let (parent, name) = split_parent(path);
+        if name.is_empty() || name == ".." {
+            return ax_err!(AlreadyExists);
+        }
+        let dir = self.this()?.lookup_dir(&parent)?;
+        dir.create_child(name, false, |upper, name| upper.symlink(name, target))
+    }
+
+    fn link(&self, path: &RelPath, src: VfsNodeRef) -> VfsResult<VfsNodeRef> {
+        let (parent, name) = split_parent(path);
+        if name.is_empty() || name == ".." {
+            return ax_err!(AlreadyExists);
+        }
+        let src = src
+            .as_any()
+            .downcast_ref::<OverlayNode>()
+            .ok_or(AxError::CrossesDevices)?;
+        let src_upper = src.copy_up()?;
+        let dir = self.this()?.lookup_dir(&parent)?;
+        dir.create_child(name, false, |upper, name| {
+            upper.link(name, src_upper).map(|_| ())
+        })?;
+        dir.lookup_child(name).map(|node| node as VfsNodeRef)
+    }
+
+    fn unlink(&self, path: &RelPath) -> VfsResult {
+        let (parent, name) = split_parent(path);
+        if name.is_empty() || name == ".." {
+            return ax_err!(InvalidInput); // remove '.' or '..'
+        }
+        let dir = self.this()?.lookup_dir(&parent)?;
+        let node = dir.lookup_child(name)?;
+        if node.ty.is_dir() && !node.entries()?.is_empty() {
+            return ax_err!(DirectoryNotEmpty);
+        }
+        let upper_dir = dir.copy_up()?;
+        if let Some(upper) = node.upper() {
+            if node.ty.is_dir() {
+                Self::clear_markers(&upper)?;
+            }
+            upper_dir.unlink(&RelPath::new(name))?;
+        }
+        if node.covers_lower {
+            upper_dir.create(
+                &RelPath::new(&format!("{}{}", WHITEOUT_PREFIX, name)),
+                VfsNodeType::File,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn rename(&self, src_path: &RelPath, dst_path: &RelPath) -> VfsResult {
+        let this = self.this()?;
+        let (src_parent, src_name) = split_parent(src_path);
+        let (dst_parent, dst_name) = split_parent(dst_path);
+        if [src_name, dst_name]
+            .iter()
+            .any(|name| name.is_empty() || *name == "." || *name == "..")
+            || dst_name.starts_with(WHITEOUT_PREFIX)
+        {
+            return ax_err!(InvalidInput);
+        }
+        let src_dir = this.lookup_dir(&src_parent)?;
+        let dst_dir = this.lookup_dir(&dst_parent)?;
+        let node = src_dir.lookup_child(src_name)?;
+        if node.ty.is_dir() && node.lower.is_some() {
+            return ax_err!(CrossesDevices);
+        }
+        let dst_path = dst_dir.child_path(dst_name);
+        if node.path == dst_path {
+            return Ok(());
+        }
+        let mut dst_is_lower_dir = false;
+        match dst_dir.lookup_child(dst_name) {
+            Ok(old) => {
+                match (node.ty.is_dir(), old.ty.is_dir()) {
+                    (true, true) if !old.entries()?.is_empty() => {
+                        return ax_err!(DirectoryNotEmpty)
+                    }
+                    (true, false) => return ax_err!(NotADirectory),
+                    (false, true) => return ax_err!(IsADirectory),
+                    _ => {}
+                }
+                if old.ty.is_dir() {
+                    dst_is_lower_dir = old.lower.is_some();
+                    if let Some(upper) = old.upper() {
+                        Self::clear_markers(&upper)?;
+                    }
+                }
+            }
+            Err(AxError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+        // a whiteout may hide a lower directory of the same name
+        let dst_is_whiteout = dst_dir.is_whiteout(dst_name);
+        let src_upper_dir = src_dir.copy_up()?;
+        let dst_upper_dir = dst_dir.copy_up()?;
+        let upper = node.copy_up()?;
+        self.layers
+            .upper
+            .rename(&RelPath::new(&node.path), &RelPath::new(&dst_path))?;
+
+        // the lower directory of the same name is replaced, not merged
+        let opaque = RelPath::new(OPAQUE_MARKER);
+        if node.ty.is_dir()
+            && (dst_is_lower_dir || dst_is_whiteout)
+            && upper.clone().lookup(&opaque).is_err()
+        {
+            upper.create(&opaque, VfsNodeType::File)?;
+        }
+        dst_upper_dir
+            .unlink(&RelPath::new(&format!("{}{}", WHITEOUT_PREFIX, dst_name)))
+            .ok();
+        if node.covers_lower {
+            src_upper_dir.create(
+                &RelPath::new(&format!("{}{}", WHITEOUT_PREFIX, src_name)),
+                VfsNodeType::File,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        if !self.ty.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+        let entries = self.entries()?;
+        let mut entries = entries.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => match entries.next() {
+                    Some((name, ty)) => *ent = VfsDirEntry::new(name, *ty),
+                    None => return Ok(i),
+                },
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn is_empty(&self) -> VfsResult<bool> {
+        self.entries().map(|entries| entries.is_empty())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn open_fifo(&self, read: bool, write: bool, non_blocking: bool) -> VfsResult {
+        self.real().open_fifo(read, write, non_blocking)
+    }
+
+    fn release_fifo(&self, read: bool, write: bool) -> VfsResult {
+        self.real().release_fifo(read, write)
+    }
+
+    fn fifo_has_readers(&self) -> bool {
+        self.real().fifo_has_readers()
+    }
+
+    fn fifo_has_writers(&self) -> bool {
+        self.real().fifo_has_writers()
+    }
+
+    fn fifo_capacity(&self) -> VfsResult<usize> {
+        self.real().fifo_capacity()
+    }
+
+    fn set_fifo_capacity(&self, size: usize) -> VfsResult<usize> {
+        self.real().set_fifo_capacity(size)
+    }
+
+    fn set_lock(&self, lock: VfsFileLock) -> VfsResult {
+        self.real().set_lock(lock)
+    }
+
+    fn get_lock(&self, lock: VfsFileLock) -> VfsResult<Option<VfsFileLock>> {
+        self.real().get_lock(lock)
+    }
+
+    fn get_seals(&self) -> VfsResult<VfsFileSeals> {
+        self.real().get_seals()
+    }
+
+    fn add_seals(&self, seals: VfsFileSeals) -> VfsResult {
+        self.copy_up()?.add_seals(seals)
+    }
+}
+
+/// The overlay filesystem.
+pub struct OverlayFileSystem {
+    root: Arc<OverlayNode>,
+}
+
+impl OverlayFileSystem {
+    /// Creates an overlay of the directories `lower` and `upper`.
+    pub fn new(lower: VfsNodeRef, upper: VfsNodeRef) -> Self {
+        let layers = Arc::new(Layers {
+            upper: upper.clone(),
+            copy_ups: AtomicU64::new(0),
+            copy_up_lock: Mutex::new(()),
+            next_temp: AtomicU64::new(0),
+        });
+        Self {
+            root: OverlayNode::new(
+                layers,
+                String::new(),
+                VfsNodeType::Dir,
+                Some(upper),
+                Some(lower),
+                true,
+                None,
+            ),
+        }
+    }
+}
+
+impl VfsOps for OverlayFileSystem {
+    fn mount(&self, _path: &AbsPath, mount_point: VfsNodeRef) -> VfsResult {
+        if let Some(parent) = mount_point.parent() {
+            self.root.mount_parent.call_once(|| parent);
+        }
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+/// Copies the contents of the file `src` to the empty file `dst`.
+fn copy_data(src: &VfsNodeRef, dst: &VfsNodeRef) -> VfsResult {
+    let mut buf = vec![0; 4096];
+    let mut offset = 0;
+    loop {
+        let len = src.read_at(offset, &mut buf)?;
+        if len == 0 {
+            return Ok(());
+        }
+        let mut written = 0;
+        while written < len {
+            match dst.write_at(offset + written as u64, &buf[written..len])? {
+                0 => return ax_err!(WriteZero),
+                n => written += n,
+            }
+        }
+        offset += len as u64;
+    }
+}
+
+/// Reads all the entries of the directory `dir`, besides `.` and `..`.
+fn read_all(dir: &VfsNodeRef) -> VfsResult<Vec<VfsDirEntry>> {
+    let mut entries = Vec::new();
+    let mut buf: Vec<_> = (0..16).map(|_| VfsDirEntry::default()).collect();
+    let mut idx = 0;
+    loop {
+        let n = dir.read_dir(idx, &mut buf)?;
+        if n == 0 {
+            return Ok(entries);
+        }
+        idx += n;
+        entries.extend(
+            buf[..n]
+                .iter()
+                .filter(|entry| !matches!(entry_name(entry), "." | ".."))
+                .cloned(),
+        );
+    }
+}
+
+fn entry_name(entry: &VfsDirEntry) -> &str {
+    core::str::from_utf8(entry.name_as_bytes()).unwrap_or("")
+}
+
+fn split_path<'a>(path: &'a RelPath) -> (&'a str, Option<RelPath<'a>>) {
+    path.find('/').map_or((path, None), |n| {
+        (&path[..n], Some(RelPath::new(&path[n + 1..])))
+    })
+}
+
+fn split_parent<'a>(path: &'a RelPath) -> (RelPath<'a>, &'a str) {
+    path.rfind('/')
+        .map_or((RelPath::new(""), path.as_str()), |n| {
+            (RelPath::new(&path[..n]), &path[n + 1..])
+        })
+}
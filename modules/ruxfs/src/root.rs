@@ -15,8 +15,10 @@
 use alloc::{format, sync::Arc, vec::Vec};
 use axerrno::{ax_err, AxResult};
 use axfs_vfs::{
-    AbsPath, RelPath, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult,
+    AbsPath, FileSystemInfo, RelPath, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef,
+    VfsNodeType, VfsOps, VfsResult,
 };
+use spin::RwLock;
 
 /// mount point information
 pub struct MountPoint {
@@ -35,6 +37,28 @@ impl MountPoint {
     }
 }
 
+/// Looks up `path` in the filesystems of `mount_points` before they are
+/// mounted, e.g. for the layers of an overlay mounted at boot.
+pub fn lookup_in_mounts(mount_points: &[MountPoint], path: &AbsPath) -> AxResult<VfsNodeRef> {
+    let path = path.to_rel();
+    let (mp, rest) = mount_points
+        .iter()
+        .filter_map(|mp| {
+            let mp_path = mp.path.to_rel();
+            if mp_path.is_empty() {
+                Some((mp, path.as_str()))
+            } else if *path == *mp_path {
+                Some((mp, ""))
+            } else {
+                path.strip_prefix(&format!("{}/", mp_path))
+                    .map(|rest| (mp, rest))
+            }
+        })
+        .max_by_key(|(mp, _)| mp.path.len())
+        .ok_or(VfsError::NotFound)?;
+    mp.fs.root_dir().lookup(&RelPath::new(rest))
+}
+
 impl Drop for MountPoint {
     fn drop(&mut self) {
         self.fs.umount().ok();
@@ -44,7 +68,7 @@ impl Drop for MountPoint {
 /// Root directory of the main filesystem
 pub struct RootDirectory {
     main_fs: Arc<dyn VfsOps>,
-    mounts: Vec<MountPoint>,
+    mounts: RwLock<Vec<MountPoint>>,
 }
 
 impl RootDirectory {
@@ -52,18 +76,19 @@ impl RootDirectory {
     pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
         Self {
             main_fs,
-            mounts: Vec::new(),
+            mounts: RwLock::new(Vec::new()),
         }
     }
 
     /// Mount the specified filesystem at the specified path.
-    pub fn mount(&mut self, path: AbsPath<'static>, fs: Arc<dyn VfsOps>) -> AxResult {
+    pub fn mount(&self, path: AbsPath<'static>, fs: Arc<dyn VfsOps>) -> AxResult {
         if path == AbsPath::new("/") {
             return ax_err!(InvalidInput, "cannot mount root filesystem");
         }
-        if self.mounts.iter().any(|mp| mp.path == path) {
+        if self.contains(&path) {
             return ax_err!(InvalidInput, "mount point already exists");
         }
+        // The lock isn't held across the I/O below, which may block on a remote filesystem.
         // create the mount point in the main filesystem if it does not exist
         match self.main_fs.root_dir().lookup(&path.to_rel()) {
             Ok(node) => {
@@ -86,29 +111,52 @@ impl RootDirectory {
             }
         }
         fs.mount(&path, self.main_fs.root_dir().lookup(&path.to_rel())?)?;
-        self.mounts.push(MountPoint::new(path, fs));
+        let mp = MountPoint::new(path, fs);
+        let mut mounts = self.mounts.write();
+        if mounts.iter().any(|other| other.path == mp.path) {
+            drop(mounts);
+            // unmounted by the drop, without the lock held
+            drop(mp);
+            return ax_err!(InvalidInput, "mount point already exists");
+        }
+        mounts.push(mp);
         Ok(())
     }
 
     /// Unmount the filesystem at the specified path.
-    pub fn _umount(&mut self, path: &AbsPath) {
-        self.mounts.retain(|mp| mp.path != *path);
+    ///
+    /// It fails with `ResourceBusy` if other filesystems are mounted under it.
+    pub fn umount(&self, path: &AbsPath) -> AxResult {
+        let mut mounts = self.mounts.write();
+        let idx = mounts
+            .iter()
+            .position(|mp| mp.path == *path)
+            .ok_or(VfsError::InvalidInput)?;
+        let prefix = format!("{}/", path);
+        if mounts.iter().any(|mp| mp.path.starts_with(&prefix)) {
+            return ax_err!(ResourceBusy);
+        }
+        let mp = mounts.remove(idx);
+        drop(mounts);
+        // unmounted by the drop, without the lock held
+        drop(mp);
+        Ok(())
     }
 
     /// Check if path is a mount point
     pub fn contains(&self, path: &AbsPath) -> bool {
-        self.mounts.iter().any(|mp| mp.path == *path)
+        self.mounts.read().iter().any(|mp| mp.path == *path)
     }
 
     /// Check if path matches a mountpoint, return the index of the matched
     /// mountpoint and the matched length.
-    fn lookup_mounted_fs(&self, path: &RelPath) -> (usize, usize) {
+    fn lookup_mounted_fs(mounts: &[MountPoint], path: &RelPath) -> (usize, usize) {
         debug!("lookup at root: {}", path);
         let mut idx = 0;
         let mut max_len = 0;
 
         // Find the filesystem that has the longest mounted path match
-        for (i, mp) in self.mounts.iter().enumerate() {
+        for (i, mp) in mounts.iter().enumerate() {
             let rel_mp = mp.path.to_rel();
             // path must have format: "<mountpoint>" or "<mountpoint>/..."
             if (rel_mp == *path || path.starts_with(&format!("{}/", rel_mp)))
@@ -121,19 +169,73 @@ impl RootDirectory {
         (idx, max_len)
     }
 
+    /// The filesystem that holds both paths of a rename, and the lengths of
+    /// their matched mountpoint.
+    fn rename_fs(
+        &self,
+        src_path: &RelPath,
+        dst_path: &RelPath,
+    ) -> AxResult<(Arc<dyn VfsOps>, usize, usize)> {
+        let mounts = self.mounts.read();
+        let (src_idx, src_len) = Self::lookup_mounted_fs(&mounts, src_path);
+        let (dst_idx, dst_len) = Self::lookup_mounted_fs(&mounts, dst_path);
+        if (src_len > 0, src_idx) != (dst_len > 0, dst_idx) {
+            return ax_err!(PermissionDenied); // cannot rename across mount points
+        }
+        if src_len > 0 && src_path.len() == src_len {
+            return ax_err!(PermissionDenied); // cannot rename mount points
+        }
+        let fs = if src_len > 0 {
+            mounts[src_idx].fs.clone()
+        } else {
+            self.main_fs.clone()
+        };
+        Ok((fs, src_len, dst_len))
+    }
+
+    /// Whether the two paths are on the same filesystem.
+    pub fn same_fs(&self, a: &AbsPath, b: &AbsPath) -> bool {
+        self.mounted_fs_index(&a.to_rel()) == self.mounted_fs_index(&b.to_rel())
+    }
+
+    /// Identifies the filesystem that holds `path` while it stays mounted, by
+    /// the address of the filesystem object.
+    pub fn fs_id(&self, path: &AbsPath) -> usize {
+        Arc::as_ptr(&self.mounted_fs(&path.to_rel()).0) as *const () as usize
+    }
+
+    /// Gets the usage of the filesystem that holds `path`.
+    pub fn statfs(&self, path: &AbsPath) -> AxResult<FileSystemInfo> {
+        self.mounted_fs(&path.to_rel()).0.statfs()
+    }
+
+    /// Index of the mountpoint whose filesystem holds `path`, `None` for the
+    /// main filesystem.
+    fn mounted_fs_index(&self, path: &RelPath) -> Option<usize> {
+        let (idx, len) = Self::lookup_mounted_fs(&self.mounts.read(), path);
+        (len > 0).then_some(idx)
+    }
+
+    /// The filesystem that holds `path`, and the length of the matched
+    /// mountpoint, which is 0 for the main filesystem.
+    fn mounted_fs(&self, path: &RelPath) -> (Arc<dyn VfsOps>, usize) {
+        let mounts = self.mounts.read();
+        match Self::lookup_mounted_fs(&mounts, path) {
+            (idx, len) if len > 0 => (mounts[idx].fs.clone(), len),
+            _ => (self.main_fs.clone(), 0),
+        }
+    }
+
     /// Check if path matches a mountpoint, dispatch the operation to the matched filesystem
     fn lookup_mounted_fs_then<F, T>(&self, path: &RelPath, f: F) -> AxResult<T>
     where
         F: FnOnce(Arc<dyn VfsOps>, &RelPath) -> AxResult<T>,
     {
-        let (idx, len) = self.lookup_mounted_fs(path);
+        let (fs, len) = self.mounted_fs(path);
         if len > 0 {
-            f(
-                self.mounts[idx].fs.clone(),
-                &RelPath::new_trimmed(&path[len..]),
-            )
+            f(fs, &RelPath::new_trimmed(&path[len..]))
         } else {
-            f(self.main_fs.clone(), path)
+            f(fs, path)
         }
     }
 }
@@ -145,6 +247,10 @@ impl VfsNodeOps for RootDirectory {
         self.main_fs.root_dir().get_attr()
     }
 
+    fn set_perm(&self, perm: VfsNodePerm) -> VfsResult {
+        self.main_fs.root_dir().set_perm(perm)
+    }
+
     fn lookup(self: Arc<Self>, path: &RelPath) -> VfsResult<VfsNodeRef> {
         self.lookup_mounted_fs_then(path, |fs, rest_path| fs.root_dir().lookup(rest_path))
     }
@@ -159,6 +265,26 @@ impl VfsNodeOps for RootDirectory {
         })
     }
 
+    fn symlink(&self, path: &RelPath, target: &str) -> VfsResult {
+        self.lookup_mounted_fs_then(path, |fs, rest_path| {
+            if rest_path.is_empty() {
+                ax_err!(AlreadyExists)
+            } else {
+                fs.root_dir().symlink(rest_path, target)
+            }
+        })
+    }
+
+    fn link(&self, name: &RelPath, src: VfsNodeRef) -> VfsResult<VfsNodeRef> {
+        self.lookup_mounted_fs_then(name, |fs, rest_path| {
+            if rest_path.is_empty() {
+                ax_err!(AlreadyExists)
+            } else {
+                fs.root_dir().link(rest_path, src)
+            }
+        })
+    }
+
     fn unlink(&self, path: &RelPath) -> VfsResult {
         self.lookup_mounted_fs_then(path, |fs, rest_path| {
             if rest_path.is_empty() {
@@ -170,15 +296,19 @@ impl VfsNodeOps for RootDirectory {
     }
 
     fn rename(&self, src_path: &RelPath, dst_path: &RelPath) -> VfsResult {
-        let (src_idx, src_len) = self.lookup_mounted_fs(src_path);
-        let (dst_idx, dst_len) = self.lookup_mounted_fs(dst_path);
-        if src_idx != dst_idx {
-            return ax_err!(PermissionDenied); // cannot rename across mount points
-        }
-        if src_path.len() == src_len {
-            return ax_err!(PermissionDenied); // cannot rename mount points
+        let (fs, src_len, dst_len) = self.rename_fs(src_path, dst_path)?;
+        fs.root_dir().rename(
+            &RelPath::new_trimmed(&src_path[src_len..]),
+            &RelPath::new_trimmed(&dst_path[dst_len..]),
+        )
+    }
+
+    fn exchange(&self, src_path: &RelPath, dst_path: &RelPath) -> VfsResult {
+        let (fs, src_len, dst_len) = self.rename_fs(src_path, dst_path)?;
+        if dst_len > 0 && dst_path.len() == dst_len {
+            return ax_err!(PermissionDenied); // cannot move mount points
         }
-        self.mounts[src_idx].fs.root_dir().rename(
+        fs.root_dir().exchange(
             &RelPath::new_trimmed(&src_path[src_len..]),
             &RelPath::new_trimmed(&dst_path[dst_len..]),
         )
@@ -0,0 +1,469 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axfs_ramfs::RamFileSystem;
+use axfs_vfs::{RelPath, VfsDirEntry, VfsError, VfsNodePerm, VfsNodeRef, VfsNodeType, VfsOps};
+
+use crate::lock::{self, FileKey, FileLock, LockOwner, LockType};
+use crate::overlay::OverlayFileSystem;
+
+fn path(path: &str) -> RelPath {
+    RelPath::new_canonicalized(path)
+}
+
+fn names(dir: &VfsNodeRef) -> Vec<String> {
+    let mut dirents: Vec<_> = (0..16).map(|_| VfsDirEntry::default()).collect();
+    let n = dir.read_dir(0, &mut dirents).unwrap();
+    let mut names: Vec<_> = dirents[..n]
+        .iter()
+        .map(|entry| String::from_utf8(entry.name_as_bytes().to_vec()).unwrap())
+        .filter(|name| name != "." && name != "..")
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_overlay_copy_up() {
+    let lower = RamFileSystem::new();
+    let upper = RamFileSystem::new();
+    let lower_root = lower.root_dir();
+    lower_root.create(&path("dir"), VfsNodeType::Dir).unwrap();
+    lower_root
+        .create(&path("dir/f"), VfsNodeType::File)
+        .unwrap();
+    let file = lower_root.clone().lookup(&path("dir/f")).unwrap();
+    file.write_at(0, b"lower").unwrap();
+    file.set_perm(VfsNodePerm::from_bits_truncate(0o640))
+        .unwrap();
+    file.set_owner(1, 2).unwrap();
+    let times = (Duration::from_secs(100), Duration::from_secs(200));
+    file.set_times(times.0, times.1).unwrap();
+
+    let overlay = OverlayFileSystem::new(lower_root, upper.root_dir());
+    let node = overlay.root_dir().lookup(&path("dir/f")).unwrap();
+    assert_eq!(node.write_at(5, b" upper").unwrap(), 6);
+
+    // the lower layer is not changed
+    let mut buf = [0; 16];
+    assert_eq!(file.read_at(0, &mut buf).unwrap(), 5);
+    assert_eq!(&buf[..5], b"lower");
+
+    let copy = upper.root_dir().lookup(&path("dir/f")).unwrap();
+    assert_eq!(copy.read_at(0, &mut buf).unwrap(), 11);
+    assert_eq!(&buf[..11], b"lower upper");
+    let attr = copy.get_attr().unwrap();
+    assert_eq!(attr.perm().mode(), 0o640);
+    assert_eq!(attr.owner(), Some((1, 2)));
+    assert_eq!(attr.times(), Some(times));
+
+    // no temporary copies are left behind
+    assert_eq!(names(&upper.root_dir()), ["dir"]);
+    let upper_dir = upper.root_dir().lookup(&path("dir")).unwrap();
+    assert_eq!(names(&upper_dir), ["f"]);
+}
+
+#[test]
+fn test_overlay_copy_up_special() {
+    let lower = RamFileSystem::new();
+    let upper = RamFileSystem::new();
+    let lower_root = lower.root_dir();
+    lower
+        .root_dir_node()
+        .create_device("tty", VfsNodeType::CharDevice, 0x0401)
+        .unwrap();
+    lower_root.create(&path("fifo"), VfsNodeType::Fifo).unwrap();
+
+    let overlay = OverlayFileSystem::new(lower_root, upper.root_dir());
+    let root = overlay.root_dir();
+    for name in ["tty", "fifo"] {
+        root.clone()
+            .lookup(&path(name))
+            .unwrap()
+            .set_owner(3, 4)
+            .unwrap();
+    }
+
+    let tty = upper.root_dir().lookup(&path("tty")).unwrap();
+    let attr = tty.get_attr().unwrap();
+    assert_eq!(attr.file_type(), VfsNodeType::CharDevice);
+    assert_eq!(attr.rdev(), 0x0401);
+    assert_eq!(attr.owner(), Some((3, 4)));
+    let fifo = upper.root_dir().lookup(&path("fifo")).unwrap();
+    assert_eq!(fifo.get_attr().unwrap().file_type(), VfsNodeType::Fifo);
+    assert_eq!(names(&upper.root_dir()), ["fifo", "tty"]);
+}
+
+#[test]
+fn test_overlay_rename_dir_over_whiteout() {
+    let lower = RamFileSystem::new();
+    let upper = RamFileSystem::new();
+    let lower_root = lower.root_dir();
+    lower_root.create(&path("d"), VfsNodeType::Dir).unwrap();
+    lower_root.create(&path("d/x"), VfsNodeType::File).unwrap();
+
+    let overlay = OverlayFileSystem::new(lower_root, upper.root_dir());
+    let root = overlay.root_dir();
+    root.unlink(&path("d/x")).unwrap();
+    root.unlink(&path("d")).unwrap();
+    root.create(&path("e"), VfsNodeType::Dir).unwrap();
+    root.rename(&path("e"), &path("d")).unwrap();
+
+    // the lower directory doesn't show through the renamed one
+    let dir = root.clone().lookup(&path("d")).unwrap();
+    assert_eq!(names(&dir), Vec::<String>::new());
+    assert_eq!(
+        root.clone().lookup(&path("d/x")).err(),
+        Some(VfsError::NotFound)
+    );
+    let upper_dir = upper.root_dir().lookup(&path("d")).unwrap();
+    assert_eq!(names(&upper_dir), [".wh..wh..opq"]);
+    assert_eq!(names(&upper.root_dir()), ["d"]);
+
+    // nor after a remount
+    let lower_root = lower.root_dir();
+    let overlay = OverlayFileSystem::new(lower_root, upper.root_dir());
+    let dir = overlay.root_dir().lookup(&path("d")).unwrap();
+    assert_eq!(names(&dir), Vec::<String>::new());
+}
+
+/// Makes an entry of a `newc` cpio archive.
+fn cpio_entry(
+    ino: u32,
+    mode: u32,
+    nlink: u32,
+    rdev: (u32, u32),
+    name: &str,
+    data: &[u8],
+) -> Vec<u8> {
+    let fields = [
+        ino,
+        mode,
+        1000,
+        100,
+        nlink,
+        1_700_000_000,
+        data.len() as u32,
+        0,
+        0,
+        rdev.0,
+        rdev.1,
+        name.len() as u32 + 1,
+        0,
+    ];
+    let mut entry = b"070701".to_vec();
+    for field in fields {
+        entry.extend_from_slice(format!("{:08x}", field).as_bytes());
+    }
+    entry.extend_from_slice(name.as_bytes());
+    entry.push(0);
+    entry.resize(entry.len().next_multiple_of(4), 0);
+    entry.extend_from_slice(data);
+    entry.resize(entry.len().next_multiple_of(4), 0);
+    entry
+}
+
+fn cpio_trailer() -> Vec<u8> {
+    cpio_entry(0, 0, 1, (0, 0), "TRAILER!!!", &[])
+}
+
+fn read_file(root: &VfsNodeRef, name: &str) -> Vec<u8> {
+    let node = root.clone().lookup(&path(name)).unwrap();
+    let mut buf = vec![0; node.get_attr().unwrap().size() as usize];
+    assert_eq!(node.read_at(0, &mut buf).unwrap(), buf.len());
+    buf
+}
+
+#[test]
+fn test_initramfs_concatenated() {
+    let mut data = Vec::new();
+    data.extend(cpio_entry(1, 0o040700, 2, (0, 0), ".", &[]));
+    data.extend(cpio_entry(2, 0o040755, 2, (0, 0), "etc", &[]));
+    data.extend(cpio_entry(3, 0o100644, 1, (0, 0), "etc/a", b"one"));
+    data.extend(cpio_entry(4, 0o100600, 1, (0, 0), "etc/b", b"kept"));
+    data.extend(cpio_trailer());
+    // the padding between archives
+    data.resize(data.len() + 512, 0);
+    data.extend(cpio_entry(2, 0o040750, 2, (0, 0), "./etc", &[]));
+    data.extend(cpio_entry(3, 0o100644, 1, (0, 0), "etc/a", b"two"));
+    data.extend(cpio_entry(5, 0o100644, 1, (0, 0), "/c", b""));
+    data.extend(cpio_trailer());
+    // entries after the last trailer are unpacked too
+    data.extend(cpio_entry(6, 0o100644, 1, (0, 0), "d", b"after"));
+
+    let ramfs = RamFileSystem::new();
+    let root = ramfs.root_dir();
+    crate::initramfs::unpack(&root, &data).unwrap();
+
+    assert_eq!(root.get_attr().unwrap().perm().mode(), 0o700);
+    assert_eq!(names(&root), ["c", "d", "etc"]);
+    let etc = root.clone().lookup(&path("etc")).unwrap();
+    assert_eq!(etc.get_attr().unwrap().perm().mode(), 0o750);
+    assert_eq!(names(&etc), ["a", "b"]);
+    assert_eq!(read_file(&root, "etc/a"), b"two");
+    assert_eq!(read_file(&root, "etc/b"), b"kept");
+    assert_eq!(read_file(&root, "c"), b"");
+    assert_eq!(read_file(&root, "d"), b"after");
+
+    let attr = root.lookup(&path("etc/b")).unwrap().get_attr().unwrap();
+    assert_eq!(attr.perm().mode(), 0o600);
+    assert_eq!(attr.owner(), Some((1000, 100)));
+    let mtime = Duration::from_secs(1_700_000_000);
+    assert_eq!(attr.times(), Some((mtime, mtime)));
+}
+
+#[test]
+fn test_initramfs_hard_links() {
+    let mut data = Vec::new();
+    // the data comes with the last entry of the file
+    data.extend(cpio_entry(7, 0o100644, 3, (0, 0), "x", &[]));
+    data.extend(cpio_entry(7, 0o100644, 3, (0, 0), "dir/y", &[]));
+    data.extend(cpio_entry(7, 0o100644, 3, (0, 0), "z", b"shared"));
+    data.extend(cpio_trailer());
+    // the same inode in the next archive is another file
+    data.extend(cpio_entry(7, 0o100644, 2, (0, 0), "w", b"other"));
+    data.extend(cpio_trailer());
+
+    let ramfs = RamFileSystem::new();
+    let root = ramfs.root_dir();
+    crate::initramfs::unpack(&root, &data).unwrap();
+
+    for name in ["x", "dir/y", "z"] {
+        assert_eq!(read_file(&root, name), b"shared");
+    }
+    let x = root.clone().lookup(&path("x")).unwrap();
+    let y = root.clone().lookup(&path("dir/y")).unwrap();
+    assert!(Arc::ptr_eq(&x, &y));
+    assert_eq!(read_file(&root, "w"), b"other");
+    let w = root.lookup(&path("w")).unwrap();
+    assert!(!Arc::ptr_eq(&x, &w));
+}
+
+#[test]
+fn test_initramfs_special_files() {
+    let mut data = Vec::new();
+    data.extend(cpio_entry(1, 0o120777, 1, (0, 0), "bin/sh", b"busybox"));
+    data.extend(cpio_entry(2, 0o020666, 1, (1, 3), "dev/null", &[]));
+    data.extend(cpio_entry(3, 0o060660, 1, (8, 1), "dev/sda1", &[]));
+    data.extend(cpio_entry(4, 0o010644, 1, (0, 0), "run/fifo", &[]));
+    data.extend(cpio_entry(5, 0o140755, 1, (0, 0), "run/socket", &[]));
+    data.extend(cpio_trailer());
+
+    let ramfs = RamFileSystem::new();
+    let root = ramfs.root_dir();
+    crate::initramfs::unpack(&root, &data).unwrap();
+
+    let sh = root.clone().lookup(&path("bin/sh")).unwrap();
+    assert!(sh.get_attr().unwrap().is_symlink());
+    let mut target = [0; 16];
+    let len = sh.readlink(&mut target).unwrap();
+    assert_eq!(&target[..len], b"busybox");
+
+    let null = root.clone().lookup(&path("dev/null")).unwrap();
+    let attr = null.get_attr().unwrap();
+    assert!(attr.is_char_device());
+    assert_eq!(attr.rdev(), (1 << 8) | 3);
+    assert_eq!(attr.perm().mode(), 0o666);
+    let sda1 = root.clone().lookup(&path("dev/sda1")).unwrap();
+    let attr = sda1.get_attr().unwrap();
+    assert!(attr.is_block_device());
+    assert_eq!(attr.rdev(), (8 << 8) | 1);
+
+    let fifo = root.clone().lookup(&path("run/fifo")).unwrap();
+    assert!(fifo.get_attr().unwrap().is_fifo());
+    // sockets are skipped
+    let run = root.lookup(&path("run")).unwrap();
+    assert_eq!(names(&run), ["fifo"]);
+}
+
+#[test]
+fn test_initramfs_bad_archives() {
+    let ramfs = RamFileSystem::new();
+    let root = ramfs.root_dir();
+    let unpack = |data: &[u8]| crate::initramfs::unpack(&root, data);
+
+    let mut data = cpio_entry(1, 0o100644, 1, (0, 0), "file", b"contents");
+    let entry_len = data.len();
+    data.extend(cpio_trailer());
+    // truncated in the data, the name and the header
+    for len in [entry_len - 4, HEADER_SIZE + 2, HEADER_SIZE - 1, 6] {
+        assert_eq!(unpack(&data[..len]), Err(VfsError::InvalidData));
+    }
+    // a bad field
+    let mut bad = data.clone();
+    bad[6] = b'x';
+    assert_eq!(unpack(&bad), Err(VfsError::InvalidData));
+    // an old binary or odc cpio archive
+    for magic in [&b"\xc7\x71"[..], b"070707"] {
+        let mut old = magic.to_vec();
+        old.resize(HEADER_SIZE * 2, b'0');
+        assert_eq!(unpack(&old), Err(VfsError::InvalidData));
+    }
+    assert!(names(&root).is_empty());
+
+    // compressed, even if shorter than a header
+    let gzip = [0x1f, 0x8b, 0x08, 0x00, 0, 0, 0, 0, 0, 0];
+    let zstd = [0x28, 0xb5, 0x2f, 0xfd, 0, 0, 0, 0];
+    for compressed in [&gzip[..], &zstd[..]] {
+        assert_eq!(unpack(compressed), Err(VfsError::Unsupported));
+    }
+
+    // the entries before an error are kept
+    let mut data = cpio_entry(1, 0o100644, 1, (0, 0), "file", b"contents");
+    data.extend(cpio_trailer());
+    data.extend(gzip);
+    assert_eq!(unpack(&data), Err(VfsError::Unsupported));
+    assert_eq!(names(&root), ["file"]);
+    assert_eq!(read_file(&root, "file"), b"contents");
+
+    // only padding
+    assert_eq!(unpack(&[0; 16]), Ok(()));
+    assert_eq!(unpack(&[]), Ok(()));
+}
+
+/// Size of the header of a `newc` entry.
+const HEADER_SIZE: usize = 110;
+
+fn record(owner: LockOwner, ty: LockType, start: u64, end: u64) -> FileLock {
+    FileLock {
+        owner,
+        ty,
+        start,
+        end,
+    }
+}
+
+fn ranges(key: &FileKey, owner: LockOwner) -> Vec<(LockType, u64, u64)> {
+    let mut locks: Vec<_> = lock::owner_locks(key, owner)
+        .iter()
+        .map(|l| (l.ty, l.start, l.end))
+        .collect();
+    locks.sort_by_key(|l| l.1);
+    locks
+}
+
+#[test]
+fn test_lock_conflict_and_split() {
+    use axfs_vfs::VfsLockType::{Read, Unlock, Write};
+    // the lock table is global, so each test uses its own files and pids
+    let key = FileKey::Path("/test_lock_conflict_and_split".into());
+    let (p1, p2) = (LockOwner::Process(1001), LockOwner::Process(1002));
+
+    lock::set_lock(&key, record(p1, Write, 0, 100)).unwrap();
+    let wanted = record(p2, Read, 50, 60);
+    assert_eq!(
+        lock::set_lock(&key, wanted).err(),
+        Some(VfsError::WouldBlock)
+    );
+    let conflict = lock::test_lock(&key, &wanted).unwrap();
+    assert_eq!((conflict.owner, conflict.start, conflict.end), (p1, 0, 100));
+    // locks of the owner never conflict with each other
+    assert!(lock::test_lock(&key, &record(p1, Read, 50, 60)).is_none());
+
+    // unlocking the middle splits the lock
+    lock::set_lock(&key, record(p1, Unlock, 40, 60)).unwrap();
+    assert_eq!(ranges(&key, p1), [(Write, 0, 40), (Write, 60, 100)]);
+    lock::set_lock(&key, record(p2, Read, 45, 55)).unwrap();
+
+    // a lock of another type replaces its range, one of the same type merges
+    lock::set_lock(&key, record(p1, Read, 30, 70)).unwrap();
+    assert_eq!(
+        ranges(&key, p1),
+        [(Write, 0, 30), (Read, 30, 70), (Write, 70, 100)]
+    );
+    lock::set_lock(&key, record(p1, Read, 70, 100)).unwrap();
+    assert_eq!(ranges(&key, p1), [(Write, 0, 30), (Read, 30, 100)]);
+    assert_eq!(
+        lock::set_lock(&key, record(p2, Write, 20, 40)).err(),
+        Some(VfsError::WouldBlock)
+    );
+
+    // OFD locks conflict with POSIX locks
+    let ofd = LockOwner::OpenFile(0x1000);
+    assert!(lock::test_lock(&key, &record(ofd, Write, 90, 95)).is_some());
+    lock::set_lock(&key, record(ofd, Write, 100, u64::MAX)).unwrap();
+    assert!(lock::release_locks(&key, ofd));
+
+    assert!(lock::release_locks(&key, p1));
+    assert!(!lock::release_locks(&key, p1));
+    lock::set_lock(&key, record(p2, Write, 0, 10)).unwrap();
+    assert_eq!(lock::release_process_locks(1002), [key.clone()]);
+    assert!(lock::owner_locks(&key, p2).is_empty());
+}
+
+#[test]
+fn test_lock_flock() {
+    let key = FileKey::Path("/test_lock_flock".into());
+    let (f1, f2) = (LockOwner::OpenFile(0x2000), LockOwner::OpenFile(0x3000));
+    let whole = FileLock::whole_file;
+
+    // `flock` locks are independent of record locks
+    lock::set_lock(&key, record(f1, LockType::Write, 0, u64::MAX)).unwrap();
+    lock::set_flock(&key, whole(f2, LockType::Write)).unwrap();
+    assert_eq!(
+        lock::set_flock(&key, whole(f1, LockType::Read)).err(),
+        Some(VfsError::WouldBlock)
+    );
+    assert!(!lock::lock_available(
+        &key,
+        &whole(f1, LockType::Read),
+        true
+    ));
+
+    // converting the lock of the owner
+    lock::set_flock(&key, whole(f2, LockType::Read)).unwrap();
+    lock::set_flock(&key, whole(f1, LockType::Read)).unwrap();
+    assert!(lock::lock_available(&key, &whole(f1, LockType::Read), true));
+    assert!(!lock::lock_available(
+        &key,
+        &whole(f1, LockType::Write),
+        true
+    ));
+
+    assert!(lock::release_locks(&key, f1));
+    assert!(!lock::release_locks(&key, f2));
+    lock::set_flock(&key, whole(f1, LockType::Write)).unwrap();
+    lock::set_flock(&key, whole(f1, LockType::Unlock)).unwrap();
+}
+
+#[test]
+fn test_lock_deadlock() {
+    let file_a = FileKey::Path("/test_lock_deadlock_a".into());
+    let file_b = FileKey::Path("/test_lock_deadlock_b".into());
+    let file_c = FileKey::Path("/test_lock_deadlock_c".into());
+    let (p1, p2, p3) = (2001, 2002, 2003);
+    let write = |pid| record(LockOwner::Process(pid), LockType::Write, 0, 10);
+
+    lock::set_lock(&file_a, write(p1)).unwrap();
+    lock::set_lock(&file_b, write(p2)).unwrap();
+    lock::set_lock(&file_c, write(p3)).unwrap();
+
+    // p1 waits for p2, so p2 waiting for p1 would deadlock
+    assert!(lock::start_waiting(&file_b, &write(p1)));
+    assert!(!lock::start_waiting(&file_a, &write(p2)));
+
+    // through a third process as well
+    assert!(lock::start_waiting(&file_c, &write(p2)));
+    assert!(!lock::start_waiting(&file_a, &write(p3)));
+
+    // a process that stopped waiting no longer makes a cycle
+    lock::stop_waiting(&write(p2));
+    assert!(lock::start_waiting(&file_a, &write(p3)));
+    // OFD locks do not take part
+    let ofd = record(LockOwner::OpenFile(0x4000), LockType::Write, 0, 10);
+    assert!(lock::start_waiting(&file_a, &ofd));
+
+    for pid in [p1, p2, p3] {
+        lock::stop_waiting(&write(pid));
+        lock::release_process_locks(pid);
+    }
+}
@@ -97,7 +97,9 @@ pub static mut COMLINE_BUF: [u8; 256] = [0; 256];
 
 #[allow(unused)]
 /// read a tty device specified by its name.
-pub fn tty_read(buf: &mut [u8], dev_name: &str) -> usize {
+/// return `None` if the read should wait for more input, and `Some(0)` at
+/// the end of file. the console without a tty never ends.
+pub fn tty_read(buf: &mut [u8], dev_name: &str) -> Option<usize> {
     #[cfg(not(feature = "tty"))]
     {
         let mut read_len = 0;
@@ -109,7 +111,7 @@ pub fn tty_read(buf: &mut [u8], dev_name: &str) -> usize {
                 break;
             }
         }
-        read_len
+        (read_len > 0 || buf.is_empty()).then_some(read_len)
     }
 
     #[cfg(feature = "tty")]
@@ -118,6 +120,48 @@ pub fn tty_read(buf: &mut [u8], dev_name: &str) -> usize {
     }
 }
 
+/// whether a tty device specified by its name has data to read. the console
+/// without a tty is polled by reads, so it is always deemed readable.
+#[allow(unused)]
+pub fn tty_readable(dev_name: &str) -> bool {
+    #[cfg(feature = "tty")]
+    {
+        tty::tty_readable(dev_name)
+    }
+    #[cfg(not(feature = "tty"))]
+    {
+        true
+    }
+}
+
+/// whether a blocked read of a tty device specified by its name is woken up
+/// when input arrives, rather than having to poll it.
+#[allow(unused)]
+pub fn tty_wakes_readers(dev_name: &str) -> bool {
+    #[cfg(feature = "tty")]
+    {
+        tty::tty_wakes_readers(dev_name)
+    }
+    #[cfg(not(feature = "tty"))]
+    {
+        false
+    }
+}
+
+/// perform a request of ioctl on a tty device specified by its name.
+/// return `None` if it is not a tty request or there is no tty.
+#[allow(unused)]
+pub fn tty_ioctl(dev_name: &str, cmd: usize, arg: usize) -> Option<usize> {
+    #[cfg(feature = "tty")]
+    {
+        tty::tty_ioctl(dev_name, cmd, arg)
+    }
+    #[cfg(not(feature = "tty"))]
+    {
+        None
+    }
+}
+
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
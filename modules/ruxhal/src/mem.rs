@@ -10,6 +10,7 @@
 //! Physical memory management.
 
 use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 #[doc(no_inline)]
 pub use memory_addr::{PhysAddr, VirtAddr, PAGE_SIZE_4K};
@@ -94,9 +95,71 @@ pub const fn phys_to_virt(paddr: PhysAddr) -> VirtAddr {
     VirtAddr::from(paddr.as_usize() + ruxconfig::PHYS_VIRT_OFFSET)
 }
 
+/// The physical address range of the initial ramdisk loaded by the bootloader.
+static INITRD_START: AtomicUsize = AtomicUsize::new(0);
+static INITRD_END: AtomicUsize = AtomicUsize::new(0);
+
 /// Returns an iterator over all physical memory regions.
 pub fn memory_regions() -> impl Iterator<Item = MemRegion> {
-    kernel_image_regions().chain(crate::platform::mem::platform_regions())
+    kernel_image_regions()
+        .chain(initrd_regions())
+        .chain(crate::platform::mem::platform_regions())
+}
+
+/// Records the initial ramdisk loaded by the bootloader at `[start, end)`,
+/// which is kept out of the free memory until it's released.
+#[allow(dead_code)]
+pub(crate) fn set_initrd(start: usize, end: usize) {
+    if start < end {
+        INITRD_START.store(start, Ordering::Relaxed);
+        INITRD_END.store(end, Ordering::Relaxed);
+    }
+}
+
+/// Records the initial ramdisk given by `linux,initrd-start` and
+/// `linux,initrd-end` of the `/chosen` node of the device tree.
+#[cfg(any(target_arch = "aarch64", target_arch = "riscv64"))]
+pub(crate) fn set_initrd_from_dtb() {
+    if let Some(chosen) = dtb::get_node("chosen") {
+        if let (Some(start), Some(end)) = (
+            chosen.find_prop("linux,initrd-start"),
+            chosen.find_prop("linux,initrd-end"),
+        ) {
+            set_initrd(start.uint() as usize, end.uint() as usize);
+        }
+    }
+}
+
+/// Returns the physical address and the size of the initial ramdisk loaded
+/// by the bootloader, if any.
+pub fn initrd() -> Option<(PhysAddr, usize)> {
+    let start = INITRD_START.load(Ordering::Relaxed);
+    let end = INITRD_END.load(Ordering::Relaxed);
+    (start < end).then(|| (PhysAddr::from(start), end - start))
+}
+
+/// Forgets the initial ramdisk, after its memory is given to the allocator.
+pub fn release_initrd() {
+    INITRD_START.store(0, Ordering::Relaxed);
+    INITRD_END.store(0, Ordering::Relaxed);
+}
+
+/// Returns the pages of the initial ramdisk, if any.
+fn initrd_regions() -> impl Iterator<Item = MemRegion> {
+    initrd()
+        .map(|(paddr, size)| {
+            let start = paddr.align_down_4k();
+            MemRegion {
+                paddr: start,
+                size: PhysAddr::from(paddr.as_usize() + size)
+                    .align_up_4k()
+                    .as_usize()
+                    - start.as_usize(),
+                flags: MemRegionFlags::RESERVED | MemRegionFlags::READ | MemRegionFlags::WRITE,
+                name: "initrd",
+            }
+        })
+        .into_iter()
 }
 
 /// Returns the memory regions of the kernel image (code and data sections).
@@ -150,20 +213,35 @@ pub(crate) fn default_mmio_regions() -> impl Iterator<Item = MemRegion> {
     })
 }
 
-/// Returns the default free memory regions (kernel image end to physical memory end).
+/// Returns the default free memory regions (kernel image end to physical memory end), except
+/// the pages of the initial ramdisk.
 #[allow(dead_code)]
 pub(crate) fn default_free_regions() -> impl Iterator<Item = MemRegion> {
-    let start = direct_virt_to_phys((_ekernel as usize).into()).align_up_4k();
-    let end = PhysAddr::from(ruxconfig::PHYS_MEMORY_END).align_down_4k();
-    core::iter::once(MemRegion {
-        paddr: start,
-        size: end.as_usize() - start.as_usize(),
-        flags: MemRegionFlags::FREE
-            | MemRegionFlags::READ
-            | MemRegionFlags::WRITE
-            | MemRegionFlags::EXECUTE,
-        name: "free memory",
-    })
+    let start = direct_virt_to_phys((_ekernel as usize).into())
+        .align_up_4k()
+        .as_usize();
+    let end = PhysAddr::from(ruxconfig::PHYS_MEMORY_END)
+        .align_down_4k()
+        .as_usize();
+    let (initrd_start, initrd_end) = match initrd_regions().next() {
+        Some(r) => (
+            r.paddr.as_usize().clamp(start, end),
+            (r.paddr.as_usize() + r.size).clamp(start, end),
+        ),
+        None => (end, end),
+    };
+    [(start, initrd_start), (initrd_end, end)]
+        .into_iter()
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| MemRegion {
+            paddr: PhysAddr::from(start),
+            size: end - start,
+            flags: MemRegionFlags::FREE
+                | MemRegionFlags::READ
+                | MemRegionFlags::WRITE
+                | MemRegionFlags::EXECUTE,
+            name: "free memory",
+        })
 }
 
 /// Fills the `.bss` section with zeros.
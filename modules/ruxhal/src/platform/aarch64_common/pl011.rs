@@ -111,7 +111,11 @@ pub fn init() {
     {
         #[cfg(feature = "tty")]
         {
-            let ops = tty::TtyDriverOps { putchar };
+            let ops = tty::TtyDriverOps {
+                putchar,
+                write: None,
+                write_room: None,
+            };
             let driver_index = tty::register_driver(ops, "ttyS");
             let dev_index = tty::register_device(driver_index);
             assert_ne!(dev_index, -1);
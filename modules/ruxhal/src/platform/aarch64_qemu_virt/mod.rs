@@ -48,6 +48,7 @@ pub(crate) unsafe extern "C" fn rust_entry(cpu_id: usize, dtb: usize) {
     unsafe {
         dtb::init(crate::mem::phys_to_virt(dtb.into()).as_ptr());
     }
+    crate::mem::set_initrd_from_dtb();
     crate::cpu::init_primary(cpu_id);
     #[cfg(not(feature = "virtio_console"))]
     super::aarch64_common::pl011::init_early();
@@ -50,6 +50,7 @@ pub(crate) unsafe extern "C" fn rust_entry(cpu_id: usize) {
         // Set the physical address of the dtb file to 0x03000000 in config.txt
         dtb::init(crate::mem::phys_to_virt(0x03000000.into()).as_ptr());
     }
+    crate::mem::set_initrd_from_dtb();
     crate::cpu::init_primary(cpu_id);
     super::aarch64_common::pl011::init_early();
     super::aarch64_common::generic_timer::init_early();
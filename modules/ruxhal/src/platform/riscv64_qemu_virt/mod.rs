@@ -34,6 +34,7 @@ unsafe extern "C" fn rust_entry(cpu_id: usize, dtb: usize) {
     unsafe {
         dtb::init(crate::mem::phys_to_virt(dtb.into()).as_ptr());
     }
+    crate::mem::set_initrd_from_dtb();
     rust_main(cpu_id, dtb);
 }
 
@@ -58,6 +58,16 @@ unsafe fn parse_cmdline(mbi: usize) {
     }
 }
 
+// find the first module in multiboot info and record it as the initial ramdisk
+unsafe fn parse_initrd(mbi: usize) {
+    let mbi = mbi as *const u32;
+    let flag = mbi.read();
+    if (flag & (1 << 3)) > 0 && *mbi.add(5) > 0 {
+        let module = *mbi.add(6) as *const u32;
+        crate::mem::set_initrd(module.read() as usize, module.add(1).read() as usize);
+    }
+}
+
 unsafe extern "C" fn rust_entry(magic: usize, mbi: usize) {
     // TODO: handle multiboot info
     if magic == self::boot::MULTIBOOT_BOOTLOADER_MAGIC {
@@ -69,6 +79,7 @@ unsafe extern "C" fn rust_entry(magic: usize, mbi: usize) {
         crate::arch::init_syscall_entry();
         self::time::init_early();
         parse_cmdline(mbi);
+        parse_initrd(mbi);
         rust_main(current_cpu_id(), 0);
     }
 }
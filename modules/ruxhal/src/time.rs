@@ -8,7 +8,18 @@
  */
 
 //! Time-related operations.
-#[cfg(all(feature = "rtc", target_arch = "x86_64"))]
+//!
+//! Two clocks are maintained here:
+//!
+//! - The monotonic clock ([`current_time`]) counts the time since boot. It
+//!   never goes backwards and is used for all timers and sleeps.
+//! - The wall clock ([`wall_time`]) is the realtime clock. It is the monotonic
+//!   clock plus an offset, which is seeded from the RTC (if any) and can be
+//!   changed by [`set_wall_time`] without affecting the monotonic clock.
+//!
+//! On x86, the timer drifts from the RTC, so the wall clock is re-synced with
+//! the RTC every half an hour. Each change of the wall clock
+//! bumps [`wall_clock_generation`].
 use core::sync::atomic::{AtomicU64, Ordering};
 pub use core::time::Duration;
 
@@ -38,51 +49,124 @@ pub const NANOS_PER_MILLIS: u64 = 1_000_000;
 /// Number of nanoseconds in a microsecond.
 pub const NANOS_PER_MICROS: u64 = 1_000;
 
-/// Returns the current clock time in nanoseconds.
+/// Marks [`WALL_OFFSET_NANOS`] as not seeded yet.
+const WALL_OFFSET_UNINIT: u64 = u64::MAX;
+
+/// Offset of the wall clock from the monotonic clock, in nanoseconds.
+static WALL_OFFSET_NANOS: AtomicU64 = AtomicU64::new(WALL_OFFSET_UNINIT);
+
+/// Number of times the wall clock has been set or re-synced.
+static WALL_CLOCK_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Interval of re-syncing the wall clock with the RTC, in seconds.
+#[cfg(all(feature = "rtc", target_arch = "x86_64"))]
+pub const RTC_RESYNC_SECS: u64 = 1800;
+
+/// Monotonic time of the last sync with the RTC, in seconds.
+#[cfg(all(feature = "rtc", target_arch = "x86_64"))]
+static LAST_RTC_SYNC_SECS: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the current monotonic clock time in nanoseconds.
 pub fn current_time_nanos() -> u64 {
     ticks_to_nanos(current_ticks())
 }
-///record first time res from x86 rtc as base time
-#[cfg(all(feature = "rtc", target_arch = "x86_64"))]
-static BASE_TIME: AtomicU64 = AtomicU64::new(0);
-///record last time res from x86 rtc to correct time
-#[cfg(all(feature = "rtc", target_arch = "x86_64"))]
-static LAST_TIME: AtomicU64 = AtomicU64::new(0);
 
-/// Returns the current clock time in [`TimeValue`].
-#[allow(unreachable_code)]
+/// Returns the current monotonic clock time in [`TimeValue`].
+///
+/// It is the time elapsed since boot, and is not affected by [`set_wall_time`].
 pub fn current_time() -> TimeValue {
-    #[cfg(all(feature = "rtc", target_arch = "x86_64"))]
+    TimeValue::from_nanos(current_time_nanos())
+}
+
+/// Returns the resolution of the clocks in nanoseconds.
+pub fn clock_resolution_nanos() -> u64 {
+    ticks_to_nanos(1).max(1)
+}
+
+/// Reads the initial offset of the wall clock, from the RTC if available.
+#[allow(unreachable_code, unused_variables)]
+fn boot_wall_offset_nanos(now_nanos: u64) -> u64 {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    #[cfg(feature = "rtc")]
+    return (rtc_read_time() * NANOS_PER_SEC).saturating_sub(now_nanos);
+    0
+}
+
+/// Re-reads the RTC if the last sync was long enough ago, and returns the
+/// new offset of the wall clock if it drifted by a second or more.
+#[cfg(all(feature = "rtc", target_arch = "x86_64"))]
+fn resync_wall_offset_nanos(now_nanos: u64) -> Option<u64> {
+    let now_secs = now_nanos / NANOS_PER_SEC;
+    let last = LAST_RTC_SYNC_SECS.load(Ordering::Relaxed);
+    if now_secs.saturating_sub(last) < RTC_RESYNC_SECS
+        || LAST_RTC_SYNC_SECS
+            .compare_exchange(last, now_secs, Ordering::AcqRel, Ordering::Relaxed)
+            .is_err()
     {
-        let nanos = current_time_nanos();
-        let mut base_time = BASE_TIME.load(Ordering::Relaxed);
-        if base_time == 0 {
-            base_time = rtc_read_time();
-            BASE_TIME.store(base_time, Ordering::Relaxed);
-        }
-        let last_time = LAST_TIME.load(Ordering::Relaxed);
-        if (nanos / NANOS_PER_SEC) - last_time > 1800 {
-            base_time = rtc_read_time() - (nanos / NANOS_PER_SEC);
-            BASE_TIME.store(base_time, Ordering::Relaxed);
-            LAST_TIME.store(nanos / NANOS_PER_SEC, Ordering::Relaxed);
-        }
-        let rtc_time = base_time + (nanos / NANOS_PER_SEC);
-        return Duration::new(rtc_time, (nanos % NANOS_PER_SEC) as u32);
+        return None;
     }
-    #[cfg(all(feature = "rtc", target_arch = "aarch64"))]
-    {
-        let nanos = current_time_nanos();
-        let rtc_time = rtc_read_time();
-        return Duration::new(rtc_time, (nanos % (NANOS_PER_SEC)) as u32);
+    let offset = boot_wall_offset_nanos(now_nanos);
+    // the RTC counts whole seconds
+    if offset.abs_diff(WALL_OFFSET_NANOS.load(Ordering::Acquire)) < NANOS_PER_SEC {
+        return None;
     }
-    TimeValue::from_nanos(current_time_nanos())
+    WALL_OFFSET_NANOS.store(offset, Ordering::Release);
+    WALL_CLOCK_GENERATION.fetch_add(1, Ordering::AcqRel);
+    Some(offset)
+}
+
+fn wall_offset_nanos(now_nanos: u64) -> u64 {
+    let offset = WALL_OFFSET_NANOS.load(Ordering::Acquire);
+    if offset != WALL_OFFSET_UNINIT {
+        #[cfg(all(feature = "rtc", target_arch = "x86_64"))]
+        let offset = resync_wall_offset_nanos(now_nanos).unwrap_or(offset);
+        return offset;
+    }
+    let offset = boot_wall_offset_nanos(now_nanos);
+    #[cfg(all(feature = "rtc", target_arch = "x86_64"))]
+    LAST_RTC_SYNC_SECS.store(now_nanos / NANOS_PER_SEC, Ordering::Relaxed);
+    match WALL_OFFSET_NANOS.compare_exchange(
+        WALL_OFFSET_UNINIT,
+        offset,
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    ) {
+        Ok(_) => offset,
+        Err(seeded) => seeded,
+    }
+}
+
+/// Returns the current wall clock (realtime) time in nanoseconds since the
+/// Unix epoch.
+pub fn wall_time_nanos() -> u64 {
+    let now = current_time_nanos();
+    now + wall_offset_nanos(now)
+}
+
+/// Returns the current wall clock (realtime) time in [`TimeValue`].
+pub fn wall_time() -> TimeValue {
+    TimeValue::from_nanos(wall_time_nanos())
 }
 
-/// set time value
-pub fn set_current_time(_new_tv: TimeValue) {
+/// Returns the number of times the wall clock has been set or re-synced, so
+/// that the waits until a wall clock time can tell when to re-arm.
+pub fn wall_clock_generation() -> u64 {
+    WALL_CLOCK_GENERATION.load(Ordering::Acquire)
+}
+
+/// Sets the wall clock to `new_tv`, and writes it back to the RTC if any.
+///
+/// The monotonic clock is left untouched.
+pub fn set_wall_time(new_tv: TimeValue) {
+    let now = current_time_nanos();
+    let new_nanos = new_tv.as_nanos() as u64;
+    WALL_OFFSET_NANOS.store(new_nanos.saturating_sub(now), Ordering::Release);
+    WALL_CLOCK_GENERATION.fetch_add(1, Ordering::AcqRel);
+    #[cfg(all(feature = "rtc", target_arch = "x86_64"))]
+    LAST_RTC_SYNC_SECS.store(now / NANOS_PER_SEC, Ordering::Relaxed);
     #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
     #[cfg(feature = "rtc")]
-    rtc_write_time(_new_tv.as_secs() as u32);
+    rtc_write_time(new_tv.as_secs() as u32);
 }
 
 /// Busy waiting for the given duration.
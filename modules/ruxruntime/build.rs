@@ -0,0 +1,27 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+use std::path::PathBuf;
+
+/// Embeds the initramfs given by `RUX_INITRAMFS` at build time, which is
+/// included as `$OUT_DIR/initramfs.rs`.
+fn main() {
+    println!("cargo:rerun-if-env-changed=RUX_INITRAMFS");
+    let out = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("initramfs.rs");
+    let content = match std::env::var("RUX_INITRAMFS") {
+        Ok(path) if !path.is_empty() => {
+            let path = std::fs::canonicalize(&path)
+                .unwrap_or_else(|e| panic!("invalid RUX_INITRAMFS {:?}: {}", path, e));
+            println!("cargo:rerun-if-changed={}", path.display());
+            format!("include_bytes!({:?})", path)
+        }
+        _ => "&[]".into(),
+    };
+    std::fs::write(out, content).unwrap();
+}
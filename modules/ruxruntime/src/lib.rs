@@ -23,6 +23,7 @@
 //! - `fs`: Enable filesystem support.
 //! - `blkfs`: Enable disk filesystem.
 //! - `signal`: Enable signal support
+//! - `tty`: Enable the tty layer for serial consoles.
 //! - `net`: Enable networking support.
 //! - `display`: Enable graphics support.
 //! - `virtio-9p`: Enable virtio-based 9pfs support.
@@ -46,6 +47,9 @@ mod trap;
 #[cfg(feature = "smp")]
 mod mp;
 
+#[cfg(feature = "fs")]
+mod mount;
+
 #[cfg(feature = "smp")]
 pub use self::mp::rust_main_secondary;
 
@@ -194,8 +198,8 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     #[cfg(feature = "alloc")]
     init_allocator();
 
-    #[cfg(feature = "tty")]
-    tty::init();
+    #[cfg(any(feature = "tty", feature = "fs"))]
+    init_tty();
 
     info!("Initialize platform devices...");
     ruxhal::platform_init();
@@ -208,6 +212,18 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
         ruxtask::init_scheduler();
         #[cfg(feature = "musl")]
         ruxfutex::init_futex();
+        // raises the signals of control chars typed on ttys
+        #[cfg(all(
+            feature = "signal",
+            feature = "irq",
+            any(feature = "tty", feature = "fs")
+        ))]
+        {
+            ruxtask::spawn(Signal::raise_queued_pgrp);
+            // the console is the controlling tty of the app
+            let console = tty::get_all_device_names().into_iter().next();
+            Signal::set_controlling_tty(&ruxtask::current().signal_if, console.as_deref());
+        }
     }
 
     #[cfg(feature = "paging")]
@@ -218,48 +234,49 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
 
     #[cfg(any(feature = "fs", feature = "net", feature = "display"))]
     {
-        #[allow(unused_variables)]
-        let all_devices = ruxdriver::init_drivers();
+        #[allow(unused_variables, unused_mut)]
+        let mut all_devices = ruxdriver::init_drivers();
 
         #[cfg(feature = "net")]
         ruxnet::init_network(all_devices.net);
 
         #[cfg(feature = "fs")]
         {
-            extern crate alloc;
-            use alloc::vec::Vec;
-            // By default, mount_points[0] will be rootfs
-            let mut mount_points: Vec<ruxfs::root::MountPoint> = Vec::new();
-
-            //setup ramfs as rootfs if no other filesystem can be mounted
-            #[cfg(not(any(feature = "blkfs", feature = "virtio-9p", feature = "net-9p")))]
-            mount_points.push(ruxfs::init_tempfs());
+            // filesystem types mountable at boot and by `mount(2)`
+            #[cfg(any(feature = "virtio-9p", feature = "net-9p"))]
+            {
+                #[cfg(feature = "virtio-9p")]
+                rux9p::add_virtio_devices(all_devices._9p);
+                rux9p::init();
+            }
 
-            // setup and initialize blkfs as one mountpoint for rootfs
+            // setup and initialize blkfs as rootfs, unless another one is mounted on `/` at boot
             #[cfg(feature = "blkfs")]
-            mount_points.push(ruxfs::init_blkfs(all_devices.block));
-
-            // setup and initialize 9pfs as mountpoint
-            #[cfg(feature = "virtio-9p")]
-            mount_points.push(rux9p::init_virtio_9pfs(
-                all_devices._9p,
-                option_env!("RUX_ANAME_9P").unwrap_or(""),
-                option_env!("RUX_PROTOCOL_9P").unwrap_or(""),
-            ));
-            #[cfg(feature = "net-9p")]
-            mount_points.push(rux9p::init_net_9pfs(
-                option_env!("RUX_9P_ADDR").unwrap_or(""),
-                option_env!("RUX_ANAME_9P").unwrap_or(""),
-                option_env!("RUX_PROTOCOL_9P").unwrap_or(""),
-            ));
+            let root = Some(ruxfs::init_blkfs(&mut all_devices.block));
+            #[cfg(not(feature = "blkfs"))]
+            let root = None;
+
+            // By default, mount_points[0] will be rootfs
+            let mut mount_points = mount::init_mount_points(root);
             ruxfs::prepare_commonfs(&mut mount_points);
 
+            // the other disks get nodes in `/dev`
+            ruxfs::devices::init_block_devices(all_devices.block);
+
             // setup and initialize rootfs
             ruxfs::init_filesystems(mount_points);
         }
 
         #[cfg(feature = "display")]
-        ruxdisplay::init_display(all_devices.display);
+        {
+            ruxdisplay::init_display(all_devices.display);
+            #[cfg(feature = "fs")]
+            {
+                extern crate alloc;
+                let fb = ruxdisplay::FbDev::new(ruxfs::devices::alloc_ino(), start_fb_flusher);
+                ruxfs::devices::register("fb0", alloc::sync::Arc::new(fb));
+            }
+        }
     }
 
     #[cfg(feature = "smp")]
@@ -369,6 +386,50 @@ fn init_cmdline(argc: &mut c_int) {
     }
 }
 
+/// Provides the line disciplines of ttys and ptys with the kernel services.
+#[cfg(any(feature = "tty", feature = "fs"))]
+fn init_tty() {
+    fn send_signal(_dev_name: &str, _pgrp: i32, _sig: u32) {
+        // may run in IRQ context, so the signal is raised by a task
+        #[cfg(all(feature = "signal", feature = "irq"))]
+        Signal::queue_pgrp(_dev_name, _pgrp, _sig as u8);
+    }
+    fn control_tty(_dev_name: &str, _attach: bool) {
+        #[cfg(all(feature = "signal", feature = "multitask"))]
+        {
+            let signal_if = &ruxtask::current().signal_if;
+            if _attach {
+                Signal::set_controlling_tty(signal_if, Some(_dev_name));
+            } else if Signal::controlling_tty(signal_if).as_deref() == Some(_dev_name) {
+                Signal::set_controlling_tty(signal_if, None);
+            }
+        }
+    }
+    fn wake_readers(_dev_name: &str) {
+        #[cfg(feature = "fs")]
+        ruxfs::devices::tty_wake_readers(_dev_name);
+    }
+    tty::init(tty::TtyKernelOps {
+        current_time: ruxhal::time::current_time,
+        send_signal,
+        control_tty,
+        wake_readers,
+    });
+}
+
+/// Shows what apps draw into the mapped `/dev/fb0` at 60 Hz, until the last
+/// mapping is gone.
+#[cfg(all(feature = "display", feature = "fs"))]
+fn start_fb_flusher() {
+    #[cfg(feature = "multitask")]
+    ruxtask::spawn(|| loop {
+        ruxtask::sleep(core::time::Duration::from_micros(16_667));
+        if !ruxdisplay::flush_mapped_framebuffer() {
+            break;
+        }
+    });
+}
+
 #[cfg(feature = "alloc")]
 fn init_allocator() {
     use ruxhal::mem::{memory_regions, phys_to_virt, MemRegionFlags};
@@ -441,16 +502,7 @@ fn init_interrupt() {
                 Signal::timer_deadline(which, Some(ddl));
             }
         }
-        let signal = Signal::signal(-1, true).unwrap();
-        for signum in 0..32 {
-            if signal & (1 << signum) != 0
-            /* TODO: && support mask */
-            {
-                Signal::signal(signum as i8, false);
-                Signal::sigaction(signum as u8, None, None);
-                Signal::signal_handle(signum as u8);
-            }
-        }
+        Signal::handle_pending();
     }
 
     ruxhal::irq::register_handler(TIMER_IRQ_NUM, || {
@@ -0,0 +1,207 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Filesystems mounted at boot.
+//!
+//! They are given by the kernel parameters before the first `;` of the
+//! command line, each as `mount=<fstype>:<source>:<path>[:<options>]`, e.g.
+//! `mount=9p:rootfs:/:trans=virtio,version=9p2000.L`. The one on `/` becomes
+//! the root filesystem.
+//!
+//! The layers of an `overlay` are looked up in the filesystems given before
+//! it, e.g. `mount=9p:rootfs:/lower:trans=virtio mount=overlay:overlay:/:lowerdir=/lower`
+//! boots on an immutable 9P share with the changes kept in a ramfs.
+//!
+//! Without a root filesystem, the root is a ramfs with the initramfs unpacked,
+//! which is embedded at build time by `RUX_INITRAMFS`, or loaded by the
+//! bootloader, or both.
+
+use alloc::{borrow::ToOwned, format, string::String, vec::Vec};
+use ruxfs::{root::MountPoint, AbsPath};
+
+/// The initramfs embedded at build time.
+static EMBEDDED_INITRAMFS: &[u8] = include!(concat!(env!("OUT_DIR"), "/initramfs.rs"));
+
+/// A mount given at boot.
+struct BootMount {
+    fstype: String,
+    source: String,
+    path: String,
+    options: String,
+}
+
+impl BootMount {
+    fn parse(param: &str) -> Option<Self> {
+        let mut parts = param.splitn(4, ':');
+        let fstype = parts.next()?;
+        let source = parts.next()?;
+        let path = parts.next()?;
+        if fstype.is_empty() || !path.starts_with('/') {
+            return None;
+        }
+        Some(Self {
+            fstype: fstype.to_owned(),
+            source: source.to_owned(),
+            path: path.to_owned(),
+            options: parts.next().unwrap_or("").to_owned(),
+        })
+    }
+
+    /// Creates the filesystem, after the ones of `mounted`.
+    fn mount(&self, mounted: &[MountPoint]) -> Option<MountPoint> {
+        info!(
+            "mount {} {:?} on {} with {:?}",
+            self.fstype, self.source, self.path, self.options
+        );
+        let path = AbsPath::new_canonicalized(&self.path);
+        let mount_point = if self.fstype == "overlay" {
+            // nothing is mounted yet to be looked up by path
+            ruxfs::overlay::new_overlay(&self.options, |layer| {
+                ruxfs::root::lookup_in_mounts(mounted, layer)
+            })
+            .map(|fs| MountPoint::new(path, fs))
+        } else {
+            ruxfs::fstype::new_fs(&self.fstype, &self.source, &self.options)
+                .map(|fs| MountPoint::new(path, fs))
+        };
+        match mount_point {
+            Ok(mount_point) => Some(mount_point),
+            Err(e) => {
+                error!("failed to mount {} on {}: {:?}", self.fstype, self.path, e);
+                None
+            }
+        }
+    }
+}
+
+/// The mounts given by the kernel parameters of the command line.
+fn boot_mounts() -> Vec<BootMount> {
+    let params = super::get_boot_str().split(';').next().unwrap_or("");
+    params
+        .split_whitespace()
+        .filter_map(|param| param.strip_prefix("mount="))
+        .filter_map(|mount| {
+            let parsed = BootMount::parse(mount);
+            if parsed.is_none() {
+                warn!("invalid boot mount {:?}", mount);
+            }
+            parsed
+        })
+        .collect()
+}
+
+/// The 9P mounts made when none is given at boot, from the build-time options.
+///
+/// The first of them is the root filesystem if there is no block device.
+#[allow(unused_mut, unused_variables)]
+fn default_mounts(has_root: bool) -> Vec<BootMount> {
+    let mut mounts = Vec::new();
+    let options: Vec<String> = [
+        ("aname", option_env!("RUX_ANAME_9P")),
+        ("version", option_env!("RUX_PROTOCOL_9P")),
+        ("msize", option_env!("RUX_MSIZE_9P")),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| Some(format!("{}={}", key, value.filter(|v| !v.is_empty())?)))
+    .collect();
+    let options = options.join(",");
+    #[cfg(feature = "virtio-9p")]
+    mounts.push(BootMount {
+        fstype: "9p".to_owned(),
+        source: String::new(),
+        path: if has_root { "/v9fs" } else { "/" }.to_owned(),
+        options: format!("trans=virtio,{}", options),
+    });
+    #[cfg(feature = "net-9p")]
+    mounts.push(BootMount {
+        fstype: "9p".to_owned(),
+        source: option_env!("RUX_9P_ADDR")
+            .unwrap_or("127.0.0.1:564")
+            .to_owned(),
+        path: if has_root || !mounts.is_empty() {
+            "/n9fs"
+        } else {
+            "/"
+        }
+        .to_owned(),
+        options: format!("trans=tcp,{}", options),
+    });
+    mounts
+}
+
+/// Creates the root filesystem from the initramfs embedded in the image and
+/// the one loaded by the bootloader, or an empty ramfs if there is neither.
+fn init_rootfs() -> MountPoint {
+    let mut archives = Vec::new();
+    if !EMBEDDED_INITRAMFS.is_empty() {
+        archives.push(EMBEDDED_INITRAMFS);
+    }
+    if let Some((paddr, size)) = ruxhal::mem::initrd() {
+        info!(
+            "initrd at [{:#x}, {:#x})",
+            paddr.as_usize(),
+            paddr.as_usize() + size
+        );
+        let vaddr = ruxhal::mem::phys_to_virt(paddr);
+        archives.push(unsafe { core::slice::from_raw_parts(vaddr.as_ptr(), size) });
+    }
+    if archives.is_empty() {
+        ruxfs::init_tempfs()
+    } else {
+        ruxfs::init_initramfs(&archives)
+    }
+}
+
+/// Gives the pages of the initrd loaded by the bootloader to the allocator,
+/// after it's unpacked or not used.
+fn free_initrd() {
+    use ruxhal::mem::{phys_to_virt, PhysAddr};
+
+    if let Some((paddr, size)) = ruxhal::mem::initrd() {
+        let start = paddr.align_down_4k();
+        let end = PhysAddr::from(paddr.as_usize() + size).align_up_4k();
+        ruxhal::mem::release_initrd();
+        if let Err(e) = axalloc::global_add_memory(
+            phys_to_virt(start).as_usize(),
+            end.as_usize() - start.as_usize(),
+        ) {
+            warn!("failed to free the memory of initrd: {:?}", e);
+        }
+    }
+}
+
+/// Makes the mounts given at boot, or the default ones if none is given.
+///
+/// The root filesystem is the first of the returned mount points, which is
+/// the one mounted on `/`, or `root` if there isn't, or a ramfs at last,
+/// with the initramfs unpacked if any.
+pub(crate) fn init_mount_points(root: Option<MountPoint>) -> Vec<MountPoint> {
+    let mut mounts = boot_mounts();
+    if mounts.is_empty() {
+        mounts = default_mounts(root.is_some());
+    }
+    let mut mount_points: Vec<MountPoint> = Vec::new();
+    for mount in &mounts {
+        if let Some(mount_point) = mount.mount(&mount_points) {
+            mount_points.push(mount_point);
+        }
+    }
+    match mount_points
+        .iter()
+        .position(|mp| mp.path == AbsPath::new("/"))
+    {
+        Some(idx) => {
+            let root_fs = mount_points.remove(idx);
+            mount_points.insert(0, root_fs);
+        }
+        None => mount_points.insert(0, root.unwrap_or_else(init_rootfs)),
+    }
+    free_initrd();
+    mount_points
+}
@@ -33,9 +33,9 @@ fn test_fatfs() {
                                // By default, mount_points[0] will be rootfs
     let mut mount_points: Vec<ruxfs::root::MountPoint> = Vec::new();
     // setup and initialize blkfs as one mountpoint for rootfs
-    mount_points.push(ruxfs::init_blkfs(AxDeviceContainer::from_one(Box::new(
-        disk,
-    ))));
+    mount_points.push(ruxfs::init_blkfs(&mut AxDeviceContainer::from_one(
+        Box::new(disk),
+    )));
     ruxfs::prepare_commonfs(&mut mount_points);
 
     // setup and initialize rootfs
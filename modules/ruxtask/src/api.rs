@@ -231,6 +231,10 @@ pub fn sleep_until(deadline: ruxhal::time::TimeValue) {
 pub fn exit(exit_code: i32) -> ! {
     #[cfg(not(feature = "musl"))]
     current().destroy_keys();
+    #[cfg(feature = "fs")]
+    if current().id() == current().process_id() {
+        crate::fs::release_process_locks(current().id().as_u64());
+    }
     RUN_QUEUE.lock().exit_current(exit_code)
 }
 
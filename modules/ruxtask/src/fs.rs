@@ -11,22 +11,31 @@
 
 #![cfg(feature = "fs")]
 
-use crate::current;
-use alloc::{borrow::ToOwned, format, sync::Arc, vec::Vec};
-use axerrno::{ax_err, AxResult};
-use axfs_vfs::VfsNodeRef;
+use crate::{current, WaitQueue};
+use alloc::{
+    borrow::ToOwned,
+    collections::VecDeque,
+    format,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::{ax_err, AxError, AxResult};
+use axfs_vfs::{VfsNodeAttr, VfsNodeRef};
 use bitmaps::Bitmap;
+use core::sync::atomic::{AtomicBool, Ordering};
 use flatten_objects::FlattenObjects;
-use ruxfdtable::FileLike;
+use ruxfdtable::{FileLike, PollObserver};
 use ruxfs::{
     fops::{lookup, CurrentWorkingDirectoryOps, OpenOptions},
+    lock::{self, FileLock, LockOwner},
     root::{MountPoint, RootDirectory},
     AbsPath, RelPath,
 };
 
 use axerrno::{LinuxError, LinuxResult};
 use axio::PollState;
-use ruxfdtable::RuxStat;
+use ruxfdtable::{RuxStat, RuxTimeSpec};
 use spin::RwLock;
 
 #[crate_interface::def_interface]
@@ -83,11 +92,55 @@ pub fn close_file_like(fd: i32) -> LinuxResult {
     // drop the binding_fs to release the lock, as some operations
     // when closing a file may need to reschedule the task.(e.g. SOCKET_CLOSE)
     drop(binding_fs);
+    // closing any descriptor of a file releases the POSIX locks of the process on it
+    if let Ok(file) = file.clone().into_any().downcast::<File>() {
+        let pid = binding_task.process_id().as_u64();
+        file.inner
+            .read()
+            .release_locks(LockOwner::Process(pid), pid)
+            .ok();
+        FILE_LOCK_WAIT.notify_all(false);
+    }
     drop(file);
 
     Ok(())
 }
 
+/// Tasks waiting for file locks.
+static FILE_LOCK_WAIT: WaitQueue = WaitQueue::new();
+
+/// Interval to retry a lock held by another client of a shared filesystem,
+/// which does not wake up local waiters when released.
+const REMOTE_LOCK_RETRY_INTERVAL: core::time::Duration = core::time::Duration::from_millis(10);
+
+/// Tasks waiting for an append to the same file to finish.
+static APPEND_WAIT: WaitQueue = WaitQueue::new();
+
+/// Releases the record locks of the process `pid` when it exits.
+pub fn release_process_locks(pid: u64) {
+    if !lock::release_process_locks(pid).is_empty() {
+        FILE_LOCK_WAIT.notify_all(false);
+    }
+}
+
+/// Overrides the owner and times in `stat` by those of `attr`, if the filesystem keeps them.
+fn fill_owner_and_times(attr: &VfsNodeAttr, stat: &mut RuxStat) {
+    if let Some((uid, gid)) = attr.owner() {
+        stat.st_uid = uid;
+        stat.st_gid = gid;
+    }
+    if let Some((atime, mtime)) = attr.times() {
+        stat.st_atime = RuxTimeSpec {
+            tv_sec: atime.as_secs() as _,
+            tv_nsec: atime.subsec_nanos() as _,
+        };
+        stat.st_mtime = RuxTimeSpec {
+            tv_sec: mtime.as_secs() as _,
+            tv_nsec: mtime.subsec_nanos() as _,
+        };
+    }
+}
+
 /// A struct representing a file object.
 pub struct File {
     /// The inner file object.
@@ -114,6 +167,141 @@ impl File {
             .downcast::<Self>()
             .map_err(|_| LinuxError::EINVAL)
     }
+
+    /// The owner of OFD and `flock` locks placed through this open file.
+    pub fn lock_owner(&self) -> LockOwner {
+        LockOwner::OpenFile(self as *const Self as usize)
+    }
+
+    /// Places or removes a record lock, waiting for conflicting locks to be
+    /// released if `wait` is set.
+    ///
+    /// Returns `EAGAIN` on conflict without `wait`, and `EDEADLK` if waiting
+    /// for a POSIX lock would deadlock.
+    pub fn set_lock(&self, lock: FileLock, wait: bool) -> LinuxResult {
+        self.lock_with(lock, wait, false)
+    }
+
+    /// Places or removes a `flock` lock, waiting for conflicting locks to be
+    /// released if `wait` is set.
+    pub fn set_flock(&self, lock: FileLock, wait: bool) -> LinuxResult {
+        self.lock_with(lock, wait, true)
+    }
+
+    fn lock_with(&self, lock: FileLock, wait: bool, flock: bool) -> LinuxResult {
+        loop {
+            let inner = self.inner.read();
+            let result = if flock {
+                inner.set_flock(lock)
+            } else {
+                inner.set_lock(lock, current().process_id().as_u64())
+            };
+            match result {
+                Err(AxError::WouldBlock) if wait => {}
+                Err(AxError::WouldBlock) => return Err(LinuxError::EAGAIN),
+                // not opened for the access the lock type requires
+                Err(AxError::PermissionDenied) => return Err(LinuxError::EBADF),
+                result => {
+                    result?;
+                    // an unlock or a downgrade may let others proceed
+                    FILE_LOCK_WAIT.notify_all(false);
+                    return Ok(());
+                }
+            }
+            let key = inner.lock_key()?;
+            drop(inner);
+
+            let held_locally = flock || lock::test_lock(&key, &lock).is_some();
+            if !flock && !lock::start_waiting(&key, &lock) {
+                return Err(LinuxError::EDEADLK);
+            }
+            if held_locally {
+                FILE_LOCK_WAIT.wait_until(|| lock::lock_available(&key, &lock, flock));
+            } else {
+                crate::sleep(REMOTE_LOCK_RETRY_INTERVAL);
+            }
+            if !flock {
+                lock::stop_waiting(&lock);
+            }
+        }
+    }
+}
+
+/// Wakes up a task blocked on a file whose node pushes its readiness.
+struct ReadyWaiter {
+    ready: AtomicBool,
+    wait_queue: WaitQueue,
+}
+
+impl ReadyWaiter {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            ready: AtomicBool::new(false),
+            wait_queue: WaitQueue::new(),
+        })
+    }
+}
+
+impl PollObserver for ReadyWaiter {
+    fn on_ready(&self, _key: usize) -> bool {
+        self.ready.store(true, Ordering::Release);
+        self.wait_queue.notify_one(false)
+    }
+}
+
+impl File {
+    /// Blocks until the readiness of the file satisfies `ready`, or may have
+    /// changed. Files whose node does not push its readiness are re-polled
+    /// after a yield.
+    fn wait_ready(&self, ready: impl Fn(&PollState) -> bool) {
+        let waiter = ReadyWaiter::new();
+        let observer: Weak<dyn PollObserver> = Arc::downgrade(&waiter) as _;
+        let inner = self.inner.read();
+        if !inner.add_poll_observer(observer.clone(), 0, false) {
+            drop(inner);
+            crate::yield_now();
+            return;
+        }
+        // the readiness may have changed before the observer was added
+        let state = inner.poll();
+        drop(inner);
+        if !state.is_ok_and(|state| ready(&state)) {
+            waiter
+                .wait_queue
+                .wait_until(|| waiter.ready.load(Ordering::Acquire));
+        }
+        self.inner.read().remove_poll_observer(&observer, 0);
+    }
+
+    /// Waits for the other end of a FIFO opened in blocking mode to be
+    /// opened, as `open` only returns once the FIFO has both a reader and
+    /// a writer.
+    pub fn wait_fifo_peer(&self) {
+        let waiter = ReadyWaiter::new();
+        let observer: Weak<dyn PollObserver> = Arc::downgrade(&waiter) as _;
+        self.inner
+            .read()
+            .add_poll_observer(observer.clone(), 0, false);
+        loop {
+            waiter.ready.store(false, Ordering::Release);
+            if self.inner.read().fifo_peer_opened() {
+                break;
+            }
+            waiter
+                .wait_queue
+                .wait_until(|| waiter.ready.load(Ordering::Acquire));
+        }
+        self.inner.read().remove_poll_observer(&observer, 0);
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        // OFD and `flock` locks live as long as the open file
+        let pid = current().process_id().as_u64();
+        self.inner.read().release_locks(self.lock_owner(), pid).ok();
+        FILE_LOCK_WAIT.notify_all(false);
+    }
 }
 
 impl FileLike for File {
@@ -122,11 +310,59 @@ impl FileLike for File {
     }
 
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        Ok(self.inner.write().read(buf)?)
+        loop {
+            let mut inner = self.inner.write();
+            if inner.is_path_only() {
+                return Err(LinuxError::EBADF);
+            }
+            match inner.read(buf) {
+                // FIFOs and device nodes such as ttys have no data yet
+                Err(AxError::WouldBlock) if !inner.is_nonblocking() => {
+                    drop(inner);
+                    self.wait_ready(|state| state.readable || state.pollhup);
+                }
+                result => return Ok(result?),
+            }
+        }
     }
 
     fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
-        Ok(self.inner.write().write(buf)?)
+        let mut written = 0;
+        loop {
+            let mut inner = self.inner.write();
+            if inner.is_path_only() {
+                return Err(LinuxError::EBADF);
+            }
+            let result = inner.write(&buf[written..]);
+            if inner.is_append() {
+                // the append lock of the file is released
+                APPEND_WAIT.notify_all(false);
+            }
+            match result {
+                Ok(n) => {
+                    written += n;
+                    // a full FIFO takes the rest as it is drained
+                    if n == 0 || written == buf.len() || inner.is_nonblocking() {
+                        return Ok(written);
+                    }
+                }
+                // another file opened on the same node appends to it
+                Err(AxError::WouldBlock)
+                    if inner.is_append()
+                        && inner.lock_key().is_ok_and(|key| lock::append_locked(&key)) =>
+                {
+                    let key = inner.lock_key()?;
+                    drop(inner);
+                    APPEND_WAIT.wait_until(|| !lock::append_locked(&key));
+                }
+                Err(AxError::WouldBlock) if !inner.is_nonblocking() => {
+                    drop(inner);
+                    self.wait_ready(|state| state.writable);
+                }
+                Err(_) if written > 0 => return Ok(written),
+                Err(e) => return Err(e.into()),
+            }
+        }
     }
 
     fn flush(&self) -> LinuxResult {
@@ -144,7 +380,7 @@ impl FileLike for File {
         // TODO: implement real inode.
         let st_ino = metadata.size() + st_mode as u64;
 
-        let res = RuxStat {
+        let mut res = RuxStat {
             st_ino,
             st_nlink: 1,
             st_mode,
@@ -153,8 +389,10 @@ impl FileLike for File {
             st_size: metadata.size() as _,
             st_blocks: metadata.blocks() as _,
             st_blksize: 512,
+            st_rdev: metadata.rdev(),
             ..Default::default()
         };
+        fill_owner_and_times(&metadata, &mut res);
 
         Ok(res)
     }
@@ -164,16 +402,43 @@ impl FileLike for File {
     }
 
     fn poll(&self) -> LinuxResult<PollState> {
-        Ok(PollState {
-            readable: true,
-            writable: true,
-            pollhup: false,
-        })
+        Ok(self.inner.read().poll()?)
     }
 
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.inner.write().set_nonblocking(nonblocking);
         Ok(())
     }
+
+    fn is_nonblocking(&self) -> bool {
+        self.inner.read().is_nonblocking()
+    }
+
+    fn ioctl(&self, cmd: usize, arg: usize) -> LinuxResult<usize> {
+        let inner = self.inner.read();
+        if inner.is_path_only() {
+            return Err(LinuxError::EBADF);
+        }
+        inner.ioctl(cmd, arg).map_err(|e| match e {
+            AxError::Unsupported => LinuxError::ENOTTY,
+            e => e.into(),
+        })
+    }
+
+    fn add_poll_observer(
+        &self,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) -> bool {
+        self.inner
+            .read()
+            .add_poll_observer(observer, key, exclusive)
+    }
+
+    fn remove_poll_observer(&self, observer: &Weak<dyn PollObserver>, key: usize) {
+        self.inner.read().remove_poll_observer(observer, key)
+    }
 }
 
 /// A struct representing a directory object.
@@ -213,10 +478,16 @@ impl FileLike for Directory {
     }
 
     fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        if self.inner.read().is_path_only() {
+            return Err(LinuxError::EBADF);
+        }
         Err(LinuxError::EACCES)
     }
 
     fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        if self.inner.read().is_path_only() {
+            return Err(LinuxError::EBADF);
+        }
         Err(LinuxError::EACCES)
     }
 
@@ -229,7 +500,7 @@ impl FileLike for Directory {
         let ty = metadata.file_type() as u8;
         let perm = metadata.perm().bits() as u32;
         let st_mode = ((ty as u32) << 12) | perm;
-        Ok(RuxStat {
+        let mut res = RuxStat {
             st_ino: 1,
             st_nlink: 1,
             st_mode,
@@ -239,7 +510,9 @@ impl FileLike for Directory {
             st_blocks: metadata.blocks() as _,
             st_blksize: 512,
             ..Default::default()
-        })
+        };
+        fill_owner_and_times(&metadata, &mut res);
+        Ok(res)
     }
 
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
@@ -254,9 +527,14 @@ impl FileLike for Directory {
         })
     }
 
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.inner.write().set_nonblocking(nonblocking);
         Ok(())
     }
+
+    fn is_nonblocking(&self) -> bool {
+        self.inner.read().is_nonblocking()
+    }
 }
 
 /// Maximum number of files per process
@@ -424,6 +702,26 @@ impl FileSystem {
     }
 }
 
+/// Whether a live process has its current directory or an open file at `path` or under it.
+pub fn path_in_use(path: &AbsPath) -> bool {
+    let prefix = format!("{}/", path);
+    let inside = |p: &AbsPath| *p == *path || p.starts_with(&prefix);
+    let processes: Vec<_> = crate::task::PROCESS_MAP
+        .lock()
+        .values()
+        .filter(|task| task.state() != crate::task::TaskState::Exited)
+        .cloned()
+        .collect();
+    processes.iter().any(|task| {
+        task.fs.lock().as_ref().is_some_and(|fs| {
+            inside(&fs.current_path)
+                || (0..fs.fd_table.files.capacity())
+                    .filter_map(|fd| fs.fd_table.files.get(fd))
+                    .any(|file| inside(&file.path()))
+        })
+    })
+}
+
 /// Initializes the file system.
 pub fn init_rootfs(mount_points: Vec<MountPoint>) {
     let main_fs = mount_points
@@ -431,7 +729,7 @@ pub fn init_rootfs(mount_points: Vec<MountPoint>) {
         .expect("No filesystem found")
         .fs
         .clone();
-    let mut root_dir = RootDirectory::new(main_fs);
+    let root_dir = RootDirectory::new(main_fs);
 
     for mp in mount_points.iter().skip(1) {
         let vfsops = mp.fs.clone();
@@ -480,6 +778,93 @@ pub fn absolute_path(path: &str) -> AxResult<AbsPath<'static>> {
     }
 }
 
+/// Maximum number of symbolic links followed while resolving a path.
+const MAX_SYMLINK_FOLLOWS: usize = 40;
+
+/// Resolves `path` as the `*at` syscalls do, into an absolute path without
+/// symbolic links, except maybe for its last component.
+///
+/// A relative `path` starts from the directory opened as `dirfd`, or from the
+/// current working directory if `dirfd` is `None`. Symbolic links are followed
+/// on the way, and at the last component if `follow_last` is set. The last
+/// component doesn't need to exist.
+///
+/// An empty `path` is `ENOENT`, unless `empty_path` is set, where it refers to
+/// the file opened as `dirfd`, of any type.
+pub fn resolve_path_at(
+    dirfd: Option<i32>,
+    path: &str,
+    follow_last: bool,
+    empty_path: bool,
+) -> LinuxResult<AbsPath<'static>> {
+    if path.is_empty() {
+        if !empty_path {
+            return Err(LinuxError::ENOENT);
+        }
+        return match dirfd {
+            Some(fd) => Ok(get_file_like(fd)?.path().to_owned()),
+            None => Ok(current_dir()?),
+        };
+    }
+    let start = if path.starts_with('/') {
+        AbsPath::new("/")
+    } else if let Some(fd) = dirfd {
+        let dir = get_file_like(fd)?
+            .into_any()
+            .downcast::<Directory>()
+            .map_err(|_| LinuxError::ENOTDIR)?;
+        let path = dir.path().to_owned();
+        path
+    } else {
+        current_dir()?
+    };
+
+    let mut resolved = start.to_string();
+    let mut rest: VecDeque<String> = path.split('/').map(String::from).collect();
+    let mut follows = 0;
+    while let Some(name) = rest.pop_front() {
+        match name.as_str() {
+            "" | "." => continue,
+            ".." => {
+                let parent = resolved.rfind('/').unwrap_or(0);
+                resolved.truncate(parent.max(1));
+                continue;
+            }
+            _ => {}
+        }
+        let next = if resolved == "/" {
+            format!("/{}", name)
+        } else {
+            format!("{}/{}", resolved, name)
+        };
+        // a trailing `/` leaves an empty component, so it is followed
+        let is_last = rest.is_empty();
+        if !is_last || follow_last {
+            match lookup(&AbsPath::new(&next)) {
+                Ok(node) if node.get_attr()?.is_symlink() => {
+                    follows += 1;
+                    if follows > MAX_SYMLINK_FOLLOWS {
+                        return Err(LinuxError::ELOOP);
+                    }
+                    let target = ruxfs::fops::read_link(&node)?;
+                    if target.starts_with('/') {
+                        resolved = String::from("/");
+                    }
+                    for name in target.split('/').rev() {
+                        rest.push_front(name.into());
+                    }
+                    continue;
+                }
+                Ok(_) => {}
+                Err(AxError::NotFound) if is_last => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        resolved = next;
+    }
+    Ok(AbsPath::new_owned(resolved))
+}
+
 /// Returns the current directory.
 pub fn current_dir() -> AxResult<AbsPath<'static>> {
     Ok(current().fs.lock().as_mut().unwrap().current_path.clone())
@@ -20,7 +20,7 @@
 //!   Otherwise, only a few APIs with naive implementation is available.
 //! - `irq`: Interrupts are enabled. If this feature is enabled, timer-based
 //!    APIs can be used, such as [`sleep`], [`sleep_until`], and
-//!    [`WaitQueue::wait_timeout`].
+//!    [`WaitQueue::wait_timeout`], as well as the [`worker_pool`].
 //! - `preempt`: Enable preemptive scheduling.
 //! - `sched_fifo`: Use the [FIFO cooperative scheduler][1]. It also enables the
 //!   `multitask` feature if it is enabled. This feature is enabled by default,
@@ -42,7 +42,7 @@
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "multitask")] {
-        #[macro_use(info, debug, trace)]
+        #[macro_use(info, debug, trace, warn)]
         extern crate log;
         extern crate alloc;
 
@@ -77,20 +77,22 @@ cfg_if::cfg_if! {
         }
 
         #[cfg(feature = "irq")]
-        mod timers;
+        pub mod timers;
+        #[cfg(feature = "irq")]
+        pub mod worker_pool;
 
         #[doc(cfg(feature = "multitask"))]
         pub use self::api::*;
         pub use self::api::{sleep, sleep_until, yield_now};
         pub use task::TaskState;
         #[cfg(feature = "signal")]
-        pub use self::signal::{rx_sigaction, Signal};
+        pub use self::signal::{rx_sigaction, SigInfo, Signal};
     } else {
         mod api_s;
         #[cfg(feature = "signal")]
         pub mod signal;
         pub use self::api_s::{sleep, sleep_until, yield_now};
         #[cfg(feature = "signal")]
-        pub use self::signal::{rx_sigaction, Signal};
+        pub use self::signal::{rx_sigaction, SigInfo, Signal};
     }
 }
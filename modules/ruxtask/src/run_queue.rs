@@ -194,6 +194,7 @@ impl AxRunQueue {
         if prev_task.ptr_eq(&next_task) {
             return;
         }
+        next_task.account_sched_in(prev_task.account_sched_out());
 
         unsafe {
             let prev_ctx_ptr = prev_task.ctx_mut_ptr();
@@ -229,6 +230,7 @@ impl AxRunQueue {
         if prev_task.ptr_eq(&next_task) {
             return;
         }
+        next_task.account_sched_in(prev_task.account_sched_out());
 
         unsafe {
             let prev_ctx_ptr = prev_task.ctx_mut_ptr();
@@ -8,16 +8,46 @@
  */
 
 //! Signal module for RuxOS.
+//!
+//! Signals 1 to 64 are supported, and signal sets use bit n - 1 for signal n,
+//! like a user `sigset_t`.
+//! A signal is either sent to a process, whose threads share it, or to a
+//! single thread. Blocked signals are per thread.
 
-use crate::current;
-#[cfg(feature = "irq")]
-use core::sync::atomic::AtomicI64;
+use crate::{current, task::PROCESS_MAP, TaskInner, TaskState, WaitQueue};
+use alloc::{
+    collections::VecDeque,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
 use core::{
     ffi::{c_int, c_uint, c_ulong},
+    sync::atomic::{AtomicU64, Ordering},
     time::Duration,
 };
 
+use ruxfdtable::{PollObserver, PollObservers};
 use ruxhal::trap::TrapHandler;
+use spinlock::SpinNoIrq;
+
+/// Number of signal numbers, signal 0 is not a signal.
+pub const NSIG: usize = 65;
+
+/// The bit of `signum` in a signal set.
+const fn sig_bit(signum: usize) -> u64 {
+    1 << (signum - 1)
+}
+
+/// Tasks waiting for signals to be raised, see [`Signal::wait_dequeue`].
+static SIGNAL_WAIT_QUEUE: WaitQueue = WaitQueue::new();
+/// Bumped whenever a signal is raised, so waiters never lock a signal set.
+static SIGNAL_GENERATION: AtomicU64 = AtomicU64::new(0);
+/// Signals for process groups of ttys queued in IRQ context, where no process
+/// is current, see [`Signal::queue_pgrp`].
+static QUEUED_PGRP: SpinNoIrq<VecDeque<(String, i32, u8)>> = SpinNoIrq::new(VecDeque::new());
+/// The task raising the signals of `QUEUED_PGRP` waits here for them.
+static QUEUED_PGRP_WAIT: WaitQueue = WaitQueue::new();
 
 /// sigaction in kernel
 #[allow(non_camel_case_types)]
@@ -45,17 +75,106 @@ impl rx_sigaction {
     }
 }
 
+/// Where a pending signal comes from, reported by `sigwaitinfo` and `signalfd`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct SigInfo {
+    /// `si_code`, such as `SI_USER` or `SI_TIMER`
+    pub code: i32,
+    /// process id of the sender, 0 if sent by the kernel
+    pub pid: i32,
+}
+
+impl SigInfo {
+    /// sent by `kill`
+    pub const SI_USER: i32 = 0;
+    /// sent by the expiration of a POSIX timer
+    pub const SI_TIMER: i32 = -2;
+    /// sent by the arrival of a message on an empty message queue
+    pub const SI_MESGQ: i32 = -3;
+    /// sent by the completion of an asynchronous I/O request
+    pub const SI_ASYNCIO: i32 = -4;
+    /// sent by `tkill` or `tgkill`
+    pub const SI_TKILL: i32 = -6;
+    /// sent by the kernel, such as for a control char typed on a tty
+    pub const SI_KERNEL: i32 = 0x80;
+}
+
 /// Signal struct
 pub struct Signal {
-    #[cfg(feature = "irq")]
-    signal: AtomicI64,
-    sigaction: [rx_sigaction; 32],
+    /// Signals pending on the process.
+    pending: u64,
+    info: [SigInfo; NSIG],
+    sigaction: [rx_sigaction; NSIG],
     timer_value: [Duration; 3],
     timer_interval: [Duration; 3],
+    /// Signal whose default action terminated the process, 0 if none.
+    killed_by: u8,
+    /// Observers of the signalfds of the process, notified whenever a signal
+    /// is raised on it or one of its threads.
+    observers: Arc<PollObservers>,
+    /// Name of the controlling tty of the process, whose control chars and
+    /// window size changes signal it.
+    ctty: Option<String>,
+}
+
+/// Signal state of a single thread.
+pub struct ThreadSignal {
+    /// Signals pending on this thread only.
+    pending: u64,
+    info: [SigInfo; NSIG],
+    /// Signals that stay pending instead of being handled by this thread.
+    blocked: u64,
+}
+
+impl Default for ThreadSignal {
+    fn default() -> Self {
+        Self {
+            pending: 0,
+            info: [SigInfo::default(); NSIG],
+            blocked: 0,
+        }
+    }
+}
+
+impl ThreadSignal {
+    /// Signal state of a new thread created by the current one, which
+    /// inherits the blocked signals.
+    pub(crate) fn inherited() -> Self {
+        Self {
+            blocked: current().thread_signal.lock().blocked,
+            ..Default::default()
+        }
+    }
+}
+
+/// The interface to terminate the current process, which the default action
+/// of a signal does.
+#[crate_interface::def_interface]
+pub trait ProcessExitIf {
+    /// Releases the resources of the current process and exits all its
+    /// threads with `exit_code`.
+    fn exit_process(exit_code: i32);
 }
 
 unsafe extern "C" fn default_handler(signum: c_int) {
-    panic!("default_handler, signum: {}", signum);
+    const SIGCHLD: c_int = 17;
+    const SIGCONT: c_int = 18;
+    const SIGSTOP: c_int = 19;
+    const SIGTTOU: c_int = 22;
+    const SIGURG: c_int = 23;
+    const SIGWINCH: c_int = 28;
+    match signum {
+        // ignored by default
+        SIGCHLD | SIGCONT | SIGURG | SIGWINCH => return,
+        // `SIGSTOP`, `SIGTSTP`, `SIGTTIN` and `SIGTTOU` would stop the
+        // process, which is not supported, so it keeps running
+        SIGSTOP..=SIGTTOU => return,
+        _ => {}
+    }
+    // killed by the signal, which `wait4` reports instead of the exit code
+    current().signal_if.lock().killed_by = signum as u8;
+    crate_interface::call_interface!(ProcessExitIf::exit_process, 0);
+    unreachable!("process killed by signal {}", signum);
 }
 
 #[cfg(feature = "signal")]
@@ -65,16 +184,7 @@ struct SignalHandler;
 #[crate_interface::impl_interface]
 impl TrapHandler for SignalHandler {
     fn handle_signal() {
-        let signal = Signal::signal(-1, true).unwrap();
-        for signum in 0..32 {
-            if signal & (1 << signum) != 0
-            /* TODO: && support mask */
-            {
-                Signal::signal(signum as i8, false);
-                Signal::sigaction(signum as u8, None, None);
-                Signal::signal_handle(signum as u8);
-            }
-        }
+        Signal::handle_pending();
     }
 }
 
@@ -84,52 +194,249 @@ impl Default for Signal {
     }
 }
 
+/// Wakes up the waiters of signals and the `observers` of the process the
+/// signal was raised on, called without any signal set locked.
+fn signal_raised(observers: &PollObservers) {
+    SIGNAL_GENERATION.fetch_add(1, Ordering::Release);
+    SIGNAL_WAIT_QUEUE.notify_all(false);
+    observers.notify();
+}
+
 impl Signal {
     ///crate new Signal struct
     pub fn new() -> Self {
         Self {
-            #[cfg(feature = "irq")]
-            signal: AtomicI64::new(0),
-            sigaction: [rx_sigaction::new(); 32],
+            pending: 0,
+            info: [SigInfo::default(); NSIG],
+            sigaction: [rx_sigaction::new(); NSIG],
             // Default::default() is not const
             timer_value: [Duration::from_nanos(0); 3],
             timer_interval: [Duration::from_nanos(0); 3],
+            killed_by: 0,
+            observers: Arc::new(PollObservers::new()),
+            ctty: None,
+        }
+    }
+    /// Signal state of a process forked by the current one, which inherits
+    /// the controlling tty
+    pub(crate) fn inherited() -> Self {
+        Self {
+            ctty: current().signal_if.lock().ctty.clone(),
+            ..Self::new()
         }
     }
-    /// Set signal
+    /// The signal whose default action terminated the process, if any
+    pub fn killed_by(&self) -> Option<u8> {
+        (self.killed_by != 0).then_some(self.killed_by)
+    }
+    /// Set signal of the current process
     /// signum: signal number, if signum < 0, just return current signal
     /// on: true: enable signal, false: disable signal
-    #[cfg(feature = "irq")]
-    pub fn signal(signum: i8, on: bool) -> Option<u32> {
-        use core::sync::atomic::Ordering;
-        if signum >= 32 {
+    /// return: signals pending on the process or the current thread
+    pub fn signal(signum: i8, on: bool) -> Option<u64> {
+        if signum >= NSIG as i8 {
             return None;
         }
         let binding = current();
-        let current_signal_if = binding.signal_if.lock();
-        let mut old = current_signal_if.signal.load(Ordering::Acquire);
-        if signum >= 0 {
+        let mut current_signal_if = binding.signal_if.lock();
+        let old = current_signal_if.pending | binding.thread_signal.lock().pending;
+        if signum > 0 && !on {
+            current_signal_if.pending &= !sig_bit(signum as usize);
+        }
+        drop(current_signal_if);
+        if signum > 0 && on {
+            Self::raise(&binding.signal_if, signum as u8, SigInfo::default());
+        }
+        Some(old)
+    }
+    /// Mark a signal pending on the signal set of a process, regardless of the current task
+    /// signum: signal number
+    /// info: where the signal comes from, kept until the signal is taken
+    /// return: true if the signal was already pending
+    pub fn raise(signal_if: &SpinNoIrq<Signal>, signum: u8, info: SigInfo) -> bool {
+        debug_assert!(signum > 0 && (signum as usize) < NSIG);
+        let mut signal = signal_if.lock();
+        let was_pending = signal.pending & sig_bit(signum as usize) != 0;
+        if !was_pending {
+            signal.pending |= sig_bit(signum as usize);
+            signal.info[signum as usize] = info;
+        }
+        let observers = signal.observers.clone();
+        drop(signal);
+        signal_raised(&observers);
+        was_pending
+    }
+    /// Mark a signal pending on the processes of the process group `pgrp`,
+    /// the foreground group of the tty called `tty`, which is 0 if it was
+    /// never set
+    ///
+    /// Process groups are not tracked yet, so the foreground group of a tty
+    /// is all the processes it is the controlling tty of.
+    pub fn raise_pgrp(tty: &str, _pgrp: i32, signum: u8, info: SigInfo) {
+        let processes: Vec<_> = PROCESS_MAP
+            .lock()
+            .values()
+            .filter(|task| task.state() != TaskState::Exited)
+            .cloned()
+            .collect();
+        for task in processes {
+            if task.signal_if.lock().ctty.as_deref() == Some(tty) {
+                Self::raise(&task.signal_if, signum, info);
+            }
+        }
+    }
+    /// Queue a signal for the processes of the process group `pgrp` of the
+    /// tty called `tty`, to be raised by the task running
+    /// [`Signal::raise_queued_pgrp`], as IRQ handlers such as the input of a
+    /// tty can't raise it themselves
+    pub fn queue_pgrp(tty: &str, pgrp: i32, signum: u8) {
+        QUEUED_PGRP
+            .lock()
+            .push_back((String::from(tty), pgrp, signum));
+        QUEUED_PGRP_WAIT.notify_one(false);
+    }
+    /// Raise the signals queued by [`Signal::queue_pgrp`] as they come, sent
+    /// by the kernel. It never returns, so a task is spawned to run it
+    pub fn raise_queued_pgrp() {
+        let info = SigInfo {
+            code: SigInfo::SI_KERNEL,
+            pid: 0,
+        };
+        loop {
+            QUEUED_PGRP_WAIT.wait_until(|| !QUEUED_PGRP.lock().is_empty());
             loop {
-                let new = if on {
-                    old | (1 << signum)
-                } else {
-                    old & !(1 << signum)
+                let queued = QUEUED_PGRP.lock().pop_front();
+                let Some((tty, pgrp, signum)) = queued else {
+                    break;
                 };
-
-                match {
-                    current_signal_if.signal.compare_exchange_weak(
-                        old,
-                        new,
-                        Ordering::AcqRel,
-                        Ordering::Acquire,
-                    )
-                } {
-                    Ok(_) => break,
-                    Err(x) => old = x,
+                Self::raise_pgrp(&tty, pgrp, signum, info);
+            }
+        }
+    }
+    /// Mark a signal pending on the thread `task` only
+    /// return: true if the signal was already pending
+    pub fn raise_thread(task: &TaskInner, signum: u8, info: SigInfo) -> bool {
+        debug_assert!(signum > 0 && (signum as usize) < NSIG);
+        let mut thread_signal = task.thread_signal.lock();
+        let was_pending = thread_signal.pending & sig_bit(signum as usize) != 0;
+        if !was_pending {
+            thread_signal.pending |= sig_bit(signum as usize);
+            thread_signal.info[signum as usize] = info;
+        }
+        drop(thread_signal);
+        let observers = task.signal_if.lock().observers.clone();
+        signal_raised(&observers);
+        was_pending
+    }
+    /// Take a pending signal out of the pending sets of the current thread
+    /// and process without calling its handler, signals of the thread first
+    /// mask: signals to look for, bit n - 1 for signal n
+    /// return: the lowest pending signal in `mask`
+    pub fn dequeue(mask: u64) -> Option<(u8, SigInfo)> {
+        Self::dequeue_from(&current().signal_if, mask)
+    }
+    /// Take a pending signal like [`Signal::dequeue`] out of the process of
+    /// `signal_if`, and out of the current thread only if it belongs to it,
+    /// e.g. for a signalfd read by a worker task
+    pub fn dequeue_from(signal_if: &Arc<SpinNoIrq<Signal>>, mask: u64) -> Option<(u8, SigInfo)> {
+        let binding = current();
+        let mut process_signal = signal_if.lock();
+        if Arc::ptr_eq(&binding.signal_if, signal_if) {
+            let mut thread_signal = binding.thread_signal.lock();
+            let ready = thread_signal.pending & mask;
+            if ready != 0 {
+                let signum = ready.trailing_zeros() as usize + 1;
+                thread_signal.pending &= !sig_bit(signum);
+                return Some((signum as u8, thread_signal.info[signum]));
+            }
+        }
+        let ready = process_signal.pending & mask;
+        if ready != 0 {
+            let signum = ready.trailing_zeros() as usize + 1;
+            process_signal.pending &= !sig_bit(signum);
+            return Some((signum as u8, process_signal.info[signum]));
+        }
+        None
+    }
+    /// Signals pending on the process of `signal_if`, and on the current
+    /// thread if it belongs to it
+    pub fn pending(signal_if: &Arc<SpinNoIrq<Signal>>) -> u64 {
+        let binding = current();
+        let mut pending = signal_if.lock().pending;
+        if Arc::ptr_eq(&binding.signal_if, signal_if) {
+            pending |= binding.thread_signal.lock().pending;
+        }
+        pending
+    }
+    /// Block until a signal in `mask` is pending and take it like [`Signal::dequeue_from`]
+    /// deadline: monotonic time to give up at, wait forever if None
+    /// return: None if timed out
+    #[cfg(feature = "irq")]
+    pub fn wait_dequeue(
+        signal_if: &Arc<SpinNoIrq<Signal>>,
+        mask: u64,
+        deadline: Option<Duration>,
+    ) -> Option<(u8, SigInfo)> {
+        loop {
+            let generation = SIGNAL_GENERATION.load(Ordering::Acquire);
+            if let Some(sig) = Self::dequeue_from(signal_if, mask) {
+                return Some(sig);
+            }
+            let raised = || SIGNAL_GENERATION.load(Ordering::Acquire) != generation;
+            match deadline {
+                None => SIGNAL_WAIT_QUEUE.wait_until(raised),
+                Some(deadline) => {
+                    let now = ruxhal::time::current_time();
+                    if now >= deadline {
+                        return None;
+                    }
+                    SIGNAL_WAIT_QUEUE.wait_timeout_until(deadline - now, raised);
                 }
             }
         }
-        Some(old.try_into().unwrap())
+    }
+    /// Set the controlling tty of the process of `signal_if` to the tty
+    /// called `tty`, or detach it from its controlling tty if None
+    pub fn set_controlling_tty(signal_if: &SpinNoIrq<Signal>, tty: Option<&str>) {
+        signal_if.lock().ctty = tty.map(String::from);
+    }
+    /// The name of the controlling tty of the process of `signal_if`
+    pub fn controlling_tty(signal_if: &SpinNoIrq<Signal>) -> Option<String> {
+        signal_if.lock().ctty.clone()
+    }
+    /// Registers `observer` to be notified whenever a signal is raised on the
+    /// process of `signal_if` or one of its threads, used by signalfds
+    pub fn add_poll_observer(
+        signal_if: &SpinNoIrq<Signal>,
+        observer: Weak<dyn PollObserver>,
+        key: usize,
+        exclusive: bool,
+    ) {
+        let observers = signal_if.lock().observers.clone();
+        observers.add(observer, key, exclusive);
+    }
+    /// Unregisters an observer added by [`Signal::add_poll_observer`]
+    pub fn remove_poll_observer(
+        signal_if: &SpinNoIrq<Signal>,
+        observer: &Weak<dyn PollObserver>,
+        key: usize,
+    ) {
+        let observers = signal_if.lock().observers.clone();
+        observers.remove(observer, key);
+    }
+    /// Set signals blocked by the current thread, which stay pending instead of being handled
+    /// new_blocked: new mask, bit n - 1 for signal n, if None, just return current mask
+    /// return: the old mask
+    pub fn blocked(new_blocked: Option<u64>) -> u64 {
+        // SIGKILL and SIGSTOP cannot be blocked
+        const UNBLOCKABLE: u64 = sig_bit(9) | sig_bit(19);
+        let binding = current();
+        let mut thread_signal = binding.thread_signal.lock();
+        let old = thread_signal.blocked;
+        if let Some(new) = new_blocked {
+            thread_signal.blocked = new & !UNBLOCKABLE;
+        }
+        old
     }
     /// Set signal action
     /// signum: signal number
@@ -161,8 +468,9 @@ impl Signal {
     /// When the registered sa_handler for the specified signal is None, logs an error message
     pub fn signal_handle(signum: u8) {
         let binding = current();
-        let current_signal_if = binding.signal_if.lock();
-        if let Some(handler) = current_signal_if.sigaction[signum as usize].sa_handler {
+        // the handler may use signals itself, so it is not called with the lock held
+        let handler = binding.signal_if.lock().sigaction[signum as usize].sa_handler;
+        if let Some(handler) = handler {
             unsafe {
                 handler(signum as c_int);
             }
@@ -170,6 +478,14 @@ impl Signal {
             log::error!("no sigaction !");
         }
     }
+    /// Handle the signals pending on the current thread or process that it
+    /// doesn't block, lowest first
+    pub fn handle_pending() {
+        let blocked = Self::blocked(None);
+        while let Some((signum, _)) = Self::dequeue(!blocked) {
+            Self::signal_handle(signum);
+        }
+    }
     /// Set timer
     /// which: timer type
     /// new_value: new timer value
@@ -34,6 +34,8 @@ use memory_addr::{align_up_4k, VirtAddr, PAGE_SIZE_4K};
 use ruxhal::arch::TaskContext;
 
 use crate::current;
+#[cfg(feature = "signal")]
+use crate::signal::ThreadSignal;
 #[cfg(not(feature = "musl"))]
 use crate::tsd::{DestrFunction, KEYS, TSD};
 #[cfg(feature = "paging")]
@@ -83,6 +85,13 @@ pub struct TaskInner {
     exit_code: AtomicI32,
     wait_for_exit: WaitQueue,
 
+    /// CPU time consumed by this task, in nanoseconds.
+    cpu_time: AtomicU64,
+    /// The monotonic time when this task was last switched in, in nanoseconds.
+    sched_in_time: AtomicU64,
+    /// CPU times shared by all the tasks of the same process.
+    process_times: Arc<ProcessTimes>,
+
     stack_map_addr: SpinNoIrq<VirtAddr>,
     kstack: SpinNoIrq<Arc<Option<TaskStack>>>,
     ctx: UnsafeCell<TaskContext>,
@@ -96,6 +105,9 @@ pub struct TaskInner {
     #[cfg(feature = "signal")]
     /// The signal to be sent to the task.
     pub signal_if: Arc<SpinNoIrq<Signal>>,
+    #[cfg(feature = "signal")]
+    /// The signals sent to this thread only, and the signals it blocks.
+    pub(crate) thread_signal: SpinNoIrq<ThreadSignal>,
 
     // set tid
     #[cfg(feature = "musl")]
@@ -114,6 +126,15 @@ pub struct TaskInner {
     pub mm: Arc<MmapStruct>,
 }
 
+/// CPU times accounted to a process, in nanoseconds.
+#[derive(Debug, Default)]
+struct ProcessTimes {
+    /// CPU time consumed by all the tasks of the process.
+    cpu_time: AtomicU64,
+    /// CPU time consumed by the waited-for children of the process.
+    children_cpu_time: AtomicU64,
+}
+
 impl TaskId {
     fn new() -> Self {
         static ID_COUNTER: AtomicU64 = AtomicU64::new(1);
@@ -177,6 +198,16 @@ impl TaskInner {
         self.exit_code.load(Ordering::Acquire)
     }
 
+    /// Status of the exited process reported by `wait4`: the signal that
+    /// killed it, or else its exit code shifted by 8 bits.
+    pub fn wait_status(&self) -> i32 {
+        #[cfg(feature = "signal")]
+        if let Some(signum) = self.signal_if.lock().killed_by() {
+            return signum as i32;
+        }
+        (self.exit_code() & 0xff) << 8
+    }
+
     /// Get process task
     pub fn process_task(&self) -> Arc<AxTask> {
         if let Some(process_task) = self.process_task.upgrade() {
@@ -245,6 +276,9 @@ impl TaskInner {
             preempt_disable_count: AtomicUsize::new(0),
             exit_code: AtomicI32::new(0),
             wait_for_exit: WaitQueue::new(),
+            cpu_time: AtomicU64::new(0),
+            sched_in_time: AtomicU64::new(0),
+            process_times: current().process_times.clone(),
             stack_map_addr: SpinNoIrq::new(VirtAddr::from(0)), // should be set later
             kstack: SpinNoIrq::new(Arc::new(None)),
             ctx: UnsafeCell::new(TaskContext::new()),
@@ -256,6 +290,8 @@ impl TaskInner {
             set_tid: AtomicU64::new(0),
             #[cfg(feature = "signal")]
             signal_if: current().signal_if.clone(),
+            #[cfg(feature = "signal")]
+            thread_signal: SpinNoIrq::new(ThreadSignal::inherited()),
             #[cfg(feature = "musl")]
             tl: AtomicU64::new(0),
             #[cfg(feature = "paging")]
@@ -294,6 +330,9 @@ impl TaskInner {
             preempt_disable_count: AtomicUsize::new(0),
             exit_code: AtomicI32::new(0),
             wait_for_exit: WaitQueue::new(),
+            cpu_time: AtomicU64::new(0),
+            sched_in_time: AtomicU64::new(0),
+            process_times: current().process_times.clone(),
             stack_map_addr: SpinNoIrq::new(VirtAddr::from(0)),
             kstack: SpinNoIrq::new(Arc::new(None)),
             ctx: UnsafeCell::new(TaskContext::new()),
@@ -302,6 +341,8 @@ impl TaskInner {
             set_tid,
             #[cfg(feature = "signal")]
             signal_if: current().signal_if.clone(),
+            #[cfg(feature = "signal")]
+            thread_signal: SpinNoIrq::new(ThreadSignal::inherited()),
             // clear child tid
             tl,
             #[cfg(feature = "paging")]
@@ -452,16 +493,32 @@ impl TaskInner {
             )
             .expect("failed to map stack region when forking");
 
+        // pages of shared mappings keep their permissions in both processes.
+        let shared_pages = cloned_mm.shared_pages();
+
         // clone parent pages in memory, and mark all unshared pages to read-only
         for (vaddr, page_info) in cloned_mm.mem_map.lock().iter() {
             let paddr = page_info.paddr;
+            let flags = if shared_pages.contains(vaddr) {
+                let (_, flags, _) = current_task
+                    .pagetable
+                    .lock()
+                    .query(VirtAddr::from(*vaddr))
+                    .expect("Inconsistent page table with mem_map");
+                flags
+            } else {
+                MappingFlags::READ
+            };
             cloned_page_table
-                .map((*vaddr).into(), paddr, PageSize::Size4K, MappingFlags::READ)
+                .map((*vaddr).into(), paddr, PageSize::Size4K, flags)
                 .expect("failed to map when forking");
         }
 
         // mark the parent process's page table to read-only.
         for (vaddr, _) in current_task.mm.mem_map.lock().iter() {
+            if shared_pages.contains(vaddr) {
+                continue;
+            }
             let mut page_table = current_task.pagetable.lock();
             let vaddr = VirtAddr::from(*vaddr);
             let (_, mapping_flag, _) = page_table
@@ -512,6 +569,9 @@ impl TaskInner {
             ),
             exit_code: AtomicI32::new(0),
             wait_for_exit: WaitQueue::new(),
+            cpu_time: AtomicU64::new(0),
+            sched_in_time: AtomicU64::new(0),
+            process_times: Arc::new(ProcessTimes::default()),
             stack_map_addr: SpinNoIrq::new(*current().stack_map_addr.lock()),
             kstack: SpinNoIrq::new(Arc::new(Some(new_stack))),
             ctx: UnsafeCell::new(TaskContext::new()),
@@ -522,7 +582,9 @@ impl TaskInner {
             #[cfg(feature = "musl")]
             set_tid: AtomicU64::new(0),
             #[cfg(feature = "signal")]
-            signal_if: Arc::new(spinlock::SpinNoIrq::new(Signal::new())),
+            signal_if: Arc::new(spinlock::SpinNoIrq::new(Signal::inherited())),
+            #[cfg(feature = "signal")]
+            thread_signal: SpinNoIrq::new(ThreadSignal::inherited()),
             #[cfg(feature = "musl")]
             tl: AtomicU64::new(0),
             #[cfg(feature = "paging")]
@@ -591,6 +653,9 @@ impl TaskInner {
             preempt_disable_count: AtomicUsize::new(0),
             exit_code: AtomicI32::new(0),
             wait_for_exit: WaitQueue::new(),
+            cpu_time: AtomicU64::new(0),
+            sched_in_time: AtomicU64::new(ruxhal::time::current_time_nanos()),
+            process_times: Arc::new(ProcessTimes::default()),
             stack_map_addr: SpinNoIrq::new(VirtAddr::from(0)), // set in set_stack_top
             kstack: SpinNoIrq::new(Arc::new(None)),
             ctx: UnsafeCell::new(TaskContext::new()),
@@ -602,6 +667,8 @@ impl TaskInner {
             set_tid: AtomicU64::new(0),
             #[cfg(feature = "signal")]
             signal_if: Arc::new(spinlock::SpinNoIrq::new(Signal::new())),
+            #[cfg(feature = "signal")]
+            thread_signal: SpinNoIrq::new(ThreadSignal::default()),
             #[cfg(feature = "musl")]
             tl: AtomicU64::new(0),
             #[cfg(feature = "paging")]
@@ -663,6 +730,9 @@ impl TaskInner {
             preempt_disable_count: AtomicUsize::new(0),
             exit_code: AtomicI32::new(0),
             wait_for_exit: WaitQueue::new(),
+            cpu_time: AtomicU64::new(0),
+            sched_in_time: AtomicU64::new(ruxhal::time::current_time_nanos()),
+            process_times: Arc::new(ProcessTimes::default()),
             stack_map_addr: SpinNoIrq::new(idle_kstack.end()),
             kstack: SpinNoIrq::new(Arc::new(Some(idle_kstack))),
             ctx: UnsafeCell::new(TaskContext::new()),
@@ -674,6 +744,8 @@ impl TaskInner {
             set_tid: AtomicU64::new(0),
             #[cfg(feature = "signal")]
             signal_if: task_ref.signal_if.clone(),
+            #[cfg(feature = "signal")]
+            thread_signal: SpinNoIrq::new(ThreadSignal::default()),
             #[cfg(feature = "musl")]
             tl: AtomicU64::new(0),
             #[cfg(feature = "paging")]
@@ -799,6 +871,67 @@ impl TaskInner {
         self.wait_for_exit.notify_all_locked(false, rq);
     }
 
+    /// Charges the CPU time since the task was last switched in, and returns
+    /// the current monotonic time in nanoseconds.
+    pub(crate) fn account_sched_out(&self) -> u64 {
+        let now = ruxhal::time::current_time_nanos();
+        let delta = now.saturating_sub(self.sched_in_time.swap(now, Ordering::AcqRel));
+        self.cpu_time.fetch_add(delta, Ordering::Relaxed);
+        if !self.is_idle {
+            self.process_times
+                .cpu_time
+                .fetch_add(delta, Ordering::Relaxed);
+        }
+        now
+    }
+
+    #[inline]
+    pub(crate) fn account_sched_in(&self, now: u64) {
+        self.sched_in_time.store(now, Ordering::Release);
+    }
+
+    /// CPU time not yet charged if the task is running.
+    fn pending_cpu_nanos(&self) -> u64 {
+        if self.is_running() {
+            ruxhal::time::current_time_nanos()
+                .saturating_sub(self.sched_in_time.load(Ordering::Acquire))
+        } else {
+            0
+        }
+    }
+
+    /// Gets the CPU time consumed by this task.
+    pub fn thread_cpu_time(&self) -> core::time::Duration {
+        let nanos = self.cpu_time.load(Ordering::Relaxed) + self.pending_cpu_nanos();
+        core::time::Duration::from_nanos(nanos)
+    }
+
+    /// Gets the CPU time consumed by the process of this task.
+    ///
+    /// Only the running time of this task is counted for the current time
+    /// slice, other running tasks of the process are charged when they are
+    /// switched out.
+    pub fn process_cpu_time(&self) -> core::time::Duration {
+        let nanos = self.process_times.cpu_time.load(Ordering::Relaxed) + self.pending_cpu_nanos();
+        core::time::Duration::from_nanos(nanos)
+    }
+
+    /// Gets the CPU time consumed by the waited-for children of the process.
+    pub fn children_cpu_time(&self) -> core::time::Duration {
+        core::time::Duration::from_nanos(
+            self.process_times.children_cpu_time.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Charges the CPU time of a reaped child process to this process.
+    pub fn add_children_cpu_time(&self, child: &TaskInner) {
+        let nanos = child.process_cpu_time().as_nanos() as u64
+            + child.children_cpu_time().as_nanos() as u64;
+        self.process_times
+            .children_cpu_time
+            .fetch_add(nanos, Ordering::Relaxed);
+    }
+
     #[inline]
     pub(crate) const unsafe fn ctx_mut_ptr(&self) -> *mut TaskContext {
         self.ctx.get()
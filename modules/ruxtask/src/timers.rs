@@ -7,7 +7,10 @@
  *   See the Mulan PSL v2 for more details.
  */
 
-use alloc::sync::Arc;
+//! Timed events of the task manager, checked on every timer tick.
+
+use alloc::{boxed::Box, sync::Arc};
+use core::sync::atomic::{AtomicU64, Ordering};
 use lazy_init::LazyInit;
 use ruxhal::time::current_time;
 use spinlock::SpinNoIrq;
@@ -16,31 +19,93 @@ use timer_list::{TimeValue, TimerEvent, TimerList};
 use crate::{AxTaskRef, RUN_QUEUE};
 
 // TODO: per-CPU
-static TIMER_LIST: LazyInit<SpinNoIrq<TimerList<TaskWakeupEvent>>> = LazyInit::new();
+static TIMER_LIST: LazyInit<SpinNoIrq<TimerList<TaskTimerEvent>>> = LazyInit::new();
+
+/// Identifier of a callback registered by [`set_timer_callback`].
+pub type TimerCallbackId = u64;
 
-struct TaskWakeupEvent(AxTaskRef);
+/// Called when the wall clock was set or re-synced, see [`set_wall_clock_handler`].
+static WALL_CLOCK_HANDLER: SpinNoIrq<Option<fn()>> = SpinNoIrq::new(None);
+/// The wall clock generation the handler was last called for.
+static WALL_CLOCK_SEEN: AtomicU64 = AtomicU64::new(0);
+
+enum TaskTimerEvent {
+    /// Wakes up a sleeping task.
+    Wakeup(AxTaskRef),
+    /// Calls a function in the timer interrupt context.
+    Callback(TimerCallbackId, Box<dyn FnOnce(TimeValue) + Send>),
+}
 
-impl TimerEvent for TaskWakeupEvent {
-    fn callback(self, _now: TimeValue) {
-        let mut rq = RUN_QUEUE.lock();
-        self.0.set_in_timer_list(false);
-        rq.unblock_task(self.0, true);
+impl TimerEvent for TaskTimerEvent {
+    fn callback(self, now: TimeValue) {
+        match self {
+            Self::Wakeup(task) => {
+                let mut rq = RUN_QUEUE.lock();
+                task.set_in_timer_list(false);
+                rq.unblock_task(task, true);
+            }
+            Self::Callback(_, f) => f(now),
+        }
     }
 }
 
-pub fn set_alarm_wakeup(deadline: TimeValue, task: AxTaskRef) {
+pub(crate) fn set_alarm_wakeup(deadline: TimeValue, task: AxTaskRef) {
     let mut timers = TIMER_LIST.lock();
     task.set_in_timer_list(true);
-    timers.set(deadline, TaskWakeupEvent(task));
+    timers.set(deadline, TaskTimerEvent::Wakeup(task));
 }
 
-pub fn cancel_alarm(task: &AxTaskRef) {
+pub(crate) fn cancel_alarm(task: &AxTaskRef) {
     let mut timers = TIMER_LIST.lock();
     task.set_in_timer_list(false);
-    timers.cancel(|t| Arc::ptr_eq(&t.0, task));
+    timers.cancel(|e| matches!(e, TaskTimerEvent::Wakeup(t) if Arc::ptr_eq(t, task)));
+}
+
+/// Registers `f` to be called once the monotonic clock reaches `deadline`.
+///
+/// The callback runs in the timer interrupt context, so it must not block.
+pub fn set_timer_callback<F>(deadline: TimeValue, f: F) -> TimerCallbackId
+where
+    F: FnOnce(TimeValue) + Send + 'static,
+{
+    static ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+    let id = ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    TIMER_LIST
+        .lock()
+        .set(deadline, TaskTimerEvent::Callback(id, Box::new(f)));
+    id
+}
+
+/// Cancels a callback registered by [`set_timer_callback`] if it has not
+/// been called yet.
+pub fn cancel_timer_callback(id: TimerCallbackId) {
+    TIMER_LIST
+        .lock()
+        .cancel(|e| matches!(e, TaskTimerEvent::Callback(cb_id, _) if *cb_id == id));
+}
+
+/// Registers `handler` to be called in the timer interrupt context once the
+/// wall clock was set or re-synced with the RTC, so that waits until a wall
+/// clock time can be re-armed.
+pub fn set_wall_clock_handler(handler: fn()) {
+    let mut current = WALL_CLOCK_HANDLER.lock();
+    WALL_CLOCK_SEEN.store(ruxhal::time::wall_clock_generation(), Ordering::Release);
+    *current = Some(handler);
+}
+
+fn check_wall_clock() {
+    let generation = ruxhal::time::wall_clock_generation();
+    if WALL_CLOCK_SEEN.swap(generation, Ordering::AcqRel) == generation {
+        return;
+    }
+    let handler = *WALL_CLOCK_HANDLER.lock();
+    if let Some(handler) = handler {
+        handler();
+    }
 }
 
-pub fn check_events() {
+pub(crate) fn check_events() {
+    check_wall_clock();
     loop {
         let now = current_time();
         let event = TIMER_LIST.lock().expire_one(now);
@@ -52,6 +117,6 @@ pub fn check_events() {
     }
 }
 
-pub fn init() {
+pub(crate) fn init() {
     TIMER_LIST.init_by(SpinNoIrq::new(TimerList::new()));
 }
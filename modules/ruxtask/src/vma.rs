@@ -15,21 +15,29 @@
 use crate::current;
 use crate::fs::get_file_like;
 use crate::{fs::File, TaskId};
-use alloc::{collections::BTreeMap, sync::Arc};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 use axalloc::global_allocator;
 use memory_addr::PhysAddr;
 use ruxhal::mem::phys_to_virt;
 
 #[cfg(feature = "fs")]
-use alloc::vec::Vec;
+use alloc::{sync::Weak, vec::Vec};
 #[cfg(feature = "fs")]
 use ruxfs::fops::OpenOptions;
 
+#[cfg(feature = "fs")]
+use core::sync::atomic::{AtomicUsize, Ordering};
 #[cfg(feature = "fs")]
 use memory_addr::PAGE_SIZE_4K;
 
 use spinlock::SpinNoIrq;
 
+/// The `MAP_SHARED` flag of a mapping.
+pub const MAP_SHARED: u32 = 0x01;
+
 // use `used_fs` instead of `#[cfg(feature = "fs")]{}` to cancel the scope of code.
 #[cfg(feature = "fs")]
 macro_rules! used_fs {
@@ -54,6 +62,12 @@ used_fs! {
         /// bitmap for free pages in swap file.
         pub static ref BITMAP_FREE: SpinNoIrq<Vec<usize>> = SpinNoIrq::new((0..SWAP_MAX).step_by(PAGE_SIZE_4K).collect());
     }
+    /// pages of shared file mappings, keyed by the file node and the offset
+    /// in the file, so that all the mappings of a file page use the same memory.
+    static SHARED_PAGES: SpinNoIrq<BTreeMap<(usize, usize), Weak<PageInfo>>> = SpinNoIrq::new(BTreeMap::new());
+    /// size of `SHARED_PAGES` at which the entries of freed pages are removed.
+    static SHARED_PAGES_PRUNE_AT: AtomicUsize = AtomicUsize::new(SHARED_PAGES_MIN_PRUNE);
+    const SHARED_PAGES_MIN_PRUNE: usize = 64;
 }
 
 /// open target file
@@ -80,6 +94,22 @@ pub struct FileInfo {
     pub size: usize,
 }
 
+#[cfg(feature = "fs")]
+impl FileInfo {
+    /// Write the content of the mapped page at `src` back to the file.
+    pub fn write_back(&self, src: *const u8) {
+        let buf = unsafe { core::slice::from_raw_parts(src, self.size) };
+        if let Err(e) = self
+            .file
+            .inner
+            .read()
+            .write_back_at(self.offset as u64, buf)
+        {
+            warn!("failed to write back mapped page: {:?}", e);
+        }
+    }
+}
+
 /// Data structure for information of mapping.
 pub struct PageInfo {
     /// physical address of the page
@@ -112,6 +142,25 @@ pub struct MmapStruct {
     pub swaped_map: SpinNoIrq<BTreeMap<usize, Arc<SwapInfo>>>,
 }
 
+impl MmapStruct {
+    /// Get the loaded pages of shared mappings, which are not copied on
+    /// write when forking.
+    pub fn shared_pages(&self) -> BTreeSet<usize> {
+        let vma_map = self.vma_map.lock();
+        self.mem_map
+            .lock()
+            .keys()
+            .filter(|&&vaddr| {
+                vma_map
+                    .range(..=vaddr)
+                    .next_back()
+                    .is_some_and(|(_, vma)| vaddr < vma.end_addr && vma.is_shared())
+            })
+            .copied()
+            .collect()
+    }
+}
+
 /// clone data structure for MmapStruct (when forking).
 impl Clone for MmapStruct {
     fn clone(&self) -> Self {
@@ -134,6 +183,11 @@ impl Drop for SwapInfo {
 // release memory of a page in memory
 impl Drop for PageInfo {
     fn drop(&mut self) {
+        // the last mapping of a shared file page is gone
+        #[cfg(feature = "fs")]
+        if let Some(file_info) = &self.mapping_file {
+            file_info.write_back(phys_to_virt(self.paddr).as_ptr());
+        }
         // use `global_allocator()` to dealloc pages.
         global_allocator().dealloc_pages(phys_to_virt(self.paddr).as_usize(), 1);
     }
@@ -156,6 +210,27 @@ pub struct Vma {
     pub flags: u32,
     /// process that the mapping belongs to
     pub from_process: TaskId,
+    /// the device memory mapped, shared with the parts split from the mapping
+    pub device: Option<Arc<DeviceMapping>>,
+}
+
+/// A mapping of the device memory of a file, which is released when the
+/// mapping and all the parts split from it are gone.
+pub struct DeviceMapping(Arc<File>);
+
+impl DeviceMapping {
+    /// Record a mapping of the device memory of `file`, which has been got
+    /// by `device_memory`.
+    pub fn new(file: Arc<File>) -> Self {
+        Self(file)
+    }
+}
+
+impl Drop for DeviceMapping {
+    fn drop(&mut self) {
+        #[cfg(feature = "fs")]
+        self.0.inner.read().device_unmapped();
+    }
 }
 
 impl MmapStruct {
@@ -194,20 +269,82 @@ impl Vma {
             flags,
             prot,
             from_process: current().id(),
+            device: None,
+        }
+    }
+
+    /// Create a new `Vma` instance backed by `file`, which needs not be in
+    /// the fd table.
+    pub fn with_file(file: Arc<File>, offset: usize, prot: u32, flags: u32) -> Self {
+        Vma {
+            start_addr: 0,
+            end_addr: 0,
+            file: Some(file),
+            offset,
+            flags,
+            prot,
+            from_process: current().id(),
+            device: None,
         }
     }
 
-    /// Clone a new `Vma` instance.
+    /// Whether the changes to the mapping are shared with other mappings.
+    pub fn is_shared(&self) -> bool {
+        self.flags & MAP_SHARED != 0
+    }
+
+    /// Get the key of the page at `vaddr` in [`find_shared_page`], if this is
+    /// a shared file mapping.
+    #[cfg(feature = "fs")]
+    pub fn shared_page_key(&self, vaddr: usize) -> Option<(usize, usize)> {
+        let file = self.file.as_ref().filter(|_| self.is_shared())?;
+        let node_id = file.inner.read().node_id();
+        Some((node_id, self.offset + (vaddr - self.start_addr)))
+    }
+
+    /// Clone a new `Vma` instance for the part `[start_addr, end_addr)` of
+    /// `vma`, which maps the file from the offset of `start_addr` in `vma`.
     pub fn clone_from(vma: &Vma, start_addr: usize, end_addr: usize) -> Self {
         Vma {
             start_addr,
             end_addr,
             // #[cfg(feature = "fs")]
             file: vma.file.clone(),
-            offset: vma.offset,
+            offset: vma.offset + (start_addr - vma.start_addr),
             prot: vma.prot,
-            flags: vma.prot,
+            flags: vma.flags,
             from_process: current().id(),
+            device: vma.device.clone(),
+        }
+    }
+
+    /// Clone a new `Vma` instance of `vma` moved to `[start_addr, end_addr)`,
+    /// which maps the file from the same offset.
+    pub fn move_from(vma: &Vma, start_addr: usize, end_addr: usize) -> Self {
+        Vma {
+            start_addr,
+            end_addr,
+            ..Self::clone_from(vma, vma.start_addr, vma.end_addr)
         }
     }
 }
+
+/// Find the page loaded by another mapping of the same file page.
+#[cfg(feature = "fs")]
+pub fn find_shared_page(key: (usize, usize)) -> Option<Arc<PageInfo>> {
+    SHARED_PAGES.lock().get(&key).and_then(Weak::upgrade)
+}
+
+/// Record a page loaded for a shared file mapping.
+#[cfg(feature = "fs")]
+pub fn add_shared_page(key: (usize, usize), page: &Arc<PageInfo>) {
+    let mut pages = SHARED_PAGES.lock();
+    pages.insert(key, Arc::downgrade(page));
+    // drop the freed pages only once the map doubled since the last time, so
+    // that recording a page stays cheap
+    if pages.len() >= SHARED_PAGES_PRUNE_AT.load(Ordering::Relaxed) {
+        pages.retain(|_, p| p.strong_count() > 0);
+        let prune_at = (pages.len() * 2).max(SHARED_PAGES_MIN_PRUNE);
+        SHARED_PAGES_PRUNE_AT.store(prune_at, Ordering::Relaxed);
+    }
+}
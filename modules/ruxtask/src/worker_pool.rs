@@ -0,0 +1,122 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+ *   See the Mulan PSL v2 for more details.
+ */
+
+//! Pools of worker tasks running work in the background.
+//!
+//! Workers are spawned on demand by the task queueing the work, so they are
+//! threads of its process sharing its address space and files. A worker exits
+//! once it has been idle for [`WORKER_IDLE_TIMEOUT`].
+
+use alloc::{boxed::Box, collections::VecDeque, string::String, sync::Arc};
+use core::time::Duration;
+
+use spinlock::SpinNoIrq;
+
+use crate::WaitQueue;
+
+/// Time after which an idle worker exits.
+pub const WORKER_IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+type Work = Box<dyn FnOnce() + Send>;
+
+struct PoolState {
+    works: VecDeque<Work>,
+    workers: usize,
+    /// number of workers waiting for work
+    idle: usize,
+}
+
+struct PoolInner {
+    name: String,
+    max_workers: usize,
+    state: SpinNoIrq<PoolState>,
+    wait_queue: WaitQueue,
+}
+
+/// A pool of worker tasks, which start the queued works in order.
+#[derive(Clone)]
+pub struct WorkerPool {
+    inner: Arc<PoolInner>,
+}
+
+impl WorkerPool {
+    /// Creates a pool of at most `max_workers` workers, with tasks named
+    /// `name`.
+    pub fn new(name: &str, max_workers: usize) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                name: name.into(),
+                max_workers: max_workers.max(1),
+                state: SpinNoIrq::new(PoolState {
+                    works: VecDeque::new(),
+                    workers: 0,
+                    idle: 0,
+                }),
+                wait_queue: WaitQueue::new(),
+            }),
+        }
+    }
+
+    /// Queues `work` to be run by a worker, spawning a new worker if none is
+    /// free and the pool is not full.
+    pub fn queue<F>(&self, work: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut state = self.inner.state.lock();
+        state.works.push_back(Box::new(work));
+        let spawn = state.works.len() > state.idle && state.workers < self.inner.max_workers;
+        if spawn {
+            state.workers += 1;
+        }
+        drop(state);
+        if spawn {
+            let inner = self.inner.clone();
+            crate::spawn_raw(
+                move || worker(inner),
+                self.inner.name.clone(),
+                ruxconfig::TASK_STACK_SIZE,
+            );
+        } else {
+            self.inner.wait_queue.notify_one(true);
+        }
+    }
+
+    /// Number of queued works that no worker has started yet.
+    pub fn pending(&self) -> usize {
+        self.inner.state.lock().works.len()
+    }
+
+    /// Number of live workers.
+    pub fn workers(&self) -> usize {
+        self.inner.state.lock().workers
+    }
+}
+
+fn worker(inner: Arc<PoolInner>) {
+    loop {
+        let mut state = inner.state.lock();
+        if let Some(work) = state.works.pop_front() {
+            drop(state);
+            work();
+            continue;
+        }
+        state.idle += 1;
+        drop(state);
+        let timed_out = inner
+            .wait_queue
+            .wait_timeout_until(WORKER_IDLE_TIMEOUT, || !inner.state.lock().works.is_empty());
+        let mut state = inner.state.lock();
+        state.idle -= 1;
+        if timed_out && state.works.is_empty() {
+            state.workers -= 1;
+            return;
+        }
+    }
+}
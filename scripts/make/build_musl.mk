@@ -11,7 +11,13 @@ install_dir := $(muslibc_dir)/$(install_dir_name)
 
 musl_dir := $(muslibc_dir)/musl-$(musl_version)
 inc_dir := $(install_dir)/include
-c_lib := $(install_dir)/lib/libc.a
+musl_lib := $(install_dir)/lib/libc.a
+c_lib := $(musl_lib)
+
+# The aio functions of musl are replaced by the ones of `ruxmusl`
+ifneq ($(filter aio,$(lib_feat)),)
+  c_lib := $(build_dir)/libc_aio.a
+endif
 
 libgcc :=
 
@@ -59,7 +65,13 @@ ifeq ($(wildcard $(install_dir)),)
 	cd $(build_dir) && $(MAKE) -j && $(MAKE) install
 endif
 
-$(c_lib): build_musl
+$(musl_lib): build_musl
+
+ifneq ($(c_lib),$(musl_lib))
+$(c_lib): $(musl_lib)
+	$(call run_cmd,cp,$(musl_lib) $@)
+	$(call run_cmd,$(AR),d $@ aio.o aio_suspend.o lio_listio.o)
+endif
 
 app-objs := main.o
 
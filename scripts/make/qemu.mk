@@ -31,7 +31,11 @@ qemu_args-aarch64 := \
 
 
 qemu_args-y := -m 2G -smp $(SMP) $(qemu_args-$(ARCH)) \
-  -append ";$(ARGS);$(ENVS)"
+  -append "$(KARGS);$(ARGS);$(ENVS)"
+
+ifneq ($(INITRD),)
+  qemu_args-y += -initrd $(INITRD)
+endif
 
 qemu_args-$(CONSOLE) += \
   -global virtio-mmio.force-legacy=false \
@@ -0,0 +1,54 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS
+ * OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A
+ * PARTICULAR PURPOSE. See the Mulan PSL v2 for more details.
+ */
+
+#ifndef _AIO_H
+#define _AIO_H
+
+#include <signal.h>
+#include <stddef.h>
+#include <sys/types.h>
+#include <time.h>
+
+struct aiocb {
+    int aio_fildes, aio_lio_opcode, aio_reqprio;
+    volatile void *aio_buf;
+    size_t aio_nbytes;
+    struct sigevent aio_sigevent;
+    void *__td;
+    int __lock[2];
+    volatile int __err;
+    ssize_t __ret;
+    off_t aio_offset;
+    void *__next, *__prev;
+    char __dummy4[32 - 2 * sizeof(void *)];
+};
+
+#define AIO_CANCELED    0
+#define AIO_NOTCANCELED 1
+#define AIO_ALLDONE     2
+
+#define LIO_READ  0
+#define LIO_WRITE 1
+#define LIO_NOP   2
+
+#define LIO_WAIT   0
+#define LIO_NOWAIT 1
+
+int aio_read(struct aiocb *);
+int aio_write(struct aiocb *);
+int aio_error(const struct aiocb *);
+ssize_t aio_return(struct aiocb *);
+int aio_cancel(int, struct aiocb *);
+int aio_suspend(const struct aiocb *const[], int, const struct timespec *);
+int aio_fsync(int, struct aiocb *);
+
+int lio_listio(int, struct aiocb *__restrict const[__restrict], int, struct sigevent *__restrict);
+
+#endif /* aio.h */
@@ -23,16 +23,24 @@
 #define O_DSYNC     010000
 #define O_SYNC      04010000
 #define O_RSYNC     04010000
-#define O_DIRECTORY 040000
-#define O_NOFOLLOW  0400000
 #define O_CLOEXEC   02000000
 
-#define O_ASYNC     020000
+#if defined(__aarch64__)
+#define O_DIRECTORY 040000
+#define O_NOFOLLOW  0100000
+#define O_DIRECT    0200000
+#define O_LARGEFILE 0400000
+#else
+#define O_DIRECTORY 0200000
+#define O_NOFOLLOW  0400000
 #define O_DIRECT    040000
 #define O_LARGEFILE 0100000
+#endif
+
+#define O_ASYNC     020000
 #define O_NOATIME   01000000
 #define O_PATH      010000000
-#define O_TMPFILE   020200000
+#define O_TMPFILE   (020000000 | O_DIRECTORY)
 #define O_NDELAY    O_NONBLOCK
 
 #define O_SEARCH   O_PATH
@@ -67,6 +75,20 @@
 
 #define FD_CLOEXEC      1
 #define F_DUPFD_CLOEXEC 1030
+#define F_SETPIPE_SZ    1031
+#define F_GETPIPE_SZ    1032
+#define F_ADD_SEALS     1033
+#define F_GET_SEALS     1034
+
+#define F_SEAL_SEAL         0x0001
+#define F_SEAL_SHRINK       0x0002
+#define F_SEAL_GROW         0x0004
+#define F_SEAL_WRITE        0x0008
+#define F_SEAL_FUTURE_WRITE 0x0010
+
+#define F_OFD_GETLK  36
+#define F_OFD_SETLK  37
+#define F_OFD_SETLKW 38
 
 #define F_RDLCK 0
 #define F_WRLCK 1
@@ -111,11 +133,24 @@
 #define AT_FDCWD      (-100)
 #define AT_EMPTY_PATH 0x1000
 #define AT_REMOVEDIR 0x200
+#define AT_SYMLINK_FOLLOW 0x400
+#define AT_NO_AUTOMOUNT   0x800
+#define AT_EACCESS        0x200
+
+#define AT_STATX_SYNC_AS_STAT 0x0000
+#define AT_STATX_FORCE_SYNC   0x2000
+#define AT_STATX_DONT_SYNC    0x4000
+#define AT_STATX_SYNC_TYPE    0x6000
 
 #define SYNC_FILE_RANGE_WAIT_BEFORE 1
 #define SYNC_FILE_RANGE_WRITE       2
 #define SYNC_FILE_RANGE_WAIT_AFTER  4
 
+#define SPLICE_F_MOVE     1
+#define SPLICE_F_NONBLOCK 2
+#define SPLICE_F_MORE     4
+#define SPLICE_F_GIFT     8
+
 #define loff_t off_t
 
 struct flock {
@@ -129,6 +164,8 @@ struct flock {
 int fcntl(int fd, int cmd, ... /* arg */);
 int posix_fadvise(int __fd, unsigned long __offset, unsigned long __len, int __advise);
 int sync_file_range(int, off_t, off_t, unsigned);
+ssize_t splice(int, off_t *, int, off_t *, size_t, unsigned);
+ssize_t tee(int, int, size_t, unsigned);
 
 int open(const char *filename, int flags, ...);
 int openat(int, const char *, int, ...);
@@ -38,6 +38,7 @@
 #define TZNAME_MAX 6
 
 #define PATH_MAX  4096
+#define PIPE_BUF  4096
 #define SSIZE_MAX LONG_MAX
 #define CHAR_MAX  127
 
@@ -0,0 +1,215 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS
+ * OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A
+ * PARTICULAR PURPOSE. See the Mulan PSL v2 for more details.
+ */
+
+#ifndef LINUX_IO_URING_H
+#define LINUX_IO_URING_H
+
+#include <stdint.h>
+
+/* Submission queue entry */
+struct io_uring_sqe {
+    uint8_t opcode;
+    uint8_t flags;
+    uint16_t ioprio;
+    int32_t fd;
+    union {
+        uint64_t off;
+        uint64_t addr2;
+    };
+    union {
+        uint64_t addr;
+        uint64_t splice_off_in;
+    };
+    uint32_t len;
+    union {
+        int rw_flags;
+        uint32_t fsync_flags;
+        uint16_t poll_events;
+        uint32_t poll32_events;
+        uint32_t sync_range_flags;
+        uint32_t msg_flags;
+        uint32_t timeout_flags;
+        uint32_t accept_flags;
+        uint32_t cancel_flags;
+        uint32_t open_flags;
+        uint32_t statx_flags;
+        uint32_t fadvise_advice;
+        uint32_t splice_flags;
+    };
+    uint64_t user_data;
+    union {
+        uint16_t buf_index;
+        uint16_t buf_group;
+    } __attribute__((packed));
+    uint16_t personality;
+    union {
+        int32_t splice_fd_in;
+        uint32_t file_index;
+    };
+    uint64_t __pad2[2];
+};
+
+#define IOSQE_FIXED_FILE       (1U << 0)
+#define IOSQE_IO_DRAIN         (1U << 1)
+#define IOSQE_IO_LINK          (1U << 2)
+#define IOSQE_IO_HARDLINK      (1U << 3)
+#define IOSQE_ASYNC            (1U << 4)
+#define IOSQE_BUFFER_SELECT    (1U << 5)
+#define IOSQE_CQE_SKIP_SUCCESS (1U << 6)
+
+/* io_uring_setup() flags */
+#define IORING_SETUP_IOPOLL     (1U << 0)
+#define IORING_SETUP_SQPOLL     (1U << 1)
+#define IORING_SETUP_SQ_AFF     (1U << 2)
+#define IORING_SETUP_CQSIZE     (1U << 3)
+#define IORING_SETUP_CLAMP      (1U << 4)
+#define IORING_SETUP_ATTACH_WQ  (1U << 5)
+#define IORING_SETUP_R_DISABLED (1U << 6)
+#define IORING_SETUP_SUBMIT_ALL (1U << 7)
+
+enum io_uring_op {
+    IORING_OP_NOP,
+    IORING_OP_READV,
+    IORING_OP_WRITEV,
+    IORING_OP_FSYNC,
+    IORING_OP_READ_FIXED,
+    IORING_OP_WRITE_FIXED,
+    IORING_OP_POLL_ADD,
+    IORING_OP_POLL_REMOVE,
+    IORING_OP_SYNC_FILE_RANGE,
+    IORING_OP_SENDMSG,
+    IORING_OP_RECVMSG,
+    IORING_OP_TIMEOUT,
+    IORING_OP_TIMEOUT_REMOVE,
+    IORING_OP_ACCEPT,
+    IORING_OP_ASYNC_CANCEL,
+    IORING_OP_LINK_TIMEOUT,
+    IORING_OP_CONNECT,
+    IORING_OP_FALLOCATE,
+    IORING_OP_OPENAT,
+    IORING_OP_CLOSE,
+    IORING_OP_FILES_UPDATE,
+    IORING_OP_STATX,
+    IORING_OP_READ,
+    IORING_OP_WRITE,
+    IORING_OP_FADVISE,
+    IORING_OP_MADVISE,
+    IORING_OP_SEND,
+    IORING_OP_RECV,
+
+    IORING_OP_LAST,
+};
+
+/* sqe->fsync_flags */
+#define IORING_FSYNC_DATASYNC (1U << 0)
+
+/* sqe->timeout_flags */
+#define IORING_TIMEOUT_ABS (1U << 0)
+
+/* Completion queue entry */
+struct io_uring_cqe {
+    uint64_t user_data;
+    int32_t res;
+    uint32_t flags;
+};
+
+/* Magic offsets for the application to mmap the data it needs */
+#define IORING_OFF_SQ_RING 0ULL
+#define IORING_OFF_CQ_RING 0x8000000ULL
+#define IORING_OFF_SQES    0x10000000ULL
+
+/* Filled with the offset for mmap(2) */
+struct io_sqring_offsets {
+    uint32_t head;
+    uint32_t tail;
+    uint32_t ring_mask;
+    uint32_t ring_entries;
+    uint32_t flags;
+    uint32_t dropped;
+    uint32_t array;
+    uint32_t resv1;
+    uint64_t user_addr;
+};
+
+/* sq_ring->flags */
+#define IORING_SQ_NEED_WAKEUP (1U << 0)
+#define IORING_SQ_CQ_OVERFLOW (1U << 1)
+
+struct io_cqring_offsets {
+    uint32_t head;
+    uint32_t tail;
+    uint32_t ring_mask;
+    uint32_t ring_entries;
+    uint32_t overflow;
+    uint32_t cqes;
+    uint32_t flags;
+    uint32_t resv1;
+    uint64_t user_addr;
+};
+
+/* cq_ring->flags */
+#define IORING_CQ_EVENTFD_DISABLED (1U << 0)
+
+/* io_uring_enter(2) flags */
+#define IORING_ENTER_GETEVENTS (1U << 0)
+#define IORING_ENTER_SQ_WAKEUP (1U << 1)
+#define IORING_ENTER_SQ_WAIT   (1U << 2)
+#define IORING_ENTER_EXT_ARG   (1U << 3)
+
+/* Passed in for io_uring_setup(2). Copied back with updated info on success */
+struct io_uring_params {
+    uint32_t sq_entries;
+    uint32_t cq_entries;
+    uint32_t flags;
+    uint32_t sq_thread_cpu;
+    uint32_t sq_thread_idle;
+    uint32_t features;
+    uint32_t wq_fd;
+    uint32_t resv[3];
+    struct io_sqring_offsets sq_off;
+    struct io_cqring_offsets cq_off;
+};
+
+/* io_uring_params->features flags */
+#define IORING_FEAT_SINGLE_MMAP     (1U << 0)
+#define IORING_FEAT_NODROP          (1U << 1)
+#define IORING_FEAT_SUBMIT_STABLE   (1U << 2)
+#define IORING_FEAT_RW_CUR_POS      (1U << 3)
+#define IORING_FEAT_CUR_PERSONALITY (1U << 4)
+
+/* io_uring_register(2) opcodes and arguments */
+#define IORING_REGISTER_BUFFERS       0
+#define IORING_UNREGISTER_BUFFERS     1
+#define IORING_REGISTER_FILES         2
+#define IORING_UNREGISTER_FILES       3
+#define IORING_REGISTER_EVENTFD       4
+#define IORING_UNREGISTER_EVENTFD     5
+#define IORING_REGISTER_FILES_UPDATE  6
+#define IORING_REGISTER_EVENTFD_ASYNC 7
+#define IORING_REGISTER_PROBE         8
+
+#define IO_URING_OP_SUPPORTED (1U << 0)
+
+struct io_uring_probe_op {
+    uint8_t op;
+    uint8_t resv;
+    uint16_t flags; /* IO_URING_OP_* flags */
+    uint32_t resv2;
+};
+
+struct io_uring_probe {
+    uint8_t last_op; /* last opcode supported */
+    uint8_t ops_len; /* length of ops[] array below */
+    uint16_t resv;
+    uint32_t resv2[3];
+    struct io_uring_probe_op ops[];
+};
+
+#endif /* linux/io_uring.h */
@@ -0,0 +1,57 @@
+/* Copyright (c) [2023] [Syswonder Community]
+ *   [Ruxos] is licensed under Mulan PSL v2.
+ *   You can use this software according to the terms and conditions of the Mulan PSL v2.
+ *   You may obtain a copy of Mulan PSL v2 at:
+ *               http://license.coscl.org.cn/MulanPSL2
+ *   THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY KIND, EITHER EXPRESS
+ * OR IMPLIED, INCLUDING BUT NOT LIMITED TO NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A
+ * PARTICULAR PURPOSE. See the Mulan PSL v2 for more details.
+ */
+
+#ifndef _SYS_SEM_H
+#define _SYS_SEM_H
+
+#include <stddef.h>
+#include <sys/ipc.h>
+#include <time.h>
+
+#define SEM_UNDO 0x1000
+
+#define GETPID  11
+#define GETVAL  12
+#define GETALL  13
+#define GETNCNT 14
+#define GETZCNT 15
+#define SETVAL  16
+#define SETALL  17
+
+#define SEM_STAT     18
+#define SEM_INFO     19
+#define SEM_STAT_ANY 20
+
+struct semid_ds {
+    struct ipc_perm sem_perm;
+    time_t sem_otime;
+    time_t sem_ctime;
+    unsigned short sem_nsems;
+    char __sem_nsems_pad[sizeof(long) - sizeof(short)];
+    long __unused3;
+    long __unused4;
+};
+
+struct seminfo {
+    int semmap, semmni, semmns, semmnu, semmsl, semopm, semume, semusz, semvmx, semaem;
+};
+
+struct sembuf {
+    unsigned short sem_num;
+    short sem_op;
+    short sem_flg;
+};
+
+int semctl(int, int, int, ...);
+int semget(key_t, int, int);
+int semop(int, struct sembuf *, size_t);
+int semtimedop(int, struct sembuf *, size_t, const struct timespec *);
+
+#endif // _SYS_SEM_H
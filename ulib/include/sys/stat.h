@@ -147,6 +147,54 @@ struct kstat {
 #define S_IRWXO 0007
 #endif
 
+#define UTIME_NOW  0x3fffffff
+#define UTIME_OMIT 0x3ffffffe
+
+#define STATX_TYPE        0x1U
+#define STATX_MODE        0x2U
+#define STATX_NLINK       0x4U
+#define STATX_UID         0x8U
+#define STATX_GID         0x10U
+#define STATX_ATIME       0x20U
+#define STATX_MTIME       0x40U
+#define STATX_CTIME       0x80U
+#define STATX_INO         0x100U
+#define STATX_SIZE        0x200U
+#define STATX_BLOCKS      0x400U
+#define STATX_BASIC_STATS 0x7ffU
+#define STATX_BTIME       0x800U
+#define STATX_ALL         0xfffU
+
+struct statx_timestamp {
+    int64_t tv_sec;
+    uint32_t tv_nsec;
+    int32_t __pad;
+};
+
+struct statx {
+    uint32_t stx_mask;
+    uint32_t stx_blksize;
+    uint64_t stx_attributes;
+    uint32_t stx_nlink;
+    uint32_t stx_uid;
+    uint32_t stx_gid;
+    uint16_t stx_mode;
+    uint16_t __pad0[1];
+    uint64_t stx_ino;
+    uint64_t stx_size;
+    uint64_t stx_blocks;
+    uint64_t stx_attributes_mask;
+    struct statx_timestamp stx_atime;
+    struct statx_timestamp stx_btime;
+    struct statx_timestamp stx_ctime;
+    struct statx_timestamp stx_mtime;
+    uint32_t stx_rdev_major;
+    uint32_t stx_rdev_minor;
+    uint32_t stx_dev_major;
+    uint32_t stx_dev_minor;
+    uint64_t __pad1[14];
+};
+
 int stat(const char *path, struct stat *buf);
 int fstat(int fd, struct stat *buf);
 int lstat(const char *path, struct stat *buf);
@@ -156,5 +204,9 @@ int chmod(const char *file, mode_t mode);
 int mkdir(const char *pathname, mode_t mode);
 mode_t umask(mode_t mask);
 int fstatat(int, const char *__restrict, struct stat *__restrict, int);
+int fchmodat(int, const char *, mode_t, int);
+int utimensat(int, const char *, const struct timespec[2], int);
+int futimens(int, const struct timespec[2]);
+int statx(int, const char *__restrict, int, unsigned, struct statx *__restrict);
 
 #endif
@@ -16,6 +16,10 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
             SyscallId::GETCWD => {
                 ruxos_posix_api::sys_getcwd(args[0] as *mut core::ffi::c_char, args[1]) as _
             }
+            #[cfg(feature = "eventfd")]
+            SyscallId::EVENTFD2 => {
+                ruxos_posix_api::sys_eventfd2(args[0] as _, args[1] as c_int) as _
+            }
             #[cfg(feature = "epoll")]
             SyscallId::EPOLL_CREATE1 => ruxos_posix_api::sys_epoll_create1(args[0] as c_int) as _,
             #[cfg(feature = "epoll")]
@@ -44,9 +48,23 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
             SyscallId::FCNTL => {
                 ruxos_posix_api::sys_fcntl(args[0] as c_int, args[1] as c_int, args[2]) as _
             }
+            #[cfg(feature = "inotify")]
+            SyscallId::INOTIFY_INIT1 => ruxos_posix_api::sys_inotify_init1(args[0] as c_int) as _,
+            #[cfg(feature = "inotify")]
+            SyscallId::INOTIFY_ADD_WATCH => ruxos_posix_api::sys_inotify_add_watch(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as _,
+            ) as _,
+            #[cfg(feature = "inotify")]
+            SyscallId::INOTIFY_RM_WATCH => {
+                ruxos_posix_api::sys_inotify_rm_watch(args[0] as c_int, args[1] as c_int) as _
+            }
             #[cfg(feature = "fd")]
             SyscallId::IOCTL => ruxos_posix_api::sys_ioctl(args[0] as c_int, args[1], args[2]) as _,
             #[cfg(feature = "fs")]
+            SyscallId::FLOCK => ruxos_posix_api::sys_flock(args[0] as c_int, args[1] as c_int) as _,
+            #[cfg(feature = "fs")]
             SyscallId::MKNODAT => ruxos_posix_api::sys_mknodat(
                 args[0] as c_int,
                 args[1] as *const core::ffi::c_char,
@@ -66,6 +84,17 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[2] as c_int,
             ) as _,
             #[cfg(feature = "fs")]
+            SyscallId::FCHMOD => {
+                ruxos_posix_api::sys_fchmod(args[0] as c_int, args[1] as ctypes::mode_t) as _
+            }
+            #[cfg(feature = "fs")]
+            SyscallId::FCHMODAT => ruxos_posix_api::sys_fchmodat(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as ctypes::mode_t,
+                args[3] as c_int,
+            ) as _,
+            #[cfg(feature = "fs")]
             SyscallId::FCHOWNAT => ruxos_posix_api::sys_fchownat(
                 args[0] as c_int,
                 args[1] as *const core::ffi::c_char,
@@ -74,6 +103,20 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[4] as c_int,
             ) as _,
             #[cfg(feature = "fs")]
+            SyscallId::SYMLINKAT => ruxos_posix_api::sys_symlinkat(
+                args[0] as *const core::ffi::c_char,
+                args[1] as c_int,
+                args[2] as *const core::ffi::c_char,
+            ) as _,
+            #[cfg(feature = "fs")]
+            SyscallId::LINKAT => ruxos_posix_api::sys_linkat(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as c_int,
+                args[3] as *const core::ffi::c_char,
+                args[4] as c_int,
+            ) as _,
+            #[cfg(feature = "fs")]
             SyscallId::RENAMEAT => ruxos_posix_api::sys_renameat(
                 args[0] as c_int,
                 args[1] as *const core::ffi::c_char,
@@ -81,6 +124,28 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[3] as *const core::ffi::c_char,
             ) as _,
             #[cfg(feature = "fs")]
+            SyscallId::UMOUNT2 => {
+                ruxos_posix_api::sys_umount2(args[0] as *const core::ffi::c_char, args[1] as c_int)
+                    as _
+            }
+            #[cfg(feature = "fs")]
+            SyscallId::MOUNT => ruxos_posix_api::sys_mount(
+                args[0] as *const core::ffi::c_char,
+                args[1] as *const core::ffi::c_char,
+                args[2] as *const core::ffi::c_char,
+                args[3] as core::ffi::c_ulong,
+                args[4] as *const core::ffi::c_void,
+            ) as _,
+            #[cfg(feature = "fs")]
+            SyscallId::STATFS => ruxos_posix_api::sys_statfs(
+                args[0] as *const core::ffi::c_char,
+                args[1] as *mut ctypes::statfs,
+            ) as _,
+            #[cfg(feature = "fs")]
+            SyscallId::FSTATFS => {
+                ruxos_posix_api::sys_fstatfs(args[0] as c_int, args[1] as *mut ctypes::statfs) as _
+            }
+            #[cfg(feature = "fs")]
             SyscallId::FACCESSAT => ruxos_posix_api::sys_faccessat(
                 args[0] as c_int,
                 args[1] as *const c_char,
@@ -158,6 +223,13 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[2] as c_int,
                 args[3] as ctypes::off_t,
             ) as _,
+            #[cfg(feature = "fs")]
+            SyscallId::SENDFILE => ruxos_posix_api::sys_sendfile(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as *mut ctypes::off_t,
+                args[3] as ctypes::size_t,
+            ) as _,
             #[cfg(feature = "select")]
             SyscallId::PSELECT6 => ruxos_posix_api::sys_pselect6(
                 args[0] as c_int,
@@ -175,6 +247,29 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[3] as *const ctypes::sigset_t,
                 args[4] as ctypes::size_t,
             ) as _,
+            #[cfg(feature = "signalfd")]
+            SyscallId::SIGNALFD4 => ruxos_posix_api::sys_signalfd4(
+                args[0] as c_int,
+                args[1] as *const ctypes::sigset_t,
+                args[2] as ctypes::size_t,
+                args[3] as c_int,
+            ) as _,
+            #[cfg(all(feature = "fs", feature = "pipe"))]
+            SyscallId::SPLICE => ruxos_posix_api::sys_splice(
+                args[0] as c_int,
+                args[1] as *mut ctypes::off_t,
+                args[2] as c_int,
+                args[3] as *mut ctypes::off_t,
+                args[4] as ctypes::size_t,
+                args[5] as _,
+            ) as _,
+            #[cfg(all(feature = "fs", feature = "pipe"))]
+            SyscallId::TEE => ruxos_posix_api::sys_tee(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as ctypes::size_t,
+                args[3] as _,
+            ) as _,
             #[cfg(feature = "fs")]
             SyscallId::READLINKAT => ruxos_posix_api::sys_readlinkat(
                 args[0] as c_int,
@@ -201,6 +296,30 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
             SyscallId::GETTID => ruxos_posix_api::sys_gettid() as _,
             #[cfg(feature = "fs")]
             SyscallId::FDATASYNC => ruxos_posix_api::sys_fdatasync(args[0] as c_int) as _,
+            #[cfg(feature = "timerfd")]
+            SyscallId::TIMERFD_CREATE => {
+                ruxos_posix_api::sys_timerfd_create(args[0] as ctypes::clockid_t, args[1] as c_int)
+                    as _
+            }
+            #[cfg(feature = "timerfd")]
+            SyscallId::TIMERFD_SETTIME => ruxos_posix_api::sys_timerfd_settime(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as *const ctypes::itimerspec,
+                args[3] as *mut ctypes::itimerspec,
+            ) as _,
+            #[cfg(feature = "timerfd")]
+            SyscallId::TIMERFD_GETTIME => ruxos_posix_api::sys_timerfd_gettime(
+                args[0] as c_int,
+                args[1] as *mut ctypes::itimerspec,
+            ) as _,
+            #[cfg(feature = "fs")]
+            SyscallId::UTIMENSAT => ruxos_posix_api::sys_utimensat(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as *const ctypes::timespec,
+                args[3] as c_int,
+            ) as _,
             SyscallId::CAP_GET => ruxos_posix_api::sys_cap_get(args[0], args[1]) as _,
             #[allow(unreachable_code)]
             #[cfg(not(feature = "multitask"))]
@@ -229,6 +348,30 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
             ) as _,
             #[cfg(feature = "signal")]
             SyscallId::SETITIMER => ruxos_posix_api::sys_setitimer(args[0] as _, args[1] as _) as _,
+            #[cfg(feature = "timer")]
+            SyscallId::TIMER_CREATE => ruxos_posix_api::sys_timer_create(
+                args[0] as ctypes::clockid_t,
+                args[1] as *const ctypes::sigevent,
+                args[2] as *mut c_int,
+            ) as _,
+            #[cfg(feature = "timer")]
+            SyscallId::TIMER_GETTIME => ruxos_posix_api::sys_timer_gettime(
+                args[0] as c_int,
+                args[1] as *mut ctypes::itimerspec,
+            ) as _,
+            #[cfg(feature = "timer")]
+            SyscallId::TIMER_GETOVERRUN => {
+                ruxos_posix_api::sys_timer_getoverrun(args[0] as c_int) as _
+            }
+            #[cfg(feature = "timer")]
+            SyscallId::TIMER_SETTIME => ruxos_posix_api::sys_timer_settime(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as *const ctypes::itimerspec,
+                args[3] as *mut ctypes::itimerspec,
+            ) as _,
+            #[cfg(feature = "timer")]
+            SyscallId::TIMER_DELETE => ruxos_posix_api::sys_timer_delete(args[0] as c_int) as _,
             SyscallId::CLOCK_SETTIME => ruxos_posix_api::sys_clock_settime(
                 args[0] as ctypes::clockid_t,
                 args[1] as *const ctypes::timespec,
@@ -271,9 +414,16 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[2] as *mut usize,
                 args[3],
             ) as _,
+            #[cfg(all(feature = "signal", feature = "multitask", feature = "irq"))]
+            SyscallId::RT_SIGTIMEDWAIT => ruxos_posix_api::sys_rt_sigtimedwait(
+                args[0] as *const ctypes::sigset_t,
+                args[1] as *mut ctypes::siginfo_t,
+                args[2] as *const ctypes::timespec,
+                args[3] as ctypes::size_t,
+            ) as _,
             SyscallId::SETGID => ruxos_posix_api::sys_setgid(args[0] as gid_t) as _,
             SyscallId::SETUID => ruxos_posix_api::sys_setuid(args[0] as uid_t) as _,
-            SyscallId::TIMES => ruxos_posix_api::sys_times(args[0] as *mut usize) as _,
+            SyscallId::TIMES => ruxos_posix_api::sys_times(args[0] as *mut ctypes::tms) as _,
             SyscallId::SETPGID => {
                 ruxos_posix_api::sys_setpgid(args[0] as pid_t, args[1] as pid_t) as _
             }
@@ -296,6 +446,114 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
             SyscallId::SYSINFO => {
                 ruxos_posix_api::sys_sysinfo(args[0] as *mut ctypes::sysinfo) as _
             }
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_OPEN => ruxos_posix_api::sys_mq_open(
+                args[0] as *const c_char,
+                args[1] as c_int,
+                args[2] as ctypes::mode_t,
+                args[3] as *const ctypes::mq_attr,
+            ) as _,
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_UNLINK => ruxos_posix_api::sys_mq_unlink(args[0] as *const c_char) as _,
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_TIMEDSEND => ruxos_posix_api::sys_mq_timedsend(
+                args[0] as c_int,
+                args[1] as *const c_char,
+                args[2] as ctypes::size_t,
+                args[3] as core::ffi::c_uint,
+                args[4] as *const ctypes::timespec,
+            ) as _,
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_TIMEDRECEIVE => ruxos_posix_api::sys_mq_timedreceive(
+                args[0] as c_int,
+                args[1] as *mut c_char,
+                args[2] as ctypes::size_t,
+                args[3] as *mut core::ffi::c_uint,
+                args[4] as *const ctypes::timespec,
+            ) as _,
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_NOTIFY => {
+                ruxos_posix_api::sys_mq_notify(args[0] as c_int, args[1] as *const ctypes::sigevent)
+                    as _
+            }
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_GETSETATTR => ruxos_posix_api::sys_mq_getsetattr(
+                args[0] as c_int,
+                args[1] as *const ctypes::mq_attr,
+                args[2] as *mut ctypes::mq_attr,
+            ) as _,
+            #[cfg(feature = "sysvipc")]
+            SyscallId::MSGGET => {
+                ruxos_posix_api::sys_msgget(args[0] as c_int, args[1] as c_int) as _
+            }
+            #[cfg(feature = "sysvipc")]
+            SyscallId::MSGCTL => ruxos_posix_api::sys_msgctl(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as *mut ctypes::msqid_ds,
+            ) as _,
+            #[cfg(feature = "sysvipc")]
+            SyscallId::MSGRCV => ruxos_posix_api::sys_msgrcv(
+                args[0] as c_int,
+                args[1] as *mut core::ffi::c_void,
+                args[2] as ctypes::size_t,
+                args[3] as core::ffi::c_long,
+                args[4] as c_int,
+            ) as _,
+            #[cfg(feature = "sysvipc")]
+            SyscallId::MSGSND => ruxos_posix_api::sys_msgsnd(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_void,
+                args[2] as ctypes::size_t,
+                args[3] as c_int,
+            ) as _,
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SEMGET => {
+                ruxos_posix_api::sys_semget(args[0] as c_int, args[1] as c_int, args[2] as c_int)
+                    as _
+            }
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SEMCTL => ruxos_posix_api::sys_semctl(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as c_int,
+                args[3],
+            ) as _,
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SEMTIMEDOP => ruxos_posix_api::sys_semtimedop(
+                args[0] as c_int,
+                args[1] as *mut ctypes::sembuf,
+                args[2] as ctypes::size_t,
+                args[3] as *const ctypes::timespec,
+            ) as _,
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SEMOP => ruxos_posix_api::sys_semop(
+                args[0] as c_int,
+                args[1] as *mut ctypes::sembuf,
+                args[2] as ctypes::size_t,
+            ) as _,
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SHMGET => ruxos_posix_api::sys_shmget(
+                args[0] as c_int,
+                args[1] as ctypes::size_t,
+                args[2] as c_int,
+            ) as _,
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SHMCTL => ruxos_posix_api::sys_shmctl(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as *mut ctypes::shmid_ds,
+            ) as _,
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SHMAT => ruxos_posix_api::sys_shmat(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_void,
+                args[2] as c_int,
+            ) as _,
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SHMDT => {
+                ruxos_posix_api::sys_shmdt(args[0] as *const core::ffi::c_void) as _
+            }
             #[cfg(feature = "net")]
             SyscallId::SOCKET => {
                 ruxos_posix_api::sys_socket(args[0] as c_int, args[1] as c_int, args[2] as c_int)
@@ -452,11 +710,61 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[2] as *const ctypes::rlimit,
                 args[3] as *mut ctypes::rlimit,
             ) as _,
+            #[cfg(feature = "fs")]
+            SyscallId::RENAMEAT2 => ruxos_posix_api::sys_renameat2(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as c_int,
+                args[3] as *const core::ffi::c_char,
+                args[4] as core::ffi::c_uint,
+            ) as _,
             SyscallId::GETRANDOM => ruxos_posix_api::sys_getrandom(
                 args[0] as *mut core::ffi::c_void,
                 args[1] as ctypes::size_t,
                 args[2] as c_int,
             ) as _,
+            #[cfg(feature = "fs")]
+            SyscallId::MEMFD_CREATE => {
+                ruxos_posix_api::sys_memfd_create(args[0] as *const c_char, args[1] as _) as _
+            }
+            #[cfg(feature = "fs")]
+            SyscallId::COPY_FILE_RANGE => ruxos_posix_api::sys_copy_file_range(
+                args[0] as c_int,
+                args[1] as *mut ctypes::off_t,
+                args[2] as c_int,
+                args[3] as *mut ctypes::off_t,
+                args[4] as ctypes::size_t,
+                args[5] as _,
+            ) as _,
+            #[cfg(feature = "fs")]
+            SyscallId::STATX => ruxos_posix_api::sys_statx(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as c_int,
+                args[3] as core::ffi::c_uint,
+                args[4] as *mut ctypes::statx,
+            ) as _,
+            #[cfg(feature = "io_uring")]
+            SyscallId::IO_URING_SETUP => ruxos_posix_api::sys_io_uring_setup(
+                args[0] as core::ffi::c_uint,
+                args[1] as *mut ctypes::io_uring_params,
+            ) as _,
+            #[cfg(feature = "io_uring")]
+            SyscallId::IO_URING_ENTER => ruxos_posix_api::sys_io_uring_enter(
+                args[0] as core::ffi::c_uint,
+                args[1] as core::ffi::c_uint,
+                args[2] as core::ffi::c_uint,
+                args[3] as core::ffi::c_uint,
+                args[4] as *const core::ffi::c_void,
+                args[5] as ctypes::size_t,
+            ) as _,
+            #[cfg(feature = "io_uring")]
+            SyscallId::IO_URING_REGISTER => ruxos_posix_api::sys_io_uring_register(
+                args[0] as core::ffi::c_uint,
+                args[1] as core::ffi::c_uint,
+                args[2] as *mut core::ffi::c_void,
+                args[3] as core::ffi::c_uint,
+            ) as _,
         }
     }
 }
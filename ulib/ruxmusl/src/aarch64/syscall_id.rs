@@ -9,6 +9,8 @@ pub enum SyscallId {
     INVALID = 999,
     #[cfg(feature = "fs")]
     GETCWD = 17,
+    #[cfg(feature = "eventfd")]
+    EVENTFD2 = 19,
     #[cfg(feature = "epoll")]
     EPOLL_CREATE1 = 20,
     #[cfg(feature = "epoll")]
@@ -21,21 +23,45 @@ pub enum SyscallId {
     DUP3 = 24,
     #[cfg(feature = "fd")]
     FCNTL = 25,
+    #[cfg(feature = "inotify")]
+    INOTIFY_INIT1 = 26,
+    #[cfg(feature = "inotify")]
+    INOTIFY_ADD_WATCH = 27,
+    #[cfg(feature = "inotify")]
+    INOTIFY_RM_WATCH = 28,
     #[cfg(feature = "fd")]
     IOCTL = 29,
     #[cfg(feature = "fs")]
+    FLOCK = 32,
+    #[cfg(feature = "fs")]
     MKNODAT = 33,
     #[cfg(feature = "fs")]
     MKDIRAT = 34,
     #[cfg(feature = "fs")]
     UNLINKAT = 35,
     #[cfg(feature = "fs")]
+    SYMLINKAT = 36,
+    #[cfg(feature = "fs")]
+    LINKAT = 37,
+    #[cfg(feature = "fs")]
     RENAMEAT = 38,
     #[cfg(feature = "fs")]
+    UMOUNT2 = 39,
+    #[cfg(feature = "fs")]
+    MOUNT = 40,
+    #[cfg(feature = "fs")]
+    STATFS = 43,
+    #[cfg(feature = "fs")]
+    FSTATFS = 44,
+    #[cfg(feature = "fs")]
     FACCESSAT = 48,
     #[cfg(feature = "fs")]
     CHDIR = 49,
     #[cfg(feature = "fs")]
+    FCHMOD = 52,
+    #[cfg(feature = "fs")]
+    FCHMODAT = 53,
+    #[cfg(feature = "fs")]
     FCHOWNAT = 54,
     #[cfg(feature = "fs")]
     OPENAT = 56,
@@ -59,10 +85,18 @@ pub enum SyscallId {
     PWRITE64 = 68,
     #[cfg(feature = "fs")]
     PREADV = 69,
+    #[cfg(feature = "fs")]
+    SENDFILE = 71,
     #[cfg(feature = "select")]
     PSELECT6 = 72,
     #[cfg(feature = "poll")]
     PPOLL = 73,
+    #[cfg(feature = "signalfd")]
+    SIGNALFD4 = 74,
+    #[cfg(all(feature = "fs", feature = "pipe"))]
+    SPLICE = 76,
+    #[cfg(all(feature = "fs", feature = "pipe"))]
+    TEE = 77,
     #[cfg(feature = "fs")]
     READLINKAT = 78,
     #[cfg(feature = "fs")]
@@ -73,6 +107,14 @@ pub enum SyscallId {
     FSYNC = 82,
     #[cfg(feature = "fs")]
     FDATASYNC = 83,
+    #[cfg(feature = "timerfd")]
+    TIMERFD_CREATE = 85,
+    #[cfg(feature = "timerfd")]
+    TIMERFD_SETTIME = 86,
+    #[cfg(feature = "timerfd")]
+    TIMERFD_GETTIME = 87,
+    #[cfg(feature = "fs")]
+    UTIMENSAT = 88,
     CAP_GET = 90,
     EXIT = 93,
     #[cfg(feature = "multitask")]
@@ -84,6 +126,16 @@ pub enum SyscallId {
     NANO_SLEEP = 101,
     #[cfg(feature = "signal")]
     SETITIMER = 103,
+    #[cfg(feature = "timer")]
+    TIMER_CREATE = 107,
+    #[cfg(feature = "timer")]
+    TIMER_GETTIME = 108,
+    #[cfg(feature = "timer")]
+    TIMER_GETOVERRUN = 109,
+    #[cfg(feature = "timer")]
+    TIMER_SETTIME = 110,
+    #[cfg(feature = "timer")]
+    TIMER_DELETE = 111,
     CLOCK_SETTIME = 112,
     CLOCK_GETTIME = 113,
     CLOCK_GETRES = 114,
@@ -99,6 +151,8 @@ pub enum SyscallId {
     RT_SIGACTION = 134,
     #[cfg(feature = "signal")]
     RT_SIGPROCMASK = 135,
+    #[cfg(all(feature = "signal", feature = "multitask", feature = "irq"))]
+    RT_SIGTIMEDWAIT = 137,
     SETGID = 144,
     SETUID = 146,
     TIMES = 153,
@@ -118,6 +172,42 @@ pub enum SyscallId {
     GETEGID = 177,
     GETTID = 178,
     SYSINFO = 179,
+    #[cfg(feature = "mqueue")]
+    MQ_OPEN = 180,
+    #[cfg(feature = "mqueue")]
+    MQ_UNLINK = 181,
+    #[cfg(feature = "mqueue")]
+    MQ_TIMEDSEND = 182,
+    #[cfg(feature = "mqueue")]
+    MQ_TIMEDRECEIVE = 183,
+    #[cfg(feature = "mqueue")]
+    MQ_NOTIFY = 184,
+    #[cfg(feature = "mqueue")]
+    MQ_GETSETATTR = 185,
+    #[cfg(feature = "sysvipc")]
+    MSGGET = 186,
+    #[cfg(feature = "sysvipc")]
+    MSGCTL = 187,
+    #[cfg(feature = "sysvipc")]
+    MSGRCV = 188,
+    #[cfg(feature = "sysvipc")]
+    MSGSND = 189,
+    #[cfg(feature = "sysvipc")]
+    SEMGET = 190,
+    #[cfg(feature = "sysvipc")]
+    SEMCTL = 191,
+    #[cfg(feature = "sysvipc")]
+    SEMTIMEDOP = 192,
+    #[cfg(feature = "sysvipc")]
+    SEMOP = 193,
+    #[cfg(feature = "sysvipc")]
+    SHMGET = 194,
+    #[cfg(feature = "sysvipc")]
+    SHMCTL = 195,
+    #[cfg(feature = "sysvipc")]
+    SHMAT = 196,
+    #[cfg(feature = "sysvipc")]
+    SHMDT = 197,
     #[cfg(feature = "net")]
     SOCKET = 198,
     #[cfg(feature = "net")]
@@ -165,5 +255,19 @@ pub enum SyscallId {
     #[cfg(feature = "multitask")]
     WAIT4 = 260,
     PRLIMIT64 = 261,
+    #[cfg(feature = "fs")]
+    RENAMEAT2 = 276,
     GETRANDOM = 278,
+    #[cfg(feature = "fs")]
+    MEMFD_CREATE = 279,
+    #[cfg(feature = "fs")]
+    COPY_FILE_RANGE = 285,
+    #[cfg(feature = "fs")]
+    STATX = 291,
+    #[cfg(feature = "io_uring")]
+    IO_URING_SETUP = 425,
+    #[cfg(feature = "io_uring")]
+    IO_URING_ENTER = 426,
+    #[cfg(feature = "io_uring")]
+    IO_URING_REGISTER = 427,
 }
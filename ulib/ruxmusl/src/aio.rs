@@ -0,0 +1,72 @@
+//! POSIX asynchronous I/O functions
+//!
+//! musl runs asynchronous I/O by threads of its own, which never reach a
+//! syscall, so these functions replace the ones of musl (see
+//! `scripts/make/build_musl.mk`) to run the requests by the worker pools of
+//! Ruxos instead.
+
+use core::ffi::c_int;
+use ruxos_posix_api::ctypes;
+
+extern "C" {
+    fn __errno_location() -> *mut c_int;
+}
+
+/// Converts the result of a syscall of `ruxos_posix_api`, a negated error
+/// number on failure, to the one of a libc function setting `errno`.
+unsafe fn libc_ret(ret: isize) -> isize {
+    if ret < 0 {
+        *__errno_location() = -ret as c_int;
+        return -1;
+    }
+    ret
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aio_read(aiocbp: *mut ctypes::aiocb) -> c_int {
+    libc_ret(ruxos_posix_api::sys_aio_read(aiocbp) as _) as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aio_write(aiocbp: *mut ctypes::aiocb) -> c_int {
+    libc_ret(ruxos_posix_api::sys_aio_write(aiocbp) as _) as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aio_fsync(op: c_int, aiocbp: *mut ctypes::aiocb) -> c_int {
+    libc_ret(ruxos_posix_api::sys_aio_fsync(op, aiocbp) as _) as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aio_error(aiocbp: *const ctypes::aiocb) -> c_int {
+    libc_ret(ruxos_posix_api::sys_aio_error(aiocbp) as _) as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aio_return(aiocbp: *mut ctypes::aiocb) -> ctypes::ssize_t {
+    libc_ret(ruxos_posix_api::sys_aio_return(aiocbp) as _) as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aio_cancel(fd: c_int, aiocbp: *mut ctypes::aiocb) -> c_int {
+    libc_ret(ruxos_posix_api::sys_aio_cancel(fd, aiocbp) as _) as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aio_suspend(
+    list: *const *const ctypes::aiocb,
+    nent: c_int,
+    timeout: *const ctypes::timespec,
+) -> c_int {
+    libc_ret(ruxos_posix_api::sys_aio_suspend(list, nent, timeout) as _) as _
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lio_listio(
+    mode: c_int,
+    list: *const *mut ctypes::aiocb,
+    nent: c_int,
+    sevp: *mut ctypes::sigevent,
+) -> c_int {
+    libc_ret(ruxos_posix_api::sys_lio_listio(mode, list, nent, sevp) as _) as _
+}
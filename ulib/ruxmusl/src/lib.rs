@@ -14,6 +14,9 @@ extern crate alloc;
 
 mod trap;
 
+#[cfg(feature = "aio")]
+mod aio;
+
 cfg_if::cfg_if! {
     if #[cfg(target_arch = "aarch64")]{
         mod aarch64;
@@ -9,6 +9,8 @@ pub enum SyscallId {
     INVALID = 999,
     #[cfg(feature = "fs")]
     GETCWD = 17,
+    #[cfg(feature = "eventfd")]
+    EVENTFD2 = 19,
     #[cfg(feature = "epoll")]
     EPOLL_CREATE1 = 20,
     #[cfg(feature = "epoll")]
@@ -21,15 +23,39 @@ pub enum SyscallId {
     DUP3 = 24,
     #[cfg(feature = "fd")]
     FCNTL = 25,
+    #[cfg(feature = "inotify")]
+    INOTIFY_INIT1 = 26,
+    #[cfg(feature = "inotify")]
+    INOTIFY_ADD_WATCH = 27,
+    #[cfg(feature = "inotify")]
+    INOTIFY_RM_WATCH = 28,
     #[cfg(feature = "fd")]
     IOCTL = 29,
     #[cfg(feature = "fs")]
+    FLOCK = 32,
+    #[cfg(feature = "fs")]
     MKDIRAT = 34,
     #[cfg(feature = "fs")]
     UNLINKAT = 35,
     #[cfg(feature = "fs")]
+    SYMLINKAT = 36,
+    #[cfg(feature = "fs")]
+    LINKAT = 37,
+    #[cfg(feature = "fs")]
     RENAMEAT = 38,
     #[cfg(feature = "fs")]
+    UMOUNT2 = 39,
+    #[cfg(feature = "fs")]
+    MOUNT = 40,
+    #[cfg(feature = "fs")]
+    STATFS = 43,
+    #[cfg(feature = "fs")]
+    FSTATFS = 44,
+    #[cfg(feature = "fs")]
+    FCHMOD = 52,
+    #[cfg(feature = "fs")]
+    FCHMODAT = 53,
+    #[cfg(feature = "fs")]
     FCHOWNAT = 54,
     #[cfg(feature = "fs")]
     OPENAT = 56,
@@ -47,10 +73,18 @@ pub enum SyscallId {
     READV = 65,
     #[cfg(feature = "fd")]
     WRITEV = 66,
+    #[cfg(feature = "fs")]
+    SENDFILE = 71,
     #[cfg(feature = "select")]
     PSELECT6 = 72,
     #[cfg(feature = "poll")]
     PPOLL = 73,
+    #[cfg(feature = "signalfd")]
+    SIGNALFD4 = 74,
+    #[cfg(all(feature = "fs", feature = "pipe"))]
+    SPLICE = 76,
+    #[cfg(all(feature = "fs", feature = "pipe"))]
+    TEE = 77,
     #[cfg(feature = "fs")]
     READLINKAT = 78,
     #[cfg(feature = "fs")]
@@ -61,22 +95,50 @@ pub enum SyscallId {
     FSYNC = 82,
     #[cfg(feature = "fs")]
     FDATASYNC = 83,
+    #[cfg(feature = "timerfd")]
+    TIMERFD_CREATE = 85,
+    #[cfg(feature = "timerfd")]
+    TIMERFD_SETTIME = 86,
+    #[cfg(feature = "timerfd")]
+    TIMERFD_GETTIME = 87,
+    #[cfg(feature = "fs")]
+    UTIMENSAT = 88,
     EXIT = 93,
     #[cfg(feature = "multitask")]
+    EXIT_GROUP = 94,
+    #[cfg(feature = "multitask")]
     SET_TID_ADDRESS = 96,
     #[cfg(feature = "multitask")]
     FUTEX = 98,
     NANO_SLEEP = 101,
+    #[cfg(feature = "timer")]
+    TIMER_CREATE = 107,
+    #[cfg(feature = "timer")]
+    TIMER_GETTIME = 108,
+    #[cfg(feature = "timer")]
+    TIMER_GETOVERRUN = 109,
+    #[cfg(feature = "timer")]
+    TIMER_SETTIME = 110,
+    #[cfg(feature = "timer")]
+    TIMER_DELETE = 111,
     CLOCK_SETTIME = 112,
     CLOCK_GETTIME = 113,
+    CLOCK_GETRES = 114,
     CLOCK_NANOSLEEP = 115,
     SCHED_YIELD = 124,
     #[cfg(feature = "signal")]
+    KILL = 129,
+    #[cfg(feature = "signal")]
+    TKILL = 130,
+    #[cfg(feature = "signal")]
     SIGALTSTACK = 132,
     #[cfg(feature = "signal")]
     RT_SIGACTION = 134,
     #[cfg(feature = "signal")]
     RT_SIGPROCMASK = 135,
+    #[cfg(all(feature = "signal", feature = "multitask", feature = "irq"))]
+    RT_SIGTIMEDWAIT = 137,
+    TIMES = 153,
     UNAME = 160,
     GETRLIMIT = 163,
     SETRLIMIT = 164,
@@ -86,6 +148,42 @@ pub enum SyscallId {
     GETEUID = 175,
     GETEGID = 177,
     SYSINFO = 179,
+    #[cfg(feature = "mqueue")]
+    MQ_OPEN = 180,
+    #[cfg(feature = "mqueue")]
+    MQ_UNLINK = 181,
+    #[cfg(feature = "mqueue")]
+    MQ_TIMEDSEND = 182,
+    #[cfg(feature = "mqueue")]
+    MQ_TIMEDRECEIVE = 183,
+    #[cfg(feature = "mqueue")]
+    MQ_NOTIFY = 184,
+    #[cfg(feature = "mqueue")]
+    MQ_GETSETATTR = 185,
+    #[cfg(feature = "sysvipc")]
+    MSGGET = 186,
+    #[cfg(feature = "sysvipc")]
+    MSGCTL = 187,
+    #[cfg(feature = "sysvipc")]
+    MSGRCV = 188,
+    #[cfg(feature = "sysvipc")]
+    MSGSND = 189,
+    #[cfg(feature = "sysvipc")]
+    SEMGET = 190,
+    #[cfg(feature = "sysvipc")]
+    SEMCTL = 191,
+    #[cfg(feature = "sysvipc")]
+    SEMTIMEDOP = 192,
+    #[cfg(feature = "sysvipc")]
+    SEMOP = 193,
+    #[cfg(feature = "sysvipc")]
+    SHMGET = 194,
+    #[cfg(feature = "sysvipc")]
+    SHMCTL = 195,
+    #[cfg(feature = "sysvipc")]
+    SHMAT = 196,
+    #[cfg(feature = "sysvipc")]
+    SHMDT = 197,
     #[cfg(feature = "net")]
     SOCKET = 198,
     #[cfg(feature = "net")]
@@ -127,4 +225,18 @@ pub enum SyscallId {
     #[cfg(feature = "alloc")]
     MPROTECT = 226,
     PRLIMIT64 = 261,
+    #[cfg(feature = "fs")]
+    RENAMEAT2 = 276,
+    #[cfg(feature = "fs")]
+    MEMFD_CREATE = 279,
+    #[cfg(feature = "fs")]
+    COPY_FILE_RANGE = 285,
+    #[cfg(feature = "fs")]
+    STATX = 291,
+    #[cfg(feature = "io_uring")]
+    IO_URING_SETUP = 425,
+    #[cfg(feature = "io_uring")]
+    IO_URING_ENTER = 426,
+    #[cfg(feature = "io_uring")]
+    IO_URING_REGISTER = 427,
 }
@@ -42,12 +42,12 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 ruxos_posix_api::sys_fstat(args[0] as c_int, args[1] as *mut c_void) as _
             }
 
-            // TODO: lstat is identical to stat(), except that if pathname is a symbolic link,
-            // then it returns information about the link itself, not the file that the link refers to.
             #[cfg(feature = "fs")]
-            SyscallId::LSTAT => ruxos_posix_api::sys_stat(
+            SyscallId::LSTAT => ruxos_posix_api::sys_newfstatat(
+                ctypes::AT_FDCWD,
                 args[0] as *const core::ffi::c_char,
-                args[1] as *mut c_void,
+                args[1] as *mut ctypes::kstat,
+                ctypes::AT_SYMLINK_NOFOLLOW as c_int,
             ) as _,
 
             #[cfg(feature = "poll")]
@@ -102,6 +102,14 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[3],
             ) as _,
 
+            #[cfg(all(feature = "signal", feature = "multitask", feature = "irq"))]
+            SyscallId::RT_SIGTIMEDWAIT => ruxos_posix_api::sys_rt_sigtimedwait(
+                args[0] as *const ctypes::sigset_t,
+                args[1] as *mut ctypes::siginfo_t,
+                args[2] as *const ctypes::timespec,
+                args[3] as ctypes::size_t,
+            ) as _,
+
             #[cfg(feature = "fd")]
             SyscallId::IOCTL => ruxos_posix_api::sys_ioctl(args[0] as c_int, args[1], args[2]) as _,
 
@@ -173,6 +181,27 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 ruxos_posix_api::sys_madvise(args[0] as *mut c_void, args[1], args[2] as c_int) as _
             }
 
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SHMGET => ruxos_posix_api::sys_shmget(
+                args[0] as c_int,
+                args[1] as ctypes::size_t,
+                args[2] as c_int,
+            ) as _,
+
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SHMAT => ruxos_posix_api::sys_shmat(
+                args[0] as c_int,
+                args[1] as *const c_void,
+                args[2] as c_int,
+            ) as _,
+
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SHMCTL => ruxos_posix_api::sys_shmctl(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as *mut ctypes::shmid_ds,
+            ) as _,
+
             #[cfg(feature = "fd")]
             SyscallId::DUP => ruxos_posix_api::sys_dup(args[0] as c_int) as _,
 
@@ -187,6 +216,14 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
             #[cfg(feature = "multitask")]
             SyscallId::GETPID => ruxos_posix_api::sys_getpid() as _,
 
+            #[cfg(feature = "fs")]
+            SyscallId::SENDFILE => ruxos_posix_api::sys_sendfile(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as *mut ctypes::off_t,
+                args[3] as ctypes::size_t,
+            ) as _,
+
             #[cfg(feature = "net")]
             SyscallId::SOCKET => {
                 ruxos_posix_api::sys_socket(args[0] as c_int, args[1] as c_int, args[2] as c_int)
@@ -318,11 +355,67 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
 
             SyscallId::UNAME => ruxos_posix_api::sys_uname(args[0] as *mut c_void) as _,
 
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SEMGET => {
+                ruxos_posix_api::sys_semget(args[0] as c_int, args[1] as c_int, args[2] as c_int)
+                    as _
+            }
+
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SEMOP => ruxos_posix_api::sys_semop(
+                args[0] as c_int,
+                args[1] as *mut ctypes::sembuf,
+                args[2] as ctypes::size_t,
+            ) as _,
+
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SEMCTL => ruxos_posix_api::sys_semctl(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as c_int,
+                args[3],
+            ) as _,
+
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SHMDT => ruxos_posix_api::sys_shmdt(args[0] as *const c_void) as _,
+
+            #[cfg(feature = "sysvipc")]
+            SyscallId::MSGGET => {
+                ruxos_posix_api::sys_msgget(args[0] as c_int, args[1] as c_int) as _
+            }
+
+            #[cfg(feature = "sysvipc")]
+            SyscallId::MSGSND => ruxos_posix_api::sys_msgsnd(
+                args[0] as c_int,
+                args[1] as *const c_void,
+                args[2] as ctypes::size_t,
+                args[3] as c_int,
+            ) as _,
+
+            #[cfg(feature = "sysvipc")]
+            SyscallId::MSGRCV => ruxos_posix_api::sys_msgrcv(
+                args[0] as c_int,
+                args[1] as *mut c_void,
+                args[2] as ctypes::size_t,
+                args[3] as core::ffi::c_long,
+                args[4] as c_int,
+            ) as _,
+
+            #[cfg(feature = "sysvipc")]
+            SyscallId::MSGCTL => ruxos_posix_api::sys_msgctl(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as *mut ctypes::msqid_ds,
+            ) as _,
+
             #[cfg(feature = "fd")]
             SyscallId::FCNTL => {
                 ruxos_posix_api::sys_fcntl(args[0] as c_int, args[1] as c_int, args[2]) as _
             }
 
+            #[cfg(feature = "fs")]
+            SyscallId::FLOCK => ruxos_posix_api::sys_flock(args[0] as c_int, args[1] as c_int) as _,
+
             #[cfg(feature = "fs")]
             SyscallId::FSYNC => ruxos_posix_api::sys_fsync(args[0] as c_int) as _,
 
@@ -361,11 +454,23 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 ruxos_posix_api::sys_rmdir(args[0] as *const core::ffi::c_char) as _
             }
 
+            #[cfg(feature = "fs")]
+            SyscallId::LINK => ruxos_posix_api::sys_link(
+                args[0] as *const core::ffi::c_char,
+                args[1] as *const core::ffi::c_char,
+            ) as _,
+
             #[cfg(feature = "fs")]
             SyscallId::UNLINK => {
                 ruxos_posix_api::sys_unlink(args[0] as *const core::ffi::c_char) as _
             }
 
+            #[cfg(feature = "fs")]
+            SyscallId::SYMLINK => ruxos_posix_api::sys_symlink(
+                args[0] as *const core::ffi::c_char,
+                args[1] as *const core::ffi::c_char,
+            ) as _,
+
             #[cfg(feature = "fs")]
             SyscallId::READLINK => ruxos_posix_api::sys_readlinkat(
                 ctypes::AT_FDCWD as c_int,
@@ -374,6 +479,17 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[2],
             ) as _,
 
+            #[cfg(feature = "fs")]
+            SyscallId::CHMOD => ruxos_posix_api::sys_chmod(
+                args[0] as *const core::ffi::c_char,
+                args[1] as ctypes::mode_t,
+            ) as _,
+
+            #[cfg(feature = "fs")]
+            SyscallId::FCHMOD => {
+                ruxos_posix_api::sys_fchmod(args[0] as c_int, args[1] as ctypes::mode_t) as _
+            }
+
             SyscallId::UMASK => ruxos_posix_api::sys_umask(args[0] as ctypes::mode_t) as _,
 
             SyscallId::GETTIMEOFDAY => ruxos_posix_api::sys_gettimeofday(
@@ -390,7 +506,7 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 ruxos_posix_api::sys_sysinfo(args[0] as *mut ctypes::sysinfo) as _
             }
 
-            SyscallId::TIMES => ruxos_posix_api::sys_times(args[0] as *mut usize) as _,
+            SyscallId::TIMES => ruxos_posix_api::sys_times(args[0] as *mut ctypes::tms) as _,
 
             SyscallId::GETUID => ruxos_posix_api::sys_getuid() as _,
 
@@ -424,9 +540,38 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 ruxos_posix_api::sys_arch_prctl(args[0] as c_int, args[1] as c_ulong) as _
             }
 
+            #[cfg(feature = "fs")]
+            SyscallId::MOUNT => ruxos_posix_api::sys_mount(
+                args[0] as *const core::ffi::c_char,
+                args[1] as *const core::ffi::c_char,
+                args[2] as *const core::ffi::c_char,
+                args[3] as c_ulong,
+                args[4] as *const core::ffi::c_void,
+            ) as _,
+
+            #[cfg(feature = "fs")]
+            SyscallId::UMOUNT2 => {
+                ruxos_posix_api::sys_umount2(args[0] as *const core::ffi::c_char, args[1] as c_int)
+                    as _
+            }
+
+            #[cfg(feature = "fs")]
+            SyscallId::STATFS => ruxos_posix_api::sys_statfs(
+                args[0] as *const core::ffi::c_char,
+                args[1] as *mut ctypes::statfs,
+            ) as _,
+
+            #[cfg(feature = "fs")]
+            SyscallId::FSTATFS => {
+                ruxos_posix_api::sys_fstatfs(args[0] as c_int, args[1] as *mut ctypes::statfs) as _
+            }
+
             #[cfg(feature = "multitask")]
             SyscallId::GETTID => ruxos_posix_api::sys_gettid() as _,
 
+            #[cfg(feature = "signal")]
+            SyscallId::TKILL => ruxos_posix_api::sys_tkill(args[0] as pid_t, args[1] as c_int) as _,
+
             #[cfg(feature = "multitask")]
             SyscallId::FUTEX => ruxos_posix_api::sys_futex(
                 args[0],
@@ -438,7 +583,7 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
             ) as _,
 
             #[cfg(feature = "epoll")]
-            SyscallId::EPOLL_CREATE => ruxos_posix_api::sys_epoll_create1(args[0] as c_int) as _,
+            SyscallId::EPOLL_CREATE => ruxos_posix_api::sys_epoll_create(args[0] as c_int) as _,
 
             #[cfg(feature = "fs")]
             SyscallId::GETDENTS64 => ruxos_posix_api::sys_getdents64(
@@ -450,6 +595,43 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
             #[cfg(feature = "multitask")]
             SyscallId::SET_TID_ADDRESS => ruxos_posix_api::sys_set_tid_address(args[0]) as _,
 
+            #[cfg(feature = "sysvipc")]
+            SyscallId::SEMTIMEDOP => ruxos_posix_api::sys_semtimedop(
+                args[0] as c_int,
+                args[1] as *mut ctypes::sembuf,
+                args[2] as ctypes::size_t,
+                args[3] as *const ctypes::timespec,
+            ) as _,
+
+            #[cfg(feature = "timer")]
+            SyscallId::TIMER_CREATE => ruxos_posix_api::sys_timer_create(
+                args[0] as ctypes::clockid_t,
+                args[1] as *const ctypes::sigevent,
+                args[2] as *mut c_int,
+            ) as _,
+
+            #[cfg(feature = "timer")]
+            SyscallId::TIMER_GETTIME => ruxos_posix_api::sys_timer_gettime(
+                args[0] as c_int,
+                args[1] as *mut ctypes::itimerspec,
+            ) as _,
+
+            #[cfg(feature = "timer")]
+            SyscallId::TIMER_GETOVERRUN => {
+                ruxos_posix_api::sys_timer_getoverrun(args[0] as c_int) as _
+            }
+
+            #[cfg(feature = "timer")]
+            SyscallId::TIMER_SETTIME => ruxos_posix_api::sys_timer_settime(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as *const ctypes::itimerspec,
+                args[3] as *mut ctypes::itimerspec,
+            ) as _,
+
+            #[cfg(feature = "timer")]
+            SyscallId::TIMER_DELETE => ruxos_posix_api::sys_timer_delete(args[0] as c_int) as _,
+
             SyscallId::CLOCK_SETTIME => ruxos_posix_api::sys_clock_settime(
                 args[0] as c_int,
                 args[1] as *const ctypes::timespec,
@@ -460,6 +642,11 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[1] as *mut ctypes::timespec,
             ) as _,
 
+            SyscallId::CLOCK_GETRES => ruxos_posix_api::sys_clock_getres(
+                args[0] as ctypes::clockid_t,
+                args[1] as *mut ctypes::timespec,
+            ) as _,
+
             SyscallId::CLOCK_NANOSLEEP => ruxos_posix_api::sys_clock_nanosleep(
                 args[0] as ctypes::clockid_t,
                 args[1] as c_int,
@@ -467,6 +654,9 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[3] as *mut ctypes::timespec,
             ) as _,
 
+            #[cfg(feature = "multitask")]
+            SyscallId::EXIT_GROUP => ruxos_posix_api::sys_exit_group(args[0] as c_int),
+
             #[cfg(feature = "epoll")]
             SyscallId::EPOLL_WAIT => ruxos_posix_api::sys_epoll_wait(
                 args[0] as c_int,
@@ -483,6 +673,63 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[3] as *mut ctypes::epoll_event,
             ) as _,
 
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_OPEN => ruxos_posix_api::sys_mq_open(
+                args[0] as *const c_char,
+                args[1] as c_int,
+                args[2] as ctypes::mode_t,
+                args[3] as *const ctypes::mq_attr,
+            ) as _,
+
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_UNLINK => ruxos_posix_api::sys_mq_unlink(args[0] as *const c_char) as _,
+
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_TIMEDSEND => ruxos_posix_api::sys_mq_timedsend(
+                args[0] as c_int,
+                args[1] as *const c_char,
+                args[2] as ctypes::size_t,
+                args[3] as core::ffi::c_uint,
+                args[4] as *const ctypes::timespec,
+            ) as _,
+
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_TIMEDRECEIVE => ruxos_posix_api::sys_mq_timedreceive(
+                args[0] as c_int,
+                args[1] as *mut c_char,
+                args[2] as ctypes::size_t,
+                args[3] as *mut core::ffi::c_uint,
+                args[4] as *const ctypes::timespec,
+            ) as _,
+
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_NOTIFY => {
+                ruxos_posix_api::sys_mq_notify(args[0] as c_int, args[1] as *const ctypes::sigevent)
+                    as _
+            }
+
+            #[cfg(feature = "mqueue")]
+            SyscallId::MQ_GETSETATTR => ruxos_posix_api::sys_mq_getsetattr(
+                args[0] as c_int,
+                args[1] as *const ctypes::mq_attr,
+                args[2] as *mut ctypes::mq_attr,
+            ) as _,
+
+            #[cfg(feature = "inotify")]
+            SyscallId::INOTIFY_INIT => ruxos_posix_api::sys_inotify_init() as _,
+
+            #[cfg(feature = "inotify")]
+            SyscallId::INOTIFY_ADD_WATCH => ruxos_posix_api::sys_inotify_add_watch(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as _,
+            ) as _,
+
+            #[cfg(feature = "inotify")]
+            SyscallId::INOTIFY_RM_WATCH => {
+                ruxos_posix_api::sys_inotify_rm_watch(args[0] as c_int, args[1] as c_int) as _
+            }
+
             #[cfg(feature = "fs")]
             SyscallId::OPENAT => ruxos_posix_api::sys_openat(
                 args[0] as c_int,
@@ -498,6 +745,15 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[2] as ctypes::mode_t,
             ) as _,
 
+            #[cfg(feature = "fs")]
+            SyscallId::FCHOWNAT => ruxos_posix_api::sys_fchownat(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as ctypes::uid_t,
+                args[3] as ctypes::gid_t,
+                args[4] as c_int,
+            ) as _,
+
             #[cfg(feature = "fs")]
             SyscallId::NEWFSTATAT => ruxos_posix_api::sys_newfstatat(
                 args[0] as c_int,
@@ -521,6 +777,22 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[3] as *const core::ffi::c_char,
             ) as _,
 
+            #[cfg(feature = "fs")]
+            SyscallId::LINKAT => ruxos_posix_api::sys_linkat(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as c_int,
+                args[3] as *const core::ffi::c_char,
+                args[4] as c_int,
+            ) as _,
+
+            #[cfg(feature = "fs")]
+            SyscallId::SYMLINKAT => ruxos_posix_api::sys_symlinkat(
+                args[0] as *const core::ffi::c_char,
+                args[1] as c_int,
+                args[2] as *const core::ffi::c_char,
+            ) as _,
+
             #[cfg(feature = "fs")]
             SyscallId::READLINKAT => ruxos_posix_api::sys_readlinkat(
                 args[0] as c_int,
@@ -529,6 +801,14 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[3],
             ) as _,
 
+            #[cfg(feature = "fs")]
+            SyscallId::FCHMODAT => ruxos_posix_api::sys_fchmodat(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as ctypes::mode_t,
+                args[3] as c_int,
+            ) as _,
+
             #[cfg(feature = "fs")]
             SyscallId::FACCESSAT => ruxos_posix_api::sys_faccessat(
                 args[0] as c_int,
@@ -556,6 +836,32 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[4] as ctypes::size_t,
             ) as _,
 
+            #[cfg(all(feature = "fs", feature = "pipe"))]
+            SyscallId::SPLICE => ruxos_posix_api::sys_splice(
+                args[0] as c_int,
+                args[1] as *mut ctypes::off_t,
+                args[2] as c_int,
+                args[3] as *mut ctypes::off_t,
+                args[4] as ctypes::size_t,
+                args[5] as _,
+            ) as _,
+
+            #[cfg(all(feature = "fs", feature = "pipe"))]
+            SyscallId::TEE => ruxos_posix_api::sys_tee(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as ctypes::size_t,
+                args[3] as _,
+            ) as _,
+
+            #[cfg(feature = "fs")]
+            SyscallId::UTIMENSAT => ruxos_posix_api::sys_utimensat(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as *const ctypes::timespec,
+                args[3] as c_int,
+            ) as _,
+
             #[cfg(feature = "epoll")]
             SyscallId::EPOLL_PWAIT => ruxos_posix_api::sys_epoll_pwait(
                 args[0] as c_int,
@@ -566,6 +872,49 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[5] as *const ctypes::size_t,
             ) as _,
 
+            #[cfg(feature = "signalfd")]
+            SyscallId::SIGNALFD => ruxos_posix_api::sys_signalfd(
+                args[0] as c_int,
+                args[1] as *const ctypes::sigset_t,
+                args[2] as ctypes::size_t,
+            ) as _,
+
+            #[cfg(feature = "timerfd")]
+            SyscallId::TIMERFD_CREATE => {
+                ruxos_posix_api::sys_timerfd_create(args[0] as ctypes::clockid_t, args[1] as c_int)
+                    as _
+            }
+
+            #[cfg(feature = "eventfd")]
+            SyscallId::EVENTFD => ruxos_posix_api::sys_eventfd(args[0] as _) as _,
+
+            #[cfg(feature = "timerfd")]
+            SyscallId::TIMERFD_SETTIME => ruxos_posix_api::sys_timerfd_settime(
+                args[0] as c_int,
+                args[1] as c_int,
+                args[2] as *const ctypes::itimerspec,
+                args[3] as *mut ctypes::itimerspec,
+            ) as _,
+
+            #[cfg(feature = "timerfd")]
+            SyscallId::TIMERFD_GETTIME => ruxos_posix_api::sys_timerfd_gettime(
+                args[0] as c_int,
+                args[1] as *mut ctypes::itimerspec,
+            ) as _,
+
+            #[cfg(feature = "signalfd")]
+            SyscallId::SIGNALFD4 => ruxos_posix_api::sys_signalfd4(
+                args[0] as c_int,
+                args[1] as *const ctypes::sigset_t,
+                args[2] as ctypes::size_t,
+                args[3] as c_int,
+            ) as _,
+
+            #[cfg(feature = "eventfd")]
+            SyscallId::EVENTFD2 => {
+                ruxos_posix_api::sys_eventfd2(args[0] as _, args[1] as c_int) as _
+            }
+
             #[cfg(feature = "epoll")]
             SyscallId::EPOLL_CREATE1 => ruxos_posix_api::sys_epoll_create1(args[0] as c_int) as _,
 
@@ -580,6 +929,9 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[1] as c_int,
             ) as _,
 
+            #[cfg(feature = "inotify")]
+            SyscallId::INOTIFY_INIT1 => ruxos_posix_api::sys_inotify_init1(args[0] as c_int) as _,
+
             #[cfg(feature = "fs")]
             SyscallId::PREADV => ruxos_posix_api::sys_preadv(
                 args[0] as c_int,
@@ -595,11 +947,68 @@ pub fn syscall(syscall_id: SyscallId, args: [usize; 6]) -> isize {
                 args[3] as *mut ctypes::rlimit,
             ) as _,
 
+            #[cfg(feature = "fs")]
+            SyscallId::RENAMEAT2 => ruxos_posix_api::sys_renameat2(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as c_int,
+                args[3] as *const core::ffi::c_char,
+                args[4] as core::ffi::c_uint,
+            ) as _,
+
             SyscallId::GETRANDOM => ruxos_posix_api::sys_getrandom(
                 args[0] as *mut c_void,
                 args[1] as ctypes::size_t,
                 args[2] as c_int,
             ) as _,
+
+            #[cfg(feature = "fs")]
+            SyscallId::MEMFD_CREATE => {
+                ruxos_posix_api::sys_memfd_create(args[0] as *const c_char, args[1] as _) as _
+            }
+
+            #[cfg(feature = "fs")]
+            SyscallId::COPY_FILE_RANGE => ruxos_posix_api::sys_copy_file_range(
+                args[0] as c_int,
+                args[1] as *mut ctypes::off_t,
+                args[2] as c_int,
+                args[3] as *mut ctypes::off_t,
+                args[4] as ctypes::size_t,
+                args[5] as _,
+            ) as _,
+
+            #[cfg(feature = "fs")]
+            SyscallId::STATX => ruxos_posix_api::sys_statx(
+                args[0] as c_int,
+                args[1] as *const core::ffi::c_char,
+                args[2] as c_int,
+                args[3] as core::ffi::c_uint,
+                args[4] as *mut ctypes::statx,
+            ) as _,
+
+            #[cfg(feature = "io_uring")]
+            SyscallId::IO_URING_SETUP => ruxos_posix_api::sys_io_uring_setup(
+                args[0] as core::ffi::c_uint,
+                args[1] as *mut ctypes::io_uring_params,
+            ) as _,
+
+            #[cfg(feature = "io_uring")]
+            SyscallId::IO_URING_ENTER => ruxos_posix_api::sys_io_uring_enter(
+                args[0] as core::ffi::c_uint,
+                args[1] as core::ffi::c_uint,
+                args[2] as core::ffi::c_uint,
+                args[3] as core::ffi::c_uint,
+                args[4] as *const core::ffi::c_void,
+                args[5] as ctypes::size_t,
+            ) as _,
+
+            #[cfg(feature = "io_uring")]
+            SyscallId::IO_URING_REGISTER => ruxos_posix_api::sys_io_uring_register(
+                args[0] as core::ffi::c_uint,
+                args[1] as core::ffi::c_uint,
+                args[2] as *mut core::ffi::c_void,
+                args[3] as core::ffi::c_uint,
+            ) as _,
         }
     }
 }
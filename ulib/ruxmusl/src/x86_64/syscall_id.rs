@@ -79,6 +79,15 @@ pub enum SyscallId {
     #[cfg(feature = "alloc")]
     MADVISE = 28,
 
+    #[cfg(feature = "sysvipc")]
+    SHMGET = 29,
+
+    #[cfg(feature = "sysvipc")]
+    SHMAT = 30,
+
+    #[cfg(feature = "sysvipc")]
+    SHMCTL = 31,
+
     #[cfg(feature = "fd")]
     DUP = 32,
 
@@ -90,6 +99,9 @@ pub enum SyscallId {
     #[cfg(feature = "multitask")]
     GETPID = 39,
 
+    #[cfg(feature = "fs")]
+    SENDFILE = 40,
+
     #[cfg(feature = "net")]
     SOCKET = 41,
 
@@ -146,9 +158,36 @@ pub enum SyscallId {
 
     UNAME = 63,
 
+    #[cfg(feature = "sysvipc")]
+    SEMGET = 64,
+
+    #[cfg(feature = "sysvipc")]
+    SEMOP = 65,
+
+    #[cfg(feature = "sysvipc")]
+    SEMCTL = 66,
+
+    #[cfg(feature = "sysvipc")]
+    SHMDT = 67,
+
+    #[cfg(feature = "sysvipc")]
+    MSGGET = 68,
+
+    #[cfg(feature = "sysvipc")]
+    MSGSND = 69,
+
+    #[cfg(feature = "sysvipc")]
+    MSGRCV = 70,
+
+    #[cfg(feature = "sysvipc")]
+    MSGCTL = 71,
+
     #[cfg(feature = "fd")]
     FCNTL = 72,
 
+    #[cfg(feature = "fs")]
+    FLOCK = 73,
+
     #[cfg(feature = "fs")]
     FSYNC = 74,
 
@@ -173,12 +212,24 @@ pub enum SyscallId {
     #[cfg(feature = "fs")]
     RMDIR = 84,
 
+    #[cfg(feature = "fs")]
+    LINK = 86,
+
     #[cfg(feature = "fs")]
     UNLINK = 87,
 
+    #[cfg(feature = "fs")]
+    SYMLINK = 88,
+
     #[cfg(feature = "fs")]
     READLINK = 89,
 
+    #[cfg(feature = "fs")]
+    CHMOD = 90,
+
+    #[cfg(feature = "fs")]
+    FCHMOD = 91,
+
     UMASK = 95,
 
     GETTIMEOFDAY = 96,
@@ -203,16 +254,34 @@ pub enum SyscallId {
 
     CAPGET = 125,
 
+    #[cfg(all(feature = "signal", feature = "multitask", feature = "irq"))]
+    RT_SIGTIMEDWAIT = 128,
+
     #[cfg(feature = "signal")]
     SIGALTSTACK = 131,
 
+    #[cfg(feature = "fs")]
+    STATFS = 137,
+
+    #[cfg(feature = "fs")]
+    FSTATFS = 138,
+
     PRCTL = 157,
 
     ARCH_PRCTL = 158,
 
+    #[cfg(feature = "fs")]
+    MOUNT = 165,
+
+    #[cfg(feature = "fs")]
+    UMOUNT2 = 166,
+
     #[cfg(feature = "multitask")]
     GETTID = 186,
 
+    #[cfg(feature = "signal")]
+    TKILL = 200,
+
     #[cfg(feature = "multitask")]
     FUTEX = 202,
 
@@ -225,24 +294,77 @@ pub enum SyscallId {
     #[cfg(feature = "multitask")]
     SET_TID_ADDRESS = 218,
 
+    #[cfg(feature = "sysvipc")]
+    SEMTIMEDOP = 220,
+
+    #[cfg(feature = "timer")]
+    TIMER_CREATE = 222,
+
+    #[cfg(feature = "timer")]
+    TIMER_SETTIME = 223,
+
+    #[cfg(feature = "timer")]
+    TIMER_GETTIME = 224,
+
+    #[cfg(feature = "timer")]
+    TIMER_GETOVERRUN = 225,
+
+    #[cfg(feature = "timer")]
+    TIMER_DELETE = 226,
+
     CLOCK_SETTIME = 227,
 
     CLOCK_GETTIME = 228,
 
+    CLOCK_GETRES = 229,
+
     CLOCK_NANOSLEEP = 230,
 
+    #[cfg(feature = "multitask")]
+    EXIT_GROUP = 231,
+
     #[cfg(feature = "epoll")]
     EPOLL_WAIT = 232,
 
     #[cfg(feature = "epoll")]
     EPOLL_CTL = 233,
 
+    #[cfg(feature = "mqueue")]
+    MQ_OPEN = 240,
+
+    #[cfg(feature = "mqueue")]
+    MQ_UNLINK = 241,
+
+    #[cfg(feature = "mqueue")]
+    MQ_TIMEDSEND = 242,
+
+    #[cfg(feature = "mqueue")]
+    MQ_TIMEDRECEIVE = 243,
+
+    #[cfg(feature = "mqueue")]
+    MQ_NOTIFY = 244,
+
+    #[cfg(feature = "mqueue")]
+    MQ_GETSETATTR = 245,
+
+    #[cfg(feature = "inotify")]
+    INOTIFY_INIT = 253,
+
+    #[cfg(feature = "inotify")]
+    INOTIFY_ADD_WATCH = 254,
+
+    #[cfg(feature = "inotify")]
+    INOTIFY_RM_WATCH = 255,
+
     #[cfg(feature = "fs")]
     OPENAT = 257,
 
     #[cfg(feature = "fs")]
     MKDIRAT = 258,
 
+    #[cfg(feature = "fs")]
+    FCHOWNAT = 260,
+
     #[cfg(feature = "fs")]
     NEWFSTATAT = 262,
 
@@ -252,9 +374,18 @@ pub enum SyscallId {
     #[cfg(feature = "fs")]
     RENAMEAT = 264,
 
+    #[cfg(feature = "fs")]
+    LINKAT = 265,
+
+    #[cfg(feature = "fs")]
+    SYMLINKAT = 266,
+
     #[cfg(feature = "fs")]
     READLINKAT = 267,
 
+    #[cfg(feature = "fs")]
+    FCHMODAT = 268,
+
     #[cfg(feature = "fs")]
     FACCESSAT = 269,
 
@@ -264,9 +395,39 @@ pub enum SyscallId {
     #[cfg(feature = "poll")]
     PPOLL = 271,
 
+    #[cfg(all(feature = "fs", feature = "pipe"))]
+    SPLICE = 275,
+
+    #[cfg(all(feature = "fs", feature = "pipe"))]
+    TEE = 276,
+
+    #[cfg(feature = "fs")]
+    UTIMENSAT = 280,
+
     #[cfg(feature = "epoll")]
     EPOLL_PWAIT = 281,
 
+    #[cfg(feature = "signalfd")]
+    SIGNALFD = 282,
+
+    #[cfg(feature = "timerfd")]
+    TIMERFD_CREATE = 283,
+
+    #[cfg(feature = "eventfd")]
+    EVENTFD = 284,
+
+    #[cfg(feature = "timerfd")]
+    TIMERFD_SETTIME = 286,
+
+    #[cfg(feature = "timerfd")]
+    TIMERFD_GETTIME = 287,
+
+    #[cfg(feature = "signalfd")]
+    SIGNALFD4 = 289,
+
+    #[cfg(feature = "eventfd")]
+    EVENTFD2 = 290,
+
     #[cfg(feature = "epoll")]
     EPOLL_CREATE1 = 291,
 
@@ -276,10 +437,34 @@ pub enum SyscallId {
     #[cfg(feature = "pipe")]
     PIPE2 = 293,
 
+    #[cfg(feature = "inotify")]
+    INOTIFY_INIT1 = 294,
+
     #[cfg(feature = "fs")]
     PREADV = 295,
 
     PRLIMIT64 = 302,
 
+    #[cfg(feature = "fs")]
+    RENAMEAT2 = 316,
+
     GETRANDOM = 318,
+
+    #[cfg(feature = "fs")]
+    MEMFD_CREATE = 319,
+
+    #[cfg(feature = "fs")]
+    COPY_FILE_RANGE = 326,
+
+    #[cfg(feature = "fs")]
+    STATX = 332,
+
+    #[cfg(feature = "io_uring")]
+    IO_URING_SETUP = 425,
+
+    #[cfg(feature = "io_uring")]
+    IO_URING_ENTER = 426,
+
+    #[cfg(feature = "io_uring")]
+    IO_URING_REGISTER = 427,
 }